@@ -0,0 +1,28 @@
+use std::{path::Path, process::Command};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=PEZ_GIT_SHA");
+    for path in [".git/HEAD", ".git/refs/heads"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+
+    // Release pipelines building from a tarball can provide the SHA explicitly.
+    let sha = std::env::var("PEZ_GIT_SHA")
+        .ok()
+        .or_else(|| {
+            let output = Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .filter(|sha| !sha.is_empty());
+    if let Some(sha) = sha {
+        println!("cargo:rustc-env=PEZ_GIT_SHA={sha}");
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{models::PluginRepo, utils};
+
+use std::collections::BTreeMap;
+use tracing::debug;
+
+/// Well-known plugins that can be installed by bare name. `[aliases]` in pez.toml
+/// extends this table and takes precedence over it.
+pub(crate) const BUNDLED: &[(&str, &str)] = &[
+    ("autopair", "jorgebucaran/autopair.fish"),
+    ("bass", "edc/bass"),
+    ("done", "franciscolourenco/done"),
+    ("fzf", "PatrickF1/fzf.fish"),
+    ("hydro", "jorgebucaran/hydro"),
+    ("nvm", "jorgebucaran/nvm.fish"),
+    ("puffer", "nickeb96/puffer-fish"),
+    ("pure", "pure-fish/pure"),
+    ("replay", "jorgebucaran/replay.fish"),
+    ("sponge", "meaningful-ooo/sponge"),
+    ("tide", "IlanCosman/tide"),
+    ("z", "jethrokuan/z"),
+];
+
+/// Whether `name` has the shape of an alias key: a single segment without `/`, `@`,
+/// or whitespace.
+pub(crate) fn is_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c == '/' || c == '@' || c == ':' || c.is_whitespace())
+}
+
+/// Look up `name` in the user table first, then the bundled one.
+pub(crate) fn lookup(name: &str, user: &BTreeMap<String, PluginRepo>) -> Option<PluginRepo> {
+    if let Some(repo) = user.get(name) {
+        return Some(repo.clone());
+    }
+    BUNDLED
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .and_then(|(_, repo)| repo.parse().ok())
+}
+
+/// Resolve a bare plugin name using the aliases from pez.toml (when it can be read)
+/// and the bundled table.
+pub(crate) fn resolve(name: &str) -> Option<PluginRepo> {
+    let user = utils::load_config()
+        .map(|(config, _)| config.aliases)
+        .unwrap_or_default();
+    let repo = lookup(name, &user)?;
+    debug!(target: crate::trace::RESOLVER, "Resolved alias {} to {}", name, repo);
+    Some(repo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_aliases_are_valid_repos() {
+        for (alias, repo) in BUNDLED {
+            assert!(is_alias_name(alias), "{alias}");
+            assert!(repo.parse::<PluginRepo>().is_ok(), "{repo}");
+        }
+    }
+
+    #[test]
+    fn user_aliases_take_precedence() {
+        let user = BTreeMap::from([
+            ("z".to_string(), "me/z".parse().unwrap()),
+            ("mine".to_string(), "gitlab.com/me/mine".parse().unwrap()),
+        ]);
+        assert_eq!(lookup("z", &user).unwrap().as_str(), "me/z");
+        assert_eq!(
+            lookup("mine", &user).unwrap().as_str(),
+            "gitlab.com/me/mine"
+        );
+        assert_eq!(
+            lookup("tide", &BTreeMap::new()).unwrap().as_str(),
+            "IlanCosman/tide"
+        );
+        assert!(lookup("unknown", &user).is_none());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{
+    lock_file::{LockFile, Plugin},
+    models::TargetDir,
+};
+
+use std::{fs, path};
+
+/// A `functions/` file fish cannot autoload because it never defines the function
+/// named after it.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Misnamed {
+    pub(crate) plugin: String,
+    /// Path relative to `functions/`, e.g. `foo.fish`.
+    pub(crate) file: String,
+    /// The functions the file defines instead.
+    pub(crate) defines: Vec<String>,
+}
+
+impl Misnamed {
+    pub(crate) fn describe(&self) -> String {
+        format!(
+            "{}: functions/{} defines {} but not `{}`, so fish never autoloads it",
+            self.plugin,
+            self.file,
+            self.defines
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", "),
+            expected_name(&self.file)
+        )
+    }
+}
+
+/// Names declared with `function NAME` at the start of a line, including one-liners
+/// such as `function foo; echo; end`.
+pub(crate) fn declared_functions(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("function"))
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .filter_map(|rest| {
+            rest.split(|c: char| c == ';' || c.is_whitespace())
+                .find(|word| !word.is_empty())
+        })
+        .map(|name| name.trim_matches(|c| c == '\'' || c == '"').to_string())
+        .filter(|name| !name.is_empty() && !name.starts_with('-'))
+        .collect()
+}
+
+/// fish autoloads `functions/foo.fish` when `foo` is first called.
+fn expected_name(file: &str) -> &str {
+    let base = file.rsplit('/').next().unwrap_or(file);
+    base.strip_suffix(".fish").unwrap_or(base)
+}
+
+/// `None` unless the file defines functions and none of them is the one its name
+/// promises. Files defining no function at all are left alone.
+pub(crate) fn check_file(plugin: &str, file: &str, contents: &str) -> Option<Misnamed> {
+    let defines = declared_functions(contents);
+    if defines.is_empty() || defines.iter().any(|name| name == expected_name(file)) {
+        return None;
+    }
+    Some(Misnamed {
+        plugin: plugin.to_string(),
+        file: file.to_string(),
+        defines,
+    })
+}
+
+/// Misnamed `functions/` files among the ones `plugin` has installed.
+pub(crate) fn misnamed_in(plugin: &Plugin, fish_config_dir: &path::Path) -> Vec<Misnamed> {
+    plugin
+        .files
+        .iter()
+        .filter(|file| file.dir == TargetDir::Functions)
+        .filter_map(|file| {
+            let contents = fs::read_to_string(file.get_path(fish_config_dir)).ok()?;
+            check_file(&plugin.display_label(), &file.name, &contents)
+        })
+        .collect()
+}
+
+/// Misnamed `functions/` files across every installed plugin.
+pub(crate) fn installed(lock_file: &LockFile, fish_config_dir: &path::Path) -> Vec<Misnamed> {
+    lock_file
+        .plugins
+        .iter()
+        .flat_map(|plugin| misnamed_in(plugin, fish_config_dir))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declared_functions_parses_names() {
+        assert_eq!(
+            declared_functions("function a\n  function 'b' -d x\n# function c\nfunction --help\n"),
+            vec!["a".to_string(), "b".to_string()]
+        );
+        assert_eq!(
+            declared_functions(
+                "function x; echo body; end
+function	y;end
+functions -q z
+"
+            ),
+            vec!["x".to_string(), "y".to_string()]
+        );
+        assert_eq!(
+            check_file(
+                "owner/p",
+                "x.fish",
+                "function x; echo body; end
+"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn check_file_flags_files_without_their_namesake_function() {
+        assert_eq!(
+            check_file("owner/p", "foo.fish", "function foo\nend\n"),
+            None
+        );
+        assert_eq!(
+            check_file(
+                "owner/p",
+                "foo.fish",
+                "function __foo_helper\nend\nfunction foo -d x\nend\n"
+            ),
+            None
+        );
+        assert_eq!(check_file("owner/p", "foo.fish", "set -g foo 1\n"), None);
+
+        let misnamed = check_file("owner/p", "sub/foo.fish", "function bar\nend\n").unwrap();
+        assert_eq!(misnamed.defines, vec!["bar".to_string()]);
+        assert_eq!(
+            misnamed.describe(),
+            "owner/p: functions/sub/foo.fish defines `bar` but not `foo`, so fish never autoloads it"
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{
+    lock_file::{Plugin, PluginBackup},
+    models::TargetDir,
+    utils,
+};
+
+use console::Emoji;
+use std::{
+    collections::HashSet,
+    fs, io, path,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+use tracing::{info, warn};
+
+/// Directory under the pez data dir holding user files displaced by plugin files.
+pub(crate) const BACKUP_DIR_NAME: &str = ".backups";
+
+/// Directory under the pez data dir holding one `<command>-<unix time>-<pid>` directory
+/// per run that removed or overwrote plugin files.
+pub(crate) const RUN_BACKUP_DIR_NAME: &str = ".run-backups";
+
+/// Where the current command copies files before [`remove_file`] deletes them.
+struct RunBackup {
+    dir: path::PathBuf,
+    /// Removed files are stored relative to this, usually the fish config dir.
+    base: path::PathBuf,
+    /// Paths copied so far, relative to `dir`.
+    copied: HashSet<path::PathBuf>,
+}
+
+/// The active run backup. It lives here rather than in [`crate::runtime::RuntimeOptions`]
+/// because it is state, not an option: nested commands (such as `sync` pruning) and
+/// blocking removal tasks share the outer run, and the guard from [`start_run`] bounds it.
+static RUN_BACKUP: Mutex<Option<RunBackup>> = Mutex::new(None);
+
+/// Runs started by this process, so two in the same second get separate directories.
+static RUNS_STARTED: AtomicUsize = AtomicUsize::new(0);
+
+/// Keeps the run backup started by [`start_run`] active; dropping it reports where the
+/// copies went and stops backing up. A guard for a nested run does nothing.
+pub(crate) struct RunBackupGuard {
+    owns_run: bool,
+}
+
+impl Drop for RunBackupGuard {
+    fn drop(&mut self) {
+        if !self.owns_run {
+            return;
+        }
+        let finished = RUN_BACKUP
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(run) = finished
+            && !run.copied.is_empty()
+        {
+            info!(
+                "{}Backed up {} removed or overwritten file(s) to {}",
+                Emoji("💾 ", ""),
+                run.copied.len(),
+                run.dir.display()
+            );
+        }
+    }
+}
+
+/// Copy every file removed through [`remove_file`] or overwritten after
+/// [`back_up_file`] into `dir`, or a new
+/// `<data dir>/.run-backups/<command>-<unix time>-<pid>` when `dir` is `None`, until the
+/// returned guard is dropped. Nothing is created until a file is actually backed up.
+/// Commands run from within another one (such as `sync` pruning) keep backing
+/// up into the outer run.
+pub(crate) fn start_run(command: &str, dir: Option<&path::Path>) -> anyhow::Result<RunBackupGuard> {
+    let dir = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => {
+            let mut name = format!(
+                "{command}-{}-{}",
+                crate::update_cache::now_secs(),
+                std::process::id()
+            );
+            let earlier = RUNS_STARTED.fetch_add(1, Ordering::Relaxed);
+            if earlier > 0 {
+                name.push_str(&format!("-{earlier}"));
+            }
+            utils::load_pez_data_dir()?
+                .join(RUN_BACKUP_DIR_NAME)
+                .join(name)
+        }
+    };
+    let base = utils::load_fish_config_dir()?;
+    let mut run = RUN_BACKUP.lock().unwrap_or_else(PoisonError::into_inner);
+    if run.is_some() {
+        return Ok(RunBackupGuard { owns_run: false });
+    }
+    *run = Some(RunBackup {
+        dir,
+        base,
+        copied: HashSet::new(),
+    });
+    Ok(RunBackupGuard { owns_run: true })
+}
+
+/// Copy a plugin file into the current run backup, when one is active, before pez
+/// overwrites it. The first copy of a path wins, so the backup holds what was there
+/// before the run.
+pub(crate) fn back_up_file(file: &path::Path) -> io::Result<()> {
+    let mut run = RUN_BACKUP.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(run) = run.as_mut() {
+        let relative = file
+            .strip_prefix(&run.base)
+            .unwrap_or_else(|_| file.strip_prefix("/").unwrap_or(file));
+        let copy = run.dir.join(relative);
+        if run.copied.contains(relative) {
+            return Ok(());
+        }
+        if let Some(parent) = copy.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(file, &copy)?;
+        run.copied.insert(relative.to_path_buf());
+    }
+    Ok(())
+}
+
+/// Delete a plugin file, first copying it into the current run backup when one is
+/// active. The file is kept when the copy fails.
+pub(crate) fn remove_file(file: &path::Path) -> io::Result<()> {
+    back_up_file(file)?;
+    fs::remove_file(file)
+}
+
+/// Decides whether an existing destination belongs to the user and, if so,
+/// preserves it before a plugin file overwrites it.
+pub(crate) struct DisplacedFiles {
+    root: path::PathBuf,
+    managed: HashSet<path::PathBuf>,
+}
+
+impl DisplacedFiles {
+    /// Files recorded in the main or temp lock are plugin-managed; anything else
+    /// found at a destination is treated as the user's own file.
+    pub(crate) fn load(fish_config_dir: &path::Path) -> anyhow::Result<Self> {
+        let root = utils::load_pez_data_dir()?.join(BACKUP_DIR_NAME);
+        let mut managed = HashSet::new();
+        let locks = [
+            utils::load_lock_file().ok().map(|(lock, _)| lock),
+            utils::load_or_create_temp_lock_file()
+                .ok()
+                .map(|(lock, _)| lock),
+        ];
+        for lock in locks.into_iter().flatten() {
+            for plugin in &lock.plugins {
+                managed.extend(plugin.resolve_paths(fish_config_dir, None));
+            }
+        }
+        Ok(Self { root, managed })
+    }
+
+    pub(crate) fn preserve(
+        &self,
+        plugin: &mut Plugin,
+        dir: &TargetDir,
+        name: &str,
+        dest: &path::Path,
+    ) -> anyhow::Result<()> {
+        if self.managed.contains(dest)
+            || plugin
+                .backups
+                .iter()
+                .any(|b| &b.dir == dir && b.name == name)
+        {
+            return Ok(());
+        }
+        let backup_path = self
+            .root
+            .join(plugin.repo.as_str())
+            .join(dir.as_str())
+            .join(name);
+        if let Some(parent) = backup_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(dest, &backup_path)?;
+        info!(
+            "   {}Backed up existing {} to {}",
+            Emoji("💾 ", ""),
+            dest.display(),
+            backup_path.display()
+        );
+        plugin.backups.push(PluginBackup {
+            dir: dir.clone(),
+            name: name.to_string(),
+            backup: backup_path.to_string_lossy().to_string(),
+        });
+        Ok(())
+    }
+}
+
+/// Move preserved user files back for every backup whose path the plugin no longer
+/// ships. Restored entries are dropped from `plugin.backups`; returns how many were restored.
+pub(crate) fn restore_displaced_files(plugin: &mut Plugin, fish_config_dir: &path::Path) -> usize {
+    let files = &plugin.files;
+    let mut restored = 0;
+    plugin.backups.retain(|b| {
+        if files.iter().any(|f| f.dir == b.dir && f.name == b.name) {
+            return true;
+        }
+        let backup_path = path::Path::new(&b.backup);
+        let dest = fish_config_dir.join(b.dir.as_str()).join(&b.name);
+        if !backup_path.exists() {
+            warn!(
+                "{} {} Backup of {} is missing: {}",
+                Emoji("⚠ ", ""),
+                utils::label_warning(),
+                dest.display(),
+                backup_path.display()
+            );
+            return false;
+        }
+        if dest.exists() {
+            warn!(
+                "{} {} Not restoring {}: the path is in use. Backup kept at {}",
+                Emoji("⚠ ", ""),
+                utils::label_warning(),
+                dest.display(),
+                backup_path.display()
+            );
+            return true;
+        }
+        let result = dest
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::copy(backup_path, &dest))
+            .and_then(|_| fs::remove_file(backup_path));
+        match result {
+            Ok(()) => {
+                info!("   {}Restored {}", Emoji("♻️  ", ""), dest.display());
+                restored += 1;
+                false
+            }
+            Err(e) => {
+                warn!("Failed to restore {}: {:?}", dest.display(), e);
+                true
+            }
+        }
+    });
+    restored
+}
+
+/// Restore every file a plugin displaced, for use once its own files are gone.
+pub(crate) fn restore_all_displaced_files(plugin: &Plugin, fish_config_dir: &path::Path) -> usize {
+    if plugin.backups.is_empty() {
+        return 0;
+    }
+    let mut removed = plugin.clone();
+    removed.files.clear();
+    restore_displaced_files(&mut removed, fish_config_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lock_file::PluginFile, models::PluginRepo};
+
+    fn plugin() -> Plugin {
+        Plugin {
+            name: "repo".to_string(),
+            repo: PluginRepo::new(None, "owner".into(), "repo".into()).unwrap(),
+            source: "https://example.com/owner/repo".to_string(),
+            commit_sha: "sha".to_string(),
+            files: vec![],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn preserve_skips_managed_and_restore_moves_back() {
+        let temp = tempfile::tempdir().unwrap();
+        let fish = temp.path().join("fish");
+        let functions = fish.join(TargetDir::Functions.as_str());
+        fs::create_dir_all(&functions).unwrap();
+        let user_file = functions.join("mine.fish");
+        let managed_file = functions.join("other.fish");
+        fs::write(&user_file, "user").unwrap();
+        fs::write(&managed_file, "plugin").unwrap();
+
+        let displaced = DisplacedFiles {
+            root: temp.path().join(BACKUP_DIR_NAME),
+            managed: HashSet::from([managed_file.clone()]),
+        };
+        let mut plugin = plugin();
+        displaced
+            .preserve(&mut plugin, &TargetDir::Functions, "mine.fish", &user_file)
+            .unwrap();
+        displaced
+            .preserve(
+                &mut plugin,
+                &TargetDir::Functions,
+                "other.fish",
+                &managed_file,
+            )
+            .unwrap();
+        assert_eq!(plugin.backups.len(), 1);
+
+        // Still shipped: nothing to restore.
+        fs::write(&user_file, "from plugin").unwrap();
+        plugin.files.push(PluginFile {
+            dir: TargetDir::Functions,
+            name: "mine.fish".to_string(),
+        });
+        assert_eq!(restore_displaced_files(&mut plugin, &fish), 0);
+        assert_eq!(plugin.backups.len(), 1);
+
+        fs::remove_file(&user_file).unwrap();
+        plugin.files.clear();
+        assert_eq!(restore_displaced_files(&mut plugin, &fish), 1);
+        assert!(plugin.backups.is_empty());
+        assert_eq!(fs::read_to_string(&user_file).unwrap(), "user");
+    }
+
+    #[test]
+    fn runs_get_their_own_directory_and_nested_runs_share_it() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let temp = tempfile::tempdir().unwrap();
+        let fish = temp.path().join("fish");
+        let data = temp.path().join("data");
+        fs::create_dir_all(fish.join("functions")).unwrap();
+        let saved: Vec<_> = ["PEZ_DATA_DIR", "PEZ_TARGET_DIR"]
+            .into_iter()
+            .map(|key| (key, std::env::var_os(key)))
+            .collect();
+        unsafe {
+            std::env::set_var("PEZ_DATA_DIR", &data);
+            std::env::set_var("PEZ_TARGET_DIR", &fish);
+        }
+        let file = fish.join("functions/a.fish");
+        let mut runs = Vec::new();
+        for contents in ["first", "second"] {
+            fs::write(&file, contents).unwrap();
+            let _run = start_run("upgrade", None).unwrap();
+            {
+                let _nested = start_run("prune", None).unwrap();
+                back_up_file(&file).unwrap();
+            }
+            fs::write(&file, "overwritten").unwrap();
+            remove_file(&file).unwrap();
+            runs.push(
+                RUN_BACKUP
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map(|run| (run.dir.clone(), run.copied.len())),
+            );
+        }
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+
+        let (first, first_files) = runs[0].clone().unwrap();
+        let (second, second_files) = runs[1].clone().unwrap();
+        assert_ne!(first, second);
+        assert_eq!((first_files, second_files), (1, 1));
+        assert_eq!(
+            fs::read_to_string(first.join("functions/a.fish")).unwrap(),
+            "first"
+        );
+        assert_eq!(
+            fs::read_to_string(second.join("functions/a.fish")).unwrap(),
+            "second"
+        );
+        assert!(RUN_BACKUP.lock().unwrap().is_none());
+    }
+}
@@ -13,6 +13,45 @@ fn parse_jobs_override(raw: &str) -> Result<usize, String> {
     Ok(value)
 }
 
+/// Parse a plugin argument, resolving partial names such as `tide` against the installed
+/// plugins (pez-lock.toml and `install --temp` ones) with
+/// [`crate::lock_file::LockFile::resolve_plugin_arg`].
+fn parse_installed_plugin(raw: &str) -> Result<crate::models::PluginRepo, String> {
+    let mut installed = crate::utils::load_lock_file()
+        .map(|(lock_file, _)| lock_file)
+        .unwrap_or_else(|_| crate::lock_file::init());
+    if let Ok((temp_lock_file, _)) = crate::utils::load_or_create_temp_lock_file() {
+        installed.plugins.extend(temp_lock_file.plugins);
+    }
+    installed
+        .resolve_plugin_arg(raw)
+        .map_err(|e| format!("{e:#}"))
+}
+
+/// Parse durations such as `90`, `30s`, `15m`, `6h`, or `2d` (bare numbers are seconds).
+fn parse_duration(raw: &str) -> Result<std::time::Duration, String> {
+    let trimmed = raw.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (digits, unit) = trimmed.split_at(split);
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("Invalid duration '{raw}'. Expected e.g. 30m, 6h, or 2d."))?;
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        _ => {
+            return Err(format!(
+                "Invalid duration unit in '{raw}'. Use s, m, h, or d."
+            ));
+        }
+    };
+    Ok(std::time::Duration::from_secs(value * multiplier))
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "pez", version, about, long_about = None)]
 pub(crate) struct Cli {
@@ -24,14 +63,49 @@ pub(crate) struct Cli {
     #[arg(long, value_name = "N", value_parser = parse_jobs_override, global = true)]
     pub(crate) jobs: Option<usize>,
 
+    /// Enable debug logging for one subsystem only (repeatable)
+    #[arg(long, value_enum, value_name = "SUBSYSTEM", global = true)]
+    pub(crate) trace: Vec<TraceTarget>,
+
+    /// Layout of `--format json` output
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub(crate) json_style: crate::output::JsonStyle,
+
+    /// When to color output; overrides NO_COLOR, CLICOLOR, and FORCE_COLOR
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t, global = true)]
+    pub(crate) color: crate::utils::ColorChoice,
+
     #[command(subcommand)]
     pub(crate) command: Commands,
 }
 
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub(crate) enum TraceTarget {
+    /// Clones, fetches, and checkouts
+    Git,
+    /// Per-plugin install decisions and staging
+    Install,
+    /// Files copied into the fish config directory
+    Copy,
+    /// Ref selectors, aliases, and commit resolution
+    Resolver,
+}
+
+impl TraceTarget {
+    pub(crate) fn target(self) -> &'static str {
+        match self {
+            TraceTarget::Git => crate::trace::GIT,
+            TraceTarget::Install => crate::trace::INSTALL,
+            TraceTarget::Copy => crate::trace::COPY,
+            TraceTarget::Resolver => crate::trace::RESOLVER,
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub(crate) enum Commands {
     /// Initialize pez
-    Init,
+    Init(InitArgs),
 
     /// Install fish plugin(s)
     Install(InstallArgs),
@@ -42,29 +116,142 @@ pub(crate) enum Commands {
     /// Upgrade installed fish plugin(s)
     Upgrade(UpgradeArgs),
 
+    /// Refresh update checks and upgrade the plugins that are behind their pez.toml selector
+    Update(UpdateArgs),
+
+    /// Undo the last install, upgrade, update, or uninstall
+    Rollback(RollbackArgs),
+
+    /// Save pez.toml and pez-lock.toml as a named snapshot (lists snapshots without a name)
+    Snapshot(SnapshotArgs),
+
+    /// Return plugins, clones, and files to a named snapshot
+    Restore(RestoreArgs),
+
     /// List installed fish plugins
     List(ListArgs),
 
     /// Prune uninstalled plugins
     Prune(PruneArgs),
 
+    /// Re-resolve pez.toml into pez-lock.toml without touching installed files, or inspect lock files
+    Lock(LockArgs),
+
+    /// Manage cloned repositories in the pez data directory
+    Cache(CacheArgs),
+
+    /// Remove orphaned clones, dead lock entries, and leftover plugin files
+    Gc(GcArgs),
+
+    /// Pin plugin(s) in pez.toml to their locked commit
+    Pin(PinArgs),
+
+    /// Remove the version/branch/tag/commit selector from plugin(s) in pez.toml
+    Unpin(PinArgs),
+
+    /// Pin every plugin in pez.toml to its locked commit
+    Freeze,
+
+    /// Remove every commit pin from pez.toml
+    Unfreeze,
+
+    /// Remove plugin(s)' files from the fish config directory but keep them in pez.toml and the lock
+    Disable(EnableArgs),
+
+    /// Copy the files of disabled plugin(s) back
+    Enable(EnableArgs),
+
+    /// Open pez.toml in $EDITOR and validate it after saving
+    Edit,
+
     /// Generate shell completion scripts
-    Completions {
-        #[arg(value_enum)]
-        shell: ShellType,
-    },
+    #[command(visible_alias = "completion")]
+    Completions(CompletionsArgs),
+
+    /// Print the CLI surface and installed plugins as JSON for external pickers
+    CompletionData,
 
     /// Output shell activation code
     Activate(ActivateArgs),
 
+    /// Re-copy missing or modified plugin files from local checkouts (no network)
+    Repair(RepairArgs),
+
+    /// Check that installed plugin files match pez-lock.toml (exits non-zero on drift)
+    Verify(VerifyArgs),
+
+    /// Install missing plugins, prune undeclared ones, and restore missing files
+    Sync(SyncArgs),
+
+    /// Check that the activated fish wrapper matches this pez version
+    VerifyActivate,
+
+    /// Show version and build metadata
+    Version(VersionArgs),
+
+    /// Show disk usage and installed files per plugin, and summarize the install/upgrade history
+    Stats(StatsArgs),
+
+    /// Search an index of known fish plugins
+    Search(SearchArgs),
+
+    /// Summarize configured, installed, missing, outdated, and orphaned plugins
+    Status(StatusArgs),
+
+    /// Show drift between pez.toml and pez-lock.toml
+    Diff(DiffArgs),
+
+    /// List plugins with updates available; exits 1 when there are any
+    Outdated(OutdatedArgs),
+
+    /// Show the commits between a plugin's locked commit and what its selector resolves to now
+    Changelog(ChangelogArgs),
+
+    /// Show an installed plugin's source, selector, commit, path, and files
+    Info(InfoArgs),
+
+    /// Print the path of an installed plugin's clone or local source
+    Path(PathArgs),
+
     /// Diagnose common setup issues
     Doctor(DoctorArgs),
 
-    /// Migrate from fisher (reads fish_plugins)
+    /// Validate pez.toml against the config JSON schema
+    Check,
+
+    /// Install and uninstall a fixture plugin in a throwaway sandbox to check that pez works
+    Smoke(SmokeArgs),
+
+    /// Import plugins from fisher, plug, Oh My Fish, fundle, or a plugin directory into pez.toml
     Migrate(MigrateArgs),
 
+    /// Write pez.toml as a fisher-compatible fish_plugins file
+    Export(ExportArgs),
+
+    /// Add plugins listed one per line in a file (or stdin) to pez.toml
+    Import(ImportArgs),
+
     /// List installed files for plugins
     Files(FilesArgs),
+
+    /// Show which plugin installed a file in the fish config dir
+    Why(WhyArgs),
+
+    /// Show which plugin provides a fish function or its completions
+    Which(WhichArgs),
+
+    /// List and apply themes shipped by installed plugins
+    Theme(ThemeArgs),
+
+    /// Open a plugin's homepage in the browser
+    Open(OpenArgs),
+
+    /// Show installed plugins as a tree of the dependencies they declare in pez-plugin.toml
+    Tree(TreeArgs),
+
+    /// Browse installed plugins full-screen and mark them to upgrade, uninstall, or pin
+    #[cfg(feature = "tui")]
+    Ui,
 }
 
 #[derive(Args, Debug)]
@@ -79,11 +266,28 @@ pub(crate) struct InstallArgs {
     /// Prune uninstalled plugins
     #[arg(short, long, conflicts_with = "plugins")]
     pub(crate) prune: bool,
+
+    /// Print what would be cloned, checked out, and copied without installing anything (with --prune, also what would be removed)
+    #[arg(long, conflicts_with_all = ["temp", "edit"])]
+    pub(crate) dry_run: bool,
+
+    /// With --prune, confirm all prompts
+    #[arg(short, long, requires = "prune")]
+    pub(crate) yes: bool,
+
+    /// Try plugins without recording them in pez.toml or pez-lock.toml (removed by `pez prune --temp`, the next `pez install`, or `pez sync`)
+    #[arg(long, visible_alias = "no-lock", requires = "plugins")]
+    pub(crate) temp: bool,
+
+    /// Open pez.toml in $VISUAL/$EDITOR with the new entries staged, to adjust them before installing
+    #[arg(short, long, requires = "plugins", conflicts_with = "temp")]
+    pub(crate) edit: bool,
 }
 
 #[derive(Args, Debug)]
 pub(crate) struct UninstallArgs {
-    /// Repo in the format `owner/repo` or `host/owner/repo`
+    /// Installed plugin: `owner/repo`, `host/owner/repo`, or a unique prefix of its name
+    #[arg(value_parser = parse_installed_plugin)]
     pub(crate) plugins: Option<Vec<crate::models::PluginRepo>>,
 
     /// Force uninstall even if the plugin data directory does not exist
@@ -93,15 +297,204 @@ pub(crate) struct UninstallArgs {
     /// Read plugin repos from stdin (one per line)
     #[arg(long)]
     pub(crate) stdin: bool,
+
+    /// Uninstall every plugin recorded in pez-lock.toml
+    #[arg(long, conflicts_with_all = ["plugins", "stdin"])]
+    pub(crate) all: bool,
+
+    /// With --all, skip the confirmation prompt
+    #[arg(short, long, requires = "all")]
+    pub(crate) yes: bool,
+
+    /// Copy removed plugin files into DIR instead of a new directory under `<data dir>/.run-backups`
+    #[arg(long, value_name = "DIR", conflicts_with = "no_backup")]
+    pub(crate) backup_dir: Option<std::path::PathBuf>,
+
+    /// Remove plugin files without keeping a copy
+    #[arg(long)]
+    pub(crate) no_backup: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct CacheArgs {
+    #[command(subcommand)]
+    pub(crate) command: CacheCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum CacheCommand {
+    /// Remove clones of plugins that are no longer in the lock file
+    Clean(CacheCleanArgs),
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct GcArgs {
+    /// List what would be removed without removing anything
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+
+    /// Skip the confirmation prompt
+    #[arg(short, long)]
+    pub(crate) yes: bool,
+
+    /// Copy removed plugin files into DIR instead of a new directory under `<data dir>/.run-backups`
+    #[arg(long, value_name = "DIR", conflicts_with = "no_backup")]
+    pub(crate) backup_dir: Option<std::path::PathBuf>,
+
+    /// Remove plugin files without keeping a copy
+    #[arg(long)]
+    pub(crate) no_backup: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct CacheCleanArgs {
+    /// Remove every clone plus the cache, shared objects, and plugin state (backups and history are kept)
+    #[arg(long)]
+    pub(crate) all: bool,
+
+    /// List what would be removed without removing anything
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+
+    /// With --all, skip the confirmation prompt
+    #[arg(short, long, requires = "all")]
+    pub(crate) yes: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct LockArgs {
+    #[command(subcommand)]
+    pub(crate) command: Option<LockCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum LockCommand {
+    /// Compare two lock files: added, removed, and re-pinned plugins
+    Diff(LockDiffArgs),
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct LockDiffArgs {
+    /// Older lock file (`-` reads stdin, e.g. from `git show HEAD~1:pez-lock.toml`)
+    pub(crate) old: std::path::PathBuf,
+
+    /// Newer lock file (`-` reads stdin)
+    pub(crate) new: std::path::PathBuf,
+
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<LockDiffFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum LockDiffFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct PinArgs {
+    /// Installed plugin: `owner/repo`, `host/owner/repo`, or a unique prefix of its name
+    #[arg(required = true, value_parser = parse_installed_plugin)]
+    pub(crate) plugins: Vec<crate::models::PluginRepo>,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct EnableArgs {
+    /// Installed plugin: `owner/repo`, `host/owner/repo`, or a unique prefix of its name
+    #[arg(required = true, value_parser = parse_installed_plugin)]
+    pub(crate) plugins: Vec<crate::models::PluginRepo>,
 }
 
 #[derive(Args, Debug)]
 pub(crate) struct UpgradeArgs {
-    /// Repo in the format `owner/repo` or `host/owner/repo`
+    /// Installed plugin: `owner/repo`, `host/owner/repo`, or a unique prefix of its name
+    #[arg(value_parser = parse_installed_plugin)]
     pub(crate) plugins: Option<Vec<crate::models::PluginRepo>>,
+
+    /// Re-copy plugin files even when the resolved commit is unchanged
+    #[arg(short, long)]
+    pub(crate) force: bool,
+
+    /// Discard local modifications inside cloned repositories instead of refusing to upgrade
+    #[arg(long)]
+    pub(crate) allow_dirty_repo: bool,
+
+    /// Move clones to exactly the commits in pez-lock.toml (up or down) instead of chasing remotes
+    #[arg(long)]
+    pub(crate) to_lock: bool,
+
+    /// Show each outdated plugin's new commits and accept, skip, or pin it before upgrading
+    #[arg(short, long, conflicts_with = "to_lock")]
+    pub(crate) interactive: bool,
+
+    /// Only upgrade plugins not updated within this age (e.g. 30d); unknown update times count as stale
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, conflicts_with = "to_lock")]
+    pub(crate) since: Option<std::time::Duration>,
+
+    /// Show which plugins would move to which commit and which files would change, without changing anything
+    #[arg(long, conflicts_with_all = ["to_lock", "interactive"])]
+    pub(crate) dry_run: bool,
+
+    /// Like --dry-run, but exit with status 1 when any plugin has an update
+    #[arg(long, conflicts_with_all = ["to_lock", "interactive", "dry_run"])]
+    pub(crate) check: bool,
+
+    /// Copy removed plugin files into DIR instead of a new directory under `<data dir>/.run-backups`
+    #[arg(long, value_name = "DIR", conflicts_with = "no_backup")]
+    pub(crate) backup_dir: Option<std::path::PathBuf>,
+
+    /// Remove plugin files without keeping a copy
+    #[arg(long)]
+    pub(crate) no_backup: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct UpdateArgs {
+    /// Installed plugin: `owner/repo`, `host/owner/repo`, or a unique prefix of its name
+    #[arg(value_parser = parse_installed_plugin)]
+    pub(crate) plugins: Option<Vec<crate::models::PluginRepo>>,
+
+    /// Discard local modifications inside cloned repositories instead of refusing to update
+    #[arg(long)]
+    pub(crate) allow_dirty_repo: bool,
+
+    /// Copy removed plugin files into DIR instead of a new directory under `<data dir>/.run-backups`
+    #[arg(long, value_name = "DIR", conflicts_with = "no_backup")]
+    pub(crate) backup_dir: Option<std::path::PathBuf>,
+
+    /// Remove plugin files without keeping a copy
+    #[arg(long)]
+    pub(crate) no_backup: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct RollbackArgs {
+    /// List what would change without changing anything
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct SnapshotArgs {
+    /// Snapshot name (letters, digits, `-`, `_`, `.`)
+    pub(crate) name: Option<String>,
+
+    /// Replace an existing snapshot with the same name
+    #[arg(short, long, requires = "name")]
+    pub(crate) force: bool,
 }
 
 #[derive(Args, Debug)]
+pub(crate) struct RestoreArgs {
+    /// Snapshot name
+    pub(crate) name: String,
+
+    /// List what would change without changing anything
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Args, Debug, Clone)]
 pub(crate) struct ListArgs {
     /// List format
     #[arg(long, value_enum)]
@@ -114,6 +507,39 @@ pub(crate) struct ListArgs {
     /// Filter plugins by source kind
     #[arg(long, value_enum)]
     pub(crate) filter: Option<ListFilter>,
+
+    /// With --outdated, reuse cached remote checks younger than this age (e.g. 30m, 6h, 2d)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "outdated", conflicts_with = "cached")]
+    pub(crate) max_age: Option<std::time::Duration>,
+
+    /// With --outdated, only use cached remote checks and never contact remotes
+    #[arg(long, requires = "outdated")]
+    pub(crate) cached: bool,
+
+    /// With --outdated, exit with status 1 when any plugin has an update
+    #[arg(long, requires = "outdated")]
+    pub(crate) fail_if_outdated: bool,
+
+    /// Show only plugins not updated within this age (e.g. 90d); unknown update times count as stale
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub(crate) stale: Option<std::time::Duration>,
+
+    /// Show destination files claimed by more than one plugin, including configured plugins not yet installed
+    #[arg(long, conflicts_with_all = ["outdated", "filter", "stale"])]
+    pub(crate) duplicates: bool,
+
+    /// With --format table, sort rows by this field instead of lock file order
+    #[arg(long, value_enum, conflicts_with_all = ["outdated", "duplicates"])]
+    pub(crate) sort: Option<ListSort>,
+
+    /// With --format table, show only these columns, in this order (comma-separated)
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        conflicts_with_all = ["outdated", "duplicates"]
+    )]
+    pub(crate) columns: Option<Vec<ListColumn>>,
 }
 
 #[derive(Args, Debug)]
@@ -129,6 +555,22 @@ pub(crate) struct PruneArgs {
     /// Confirm all prompts
     #[arg(short, long)]
     pub(crate) yes: bool,
+
+    /// Remove only plugins installed with `install --temp`
+    #[arg(long)]
+    pub(crate) temp: bool,
+
+    /// Keep unused plugins matching PATTERN (repo or name; `*` and `?` wildcards; repeatable)
+    #[arg(long, value_name = "PATTERN", conflicts_with = "temp")]
+    pub(crate) except: Vec<String>,
+
+    /// Copy removed plugin files into DIR instead of a new directory under `<data dir>/.run-backups`
+    #[arg(long, value_name = "DIR", conflicts_with = "no_backup")]
+    pub(crate) backup_dir: Option<std::path::PathBuf>,
+
+    /// Remove plugin files without keeping a copy
+    #[arg(long)]
+    pub(crate) no_backup: bool,
 }
 
 #[derive(Debug, Clone, clap::ValueEnum)]
@@ -136,6 +578,19 @@ pub(crate) enum ListFormat {
     Plain,
     Table,
     Json,
+    Yaml,
+    /// GitHub Actions workflow annotations (list --outdated only)
+    Github,
+}
+
+impl ListFormat {
+    pub(crate) fn structured(&self) -> Option<crate::output::Structured> {
+        match self {
+            ListFormat::Plain | ListFormat::Table | ListFormat::Github => None,
+            ListFormat::Json => Some(crate::output::Structured::Json),
+            ListFormat::Yaml => Some(crate::output::Structured::Yaml),
+        }
+    }
 }
 
 #[derive(Debug, Clone, clap::ValueEnum)]
@@ -145,6 +600,24 @@ pub(crate) enum ListFilter {
     Remote,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ListSort {
+    Name,
+    Repo,
+    Commit,
+    Source,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ListColumn {
+    Name,
+    Repo,
+    Source,
+    Selector,
+    Commit,
+    Updated,
+}
+
 #[derive(clap::ValueEnum, Clone, Debug)]
 pub(crate) enum ShellType {
     Fish,
@@ -152,8 +625,19 @@ pub(crate) enum ShellType {
 
 #[derive(clap::ValueEnum, Clone, Debug, PartialEq, Eq)]
 pub(crate) enum FilesDir {
+    /// Files under conf.d
     #[value(name = "conf.d")]
     ConfD,
+    /// Files under functions
+    #[value(name = "functions")]
+    Functions,
+    /// Files under completions
+    #[value(name = "completions")]
+    Completions,
+    /// Files under themes
+    #[value(name = "themes")]
+    Themes,
+    /// Files in every directory
     #[value(name = "all")]
     All,
 }
@@ -164,6 +648,8 @@ pub(crate) enum FilesFormat {
     Paths,
     #[value(name = "json")]
     Json,
+    #[value(name = "yaml")]
+    Yaml,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug, PartialEq, Eq)]
@@ -175,23 +661,108 @@ pub(crate) enum FilesFrom {
     Remove,
 }
 
+#[derive(Args, Debug)]
+pub(crate) struct WhyArgs {
+    /// File path (absolute or relative to the fish config dir, e.g. `conf.d/tide.fish`) or bare file name
+    pub(crate) file: String,
+
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<WhyFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum WhyFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct TreeArgs {
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<TreeFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum TreeFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct WhichArgs {
+    /// Function name, e.g. `fzf_configure_bindings`
+    pub(crate) name: String,
+
+    /// Also search installed functions and conf.d files for `function <name>` definitions
+    #[arg(long)]
+    pub(crate) grep: bool,
+
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<WhichFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum WhichFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct OpenArgs {
+    /// Installed or declared plugin (`owner/repo`, `host/owner/repo`, or its name)
+    pub(crate) plugin: String,
+
+    /// Print the URL instead of opening it
+    #[arg(long)]
+    pub(crate) print: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct ThemeArgs {
+    #[command(subcommand)]
+    pub(crate) command: ThemeCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum ThemeCommand {
+    /// List the themes/*.theme files installed plugins provide
+    List,
+
+    /// Make a plugin theme the current fish colors (runs `fish_config theme save`)
+    Set(ThemeSetArgs),
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct ThemeSetArgs {
+    /// Theme name as shown by `pez theme list`, e.g. "Catppuccin Mocha"
+    pub(crate) name: String,
+}
+
 #[derive(Args, Debug, Clone)]
 pub(crate) struct FilesArgs {
-    /// Plugin identifiers (owner/repo, host/owner/repo, or URL; @ref accepted for shorthand/host forms)
+    /// Plugin identifiers (owner/repo, host/owner/repo, URL, or a unique prefix of an installed plugin's name)
     pub(crate) plugins: Option<Vec<String>>,
 
     /// List files for all installed plugins
     #[arg(long)]
     pub(crate) all: bool,
 
-    /// Filter by destination directory (conf.d or all)
+    /// Filter by destination directory (conf.d, functions, completions, themes, or all)
     #[arg(long, value_enum, default_value = "all")]
     pub(crate) dir: FilesDir,
 
-    /// Output format (paths or json)
+    /// Only list recorded files that are missing on disk
+    #[arg(long)]
+    pub(crate) missing: bool,
+
+    /// Output format (paths, json, or yaml)
     #[arg(long, value_enum, default_value = "paths")]
     pub(crate) format: FilesFormat,
 
+    /// Append a tab and the owning plugin's state directory to each path (paths format)
+    #[arg(long)]
+    pub(crate) with_state_dir: bool,
+
     /// Derive target plugins by parsing argv for a subcommand (install/update/upgrade/uninstall/remove)
     #[arg(long, value_enum)]
     pub(crate) from: Option<FilesFrom>,
@@ -201,6 +772,23 @@ pub(crate) struct FilesArgs {
     pub(crate) passthrough: Vec<String>,
 }
 
+#[derive(Args, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub(crate) struct CompletionsArgs {
+    /// Target shell for completion code
+    #[arg(value_enum, required = true)]
+    pub(crate) shell: Option<ShellType>,
+
+    #[command(subcommand)]
+    pub(crate) command: Option<CompletionsCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum CompletionsCommand {
+    /// Syntax-check the generated activation and completion scripts with the local fish
+    SelfTest,
+}
+
 #[derive(Args, Debug)]
 pub(crate) struct ActivateArgs {
     /// Target shell for activation code
@@ -209,302 +797,325 @@ pub(crate) struct ActivateArgs {
 }
 
 #[derive(Args, Debug)]
-pub(crate) struct DoctorArgs {
+pub(crate) struct RepairArgs {
+    /// Installed plugin: `owner/repo`, `host/owner/repo`, or a unique prefix of its name (defaults to every installed plugin)
+    #[arg(value_parser = parse_installed_plugin)]
+    pub(crate) plugins: Option<Vec<crate::models::PluginRepo>>,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct VerifyArgs {
+    /// Installed plugin: `owner/repo`, `host/owner/repo`, or a unique prefix of its name (defaults to every installed plugin)
+    #[arg(value_parser = parse_installed_plugin)]
+    pub(crate) plugins: Option<Vec<crate::models::PluginRepo>>,
+
     /// Output format
     #[arg(long, value_enum)]
-    pub(crate) format: Option<DoctorFormat>,
+    pub(crate) format: Option<VerifyFormat>,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
-pub(crate) enum DoctorFormat {
+pub(crate) enum VerifyFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct SmokeArgs {
+    /// Keep the sandbox directory afterwards for inspection
+    #[arg(long)]
+    pub(crate) keep: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct SyncArgs {
+    /// Confirm removing every plugin when pez.toml declares none
+    #[arg(short, long)]
+    pub(crate) yes: bool,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct VersionArgs {
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<VersionFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum VersionFormat {
     Json,
 }
 
-// Types moved to models.rs: PluginRepo, InstallTarget, ResolvedInstallTarget
+#[derive(Args, Debug, Default)]
+pub(crate) struct InitArgs {
+    /// Also write conf.d/pez_bootstrap.fish with completions and the activation wrapper
+    #[arg(long)]
+    pub(crate) write_bootstrap: bool,
 
-use crate::models::{InstallTarget, PluginRepo, ResolvedInstallTarget};
+    /// Declare the plugins already installed by fisher, Oh My Fish, or by hand in the new pez.toml
+    #[arg(long, conflicts_with = "write_bootstrap")]
+    pub(crate) from_existing: bool,
+}
 
-impl InstallTarget {
-    /// Create an InstallTarget from a raw string.
-    pub fn from_raw<S: Into<String>>(s: S) -> Self {
-        InstallTarget { raw: s.into() }
-    }
-    /// Parse the raw string into a `ResolvedInstallTarget`.
-    /// Rules:
-    /// - `owner/repo[@ref]` => github.com
-    /// - `host/owner/repo[@ref]` (no scheme) => <https://host/owner/repo>
-    /// - URLs with scheme left as-is (no `@ref` parsing to avoid ssh user@ conflicts)
-    /// - Paths beginning with '/', './', '../', or '~' are treated as local
-    pub fn resolve(&self) -> anyhow::Result<ResolvedInstallTarget> {
-        use anyhow::Context;
-        let raw = self.raw.trim();
-
-        // Local path detection
-        let looks_like_path = raw.starts_with('/')
-            || raw.starts_with("./")
-            || raw.starts_with("../")
-            || raw.starts_with('~');
-
-        // URL/scheme detection
-        let has_scheme =
-            raw.contains("://") || raw.starts_with("git@") || raw.starts_with("ssh://");
-
-        // Helper to expand ~
-        let expand_tilde = |p: &str| -> anyhow::Result<String> {
-            if let Some(stripped) = p.strip_prefix("~/") {
-                let home =
-                    std::env::var_os("HOME").ok_or_else(|| anyhow::anyhow!("HOME not set"))?;
-                Ok(std::path::Path::new(&home)
-                    .join(stripped)
-                    .to_string_lossy()
-                    .to_string())
-            } else if p == "~" {
-                let home =
-                    std::env::var_os("HOME").ok_or_else(|| anyhow::anyhow!("HOME not set"))?;
-                Ok(std::path::PathBuf::from(home).to_string_lossy().to_string())
-            } else {
-                Ok(p.to_string())
-            }
-        };
+#[derive(Args, Debug)]
+pub(crate) struct SearchArgs {
+    /// Words to match against plugin names, repos, and descriptions
+    #[arg(required = true)]
+    pub(crate) query: Vec<String>,
 
-        if looks_like_path {
-            let mut path_str = expand_tilde(raw)?;
-            // Normalize relative paths (./, ../, .) to absolute using current_dir
-            if path_str == "." || path_str.starts_with("./") || path_str.starts_with("../") {
-                let abs = std::env::current_dir()
-                    .context("Failed to read current working directory")?
-                    .join(&path_str);
-                path_str = abs.to_string_lossy().to_string();
-            }
-            let plugin_name = std::path::Path::new(&path_str)
-                .file_name()
-                .and_then(|s| s.to_str())
-                .ok_or_else(|| anyhow::anyhow!("Invalid local path: {path_str}"))?
-                .to_string();
-            let plugin_repo = PluginRepo::new(None, "local".to_string(), plugin_name)
-                .map_err(|e| anyhow::anyhow!(e))?;
-            return Ok(ResolvedInstallTarget {
-                plugin_repo,
-                source: path_str,
-                ref_kind: crate::resolver::RefKind::None,
-                is_local: true,
-            });
-        }
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<ListFormat>,
 
-        // Full URL (leave as-is; no @ref parsing to avoid ssh user@host conflict)
-        if has_scheme {
-            let url = raw.to_string();
-            if let Some(plugin_repo) = PluginRepo::from_remote_url(&url) {
-                return Ok(ResolvedInstallTarget {
-                    plugin_repo,
-                    source: url,
-                    ref_kind: crate::resolver::RefKind::None,
-                    is_local: false,
-                });
-            }
-            let repo_name = url
-                .rsplit('/')
-                .next()
-                .map(|s| s.trim_end_matches(".git"))
-                .unwrap_or("repo")
-                .to_string();
-            let plugin_repo = PluginRepo::new(None, "url".to_string(), repo_name)
-                .map_err(|e| anyhow::anyhow!(e))?;
-            return Ok(ResolvedInstallTarget {
-                plugin_repo,
-                source: url,
-                ref_kind: crate::resolver::RefKind::None,
-                is_local: false,
-            });
-        }
+    /// Maximum number of results
+    #[arg(long, default_value_t = 20)]
+    pub(crate) limit: usize,
 
-        // host/owner/repo[@ref] or owner/repo[@ref]
-        let (base, ref_kind) = match raw.split_once('@') {
-            Some((lhs, rhs)) => (lhs.to_string(), crate::resolver::parse_ref_kind(rhs)),
-            None => (raw.to_string(), crate::resolver::RefKind::None),
-        };
+    /// Refresh the cached index even if it is less than a day old
+    #[arg(long, conflicts_with = "offline")]
+    pub(crate) refresh: bool,
 
-        let parts: Vec<&str> = base.split('/').collect();
-        if parts.len() == 2 {
-            // owner/repo -> default host github.com
-            let owner = parts[0].to_string();
-            let repo = parts[1].to_string();
-            let plugin_repo = PluginRepo::new(None, owner, repo).map_err(|e| anyhow::anyhow!(e))?;
-            let source = plugin_repo.default_remote_source();
-            return Ok(ResolvedInstallTarget {
-                plugin_repo,
-                source,
-                ref_kind,
-                is_local: false,
-            });
-        } else if parts.len() == 3 {
-            // host/owner/repo -> https host
-            let host = parts[0];
-            let owner = parts[1].to_string();
-            let repo = parts[2].to_string();
-            let plugin_repo = PluginRepo::new(Some(host.to_string()), owner, repo)
-                .map_err(|e| anyhow::anyhow!(e))?;
-            let source = format!("https://{}/{}", host, plugin_repo.owner_repo_path());
-            return Ok(ResolvedInstallTarget {
-                plugin_repo,
-                source,
-                ref_kind,
-                is_local: false,
-            });
-        }
+    /// Use only the cached index and bundled aliases; never contact the network
+    #[arg(long)]
+    pub(crate) offline: bool,
+}
 
-        Err(anyhow::anyhow!(format!(
-            "Invalid plugin source: {raw}. Expected <owner>/<repo>[@ref], <host>/<owner>/<repo>[@ref], URL, or local path"
-        )))
-            .context("Failed to parse install target")
-    }
+#[derive(Args, Debug)]
+pub(crate) struct StatsArgs {
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<StatsFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum StatsFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct StatusArgs {
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<StatusFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum StatusFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct DiffArgs {
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<DiffFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum DiffFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct OutdatedArgs {
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<OutdatedFormat>,
+
+    /// Reuse cached remote checks younger than this age (e.g. 30m, 6h, 2d)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, conflicts_with = "cached")]
+    pub(crate) max_age: Option<std::time::Duration>,
+
+    /// Only use cached remote checks and never contact remotes
+    #[arg(long)]
+    pub(crate) cached: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum OutdatedFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct ChangelogArgs {
+    /// Installed plugin (`owner/repo`, `host/owner/repo`, or its name)
+    pub(crate) plugin: String,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct InfoArgs {
+    /// Installed plugin: `owner/repo`, `host/owner/repo`, or a unique prefix of its name
+    #[arg(value_parser = parse_installed_plugin)]
+    pub(crate) plugin: crate::models::PluginRepo,
+
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<InfoFormat>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum InfoFormat {
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct PathArgs {
+    /// Installed plugin: `owner/repo`, `host/owner/repo`, or a unique prefix of its name
+    #[arg(value_parser = parse_installed_plugin)]
+    pub(crate) plugin: crate::models::PluginRepo,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct DoctorArgs {
+    /// Output format
+    #[arg(long, value_enum)]
+    pub(crate) format: Option<DoctorFormat>,
+
+    /// Also fail (exit code 1) on warnings; errors always exit with code 2
+    #[arg(long)]
+    pub(crate) strict: bool,
+
+    /// Clone missing repos, restore missing files, and repair the lock before checking
+    #[arg(long)]
+    pub(crate) fix: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+pub(crate) enum DoctorFormat {
+    Json,
+    Yaml,
+    /// GitHub Actions workflow annotations, one per warning or error
+    Github,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests_support::log::env_lock;
-    use std::ffi::OsString;
-    use std::path::PathBuf;
-
-    struct EnvGuard {
-        vars: Vec<(&'static str, Option<OsString>)>,
-    }
-
-    impl EnvGuard {
-        fn capture(keys: &[&'static str]) -> Self {
-            let vars = keys
-                .iter()
-                .map(|&key| (key, std::env::var_os(key)))
-                .collect();
-            Self { vars }
-        }
-    }
+    use crate::lock_file::{LockFile, Plugin};
+    use crate::models::PluginRepo;
+    use crate::tests_support::env::TestEnvironmentSetup;
 
-    impl Drop for EnvGuard {
-        fn drop(&mut self) {
-            for (key, value) in &self.vars {
-                match value {
-                    Some(val) => unsafe { std::env::set_var(key, val.clone()) },
-                    None => unsafe { std::env::remove_var(key) },
-                }
+    #[test]
+    fn plugin_args_resolve_partial_names_against_the_lock_file() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut env = TestEnvironmentSetup::new();
+        let plugins = [
+            "IlanCosman/tide",
+            "jorgebucaran/fisher",
+            "jorgebucaran/fishtape",
+        ]
+        .map(|repo| {
+            let repo: PluginRepo = repo.parse().unwrap();
+            Plugin {
+                name: repo.repo.clone(),
+                source: repo.default_remote_source(),
+                repo,
+                ..Default::default()
             }
+        });
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: plugins.to_vec(),
+        });
+        let saved = ["PEZ_CONFIG_DIR", "PEZ_DATA_DIR"].map(|k| (k, std::env::var_os(k)));
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
         }
-    }
 
-    struct CwdGuard {
-        original: PathBuf,
-    }
-
-    impl CwdGuard {
-        fn new() -> Self {
-            Self {
-                original: std::env::current_dir().unwrap(),
+        let repos = |args: &[&str]| -> Result<Vec<String>, String> {
+            let cli = Cli::try_parse_from(args).map_err(|e| e.to_string())?;
+            Ok(match cli.command {
+                Commands::Pin(args) | Commands::Unpin(args) => args.plugins,
+                Commands::Disable(args) | Commands::Enable(args) => args.plugins,
+                Commands::Upgrade(args) => args.plugins.unwrap_or_default(),
+                Commands::Path(args) => vec![args.plugin],
+                _ => unreachable!(),
             }
-        }
-    }
+            .iter()
+            .map(PluginRepo::as_str)
+            .collect())
+        };
+        assert_eq!(
+            repos(&["pez", "pin", "tide"]),
+            Ok(vec!["IlanCosman/tide".into()])
+        );
+        assert_eq!(
+            repos(&["pez", "disable", "fisher", "fishtape"]),
+            Ok(vec![
+                "jorgebucaran/fisher".into(),
+                "jorgebucaran/fishtape".into()
+            ])
+        );
+        assert_eq!(
+            repos(&["pez", "upgrade", "ilan"]),
+            Ok(vec!["IlanCosman/tide".into()])
+        );
+        assert_eq!(
+            repos(&["pez", "path", "owner/other"]),
+            Ok(vec!["owner/other".into()])
+        );
+        let ambiguous = repos(&["pez", "enable", "fish"]).unwrap_err();
+        assert!(
+            ambiguous.contains("matches several installed plugins"),
+            "{ambiguous}"
+        );
 
-    impl Drop for CwdGuard {
-        fn drop(&mut self) {
-            let _ = std::env::set_current_dir(&self.original);
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(value) => std::env::set_var(key, value),
+                    None => std::env::remove_var(key),
+                }
+            }
         }
     }
 
     #[test]
-    fn resolve_owner_repo_and_variants() {
-        let _lock = env_lock().lock().unwrap();
-        let _cwd_guard = CwdGuard::new();
-
-        // owner/repo (github)
-        let t: InstallTarget = "o/r".parse().unwrap();
-        let r = t.resolve().unwrap();
-        assert_eq!(r.plugin_repo.as_str(), "o/r");
-        assert_eq!(r.plugin_repo.host.as_deref(), None);
-        assert_eq!(r.source, "https://github.com/o/r");
-        assert!(!r.is_local);
-
-        // owner/repo@v3 -> Version
-        let t: InstallTarget = "o/r@v3".parse().unwrap();
-        let r = t.resolve().unwrap();
-        matches!(r.ref_kind, crate::resolver::RefKind::Version(_));
-
-        // explicit tag/branch/commit
-        let t: InstallTarget = "o/r@tag:v1.0.0".parse().unwrap();
-        let r = t.resolve().unwrap();
-        matches!(r.ref_kind, crate::resolver::RefKind::Tag(_));
-
-        let t: InstallTarget = "gitlab.com/o/r@branch:dev".parse().unwrap();
-        let r = t.resolve().unwrap();
-        assert_eq!(r.source, "https://gitlab.com/o/r");
-        assert_eq!(r.plugin_repo.host.as_deref(), Some("gitlab.com"));
-        assert_eq!(r.plugin_repo.as_str(), "gitlab.com/o/r");
-        matches!(r.ref_kind, crate::resolver::RefKind::Branch(_));
-
-        let t: InstallTarget = "https://example.com/o/r".parse().unwrap();
-        let r = t.resolve().unwrap();
-        assert_eq!(r.source, "https://example.com/o/r");
-        assert_eq!(r.plugin_repo.host.as_deref(), Some("example.com"));
-        assert_eq!(r.plugin_repo.as_str(), "example.com/o/r");
-        matches!(r.ref_kind, crate::resolver::RefKind::None);
-
-        let t: InstallTarget = "https://github.com/o/r".parse().unwrap();
-        let r = t.resolve().unwrap();
-        assert_eq!(r.plugin_repo.host.as_deref(), None);
-        assert_eq!(r.plugin_repo.as_str(), "o/r");
-        assert_eq!(r.source, "https://github.com/o/r");
-
-        let t: InstallTarget = "git@bitbucket.org:o/r.git".parse().unwrap();
-        let r = t.resolve().unwrap();
-        assert_eq!(r.plugin_repo.host.as_deref(), Some("bitbucket.org"));
-        assert_eq!(r.plugin_repo.as_str(), "bitbucket.org/o/r");
-        assert_eq!(r.source, "git@bitbucket.org:o/r.git");
-        matches!(r.ref_kind, crate::resolver::RefKind::None);
-
-        // local path
-        let home = std::env::var("HOME").unwrap();
-        let t = InstallTarget::from_raw(home.to_string());
-        let r = t.resolve().unwrap();
-        assert!(r.is_local);
-
-        // Relative paths are normalized to absolute using the current working directory
-        let t = InstallTarget::from_raw("./some/dir");
-        let r = t.resolve().unwrap();
-        assert!(r.is_local);
-        let cwd = std::env::current_dir().unwrap();
-        assert!(r.source.starts_with(&*cwd.to_string_lossy()));
+    fn parse_duration_accepts_units() {
+        use std::time::Duration;
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_duration("6h"), Ok(Duration::from_secs(21_600)));
+        assert_eq!(parse_duration("2d"), Ok(Duration::from_secs(172_800)));
+        assert!(parse_duration("6w").is_err());
+        assert!(parse_duration("h").is_err());
     }
 
     #[test]
-    fn resolve_tilde_path_expands_and_marks_local() {
-        let _lock = env_lock().lock().unwrap();
-        let _guard = EnvGuard::capture(&["HOME"]);
-        let temp = tempfile::tempdir().unwrap();
-        unsafe {
-            std::env::set_var("HOME", temp.path());
+    fn list_cache_flags_require_outdated() {
+        assert!(Cli::try_parse_from(["pez", "list", "--cached"]).is_err());
+        assert!(
+            Cli::try_parse_from(["pez", "list", "--outdated", "--cached", "--max-age", "1h"])
+                .is_err()
+        );
+        let cli = Cli::try_parse_from(["pez", "list", "--outdated", "--max-age", "6h"]).unwrap();
+        match cli.command {
+            Commands::List(args) => {
+                assert_eq!(args.max_age, Some(std::time::Duration::from_secs(21_600)));
+            }
+            _ => panic!("expected list command"),
         }
-
-        let target = InstallTarget::from_raw("~/plugins");
-        let resolved = target.resolve().unwrap();
-        assert!(resolved.is_local);
-        let expected = temp.path().join("plugins").to_string_lossy().to_string();
-        assert_eq!(resolved.source, expected);
-        assert_eq!(resolved.plugin_repo.owner, "local");
-        assert_eq!(resolved.plugin_repo.repo, "plugins");
     }
 
     #[test]
-    fn resolve_absolute_path_does_not_require_current_dir() {
-        let _lock = env_lock().lock().unwrap();
-        let _cwd_guard = CwdGuard::new();
-        let temp = tempfile::tempdir().unwrap();
-        std::env::set_current_dir(temp.path()).unwrap();
-        drop(temp);
-
-        let abs_path = _cwd_guard.original.join("absolute-plugin");
-        let target = InstallTarget::from_raw(abs_path.to_string_lossy().to_string());
-        let resolved = target.resolve().expect("absolute path should resolve");
-        assert!(resolved.is_local);
-        assert_eq!(resolved.source, abs_path.to_string_lossy().to_string());
+    fn completions_accepts_shell_or_self_test() {
+        let cli = Cli::try_parse_from(["pez", "completions", "fish"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Completions(CompletionsArgs {
+                shell: Some(ShellType::Fish),
+                command: None
+            })
+        ));
+        let cli = Cli::try_parse_from(["pez", "completion", "self-test"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Completions(CompletionsArgs {
+                shell: None,
+                command: Some(CompletionsCommand::SelfTest)
+            })
+        ));
+        assert!(Cli::try_parse_from(["pez", "completions"]).is_err());
     }
 
     #[test]
@@ -528,12 +1139,41 @@ mod tests {
         assert!(matches!(cli.command, Commands::Install(_)));
     }
 
+    #[test]
+    fn parse_repeated_trace_targets() {
+        let cli = Cli::parse_from(["pez", "upgrade", "--trace", "git", "--trace", "resolver"]);
+        let targets: Vec<&str> = cli.trace.iter().map(|t| t.target()).collect();
+        assert_eq!(targets, vec!["git", "resolver"]);
+        assert!(Cli::try_parse_from(["pez", "--trace", "network", "list"]).is_err());
+    }
+
     #[test]
     fn jobs_override_rejects_zero() {
         assert!(Cli::try_parse_from(["pez", "--jobs", "0", "list"]).is_err());
     }
 }
 
+#[derive(Args, Debug)]
+pub(crate) struct ExportArgs {
+    /// Write to this file instead of stdout (e.g. ~/.config/fish/fish_plugins)
+    #[arg(short, long)]
+    pub(crate) output: Option<std::path::PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct ImportArgs {
+    /// File with one install target per line (`-` reads stdin)
+    pub(crate) file: std::path::PathBuf,
+
+    /// Do not write files; print planned changes
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+
+    /// Immediately install imported plugins
+    #[arg(long)]
+    pub(crate) install: bool,
+}
+
 #[derive(Args, Debug)]
 pub(crate) struct MigrateArgs {
     /// Do not write files; print planned changes
@@ -547,4 +1187,27 @@ pub(crate) struct MigrateArgs {
     /// Immediately install migrated plugins
     #[arg(long)]
     pub(crate) install: bool,
+
+    /// Plugin manager or layout to migrate from
+    #[arg(long, value_enum, default_value_t = MigrateFrom::Fisher)]
+    pub(crate) from: MigrateFrom,
+
+    /// File or directory to read instead of the importer's default
+    /// (fisher: fish_plugins, plug: config.fish, omf: the Oh My Fish directory,
+    /// fundle: the fish config directory; dir has none)
+    pub(crate) path: Option<std::path::PathBuf>,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub(crate) enum MigrateFrom {
+    /// fisher's fish_plugins file
+    Fisher,
+    /// `plug <target>` lines in a fish script
+    Plug,
+    /// A directory whose subdirectories are plugins
+    Dir,
+    /// Oh My Fish's bundle and installed packages
+    Omf,
+    /// `fundle plugin` lines in fish config files
+    Fundle,
 }
@@ -1,15 +1,31 @@
+use crate::output::out;
+
 /// Fish activation script emitter.
 ///
 /// Prints Fish wrapper code to stdout. Keep stdout clean of logs so it can be
 /// piped into `source`.
 pub(crate) fn run_fish() -> String {
     let script = fish_script();
-    print!("{script}");
+    out!("{script}");
     script
 }
 
-fn fish_script() -> String {
+/// Global options that take a value, which the wrapper skips together with their value
+/// while looking for the subcommand. A test checks this against the `Cli` definition.
+const GLOBAL_VALUE_FLAGS: &[&str] = &["--jobs", "--color", "--trace", "--json-style"];
+
+pub(crate) fn fish_script() -> String {
     let version = env!("CARGO_PKG_VERSION");
+    let value_flags = GLOBAL_VALUE_FLAGS
+        .iter()
+        .map(|flag| format!("'{flag}'"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let value_flag_assignments = GLOBAL_VALUE_FLAGS
+        .iter()
+        .map(|flag| format!("'{flag}=*'"))
+        .collect::<Vec<_>>()
+        .join(" ");
     // Guard against multiple sourcing and wrap pez to emit events in-process.
     format!(
         r#"
@@ -34,10 +50,10 @@ if not set -q __pez_activate_version; or test "$__pez_activate_version" != "$__p
             case '--verbose' '-V' '--version' '-h' '--help'
                 set i (math $i + 1)
                 continue
-            case '--jobs'
+            case {value_flags}
                 set i (math $i + 2)
                 continue
-            case '--jobs=*'
+            case {value_flag_assignments}
                 set i (math $i + 1)
                 continue
             end
@@ -52,19 +68,26 @@ if not set -q __pez_activate_version; or test "$__pez_activate_version" != "$__p
         return 1
     end
 
-    function __pez_fish_source_and_emit --description "Source conf.d and emit events" --argument-names phase from
-        set -l passthrough $argv[3..-1]
-        set -l paths (command pez files --dir conf.d --from $from -- $passthrough | sort)
-        for path in $paths
+    function __pez_fish_source_and_emit --description "Source conf.d and emit events" --argument-names from
+        set -l passthrough $argv[2..-1]
+        set -l entries (command pez files --dir conf.d --with-state-dir --from $from -- $passthrough | sort)
+        for entry in $entries
+            set -l fields (string split -m 2 \t -- $entry)
+            set -l path $fields[1]
             if test -f "$path"
+                set -gx PEZ_PLUGIN_STATE_DIR $fields[2]
                 source "$path"
-                set -l name (basename "$path" .fish)
-                emit "$name"_"$phase"
+                for event in (string split -n ' ' -- $fields[3])
+                    emit $event
+                end
+                set -e PEZ_PLUGIN_STATE_DIR
             end
         end
     end
 
     function pez --wraps pez --description "pez with fish event hooks"
+        # Tell the binary that events and universal variables cannot persist here.
+        set -q fish_private_mode; and set -lx PEZ_FISH_PRIVATE 1
         set -l parsed (__pez_fish_split_subcmd $argv)
         if test (count $parsed) -eq 0
             command pez $argv
@@ -77,30 +100,30 @@ if not set -q __pez_activate_version; or test "$__pez_activate_version" != "$__p
             env PEZ_SUPPRESS_EMIT=1 command pez $argv
             set -l exit_status $status
             if test $exit_status -eq 0
-                __pez_fish_source_and_emit install install $subargs
+                __pez_fish_source_and_emit install $subargs
             end
             return $exit_status
         case update upgrade
             env PEZ_SUPPRESS_EMIT=1 command pez $argv
             set -l exit_status $status
             if test $exit_status -eq 0
-                __pez_fish_source_and_emit update $subcmd $subargs
+                __pez_fish_source_and_emit $subcmd $subargs
             end
             return $exit_status
         case uninstall remove
             if contains -- --stdin $subargs
                 set -l stdin_file (cat | psub -f -s .pez_uninstall)
-                cat $stdin_file | __pez_fish_source_and_emit uninstall $subcmd $subargs
+                cat $stdin_file | __pez_fish_source_and_emit $subcmd $subargs
                 cat $stdin_file | env PEZ_SUPPRESS_EMIT=1 command pez $argv
                 set -l exit_status $status
                 return $exit_status
             end
-            __pez_fish_source_and_emit uninstall $subcmd $subargs
+            __pez_fish_source_and_emit $subcmd $subargs
             env PEZ_SUPPRESS_EMIT=1 command pez $argv
             set -l exit_status $status
             return $exit_status
         case '*'
-            command pez $argv
+            env PEZ_ACTIVATE_VERSION=$__pez_activate_version command pez $argv
         end
     end
 end
@@ -119,8 +142,22 @@ mod tests {
         assert!(text.contains("__pez_version"));
         assert!(text.contains(env!("CARGO_PKG_VERSION")));
         assert!(text.contains("PEZ_SUPPRESS_EMIT"));
-        assert!(text.contains("command pez files --dir conf.d --from"));
+        assert!(text.contains("command pez files --dir conf.d --with-state-dir --from"));
+        assert!(text.contains("set -gx PEZ_PLUGIN_STATE_DIR $fields[2]"));
+        assert!(text.contains("for event in (string split -n ' ' -- $fields[3])"));
+        assert!(!text.contains("(basename \"$path\" .fish)"));
         assert!(text.contains("__pez_fish_split_subcmd"));
+        assert!(text.contains("set -q fish_private_mode; and set -lx PEZ_FISH_PRIVATE 1"));
+    }
+
+    #[test]
+    fn default_case_exports_activate_version() {
+        let text = fish_script();
+        let parts: Vec<&str> = text.split("case '*'").collect();
+        assert!(parts.len() > 1, "default case missing");
+        assert!(
+            parts[1].contains("env PEZ_ACTIVATE_VERSION=$__pez_activate_version command pez $argv")
+        );
     }
 
     #[test]
@@ -130,7 +167,7 @@ mod tests {
         assert!(parts.len() > 1, "uninstall case missing");
         let segment = parts[1];
         let emit_pos = segment
-            .find("__pez_fish_source_and_emit uninstall")
+            .find("__pez_fish_source_and_emit $subcmd")
             .expect("emit call missing");
         let command_pos = segment
             .find("env PEZ_SUPPRESS_EMIT=1 command pez $argv")
@@ -147,25 +184,40 @@ mod tests {
         let text = fish_script();
         assert!(!text.contains("__pez_fish_filter_targets"));
         assert!(text.contains("set -l subargs $parsed[2..-1]"));
-        assert!(text.contains("__pez_fish_source_and_emit install install $subargs"));
-        assert!(text.contains("set -l passthrough $argv[3..-1]"));
+        assert!(text.contains("__pez_fish_source_and_emit install $subargs"));
+        assert!(text.contains("set -l passthrough $argv[2..-1]"));
     }
 
     #[test]
     fn global_flags_are_skipped() {
         let text = fish_script();
-        assert!(text.contains("--jobs"));
-        assert!(text.contains("--jobs=*"));
+        assert!(text.contains("case '--jobs' '--color' '--trace' '--json-style'\n"));
+        assert!(text.contains("case '--jobs=*' '--color=*' '--trace=*' '--json-style=*'\n"));
         assert!(text.contains("--verbose"));
         assert!(text.contains("^-[v]+$"));
     }
 
+    #[test]
+    fn global_value_flags_match_cli() {
+        use clap::CommandFactory;
+
+        let mut from_cli: Vec<String> = crate::cli::Cli::command()
+            .get_arguments()
+            .filter(|arg| arg.is_global_set() && arg.get_action().takes_values())
+            .filter_map(|arg| arg.get_long().map(|long| format!("--{long}")))
+            .collect();
+        from_cli.sort();
+        let mut skipped: Vec<String> = GLOBAL_VALUE_FLAGS.iter().map(|f| f.to_string()).collect();
+        skipped.sort();
+        assert_eq!(skipped, from_cli);
+    }
+
     #[test]
     fn uninstall_stdin_is_tapped() {
         let text = fish_script();
         assert!(text.contains("contains -- --stdin $subargs"));
         assert!(text.contains("psub -f -s .pez_uninstall"));
-        assert!(text.contains("cat $stdin_file | __pez_fish_source_and_emit uninstall"));
+        assert!(text.contains("cat $stdin_file | __pez_fish_source_and_emit $subcmd"));
         assert!(text.contains("cat $stdin_file | env PEZ_SUPPRESS_EMIT=1 command pez $argv"));
     }
 
@@ -0,0 +1,348 @@
+use crate::{
+    cli::{CacheArgs, CacheCleanArgs, CacheCommand},
+    cmd::prune,
+    lock_file::LockFile,
+    utils,
+};
+
+use console::Emoji;
+use std::{collections::HashSet, fs, path};
+use tracing::{info, warn};
+
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct CleanSummary {
+    pub(crate) removed: Vec<path::PathBuf>,
+    pub(crate) bytes: u64,
+}
+
+pub(crate) fn run(args: &CacheArgs) -> anyhow::Result<()> {
+    match &args.command {
+        CacheCommand::Clean(clean_args) => {
+            let _ = clean(clean_args)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove clones no longer recorded in pez-lock.toml (or the `install --temp` lock),
+/// or every clone plus the disposable dot-directories with `--all`.
+fn clean(args: &CacheCleanArgs) -> anyhow::Result<CleanSummary> {
+    let data_dir = utils::load_pez_data_dir()?;
+    if !data_dir.exists() {
+        info!("{}Nothing to clean.", Emoji("🎉 ", ""));
+        return Ok(CleanSummary::default());
+    }
+
+    let targets = if args.all {
+        if !args.dry_run && !args.yes {
+            warn!(
+                "{} {} This removes every clone in {} plus the update-check cache, shared objects, and plugin state. Backups, snapshots, and rollback history are kept.",
+                Emoji("🚧 ", ""),
+                utils::label_warning(),
+                data_dir.display()
+            );
+            if !prune::confirm_removal()? {
+                anyhow::bail!("{}Cache clean aborted.", Emoji("🚧 ", ""));
+            }
+        }
+        let mut targets = find_clones(&data_dir);
+        targets.extend(
+            [
+                utils::load_pez_cache_dir()?,
+                utils::load_pez_shared_objects_dir()?,
+                utils::load_pez_state_dir()?,
+            ]
+            .into_iter()
+            .filter(|dir| dir.exists()),
+        );
+        targets
+    } else {
+        let (lock_file, _) = utils::load_lock_file()?;
+        let (temp_lock_file, _) = utils::load_or_create_temp_lock_file()?;
+        unused_clones(&data_dir, &[&lock_file, &temp_lock_file])
+    };
+
+    let mut summary = CleanSummary::default();
+    for target in targets {
+        let bytes = dir_size(&target);
+        if args.dry_run {
+            info!(
+                "Would remove {} ({})",
+                target.display(),
+                format_bytes(bytes)
+            );
+        } else {
+            fs::remove_dir_all(&target)?;
+            if target.starts_with(&data_dir) {
+                remove_empty_parents(&target, &data_dir);
+            }
+            info!("Removed {} ({})", target.display(), format_bytes(bytes));
+        }
+        summary.bytes += bytes;
+        summary.removed.push(target);
+    }
+
+    if summary.removed.is_empty() {
+        info!("{}No unused clones found.", Emoji("🎉 ", ""));
+    } else if args.dry_run {
+        info!(
+            "{}Dry run: {} would be reclaimed.",
+            Emoji("🧪 ", ""),
+            format_bytes(summary.bytes)
+        );
+    } else {
+        info!(
+            "{}Reclaimed {}.",
+            Emoji("🧹 ", ""),
+            format_bytes(summary.bytes)
+        );
+        if args.all {
+            info!("Run `pez install` to clone the plugins in pez-lock.toml again.");
+            if let Some(disabled) = disabled_files_summary() {
+                info!(
+                    "Until then, disabled files cannot be copied back from their clones: {}",
+                    disabled
+                );
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Paths recorded under `disabled_files` in pez-lock.toml, which `pez enable` and
+/// removing them from `disable_files` restore from the plugin's clone.
+fn disabled_files_summary() -> Option<String> {
+    let (lock_file, _) = utils::load_lock_file().ok()?;
+    let fish_config_dir = utils::load_fish_config_dir().ok()?;
+    let paths: Vec<String> = lock_file
+        .plugins
+        .iter()
+        .flat_map(|plugin| &plugin.disabled_files)
+        .map(|file| file.get_path(&fish_config_dir).display().to_string())
+        .collect();
+    (!paths.is_empty()).then(|| paths.join(", "))
+}
+
+/// Git clones under the data dir (`owner/repo` or `host/owner/repo`). Dot-directories
+/// such as `.cache`, `.objects`, and `.state` belong to pez itself and are skipped.
+pub(crate) fn find_clones(data_dir: &path::Path) -> Vec<path::PathBuf> {
+    let mut clones = Vec::new();
+    let mut walker = walkdir::WalkDir::new(data_dir)
+        .min_depth(1)
+        .max_depth(3)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.file_type().is_dir() && !entry.file_name().to_string_lossy().starts_with('.')
+        });
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else { continue };
+        if entry.path().join(".git").exists() {
+            clones.push(entry.into_path());
+            walker.skip_current_dir();
+        }
+    }
+    clones
+}
+
+pub(crate) fn unused_clones(data_dir: &path::Path, lock_files: &[&LockFile]) -> Vec<path::PathBuf> {
+    let used: HashSet<path::PathBuf> = lock_files
+        .iter()
+        .flat_map(|lock_file| &lock_file.plugins)
+        .map(|plugin| data_dir.join(plugin.repo.as_str()))
+        .collect();
+    find_clones(data_dir)
+        .into_iter()
+        .filter(|clone| !used.contains(clone))
+        .collect()
+}
+
+/// Drop `owner` and `host` directories left empty by a removed clone.
+pub(crate) fn remove_empty_parents(removed: &path::Path, data_dir: &path::Path) {
+    let mut dir = removed.parent();
+    while let Some(current) = dir {
+        if current == data_dir || fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+}
+
+/// Total size of the files under `path`, not following symlinks.
+pub(crate) fn dir_size(path: &path::Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Human-readable size in binary units, e.g. `1.5 MiB`.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lock_file::Plugin,
+        models::PluginRepo,
+        tests_support::{env::TestEnvironmentSetup, log::env_lock},
+    };
+
+    fn make_clone(data_dir: &path::Path, repo: &str, bytes: usize) {
+        let clone = data_dir.join(repo);
+        fs::create_dir_all(clone.join(".git")).unwrap();
+        fs::write(clone.join("init.fish"), vec![b'x'; bytes]).unwrap();
+    }
+
+    fn locked(repo: &str) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "abc1234".into(),
+            files: vec![],
+            ..Default::default()
+        }
+    }
+
+    fn clean_with_env(
+        env: &TestEnvironmentSetup,
+        args: &CacheCleanArgs,
+    ) -> anyhow::Result<CleanSummary> {
+        let _lock = env_lock().lock().unwrap();
+        let saved: Vec<_> = ["PEZ_CONFIG_DIR", "PEZ_DATA_DIR", "PEZ_CACHE_DIR"]
+            .iter()
+            .map(|key| (*key, std::env::var_os(key)))
+            .collect();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+            std::env::remove_var("PEZ_CACHE_DIR");
+        }
+        let result = clean(args);
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn clean_removes_only_clones_missing_from_lock_files() {
+        let mut env = TestEnvironmentSetup::new();
+        make_clone(&env.data_dir, "owner/kept", 10);
+        make_clone(&env.data_dir, "owner/unused", 100);
+        make_clone(&env.data_dir, "gitlab.com/group/gone", 1000);
+        make_clone(&env.data_dir, "owner/temp", 10);
+        fs::create_dir_all(env.data_dir.join(".cache")).unwrap();
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![locked("owner/kept")],
+        });
+        LockFile {
+            version: 2,
+            plugins: vec![locked("owner/temp")],
+        }
+        .save(&env.data_dir.join(".temp-lock.toml"))
+        .unwrap();
+
+        let args = CacheCleanArgs {
+            all: false,
+            dry_run: true,
+            yes: false,
+        };
+        let planned = clean_with_env(&env, &args).unwrap();
+        assert_eq!(planned.bytes, 1100);
+        assert!(env.data_dir.join("owner/unused").exists());
+
+        let summary = clean_with_env(
+            &env,
+            &CacheCleanArgs {
+                dry_run: false,
+                ..args
+            },
+        )
+        .unwrap();
+        assert_eq!(summary, planned);
+        assert!(!env.data_dir.join("owner/unused").exists());
+        assert!(!env.data_dir.join("gitlab.com").exists());
+        assert!(env.data_dir.join("owner/kept").exists());
+        assert!(env.data_dir.join("owner/temp").exists());
+        assert!(env.data_dir.join(".cache").exists());
+    }
+
+    #[test]
+    fn clean_all_removes_clones_and_disposable_dirs_only() {
+        let env = TestEnvironmentSetup::new();
+        make_clone(&env.data_dir, "owner/kept", 10);
+        make_clone(&env.data_dir, "gitlab.com/group/other", 10);
+        for dir in [
+            ".cache",
+            ".objects",
+            ".state",
+            ".backups",
+            ".snapshots",
+            ".run-backups",
+        ] {
+            fs::create_dir_all(env.data_dir.join(dir)).unwrap();
+        }
+        fs::write(env.data_dir.join(".backups/gamma.fish"), "USER ORIGINAL").unwrap();
+        fs::write(env.data_dir.join(".journal.json"), "{}").unwrap();
+
+        let summary = clean_with_env(
+            &env,
+            &CacheCleanArgs {
+                all: true,
+                dry_run: false,
+                yes: true,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            summary.removed,
+            vec![
+                env.data_dir.join("gitlab.com/group/other"),
+                env.data_dir.join("owner/kept"),
+                env.data_dir.join(".cache"),
+                env.data_dir.join(".objects"),
+                env.data_dir.join(".state"),
+            ]
+        );
+        assert!(!env.data_dir.join("owner").exists());
+        assert!(!env.data_dir.join("gitlab.com").exists());
+        assert_eq!(
+            fs::read_to_string(env.data_dir.join(".backups/gamma.fish")).unwrap(),
+            "USER ORIGINAL"
+        );
+        assert!(env.data_dir.join(".snapshots").exists());
+        assert!(env.data_dir.join(".run-backups").exists());
+        assert!(env.data_dir.join(".journal.json").exists());
+    }
+
+    #[test]
+    fn format_bytes_uses_binary_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    cli::ChangelogArgs,
+    git::{self, CommitSummary},
+    output::out,
+    resolver::{self, Channel, Selection},
+    utils,
+};
+
+use anyhow::Context;
+use console::Emoji;
+use tracing::info;
+
+/// Show the commits between the locked commit and what the plugin's selector resolves to
+/// now, fetched into its existing clone.
+pub(crate) fn run(args: &ChangelogArgs) -> anyhow::Result<Vec<CommitSummary>> {
+    let (lock_file, _) = utils::load_lock_file()?;
+    let plugin = lock_file.find_plugin(&args.plugin)?;
+    if git::is_local_source(&plugin.source) {
+        anyhow::bail!(
+            "{} is a local plugin; pez does not track its commits",
+            plugin.repo
+        );
+    }
+
+    let (selection, channel) = selection_for(&plugin.repo);
+    let clone = utils::load_pez_data_dir()?.join(plugin.repo.as_str());
+    let repo = git2::Repository::open(&clone).with_context(|| {
+        format!(
+            "No clone of {} at {}; run `pez install {}` first",
+            plugin.repo,
+            clone.display(),
+            plugin.repo
+        )
+    })?;
+    git::fetch_all(&repo)?;
+    let latest = git::resolve_selection(&repo, &selection, channel)
+        .with_context(|| format!("failed to resolve {}", plugin.repo))?;
+    let commits = git::commits_between(&repo, &plugin.commit_sha, &latest).with_context(|| {
+        format!(
+            "{} does not have the locked commit {}",
+            clone.display(),
+            plugin.commit_sha
+        )
+    })?;
+
+    if commits.is_empty() {
+        info!(
+            "{}{} is up to date at {}",
+            Emoji("🎉 ", ""),
+            plugin.repo,
+            short_sha(&plugin.commit_sha)
+        );
+    } else {
+        out!(
+            "{}",
+            render(&plugin.repo.as_str(), &plugin.commit_sha, &latest, &commits)
+        );
+    }
+    Ok(commits)
+}
+
+/// The selector from the plugin's pez.toml entry; the remote default branch otherwise.
+fn selection_for(repo: &crate::models::PluginRepo) -> (Selection, Channel) {
+    let resolved = utils::load_config().ok().and_then(|(config, _)| {
+        config
+            .find_plugin_spec(repo)
+            .and_then(|spec| spec.to_resolved().ok())
+    });
+    match resolved {
+        Some(resolved) => (
+            resolver::selection_from_ref_kind(&resolved.ref_kind),
+            resolved.channel,
+        ),
+        None => (Selection::DefaultHead, Channel::Stable),
+    }
+}
+
+/// `repo locked -> latest (N commit(s))`, then one `sha date author: subject` line per
+/// commit, newest first.
+fn render(repo: &str, locked: &str, latest: &str, commits: &[CommitSummary]) -> String {
+    let mut out = format!(
+        "{repo} {} -> {} ({} commit(s))\n",
+        short_sha(locked),
+        short_sha(latest),
+        commits.len()
+    );
+    for commit in commits {
+        out.push_str(&format!(
+            "    {} {} {}: {}\n",
+            short_sha(&commit.sha),
+            format_date(commit.time),
+            commit.author,
+            commit.summary
+        ));
+    }
+    out
+}
+
+fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+/// `YYYY-MM-DD` (UTC) for a Unix timestamp.
+fn format_date(secs: i64) -> String {
+    // Civil-from-days conversion for the proleptic Gregorian calendar.
+    let days = secs.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_lists_commits_with_date_and_author() {
+        let temp = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(temp.path()).unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let mut parent: Option<git2::Oid> = None;
+        let mut commit = |author: &str, time: i64, message: &str| {
+            let sig =
+                git2::Signature::new(author, "dev@example.com", &git2::Time::new(time, 0)).unwrap();
+            let parents: Vec<git2::Commit> = parent
+                .iter()
+                .map(|id| repo.find_commit(*id).unwrap())
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            let id = repo
+                .commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+                .unwrap();
+            parent = Some(id);
+            id.to_string()
+        };
+        let locked = commit("Ann", 1_700_000_000, "init");
+        commit("Bob", 1_760_000_000, "Fix prompt\n\nLonger body");
+        let latest = commit("Cy", 1_767_225_600, "Add completions");
+
+        let commits = git::commits_between(&repo, &locked, &latest).unwrap();
+        let rendered = render("owner/repo", &locked, &latest, &commits);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!(
+                "owner/repo {} -> {} (2 commit(s))",
+                short_sha(&locked),
+                short_sha(&latest)
+            )
+        );
+        assert_eq!(
+            lines[1],
+            format!("    {} 2026-01-01 Cy: Add completions", short_sha(&latest))
+        );
+        assert!(
+            lines[2].ends_with(" 2025-10-09 Bob: Fix prompt"),
+            "{}",
+            lines[2]
+        );
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{
+    config,
+    json_schema::{self, InstancePath, PathSegment},
+    utils,
+};
+
+use console::Emoji;
+use std::{collections::HashMap, fs, ops::Range};
+use tracing::{error, info};
+
+const CONFIG_SCHEMA: &str = include_str!("../../config.schema.json");
+
+/// Validate pez.toml against the bundled JSON schema, then against the checks pez runs
+/// when loading it. Each problem is reported with the line and column it starts at.
+pub(crate) fn run() -> anyhow::Result<()> {
+    let config_path = utils::load_pez_config_dir()?.join("pez.toml");
+    if !config_path.exists() {
+        anyhow::bail!("{} not found; run `pez init` first", config_path.display());
+    }
+    let content = fs::read_to_string(&config_path)?;
+    let problems = check(&content)?;
+    if problems.is_empty() {
+        info!("{}{} is valid", Emoji("🎉 ", ""), config_path.display());
+        return Ok(());
+    }
+    for problem in &problems {
+        error!(
+            "{} {}:{}",
+            utils::label_error(),
+            config_path.display(),
+            problem
+        );
+    }
+    anyhow::bail!(
+        "{} problem(s) found in {}",
+        problems.len(),
+        config_path.display()
+    );
+}
+
+/// `line:col: path: message`, or `line:col: message` for problems with no path.
+fn check(content: &str) -> anyhow::Result<Vec<String>> {
+    let spans = match toml::de::DeTable::parse(content) {
+        Ok(table) => {
+            let mut spans = HashMap::new();
+            collect_spans(table.get_ref(), &mut Vec::new(), &mut spans);
+            spans
+        }
+        Err(e) => {
+            let at = e.span().map(|span| span.start).unwrap_or_default();
+            return Ok(vec![format!(
+                "{}: {}",
+                line_col(content, at),
+                e.message().trim_end()
+            )]);
+        }
+    };
+    let schema: serde_json::Value = serde_json::from_str(CONFIG_SCHEMA)?;
+    let instance: serde_json::Value = toml::from_str(content)?;
+    let mut problems: Vec<String> = json_schema::validate(&schema, &instance)
+        .into_iter()
+        .map(|e| {
+            let at = span_for(&e.path, &spans).map_or(0, |span| span.start);
+            if e.path.0.is_empty() {
+                format!("{}: {}", line_col(content, at), e.message)
+            } else {
+                format!("{}: {}: {}", line_col(content, at), e.path, e.message)
+            }
+        })
+        .collect();
+    // Duplicate names and aliases are outside what the schema can express.
+    if problems.is_empty()
+        && let Err(e) = config::parse_config(content)
+    {
+        problems.push(format!("{}: {e:#}", line_col(content, 0)));
+    }
+    Ok(problems)
+}
+
+/// Key spans for table entries and value spans for array items, by document path.
+fn collect_spans(
+    table: &toml::de::DeTable<'_>,
+    path: &mut Vec<PathSegment>,
+    spans: &mut HashMap<Vec<PathSegment>, Range<usize>>,
+) {
+    for (key, value) in table {
+        path.push(PathSegment::Key(key.get_ref().to_string()));
+        spans.insert(path.clone(), key.span());
+        collect_value_spans(value.get_ref(), path, spans);
+        path.pop();
+    }
+}
+
+fn collect_value_spans(
+    value: &toml::de::DeValue<'_>,
+    path: &mut Vec<PathSegment>,
+    spans: &mut HashMap<Vec<PathSegment>, Range<usize>>,
+) {
+    match value {
+        toml::de::DeValue::Table(table) => collect_spans(table, path, spans),
+        toml::de::DeValue::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                spans.insert(path.clone(), item.span());
+                collect_value_spans(item.get_ref(), path, spans);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The span of the deepest part of `path` present in the document.
+fn span_for(
+    path: &InstancePath,
+    spans: &HashMap<Vec<PathSegment>, Range<usize>>,
+) -> Option<Range<usize>> {
+    (1..=path.0.len())
+        .rev()
+        .find_map(|len| spans.get(&path.0[..len]).cloned())
+}
+
+/// 1-based `line:col` of a byte offset.
+fn line_col(content: &str, offset: usize) -> String {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |last| last.chars().count())
+        + 1;
+    format!("{line}:{col}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_points_at_the_offending_plugin() {
+        let content = r#"plugins = [
+  { repo = "owner/ok" },
+  { path = "/tmp/local", tag = "v1" },
+  { repo = "owner/both", tag = "v1", branch = "main" },
+  { repo = "owner/typo", brnch = "main" },
+]
+"#;
+        assert_eq!(
+            check(content).unwrap(),
+            vec![
+                "3:3: plugins[1]: `tag` is not allowed with `path`",
+                "4:3: plugins[2]: both `branch` and `tag` set; use only one",
+                "5:26: plugins[3].brnch: unknown key `brnch`",
+            ]
+        );
+        assert!(
+            check("plugins = [{ repo = \"owner/ok\" }]\n")
+                .unwrap()
+                .is_empty()
+        );
+        let syntax = check("plugins = [\n  { repo = }\n]\n").unwrap();
+        assert!(syntax[0].starts_with("2:"), "{syntax:?}");
+    }
+}
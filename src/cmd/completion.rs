@@ -1,6 +1,15 @@
-use crate::cli;
+use crate::{
+    cli,
+    cmd::{activate, init},
+    output, utils,
+};
 use clap::CommandFactory;
-use std::io::{self, Write};
+use console::Emoji;
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+use tracing::{error, info};
 
 const FISH_DYNAMIC_COMPLETIONS: &str = r#"
 # Dynamic completions for installed plugins
@@ -13,12 +22,11 @@ complete -c pez -n '__fish_seen_subcommand_from uninstall upgrade' -f -a '(__pez
 
 pub(crate) fn generate_fish_completion() -> anyhow::Result<Vec<u8>> {
     let buffer = build_fish_completion();
-    let mut stdout = io::stdout();
-    stdout.write_all(&buffer)?;
+    output::Stdout.write_all(&buffer)?;
     Ok(buffer)
 }
 
-fn build_fish_completion() -> Vec<u8> {
+pub(crate) fn build_fish_completion() -> Vec<u8> {
     let mut cmd = cli::Cli::command();
     let mut buffer = Vec::new();
     clap_complete::generate(clap_complete::aot::Fish, &mut cmd, "pez", &mut buffer);
@@ -34,6 +42,83 @@ fn append_dynamic_completions(mut buffer: Vec<u8>) -> Vec<u8> {
     buffer
 }
 
+/// Syntax-check the generated activation and completion scripts with the local
+/// `fish --no-execute`, so incompatibilities surface before users source them.
+pub(crate) fn self_test() -> anyhow::Result<()> {
+    self_test_with("fish")
+}
+
+fn self_test_with(fish: &str) -> anyhow::Result<()> {
+    let version = fish_version(fish)?;
+    info!(
+        "{}Checking generated scripts with {}",
+        Emoji("🔍 ", ""),
+        version
+    );
+
+    let scripts = [
+        ("activate fish", activate::fish_script().into_bytes()),
+        ("completions fish", build_fish_completion()),
+        (
+            "init --write-bootstrap",
+            init::bootstrap_script().into_bytes(),
+        ),
+    ];
+    let mut failures = 0;
+    for (name, script) in scripts {
+        match check_fish_syntax(fish, &script)? {
+            None => info!("{}{}", Emoji("✅ ", ""), name),
+            Some(message) => {
+                failures += 1;
+                error!(
+                    "{} {} {} does not parse:",
+                    Emoji("❌ ", ""),
+                    utils::label_error(),
+                    name
+                );
+                for line in message.lines() {
+                    error!("   {}", line);
+                }
+            }
+        }
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{failures} generated script(s) failed fish syntax check ({version})");
+    }
+    Ok(())
+}
+
+fn fish_version(fish: &str) -> anyhow::Result<String> {
+    let output = Command::new(fish)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| anyhow::anyhow!("failed to run `{fish} --version`: {e}"))?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Returns fish's diagnostics when `script` fails to parse.
+fn check_fish_syntax(fish: &str, script: &[u8]) -> anyhow::Result<Option<String>> {
+    let mut child = Command::new(fish)
+        .arg("--no-execute")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("failed to run `{fish} --no-execute`: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(script)?;
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&output.stderr).trim().to_string(),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -54,6 +139,19 @@ mod tests {
         assert!(output.contains("__pez_installed_plugins"));
     }
 
+    #[test]
+    fn build_fish_completion_offers_every_files_dir() {
+        let buffer = build_fish_completion();
+        let output = String::from_utf8_lossy(&buffer);
+        for dir in ["conf.d", "functions", "completions", "themes", "all"] {
+            assert!(
+                output.contains(&format!("{dir}\\t'Files ")),
+                "missing --dir value {dir}"
+            );
+        }
+        assert!(output.contains("subcommand files\" -l missing"));
+    }
+
     #[test]
     fn append_dynamic_completions_inserts_single_newline() {
         let buffer = append_dynamic_completions(b"static".to_vec());
@@ -73,4 +171,41 @@ mod tests {
         let output = String::from_utf8_lossy(&buffer);
         assert!(output.starts_with("static\n# Dynamic completions"));
     }
+
+    fn fake_fish(dir: &std::path::Path, reject: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("fish");
+        let script = format!(
+            "#!/bin/sh\nif [ \"$1\" = --version ]; then echo 'fish, version 3.7.1'; exit 0; fi\n\
+             if grep -q '{reject}'; then echo 'parse error near {reject}' >&2; exit 127; fi\n"
+        );
+        std::fs::write(&path, script).unwrap();
+        let mut perms = std::fs::metadata(&path).unwrap().permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(&path, perms).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn self_test_passes_when_scripts_parse() {
+        let temp = tempfile::tempdir().unwrap();
+        let fish = fake_fish(temp.path(), "no-such-token-anywhere");
+        self_test_with(&fish).expect("scripts should pass");
+    }
+
+    #[test]
+    fn self_test_reports_parse_failures() {
+        let temp = tempfile::tempdir().unwrap();
+        let fish = fake_fish(temp.path(), "__pez_installed_plugins");
+        let err = self_test_with(&fish).unwrap_err();
+        // The bootstrap embeds the completion script, so both fail.
+        assert!(err.to_string().contains("2 generated script(s)"), "{err}");
+        assert!(err.to_string().contains("fish, version 3.7.1"), "{err}");
+    }
+
+    #[test]
+    fn self_test_errors_without_fish() {
+        let err = self_test_with("/nonexistent/fish").unwrap_err();
+        assert!(err.to_string().contains("--version"), "{err}");
+    }
 }
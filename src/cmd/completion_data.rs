@@ -0,0 +1,235 @@
+use crate::{
+    cli, git,
+    lock_file::Plugin,
+    output::{self, outln},
+    runtime::RuntimeOptions,
+    utils,
+};
+
+use clap::CommandFactory;
+use serde_derive::Serialize;
+use std::path;
+
+/// Bumped when fields are renamed or removed, so external pickers can detect it.
+const COMPLETION_DATA_VERSION: u32 = 1;
+
+#[derive(Serialize, Debug)]
+pub(crate) struct CompletionData {
+    pub(crate) version: u32,
+    pub(crate) pez_version: &'static str,
+    pub(crate) cli: CommandData,
+    pub(crate) plugins: Vec<PluginData>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct CommandData {
+    pub(crate) name: String,
+    pub(crate) about: Option<String>,
+    pub(crate) aliases: Vec<String>,
+    pub(crate) args: Vec<ArgData>,
+    pub(crate) subcommands: Vec<CommandData>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ArgData {
+    pub(crate) id: String,
+    pub(crate) long: Option<String>,
+    pub(crate) short: Option<String>,
+    pub(crate) help: Option<String>,
+    pub(crate) positional: bool,
+    pub(crate) takes_value: bool,
+    pub(crate) required: bool,
+    pub(crate) global: bool,
+    pub(crate) value_names: Vec<String>,
+    /// Accepted values for enum-typed arguments, e.g. `json`, `yaml`.
+    pub(crate) possible_values: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct PluginData {
+    pub(crate) name: String,
+    pub(crate) repo: String,
+    pub(crate) commit: String,
+    /// Tags and remote branches already in the local clone; never fetched.
+    pub(crate) tags: Vec<String>,
+    pub(crate) branches: Vec<String>,
+}
+
+/// Print the CLI surface and installed plugins as one JSON document for external
+/// pickers. Reads only local state.
+pub(crate) fn run(options: &RuntimeOptions) -> anyhow::Result<CompletionData> {
+    let plugins = match utils::load_lock_file() {
+        Ok((lock_file, _)) => {
+            let data_dir = utils::load_pez_data_dir()?;
+            lock_file
+                .plugins
+                .iter()
+                .map(|plugin| plugin_data(plugin, &data_dir))
+                .collect()
+        }
+        Err(_) => vec![],
+    };
+    let mut command = cli::Cli::command();
+    command.build();
+    let data = CompletionData {
+        version: COMPLETION_DATA_VERSION,
+        pez_version: env!("CARGO_PKG_VERSION"),
+        cli: command_data(&command),
+        plugins,
+    };
+    outln!("{}", output::to_json(&data, options.json_style)?);
+    Ok(data)
+}
+
+fn command_data(command: &clap::Command) -> CommandData {
+    CommandData {
+        name: command.get_name().to_string(),
+        about: command.get_about().map(ToString::to_string),
+        aliases: command.get_all_aliases().map(str::to_string).collect(),
+        args: command
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set())
+            .map(arg_data)
+            .collect(),
+        subcommands: command
+            .get_subcommands()
+            .filter(|sub| !sub.is_hide_set())
+            .map(command_data)
+            .collect(),
+    }
+}
+
+fn arg_data(arg: &clap::Arg) -> ArgData {
+    ArgData {
+        id: arg.get_id().to_string(),
+        long: arg.get_long().map(str::to_string),
+        short: arg.get_short().map(String::from),
+        help: arg.get_help().map(ToString::to_string),
+        positional: arg.is_positional(),
+        takes_value: arg.get_action().takes_values(),
+        required: arg.is_required_set(),
+        global: arg.is_global_set(),
+        value_names: arg
+            .get_value_names()
+            .unwrap_or_default()
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        possible_values: arg
+            .get_possible_values()
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect(),
+    }
+}
+
+fn plugin_data(plugin: &Plugin, data_dir: &path::Path) -> PluginData {
+    let repo = (!git::is_local_source(&plugin.source))
+        .then(|| git2::Repository::open(data_dir.join(plugin.repo.as_str())).ok())
+        .flatten();
+    let tags = repo
+        .as_ref()
+        .and_then(|repo| repo.tag_names(None).ok())
+        .map(|names| names.iter().flatten().map(str::to_string).collect())
+        .unwrap_or_default();
+    let branches = repo
+        .as_ref()
+        .and_then(|repo| remote_branches(repo).ok())
+        .unwrap_or_default();
+    PluginData {
+        name: plugin.get_name(),
+        repo: plugin.repo.as_str(),
+        commit: plugin.commit_sha.clone(),
+        tags,
+        branches,
+    }
+}
+
+fn remote_branches(repo: &git2::Repository) -> anyhow::Result<Vec<String>> {
+    let mut branches = Vec::new();
+    for reference in repo.references_glob("refs/remotes/origin/*")? {
+        if let Some(name) = reference?
+            .name()
+            .and_then(|name| name.strip_prefix("refs/remotes/origin/"))
+            && name != "HEAD"
+        {
+            branches.push(name.to_string());
+        }
+    }
+    branches.sort();
+    Ok(branches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_data_covers_subcommands_flags_and_value_enums() {
+        let mut command = cli::Cli::command();
+        command.build();
+        let data = command_data(&command);
+
+        let color = data.args.iter().find(|a| a.id == "color").unwrap();
+        assert!(color.global);
+        assert_eq!(color.possible_values, vec!["auto", "always", "never"]);
+
+        let list = data.subcommands.iter().find(|c| c.name == "list").unwrap();
+        let format = list.args.iter().find(|a| a.id == "format").unwrap();
+        assert_eq!(format.long.as_deref(), Some("format"));
+        assert!(format.takes_value);
+        assert!(format.possible_values.contains(&"json".to_string()));
+
+        let install = data
+            .subcommands
+            .iter()
+            .find(|c| c.name == "install")
+            .unwrap();
+        assert!(install.args.iter().any(|a| a.positional));
+        let completions = data
+            .subcommands
+            .iter()
+            .find(|c| c.name == "completions")
+            .unwrap();
+        assert!(completions.aliases.contains(&"completion".to_string()));
+    }
+
+    #[test]
+    fn plugin_data_reads_refs_from_the_clone() {
+        let temp = tempfile::tempdir().unwrap();
+        let clone = temp.path().join("owner/refs");
+        let repo = git2::Repository::init(&clone).unwrap();
+        let sig = git2::Signature::now("pez", "pez@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let head = repo
+            .commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+        repo.tag_lightweight("v1.0.0", &repo.find_object(head, None).unwrap(), false)
+            .unwrap();
+        repo.reference("refs/remotes/origin/main", head, false, "test")
+            .unwrap();
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/main",
+            false,
+            "test",
+        )
+        .unwrap();
+
+        let plugin_repo: crate::models::PluginRepo = "owner/refs".parse().unwrap();
+        let plugin = Plugin {
+            name: "refs".into(),
+            source: plugin_repo.default_remote_source(),
+            repo: plugin_repo,
+            commit_sha: head.to_string(),
+            files: vec![],
+            ..Default::default()
+        };
+        let data = plugin_data(&plugin, temp.path());
+        assert_eq!(data.tags, vec!["v1.0.0"]);
+        assert_eq!(data.branches, vec!["main"]);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{
+    cli::{DiffArgs, DiffFormat},
+    config::Config,
+    git,
+    lock_file::{LockFile, Plugin},
+    output::{self, out, outln},
+    resolver::{Channel, RefKind},
+    runtime::RuntimeOptions,
+    utils,
+};
+
+use console::style;
+use serde_derive::Serialize;
+use std::path;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct SelectorMismatch {
+    pub(crate) repo: String,
+    /// The selector in pez.toml, e.g. `tag:v2.0.0`.
+    pub(crate) selector: String,
+    pub(crate) locked: String,
+}
+
+/// How pez.toml and pez-lock.toml disagree.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub(crate) struct ConfigDiff {
+    /// Declared in pez.toml but not in pez-lock.toml.
+    pub(crate) only_in_config: Vec<String>,
+    /// Recorded in pez-lock.toml but no longer declared in pez.toml.
+    pub(crate) only_in_lock: Vec<String>,
+    /// Locked at a commit the pez.toml selector does not select.
+    pub(crate) mismatched: Vec<SelectorMismatch>,
+    /// Selectors that could not be checked because the clone is missing.
+    pub(crate) unchecked: Vec<String>,
+}
+
+impl ConfigDiff {
+    fn is_empty(&self) -> bool {
+        self.only_in_config.is_empty() && self.only_in_lock.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+/// Compare pez.toml with pez-lock.toml without touching the network or any file.
+pub(crate) fn run(args: &DiffArgs, options: &RuntimeOptions) -> anyhow::Result<ConfigDiff> {
+    let (config, _) = utils::load_config()?;
+    let lock_file = utils::load_lock_file()
+        .map(|(lock, _)| lock)
+        .unwrap_or_else(|_| crate::lock_file::init());
+    let data_dir = utils::load_pez_data_dir()?;
+    let diff = diff(&config, &lock_file, &data_dir)?;
+    match args.format {
+        Some(DiffFormat::Json) => outln!("{}", output::to_json(&diff, options.json_style)?),
+        None => out!("{}", render_plain(&diff)),
+    }
+    Ok(diff)
+}
+
+fn diff(
+    config: &Config,
+    lock_file: &LockFile,
+    data_dir: &path::Path,
+) -> anyhow::Result<ConfigDiff> {
+    let mut diff = ConfigDiff::default();
+    let specs = config.plugins.as_deref().unwrap_or_default();
+    let mut declared = Vec::new();
+    for spec in specs {
+        let resolved = spec.to_resolved()?;
+        declared.push(resolved.plugin_repo.clone());
+        let Some(locked) = lock_file.get_plugin_by_repo(&resolved.plugin_repo) else {
+            diff.only_in_config.push(resolved.plugin_repo.as_str());
+            continue;
+        };
+        let Some(selector) = selector_label(&resolved.ref_kind) else {
+            continue;
+        };
+        match selects(&resolved.ref_kind, resolved.channel, locked, data_dir) {
+            Some(true) => {}
+            Some(false) => diff.mismatched.push(SelectorMismatch {
+                repo: locked.repo.as_str(),
+                selector,
+                locked: locked.commit_sha.clone(),
+            }),
+            None => diff.unchecked.push(locked.repo.as_str()),
+        }
+    }
+    diff.only_in_lock = lock_file
+        .plugins
+        .iter()
+        .filter(|plugin| !declared.contains(&plugin.repo))
+        .map(|plugin| plugin.repo.as_str())
+        .collect();
+    Ok(diff)
+}
+
+/// `None` for selectors that follow the default branch and so match any commit.
+fn selector_label(kind: &RefKind) -> Option<String> {
+    match kind {
+        RefKind::None | RefKind::Latest => None,
+        RefKind::Version(v) if v == "latest" => None,
+        RefKind::Version(v) => Some(format!("version:{v}")),
+        RefKind::Tag(t) => Some(format!("tag:{t}")),
+        RefKind::Branch(b) => Some(format!("branch:{b}")),
+        RefKind::Commit(c) => Some(format!("commit:{c}")),
+    }
+}
+
+/// Whether the selector picks the locked commit, judged from the refs already in the
+/// clone. A branch matches while the locked commit is on it. `None` without a clone.
+fn selects(
+    kind: &RefKind,
+    channel: Channel,
+    locked: &Plugin,
+    data_dir: &path::Path,
+) -> Option<bool> {
+    if let RefKind::Commit(sha) = kind {
+        return Some(
+            locked
+                .commit_sha
+                .to_ascii_lowercase()
+                .starts_with(&sha.to_ascii_lowercase()),
+        );
+    }
+    if git::is_local_source(&locked.source) {
+        return Some(true);
+    }
+    let repo = git2::Repository::open(data_dir.join(locked.repo.as_str())).ok()?;
+    let oid = git2::Oid::from_str(&locked.commit_sha).ok()?;
+    let on_branch = |branch: &str| -> Option<bool> {
+        let tip = repo
+            .find_reference(&format!("refs/remotes/origin/{branch}"))
+            .ok()?
+            .target()?;
+        Some(tip == oid || repo.graph_descendant_of(tip, oid).unwrap_or(false))
+    };
+    let tags = git::tags_at_commit(&repo, &locked.commit_sha).unwrap_or_default();
+    match kind {
+        RefKind::Tag(tag) => Some(tags.contains(tag)),
+        RefKind::Branch(branch) => Some(on_branch(branch).unwrap_or(false)),
+        // Like resolution, a version naming a branch follows that branch.
+        RefKind::Version(v) => on_branch(v).or_else(|| {
+            Some(
+                git::pick_tag_for_version(&tags, v, channel)
+                    .ok()
+                    .flatten()
+                    .is_some(),
+            )
+        }),
+        _ => Some(true),
+    }
+}
+
+fn render_plain(diff: &ConfigDiff) -> String {
+    if diff.is_empty() && diff.unchecked.is_empty() {
+        return "pez.toml and pez-lock.toml agree.\n".to_string();
+    }
+    let mut out = String::new();
+    let mut section = |title: String, items: Vec<String>, fix: &str| {
+        if items.is_empty() {
+            return;
+        }
+        out.push_str(&format!("{title} ({}; fix with `{fix}`):\n", items.len()));
+        for item in items {
+            out.push_str(&format!("  {item}\n"));
+        }
+    };
+    section(
+        style("Only in pez.toml").green().bold().to_string(),
+        diff.only_in_config.clone(),
+        "pez install",
+    );
+    section(
+        style("Only in pez-lock.toml").red().bold().to_string(),
+        diff.only_in_lock.clone(),
+        "pez prune",
+    );
+    section(
+        style("Selector does not match the lock")
+            .yellow()
+            .bold()
+            .to_string(),
+        diff.mismatched
+            .iter()
+            .map(|m| {
+                format!(
+                    "{} ({} locked at {})",
+                    m.repo,
+                    m.selector,
+                    short_sha(&m.locked)
+                )
+            })
+            .collect(),
+        "pez upgrade",
+    );
+    if !diff.unchecked.is_empty() {
+        out.push_str(&format!(
+            "Selectors not checked for {} plugin(s) without a clone: {}\n",
+            diff.unchecked.len(),
+            diff.unchecked.join(", ")
+        ));
+    }
+    out
+}
+
+fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{PluginSource, PluginSpec},
+        models::PluginRepo,
+    };
+    use std::fs;
+
+    fn spec(repo: &str, source: impl FnOnce(PluginRepo) -> PluginSource) -> PluginSpec {
+        PluginSpec {
+            name: None,
+            prerelease: false,
+            disabled: false,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
+            source: source(repo.parse().unwrap()),
+        }
+    }
+
+    fn locked(repo: &str, commit: &str) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: commit.into(),
+            files: vec![],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn diff_reports_missing_entries_and_selector_mismatches() {
+        let temp = tempfile::tempdir().unwrap();
+        let clone = temp.path().join("owner/tagged");
+        fs::create_dir_all(&clone).unwrap();
+        let repo = git2::Repository::init(&clone).unwrap();
+        let sig = git2::Signature::now("pez", "pez@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let head = repo
+            .commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+        repo.tag_lightweight("v1.0.0", &repo.find_object(head, None).unwrap(), false)
+            .unwrap();
+
+        let config = Config {
+            plugins: Some(vec![
+                spec("owner/new", |repo| PluginSource::Repo {
+                    repo,
+                    version: None,
+                    branch: None,
+                    tag: None,
+                    commit: None,
+                }),
+                spec("owner/pinned", |repo| PluginSource::Repo {
+                    repo,
+                    version: None,
+                    branch: None,
+                    tag: None,
+                    commit: Some("abc1234".into()),
+                }),
+                spec("owner/tagged", |repo| PluginSource::Repo {
+                    repo,
+                    version: None,
+                    branch: None,
+                    tag: Some("v2.0.0".into()),
+                    commit: None,
+                }),
+                spec("owner/ranged", |repo| PluginSource::Repo {
+                    repo,
+                    version: Some("1".into()),
+                    branch: None,
+                    tag: None,
+                    commit: None,
+                }),
+            ]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        };
+        let lock_file = LockFile {
+            version: 2,
+            plugins: vec![
+                locked("owner/pinned", "def5678900000000000000000000000000000000"),
+                locked("owner/tagged", &head.to_string()),
+                locked("owner/ranged", "0123456789abcdef0123456789abcdef01234567"),
+                locked("owner/orphan", "0123456789abcdef0123456789abcdef01234567"),
+            ],
+        };
+
+        let diff = diff(&config, &lock_file, temp.path()).unwrap();
+        assert_eq!(diff.only_in_config, vec!["owner/new"]);
+        assert_eq!(diff.only_in_lock, vec!["owner/orphan"]);
+        let mismatched: Vec<_> = diff
+            .mismatched
+            .iter()
+            .map(|m| format!("{} {}", m.repo, m.selector))
+            .collect();
+        assert_eq!(
+            mismatched,
+            vec!["owner/pinned commit:abc1234", "owner/tagged tag:v2.0.0"]
+        );
+        assert_eq!(diff.unchecked, vec!["owner/ranged"]);
+        assert!(render_plain(&diff).contains("owner/tagged (tag:v2.0.0 locked at "));
+    }
+}
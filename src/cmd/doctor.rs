@@ -1,27 +1,127 @@
-use crate::{cli, lock_file::LockFile, models::TargetDir, utils};
+use crate::{
+    autoload, cli,
+    cmd::{repair, rollback, verify_activate},
+    duplicates::{self, Duplicate},
+    git,
+    lock_file::{LockFile, Plugin},
+    models::{PluginRepo, TargetDir},
+    output::{self, outln},
+    runtime::RuntimeOptions,
+    update_cache, utils,
+    vendor::{self, VendorDirs},
+};
+use console::Emoji;
 use serde_derive::Serialize;
-use serde_json::json;
-use std::{collections::HashSet, fs, path};
+use std::{collections::BTreeMap, fs, path};
 use tracing::{info, warn};
 
+/// Every check name, for validating `[doctor] ignore`.
+const CHECK_NAMES: &[&str] = &[
+    "config",
+    "lock_file",
+    "fish_config_dir",
+    "pez_data_dir",
+    "repos",
+    "target_files",
+    "duplicates",
+    "activate_configured",
+    "event_hook_readiness",
+    "activate_version",
+    "install_layout",
+    "theme_assets",
+    "function_shadowing",
+    "autoload_names",
+    "vendor_conflicts",
+];
+
+/// Exit code when any check reports an error.
+const EXIT_ERRORS: u8 = 2;
+/// Exit code under `--strict` when the worst finding is a warning.
+const EXIT_WARNINGS: u8 = 1;
+
+/// Version of the `--format json|yaml` report layout. Bump it when a field is renamed
+/// or removed or its meaning changes; adding fields or check ids does not.
+const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    Ok,
+    Warn,
+    Error,
+}
+
+/// The structured report: the checks plus enough metadata for dashboards to tell
+/// which layout and which run they are reading.
+#[derive(Serialize)]
+struct DoctorReport<'a> {
+    schema_version: u32,
+    /// Seconds since the Unix epoch.
+    generated_at: u64,
+    checks: &'a [DoctorCheck],
+}
+
 #[derive(Serialize)]
 pub(crate) struct DoctorCheck {
+    /// One of `CHECK_NAMES`; stable across releases.
+    #[serde(rename = "id")]
     name: &'static str,
-    status: &'static str, // ok | warn | error
+    #[serde(rename = "severity")]
+    status: Severity,
     details: String,
 }
 
-pub(crate) fn run(args: &cli::DoctorArgs) -> anyhow::Result<Vec<DoctorCheck>> {
-    let checks = collect_checks()?;
+/// Run the checks not ignored in `[doctor]`. Errors fail the command with exit code 2;
+/// with `--strict`, warnings fail it with exit code 1.
+pub(crate) fn run(
+    args: &cli::DoctorArgs,
+    options: &RuntimeOptions,
+) -> anyhow::Result<Vec<DoctorCheck>> {
+    let ignore = utils::load_config()
+        .map(|(config, _)| config.doctor.ignore)
+        .unwrap_or_default();
+    for name in ignore
+        .iter()
+        .filter(|name| !CHECK_NAMES.contains(&name.as_str()))
+    {
+        warn!(
+            "{} {} Unknown check `{}` in [doctor] ignore; known checks: {}",
+            Emoji("⚠ ", ""),
+            utils::label_warning(),
+            name,
+            CHECK_NAMES.join(", ")
+        );
+    }
+    if args.fix {
+        let fixed = fix()?;
+        if fixed.is_empty() {
+            info!("{}Nothing to fix.", Emoji("🎉 ", ""));
+        } else {
+            info!("{}Fixed {} finding(s):", Emoji("🔧 ", ""), fixed.len());
+            for line in &fixed {
+                info!("  - {line}");
+            }
+        }
+    }
+    let mut checks = collect_checks()?;
+    checks.retain(|check| !ignore.iter().any(|name| name == check.name));
 
     match args.format {
         Some(cli::DoctorFormat::Json) => {
-            println!("{}", serde_json::to_string_pretty(&json!(checks))?);
+            outln!("{}", output::to_json(&report(&checks), options.json_style)?);
+        }
+        Some(cli::DoctorFormat::Yaml) => {
+            outln!("{}", output::to_yaml(&report(&checks))?);
+        }
+        Some(cli::DoctorFormat::Github) => {
+            for line in render_github_annotations(&checks) {
+                outln!("{line}");
+            }
         }
         None => {
             info!("pez doctor checks:");
             for line in render_plain_lines(&checks) {
-                println!("{line}");
+                outln!("{line}");
             }
             if has_error(&checks) {
                 warn!("Errors detected. Please resolve the above items.");
@@ -29,21 +129,160 @@ pub(crate) fn run(args: &cli::DoctorArgs) -> anyhow::Result<Vec<DoctorCheck>> {
         }
     }
 
+    if let Some(exit) = exit_error(&checks, args.strict) {
+        return Err(exit.into());
+    }
     Ok(checks)
 }
 
+fn report(checks: &[DoctorCheck]) -> DoctorReport<'_> {
+    DoctorReport {
+        schema_version: SCHEMA_VERSION,
+        generated_at: update_cache::now_secs(),
+        checks,
+    }
+}
+
+fn exit_error(checks: &[DoctorCheck], strict: bool) -> Option<utils::ExitError> {
+    let count = |status: Severity| checks.iter().filter(|c| c.status == status).count();
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warn));
+    if errors > 0 {
+        Some(utils::ExitError {
+            code: EXIT_ERRORS,
+            message: format!("doctor found {errors} error(s)"),
+        })
+    } else if strict && warnings > 0 {
+        Some(utils::ExitError {
+            code: EXIT_WARNINGS,
+            message: format!("doctor found {warnings} warning(s) (--strict)"),
+        })
+    } else {
+        None
+    }
+}
+
+/// What `--fix` can remediate without guessing: clone missing repos again, re-copy
+/// missing files from the locked commit, keep a duplicate destination only with the
+/// plugin whose copy is on disk, and drop records of files the locked source no longer
+/// contains. Returns one line per fix.
+fn fix() -> anyhow::Result<Vec<String>> {
+    let mut fixed = Vec::new();
+    let Ok((lock_file, _)) = utils::load_lock_file() else {
+        return Ok(fixed);
+    };
+    let config = utils::load_config().ok().map(|(c, _)| c);
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let data_dir = utils::load_pez_data_dir()?;
+
+    // Disabled plugins keep their lock entry but are meant to have no files installed.
+    let enabled = lock_file.without_disabled(config.as_ref());
+    for plugin in missing_clones(&enabled, &data_dir) {
+        if git::is_local_source(&plugin.source) {
+            continue;
+        }
+        match rollback::restore_plugin(plugin, &data_dir) {
+            Ok(()) => fixed.push(format!("cloned {} again", plugin.display_label())),
+            Err(e) => warn!(
+                "{} {} Could not clone {}: {:#}",
+                Emoji("⚠ ", ""),
+                utils::label_warning(),
+                plugin.display_label(),
+                e
+            ),
+        }
+    }
+
+    // Restoring clones re-copies their files and may rewrite the lock, so reload it.
+    let (mut lock_file, lock_file_path) = utils::load_lock_file()?;
+    let enabled = lock_file.clone().without_disabled(config.as_ref());
+    let mut lock_changed = false;
+    for plugin in &enabled.plugins {
+        let missing = repair::restore_missing(plugin, &data_dir, &fish_config_dir)?;
+        for dest in &missing.restored {
+            fixed.push(format!("restored {}", dest.display()));
+        }
+        if missing.not_in_source.is_empty() {
+            continue;
+        }
+        if let Some(locked) = lock_file.plugins.iter_mut().find(|p| p.repo == plugin.repo) {
+            locked
+                .files
+                .retain(|file| !missing.not_in_source.contains(file));
+            lock_changed = true;
+        }
+        for file in &missing.not_in_source {
+            fixed.push(format!(
+                "dropped {} from the lock ({} no longer ships it)",
+                file.get_path(&fish_config_dir).display(),
+                plugin.display_label()
+            ));
+        }
+    }
+    for duplicate in duplicates::installed(&enabled, &fish_config_dir, &data_dir) {
+        let Some(owner) = duplicate_owner(&enabled, &duplicate, &fish_config_dir, &data_dir) else {
+            continue;
+        };
+        for plugin in lock_file.plugins.iter_mut().filter(|p| p.repo != owner) {
+            let before = plugin.files.len();
+            plugin
+                .files
+                .retain(|file| file.get_path(&fish_config_dir) != duplicate.path);
+            if plugin.files.len() != before {
+                fixed.push(format!(
+                    "{} now belongs to {} only; dropped it from {}",
+                    duplicate.path.display(),
+                    owner,
+                    plugin.display_label()
+                ));
+                lock_changed = true;
+            }
+        }
+    }
+    if lock_changed {
+        lock_file.save(&lock_file_path)?;
+    }
+    Ok(fixed)
+}
+
+/// The plugin whose copy of a duplicate destination matches the file on disk, i.e. the
+/// one installed last. None when no copy matches (e.g. the file was edited).
+fn duplicate_owner(
+    lock_file: &LockFile,
+    duplicate: &Duplicate,
+    fish_config_dir: &path::Path,
+    data_dir: &path::Path,
+) -> Option<PluginRepo> {
+    lock_file
+        .plugins
+        .iter()
+        .find(|plugin| {
+            plugin.files.iter().any(|file| {
+                file.get_path(fish_config_dir) == duplicate.path
+                    && utils::same_contents(
+                        &duplicates::source_path(plugin, file, data_dir),
+                        &duplicate.path,
+                    )
+            })
+        })
+        .map(|plugin| plugin.repo.clone())
+}
+
 fn collect_checks() -> anyhow::Result<Vec<DoctorCheck>> {
     let mut checks: Vec<DoctorCheck> = Vec::new();
 
+    let mut config = None;
     match utils::load_config() {
-        Ok((_cfg, path)) => checks.push(DoctorCheck {
-            name: "config",
-            status: "ok",
-            details: format!("found: {}", path.display()),
-        }),
+        Ok((cfg, path)) => {
+            config = Some(cfg);
+            checks.push(DoctorCheck {
+                name: "config",
+                status: Severity::Ok,
+                details: format!("found: {}", path.display()),
+            })
+        }
         Err(_) => checks.push(DoctorCheck {
             name: "config",
-            status: "warn",
+            status: Severity::Warn,
             details: "pez.toml not found".to_string(),
         }),
     }
@@ -54,13 +293,13 @@ fn collect_checks() -> anyhow::Result<Vec<DoctorCheck>> {
             lock = Some(l);
             checks.push(DoctorCheck {
                 name: "lock_file",
-                status: "ok",
+                status: Severity::Ok,
                 details: format!("found: {}", path.display()),
             })
         }
         Err(_) => checks.push(DoctorCheck {
             name: "lock_file",
-            status: "warn",
+            status: Severity::Warn,
             details: "pez-lock.toml not found".to_string(),
         }),
     }
@@ -69,9 +308,9 @@ fn collect_checks() -> anyhow::Result<Vec<DoctorCheck>> {
     checks.push(DoctorCheck {
         name: "fish_config_dir",
         status: if fish_config_dir.exists() {
-            "ok"
+            Severity::Ok
         } else {
-            "warn"
+            Severity::Warn
         },
         details: fish_config_dir.display().to_string(),
     });
@@ -79,32 +318,36 @@ fn collect_checks() -> anyhow::Result<Vec<DoctorCheck>> {
     let pez_data_dir = utils::load_pez_data_dir()?;
     checks.push(DoctorCheck {
         name: "pez_data_dir",
-        status: if pez_data_dir.exists() { "ok" } else { "warn" },
+        status: if pez_data_dir.exists() {
+            Severity::Ok
+        } else {
+            Severity::Warn
+        },
         details: pez_data_dir.display().to_string(),
     });
 
     // Activation is configured in the user's fish config directory, not the install target.
     let fish_runtime_config_dir = utils::load_default_fish_config_dir()?;
     let activate_check = check_activate_configured(&fish_runtime_config_dir);
-    let activation_enabled = activate_check.status == "ok";
+    let activation_enabled = activate_check.status == Severity::Ok;
     checks.push(activate_check);
     checks.push(check_event_hook_readiness(activation_enabled));
+    checks.push(check_activate_version(
+        verify_activate::activate_version_from_env().as_deref(),
+    ));
     checks.push(check_install_layout(&fish_config_dir));
 
     if let Some(lock_file) = lock {
-        let mut missing_repos = vec![];
-        for p in &lock_file.plugins {
-            let repo_path = pez_data_dir.join(p.repo.as_str());
-            if !repo_path.exists() {
-                missing_repos.push(p.repo.as_str());
-            }
-        }
+        let missing_repos: Vec<String> = missing_clones(&lock_file, &pez_data_dir)
+            .iter()
+            .map(|p| p.display_label())
+            .collect();
         checks.push(DoctorCheck {
             name: "repos",
             status: if missing_repos.is_empty() {
-                "ok"
+                Severity::Ok
             } else {
-                "warn"
+                Severity::Warn
             },
             details: if missing_repos.is_empty() {
                 "all cloned".to_string()
@@ -113,26 +356,26 @@ fn collect_checks() -> anyhow::Result<Vec<DoctorCheck>> {
             },
         });
 
-        let mut missing_files = vec![];
-        let mut dest_set: HashSet<path::PathBuf> = HashSet::new();
-        let mut duplicates = vec![];
-        for p in &lock_file.plugins {
-            for f in &p.files {
-                let dest = fish_config_dir.join(f.dir.as_str()).join(&f.name);
-                if !dest.exists() {
-                    missing_files.push(dest.display().to_string());
-                }
-                if !dest_set.insert(dest.clone()) {
-                    duplicates.push(dest.display().to_string());
-                }
-            }
-        }
+        // Clones stay for disabled plugins, but their files are meant to be absent.
+        let lock_file = lock_file.without_disabled(config.as_ref());
+        let missing_files: Vec<String> = missing_target_files(&lock_file, &fish_config_dir)
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        let duplicates: Vec<String> =
+            duplicates::installed(&lock_file, &fish_config_dir, &pez_data_dir)
+                .iter()
+                .map(|d| {
+                    let owners: Vec<&str> = d.claims.iter().map(|c| c.plugin.as_str()).collect();
+                    format!("{} ({})", d.path.display(), owners.join(", "))
+                })
+                .collect();
         checks.push(DoctorCheck {
             name: "target_files",
             status: if missing_files.is_empty() {
-                "ok"
+                Severity::Ok
             } else {
-                "warn"
+                Severity::Warn
             },
             details: if missing_files.is_empty() {
                 "all present".to_string()
@@ -142,7 +385,11 @@ fn collect_checks() -> anyhow::Result<Vec<DoctorCheck>> {
         });
         checks.push(DoctorCheck {
             name: "duplicates",
-            status: if duplicates.is_empty() { "ok" } else { "error" },
+            status: if duplicates.is_empty() {
+                Severity::Ok
+            } else {
+                Severity::Error
+            },
             details: if duplicates.is_empty() {
                 "no conflicts".to_string()
             } else {
@@ -150,17 +397,48 @@ fn collect_checks() -> anyhow::Result<Vec<DoctorCheck>> {
             },
         });
         checks.push(check_theme_assets(&lock_file, &fish_config_dir));
+        checks.push(check_function_shadowing(&lock_file, &fish_config_dir));
+        checks.push(check_autoload_names(&lock_file, &fish_config_dir));
+        if vendor::enabled() {
+            checks.push(check_vendor_conflicts(&lock_file, &VendorDirs::detect()));
+        }
     }
 
     Ok(checks)
 }
 
+/// Locked plugins whose clone is gone from the pez data directory.
+pub(crate) fn missing_clones<'a>(
+    lock_file: &'a LockFile,
+    pez_data_dir: &path::Path,
+) -> Vec<&'a Plugin> {
+    lock_file
+        .plugins
+        .iter()
+        .filter(|p| !pez_data_dir.join(p.repo.as_str()).exists())
+        .collect()
+}
+
+/// Installed file destinations recorded in the lock file that no longer exist.
+pub(crate) fn missing_target_files(
+    lock_file: &LockFile,
+    fish_config_dir: &path::Path,
+) -> Vec<path::PathBuf> {
+    lock_file
+        .plugins
+        .iter()
+        .flat_map(|p| &p.files)
+        .map(|f| f.get_path(fish_config_dir))
+        .filter(|dest| !dest.exists())
+        .collect()
+}
+
 fn check_activate_configured(fish_config_dir: &path::Path) -> DoctorCheck {
     let config_fish_path = fish_config_dir.join("config.fish");
     if !config_fish_path.exists() {
         return DoctorCheck {
             name: "activate_configured",
-            status: "warn",
+            status: Severity::Warn,
             details: format!(
                 "missing: {} (add `pez activate fish | source` for shell hooks)",
                 config_fish_path.display()
@@ -173,13 +451,13 @@ fn check_activate_configured(fish_config_dir: &path::Path) -> DoctorCheck {
             if has_activate_fish_line(&contents) {
                 DoctorCheck {
                     name: "activate_configured",
-                    status: "ok",
+                    status: Severity::Ok,
                     details: format!("found in {}", config_fish_path.display()),
                 }
             } else {
                 DoctorCheck {
                     name: "activate_configured",
-                    status: "warn",
+                    status: Severity::Warn,
                     details: format!(
                         "not found in {} (add `pez activate fish | source`)",
                         config_fish_path.display()
@@ -189,7 +467,7 @@ fn check_activate_configured(fish_config_dir: &path::Path) -> DoctorCheck {
         }
         Err(err) => DoctorCheck {
             name: "activate_configured",
-            status: "warn",
+            status: Severity::Warn,
             details: format!("failed to read {}: {err}", config_fish_path.display()),
         },
     }
@@ -206,7 +484,7 @@ fn check_event_hook_readiness(activation_enabled: bool) -> DoctorCheck {
     if activation_enabled {
         return DoctorCheck {
             name: "event_hook_readiness",
-            status: "ok",
+            status: Severity::Ok,
             details: "activate wrapper detected; conf.d events should run in the current shell"
                 .to_string(),
         };
@@ -214,11 +492,37 @@ fn check_event_hook_readiness(activation_enabled: bool) -> DoctorCheck {
 
     DoctorCheck {
         name: "event_hook_readiness",
-        status: "warn",
+        status: Severity::Warn,
         details: "activate wrapper not detected; run `pez activate fish | source`".to_string(),
     }
 }
 
+fn check_activate_version(activated: Option<&str>) -> DoctorCheck {
+    match verify_activate::evaluate(activated) {
+        verify_activate::ActivateVersionStatus::Match(version) => DoctorCheck {
+            name: "activate_version",
+            status: Severity::Ok,
+            details: format!("wrapper matches pez {version}"),
+        },
+        verify_activate::ActivateVersionStatus::Mismatch { activated } => DoctorCheck {
+            name: "activate_version",
+            status: Severity::Warn,
+            details: format!(
+                "wrapper is from pez {activated}, binary is {}; {}",
+                verify_activate::binary_version(),
+                verify_activate::reactivate_hint()
+            ),
+        },
+        verify_activate::ActivateVersionStatus::NotActivated => DoctorCheck {
+            name: "activate_version",
+            status: Severity::Ok,
+            details:
+                "skipped: not running through the activate wrapper (run `pez verify-activate`)"
+                    .to_string(),
+        },
+    }
+}
+
 fn check_install_layout(fish_config_dir: &path::Path) -> DoctorCheck {
     let mut invalid_paths = Vec::new();
     let mut missing_dirs = Vec::new();
@@ -237,7 +541,7 @@ fn check_install_layout(fish_config_dir: &path::Path) -> DoctorCheck {
     if !invalid_paths.is_empty() {
         return DoctorCheck {
             name: "install_layout",
-            status: "warn",
+            status: Severity::Warn,
             details: format!(
                 "expected directories but found non-directories: {}",
                 invalid_paths.join(", ")
@@ -248,13 +552,13 @@ fn check_install_layout(fish_config_dir: &path::Path) -> DoctorCheck {
     if missing_dirs.is_empty() {
         DoctorCheck {
             name: "install_layout",
-            status: "ok",
+            status: Severity::Ok,
             details: "target directories are present".to_string(),
         }
     } else {
         DoctorCheck {
             name: "install_layout",
-            status: "ok",
+            status: Severity::Ok,
             details: format!(
                 "ready (missing dirs will be created on install: {})",
                 missing_dirs.join(", ")
@@ -283,7 +587,7 @@ fn check_theme_assets(lock_file: &LockFile, fish_config_dir: &path::Path) -> Doc
     if tracked_theme_count == 0 {
         return DoctorCheck {
             name: "theme_assets",
-            status: "ok",
+            status: Severity::Ok,
             details: "no theme assets recorded in lock file".to_string(),
         };
     }
@@ -291,23 +595,168 @@ fn check_theme_assets(lock_file: &LockFile, fish_config_dir: &path::Path) -> Doc
     if missing.is_empty() {
         DoctorCheck {
             name: "theme_assets",
-            status: "ok",
+            status: Severity::Ok,
             details: "all theme assets are present".to_string(),
         }
     } else {
         DoctorCheck {
             name: "theme_assets",
-            status: "warn",
+            status: Severity::Warn,
             details: format!("missing: {}", missing.join(", ")),
         }
     }
 }
 
-fn status_prefix(status: &str) -> &'static str {
+/// A `function NAME` declaration found in an installed plugin file.
+struct FunctionDefinition {
+    plugin: String,
+    dir: TargetDir,
+    file: String,
+}
+
+impl FunctionDefinition {
+    fn describe(&self) -> String {
+        format!("{} ({}/{})", self.plugin, self.dir.as_str(), self.file)
+    }
+}
+
+/// Which definition fish ends up using: conf.d files are sourced at startup in
+/// file-name order, so the last one wins and autoloading never replaces it.
+/// Otherwise the autoload file named after the function wins.
+fn winning_definition<'a>(
+    name: &str,
+    definitions: &'a [FunctionDefinition],
+) -> Option<&'a FunctionDefinition> {
+    definitions
+        .iter()
+        .filter(|d| d.dir == TargetDir::ConfD)
+        .max_by(|a, b| a.file.cmp(&b.file))
+        .or_else(|| {
+            definitions
+                .iter()
+                .find(|d| d.dir == TargetDir::Functions && d.file == format!("{name}.fish"))
+        })
+}
+
+fn check_function_shadowing(lock_file: &LockFile, fish_config_dir: &path::Path) -> DoctorCheck {
+    let mut definitions: BTreeMap<String, Vec<FunctionDefinition>> = BTreeMap::new();
+    for plugin in &lock_file.plugins {
+        for file in &plugin.files {
+            if file.dir == TargetDir::Themes {
+                continue;
+            }
+            let Ok(contents) = fs::read_to_string(file.get_path(fish_config_dir)) else {
+                continue;
+            };
+            for name in autoload::declared_functions(&contents) {
+                definitions
+                    .entry(name)
+                    .or_default()
+                    .push(FunctionDefinition {
+                        plugin: plugin.display_label(),
+                        dir: file.dir.clone(),
+                        file: file.name.clone(),
+                    });
+            }
+        }
+    }
+
+    let mut shadowed = Vec::new();
+    for (name, defs) in &definitions {
+        let first = &defs[0].plugin;
+        if defs.iter().all(|d| &d.plugin == first) {
+            continue;
+        }
+        let others = |winner: Option<&FunctionDefinition>| {
+            defs.iter()
+                .filter(|d| winner.is_none_or(|w| !std::ptr::eq(*d, w)))
+                .map(FunctionDefinition::describe)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match winning_definition(name, defs) {
+            Some(winner) => shadowed.push(format!(
+                "{name}: {} wins over {}",
+                winner.describe(),
+                others(Some(winner))
+            )),
+            None => shadowed.push(format!(
+                "{name}: defined by {} (winner depends on load order)",
+                others(None)
+            )),
+        }
+    }
+
+    if shadowed.is_empty() {
+        DoctorCheck {
+            name: "function_shadowing",
+            status: Severity::Ok,
+            details: "no function is defined by more than one plugin".to_string(),
+        }
+    } else {
+        DoctorCheck {
+            name: "function_shadowing",
+            status: Severity::Warn,
+            details: shadowed.join("; "),
+        }
+    }
+}
+
+fn check_autoload_names(lock_file: &LockFile, fish_config_dir: &path::Path) -> DoctorCheck {
+    let misnamed = autoload::installed(lock_file, fish_config_dir);
+    if misnamed.is_empty() {
+        DoctorCheck {
+            name: "autoload_names",
+            status: Severity::Ok,
+            details: "every functions/ file defines the function it is named after".to_string(),
+        }
+    } else {
+        DoctorCheck {
+            name: "autoload_names",
+            status: Severity::Warn,
+            details: misnamed
+                .iter()
+                .map(autoload::Misnamed::describe)
+                .collect::<Vec<_>>()
+                .join("; "),
+        }
+    }
+}
+
+fn check_vendor_conflicts(lock_file: &LockFile, vendor: &VendorDirs) -> DoctorCheck {
+    let shadowed: Vec<String> = lock_file
+        .plugins
+        .iter()
+        .flat_map(|p| vendor.shadowed_by(p))
+        .map(|s| {
+            format!(
+                "{} ({} shadows {})",
+                s.name,
+                s.plugin,
+                s.vendor_path.display()
+            )
+        })
+        .collect();
+    if shadowed.is_empty() {
+        DoctorCheck {
+            name: "vendor_conflicts",
+            status: Severity::Ok,
+            details: "no plugin files shadow vendor completions or functions".to_string(),
+        }
+    } else {
+        DoctorCheck {
+            name: "vendor_conflicts",
+            status: Severity::Warn,
+            details: format!("shadowed: {}", shadowed.join(", ")),
+        }
+    }
+}
+
+fn status_prefix(status: Severity) -> &'static str {
     match status {
-        "ok" => "✔",
-        "warn" => "⚠",
-        _ => "✖",
+        Severity::Ok => "✔",
+        Severity::Warn => "⚠",
+        Severity::Error => "✖",
     }
 }
 
@@ -318,8 +767,27 @@ fn render_plain_lines(checks: &[DoctorCheck]) -> Vec<String> {
         .collect()
 }
 
+/// One workflow annotation per check that did not pass.
+fn render_github_annotations(checks: &[DoctorCheck]) -> Vec<String> {
+    checks
+        .iter()
+        .filter_map(|c| {
+            let level = match c.status {
+                Severity::Ok => return None,
+                Severity::Warn => output::Annotation::Warning,
+                Severity::Error => output::Annotation::Error,
+            };
+            Some(output::github_annotation(
+                level,
+                &format!("pez doctor: {}", c.name),
+                &c.details,
+            ))
+        })
+        .collect()
+}
+
 fn has_error(checks: &[DoctorCheck]) -> bool {
-    checks.iter().any(|c| c.status == "error")
+    checks.iter().any(|c| c.status == Severity::Error)
 }
 
 #[cfg(test)]
@@ -412,7 +880,7 @@ mod tests {
         result
     }
 
-    fn status_map(checks: Vec<DoctorCheck>) -> HashMap<&'static str, &'static str> {
+    fn status_map(checks: Vec<DoctorCheck>) -> HashMap<&'static str, Severity> {
         let mut statuses = HashMap::new();
         for check in checks {
             statuses.insert(check.name, check.status);
@@ -440,22 +908,106 @@ mod tests {
                     dir: TargetDir::ConfD,
                     name: "pkg.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
 
         with_env(&env, || {
             let checks = collect_checks().unwrap();
+            assert!(checks.iter().all(|c| CHECK_NAMES.contains(&c.name)));
             let statuses = status_map(checks);
-            assert_eq!(statuses.get("config"), Some(&"ok"));
-            assert_eq!(statuses.get("lock_file"), Some(&"ok"));
-            assert_eq!(statuses.get("fish_config_dir"), Some(&"ok"));
-            assert_eq!(statuses.get("pez_data_dir"), Some(&"ok"));
-            assert_eq!(statuses.get("repos"), Some(&"warn"));
-            assert_eq!(statuses.get("target_files"), Some(&"warn"));
-            assert_eq!(statuses.get("duplicates"), Some(&"ok"));
+            assert_eq!(statuses.get("config"), Some(&Severity::Ok));
+            assert_eq!(statuses.get("lock_file"), Some(&Severity::Ok));
+            assert_eq!(statuses.get("fish_config_dir"), Some(&Severity::Ok));
+            assert_eq!(statuses.get("pez_data_dir"), Some(&Severity::Ok));
+            assert_eq!(statuses.get("repos"), Some(&Severity::Warn));
+            assert_eq!(statuses.get("target_files"), Some(&Severity::Warn));
+            assert_eq!(statuses.get("duplicates"), Some(&Severity::Ok));
         });
     }
 
+    #[test]
+    fn fix_restores_files_and_repairs_lock_records() {
+        let mut env = TestEnvironmentSetup::new();
+        env.setup_config(config::init());
+        let local = |name: &str, files: &[(TargetDir, &str, &str)]| {
+            let source = env._temp_dir.path().join("src").join(name);
+            for (dir, file, contents) in files {
+                let path = source.join(dir.as_str()).join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, contents).unwrap();
+            }
+            Plugin {
+                name: name.into(),
+                repo: PluginRepo {
+                    host: None,
+                    owner: "local".into(),
+                    repo: name.into(),
+                },
+                source: source.display().to_string(),
+                commit_sha: "local".into(),
+                files: vec![],
+                ..Default::default()
+            }
+        };
+        let record = |dir: TargetDir, name: &str| PluginFile {
+            dir,
+            name: name.into(),
+        };
+        let mut first = local(
+            "first",
+            &[
+                (TargetDir::ConfD, "first.fish", "echo first\n"),
+                (TargetDir::Functions, "dup.fish", "echo from first\n"),
+            ],
+        );
+        first.files = vec![
+            record(TargetDir::ConfD, "first.fish"),
+            record(TargetDir::ConfD, "gone.fish"),
+            record(TargetDir::Functions, "dup.fish"),
+        ];
+        let mut second = local(
+            "second",
+            &[(TargetDir::Functions, "dup.fish", "echo from second\n")],
+        );
+        second.files = vec![record(TargetDir::Functions, "dup.fish")];
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![first, second],
+        });
+        let functions = env.fish_config_dir.join(TargetDir::Functions.as_str());
+        std::fs::create_dir_all(&functions).unwrap();
+        std::fs::write(functions.join("dup.fish"), "echo from second\n").unwrap();
+
+        let fixed = with_env(&env, || fix().unwrap());
+        let conf_d = env.fish_config_dir.join(TargetDir::ConfD.as_str());
+        assert_eq!(
+            fixed,
+            vec![
+                format!("restored {}", conf_d.join("first.fish").display()),
+                format!(
+                    "dropped {} from the lock (local/first no longer ships it)",
+                    conf_d.join("gone.fish").display()
+                ),
+                format!(
+                    "{} now belongs to local/second only; dropped it from local/first",
+                    functions.join("dup.fish").display()
+                ),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(conf_d.join("first.fish")).unwrap(),
+            "echo first\n"
+        );
+        let lock = crate::lock_file::load(&env.lock_file_path).unwrap();
+        assert_eq!(
+            lock.plugins[0].files,
+            vec![record(TargetDir::ConfD, "first.fish")]
+        );
+        assert_eq!(lock.plugins[1].files.len(), 1);
+        with_env(&env, || assert!(fix().unwrap().is_empty()));
+    }
+
     #[test]
     fn doctor_warns_when_activate_is_not_configured() {
         let mut env = TestEnvironmentSetup::new();
@@ -463,9 +1015,9 @@ mod tests {
 
         with_env(&env, || {
             let statuses = status_map(collect_checks().unwrap());
-            assert_eq!(statuses.get("activate_configured"), Some(&"warn"));
-            assert_eq!(statuses.get("event_hook_readiness"), Some(&"warn"));
-            assert_eq!(statuses.get("install_layout"), Some(&"ok"));
+            assert_eq!(statuses.get("activate_configured"), Some(&Severity::Warn));
+            assert_eq!(statuses.get("event_hook_readiness"), Some(&Severity::Warn));
+            assert_eq!(statuses.get("install_layout"), Some(&Severity::Ok));
         });
     }
 
@@ -481,8 +1033,8 @@ mod tests {
 
         with_env(&env, || {
             let statuses = status_map(collect_checks().unwrap());
-            assert_eq!(statuses.get("activate_configured"), Some(&"ok"));
-            assert_eq!(statuses.get("event_hook_readiness"), Some(&"ok"));
+            assert_eq!(statuses.get("activate_configured"), Some(&Severity::Ok));
+            assert_eq!(statuses.get("event_hook_readiness"), Some(&Severity::Ok));
         });
     }
 
@@ -500,8 +1052,8 @@ mod tests {
 
         with_env_and_target_dir(&env, &target_dir, || {
             let statuses = status_map(collect_checks().unwrap());
-            assert_eq!(statuses.get("activate_configured"), Some(&"ok"));
-            assert_eq!(statuses.get("event_hook_readiness"), Some(&"ok"));
+            assert_eq!(statuses.get("activate_configured"), Some(&Severity::Ok));
+            assert_eq!(statuses.get("event_hook_readiness"), Some(&Severity::Ok));
         });
     }
 
@@ -513,7 +1065,7 @@ mod tests {
 
         with_env(&env, || {
             let statuses = status_map(collect_checks().unwrap());
-            assert_eq!(statuses.get("install_layout"), Some(&"warn"));
+            assert_eq!(statuses.get("install_layout"), Some(&Severity::Warn));
         });
     }
 
@@ -537,15 +1089,77 @@ mod tests {
                     dir: TargetDir::Themes,
                     name: "theme.theme".into(),
                 }],
+                ..Default::default()
             }],
         });
 
         with_env(&env, || {
             let statuses = status_map(collect_checks().unwrap());
-            assert_eq!(statuses.get("theme_assets"), Some(&"warn"));
+            assert_eq!(statuses.get("theme_assets"), Some(&Severity::Warn));
         });
     }
 
+    #[test]
+    fn function_shadowing_reports_winning_plugin() {
+        let temp = tempfile::tempdir().unwrap();
+        let fish = temp.path();
+        fs::create_dir_all(fish.join("conf.d")).unwrap();
+        fs::create_dir_all(fish.join("functions")).unwrap();
+        fs::write(
+            fish.join("functions/greet.fish"),
+            "function greet --description 'hi'\n    echo hi\nend\n",
+        )
+        .unwrap();
+        fs::write(
+            fish.join("conf.d/zz_other.fish"),
+            "function greet; echo other; end\nfunction helper\nend\n",
+        )
+        .unwrap();
+
+        let plugin = |repo: &str, dir: TargetDir, name: &str| {
+            let repo: PluginRepo = repo.parse().unwrap();
+            Plugin {
+                name: repo.repo.clone(),
+                source: repo.default_remote_source(),
+                repo,
+                commit_sha: "abc".into(),
+                files: vec![PluginFile {
+                    dir,
+                    name: name.into(),
+                }],
+                ..Default::default()
+            }
+        };
+        let mut lock = LockFile {
+            version: 1,
+            plugins: vec![plugin("owner/greet", TargetDir::Functions, "greet.fish")],
+        };
+        assert_eq!(check_function_shadowing(&lock, fish).status, Severity::Ok);
+
+        lock.plugins
+            .push(plugin("owner/other", TargetDir::ConfD, "zz_other.fish"));
+        let check = check_function_shadowing(&lock, fish);
+        assert_eq!(check.status, Severity::Warn);
+        assert_eq!(
+            check.details,
+            "greet: owner/other (conf.d/zz_other.fish) wins over owner/greet (functions/greet.fish)"
+        );
+    }
+
+    #[test]
+    fn check_activate_version_warns_on_stale_wrapper() {
+        let stale = check_activate_version(Some("0.0.1"));
+        assert_eq!(stale.status, Severity::Warn);
+        assert!(stale.details.contains("pez activate fish | source"));
+
+        let current = check_activate_version(Some(env!("CARGO_PKG_VERSION")));
+        assert_eq!(current.status, Severity::Ok);
+
+        let unknown = check_activate_version(None);
+        assert_eq!(unknown.status, Severity::Ok);
+        assert!(unknown.details.contains("skipped"));
+    }
+
     #[test]
     fn has_activate_fish_line_requires_activation_command() {
         let contents = "set -gx PEZ_TEST 1\nfunctions -q pez\n";
@@ -563,17 +1177,17 @@ mod tests {
         let checks = vec![
             DoctorCheck {
                 name: "ok",
-                status: "ok",
+                status: Severity::Ok,
                 details: "one".into(),
             },
             DoctorCheck {
                 name: "warn",
-                status: "warn",
+                status: Severity::Warn,
                 details: "two".into(),
             },
             DoctorCheck {
                 name: "error",
-                status: "error",
+                status: Severity::Error,
                 details: "three".into(),
             },
         ];
@@ -583,18 +1197,73 @@ mod tests {
         assert!(lines[2].starts_with("✖ "));
     }
 
+    #[test]
+    fn github_annotations_cover_warnings_and_errors_only() {
+        let check = |name, status| DoctorCheck {
+            name,
+            status,
+            details: format!("{name} details"),
+        };
+        let checks = vec![
+            check("config", Severity::Ok),
+            check("repos", Severity::Warn),
+            check("duplicates", Severity::Error),
+        ];
+        assert_eq!(
+            render_github_annotations(&checks),
+            vec![
+                "::warning title=pez doctor%3A repos::repos details",
+                "::error title=pez doctor%3A duplicates::duplicates details",
+            ]
+        );
+    }
+
+    #[test]
+    fn exit_error_tells_warnings_from_errors() {
+        let check = |status| DoctorCheck {
+            name: "config",
+            status,
+            details: String::new(),
+        };
+        let warned = vec![check(Severity::Ok), check(Severity::Warn)];
+        assert!(exit_error(&warned, false).is_none());
+        let strict = exit_error(&warned, true).unwrap();
+        assert_eq!(strict.code, EXIT_WARNINGS);
+        assert_eq!(strict.message, "doctor found 1 warning(s) (--strict)");
+
+        let failed = vec![check(Severity::Warn), check(Severity::Error)];
+        assert_eq!(exit_error(&failed, false).unwrap().code, EXIT_ERRORS);
+        assert_eq!(exit_error(&failed, true).unwrap().code, EXIT_ERRORS);
+    }
+
+    #[test]
+    fn structured_report_is_versioned_with_ids_and_severities() {
+        let checks = vec![DoctorCheck {
+            name: "duplicates",
+            status: Severity::Error,
+            details: "oops".into(),
+        }];
+        let value = serde_json::to_value(report(&checks)).unwrap();
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        assert!(value["generated_at"].as_u64().unwrap() > 0);
+        assert_eq!(
+            value["checks"],
+            serde_json::json!([{"id": "duplicates", "severity": "error", "details": "oops"}])
+        );
+    }
+
     #[test]
     fn has_error_detects_errors() {
         let ok_checks = vec![DoctorCheck {
             name: "config",
-            status: "ok",
+            status: Severity::Ok,
             details: "ok".into(),
         }];
         assert!(!has_error(&ok_checks));
 
         let err_checks = vec![DoctorCheck {
             name: "duplicates",
-            status: "error",
+            status: Severity::Error,
             details: "oops".into(),
         }];
         assert!(has_error(&err_checks));
@@ -620,12 +1289,17 @@ mod tests {
                     dir: TargetDir::ConfD,
                     name: "pkg.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
 
         with_env(&env, || {
-            let args = cli::DoctorArgs { format: None };
-            let (logs, result) = capture_logs(|| run(&args));
+            let args = cli::DoctorArgs {
+                fix: false,
+                format: None,
+                strict: false,
+            };
+            let (logs, result) = capture_logs(|| run(&args, &RuntimeOptions::default()));
             let checks = result.unwrap();
             assert!(!checks.is_empty());
             assert!(
@@ -638,4 +1312,41 @@ mod tests {
             );
         });
     }
+
+    #[test]
+    fn run_leaves_out_ignored_checks() {
+        let mut env = TestEnvironmentSetup::new();
+        let mut config = config::init();
+        config.doctor.ignore = vec!["repos".into(), "bogus".into()];
+        env.setup_config(config);
+        // The clone is missing, so `repos` would warn.
+        let repo: PluginRepo = "owner/pkg".parse().unwrap();
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![Plugin {
+                name: "pkg".into(),
+                source: repo.default_remote_source(),
+                repo,
+                commit_sha: "abc".into(),
+                files: vec![],
+                ..Default::default()
+            }],
+        });
+
+        with_env(&env, || {
+            let args = cli::DoctorArgs {
+                fix: false,
+                format: None,
+                strict: false,
+            };
+            let (logs, result) = capture_logs(|| run(&args, &RuntimeOptions::default()));
+            let checks = result.unwrap();
+            assert!(checks.iter().any(|c| c.name == "config"));
+            assert!(!checks.iter().any(|c| c.name == "repos"));
+            assert!(
+                logs.iter().any(|msg| msg.contains("Unknown check `bogus`")),
+                "missing unknown-check warning: {logs:?}"
+            );
+        });
+    }
 }
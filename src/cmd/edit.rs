@@ -0,0 +1,197 @@
+use crate::{config::Config, prompt, utils};
+
+use console::Emoji;
+use std::{fs, path, process::Command};
+use tracing::{error, info};
+
+/// Open pez.toml in `$VISUAL`/`$EDITOR` and only accept the edit once it parses
+/// and validates. Invalid edits can be reopened, or are reverted.
+pub(crate) fn run() -> anyhow::Result<()> {
+    let config_path = utils::load_pez_config_dir()?.join("pez.toml");
+    if !config_path.exists() {
+        anyhow::bail!("{} not found; run `pez init` first", config_path.display());
+    }
+    let original = fs::read_to_string(&config_path)?;
+    edit_with_retry(&config_path, &original, edit_again)
+}
+
+/// Open pez.toml after changes were already written to it, e.g. entries staged by
+/// `pez install --edit`. Discarding an invalid edit restores `original`.
+pub(crate) fn edit_staged(config_path: &path::Path, original: &str) -> anyhow::Result<()> {
+    edit_with_retry(config_path, original, edit_again)
+}
+
+fn edit_with_retry(
+    config_path: &path::Path,
+    original: &str,
+    mut retry: impl FnMut() -> anyhow::Result<bool>,
+) -> anyhow::Result<()> {
+    loop {
+        open_editor(config_path)?;
+        let content = fs::read_to_string(config_path)?;
+        let Err(message) = check(&content) else {
+            if content == original {
+                info!("{}No changes to {}", Emoji("ℹ ", ""), config_path.display());
+            } else {
+                info!("{}Saved {}", Emoji("✅ ", ""), config_path.display());
+            }
+            return Ok(());
+        };
+
+        error!(
+            "{} {} is invalid:\n{}",
+            utils::label_error(),
+            config_path.display(),
+            message
+        );
+        if !retry()? {
+            fs::write(config_path, original)?;
+            anyhow::bail!(
+                "Discarded invalid changes; {} was restored",
+                config_path.display()
+            );
+        }
+    }
+}
+
+/// `$VISUAL`, then `$EDITOR`, then `vi`. The value may carry arguments, e.g. `code --wait`.
+fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|parts| !parts.is_empty())
+        .unwrap_or_else(|| vec!["vi".to_string()])
+}
+
+fn open_editor(config_path: &path::Path) -> anyhow::Result<()> {
+    let editor = editor_command();
+    let status = Command::new(&editor[0])
+        .args(&editor[1..])
+        .arg(config_path)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to start editor `{}`: {e}", editor.join(" ")))?;
+    if !status.success() {
+        anyhow::bail!("Editor `{}` exited with {status}", editor.join(" "));
+    }
+    Ok(())
+}
+
+/// Parse and validate edited content. TOML errors already carry a line and column;
+/// invalid plugin entries are located by their `[[plugins]]` header.
+fn check(content: &str) -> Result<(), String> {
+    let config: Config = toml::from_str(content).map_err(|e| e.to_string())?;
+    let Err(err) = config.validate() else {
+        return Ok(());
+    };
+    let failing = config
+        .plugins
+        .iter()
+        .flatten()
+        .position(|plugin| plugin.validate().is_err());
+    match failing.and_then(|idx| plugin_header_line(content, idx)) {
+        Some(line) => Err(format!("line {line}: {err:#}")),
+        None => Err(format!("{err:#}")),
+    }
+}
+
+/// 1-based line of the `idx`-th `[[plugins]]` header.
+pub(crate) fn plugin_header_line(content: &str, idx: usize) -> Option<usize> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.trim_start().starts_with("[[plugins]]"))
+        .nth(idx)
+        .map(|(number, _)| number + 1)
+}
+
+/// Reopening the editor needs someone at a terminal, even with `PEZ_ASSUME_YES`.
+fn edit_again() -> anyhow::Result<bool> {
+    Ok(prompt::interactive() && prompt::confirm("Edit again?", true)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_support::{env::TestEnvironmentSetup, log::env_lock};
+    use std::os::unix::fs::PermissionsExt;
+
+    const VALID: &str = "[[plugins]]\nrepo = \"owner/one\"\n";
+
+    /// An "editor" that overwrites the file it is given with `content`.
+    fn fake_editor(dir: &path::Path, content: &str) -> path::PathBuf {
+        let script = dir.join("editor.sh");
+        let source = dir.join("next.toml");
+        fs::write(&source, content).unwrap();
+        fs::write(
+            &script,
+            format!("#!/bin/sh\ncp '{}' \"$1\"\n", source.display()),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    fn run_with_editor(env: &TestEnvironmentSetup, new_content: &str) -> anyhow::Result<()> {
+        let _lock = env_lock().lock().unwrap();
+        let editor = fake_editor(env.config_dir.parent().unwrap(), new_content);
+        let saved: Vec<_> = ["PEZ_CONFIG_DIR", "VISUAL", "EDITOR"]
+            .iter()
+            .map(|key| (*key, std::env::var_os(key)))
+            .collect();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::remove_var("VISUAL");
+            std::env::set_var("EDITOR", &editor);
+        }
+        let config_path = env.config_dir.join("pez.toml");
+        let original = fs::read_to_string(&config_path).unwrap();
+        let result = edit_with_retry(&config_path, &original, || Ok(false));
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn check_reports_line_numbers() {
+        assert!(check(VALID).is_ok());
+
+        let syntax = check("[[plugins]]\nrepo = owner/one\n").unwrap_err();
+        assert!(syntax.contains("line 2"), "{syntax}");
+
+        let invalid = check(&format!(
+            "{VALID}\n[[plugins]]\nrepo = \"owner/two\"\ntag = \"v1\"\nbranch = \"main\"\n"
+        ))
+        .unwrap_err();
+        assert!(
+            invalid.starts_with("line 4: invalid plugins[1]"),
+            "{invalid}"
+        );
+    }
+
+    #[test]
+    fn run_keeps_valid_edits_and_reverts_invalid_ones() {
+        let env = TestEnvironmentSetup::new();
+        let config_path = env.config_dir.join("pez.toml");
+        fs::write(&config_path, "").unwrap();
+
+        run_with_editor(&env, VALID).unwrap();
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), VALID);
+
+        // Declining to edit again reverts the invalid edit.
+        let err = run_with_editor(&env, "[[plugins]\n").unwrap_err();
+        assert!(err.to_string().contains("Discarded invalid changes"));
+        assert_eq!(fs::read_to_string(&config_path).unwrap(), VALID);
+    }
+}
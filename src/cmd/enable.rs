@@ -0,0 +1,414 @@
+use crate::{
+    backup,
+    cli::EnableArgs,
+    cmd::pin::unique,
+    config::PluginSpec,
+    git,
+    lock_file::{LockFile, Plugin, PluginFile},
+    utils::{self, Event},
+};
+
+use console::Emoji;
+use std::{fs, path};
+use tracing::{info, warn};
+
+/// Mark each plugin `disabled = true` in pez.toml and remove its files from the fish
+/// config directory. The clone and lock entry stay, so `pez enable` needs no network.
+pub(crate) fn disable(args: &EnableArgs) -> anyhow::Result<()> {
+    let _backup = backup::start_run("disable", None)?;
+    let (mut config, config_path) = utils::load_config()?;
+    let (lock_file, _) = utils::load_lock_file()?;
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let repos = unique(&args.plugins);
+    for plugin_repo in &repos {
+        if config.find_plugin_spec(plugin_repo).is_none() {
+            anyhow::bail!("{plugin_repo} is not declared in pez.toml");
+        }
+    }
+
+    for plugin_repo in repos {
+        let Some(spec) = config.find_plugin_spec_mut(plugin_repo) else {
+            continue;
+        };
+        if spec.disabled {
+            info!("{}{} is already disabled", Emoji("💤 ", ""), plugin_repo);
+            continue;
+        }
+        spec.disabled = true;
+        config.save(&config_path)?;
+        if let Some(locked) = lock_file.get_plugin_by_repo(plugin_repo) {
+            utils::emit_plugin_events(locked, &Event::Uninstall);
+            remove_files(locked, &lock_file, &fish_config_dir);
+        }
+        info!(
+            "{}Disabled {}; run `pez enable {}` to restore its files",
+            Emoji("💤 ", ""),
+            plugin_repo,
+            plugin_repo
+        );
+    }
+    Ok(())
+}
+
+/// Clear `disabled` in pez.toml and copy each plugin's files back from its clone (or
+/// local path) at the locked commit.
+pub(crate) fn enable(args: &EnableArgs) -> anyhow::Result<()> {
+    let _backup = backup::start_run("enable", None)?;
+    let (mut config, config_path) = utils::load_config()?;
+    let (mut lock_file, lock_file_path) = utils::load_lock_file()?;
+    let data_dir = utils::load_pez_data_dir()?;
+    let repos = unique(&args.plugins);
+    for plugin_repo in &repos {
+        if config.find_plugin_spec(plugin_repo).is_none() {
+            anyhow::bail!("{plugin_repo} is not declared in pez.toml");
+        }
+    }
+
+    for plugin_repo in repos {
+        let Some(spec) = config.find_plugin_spec_mut(plugin_repo) else {
+            continue;
+        };
+        if !spec.disabled {
+            info!("{}{} is already enabled", Emoji("🔔 ", ""), plugin_repo);
+            continue;
+        }
+        spec.disabled = false;
+        config.save(&config_path)?;
+        let Some(locked) = lock_file.get_plugin_by_repo(plugin_repo).cloned() else {
+            info!(
+                "{}Enabled {}; run `pez install` to install it",
+                Emoji("🔔 ", ""),
+                plugin_repo
+            );
+            continue;
+        };
+        let restored = restore_files(&locked, &data_dir)?;
+        utils::emit_plugin_events(&restored, &Event::Install);
+        if restored.files != locked.files {
+            lock_file.upsert_plugin_by_repo(restored)?;
+            lock_file.save(&lock_file_path)?;
+        }
+        info!("{}Enabled {}", Emoji("🔔 ", ""), plugin_repo);
+    }
+    Ok(())
+}
+
+/// Bring an installed plugin in line with its `disable_files`: remove files newly listed
+/// there and copy back the ones no longer listed. Returns the updated lock entry, or
+/// None when nothing changed.
+pub(crate) fn apply_disable_files(
+    spec: &PluginSpec,
+    locked: &Plugin,
+    lock_file: &LockFile,
+    data_dir: &path::Path,
+    fish_config_dir: &path::Path,
+) -> anyhow::Result<Option<Plugin>> {
+    let disabled = |file: &PluginFile| spec.disables(&file.dir, &file.name);
+    let (newly_disabled, files): (Vec<PluginFile>, Vec<PluginFile>) =
+        locked.files.iter().cloned().partition(disabled);
+    let (still_disabled, reenabled): (Vec<PluginFile>, Vec<PluginFile>) =
+        locked.disabled_files.iter().cloned().partition(disabled);
+    if newly_disabled.is_empty() && reenabled.is_empty() {
+        return Ok(None);
+    }
+
+    let mut updated = Plugin {
+        files,
+        disabled_files: still_disabled,
+        ..locked.clone()
+    };
+    for file in newly_disabled {
+        let dest = file.get_path(fish_config_dir);
+        if !lock_file.is_claimed_by_other(&locked.repo, &file) && dest.exists() {
+            backup::remove_file(&dest)?;
+        }
+        info!("   - {} (disable_files; removed)", dest.display());
+        updated.disabled_files.push(file);
+    }
+    if !reenabled.is_empty() {
+        let source = if git::is_local_source(&locked.source) {
+            path::PathBuf::from(&locked.source)
+        } else {
+            data_dir.join(locked.repo.as_str())
+        };
+        let displaced = backup::DisplacedFiles::load(fish_config_dir)?;
+        for file in reenabled {
+            let src = source.join(locked.source_path(&file));
+            let dest = file.get_path(fish_config_dir);
+            if !src.is_file() {
+                warn!(
+                    "{} {} {} is missing; run `pez install --force {}` to restore {}",
+                    Emoji("⚠ ", ""),
+                    utils::label_warning(),
+                    src.display(),
+                    locked.repo,
+                    dest.display()
+                );
+                updated.disabled_files.push(file);
+                continue;
+            }
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if dest.exists() {
+                displaced.preserve(&mut updated, &file.dir, &file.name, &dest)?;
+            }
+            fs::copy(&src, &dest)?;
+            info!("   - {} (restored)", dest.display());
+            updated.files.push(file);
+        }
+    }
+    Ok(Some(updated))
+}
+
+/// Files shared with another plugin stay, since that plugin still uses them.
+fn remove_files(plugin: &Plugin, lock_file: &LockFile, fish_config_dir: &path::Path) {
+    for file in &plugin.files {
+        let dest = file.get_path(fish_config_dir);
+        if !lock_file.is_claimed_by_other(&plugin.repo, file)
+            && dest.exists()
+            && let Err(e) = backup::remove_file(&dest)
+        {
+            warn!("Failed to remove {}: {:?}", dest.display(), e);
+        }
+    }
+}
+
+/// Copy the plugin's files from where pez keeps its source. Files it displaced stayed
+/// in their backups while it was disabled, so nothing needs backing up again.
+fn restore_files(locked: &Plugin, data_dir: &path::Path) -> anyhow::Result<Plugin> {
+    let source = if git::is_local_source(&locked.source) {
+        path::PathBuf::from(&locked.source)
+    } else {
+        data_dir.join(locked.repo.as_str())
+    };
+    if !source.exists() {
+        anyhow::bail!(
+            "{} is missing; run `pez install --force` to reinstall {}",
+            source.display(),
+            locked.display_label()
+        );
+    }
+    let mut restored = Plugin {
+        files: vec![],
+        ..locked.clone()
+    };
+    utils::copy_plugin_files_from_repo(&source, &mut restored)?;
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{self, PluginSource, PluginSpec},
+        lock_file::{LockFile, PluginFile},
+        models::{PluginRepo, TargetDir},
+        tests_support::{env::TestEnvironmentSetup, log::env_lock},
+    };
+
+    #[test]
+    fn disable_removes_files_and_enable_copies_them_back() {
+        let mut env = TestEnvironmentSetup::new();
+        let source = env.data_dir.parent().unwrap().join("toggle-plugin");
+        fs::create_dir_all(source.join("functions")).unwrap();
+        fs::write(source.join("functions/toggle.fish"), "function toggle; end").unwrap();
+        let repo: PluginRepo = "local/toggle-plugin".parse().unwrap();
+        env.setup_config(config::Config {
+            plugins: Some(vec![PluginSpec {
+                name: None,
+                prerelease: false,
+                disabled: false,
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
+                source: PluginSource::Path {
+                    path: source.display().to_string(),
+                },
+            }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![Plugin {
+                name: "toggle-plugin".into(),
+                repo: repo.clone(),
+                source: source.display().to_string(),
+                commit_sha: "local".into(),
+                files: vec![PluginFile {
+                    dir: TargetDir::Functions,
+                    name: "toggle.fish".into(),
+                }],
+                ..Default::default()
+            }],
+        });
+        let installed = env.fish_config_dir.join("functions/toggle.fish");
+        fs::create_dir_all(installed.parent().unwrap()).unwrap();
+        fs::write(&installed, "function toggle; end").unwrap();
+        let lock_before = fs::read_to_string(&env.lock_file_path).unwrap();
+
+        let _lock = env_lock().lock().unwrap();
+        let saved: Vec<_> = [
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "__fish_config_dir",
+            "PEZ_SUPPRESS_EMIT",
+        ]
+        .iter()
+        .map(|key| (*key, std::env::var_os(key)))
+        .collect();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+            std::env::set_var("__fish_config_dir", &env.fish_config_dir);
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+        }
+        let args = EnableArgs {
+            plugins: vec![repo.clone()],
+        };
+
+        disable(&args).unwrap();
+        let disabled = config::load(&env.config_path).unwrap();
+        let disabled_state = (
+            disabled.is_disabled(&repo),
+            installed.exists(),
+            fs::read_to_string(&env.lock_file_path).unwrap() == lock_before,
+        );
+        enable(&args).unwrap();
+        let enabled = config::load(&env.config_path).unwrap();
+        let unknown = enable(&EnableArgs {
+            plugins: vec!["owner/unknown".parse().unwrap()],
+        });
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+
+        assert_eq!(disabled_state, (true, false, true));
+        assert!(!enabled.is_disabled(&repo));
+        assert!(installed.exists());
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn disable_files_skip_remove_and_restore_single_files() {
+        let mut env = TestEnvironmentSetup::new();
+        let source = env.data_dir.parent().unwrap().join("keys-plugin");
+        fs::create_dir_all(source.join("functions")).unwrap();
+        fs::create_dir_all(source.join("conf.d")).unwrap();
+        fs::write(source.join("functions/keys.fish"), "function keys; end").unwrap();
+        fs::write(source.join("conf.d/keybindings.fish"), "bind \\cx keys").unwrap();
+        let repo: PluginRepo = "local/keys-plugin".parse().unwrap();
+        let spec = |disable_files: Vec<String>| PluginSpec {
+            name: None,
+            prerelease: false,
+            disabled: false,
+            dir: None,
+            events: vec![],
+            disable_files,
+            source: PluginSource::Path {
+                path: source.display().to_string(),
+            },
+        };
+        let disabling = spec(vec!["conf.d/keybindings.fish".into()]);
+        env.setup_config(config::Config {
+            plugins: Some(vec![disabling.clone()]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        let file = |dir: TargetDir, name: &str| PluginFile {
+            dir,
+            name: name.into(),
+        };
+        let bindings = env.fish_config_dir.join("conf.d/keybindings.fish");
+        let plugin = Plugin {
+            name: "keys-plugin".into(),
+            repo: repo.clone(),
+            source: source.display().to_string(),
+            commit_sha: "local".into(),
+            files: vec![],
+            ..Default::default()
+        };
+
+        let _lock = env_lock().lock().unwrap();
+        let saved: Vec<_> = ["PEZ_CONFIG_DIR", "PEZ_DATA_DIR", "__fish_config_dir"]
+            .iter()
+            .map(|key| (*key, std::env::var_os(key)))
+            .collect();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+            std::env::set_var("__fish_config_dir", &env.fish_config_dir);
+        }
+        let copied = restore_files(&plugin, &env.data_dir).unwrap();
+
+        // Installed before `disable_files` was added: both files are on disk.
+        fs::create_dir_all(bindings.parent().unwrap()).unwrap();
+        fs::write(&bindings, "bind \\cx keys").unwrap();
+        let installed = Plugin {
+            files: vec![
+                file(TargetDir::Functions, "keys.fish"),
+                file(TargetDir::ConfD, "keybindings.fish"),
+            ],
+            ..plugin.clone()
+        };
+        let lock_file = LockFile {
+            version: 2,
+            plugins: vec![installed.clone()],
+        };
+        let removed = apply_disable_files(
+            &disabling,
+            &installed,
+            &lock_file,
+            &env.data_dir,
+            &env.fish_config_dir,
+        )
+        .unwrap()
+        .unwrap();
+        let bindings_after_disable = bindings.exists();
+        let restored = apply_disable_files(
+            &spec(vec![]),
+            &removed,
+            &lock_file,
+            &env.data_dir,
+            &env.fish_config_dir,
+        )
+        .unwrap()
+        .unwrap();
+        let unchanged = apply_disable_files(
+            &spec(vec![]),
+            &restored,
+            &lock_file,
+            &env.data_dir,
+            &env.fish_config_dir,
+        )
+        .unwrap();
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+
+        assert_eq!(copied.files, vec![file(TargetDir::Functions, "keys.fish")]);
+        assert_eq!(
+            copied.disabled_files,
+            vec![file(TargetDir::ConfD, "keybindings.fish")]
+        );
+        assert_eq!(removed.disabled_files, copied.disabled_files);
+        assert!(!bindings_after_disable);
+        assert_eq!(restored.files, installed.files);
+        assert!(restored.disabled_files.is_empty());
+        assert!(bindings.exists());
+        assert!(unchanged.is_none());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{
+    cli::ExportArgs,
+    config::{PluginSource, PluginSpec},
+    models::PluginRepo,
+    output::out,
+    utils,
+};
+
+use console::Emoji;
+use std::fs;
+use tracing::{info, warn};
+
+/// fisher manages itself through fish_plugins; dropping this line would make
+/// `fisher update` uninstall fisher.
+const FISHER_REPO: &str = "jorgebucaran/fisher";
+
+/// Write pez.toml as a fisher `fish_plugins` file, the inverse of `pez migrate`.
+pub(crate) fn run(args: &ExportArgs) -> anyhow::Result<()> {
+    let (config, _) = utils::load_config()?;
+    let (content, count) = render(&config.plugins.unwrap_or_default());
+
+    match &args.output {
+        Some(path) => {
+            fs::write(path, content)?;
+            info!(
+                "{}Exported {} plugin(s) to {}",
+                Emoji("📄 ", ""),
+                count,
+                path.display()
+            );
+        }
+        None => out!("{content}"),
+    }
+    Ok(())
+}
+
+/// The fish_plugins content for `specs` and how many plugins it lists besides
+/// fisher itself. Entries fisher cannot fetch are warned about and left out, and
+/// disabled plugins are left out so `fisher update` does not bring them back.
+fn render(specs: &[PluginSpec]) -> (String, usize) {
+    let mut lines = vec![FISHER_REPO.to_string()];
+    for spec in specs.iter().filter(|spec| !spec.disabled) {
+        match fisher_entry(spec) {
+            Ok(line) if line == FISHER_REPO => {}
+            Ok(line) => lines.push(line),
+            Err(reason) => warn!(
+                "{} {} Skipping {}: {}",
+                Emoji("🚧 ", ""),
+                utils::label_warning(),
+                spec.get_plugin_repo()
+                    .map(|repo| repo.as_str())
+                    .unwrap_or_default(),
+                reason
+            ),
+        }
+    }
+    (lines.join("\n") + "\n", lines.len() - 1)
+}
+
+/// Rewrite `<fish config dir>/fish_plugins` from pez.toml when
+/// `[settings] sync_fish_plugins = true`, so fisher sees the same plugins. Called
+/// after commands that change pez.toml or what is installed; the file is only
+/// written when its content changes, and failures never fail the command.
+pub(crate) fn sync_fish_plugins() {
+    let Ok((config, _)) = utils::load_config() else {
+        return;
+    };
+    if !config.settings.sync_fish_plugins {
+        return;
+    }
+    let result = utils::load_fish_config_dir().and_then(|dir| {
+        let path = dir.join("fish_plugins");
+        let (content, _) = render(&config.plugins.unwrap_or_default());
+        if fs::read_to_string(&path).is_ok_and(|current| current == content) {
+            return Ok(());
+        }
+        fs::write(&path, content)?;
+        info!("{}Updated {}", Emoji("📄 ", ""), path.display());
+        Ok(())
+    });
+    if let Err(e) = result {
+        warn!(
+            "{} Failed to update fish_plugins: {:#}",
+            utils::label_warning(),
+            e
+        );
+    }
+}
+
+/// One fish_plugins line for `spec`. fisher only fetches from GitHub, GitLab, and
+/// local paths, and treats the text after `@` as a plain git ref.
+fn fisher_entry(spec: &PluginSpec) -> Result<String, String> {
+    let (repo, version, branch, tag, commit) = match &spec.source {
+        PluginSource::Path { path } => return Ok(path.clone()),
+        PluginSource::Repo {
+            repo,
+            version,
+            branch,
+            tag,
+            commit,
+        } => (repo.clone(), version, branch, tag, commit),
+        PluginSource::Url {
+            url,
+            version,
+            branch,
+            tag,
+            commit,
+        } => {
+            let repo = PluginRepo::from_remote_url(url)
+                .ok_or_else(|| format!("cannot derive owner/repo from {url}"))?;
+            (repo, version, branch, tag, commit)
+        }
+    };
+    let base = match repo.host.as_deref().map(str::to_ascii_lowercase).as_deref() {
+        None | Some("github.com") => repo.owner_repo_path(),
+        Some("gitlab.com") => format!("gitlab.com/{}", repo.owner_repo_path()),
+        Some(host) => return Err(format!("fisher cannot install from {host}")),
+    };
+    // `pez migrate` reads a bare ref as a version, which also matches branch and
+    // tag names, and a full SHA as a commit, so these all round-trip.
+    let reference = version
+        .as_ref()
+        .filter(|v| !v.eq_ignore_ascii_case("latest"))
+        .or(tag.as_ref())
+        .or(branch.as_ref())
+        .or(commit.as_ref());
+    if commit.as_ref().is_some_and(|sha| sha.len() != 40) {
+        return Err("fisher needs a full 40-character commit SHA".to_string());
+    }
+    Ok(match reference {
+        Some(reference) => format!("{base}@{reference}"),
+        None => base,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo_spec(repo: &str, tag: Option<&str>, commit: Option<&str>) -> PluginSpec {
+        PluginSpec {
+            name: None,
+            prerelease: false,
+            disabled: false,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
+            source: PluginSource::Repo {
+                repo: repo.parse().unwrap(),
+                version: None,
+                branch: None,
+                tag: tag.map(str::to_string),
+                commit: commit.map(str::to_string),
+            },
+        }
+    }
+
+    #[test]
+    fn fisher_entry_round_trips_supported_sources() {
+        assert_eq!(
+            fisher_entry(&repo_spec("owner/repo", None, None)).unwrap(),
+            "owner/repo"
+        );
+        assert_eq!(
+            fisher_entry(&repo_spec("github.com/owner/repo", Some("v1.2.0"), None)).unwrap(),
+            "owner/repo@v1.2.0"
+        );
+        assert_eq!(
+            fisher_entry(&repo_spec("gitlab.com/group/repo", None, None)).unwrap(),
+            "gitlab.com/group/repo"
+        );
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(
+            fisher_entry(&repo_spec("owner/repo", None, Some(sha))).unwrap(),
+            format!("owner/repo@{sha}")
+        );
+        let url = PluginSpec {
+            source: PluginSource::Url {
+                url: "git@github.com:owner/from-url.git".into(),
+                version: Some("latest".into()),
+                branch: None,
+                tag: None,
+                commit: None,
+            },
+            ..repo_spec("owner/repo", None, None)
+        };
+        assert_eq!(fisher_entry(&url).unwrap(), "owner/from-url");
+        let path = PluginSpec {
+            source: PluginSource::Path {
+                path: "~/src/plugin".into(),
+            },
+            ..repo_spec("owner/repo", None, None)
+        };
+        assert_eq!(fisher_entry(&path).unwrap(), "~/src/plugin");
+    }
+
+    #[test]
+    fn fisher_entry_rejects_what_fisher_cannot_fetch() {
+        assert!(fisher_entry(&repo_spec("codeberg.org/owner/repo", None, None)).is_err());
+        assert!(fisher_entry(&repo_spec("gist.github.com/owner/abc", None, None)).is_err());
+        assert!(fisher_entry(&repo_spec("owner/repo", None, Some("abc1234"))).is_err());
+    }
+
+    #[test]
+    fn sync_fish_plugins_follows_the_setting() {
+        let mut env = crate::tests_support::env::TestEnvironmentSetup::new();
+        let mut config = crate::config::init();
+        let disabled = PluginSpec {
+            disabled: true,
+            ..repo_spec("owner/disabled", None, None)
+        };
+        config.plugins = Some(vec![repo_spec("edc/bass", Some("v1.0"), None), disabled]);
+        config.settings.sync_fish_plugins = true;
+        env.setup_config(config.clone());
+        let fish_plugins = env.fish_config_dir.join("fish_plugins");
+
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let saved: Vec<_> = ["PEZ_CONFIG_DIR", "PEZ_TARGET_DIR"]
+            .into_iter()
+            .map(|key| (key, std::env::var_os(key)))
+            .collect();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_TARGET_DIR", &env.fish_config_dir);
+        }
+        sync_fish_plugins();
+        let synced = fs::read_to_string(&fish_plugins).unwrap();
+        config.settings.sync_fish_plugins = false;
+        config.plugins = None;
+        env.setup_config(config);
+        sync_fish_plugins();
+        let after_disabling = fs::read_to_string(&fish_plugins).unwrap();
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+
+        assert_eq!(synced, "jorgebucaran/fisher\nedc/bass@v1.0\n");
+        assert_eq!(after_disabling, synced);
+    }
+}
@@ -1,24 +1,45 @@
 use crate::cli::{Cli, Commands, FilesArgs, FilesDir, FilesFormat, FilesFrom};
-use crate::cmd::uninstall;
+use crate::cmd::{uninstall, update};
 use crate::lock_file::LockFile;
-use crate::models::{InstallTarget, PluginRepo, TargetDir};
+use crate::models::{PluginRepo, TargetDir};
+use crate::output::{self, outln};
+use crate::runtime::RuntimeOptions;
 use crate::utils;
-use anyhow::{Context, anyhow};
+use anyhow::anyhow;
 use clap::Parser;
 use clap::error::ErrorKind;
 use std::io::Read;
 use std::path::PathBuf;
 
-pub(crate) fn run(args: &FilesArgs) -> anyhow::Result<Vec<PathBuf>> {
+pub(crate) fn run(args: &FilesArgs, options: &RuntimeOptions) -> anyhow::Result<Vec<PathBuf>> {
     let paths = collect_paths(args)?;
     match args.format {
+        FilesFormat::Paths if args.with_state_dir => {
+            let lock_file = load_lock_file_for(args)?;
+            let config_dir = utils::load_fish_config_dir()?;
+            let event = args.from.as_ref().map(from_event);
+            for line in
+                render_paths_with_state_dirs(&paths, &lock_file, &config_dir, event.as_ref())?
+            {
+                outln!("{line}");
+            }
+        }
         FilesFormat::Paths => {
             for line in render_paths(&paths) {
-                println!("{line}");
+                outln!("{line}");
             }
         }
         FilesFormat::Json => {
-            println!("{}", render_paths_json(&paths)?);
+            outln!(
+                "{}",
+                render_paths_structured(&paths, output::Structured::Json, options.json_style)?
+            );
+        }
+        FilesFormat::Yaml => {
+            outln!(
+                "{}",
+                render_paths_structured(&paths, output::Structured::Yaml, options.json_style)?
+            );
         }
     }
     Ok(paths)
@@ -28,12 +49,68 @@ fn render_paths(paths: &[PathBuf]) -> Vec<String> {
     paths.iter().map(|p| p.display().to_string()).collect()
 }
 
-fn render_paths_json(paths: &[PathBuf]) -> anyhow::Result<String> {
+/// `<path>\t<state dir>` lines so the activate wrapper can export
+/// `PEZ_PLUGIN_STATE_DIR` while emitting events in-process. With `event`, a third
+/// field lists the event names to emit after sourcing the path, space-separated: the
+/// file's `<stem>_<event>`, or the plugin's `events` overrides on its last path.
+fn render_paths_with_state_dirs(
+    paths: &[PathBuf],
+    lock_file: &LockFile,
+    config_dir: &std::path::Path,
+    event: Option<&utils::Event>,
+) -> anyhow::Result<Vec<String>> {
+    let owners = paths
+        .iter()
+        .map(|path| {
+            lock_file
+                .plugins
+                .iter()
+                .find(|p| p.resolve_paths(config_dir, None).contains(path))
+                .ok_or_else(|| anyhow!("No installed plugin owns {}", path.display()))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut lines = Vec::with_capacity(paths.len());
+    for (i, (path, owner)) in paths.iter().zip(&owners).enumerate() {
+        let state_dir = utils::plugin_state_dir(&owner.repo)?;
+        let mut line = format!("{}\t{}", path.display(), state_dir.display());
+        if let Some(event) = event {
+            let overrides = utils::configured_event_names(owner, event);
+            let names = if overrides.is_empty() {
+                utils::conf_d_event_name(&path.to_string_lossy(), event)
+                    .into_iter()
+                    .collect()
+            } else if owners[i + 1..].iter().all(|o| o.repo != owner.repo) {
+                overrides
+            } else {
+                vec![]
+            };
+            line.push('\t');
+            line.push_str(&names.join(" "));
+        }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+/// The lifecycle event the wrapper emits after the command `from` names.
+fn from_event(from: &FilesFrom) -> utils::Event {
+    match from {
+        FilesFrom::Install => utils::Event::Install,
+        FilesFrom::Update | FilesFrom::Upgrade => utils::Event::Update,
+        FilesFrom::Uninstall | FilesFrom::Remove => utils::Event::Uninstall,
+    }
+}
+
+fn render_paths_structured(
+    paths: &[PathBuf],
+    format: output::Structured,
+    style: output::JsonStyle,
+) -> anyhow::Result<String> {
     let rendered: Vec<String> = paths
         .iter()
         .map(|p| p.to_string_lossy().to_string())
         .collect();
-    Ok(serde_json::to_string_pretty(&rendered)?)
+    output::to_structured(&rendered, format, style)
 }
 
 fn collect_paths(args: &FilesArgs) -> anyhow::Result<Vec<PathBuf>> {
@@ -43,12 +120,15 @@ fn collect_paths(args: &FilesArgs) -> anyhow::Result<Vec<PathBuf>> {
         return Ok(vec![]);
     }
 
-    let (lock_file, _) = utils::load_lock_file()?;
+    let lock_file = load_lock_file_for(args)?;
 
     let config_dir = utils::load_fish_config_dir()?;
     let dir_filter = match args.dir {
         FilesDir::All => None,
         FilesDir::ConfD => Some(TargetDir::ConfD),
+        FilesDir::Functions => Some(TargetDir::Functions),
+        FilesDir::Completions => Some(TargetDir::Completions),
+        FilesDir::Themes => Some(TargetDir::Themes),
     };
 
     let repos_opt: Option<Vec<PluginRepo>> = if let Some(from) = &args.from {
@@ -62,7 +142,7 @@ fn collect_paths(args: &FilesArgs) -> anyhow::Result<Vec<PathBuf>> {
             .ok_or_else(|| anyhow!("No plugins specified; pass --all or plugin names"))?;
         Some(
             list.iter()
-                .map(|s| parse_plugin_arg(s))
+                .map(|s| lock_file.resolve_plugin_arg(s))
                 .collect::<Result<_, _>>()?,
         )
     };
@@ -72,15 +152,37 @@ fn collect_paths(args: &FilesArgs) -> anyhow::Result<Vec<PathBuf>> {
         None => return Ok(vec![]),
     };
     if repos.is_empty() {
+        // A wrapped command that touched no plugin leaves nothing to source.
+        if args.from.is_some() {
+            return Ok(vec![]);
+        }
         anyhow::bail!("No plugins are installed.");
     }
 
     let mut paths = lock_file.paths_for_repos(&repos, &config_dir, dir_filter.as_ref())?;
+    if args.missing {
+        paths.retain(|path| !path.exists());
+    }
     paths.sort();
     paths.dedup();
     Ok(paths)
 }
 
+/// `install --temp` records its plugins in the temp lock, not pez-lock.toml.
+fn load_lock_file_for(args: &FilesArgs) -> anyhow::Result<LockFile> {
+    let temp = args.from.as_ref().is_some_and(|from| {
+        Cli::try_parse_from(build_from_argv(from, &args.passthrough)).is_ok_and(
+            |parsed| matches!(parsed.command, Commands::Install(install_args) if install_args.temp),
+        )
+    });
+    let (lock_file, _) = if temp {
+        utils::load_or_create_temp_lock_file()?
+    } else {
+        utils::load_lock_file()?
+    };
+    Ok(lock_file)
+}
+
 fn repos_from_from_arg(
     from: &FilesFrom,
     passthrough: &[String],
@@ -126,10 +228,17 @@ fn repos_from_from_arg_with_reader(
                 ))
             }
         }
+        // `pez update` upgrades only what has a newer commit, so it records which.
+        Commands::Update(_) => update::last_updated().map(Some),
         Commands::Uninstall(uninstall_args) => {
             if let Some(list) = uninstall_args.plugins.as_ref() {
                 return Ok(Some(list.clone()));
             }
+            if uninstall_args.all {
+                return Ok(Some(
+                    lock_file.plugins.iter().map(|p| p.repo.clone()).collect(),
+                ));
+            }
             if uninstall_args.stdin {
                 let repos = if let Some(reader) = stdin_reader {
                     uninstall::read_plugins_from_reader(reader)?
@@ -148,7 +257,7 @@ fn repos_from_from_arg_with_reader(
 fn build_from_argv(from: &FilesFrom, passthrough: &[String]) -> Vec<String> {
     let subcmd = match from {
         FilesFrom::Install => "install",
-        FilesFrom::Update => "upgrade",
+        FilesFrom::Update => "update",
         FilesFrom::Upgrade => "upgrade",
         FilesFrom::Uninstall => "uninstall",
         FilesFrom::Remove => "uninstall",
@@ -161,10 +270,12 @@ fn build_from_argv(from: &FilesFrom, passthrough: &[String]) -> Vec<String> {
     argv
 }
 
+/// Help and version output, and runs that only report what they would do, leave
+/// every plugin as it was, so there is nothing to source or emit.
 fn should_skip_from_parse(from: &FilesFrom, passthrough: &[String]) -> anyhow::Result<bool> {
     let argv = build_from_argv(from, passthrough);
     match Cli::try_parse_from(argv) {
-        Ok(_) => Ok(false),
+        Ok(parsed) => Ok(changes_nothing(&parsed.command)),
         Err(err) => {
             if is_display_help_or_version(&err) {
                 Ok(true)
@@ -175,6 +286,14 @@ fn should_skip_from_parse(from: &FilesFrom, passthrough: &[String]) -> anyhow::R
     }
 }
 
+fn changes_nothing(command: &Commands) -> bool {
+    match command {
+        Commands::Install(install_args) => install_args.dry_run,
+        Commands::Upgrade(upgrade_args) => upgrade_args.dry_run || upgrade_args.check,
+        _ => false,
+    }
+}
+
 fn is_display_help_or_version(err: &clap::Error) -> bool {
     matches!(
         err.kind(),
@@ -182,18 +301,6 @@ fn is_display_help_or_version(err: &clap::Error) -> bool {
     )
 }
 
-fn parse_plugin_arg(raw: &str) -> anyhow::Result<PluginRepo> {
-    // Try full InstallTarget parsing to allow URLs and @ref, but ignore ref in the lookup.
-    let target = InstallTarget::from_raw(raw.to_string());
-    match target.resolve() {
-        Ok(resolved) => Ok(resolved.plugin_repo),
-        Err(_) => raw
-            .parse::<PluginRepo>()
-            .map_err(|e| anyhow!(e))
-            .context("Failed to parse plugin identifier"),
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -206,13 +313,20 @@ mod tests {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
         let prev_fc = std::env::var_os("__fish_config_dir");
         let prev_pc = std::env::var_os("PEZ_CONFIG_DIR");
+        let prev_pd = std::env::var_os("PEZ_DATA_DIR");
         unsafe {
             std::env::set_var("__fish_config_dir", &env.fish_config_dir);
             std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
         }
         let res = f();
         // restore
         unsafe {
+            if let Some(v) = prev_pd {
+                std::env::set_var("PEZ_DATA_DIR", v);
+            } else {
+                std::env::remove_var("PEZ_DATA_DIR");
+            }
             if let Some(v) = prev_fc {
                 std::env::set_var("__fish_config_dir", v);
             } else {
@@ -227,6 +341,107 @@ mod tests {
         res.expect("test case failed");
     }
 
+    #[test]
+    fn render_paths_with_state_dirs_appends_owner_state_dir() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let prev = std::env::var_os("PEZ_DATA_DIR");
+        unsafe {
+            std::env::set_var("PEZ_DATA_DIR", "/data");
+        }
+        let repo: PluginRepo = "owner/pkg".parse().unwrap();
+        let lock = LockFile {
+            version: 1,
+            plugins: vec![Plugin {
+                name: "pkg".into(),
+                repo: repo.clone(),
+                source: repo.default_remote_source(),
+                commit_sha: "abc".into(),
+                files: vec![PluginFile {
+                    dir: TargetDir::ConfD,
+                    name: "a.fish".into(),
+                }],
+                ..Default::default()
+            }],
+        };
+        let config_dir = std::path::Path::new("/fish");
+        let lines = render_paths_with_state_dirs(
+            &[config_dir.join("conf.d/a.fish")],
+            &lock,
+            config_dir,
+            None,
+        );
+        let missing = render_paths_with_state_dirs(
+            &[config_dir.join("conf.d/x.fish")],
+            &lock,
+            config_dir,
+            None,
+        );
+        unsafe {
+            match prev {
+                Some(v) => std::env::set_var("PEZ_DATA_DIR", v),
+                None => std::env::remove_var("PEZ_DATA_DIR"),
+            }
+        }
+        assert_eq!(
+            lines.unwrap(),
+            vec!["/fish/conf.d/a.fish\t/data/.state/owner/pkg".to_string()]
+        );
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn render_paths_with_state_dirs_lists_event_names() {
+        let mut env = TestEnvironmentSetup::new();
+        std::fs::write(
+            &env.config_path,
+            "[[plugins]]\nrepo = \"owner/omf\"\nevents = [\"omf_install\", \"omf_uninstall\"]\n",
+        )
+        .unwrap();
+        let conf_d = |name: &str| PluginFile {
+            dir: TargetDir::ConfD,
+            name: name.into(),
+        };
+        let lock = LockFile {
+            version: 1,
+            plugins: vec![
+                Plugin {
+                    name: "omf".into(),
+                    repo: "owner/omf".parse().unwrap(),
+                    files: vec![conf_d("a.fish"), conf_d("b.fish")],
+                    ..Default::default()
+                },
+                Plugin {
+                    name: "plain".into(),
+                    repo: "owner/plain".parse().unwrap(),
+                    files: vec![conf_d("c.fish")],
+                    ..Default::default()
+                },
+            ],
+        };
+        env.setup_lock_file(lock.clone());
+        let conf_d_dir = env.fish_config_dir.join("conf.d");
+        let paths: Vec<PathBuf> = ["a.fish", "b.fish", "c.fish"]
+            .iter()
+            .map(|name| conf_d_dir.join(name))
+            .collect();
+
+        with_env(&env, || {
+            let lines = render_paths_with_state_dirs(
+                &paths,
+                &lock,
+                &env.fish_config_dir,
+                Some(&utils::Event::Install),
+            )?;
+            let events: Vec<&str> = lines
+                .iter()
+                .map(|line| line.split('\t').nth(2).unwrap())
+                .collect();
+            // Overrides replace the stem names and fire once, after the plugin's last file.
+            assert_eq!(events, vec!["", "omf_install", "c_install"]);
+            Ok(())
+        });
+    }
+
     #[test]
     fn lists_conf_d_paths_sorted_and_deduped() {
         let mut env = TestEnvironmentSetup::new();
@@ -256,6 +471,7 @@ mod tests {
                         name: "noop.fish".into(),
                     },
                 ],
+                ..Default::default()
             }],
         };
         env.setup_lock_file(lock);
@@ -269,9 +485,11 @@ mod tests {
             plugins: Some(vec!["owner/pkg@v1".into()]),
             all: false,
             dir: FilesDir::ConfD,
+            missing: false,
             format: FilesFormat::Paths,
             from: None,
             passthrough: vec![],
+            with_state_dir: false,
         };
 
         with_env(&env, || {
@@ -287,6 +505,67 @@ mod tests {
         });
     }
 
+    #[test]
+    fn lists_missing_paths_for_functions_dir() {
+        let mut env = TestEnvironmentSetup::new();
+        let repo = PluginRepo {
+            host: None,
+            owner: "owner".into(),
+            repo: "pkg".into(),
+        };
+        let file = |dir, name: &str| PluginFile {
+            dir,
+            name: name.into(),
+        };
+        env.setup_lock_file(LockFile {
+            version: 1,
+            plugins: vec![Plugin {
+                name: "pkg".into(),
+                repo: repo.clone(),
+                source: repo.default_remote_source(),
+                commit_sha: "abc".into(),
+                files: vec![
+                    file(TargetDir::Functions, "present.fish"),
+                    file(TargetDir::Functions, "gone.fish"),
+                    file(TargetDir::Completions, "gone.fish"),
+                ],
+                ..Default::default()
+            }],
+        });
+        let functions = env.fish_config_dir.join(TargetDir::Functions.as_str());
+        std::fs::create_dir_all(&functions).unwrap();
+        std::fs::write(functions.join("present.fish"), "").unwrap();
+
+        let mut args = FilesArgs {
+            plugins: Some(vec!["owner/pkg".into()]),
+            all: false,
+            dir: FilesDir::Functions,
+            missing: false,
+            format: FilesFormat::Paths,
+            from: None,
+            passthrough: vec![],
+            with_state_dir: false,
+        };
+
+        with_env(&env, || {
+            assert_eq!(
+                collect_paths(&args)?,
+                vec![functions.join("gone.fish"), functions.join("present.fish")]
+            );
+            args.missing = true;
+            assert_eq!(collect_paths(&args)?, vec![functions.join("gone.fish")]);
+            args.dir = FilesDir::All;
+            assert_eq!(
+                collect_paths(&args)?,
+                vec![
+                    env.fish_config_dir.join("completions/gone.fish"),
+                    functions.join("gone.fish")
+                ]
+            );
+            Ok(())
+        });
+    }
+
     #[test]
     fn errors_without_plugins_and_not_all() {
         let mut env = TestEnvironmentSetup::new();
@@ -298,9 +577,11 @@ mod tests {
             plugins: None,
             all: false,
             dir: FilesDir::All,
+            missing: false,
             format: FilesFormat::Paths,
             from: None,
             passthrough: vec![],
+            with_state_dir: false,
         };
         with_env(&env, || {
             let err = collect_paths(&args).expect_err("should fail");
@@ -328,6 +609,7 @@ mod tests {
                     dir: TargetDir::ConfD,
                     name: "a.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
         let confd = env.fish_config_dir.join(TargetDir::ConfD.as_str());
@@ -338,9 +620,11 @@ mod tests {
             plugins: None,
             all: false,
             dir: FilesDir::ConfD,
+            missing: false,
             format: FilesFormat::Paths,
             from: Some(FilesFrom::Install),
             passthrough: vec!["--force".into(), "owner/pkg@v1".into()],
+            with_state_dir: false,
         };
 
         with_env(&env, || {
@@ -370,6 +654,7 @@ mod tests {
                     dir: TargetDir::ConfD,
                     name: "a.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
         let confd = env.fish_config_dir.join(TargetDir::ConfD.as_str());
@@ -380,9 +665,11 @@ mod tests {
             plugins: None,
             all: false,
             dir: FilesDir::ConfD,
+            missing: false,
             format: FilesFormat::Paths,
             from: Some(FilesFrom::Install),
             passthrough: vec![],
+            with_state_dir: false,
         };
 
         with_env(&env, || {
@@ -393,6 +680,97 @@ mod tests {
         });
     }
 
+    #[test]
+    fn from_install_temp_reads_temp_lock() {
+        let mut env = TestEnvironmentSetup::new();
+        env.setup_lock_file(LockFile {
+            version: 1,
+            plugins: vec![],
+        });
+        LockFile {
+            version: 1,
+            plugins: vec![Plugin {
+                name: "trial".into(),
+                repo: "owner/trial".parse().unwrap(),
+                files: vec![PluginFile {
+                    dir: TargetDir::ConfD,
+                    name: "trial.fish".into(),
+                }],
+                ..Default::default()
+            }],
+        }
+        .save(&env.data_dir.join(".temp-lock.toml"))
+        .unwrap();
+        let args = FilesArgs {
+            plugins: None,
+            all: false,
+            dir: FilesDir::ConfD,
+            missing: false,
+            format: FilesFormat::Paths,
+            from: Some(FilesFrom::Install),
+            passthrough: vec!["--temp".into(), "owner/trial".into()],
+            with_state_dir: false,
+        };
+
+        with_env(&env, || {
+            assert_eq!(
+                collect_paths(&args)?,
+                vec![env.fish_config_dir.join("conf.d/trial.fish")]
+            );
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn from_update_lists_only_updated_plugins() {
+        let mut env = TestEnvironmentSetup::new();
+        let conf_d = |name: &str| PluginFile {
+            dir: TargetDir::ConfD,
+            name: name.into(),
+        };
+        env.setup_lock_file(LockFile {
+            version: 1,
+            plugins: vec![
+                Plugin {
+                    repo: "owner/moved".parse().unwrap(),
+                    source: "https://github.com/owner/moved".into(),
+                    files: vec![conf_d("moved.fish")],
+                    ..Default::default()
+                },
+                Plugin {
+                    repo: "owner/current".parse().unwrap(),
+                    source: "https://github.com/owner/current".into(),
+                    files: vec![conf_d("current.fish")],
+                    ..Default::default()
+                },
+            ],
+        });
+        let args = FilesArgs {
+            plugins: None,
+            all: false,
+            dir: FilesDir::ConfD,
+            missing: false,
+            format: FilesFormat::Paths,
+            from: Some(FilesFrom::Update),
+            passthrough: vec![],
+            with_state_dir: false,
+        };
+
+        with_env(&env, || {
+            // Nothing is listed before the first update.
+            assert!(collect_paths(&args)?.is_empty());
+            std::fs::write(
+                env.data_dir.join(update::LAST_UPDATE_FILE_NAME),
+                r#"["owner/moved"]"#,
+            )?;
+            assert_eq!(
+                collect_paths(&args)?,
+                vec![env.fish_config_dir.join("conf.d/moved.fish")]
+            );
+            Ok(())
+        });
+    }
+
     #[test]
     fn from_uninstall_with_stdin_reads_reader() {
         let repo = PluginRepo {
@@ -417,6 +795,7 @@ mod tests {
                         dir: TargetDir::ConfD,
                         name: "a.fish".into(),
                     }],
+                    ..Default::default()
                 },
                 Plugin {
                     name: "other".into(),
@@ -424,6 +803,7 @@ mod tests {
                     source: other.default_remote_source(),
                     commit_sha: "def".into(),
                     files: vec![],
+                    ..Default::default()
                 },
             ],
         };
@@ -441,6 +821,33 @@ mod tests {
         assert_eq!(repos[0].as_str(), "owner/pkg");
     }
 
+    #[test]
+    fn from_uninstall_all_uses_lock() {
+        let lock_file = LockFile {
+            version: 1,
+            plugins: vec![
+                Plugin {
+                    repo: "owner/pkg".parse().unwrap(),
+                    ..Default::default()
+                },
+                Plugin {
+                    repo: "owner/other".parse().unwrap(),
+                    ..Default::default()
+                },
+            ],
+        };
+        for passthrough in [
+            vec!["--all".to_string()],
+            vec!["--all".into(), "--yes".into()],
+        ] {
+            let repos = repos_from_from_arg(&FilesFrom::Uninstall, &passthrough, &lock_file)
+                .unwrap()
+                .unwrap();
+            let names: Vec<String> = repos.iter().map(PluginRepo::as_str).collect();
+            assert_eq!(names, vec!["owner/pkg", "owner/other"]);
+        }
+    }
+
     #[test]
     fn from_help_returns_none() {
         let lock_file = LockFile {
@@ -458,6 +865,50 @@ mod tests {
         assert!(res);
     }
 
+    #[test]
+    fn from_install_dry_run_lists_nothing() {
+        let mut env = TestEnvironmentSetup::new();
+        env.setup_lock_file(LockFile {
+            version: 1,
+            plugins: vec![Plugin {
+                name: "pkg".into(),
+                repo: "owner/pkg".parse().unwrap(),
+                files: vec![PluginFile {
+                    dir: TargetDir::ConfD,
+                    name: "a.fish".into(),
+                }],
+                ..Default::default()
+            }],
+        });
+        let mut args = FilesArgs {
+            plugins: None,
+            all: false,
+            dir: FilesDir::ConfD,
+            missing: false,
+            format: FilesFormat::Paths,
+            from: Some(FilesFrom::Install),
+            passthrough: vec!["--dry-run".into()],
+            with_state_dir: false,
+        };
+
+        with_env(&env, || {
+            assert!(collect_paths(&args)?.is_empty());
+            args.passthrough = vec!["--dry-run".into(), "owner/pkg".into()];
+            assert!(collect_paths(&args)?.is_empty());
+            args.passthrough = vec!["owner/pkg".into()];
+            assert_eq!(collect_paths(&args)?.len(), 1);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn should_skip_from_parse_for_upgrade_dry_run_and_check() {
+        for flag in ["--dry-run", "--check"] {
+            assert!(should_skip_from_parse(&FilesFrom::Upgrade, &[flag.into()]).unwrap());
+        }
+        assert!(!should_skip_from_parse(&FilesFrom::Upgrade, &["owner/pkg".into()]).unwrap());
+    }
+
     #[test]
     fn should_skip_from_parse_errors_on_invalid_args() {
         let err = should_skip_from_parse(&FilesFrom::Install, &["--nope".into()]);
@@ -483,6 +934,7 @@ mod tests {
                     dir: TargetDir::ConfD,
                     name: "a.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
         let confd = env.fish_config_dir.join(TargetDir::ConfD.as_str());
@@ -493,14 +945,21 @@ mod tests {
             plugins: Some(vec!["owner/pkg".into()]),
             all: false,
             dir: FilesDir::ConfD,
+            missing: false,
             format: FilesFormat::Json,
             from: None,
             passthrough: vec![],
+            with_state_dir: false,
         };
 
         with_env(&env, || {
-            let paths = run(&args).unwrap();
-            let json = render_paths_json(&paths).unwrap();
+            let paths = run(&args, &RuntimeOptions::default()).unwrap();
+            let json = render_paths_structured(
+                &paths,
+                output::Structured::Json,
+                output::JsonStyle::Pretty,
+            )
+            .unwrap();
             let paths: Vec<String> = serde_json::from_str(&json).unwrap();
             assert_eq!(paths.len(), 1);
             assert!(paths[0].ends_with("conf.d/a.fish"));
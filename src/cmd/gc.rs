@@ -0,0 +1,487 @@
+use crate::{
+    backup,
+    cli::GcArgs,
+    cmd::{cache, prune},
+    git, journal,
+    lock_file::{self, LockFile, Plugin},
+    models::TargetDir,
+    utils,
+};
+
+use anyhow::Context;
+use console::Emoji;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, path,
+};
+use tracing::info;
+
+/// What `pez gc` removes, found by [`plan`].
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct GcPlan {
+    /// Clones in the data dir that no lock file records.
+    pub(crate) clones: Vec<path::PathBuf>,
+    /// Lock entries whose clone (or local source) and installed files are all gone.
+    pub(crate) entries: Vec<String>,
+    /// Files in the fish config dir that the lock file before the last change recorded
+    /// as plugin files, still identical to the plugin's copy, that no lock entry owns or
+    /// disables now.
+    pub(crate) files: Vec<path::PathBuf>,
+}
+
+impl GcPlan {
+    fn is_empty(&self) -> bool {
+        self.clones.is_empty() && self.entries.is_empty() && self.files.is_empty()
+    }
+}
+
+/// Remove orphaned clones, dead lock entries, and plugin files left behind in the fish
+/// config dir.
+pub(crate) fn run(args: &GcArgs) -> anyhow::Result<GcPlan> {
+    let data_dir = utils::load_pez_data_dir()?;
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    // Without the lock file every clone and file would look orphaned.
+    let (mut lock_file, lock_file_path) =
+        utils::load_lock_file().context("pez gc needs a readable pez-lock.toml")?;
+    let temp_lock_file = utils::load_or_create_temp_lock_file()
+        .map(|(lock, _)| lock)
+        .unwrap_or_else(|_| lock_file::init());
+    let previous_lock_file = previous_lock_file().unwrap_or_else(lock_file::init);
+
+    let gc = plan(
+        &data_dir,
+        &fish_config_dir,
+        &lock_file,
+        &temp_lock_file,
+        &previous_lock_file,
+    );
+    if gc.is_empty() {
+        info!("{}Nothing to collect.", Emoji("🎉 ", ""));
+        return Ok(gc);
+    }
+    report(&gc);
+    if args.dry_run {
+        info!(
+            "{}Dry run completed. Nothing has been removed.",
+            Emoji("🧪 ", "")
+        );
+        return Ok(gc);
+    }
+    if !args.yes && !prune::confirm_removal()? {
+        anyhow::bail!("{}Garbage collection aborted.", Emoji("🚧 ", ""));
+    }
+
+    let _backup = (!args.no_backup)
+        .then(|| backup::start_run("gc", args.backup_dir.as_deref()))
+        .transpose()?;
+    for clone in &gc.clones {
+        fs::remove_dir_all(clone)?;
+        cache::remove_empty_parents(clone, &data_dir);
+    }
+    if !gc.entries.is_empty() {
+        lock_file
+            .plugins
+            .retain(|plugin| !gc.entries.contains(&plugin.repo.as_str()));
+        lock_file.save(&lock_file_path)?;
+    }
+    for file in &gc.files {
+        backup::remove_file(file)?;
+    }
+    info!(
+        "{}Removed {} clone(s), {} lock entries, and {} file(s).",
+        Emoji("🧹 ", ""),
+        gc.clones.len(),
+        gc.entries.len(),
+        gc.files.len()
+    );
+    Ok(gc)
+}
+
+/// pez-lock.toml as it was before the last command recorded for `pez rollback`.
+fn previous_lock_file() -> Option<LockFile> {
+    let journal = journal::load(&journal::journal_path().ok()?).ok()??;
+    lock_file::parse(journal.lock.as_deref()?, journal::JOURNAL_FILE_NAME).ok()
+}
+
+fn plan(
+    data_dir: &path::Path,
+    fish_config_dir: &path::Path,
+    lock_file: &LockFile,
+    temp_lock_file: &LockFile,
+    previous_lock_file: &LockFile,
+) -> GcPlan {
+    let lock_files = [lock_file, temp_lock_file];
+    let clones = cache::unused_clones(data_dir, &lock_files);
+    let entries = lock_file
+        .plugins
+        .iter()
+        .filter(|plugin| is_dead(plugin, data_dir, fish_config_dir))
+        .map(|plugin| plugin.repo.as_str())
+        .collect();
+
+    // Disabled paths may hold the user's own replacement, so they count as owned.
+    let owned: BTreeSet<path::PathBuf> = lock_files
+        .iter()
+        .flat_map(|lock_file| &lock_file.plugins)
+        .flat_map(|plugin| &plugin.files)
+        .chain(
+            [lock_file, temp_lock_file, previous_lock_file]
+                .iter()
+                .flat_map(|lock_file| &lock_file.plugins)
+                .flat_map(|plugin| &plugin.disabled_files),
+        )
+        .map(|file| file.get_path(fish_config_dir))
+        .collect();
+    // A path the plugin had backed up holds the user's original again once the plugin
+    // is gone, and a file that no longer matches the plugin's copy was edited or replaced
+    // since, so only exact leftovers are collected.
+    let files = previous_lock_file
+        .plugins
+        .iter()
+        .flat_map(|plugin| {
+            let restored: BTreeSet<path::PathBuf> = plugin
+                .backups
+                .iter()
+                .map(|backup| fish_config_dir.join(backup.dir.as_str()).join(&backup.name))
+                .collect();
+            let shipped = shipped_sources(plugin, data_dir, fish_config_dir);
+            plugin
+                .files
+                .iter()
+                .map(|file| file.get_path(fish_config_dir))
+                .filter(move |path| {
+                    path.is_file()
+                        && !restored.contains(path)
+                        && shipped
+                            .get(path)
+                            .is_some_and(|source| same_content(path, source))
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|path| !owned.contains(path))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    GcPlan {
+        clones,
+        entries,
+        files,
+    }
+}
+
+/// Installed path to the file in the plugin's clone (or local source) it is copied from.
+fn shipped_sources(
+    plugin: &Plugin,
+    data_dir: &path::Path,
+    fish_config_dir: &path::Path,
+) -> BTreeMap<path::PathBuf, path::PathBuf> {
+    let source = if git::is_local_source(&plugin.source) {
+        path::PathBuf::from(&plugin.source)
+    } else {
+        data_dir.join(plugin.repo.as_str())
+    };
+    utils::plugin_source_files(&source, plugin)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(dir, rel, src)| (fish_config_dir.join(dir.as_str()).join(rel), src))
+        .collect()
+}
+
+fn same_content(a: &path::Path, b: &path::Path) -> bool {
+    matches!((fs::read(a), fs::read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Nothing of the plugin is left: no clone (or local source directory) and none of its
+/// recorded files.
+fn is_dead(plugin: &Plugin, data_dir: &path::Path, fish_config_dir: &path::Path) -> bool {
+    let source = if git::is_local_source(&plugin.source) {
+        path::PathBuf::from(&plugin.source)
+    } else {
+        data_dir.join(plugin.repo.as_str())
+    };
+    !source.exists()
+        && plugin
+            .files
+            .iter()
+            .all(|file| !file.get_path(fish_config_dir).exists())
+}
+
+/// `(dir, relative path)` of the files under a clone's `functions/`, `completions/`,
+/// `conf.d/`, and `themes/` directories.
+pub(crate) fn shipped_files(clone: &path::Path) -> Vec<(TargetDir, path::PathBuf)> {
+    let mut files = Vec::new();
+    for dir in TargetDir::all() {
+        let root = clone.join(dir.as_str());
+        for entry in walkdir::WalkDir::new(&root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+        {
+            if let Ok(rel) = entry.path().strip_prefix(&root) {
+                files.push((dir.clone(), rel.to_path_buf()));
+            }
+        }
+    }
+    files
+}
+
+fn report(gc: &GcPlan) {
+    if !gc.clones.is_empty() {
+        info!("{}Clones no lock file records:", Emoji("🐟 ", ""));
+        for clone in &gc.clones {
+            info!(
+                "  - {} ({})",
+                clone.display(),
+                cache::format_bytes(cache::dir_size(clone))
+            );
+        }
+    }
+    if !gc.entries.is_empty() {
+        info!(
+            "{}Lock entries with no clone and no installed files:",
+            Emoji("🐟 ", "")
+        );
+        for entry in &gc.entries {
+            info!("  - {entry}");
+        }
+    }
+    if !gc.files.is_empty() {
+        info!("{}Plugin files no plugin owns:", Emoji("🐟 ", ""));
+        for file in &gc.files {
+            info!("  - {}", file.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lock_file::PluginFile,
+        models::PluginRepo,
+        tests_support::{env::TestEnvironmentSetup, log::env_lock},
+    };
+
+    fn make_clone(data_dir: &path::Path, repo: &str, files: &[&str]) {
+        let clone = data_dir.join(repo);
+        fs::create_dir_all(clone.join(".git")).unwrap();
+        for file in files {
+            let path = clone.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "echo hi\n").unwrap();
+        }
+    }
+
+    fn locked(repo: &str, files: &[(TargetDir, &str)]) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "abc1234".into(),
+            files: files
+                .iter()
+                .map(|(dir, name)| PluginFile {
+                    dir: dir.clone(),
+                    name: name.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn record_previous_lock(env: &TestEnvironmentSetup, plugins: Vec<Plugin>) {
+        let previous = LockFile {
+            version: 2,
+            plugins,
+        };
+        journal::save(
+            &env.data_dir.join(journal::JOURNAL_FILE_NAME),
+            &journal::Journal {
+                time: 0,
+                command: "uninstall".into(),
+                lock: Some(toml::to_string(&previous).unwrap()),
+                config: None,
+            },
+        )
+        .unwrap();
+    }
+
+    fn gc_with_env(env: &TestEnvironmentSetup, args: &GcArgs) -> anyhow::Result<GcPlan> {
+        let _lock = env_lock().lock().unwrap();
+        let keys = ["PEZ_CONFIG_DIR", "PEZ_DATA_DIR", "__fish_config_dir"];
+        let saved: Vec<_> = keys
+            .iter()
+            .map(|key| (*key, std::env::var_os(key)))
+            .collect();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+            std::env::set_var("__fish_config_dir", &env.fish_config_dir);
+        }
+        let result = run(args);
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn gc_removes_orphaned_clones_entries_and_files() {
+        let mut env = TestEnvironmentSetup::new();
+        make_clone(&env.data_dir, "owner/kept", &["functions/kept.fish"]);
+        make_clone(&env.data_dir, "owner/orphan", &["functions/left.fish"]);
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![
+                locked("owner/kept", &[(TargetDir::Functions, "kept.fish")]),
+                locked("owner/gone", &[(TargetDir::ConfD, "gone.fish")]),
+            ],
+        });
+        // The last command dropped owner/orphan from the lock but left its file behind.
+        record_previous_lock(
+            &env,
+            vec![locked(
+                "owner/orphan",
+                &[(TargetDir::Functions, "left.fish")],
+            )],
+        );
+        let functions = env.fish_config_dir.join(TargetDir::Functions.as_str());
+        fs::create_dir_all(&functions).unwrap();
+        for name in ["kept.fish", "left.fish", "mine.fish"] {
+            fs::write(functions.join(name), "echo hi\n").unwrap();
+        }
+
+        let args = GcArgs {
+            dry_run: true,
+            yes: true,
+            backup_dir: None,
+            no_backup: true,
+        };
+        let planned = gc_with_env(&env, &args).unwrap();
+        assert_eq!(
+            planned,
+            GcPlan {
+                clones: vec![env.data_dir.join("owner/orphan")],
+                entries: vec!["owner/gone".into()],
+                files: vec![functions.join("left.fish")],
+            }
+        );
+        assert!(env.data_dir.join("owner/orphan").exists());
+
+        let collected = gc_with_env(
+            &env,
+            &GcArgs {
+                dry_run: false,
+                ..args
+            },
+        )
+        .unwrap();
+        assert_eq!(collected, planned);
+        assert!(!env.data_dir.join("owner/orphan").exists());
+        assert!(!functions.join("left.fish").exists());
+        assert!(functions.join("kept.fish").exists());
+        assert!(functions.join("mine.fish").exists());
+        let lock = lock_file::load(&env.lock_file_path).unwrap();
+        let repos: Vec<String> = lock.plugins.iter().map(|p| p.repo.as_str()).collect();
+        assert_eq!(repos, vec!["owner/kept"]);
+    }
+
+    #[test]
+    fn gc_refuses_to_run_without_a_lock_file() {
+        let env = TestEnvironmentSetup::new();
+        make_clone(&env.data_dir, "owner/unknown", &["functions/unknown.fish"]);
+
+        let args = GcArgs {
+            dry_run: false,
+            yes: true,
+            backup_dir: None,
+            no_backup: true,
+        };
+        assert!(gc_with_env(&env, &args).is_err());
+        assert!(env.data_dir.join("owner/unknown").exists());
+    }
+
+    #[test]
+    fn gc_keeps_user_file_at_disabled_path() {
+        let mut env = TestEnvironmentSetup::new();
+        make_clone(&env.data_dir, "owner/alpha", &["conf.d/alpha.fish"]);
+        let installed = locked("owner/alpha", &[(TargetDir::ConfD, "alpha.fish")]);
+        // `disable_files = ["conf.d/alpha.fish"]` moved the file out of `files`.
+        let disabled = Plugin {
+            disabled_files: installed.files.clone(),
+            files: vec![],
+            ..installed.clone()
+        };
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![disabled],
+        });
+        record_previous_lock(&env, vec![installed]);
+        let conf_d = env.fish_config_dir.join(TargetDir::ConfD.as_str());
+        fs::create_dir_all(&conf_d).unwrap();
+        fs::write(conf_d.join("alpha.fish"), "set -g mine 1\n").unwrap();
+
+        let args = GcArgs {
+            dry_run: false,
+            yes: true,
+            backup_dir: None,
+            no_backup: true,
+        };
+        assert_eq!(gc_with_env(&env, &args).unwrap(), GcPlan::default());
+        assert!(conf_d.join("alpha.fish").exists());
+    }
+
+    #[test]
+    fn gc_keeps_user_files_restored_or_changed_after_uninstall() {
+        let mut env = TestEnvironmentSetup::new();
+        make_clone(
+            &env.data_dir,
+            "owner/gamma",
+            &["functions/gamma.fish", "functions/edited.fish"],
+        );
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![],
+        });
+        // gamma.fish overwrote the user's own file, which uninstall put back.
+        let mut installed = locked(
+            "owner/gamma",
+            &[
+                (TargetDir::Functions, "gamma.fish"),
+                (TargetDir::Functions, "edited.fish"),
+            ],
+        );
+        installed.backups = vec![lock_file::PluginBackup {
+            dir: TargetDir::Functions,
+            name: "gamma.fish".into(),
+            backup: env
+                .data_dir
+                .join(".backups/gamma.fish")
+                .display()
+                .to_string(),
+        }];
+        record_previous_lock(&env, vec![installed]);
+        let functions = env.fish_config_dir.join(TargetDir::Functions.as_str());
+        fs::create_dir_all(&functions).unwrap();
+        // Restored from backup: kept even though it happens to match the plugin's copy.
+        fs::write(functions.join("gamma.fish"), "echo hi\n").unwrap();
+        fs::write(functions.join("edited.fish"), "echo mine\n").unwrap();
+
+        let args = GcArgs {
+            dry_run: true,
+            yes: true,
+            backup_dir: None,
+            no_backup: true,
+        };
+        let planned = gc_with_env(&env, &args).unwrap();
+        assert!(planned.files.is_empty());
+        assert_eq!(planned.clones, vec![env.data_dir.join("owner/gamma")]);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    cli::ImportArgs,
+    cmd::migrate::{self, MigratedEntry},
+    models::InstallTarget,
+    output::outln,
+    runtime::RuntimeOptions,
+    utils,
+};
+
+use console::Emoji;
+use std::{
+    fs,
+    io::{self, Read},
+};
+use tracing::{info, warn};
+
+/// Add every install target listed in a file (or stdin) to pez.toml.
+pub(crate) async fn run(args: &ImportArgs, options: &RuntimeOptions) -> anyhow::Result<()> {
+    let content = if args.file.as_os_str() == "-" {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        content
+    } else {
+        fs::read_to_string(&args.file)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", args.file.display()))?
+    };
+
+    let entries = migrate::dedup_entries(parse_entries(&content));
+    if entries.is_empty() {
+        warn!("{}No valid entries to import.", Emoji("⚠ ", ""));
+        return Ok(());
+    }
+
+    let (mut cfg, cfg_path) = utils::load_or_create_config()?;
+    let planned = migrate::merge_entries(&mut cfg, &entries, false, args.dry_run);
+
+    if args.dry_run {
+        info!("{}Dry run: planned updates to pez.toml", Emoji("🧪 ", ""));
+    } else if !planned.is_empty() {
+        cfg.save(&cfg_path)?;
+        info!("{}Updated {}", Emoji("✅ ", ""), cfg_path.display());
+    }
+    for entry in &planned {
+        outln!("  - {}", migrate::describe_spec(&entry.spec));
+    }
+    if planned.is_empty() {
+        info!("{}Nothing to update.", Emoji("ℹ ", ""));
+    }
+
+    if !args.dry_run && args.install && !planned.is_empty() {
+        info!("{}Installing imported plugins...", Emoji("🚀 ", ""));
+        migrate::install_entries(&planned, options).await?;
+    } else if !args.dry_run && !planned.is_empty() {
+        info!("Run `pez install` to install the imported plugins.");
+    }
+    Ok(())
+}
+
+/// One install target per line, as accepted by `pez install`. Blank lines and `#`
+/// comments are skipped; invalid targets are reported and skipped.
+fn parse_entries(content: &str) -> Vec<MigratedEntry> {
+    let mut entries = Vec::new();
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        match InstallTarget::from_raw(trimmed).resolve() {
+            Ok(resolved) => entries.push(MigratedEntry::new(trimmed.to_string(), resolved)),
+            Err(err) => warn!(
+                "{}Skipping unrecognized entry: {} ({err})",
+                Emoji("⚠ ", ""),
+                trimmed
+            ),
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PluginSource;
+
+    #[test]
+    fn parse_entries_skips_comments_and_invalid_targets() {
+        let entries = parse_entries(
+            "# plugins\n\nowner/one@v1.0.0\n  https://gitlab.com/group/two  \nowner/bad@commit:xyz\nowner/one\n",
+        );
+        let repos: Vec<String> = entries
+            .iter()
+            .map(|entry| entry.spec.get_plugin_repo().unwrap().as_str())
+            .collect();
+        assert_eq!(
+            repos,
+            vec!["owner/one", "gitlab.com/group/two", "owner/one"]
+        );
+
+        let unique = migrate::dedup_entries(entries);
+        assert_eq!(unique.len(), 2);
+        assert!(matches!(
+            &unique[0].spec.source,
+            PluginSource::Repo { version: None, .. }
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    cli::{InfoArgs, InfoFormat, PathArgs},
+    config::Config,
+    lock_file::Plugin,
+    models::PluginRepo,
+    output::{self, outln},
+    resolver::{self, Selection},
+    runtime::RuntimeOptions,
+    update_cache, utils,
+};
+
+use serde_derive::Serialize;
+use std::path;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct PluginInfo {
+    pub(crate) name: String,
+    pub(crate) repo: String,
+    pub(crate) source: String,
+    /// What pez.toml asks for, e.g. `tag:v6` or `origin/HEAD` when it sets no ref.
+    pub(crate) selector: String,
+    pub(crate) commit: String,
+    pub(crate) updated_at: Option<u64>,
+    /// Local path or clone under the pez data dir.
+    pub(crate) path: path::PathBuf,
+    /// Installed files, relative to the fish config dir.
+    pub(crate) files: Vec<String>,
+    pub(crate) disabled: bool,
+    /// Installed with `pez install --temp` rather than from pez.toml.
+    pub(crate) temp: bool,
+}
+
+/// Show what pez knows about one installed plugin.
+pub(crate) fn run(args: &InfoArgs, options: &RuntimeOptions) -> anyhow::Result<PluginInfo> {
+    let (plugin, temp) = find_installed(&args.plugin)?;
+    let config = utils::load_config().ok().map(|(config, _)| config);
+    let info = info(&plugin, config.as_ref(), &utils::load_pez_data_dir()?, temp);
+
+    match args.format {
+        Some(InfoFormat::Json) => outln!("{}", output::to_json(&info, options.json_style)?),
+        None => outln!("{}", render_plain(&info, update_cache::now_secs())),
+    }
+    Ok(info)
+}
+
+/// Print where an installed plugin's checkout lives, for `cd (pez path tide)`.
+pub(crate) fn path(args: &PathArgs) -> anyhow::Result<path::PathBuf> {
+    let (plugin, _) = find_installed(&args.plugin)?;
+    let dir = plugin.checkout_dir(&utils::load_pez_data_dir()?);
+    outln!("{}", dir.display());
+    Ok(dir)
+}
+
+/// The plugin from pez-lock.toml, or from the `install --temp` record, and whether it
+/// came from the latter.
+fn find_installed(repo: &PluginRepo) -> anyhow::Result<(Plugin, bool)> {
+    let lock_file = utils::load_lock_file()
+        .map(|(lock_file, _)| lock_file)
+        .unwrap_or_else(|_| crate::lock_file::init());
+    if let Some(plugin) = lock_file.get_plugin_by_repo(repo) {
+        return Ok((plugin.clone(), false));
+    }
+    let (temp_lock_file, _) = utils::load_or_create_temp_lock_file()?;
+    match temp_lock_file.get_plugin_by_repo(repo) {
+        Some(plugin) => Ok((plugin.clone(), true)),
+        None => anyhow::bail!("Plugin is not installed: {repo}"),
+    }
+}
+
+fn info(plugin: &Plugin, config: Option<&Config>, data_dir: &path::Path, temp: bool) -> PluginInfo {
+    let selection = config
+        .and_then(|config| config.find_plugin_spec(&plugin.repo))
+        .and_then(|spec| spec.to_resolved().ok())
+        .map_or(Selection::DefaultHead, |resolved| {
+            resolver::selection_from_ref_kind(&resolved.ref_kind)
+        });
+    PluginInfo {
+        name: plugin.get_name(),
+        repo: plugin.repo.as_str(),
+        source: plugin.source.clone(),
+        selector: crate::cmd::list::describe_selection(&selection),
+        commit: plugin.commit_sha.clone(),
+        updated_at: plugin.updated_at,
+        path: plugin.checkout_dir(data_dir),
+        files: plugin
+            .files
+            .iter()
+            .map(|file| format!("{}/{}", file.dir.as_str(), file.name))
+            .collect(),
+        disabled: config.is_some_and(|config| config.is_disabled(&plugin.repo)),
+        temp,
+    }
+}
+
+fn render_plain(info: &PluginInfo, now: u64) -> String {
+    let mut flags = Vec::new();
+    if info.disabled {
+        flags.push("disabled");
+    }
+    if info.temp {
+        flags.push("temp");
+    }
+    let mut out = info.repo.clone();
+    if !flags.is_empty() {
+        out.push_str(&format!(" ({})", flags.join(", ")));
+    }
+    out.push_str(&format!(
+        "\n  name:     {}\n  source:   {}\n  selector: {}\n  commit:   {}\n  updated:  {}\n  path:     {}\n  files:    {}",
+        info.name,
+        info.source,
+        info.selector,
+        info.commit,
+        crate::cmd::list::format_age(info.updated_at, now),
+        info.path.display(),
+        info.files.len()
+    ));
+    for file in &info.files {
+        out.push_str(&format!("\n    {file}"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lock_file::PluginFile;
+    use crate::models::TargetDir;
+
+    fn plugin(repo: &str) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "0123456789abcdef".into(),
+            files: vec![PluginFile {
+                dir: TargetDir::Functions,
+                name: "tide.fish".into(),
+            }],
+            updated_at: Some(1_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn info_reads_selector_and_disabled_state_from_config() {
+        let config: Config = toml::from_str(
+            "[[plugins]]\nrepo = \"IlanCosman/tide\"\ntag = \"v6\"\ndisabled = true\n",
+        )
+        .unwrap();
+        let info = info(
+            &plugin("IlanCosman/tide"),
+            Some(&config),
+            path::Path::new("/data"),
+            false,
+        );
+        assert_eq!(info.selector, "tag:v6");
+        assert!(info.disabled);
+        assert_eq!(info.path, path::Path::new("/data/IlanCosman/tide"));
+        assert_eq!(info.files, vec!["functions/tide.fish"]);
+    }
+
+    #[test]
+    fn render_plain_lists_fields_and_files() {
+        let info = info(
+            &plugin("IlanCosman/tide"),
+            None,
+            path::Path::new("/data"),
+            true,
+        );
+        assert_eq!(
+            render_plain(&info, 1_000 + 120),
+            "IlanCosman/tide (temp)\n  name:     tide\n  source:   https://github.com/IlanCosman/tide\n  selector: origin/HEAD\n  commit:   0123456789abcdef\n  updated:  2m ago\n  path:     /data/IlanCosman/tide\n  files:    1\n    functions/tide.fish"
+        );
+    }
+}
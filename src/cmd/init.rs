@@ -1,11 +1,338 @@
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::utils;
-use std::{fs, path};
+use crate::{
+    cli,
+    cmd::{
+        activate, cache, completion, gc,
+        migrate::{self, MigratedEntry},
+    },
+    config, git,
+    models::TargetDir,
+    utils,
+};
+use console::Emoji;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, path,
+};
 
-pub(crate) fn run() -> anyhow::Result<()> {
+/// Name of the managed bootstrap under `conf.d`.
+pub(crate) const BOOTSTRAP_FILE_NAME: &str = "pez_bootstrap.fish";
+/// First line of every bootstrap pez writes; files without it are never replaced.
+const BOOTSTRAP_MARKER: &str = "# Managed by pez";
+
+pub(crate) fn run(args: &cli::InitArgs) -> anyhow::Result<()> {
     let config_dir = utils::load_pez_config_dir()?;
-    create_config(&config_dir)
+    if args.from_existing {
+        return init_from_existing(&config_dir);
+    }
+    if !args.write_bootstrap {
+        return create_config(&config_dir);
+    }
+
+    // Re-running to refresh the bootstrap keeps an existing pez.toml.
+    if config_dir.join("pez.toml").exists() {
+        info!("Keeping existing {}", config_dir.join("pez.toml").display());
+    } else {
+        create_config(&config_dir)?;
+    }
+    let path = write_bootstrap(&utils::load_fish_config_dir()?)?;
+    info!(
+        "Wrote {}; open a new shell or run `source {}`",
+        path.display(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Write (or replace) `conf.d/pez_bootstrap.fish` for the running pez version.
+fn write_bootstrap(fish_config_dir: &path::Path) -> anyhow::Result<path::PathBuf> {
+    let conf_d = fish_config_dir.join("conf.d");
+    fs::create_dir_all(&conf_d)?;
+    let path = conf_d.join(BOOTSTRAP_FILE_NAME);
+    if let Ok(existing) = fs::read_to_string(&path)
+        && !existing.starts_with(BOOTSTRAP_MARKER)
+    {
+        anyhow::bail!(
+            "{} exists and was not written by pez; move it away to let pez manage it",
+            path.display()
+        );
+    }
+    fs::write(&path, bootstrap_script())?;
+    Ok(path)
+}
+
+pub(crate) fn bootstrap_script() -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    let completions = String::from_utf8_lossy(&completion::build_fish_completion()).into_owned();
+    format!(
+        r#"{BOOTSTRAP_MARKER} {version}. Regenerate with `pez init --write-bootstrap`; local edits are overwritten.
+if not status is-interactive; or not type -q pez
+    exit
+end
+
+set -g __pez_bootstrap_version "{version}"
+
+# Completions
+{completions}
+# Activation wrapper (same as `pez activate fish`)
+{wrapper}
+# Update check: runs once, at the first prompt, and only compares versions.
+function __pez_bootstrap_check --on-event fish_prompt
+    functions -e __pez_bootstrap_check
+    set -l current (command pez --version 2>/dev/null | string replace -r '^pez ' '')
+    if test -n "$current"; and test "$current" != "$__pez_bootstrap_version"
+        echo "pez: bootstrap is for $__pez_bootstrap_version but pez is $current; run `pez init --write-bootstrap`" >&2
+    end
+end
+"#,
+        wrapper = activate::fish_script().trim_matches('\n'),
+    )
+}
+
+/// Plugins found in a fish config set up by fisher, Oh My Fish, or by hand.
+struct Existing {
+    /// Each plugin with the installed files attributed to it.
+    plugins: Vec<(MigratedEntry, Vec<path::PathBuf>)>,
+    /// Installed files no plugin could be attributed to.
+    unattributed: Vec<path::PathBuf>,
+}
+
+/// Write a pez.toml declaring the plugins already present in the fish config.
+fn init_from_existing(config_dir: &path::Path) -> anyhow::Result<()> {
+    let config_path = config_dir.join("pez.toml");
+    if config_path.exists() {
+        anyhow::bail!("{} already exists", config_path.display());
+    }
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let clone_roots: Vec<path::PathBuf> = [
+        utils::load_omf_dir().map(|dir| dir.join("pkg")),
+        utils::load_omf_dir().map(|dir| dir.join("themes")),
+        utils::load_fisher_v3_dir(),
+    ]
+    .into_iter()
+    .filter_map(Result::ok)
+    .collect();
+    let existing = scan_existing(&fish_config_dir, &clone_roots);
+
+    if existing.plugins.is_empty() {
+        warn!(
+            "{}No existing plugins found in {}",
+            Emoji("⚠ ", ""),
+            fish_config_dir.display()
+        );
+        return create_config(config_dir);
+    }
+    info!("{}Found plugins:", Emoji("🐟 ", ""));
+    for (entry, files) in &existing.plugins {
+        info!(
+            "  - {} ({} file(s))",
+            migrate::describe_spec(&entry.spec),
+            files.len()
+        );
+    }
+    if !existing.unattributed.is_empty() {
+        warn!(
+            "{}Files no plugin could be attributed to (left out of pez.toml):",
+            Emoji("⚠ ", "")
+        );
+        for file in &existing.unattributed {
+            warn!("  - {}", file.display());
+        }
+    }
+
+    let config = config::Config {
+        plugins: Some(
+            existing
+                .plugins
+                .into_iter()
+                .map(|(entry, _)| entry.spec)
+                .collect(),
+        ),
+        settings: Default::default(),
+        aliases: Default::default(),
+        doctor: Default::default(),
+    };
+    fs::create_dir_all(config_dir)?;
+    fs::write(
+        &config_path,
+        format!(
+            "# Generated by `pez init --from-existing` from {}.\n# Review the entries, then run `pez install`.\n\n{}",
+            fish_config_dir.display(),
+            toml::to_string(&config)?
+        ),
+    )?;
+    info!("Created {}", config_path.display());
+    Ok(())
+}
+
+/// Find plugins from fisher's `fish_plugins` and its per-plugin file lists in
+/// `fish_variables`, and from git clones under `clone_roots` (Oh My Fish packages,
+/// fisher 3). Installed files are attributed from fisher's lists, or by matching a file a
+/// clone ships.
+fn scan_existing(fish_config_dir: &path::Path, clone_roots: &[path::PathBuf]) -> Existing {
+    let mut entries: Vec<MigratedEntry> = fs::read_to_string(fish_config_dir.join("fish_plugins"))
+        .map(|content| migrate::parse_fisher_plugins(&content))
+        .unwrap_or_default();
+    let mut attributed: BTreeMap<String, BTreeSet<path::PathBuf>> = BTreeMap::new();
+    // Files fisher installed for itself: known, but not a plugin for pez.toml.
+    let mut fisher_files: BTreeSet<path::PathBuf> = BTreeSet::new();
+
+    let variables = fs::read_to_string(fish_config_dir.join("fish_variables")).unwrap_or_default();
+    for (plugin, files) in fisher_file_lists(&variables) {
+        if plugin.eq_ignore_ascii_case("jorgebucaran/fisher") {
+            fisher_files.extend(files);
+            continue;
+        }
+        let Some(entry) = migrate::parse_fisher_plugins(&plugin).into_iter().next() else {
+            continue;
+        };
+        let Ok(repo) = entry.spec.get_plugin_repo() else {
+            continue;
+        };
+        attributed.entry(repo.as_str()).or_default().extend(files);
+        entries.push(entry);
+    }
+
+    let installed = installed_files(fish_config_dir);
+    for clone in clone_roots.iter().flat_map(|root| cache::find_clones(root)) {
+        let Some(entry) =
+            git::origin_url(&clone).and_then(|url| migrate::resolve_entry(url.clone(), &url))
+        else {
+            continue;
+        };
+        let Ok(repo) = entry.spec.get_plugin_repo() else {
+            continue;
+        };
+        let files = attributed.entry(repo.as_str()).or_default();
+        for (dir, rel) in gc::shipped_files(&clone) {
+            let path = fish_config_dir.join(dir.as_str()).join(rel);
+            if installed.contains(&path) {
+                files.insert(path);
+            }
+        }
+        entries.push(entry);
+    }
+
+    let plugins: Vec<(MigratedEntry, Vec<path::PathBuf>)> = migrate::dedup_entries(entries)
+        .into_iter()
+        .map(|entry| {
+            let files = entry
+                .spec
+                .get_plugin_repo()
+                .ok()
+                .and_then(|repo| attributed.get(&repo.as_str()))
+                .map(|files| {
+                    files
+                        .iter()
+                        .filter(|file| installed.contains(*file))
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default();
+            (entry, files)
+        })
+        .collect();
+    let known: BTreeSet<&path::PathBuf> = plugins
+        .iter()
+        .flat_map(|(_, files)| files)
+        .chain(&fisher_files)
+        .collect();
+    let bootstrap = fish_config_dir.join("conf.d").join(BOOTSTRAP_FILE_NAME);
+    let unattributed = installed
+        .iter()
+        .filter(|file| !known.contains(file) && **file != bootstrap)
+        .cloned()
+        .collect();
+    Existing {
+        plugins,
+        unattributed,
+    }
+}
+
+/// Files under the fish config's `functions/`, `completions/`, `conf.d/`, and `themes/`.
+fn installed_files(fish_config_dir: &path::Path) -> BTreeSet<path::PathBuf> {
+    TargetDir::all()
+        .iter()
+        .flat_map(|dir| {
+            walkdir::WalkDir::new(fish_config_dir.join(dir.as_str()))
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| entry.into_path())
+        })
+        .collect()
+}
+
+/// fisher 4 records each plugin's installed files in a `_fisher_<plugin>_files`
+/// universal variable. Returns `(plugin, files)` from a `fish_variables` file.
+fn fisher_file_lists(variables: &str) -> Vec<(String, Vec<path::PathBuf>)> {
+    variables
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("SETUVAR ")?;
+            let rest = rest.strip_prefix("--export ").unwrap_or(rest);
+            let (name, value) = rest.split_once(':')?;
+            let plugin = name.strip_prefix("_fisher_")?.strip_suffix("_files")?;
+            let files = unescape_uvar(value)
+                .split('\x1e')
+                .filter(|file| !file.is_empty())
+                .map(path::PathBuf::from)
+                .collect();
+            Some((unescape_var_name(plugin), files))
+        })
+        .collect()
+}
+
+/// Undo fish's `\xHH` escapes in a `fish_variables` value.
+fn unescape_uvar(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let raw = value.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'\\' {
+            if raw.get(i + 1) == Some(&b'x')
+                && let Some(byte) = raw
+                    .get(i + 2..i + 4)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                bytes.push(byte);
+                i += 4;
+                continue;
+            }
+            if let Some(&next) = raw.get(i + 1) {
+                bytes.push(next);
+                i += 2;
+                continue;
+            }
+        }
+        bytes.push(raw[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Undo `string escape --style=var`, which spells every other character as `_HH_`.
+fn unescape_var_name(name: &str) -> String {
+    let mut bytes = Vec::with_capacity(name.len());
+    let raw = name.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'_'
+            && raw.get(i + 3) == Some(&b'_')
+            && let Some(byte) = std::str::from_utf8(&raw[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            bytes.push(byte);
+            i += 4;
+            continue;
+        }
+        bytes.push(raw[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
 }
 
 fn create_config(config_dir: &path::Path) -> anyhow::Result<()> {
@@ -46,6 +373,126 @@ mod tests {
     use crate::tests_support::log::env_lock;
     use std::fs;
 
+    #[test]
+    fn write_bootstrap_replaces_only_managed_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = write_bootstrap(temp_dir.path()).unwrap();
+        assert_eq!(
+            path,
+            temp_dir.path().join("conf.d").join(BOOTSTRAP_FILE_NAME)
+        );
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with(&format!(
+            "{BOOTSTRAP_MARKER} {}.",
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert!(contents.contains("complete -c pez"));
+        assert!(contents.contains("__pez_activate_version"));
+        assert!(contents.contains("function __pez_bootstrap_check --on-event fish_prompt"));
+
+        // A previous pez-written bootstrap is replaced.
+        fs::write(&path, format!("{BOOTSTRAP_MARKER} 0.0.1.\nold\n")).unwrap();
+        write_bootstrap(temp_dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), contents);
+
+        // A user's own file is left alone.
+        fs::write(&path, "# mine\n").unwrap();
+        let err = write_bootstrap(temp_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("was not written by pez"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# mine\n");
+    }
+
+    #[test]
+    fn scan_existing_attributes_files_from_fisher_and_clones() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let fish = temp_dir.path().join("fish");
+        for file in [
+            "functions/a.fish",
+            "conf.d/c.fish",
+            "functions/b.fish",
+            "functions/fisher.fish",
+            "functions/mine.fish",
+            "conf.d/pez_bootstrap.fish",
+        ] {
+            let path = fish.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        fs::write(
+            fish.join("fish_plugins"),
+            "jorgebucaran/fisher\nowner/alpha\nowner/listed\n",
+        )
+        .unwrap();
+        let escaped = |path: path::PathBuf| path.display().to_string().replace('.', "\\x2e");
+        fs::write(
+            fish.join("fish_variables"),
+            format!(
+                "# This file contains fish universal variable definitions.\n\
+                 SETUVAR _fisher_jorgebucaran_2F_fisher_files:{}\n\
+                 SETUVAR _fisher_owner_2F_alpha_files:{}\\x1e{}\n\
+                 SETUVAR _fisher_plugins:jorgebucaran/fisher\\x1eowner/alpha\n",
+                escaped(fish.join("functions/fisher.fish")),
+                escaped(fish.join("functions/a.fish")),
+                escaped(fish.join("conf.d/c.fish")),
+            ),
+        )
+        .unwrap();
+
+        // An Oh My Fish package cloned from owner/beta.
+        let pkg = temp_dir.path().join("omf/pkg");
+        let clone = pkg.join("beta");
+        fs::create_dir_all(clone.join("functions")).unwrap();
+        fs::write(clone.join("functions/b.fish"), "").unwrap();
+        git2::Repository::init(&clone)
+            .unwrap()
+            .remote("origin", "https://github.com/owner/beta")
+            .unwrap();
+
+        let existing = scan_existing(&fish, &[pkg, temp_dir.path().join("missing")]);
+        let plugins: Vec<(String, Vec<path::PathBuf>)> = existing
+            .plugins
+            .iter()
+            .map(|(entry, files)| {
+                (
+                    entry.spec.get_plugin_repo().unwrap().as_str(),
+                    files.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            plugins,
+            vec![
+                (
+                    "owner/alpha".to_string(),
+                    vec![fish.join("conf.d/c.fish"), fish.join("functions/a.fish")]
+                ),
+                ("owner/listed".to_string(), vec![]),
+                (
+                    "owner/beta".to_string(),
+                    vec![fish.join("functions/b.fish")]
+                ),
+            ]
+        );
+        assert_eq!(
+            existing.unattributed,
+            vec![fish.join("functions/mine.fish")]
+        );
+    }
+
+    #[test]
+    fn unescape_handles_fish_variable_and_var_name_escapes() {
+        assert_eq!(
+            unescape_uvar("\\x7e/a\\x2efish\\x1eb"),
+            "~/a.fish\u{1e}b".to_string()
+        );
+        assert_eq!(
+            unescape_var_name("PatrickF1_2F_fzf_2E_fish"),
+            "PatrickF1/fzf.fish"
+        );
+        assert_eq!(unescape_var_name("a_b"), "a_b");
+    }
+
     #[test]
     fn test_create_config() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -98,7 +545,7 @@ mod tests {
             std::env::set_var("PEZ_CONFIG_DIR", &config_dir);
         }
 
-        let result = run();
+        let result = run(&cli::InitArgs::default());
 
         unsafe {
             if let Some(v) = prev_pc {
@@ -1,55 +1,110 @@
 use crate::resolver;
 use crate::{
+    backup,
     cli::InstallArgs,
-    config, git,
+    cmd::{edit, enable},
+    config, git, history,
     lock_file::{LockFile, Plugin},
-    models::TargetDir,
     models::{InstallTarget, PluginRepo, ResolvedInstallTarget},
-    utils,
+    runtime::RuntimeOptions,
+    update_cache, utils, vendor,
 };
 
 use anyhow::Context;
 use console::Emoji;
-use futures::{StreamExt, stream};
-use std::{collections::HashSet, fs, path, sync::Arc};
+use futures::{FutureExt, StreamExt, stream};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, path,
+    sync::Arc,
+};
 use tokio::sync::Mutex;
 use tracing::{debug, info, warn};
 
-pub(crate) async fn run(args: &InstallArgs) -> anyhow::Result<()> {
+pub(crate) async fn run(args: &InstallArgs, options: &RuntimeOptions) -> anyhow::Result<()> {
     info!("{}Starting installation process...", Emoji("🔍 ", ""));
+    let _backup = (!args.dry_run)
+        .then(|| backup::start_run("install", None))
+        .transpose()?;
 
-    handle_installation(args).await?;
+    handle_installation(args, options.jobs()).await?;
 
     Ok(())
 }
 
-async fn handle_installation(args: &InstallArgs) -> anyhow::Result<()> {
-    if let Some(plugins) = &args.plugins {
-        install(plugins, &args.force).await?;
+async fn handle_installation(args: &InstallArgs, jobs: usize) -> anyhow::Result<()> {
+    if args.dry_run {
+        report_plan(&plan(args)?);
+        if args.prune {
+            crate::cmd::prune::prune_unused(crate::cmd::prune::PruneOptions {
+                force: args.force,
+                dry_run: true,
+                yes: args.yes,
+                jobs,
+                except: Vec::new(),
+            })
+            .await?;
+        }
+        info!(
+            "\n{}Dry run completed. Nothing has been installed.",
+            Emoji("🧪 ", "")
+        );
+        return Ok(());
+    }
+    if args.temp
+        && let Some(plugins) = &args.plugins
+    {
+        install_temp(plugins, &args.force, jobs).await?;
+        info!(
+            "\n{}Temporary plugins installed. Remove them with `pez prune --temp`.",
+            Emoji("🧪 ", "")
+        );
+    } else if let Some(plugins) = &args.plugins {
+        install(plugins, &args.force, args.edit, jobs).await?;
         info!(
             "\n{}All specified plugins have been installed successfully!",
             Emoji("🎉 ", "")
         );
     } else {
         install_all(&args.force, &args.prune)?;
+        if args.prune {
+            crate::cmd::prune::prune_unused(crate::cmd::prune::PruneOptions {
+                force: args.force,
+                dry_run: args.dry_run,
+                yes: args.yes,
+                jobs,
+                except: Vec::new(),
+            })
+            .await?;
+        }
     }
 
     Ok(())
 }
 
-async fn install(targets: &[InstallTarget], force: &bool) -> anyhow::Result<()> {
+async fn install(
+    targets: &[InstallTarget],
+    force: &bool,
+    edit: bool,
+    jobs: usize,
+) -> anyhow::Result<()> {
     let (mut config, config_path) = utils::load_or_create_config()?;
-    add_plugins_to_config(&mut config, &config_path, targets)?;
+    ensure_installable(&config.settings, targets)?;
+    let resolved = if edit {
+        edit_staged_plugins(&mut config, &config_path, targets)?
+    } else {
+        add_plugins_to_config(&mut config, &config_path, targets)?;
+        resolve_targets(&config, targets)?
+    };
 
     let (mut lock_file, lock_file_path) = utils::load_or_create_lock_file()?;
 
     let pez_data_dir = utils::load_pez_data_dir()?;
-    let resolved: Vec<ResolvedInstallTarget> = targets
-        .iter()
-        .map(|t| t.resolve())
-        .collect::<anyhow::Result<Vec<_>>>()?;
     let mut new_plugins =
-        clone_plugins(&resolved, *force, lock_file.clone(), &pez_data_dir).await?;
+        clone_plugins(&resolved, *force, lock_file.clone(), &pez_data_dir, jobs).await?;
+    for plugin in &mut new_plugins {
+        plugin.name = config.plugin_name_for_repo(&plugin.repo);
+    }
 
     let new_plugins = sync_plugin_files(&mut new_plugins, &pez_data_dir).await?;
 
@@ -57,6 +112,10 @@ async fn install(targets: &[InstallTarget], force: &bool) -> anyhow::Result<()>
         emit_event(plugin, &utils::Event::Install)?;
     }
 
+    vendor::warn_shadowed(&new_plugins);
+    for plugin in &new_plugins {
+        history::record(history::Action::Install, &plugin.repo, None);
+    }
     lock_file.merge_plugins(new_plugins);
     lock_file.save(&lock_file_path)?;
     info!(
@@ -66,14 +125,60 @@ async fn install(targets: &[InstallTarget], force: &bool) -> anyhow::Result<()>
     Ok(())
 }
 
+async fn install_temp(targets: &[InstallTarget], force: &bool, jobs: usize) -> anyhow::Result<()> {
+    let settings = utils::load_config()
+        .map(|(config, _)| config.settings)
+        .unwrap_or_default();
+    ensure_installable(&settings, targets)?;
+    let managed = utils::load_lock_file().ok().map(|(lock, _)| lock);
+    let (mut temp_lock_file, temp_lock_file_path) = utils::load_or_create_temp_lock_file()?;
+    let pez_data_dir = utils::load_pez_data_dir()?;
+
+    let mut resolved: Vec<ResolvedInstallTarget> = Vec::new();
+    for target in targets {
+        let target = target.resolve()?;
+        if managed
+            .as_ref()
+            .is_some_and(|lock| lock.contains_repo(&target.plugin_repo))
+        {
+            warn!(
+                "{}Skipped: {} is already installed and managed by pez.toml",
+                Emoji("⚠ ", ""),
+                target.plugin_repo
+            );
+            continue;
+        }
+        resolved.push(target);
+    }
+
+    let mut new_plugins = clone_plugins(
+        &resolved,
+        *force,
+        temp_lock_file.clone(),
+        &pez_data_dir,
+        jobs,
+    )
+    .await?;
+    let new_plugins = sync_plugin_files(&mut new_plugins, &pez_data_dir).await?;
+
+    for plugin in &new_plugins {
+        emit_event(plugin, &utils::Event::Install)?;
+    }
+
+    vendor::warn_shadowed(&new_plugins);
+    temp_lock_file.merge_plugins(new_plugins);
+    fs::create_dir_all(&pez_data_dir)?;
+    temp_lock_file.save(&temp_lock_file_path)?;
+    Ok(())
+}
+
 fn emit_event(plugin: &Plugin, event: &utils::Event) -> anyhow::Result<()> {
-    plugin
-        .files
-        .iter()
-        .filter(|f| f.dir == TargetDir::ConfD)
-        .for_each(|f| {
-            let _ = utils::emit_event(&f.name, event);
-        });
+    if let utils::Event::Install = event
+        && let Err(e) = utils::ensure_plugin_state_dir(&plugin.repo)
+    {
+        warn!("{:?}", e);
+    }
+    utils::emit_plugin_events(plugin, event);
 
     Ok(())
 }
@@ -120,6 +225,21 @@ fn cleanup_prepared_remote_repos(plugins: &[Plugin], pez_data_dir: &path::Path)
     }
 }
 
+/// Refuse targets that are not fish plugins (such as pez itself) before anything
+/// is written to pez.toml or cloned.
+fn ensure_installable(
+    settings: &config::Settings,
+    targets: &[InstallTarget],
+) -> anyhow::Result<()> {
+    for target in targets {
+        let repo = target.resolve()?.plugin_repo;
+        if let Some(reason) = settings.non_plugin_reason(&repo) {
+            anyhow::bail!("{repo} is not a fish plugin: {reason}");
+        }
+    }
+    Ok(())
+}
+
 fn add_plugins_to_config(
     config: &mut config::Config,
     config_path: &path::Path,
@@ -140,11 +260,136 @@ fn add_plugins_to_config(
     Ok(())
 }
 
+fn resolve_targets(
+    config: &config::Config,
+    targets: &[InstallTarget],
+) -> anyhow::Result<Vec<ResolvedInstallTarget>> {
+    targets
+        .iter()
+        .map(|t| {
+            let mut resolved = t.resolve()?;
+            if let Some(spec) = config.find_plugin_spec(&resolved.plugin_repo) {
+                resolved.channel = resolver::Channel::from_prerelease(spec.prerelease);
+            }
+            Ok(resolved)
+        })
+        .collect()
+}
+
+/// Write the entries `targets` would add to pez.toml, open the editor on them, and
+/// resolve what to install from the edited file: the entries added (as edited; deleting
+/// one skips it) plus targets pez.toml already declared.
+fn edit_staged_plugins(
+    config: &mut config::Config,
+    config_path: &path::Path,
+    targets: &[InstallTarget],
+) -> anyhow::Result<Vec<ResolvedInstallTarget>> {
+    if !config_path.exists() {
+        config.save(&config_path.to_path_buf())?;
+    }
+    let original = fs::read_to_string(config_path)?;
+    let declared: HashSet<String> = declared_repos(config);
+    let mut staged = config.clone();
+    let mut existing = Vec::new();
+    for target in targets {
+        let resolved = target.resolve()?;
+        if declared.contains(&resolved.plugin_repo.as_str()) {
+            existing.push(target.clone());
+        } else {
+            staged.ensure_plugin_from_resolved(&resolved);
+        }
+    }
+    let mut resolved = resolve_targets(config, &existing)?;
+    if existing.len() == targets.len() {
+        return Ok(resolved);
+    }
+
+    let entries = config.plugins.as_ref().map_or(0, Vec::len);
+    fs::write(
+        config_path,
+        stage_comment(&toml::to_string(&staged)?, entries),
+    )?;
+    edit::edit_staged(config_path, &original)?;
+
+    let (edited, _) = utils::load_config()?;
+    for spec in edited.plugins.iter().flatten() {
+        let repo = spec.get_plugin_repo()?;
+        if declared.contains(&repo.as_str()) || spec.disabled {
+            continue;
+        }
+        if let Some(reason) = edited.settings.non_plugin_reason(&repo) {
+            fs::write(config_path, &original)?;
+            anyhow::bail!("{repo} is not a fish plugin: {reason}");
+        }
+        resolved.push(spec.to_resolved()?);
+    }
+    *config = edited;
+    Ok(resolved)
+}
+
+fn declared_repos(config: &config::Config) -> HashSet<String> {
+    config
+        .plugins
+        .iter()
+        .flatten()
+        .filter_map(|spec| spec.get_plugin_repo().ok())
+        .map(|repo| repo.as_str())
+        .collect()
+}
+
+/// `content` with a comment above the first staged `[[plugins]]` entry, the one after
+/// the `existing` entries.
+fn stage_comment(content: &str, existing: usize) -> String {
+    let Some(line) = edit::plugin_header_line(content, existing) else {
+        return content.to_string();
+    };
+    let mut lines: Vec<&str> = content.lines().collect();
+    lines.insert(
+        line - 1,
+        "# Staged by `pez install --edit`: adjust selectors or names below, or delete an entry to skip it.",
+    );
+    lines.join("\n") + "\n"
+}
+
 enum ExistingRepoPolicy {
     CliInstall,
     InstallAll,
 }
 
+/// What to do about the plugin's clone path before cloning.
+#[derive(Debug, PartialEq)]
+enum ExistingRepoStep {
+    /// Nothing is in the way; clone (or use the local source).
+    Proceed,
+    /// Already installed; leave it alone.
+    Skip,
+    /// Remove the existing clone, then clone again.
+    Replace,
+    /// A clone exists that the lock file does not explain.
+    Reject,
+}
+
+fn existing_repo_step(
+    policy: &ExistingRepoPolicy,
+    locked: bool,
+    force: bool,
+    exists: bool,
+    is_local_source: bool,
+) -> ExistingRepoStep {
+    if !exists {
+        return ExistingRepoStep::Proceed;
+    }
+    match policy {
+        ExistingRepoPolicy::CliInstall if force => ExistingRepoStep::Replace,
+        ExistingRepoPolicy::CliInstall => ExistingRepoStep::Skip,
+        ExistingRepoPolicy::InstallAll if locked && !force => ExistingRepoStep::Skip,
+        ExistingRepoPolicy::InstallAll if is_local_source => ExistingRepoStep::Proceed,
+        ExistingRepoPolicy::InstallAll if force => ExistingRepoStep::Replace,
+        ExistingRepoPolicy::InstallAll => ExistingRepoStep::Reject,
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
 enum PreparedInstall {
     Prepared {
         plugin: Plugin,
@@ -153,19 +398,34 @@ enum PreparedInstall {
     Skipped,
 }
 
+/// Final error listing every plugin whose remote hung, so one slow host does not
+/// hide the others.
+fn timeout_summary(timed_out: &[(PluginRepo, String)]) -> anyhow::Error {
+    let lines: Vec<String> = timed_out
+        .iter()
+        .map(|(repo, reason)| format!("  - {repo}: {reason}"))
+        .collect();
+    anyhow::anyhow!(
+        "{} plugin(s) timed out:\n{}\nRetry later, or raise `clone_timeout` in the [settings] table of pez.toml.",
+        timed_out.len(),
+        lines.join("\n")
+    )
+}
+
 async fn clone_plugins(
     resolved_targets: &[ResolvedInstallTarget],
     force: bool,
     lock_file: LockFile,
     pez_data_dir: &path::Path,
+    jobs: usize,
 ) -> anyhow::Result<Vec<Plugin>> {
     let lock_file = Arc::new(Mutex::new(lock_file));
 
-    let jobs = utils::load_jobs().max(1);
     let prepare_results = stream::iter(resolved_targets.iter().cloned())
         .map(|resolved| {
             let lock_file = Arc::clone(&lock_file);
             let pez_data_dir = pez_data_dir.to_path_buf();
+            let plugin_repo = resolved.plugin_repo.clone();
             async move {
                 let plugin_repo = resolved.plugin_repo.clone();
                 let locked_opt = lock_file
@@ -192,6 +452,7 @@ async fn clone_plugins(
                     PreparedInstall::Skipped => Ok::<Option<Plugin>, anyhow::Error>(None),
                 }
             }
+            .map(move |result| (plugin_repo, result))
         })
         .buffer_unordered(jobs)
         .collect::<Vec<_>>()
@@ -199,15 +460,19 @@ async fn clone_plugins(
 
     let mut prepared_plugins = Vec::new();
     let mut first_err = None;
-    for result in prepare_results {
+    let mut timed_out = Vec::new();
+    for (plugin_repo, result) in prepare_results {
         match result {
             Ok(Some(plugin)) => prepared_plugins.push(plugin),
             Ok(None) => {}
-            Err(err) => {
-                if first_err.is_none() {
-                    first_err = Some(err);
+            Err(err) => match git::timed_out(&err) {
+                Some(timeout) => timed_out.push((plugin_repo, timeout.to_string())),
+                None => {
+                    if first_err.is_none() {
+                        first_err = Some(err);
+                    }
                 }
-            }
+            },
         }
     }
 
@@ -215,6 +480,10 @@ async fn clone_plugins(
         cleanup_prepared_remote_repos(&prepared_plugins, pez_data_dir);
         return Err(err);
     }
+    if !timed_out.is_empty() {
+        cleanup_prepared_remote_repos(&prepared_plugins, pez_data_dir);
+        return Err(timeout_summary(&timed_out));
+    }
 
     Ok(prepared_plugins)
 }
@@ -247,55 +516,58 @@ fn prepare_plugin_from_resolved(
 ) -> anyhow::Result<PreparedInstall> {
     let repo_for_id = resolved.plugin_repo.clone();
     let source_base = resolved.source.clone();
-    let ref_kind = resolved.ref_kind.clone();
     let repo_path = pez_data_dir.join(repo_for_id.as_str());
     let is_local_source = git::is_local_source(&source_base);
+    debug!(
+        target: crate::trace::INSTALL,
+        repo = %repo_for_id,
+        locked = locked_plugin.map(|p| p.commit_sha.as_str()),
+        force,
+        exists = repo_path.exists(),
+        "Preparing install"
+    );
 
-    match existing_repo_policy {
-        ExistingRepoPolicy::CliInstall => {
-            if repo_path.exists() {
-                if force {
-                    handle_existing_repository(&force, &repo_for_id, &repo_path)?;
-                } else {
-                    warn!(
-                        "{}Skipped: {} is already installed. Use --force to reinstall",
-                        Emoji("⚠ ", ""),
-                        repo_for_id
-                    );
-                    return Ok(PreparedInstall::Skipped);
-                }
-            }
-        }
-        ExistingRepoPolicy::InstallAll => {
-            if let Some(_locked) = locked_plugin
-                && repo_path.exists()
-                && !force
-            {
-                info!(
+    match existing_repo_step(
+        &existing_repo_policy,
+        locked_plugin.is_some(),
+        force,
+        repo_path.exists(),
+        is_local_source,
+    ) {
+        ExistingRepoStep::Proceed => {}
+        ExistingRepoStep::Skip => {
+            match existing_repo_policy {
+                ExistingRepoPolicy::CliInstall => warn!(
+                    "{}Skipped: {} is already installed. Use --force to reinstall",
+                    Emoji("⚠ ", ""),
+                    repo_for_id
+                ),
+                ExistingRepoPolicy::InstallAll => info!(
                     "{}Skipped: {} is already installed.",
                     Emoji("⏭️  ", ""),
                     repo_for_id
-                );
-                return Ok(PreparedInstall::Skipped);
-            }
-
-            if repo_path.exists() && !is_local_source {
-                if force {
-                    fs::remove_dir_all(&repo_path).with_context(|| {
-                        format!("failed to remove existing repo at {}", repo_path.display())
-                    })?;
-                } else if locked_plugin.is_none() {
-                    anyhow::bail!(
-                        "Plugin already exists: {} (path: {}). Use --force to reinstall",
-                        repo_for_id,
-                        repo_path.display()
-                    );
-                }
+                ),
             }
+            return Ok(PreparedInstall::Skipped);
         }
+        ExistingRepoStep::Replace => match existing_repo_policy {
+            ExistingRepoPolicy::CliInstall => {
+                handle_existing_repository(&force, &repo_for_id, &repo_path)?
+            }
+            ExistingRepoPolicy::InstallAll => {
+                fs::remove_dir_all(&repo_path).with_context(|| {
+                    format!("failed to remove existing repo at {}", repo_path.display())
+                })?
+            }
+        },
+        ExistingRepoStep::Reject => anyhow::bail!(
+            "Plugin already exists: {} (path: {}). Use --force to reinstall",
+            repo_for_id,
+            repo_path.display()
+        ),
     }
 
-    let repo = if is_local_source {
+    let staged = if is_local_source {
         None
     } else {
         info!(
@@ -305,93 +577,48 @@ fn prepare_plugin_from_resolved(
             repo_path.display()
         );
         ensure_repo_parent(&repo_path)?;
-        let cloned_repo = match git::clone_repository(&source_base, &repo_path) {
-            Ok(repo) => repo,
-            Err(err) => {
-                cleanup_failed_repo(&repo_path);
-                return Err(err).with_context(|| {
-                    format!(
-                        "failed to clone {} into {}",
-                        &source_base,
-                        repo_path.display()
-                    )
-                });
-            }
-        };
-        Some(cloned_repo)
+        remove_stale_staging_dirs(&repo_path);
+        let staging_dir = create_staging_dir(&repo_path)?;
+        let cloned_repo = clone_plugin_repository(&source_base, staging_dir.path(), &repo_for_id)
+            .with_context(|| {
+            format!(
+                "failed to clone {} into {}",
+                &source_base,
+                repo_path.display()
+            )
+        })?;
+        Some((staging_dir, cloned_repo))
     };
+    let repo = staged.as_ref().map(|(_, repo)| repo);
 
     let prepared = (|| -> anyhow::Result<PreparedInstall> {
-        let commit_sha = if let Some(locked) = locked_plugin {
-            if force {
-                if let Some(repo) = &repo {
-                    let sel = resolver::selection_from_ref_kind(&ref_kind);
-                    match git::resolve_selection(repo, &sel) {
-                        std::result::Result::Ok(sha) => sha,
-                        Err(e) => {
-                            warn!(
-                                "Failed to resolve selection: {:?}. Falling back to HEAD.",
-                                e
-                            );
-                            git::get_latest_commit_sha(repo)?
-                        }
-                    }
-                } else {
-                    "local".to_string()
-                }
-            } else {
-                if let Some(repo) = &repo {
-                    info!(
-                        "{}Using pinned commit: {}",
-                        Emoji("🔄 ", ""),
-                        &locked.commit_sha
-                    );
-                    git::checkout_commit(repo, &locked.commit_sha).with_context(|| {
-                        format!(
-                            "failed to checkout pinned commit {} for repository {}",
-                            &locked.commit_sha, &source_base
-                        )
-                    })?;
-                }
-                locked.commit_sha.clone()
+        let commit_sha = match (&repo, locked_plugin) {
+            (Some(repo), Some(locked)) if !force => {
+                info!(
+                    "{}Using pinned commit: {}",
+                    Emoji("🔄 ", ""),
+                    &locked.commit_sha
+                );
+                checkout_install_commit(repo, &source_base, resolved, locked_plugin, force)?
             }
-        } else if is_local_source {
-            info!(
-                "{}Installing from local path: {}",
-                Emoji("📁 ", ""),
-                &source_base
-            );
-            "local".to_string()
-        } else {
-            let repo = repo
-                .as_ref()
-                .ok_or_else(|| anyhow::anyhow!("expected cloned repository for remote source"))?;
-            let sel = resolver::selection_from_ref_kind(&ref_kind);
-            let commit_sha = match git::resolve_selection(repo, &sel) {
-                std::result::Result::Ok(sha) => sha,
-                Err(e) => {
-                    warn!(
-                        "Failed to resolve selection: {:?}. Falling back to HEAD.",
-                        e
-                    );
-                    git::get_latest_commit_sha(repo)?
-                }
-            };
-            if let Err(e) = git::checkout_commit(repo, &commit_sha) {
-                warn!("Failed to detach HEAD to {}: {:?}", &commit_sha, e);
+            (Some(repo), _) => {
+                checkout_install_commit(repo, &source_base, resolved, locked_plugin, force)?
+            }
+            (None, Some(locked)) if !force => locked.commit_sha.clone(),
+            (None, Some(_)) => "local".to_string(),
+            (None, None) if is_local_source => {
+                info!(
+                    "{}Installing from local path: {}",
+                    Emoji("📁 ", ""),
+                    &source_base
+                );
+                "local".to_string()
             }
-            commit_sha
+            (None, None) => anyhow::bail!("expected cloned repository for remote source"),
         };
 
-        if locked_plugin.is_some()
-            && force
-            && let Some(repo) = &repo
-            && let Err(e) = git::checkout_commit(repo, &commit_sha)
-        {
-            warn!("Failed to detach HEAD to {}: {:?}", &commit_sha, e);
-        }
-
         debug!(
+            target: crate::trace::INSTALL,
             repo = %repo_for_id,
             source = %source_base,
             commit = %commit_sha,
@@ -404,6 +631,19 @@ fn prepare_plugin_from_resolved(
             source: source_base.clone(),
             commit_sha,
             files: vec![],
+            backups: locked_plugin
+                .map(|locked| locked.backups.clone())
+                .unwrap_or_default(),
+            // Reinstalling a locked commit keeps the time it was last actually updated.
+            updated_at: match locked_plugin {
+                Some(locked) if !force => locked.updated_at,
+                _ => Some(update_cache::now_secs()),
+            },
+            default_branch: repo
+                .as_ref()
+                .and_then(|repo| git::default_branch(repo))
+                .or_else(|| locked_plugin.and_then(|locked| locked.default_branch.clone())),
+            disabled_files: vec![],
         };
 
         let repo_base = if is_local_source {
@@ -415,11 +655,150 @@ fn prepare_plugin_from_resolved(
         Ok(PreparedInstall::Prepared { plugin, repo_base })
     })();
 
-    if prepared.is_err() && repo.is_some() {
-        cleanup_failed_repo(&repo_path);
+    let prepared = prepared?;
+    if let Some((staging_dir, repo)) = staged {
+        drop(repo);
+        promote_staging_dir(staging_dir, &repo_path)?;
+    }
+
+    Ok(prepared)
+}
+
+/// Check out the commit to install in a fresh clone and return it: the locked commit
+/// unless forcing, otherwise what the selector resolves to (HEAD when it does not
+/// resolve).
+fn checkout_install_commit(
+    repo: &git2::Repository,
+    source: &str,
+    resolved: &ResolvedInstallTarget,
+    locked_plugin: Option<&Plugin>,
+    force: bool,
+) -> anyhow::Result<String> {
+    if let Some(locked) = locked_plugin
+        && !force
+    {
+        git::checkout_commit(repo, &locked.commit_sha).with_context(|| {
+            format!(
+                "failed to checkout pinned commit {} for repository {}",
+                &locked.commit_sha, source
+            )
+        })?;
+        return Ok(locked.commit_sha.clone());
+    }
+
+    let sel = resolver::selection_from_ref_kind(&resolved.ref_kind);
+    let commit_sha = match git::resolve_selection(repo, &sel, resolved.channel) {
+        Ok(sha) => sha,
+        Err(e) => {
+            warn!(
+                "Failed to resolve selection: {:?}. Falling back to HEAD.",
+                e
+            );
+            git::get_latest_commit_sha(repo)?
+        }
+    };
+    if let Err(e) = git::checkout_commit(repo, &commit_sha) {
+        warn!("Failed to detach HEAD to {}: {:?}", &commit_sha, e);
+    }
+    Ok(commit_sha)
+}
+
+/// Clone through the shared object store when `[settings] shared_objects = true`,
+/// falling back to a plain clone if the store cannot be used.
+fn clone_plugin_repository(
+    source: &str,
+    target: &path::Path,
+    plugin_repo: &PluginRepo,
+) -> anyhow::Result<git2::Repository> {
+    let shared = utils::load_config().is_ok_and(|(config, _)| config.settings.shared_objects);
+    if shared {
+        let store = utils::load_pez_shared_objects_dir()?;
+        match git::clone_repository_shared(source, target, &store, &plugin_repo.as_str()) {
+            Ok(repo) => return Ok(repo),
+            Err(e) if git::timed_out(&e).is_some() => return Err(e),
+            Err(e) => {
+                warn!(
+                    "{} {} Shared object store unavailable for {} ({:#}); using a regular clone.",
+                    Emoji("🚧 ", ""),
+                    utils::label_warning(),
+                    plugin_repo,
+                    e
+                );
+                fs::remove_dir_all(target)?;
+                fs::create_dir_all(target)?;
+            }
+        }
+    }
+    git::clone_repository(source, target)
+}
+
+const STAGING_DIR_MARKER: &str = ".tmp-";
+
+fn staging_dir_prefix(repo_path: &path::Path) -> Option<String> {
+    let name = repo_path.file_name()?.to_str()?;
+    Some(format!("{name}{STAGING_DIR_MARKER}"))
+}
+
+/// Create an empty sibling directory (`<repo>.tmp-<rand>`) to clone into.
+/// The directory is removed on drop unless it is promoted into place.
+fn create_staging_dir(repo_path: &path::Path) -> anyhow::Result<tempfile::TempDir> {
+    let parent = repo_path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("invalid repository path: {}", repo_path.display()))?;
+    let prefix = staging_dir_prefix(repo_path)
+        .ok_or_else(|| anyhow::anyhow!("invalid repository path: {}", repo_path.display()))?;
+    tempfile::Builder::new()
+        .prefix(&prefix)
+        .tempdir_in(parent)
+        .with_context(|| {
+            format!(
+                "failed to create staging directory for {}",
+                repo_path.display()
+            )
+        })
+}
+
+fn promote_staging_dir(
+    staging_dir: tempfile::TempDir,
+    repo_path: &path::Path,
+) -> anyhow::Result<()> {
+    let staging_path = staging_dir.keep();
+    if let Err(err) = fs::rename(&staging_path, repo_path) {
+        cleanup_failed_repo(&staging_path);
+        return Err(err).with_context(|| {
+            format!(
+                "failed to move {} into {}",
+                staging_path.display(),
+                repo_path.display()
+            )
+        });
     }
+    Ok(())
+}
 
-    prepared
+/// Leftover staging directories come from interrupted clones and are never
+/// referenced by the lockfile, so they can be removed unconditionally.
+fn remove_stale_staging_dirs(repo_path: &path::Path) {
+    let (Some(parent), Some(prefix)) = (repo_path.parent(), staging_dir_prefix(repo_path)) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let is_stale = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.starts_with(&prefix));
+        if is_stale {
+            debug!(
+                target: crate::trace::INSTALL,
+                path = %entry.path().display(),
+                "Removing stale staging directory"
+            );
+            cleanup_failed_repo(&entry.path());
+        }
+    }
 }
 
 enum CopyStrategy {
@@ -486,6 +865,7 @@ async fn sync_plugin_files(
     Ok(new_plugins.to_vec())
 }
 
+#[allow(clippy::large_enum_variant)]
 enum InstallOutcome {
     Installed(Plugin),
     Skipped,
@@ -541,9 +921,10 @@ fn install_resolved_target(
     Ok(InstallOutcome::Installed(plugin))
 }
 
-fn install_all(force: &bool, prune: &bool) -> anyhow::Result<()> {
+pub(crate) fn install_all(force: &bool, prune: &bool) -> anyhow::Result<()> {
     let (mut lock_file, lock_file_path) = utils::load_or_create_lock_file()?;
     let (config, _) = utils::load_config()?;
+    crate::cmd::prune::prune_temp_installs(false)?;
     let pez_data_dir = utils::load_pez_data_dir()?;
     let fish_config_dir = utils::load_fish_config_dir()?;
 
@@ -557,11 +938,23 @@ fn install_all(force: &bool, prune: &bool) -> anyhow::Result<()> {
 
     // Track destination paths we've populated to detect duplicates across plugins
     let mut dest_paths: HashSet<path::PathBuf> = HashSet::new();
+    // Hung remotes are skipped so the remaining plugins still install.
+    let mut timed_out = Vec::new();
 
     for plugin_spec in plugin_specs.iter() {
         let resolved = plugin_spec.to_resolved()?;
         let repo_for_id = resolved.plugin_repo.clone();
-        let outcome = install_resolved_target(
+        if plugin_spec.disabled {
+            info!(
+                "{} {} Skipping disabled plugin {} (run `pez enable {}` to install it)",
+                Emoji("🚧 ", ""),
+                utils::label_info(),
+                repo_for_id,
+                repo_for_id
+            );
+            continue;
+        }
+        let outcome = match install_resolved_target(
             plugin_spec,
             &resolved,
             lock_file.get_plugin_by_repo(&repo_for_id),
@@ -569,8 +962,39 @@ fn install_all(force: &bool, prune: &bool) -> anyhow::Result<()> {
             &pez_data_dir,
             &fish_config_dir,
             &mut dest_paths,
-        )?;
+        ) {
+            Ok(outcome) => outcome,
+            Err(err) => match git::timed_out(&err) {
+                Some(timeout) => {
+                    warn!(
+                        "{} {} Skipping {}: {}",
+                        Emoji("🚧 ", ""),
+                        utils::label_warning(),
+                        repo_for_id,
+                        timeout
+                    );
+                    timed_out.push((repo_for_id, timeout.to_string()));
+                    continue;
+                }
+                None => return Err(err),
+            },
+        };
+        if let InstallOutcome::Skipped = outcome
+            && let Some(locked) = lock_file.get_plugin_by_repo(&repo_for_id)
+            && let Some(updated) = enable::apply_disable_files(
+                plugin_spec,
+                locked,
+                &lock_file,
+                &pez_data_dir,
+                &fish_config_dir,
+            )?
+        {
+            lock_file.upsert_plugin_by_repo(updated)?;
+            lock_file.save(&lock_file_path)?;
+        }
         if let InstallOutcome::Installed(plugin) = outcome {
+            vendor::warn_shadowed(std::slice::from_ref(&plugin));
+            history::record(history::Action::Install, &plugin.repo, None);
             if let Err(e) = lock_file.upsert_plugin_by_repo(plugin) {
                 warn!("Failed to update lock file entry: {:?}", e);
             }
@@ -589,94 +1013,329 @@ fn install_all(force: &bool, prune: &bool) -> anyhow::Result<()> {
         .cloned()
         .collect::<Vec<Plugin>>();
 
-    if !ignored_lock_file_plugins.is_empty() {
-        if *prune {
-            for plugin in ignored_lock_file_plugins {
-                info!("{}Removing plugin: {}", Emoji("🐟 ", ""), &plugin.name);
-                let repo_path = utils::load_pez_data_dir()?.join(plugin.repo.as_str());
-                if repo_path.exists() {
-                    fs::remove_dir_all(&repo_path)?;
-                } else {
-                    let path_display = repo_path.display();
-                    warn!(
-                        "{}Repository directory at {} does not exist.",
-                        Emoji("🚧 ", ""),
-                        path_display
-                    );
-
-                    if !force {
-                        info!(
-                            "{}Detected plugin files based on pez-lock.toml:",
-                            Emoji("📄 ", ""),
-                        );
-                        let fish_config_dir = utils::load_fish_config_dir()?;
-
-                        plugin.files.iter().for_each(|file| {
-                            let dest_path =
-                                fish_config_dir.join(file.dir.as_str()).join(&file.name);
-                            info!("   - {}", dest_path.display());
-                        });
-                        info!("If you want to remove these files, use the --force flag.");
-                        continue;
-                    }
-                }
-
-                info!(
-                    "{}Removing plugin files based on pez-lock.toml:",
-                    Emoji("🗑️  ", ""),
-                );
-
-                emit_event(&plugin, &utils::Event::Uninstall)?;
-
-                let fish_config_dir = utils::load_fish_config_dir()?;
-                for file in &plugin.files {
-                    let dest_path = fish_config_dir.join(file.dir.as_str()).join(&file.name);
-                    if dest_path.exists()
-                        && let Err(e) = fs::remove_file(&dest_path)
-                    {
-                        warn!("Failed to remove {}: {:?}", dest_path.display(), e);
-                    }
-                }
-                lock_file.remove_plugin(&plugin.source);
-                if let Err(e) = lock_file.save(&lock_file_path) {
-                    warn!("Failed to save lock file: {:?}", e);
-                }
-            }
-        } else {
-            info!(
-                "{} The following plugins are in pez-lock.toml but not in pez.toml:",
-                crate::utils::label_notice()
-            );
-            for plugin in ignored_lock_file_plugins {
-                info!("  - {}", plugin.name);
-            }
-            info!("If you want to remove them completely, please run:");
-            info!("  pez install --prune");
-            info!("or:");
-            info!("  pez prune");
+    // With --prune the caller hands these to the shared prune service instead.
+    if !ignored_lock_file_plugins.is_empty() && !*prune {
+        info!(
+            "{} The following plugins are in pez-lock.toml but not in pez.toml:",
+            crate::utils::label_notice()
+        );
+        for plugin in ignored_lock_file_plugins {
+            info!("  - {}", plugin.name);
         }
+        info!("If you want to remove them completely, please run:");
+        info!("  pez install --prune");
+        info!("or:");
+        info!("  pez prune");
+    }
+    if !timed_out.is_empty() {
+        return Err(timeout_summary(&timed_out));
     }
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use config::{PluginSource, PluginSpec};
+/// One plugin in the plan `pez install --dry-run` prints.
+#[derive(Debug, PartialEq)]
+pub(crate) struct PlannedInstall {
+    pub(crate) repo: PluginRepo,
+    pub(crate) action: PlannedAction,
+}
 
-    use super::*;
-    use crate::lock_file::PluginFile;
-    use crate::tests_support::env::TestEnvironmentSetup;
-    use std::os::unix::fs::PermissionsExt;
-    use std::path::Path;
+#[derive(Debug, PartialEq)]
+pub(crate) enum PlannedAction {
+    /// Left alone, with the reason.
+    Skip(String),
+    /// The install would stop with this error.
+    Fail(String),
+    Install {
+        source: String,
+        /// Cloned into the data directory; false for local sources.
+        clone: bool,
+        /// What the selector resolves to (`local` for local sources).
+        commit: String,
+        files: Vec<PlannedFile>,
+    },
+}
 
-    struct EnvOverride {
-        entries: Vec<(&'static str, Option<std::ffi::OsString>)>,
-    }
+#[derive(Debug, PartialEq)]
+pub(crate) struct PlannedFile {
+    pub(crate) dest: path::PathBuf,
+    pub(crate) note: Option<FileNote>,
+}
 
-    impl EnvOverride {
-        fn new(keys: &[&'static str]) -> Self {
-            let entries = keys
-                .iter()
+#[derive(Debug, PartialEq)]
+pub(crate) enum FileNote {
+    /// Listed in the entry's `disable_files`; not copied.
+    Disabled,
+    /// Byte-identical to the file another plugin installs there; shared.
+    Shared(PluginRepo),
+    /// Another plugin installs (or would install) a different file there.
+    Collision(PluginRepo),
+    /// Replaces a file no plugin owns, which is backed up first.
+    Displaces,
+}
+
+/// Work out what `pez install` would clone, check out, and copy without writing to
+/// pez.toml, the lock file, the data directory, or the fish config directory. Remote
+/// plugins are cloned into a temporary directory to resolve their commit and files.
+pub(crate) fn plan(args: &InstallArgs) -> anyhow::Result<Vec<PlannedInstall>> {
+    let (config, targets, policy) = match &args.plugins {
+        Some(plugins) => {
+            let config = utils::load_config()
+                .map(|(config, _)| config)
+                .unwrap_or_else(|_| config::init());
+            ensure_installable(&config.settings, plugins)?;
+            let targets = resolve_targets(&config, plugins)?
+                .into_iter()
+                .map(Ok)
+                .collect();
+            (config, targets, ExistingRepoPolicy::CliInstall)
+        }
+        None => {
+            let (config, _) = utils::load_config()?;
+            let targets = config
+                .plugins
+                .iter()
+                .flatten()
+                .map(|spec| {
+                    let resolved = spec.to_resolved()?;
+                    Ok(if spec.disabled {
+                        Err(resolved.plugin_repo)
+                    } else {
+                        Ok(resolved)
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            (config, targets, ExistingRepoPolicy::InstallAll)
+        }
+    };
+    let lock_file = utils::load_lock_file()
+        .map(|(lock, _)| lock)
+        .unwrap_or_else(|_| crate::lock_file::init());
+    let pez_data_dir = utils::load_pez_data_dir()?;
+    let fish_config_dir = utils::load_fish_config_dir()?;
+
+    let owned: HashMap<path::PathBuf, PluginRepo> = lock_file
+        .plugins
+        .iter()
+        .flat_map(|plugin| {
+            plugin
+                .files
+                .iter()
+                .map(|file| (file.get_path(&fish_config_dir), plugin.repo.clone()))
+        })
+        .collect();
+    let mut planned_dests: HashMap<path::PathBuf, (PluginRepo, path::PathBuf)> = HashMap::new();
+
+    let mut planned = Vec::new();
+    for target in targets {
+        let resolved = match target {
+            Ok(resolved) => resolved,
+            Err(repo) => {
+                planned.push(PlannedInstall {
+                    repo,
+                    action: PlannedAction::Skip("disabled in pez.toml".to_string()),
+                });
+                continue;
+            }
+        };
+        let repo = resolved.plugin_repo.clone();
+        let action = plan_target(
+            &resolved,
+            lock_file.get_plugin_by_repo(&repo),
+            args.force,
+            &policy,
+            &pez_data_dir,
+            &fish_config_dir,
+            config.find_plugin_spec(&repo),
+            &owned,
+            &mut planned_dests,
+        );
+        planned.push(PlannedInstall { repo, action });
+    }
+    Ok(planned)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn plan_target(
+    resolved: &ResolvedInstallTarget,
+    locked_plugin: Option<&Plugin>,
+    force: bool,
+    policy: &ExistingRepoPolicy,
+    pez_data_dir: &path::Path,
+    fish_config_dir: &path::Path,
+    spec: Option<&config::PluginSpec>,
+    owned: &HashMap<path::PathBuf, PluginRepo>,
+    planned_dests: &mut HashMap<path::PathBuf, (PluginRepo, path::PathBuf)>,
+) -> PlannedAction {
+    let repo_path = pez_data_dir.join(resolved.plugin_repo.as_str());
+    let is_local_source = git::is_local_source(&resolved.source);
+    match existing_repo_step(
+        policy,
+        locked_plugin.is_some(),
+        force,
+        repo_path.exists(),
+        is_local_source,
+    ) {
+        ExistingRepoStep::Proceed | ExistingRepoStep::Replace => {}
+        ExistingRepoStep::Skip => {
+            return PlannedAction::Skip("already installed; use --force to reinstall".into());
+        }
+        ExistingRepoStep::Reject => {
+            return PlannedAction::Fail(format!(
+                "a clone already exists at {} but pez-lock.toml has no entry for it; use --force to reinstall",
+                repo_path.display()
+            ));
+        }
+    }
+
+    let checkout = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(e) => return PlannedAction::Fail(format!("{e:#}")),
+    };
+    let (repo_base, commit) = if is_local_source {
+        let commit = match locked_plugin {
+            Some(locked) if !force => locked.commit_sha.clone(),
+            _ => "local".to_string(),
+        };
+        (path::PathBuf::from(&resolved.source), commit)
+    } else {
+        let commit = git::clone_repository(&resolved.source, checkout.path()).and_then(|repo| {
+            checkout_install_commit(&repo, &resolved.source, resolved, locked_plugin, force)
+        });
+        match commit {
+            Ok(commit) => (checkout.path().to_path_buf(), commit),
+            Err(e) => return PlannedAction::Fail(format!("{e:#}")),
+        }
+    };
+
+    let plugin = Plugin {
+        name: resolved.plugin_repo.repo.clone(),
+        repo: resolved.plugin_repo.clone(),
+        source: resolved.source.clone(),
+        commit_sha: commit.clone(),
+        files: vec![],
+        backups: vec![],
+        updated_at: None,
+        default_branch: None,
+        disabled_files: vec![],
+    };
+    let sources = match utils::plugin_source_files(&repo_base, &plugin) {
+        Ok(sources) => sources,
+        Err(e) => return PlannedAction::Fail(format!("{e:#}")),
+    };
+    let mut files = Vec::new();
+    for (dir, rel, src) in sources {
+        let dest = fish_config_dir.join(dir.as_str()).join(&rel);
+        let name = rel.to_string_lossy();
+        // The copy already at `dest` (or planned for it) that another plugin provides.
+        let other = planned_dests
+            .get(&dest)
+            .cloned()
+            .or_else(|| match owned.get(&dest) {
+                Some(owner) if owner != &plugin.repo => Some((owner.clone(), dest.clone())),
+                _ => None,
+            });
+        let note = if spec.is_some_and(|spec| spec.disables(&dir, &name)) {
+            Some(FileNote::Disabled)
+        } else if let Some((other, other_src)) = other {
+            if utils::same_contents(&src, &other_src) {
+                Some(FileNote::Shared(other))
+            } else {
+                Some(FileNote::Collision(other))
+            }
+        } else if dest.exists() && !owned.contains_key(&dest) {
+            Some(FileNote::Displaces)
+        } else {
+            None
+        };
+        if note != Some(FileNote::Disabled) {
+            planned_dests
+                .entry(dest.clone())
+                .or_insert_with(|| (plugin.repo.clone(), src));
+        }
+        files.push(PlannedFile { dest, note });
+    }
+
+    PlannedAction::Install {
+        source: resolved.source.clone(),
+        clone: !is_local_source,
+        commit,
+        files,
+    }
+}
+
+fn report_plan(planned: &[PlannedInstall]) {
+    for PlannedInstall { repo, action } in planned {
+        match action {
+            PlannedAction::Skip(reason) => {
+                info!("{}Skip {}: {}", Emoji("⏭️  ", ""), repo, reason);
+            }
+            PlannedAction::Fail(reason) => {
+                warn!(
+                    "{} {} {}: {}",
+                    Emoji("❌ ", ""),
+                    utils::label_error(),
+                    repo,
+                    reason
+                );
+            }
+            PlannedAction::Install {
+                source,
+                clone,
+                commit,
+                files,
+            } => {
+                let verb = if *clone { "Clone" } else { "Install from" };
+                info!("\n{}{} {} at {}", Emoji("🔗 ", ""), verb, source, commit);
+                if files.is_empty() {
+                    warn!(
+                        "{} {} {} provides no plugin files",
+                        Emoji("⚠ ", ""),
+                        utils::label_warning(),
+                        repo
+                    );
+                }
+                for file in files {
+                    let dest = file.dest.display();
+                    match &file.note {
+                        None => info!("   - {dest}"),
+                        Some(FileNote::Disabled) => info!("   - {dest} (disable_files; skipped)"),
+                        Some(FileNote::Shared(other)) => {
+                            info!("   - {dest} (identical to {other}, shared)")
+                        }
+                        Some(FileNote::Displaces) => {
+                            info!("   - {dest} (replaces an existing file, backed up first)")
+                        }
+                        Some(FileNote::Collision(other)) => warn!(
+                            "{} Duplicate detected: {dest} differs from the copy {other} installs",
+                            Emoji("🚨 ", "")
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{PluginSource, PluginSpec};
+
+    use super::*;
+    use crate::lock_file::PluginFile;
+    use crate::models::TargetDir;
+    use crate::tests_support::env::TestEnvironmentSetup;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    struct EnvOverride {
+        entries: Vec<(&'static str, Option<std::ffi::OsString>)>,
+    }
+
+    impl EnvOverride {
+        fn new(keys: &[&'static str]) -> Self {
+            let entries = keys
+                .iter()
                 .map(|&key| (key, std::env::var_os(key)))
                 .collect();
             Self { entries }
@@ -797,6 +1456,8 @@ mod tests {
             Self {
                 new_plugin_spec: PluginSpec {
                     name: None,
+                    prerelease: false,
+                    disabled: false,
                     source: PluginSource::Repo {
                         repo: PluginRepo {
                             host: None,
@@ -808,9 +1469,14 @@ mod tests {
                         tag: None,
                         commit: None,
                     },
+                    dir: None,
+                    events: vec![],
+                    disable_files: vec![],
                 },
                 added_plugin_spec: PluginSpec {
                     name: None,
+                    prerelease: false,
+                    disabled: false,
                     source: PluginSource::Repo {
                         repo: PluginRepo {
                             host: None,
@@ -822,6 +1488,9 @@ mod tests {
                         tag: None,
                         commit: None,
                     },
+                    dir: None,
+                    events: vec![],
+                    disable_files: vec![],
                 },
             }
         }
@@ -843,7 +1512,12 @@ mod tests {
     fn test_add_plugin_in_empty_config() {
         let mut test_env = TestEnvironmentSetup::new();
         let _test_data = TestDataBuilder::new().build();
-        test_env.setup_config(config::Config { plugins: None });
+        test_env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
 
         let config = test_env.config.as_mut().expect("Config is not initialized");
         let targets = vec![crate::models::InstallTarget::from_raw("owner/new-repo")];
@@ -860,12 +1534,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn timeout_summary_lists_every_hung_remote() {
+        let err = timeout_summary(&[
+            (
+                "owner/slow".parse().unwrap(),
+                "https://github.com/owner/slow did not respond within 300s".into(),
+            ),
+            (
+                "git.example.com/owner/stuck".parse().unwrap(),
+                "https://git.example.com/owner/stuck did not respond within 300s".into(),
+            ),
+        ]);
+        let message = err.to_string();
+        assert!(
+            message.starts_with(
+                "2 plugin(s) timed out:\n  - owner/slow: https://github.com/owner/slow"
+            ),
+            "{message}"
+        );
+        assert!(message.contains(
+            "  - git.example.com/owner/stuck: https://git.example.com/owner/stuck did not respond"
+        ));
+        assert!(message.contains("clone_timeout"));
+    }
+
+    #[test]
+    fn ensure_installable_rejects_pez_itself() {
+        let settings = config::Settings::default();
+        let targets = vec![
+            crate::models::InstallTarget::from_raw("owner/plugin"),
+            crate::models::InstallTarget::from_raw("https://github.com/tetzng/pez"),
+        ];
+        let err = ensure_installable(&settings, &targets).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("tetzng/pez is not a fish plugin:"),
+            "{err}"
+        );
+        assert!(ensure_installable(&settings, &targets[..1]).is_ok());
+    }
+
     #[test]
     fn test_add_existing_plugin_to_config() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.added_plugin_spec.clone()]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let config = test_env.config.as_mut().expect("Config is not initialized");
@@ -891,6 +1609,9 @@ mod tests {
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.added_plugin_spec.clone()]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let config = test_env.config.as_mut().expect("Config is not initialized");
@@ -948,6 +1669,246 @@ mod tests {
         assert!(repo_path.exists());
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn run_prune_uses_shared_prune_and_honors_dry_run() {
+        let _env_lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut test_env = TestEnvironmentSetup::new();
+        let _override = EnvOverride::new(&[
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "PEZ_TARGET_DIR",
+            "__fish_config_dir",
+            "XDG_CONFIG_HOME",
+            "__fish_user_data_dir",
+            "XDG_DATA_HOME",
+            "HOME",
+            "PEZ_SUPPRESS_EMIT",
+        ]);
+
+        let repo_extra = PluginRepo::new(None, "owner".to_string(), "extra".to_string()).unwrap();
+        test_env.setup_config(config::Config {
+            plugins: Some(vec![]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        test_env.setup_lock_file(crate::lock_file::LockFile {
+            version: 1,
+            plugins: vec![Plugin {
+                name: repo_extra.repo.clone(),
+                repo: repo_extra.clone(),
+                source: repo_extra.default_remote_source(),
+                commit_sha: "extra-sha".to_string(),
+                files: vec![PluginFile {
+                    dir: TargetDir::Functions,
+                    name: "extra.fish".to_string(),
+                }],
+                ..Default::default()
+            }],
+        });
+        let repo_path = test_env.data_dir.join(repo_extra.as_str());
+        std::fs::create_dir_all(&repo_path).unwrap();
+        let installed_file = test_env.fish_config_dir.join("functions/extra.fish");
+        std::fs::create_dir_all(installed_file.parent().unwrap()).unwrap();
+        std::fs::write(&installed_file, "function extra; end\n").unwrap();
+
+        set_test_env_vars(&test_env);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+        }
+
+        let mut args = InstallArgs {
+            plugins: None,
+            force: false,
+            prune: true,
+            dry_run: true,
+            yes: false,
+            temp: false,
+            edit: false,
+        };
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(run(&args, &RuntimeOptions::default()))
+        })
+        .unwrap();
+        let saved_lock = crate::lock_file::load(&test_env.lock_file_path).unwrap();
+        assert_eq!(saved_lock.plugins.len(), 1);
+        assert!(repo_path.exists());
+        assert!(installed_file.exists());
+
+        args.dry_run = false;
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(run(&args, &RuntimeOptions::default()))
+        })
+        .unwrap();
+        let saved_lock = crate::lock_file::load(&test_env.lock_file_path).unwrap();
+        assert!(saved_lock.plugins.is_empty());
+        assert!(!repo_path.exists());
+        assert!(!installed_file.exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn run_install_dry_run_plans_clones_commits_and_files_without_writing() {
+        let _env_lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut test_env = TestEnvironmentSetup::new();
+        let _override = EnvOverride::new(&[
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "PEZ_TARGET_DIR",
+            "__fish_config_dir",
+            "XDG_CONFIG_HOME",
+            "__fish_user_data_dir",
+            "XDG_DATA_HOME",
+            "HOME",
+            "PEZ_SUPPRESS_EMIT",
+        ]);
+
+        let remote_root = tempfile::tempdir().unwrap();
+        let first_path = remote_root.path().join("owner").join("first");
+        init_remote_repo(&first_path);
+        let pinned_path = remote_root.path().join("owner").join("pinned");
+        let (pinned_commit, _) = init_remote_repo_with_two_commits(&pinned_path);
+        let local_dir = test_env._temp_dir.path().join("local-plugin");
+        std::fs::create_dir_all(local_dir.join("conf.d")).unwrap();
+        std::fs::create_dir_all(local_dir.join("functions")).unwrap();
+        std::fs::write(local_dir.join("conf.d/force-test.fish"), "echo other\n").unwrap();
+        std::fs::write(local_dir.join("functions/helper.fish"), "echo helper\n").unwrap();
+
+        let spec = |source: PluginSource, disabled: bool, disable_files: Vec<String>| PluginSpec {
+            name: None,
+            prerelease: false,
+            disabled,
+            source,
+            dir: None,
+            events: vec![],
+            disable_files,
+        };
+        let url = |path: &Path, commit: Option<String>| PluginSource::Url {
+            url: format!("file://{}", path.display()),
+            version: None,
+            branch: None,
+            tag: None,
+            commit,
+        };
+        let specs = vec![
+            spec(url(&first_path, None), false, vec![]),
+            spec(
+                url(&pinned_path, Some(pinned_commit.clone())),
+                false,
+                vec![],
+            ),
+            spec(
+                PluginSource::Path {
+                    path: local_dir.to_string_lossy().to_string(),
+                },
+                false,
+                vec!["functions/helper.fish".to_string()],
+            ),
+            spec(
+                PluginSource::Repo {
+                    repo: "owner/off".parse().unwrap(),
+                    version: None,
+                    branch: None,
+                    tag: None,
+                    commit: None,
+                },
+                true,
+                vec![],
+            ),
+        ];
+        let repos: Vec<PluginRepo> = specs
+            .iter()
+            .map(|spec| spec.get_plugin_repo().unwrap())
+            .collect();
+        test_env.setup_config(config::Config {
+            plugins: Some(specs),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        let conf_d = test_env.fish_config_dir.join("conf.d");
+        std::fs::create_dir_all(&conf_d).unwrap();
+        std::fs::write(conf_d.join("sequence-test.fish"), "echo mine\n").unwrap();
+        let config_before = std::fs::read_to_string(&test_env.config_path).unwrap();
+
+        set_test_env_vars(&test_env);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+        }
+
+        let args = InstallArgs {
+            plugins: None,
+            force: false,
+            prune: false,
+            dry_run: true,
+            yes: false,
+            temp: false,
+            edit: false,
+        };
+        let planned = plan(&args).unwrap();
+        let file = |dir: &str, name: &str, note: Option<FileNote>| PlannedFile {
+            dest: test_env.fish_config_dir.join(dir).join(name),
+            note,
+        };
+        assert_eq!(planned.len(), 4);
+        assert!(matches!(
+            &planned[0].action,
+            PlannedAction::Install { clone: true, files, .. }
+                if files == &vec![file("conf.d", "force-test.fish", None)]
+        ));
+        assert_eq!(
+            planned[1].action,
+            PlannedAction::Install {
+                source: format!("file://{}", pinned_path.display()),
+                clone: true,
+                commit: pinned_commit,
+                files: vec![file(
+                    "conf.d",
+                    "sequence-test.fish",
+                    Some(FileNote::Displaces)
+                )],
+            }
+        );
+        assert_eq!(
+            planned[2].action,
+            PlannedAction::Install {
+                source: local_dir.to_string_lossy().to_string(),
+                clone: false,
+                commit: "local".to_string(),
+                files: vec![
+                    file("functions", "helper.fish", Some(FileNote::Disabled)),
+                    file(
+                        "conf.d",
+                        "force-test.fish",
+                        Some(FileNote::Collision(repos[0].clone()))
+                    ),
+                ],
+            }
+        );
+        assert_eq!(
+            planned[3],
+            PlannedInstall {
+                repo: repos[3].clone(),
+                action: PlannedAction::Skip("disabled in pez.toml".to_string()),
+            }
+        );
+
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(run(&args, &RuntimeOptions::default()))
+        })
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&test_env.config_path).unwrap(),
+            config_before
+        );
+        assert!(!test_env.lock_file_path.exists());
+        assert!(!test_env.data_dir.join(repos[0].as_str()).exists());
+        assert!(!conf_d.join("force-test.fish").exists());
+        assert_eq!(
+            std::fs::read_to_string(conf_d.join("sequence-test.fish")).unwrap(),
+            "echo mine\n"
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn run_installs_local_plugin_and_updates_lock() {
         let _env_lock = crate::tests_support::log::env_lock().lock().unwrap();
@@ -980,10 +1941,16 @@ mod tests {
             )]),
             force: false,
             prune: false,
+            dry_run: false,
+            yes: false,
+            temp: false,
+            edit: false,
         };
 
-        tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(run(&args)))
-            .unwrap();
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(run(&args, &RuntimeOptions::default()))
+        })
+        .unwrap();
 
         let updated_config = config::load(&test_env.config_path).unwrap();
         let plugin_specs = updated_config.plugins.unwrap();
@@ -1003,6 +1970,239 @@ mod tests {
         assert!(fish_file.exists());
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn run_install_records_configured_plugin_name_in_lock() {
+        let _env_lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut test_env = TestEnvironmentSetup::new();
+        let _override = EnvOverride::new(&[
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "PEZ_TARGET_DIR",
+            "__fish_config_dir",
+            "XDG_CONFIG_HOME",
+            "__fish_user_data_dir",
+            "XDG_DATA_HOME",
+            "HOME",
+            "PEZ_SUPPRESS_EMIT",
+        ]);
+
+        let source_dir = test_env._temp_dir.path().join("local-plugin");
+        let conf_dir = source_dir.join(TargetDir::ConfD.as_str());
+        std::fs::create_dir_all(&conf_dir).unwrap();
+        std::fs::write(conf_dir.join("local-plugin.fish"), "echo local\n").unwrap();
+        test_env.setup_config(config::Config {
+            plugins: Some(vec![PluginSpec {
+                name: Some("renamed".to_string()),
+                prerelease: false,
+                disabled: false,
+                source: PluginSource::Path {
+                    path: source_dir.to_string_lossy().to_string(),
+                },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
+            }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+
+        set_test_env_vars(&test_env);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+        }
+
+        let args = InstallArgs {
+            plugins: Some(vec![InstallTarget::from_raw(
+                source_dir.to_string_lossy().to_string(),
+            )]),
+            force: false,
+            prune: false,
+            dry_run: false,
+            yes: false,
+            temp: false,
+            edit: false,
+        };
+
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(run(&args, &RuntimeOptions::default()))
+        })
+        .unwrap();
+
+        let saved_lock = crate::lock_file::load(&test_env.lock_file_path).unwrap();
+        assert_eq!(saved_lock.plugins.len(), 1);
+        assert_eq!(saved_lock.plugins[0].name, "renamed");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn run_install_edit_installs_staged_entries_as_edited() {
+        let _env_lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut test_env = TestEnvironmentSetup::new();
+        let _override = EnvOverride::new(&[
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "PEZ_TARGET_DIR",
+            "__fish_config_dir",
+            "XDG_CONFIG_HOME",
+            "__fish_user_data_dir",
+            "XDG_DATA_HOME",
+            "HOME",
+            "PEZ_SUPPRESS_EMIT",
+            "VISUAL",
+            "EDITOR",
+        ]);
+
+        let source_dir = test_env._temp_dir.path().join("local-plugin");
+        let conf_dir = source_dir.join(TargetDir::ConfD.as_str());
+        std::fs::create_dir_all(&conf_dir).unwrap();
+        std::fs::write(conf_dir.join("local-plugin.fish"), "echo local\n").unwrap();
+        test_env.setup_config(config::Config {
+            plugins: Some(vec![PluginSpec {
+                name: None,
+                prerelease: false,
+                disabled: true,
+                source: PluginSource::Repo {
+                    repo: "owner/kept".parse().unwrap(),
+                    version: None,
+                    branch: None,
+                    tag: None,
+                    commit: None,
+                },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
+            }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+
+        // An "editor" that keeps a copy of what it was shown and names the staged entry.
+        let shown = test_env._temp_dir.path().join("shown.toml");
+        let editor = test_env._temp_dir.path().join("editor.sh");
+        std::fs::write(
+            &editor,
+            format!(
+                "#!/bin/sh\ncp \"$1\" '{}'\nprintf 'name = \"renamed\"\\n' >> \"$1\"\n",
+                shown.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&editor, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        set_test_env_vars(&test_env);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+            std::env::remove_var("VISUAL");
+            std::env::set_var("EDITOR", &editor);
+        }
+
+        let args = InstallArgs {
+            plugins: Some(vec![InstallTarget::from_raw(
+                source_dir.to_string_lossy().to_string(),
+            )]),
+            force: false,
+            prune: false,
+            dry_run: false,
+            yes: false,
+            temp: false,
+            edit: true,
+        };
+
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(run(&args, &RuntimeOptions::default()))
+        })
+        .unwrap();
+
+        let shown = std::fs::read_to_string(shown).unwrap();
+        let lines: Vec<&str> = shown.lines().collect();
+        let comment = lines
+            .iter()
+            .position(|line| line.starts_with("# Staged by `pez install --edit`"))
+            .unwrap();
+        assert_eq!(lines[comment + 1], "[[plugins]]");
+        assert!(
+            lines[comment..]
+                .iter()
+                .any(|line| line.starts_with("path = "))
+        );
+        assert!(
+            lines[..comment]
+                .iter()
+                .any(|line| line.contains("owner/kept"))
+        );
+
+        let specs = config::load(&test_env.config_path)
+            .unwrap()
+            .plugins
+            .unwrap();
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs[1].name.as_deref(), Some("renamed"));
+        let saved_lock = crate::lock_file::load(&test_env.lock_file_path).unwrap();
+        assert_eq!(saved_lock.plugins.len(), 1);
+        assert_eq!(saved_lock.plugins[0].name, "renamed");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn run_install_temp_skips_config_and_lock_until_pruned() {
+        let _env_lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let test_env = TestEnvironmentSetup::new();
+        let _override = EnvOverride::new(&[
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "PEZ_TARGET_DIR",
+            "__fish_config_dir",
+            "XDG_CONFIG_HOME",
+            "__fish_user_data_dir",
+            "XDG_DATA_HOME",
+            "HOME",
+            "PEZ_SUPPRESS_EMIT",
+        ]);
+
+        let source_dir = test_env._temp_dir.path().join("trial-plugin");
+        let conf_dir = source_dir.join(TargetDir::ConfD.as_str());
+        std::fs::create_dir_all(&conf_dir).unwrap();
+        std::fs::write(conf_dir.join("trial-plugin.fish"), "echo trial\n").unwrap();
+
+        set_test_env_vars(&test_env);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+        }
+
+        let args = InstallArgs {
+            plugins: Some(vec![InstallTarget::from_raw(
+                source_dir.to_string_lossy().to_string(),
+            )]),
+            force: false,
+            prune: false,
+            dry_run: false,
+            yes: false,
+            temp: true,
+            edit: false,
+        };
+
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(run(&args, &RuntimeOptions::default()))
+        })
+        .unwrap();
+
+        let fish_file = test_env
+            .fish_config_dir
+            .join(TargetDir::ConfD.as_str())
+            .join("trial-plugin.fish");
+        assert!(fish_file.exists());
+        assert!(!test_env.config_path.exists());
+        assert!(!test_env.lock_file_path.exists());
+        let (temp_lock, temp_lock_path) = utils::load_or_create_temp_lock_file().unwrap();
+        assert_eq!(temp_lock.plugins.len(), 1);
+
+        let removed = crate::cmd::prune::prune_temp_installs(false).unwrap();
+        assert_eq!(removed, 1);
+        assert!(!fish_file.exists());
+        assert!(!temp_lock_path.exists());
+        assert!(source_dir.exists());
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn run_install_fails_when_target_dir_is_file() {
         let _env_lock = crate::tests_support::log::env_lock().lock().unwrap();
@@ -1038,10 +2238,15 @@ mod tests {
             )]),
             force: false,
             prune: false,
+            dry_run: false,
+            yes: false,
+            temp: false,
+            edit: false,
         };
 
-        let result =
-            tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(run(&args)));
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(run(&args, &RuntimeOptions::default()))
+        });
         assert!(
             result.is_err(),
             "install should fail when target dir is not a directory"
@@ -1094,6 +2299,7 @@ mod tests {
                     plugins: vec![],
                 },
                 &test_env.data_dir,
+                4,
             ))
             .unwrap();
         let installed_plugins = rt
@@ -1157,6 +2363,7 @@ mod tests {
             source: remote_url.clone(),
             commit_sha: first.clone(),
             files: vec![],
+            ..Default::default()
         };
         let lock_file = LockFile {
             version: 1,
@@ -1165,7 +2372,7 @@ mod tests {
         let data_dir = temp_dir.path().join("data");
         std::fs::create_dir_all(&data_dir).unwrap();
 
-        let plugins = clone_plugins(&[resolved], false, lock_file, &data_dir)
+        let plugins = clone_plugins(&[resolved], false, lock_file, &data_dir, 4)
             .await
             .unwrap();
         assert_eq!(plugins.len(), 1);
@@ -1191,12 +2398,13 @@ mod tests {
                 source: remote_url,
                 commit_sha: "deadbeef".to_string(),
                 files: vec![],
+                ..Default::default()
             }],
         };
         let data_dir = temp_dir.path().join("data");
         std::fs::create_dir_all(&data_dir).unwrap();
 
-        let err = clone_plugins(&[resolved], false, lock_file, &data_dir)
+        let err = clone_plugins(&[resolved], false, lock_file, &data_dir, 4)
             .await
             .unwrap_err();
         let err_text = format!("{:#}", err);
@@ -1230,6 +2438,7 @@ mod tests {
                 source: bad_remote_url,
                 commit_sha: "deadbeef".to_string(),
                 files: vec![],
+                ..Default::default()
             }],
         };
         let data_dir = temp_dir.path().join("data");
@@ -1240,6 +2449,7 @@ mod tests {
             false,
             lock_file,
             &data_dir,
+            4,
         )
         .await
         .unwrap_err();
@@ -1271,6 +2481,7 @@ mod tests {
                 plugins: vec![],
             },
             &data_dir,
+            4,
         )
         .await
         .unwrap();
@@ -1279,6 +2490,113 @@ mod tests {
         assert!(existing_repo_path.exists());
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clone_plugins_replaces_stale_staging_dirs_with_final_repo() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let remote_repo_path = temp_dir.path().join("owner").join("staged-repo");
+        let remote_url = format!("file://{}", remote_repo_path.display());
+        init_remote_repo(&remote_repo_path);
+
+        let resolved = InstallTarget::from_raw(remote_url).resolve().unwrap();
+        let data_dir = temp_dir.path().join("data");
+        let repo_path = data_dir.join(resolved.plugin_repo.as_str());
+        let repo_parent = repo_path.parent().unwrap().to_path_buf();
+        let stale_dir = repo_parent.join("staged-repo.tmp-interrupted");
+        std::fs::create_dir_all(stale_dir.join(".git")).unwrap();
+
+        let plugins = clone_plugins(&[resolved], false, crate::lock_file::init(), &data_dir, 4)
+            .await
+            .unwrap();
+
+        assert_eq!(plugins.len(), 1);
+        assert!(repo_path.join(".git").exists());
+        let leftovers: Vec<_> = std::fs::read_dir(&repo_parent)
+            .unwrap()
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name != "staged-repo")
+            .collect();
+        assert!(leftovers.is_empty(), "unexpected leftovers: {leftovers:?}");
+    }
+
+    #[test]
+    fn clone_plugin_repository_uses_shared_objects_with_fallback() {
+        let _env_lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut test_env = TestEnvironmentSetup::new();
+        let _override = EnvOverride::new(&[
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "PEZ_TARGET_DIR",
+            "__fish_config_dir",
+            "XDG_CONFIG_HOME",
+            "__fish_user_data_dir",
+            "XDG_DATA_HOME",
+            "HOME",
+        ]);
+        set_test_env_vars(&test_env);
+        test_env.setup_config(config::Config {
+            settings: config::Settings {
+                shared_objects: true,
+                ..Default::default()
+            },
+            plugins: None,
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+
+        let remote_root = tempfile::tempdir().unwrap();
+        let remote_repo_path = remote_root.path().join("owner").join("shared");
+        let commit = init_remote_repo(&remote_repo_path);
+        let remote_url = format!("file://{}", remote_repo_path.display());
+        let plugin_repo: PluginRepo = "owner/shared".parse().unwrap();
+
+        let target = test_env.data_dir.join("shared-clone");
+        std::fs::create_dir_all(&target).unwrap();
+        let repo = clone_plugin_repository(&remote_url, &target, &plugin_repo).unwrap();
+        assert_eq!(git::get_latest_commit_sha(&repo).unwrap(), commit);
+        assert!(repo.path().join("objects/info/alternates").exists());
+        assert!(utils::load_pez_shared_objects_dir().unwrap().exists());
+
+        // An unusable store falls back to a regular clone.
+        let store = utils::load_pez_shared_objects_dir().unwrap();
+        std::fs::remove_dir_all(&store).unwrap();
+        std::fs::write(&store, "not a repository").unwrap();
+        let fallback_target = test_env.data_dir.join("fallback-clone");
+        std::fs::create_dir_all(&fallback_target).unwrap();
+        let (logs, repo) = crate::tests_support::log::capture_logs(|| {
+            clone_plugin_repository(&remote_url, &fallback_target, &plugin_repo)
+        });
+        let repo = repo.unwrap();
+        assert_eq!(git::get_latest_commit_sha(&repo).unwrap(), commit);
+        assert!(!repo.path().join("objects/info/alternates").exists());
+        assert!(
+            logs.iter().any(|l| l.contains("using a regular clone")),
+            "logs: {logs:?}"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clone_plugins_leaves_no_partial_repo_when_clone_fails() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let missing_remote = temp_dir.path().join("owner").join("missing-repo");
+        let remote_url = format!("file://{}", missing_remote.display());
+
+        let resolved = InstallTarget::from_raw(remote_url).resolve().unwrap();
+        let data_dir = temp_dir.path().join("data");
+        let repo_path = data_dir.join(resolved.plugin_repo.as_str());
+
+        let err = clone_plugins(&[resolved], false, crate::lock_file::init(), &data_dir, 4)
+            .await
+            .unwrap_err();
+
+        assert!(format!("{err:#}").contains("failed to clone"));
+        assert!(!repo_path.exists());
+        let leftovers = std::fs::read_dir(repo_path.parent().unwrap())
+            .unwrap()
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
     #[test]
     fn ensure_repo_parent_creates_missing_parent() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -1305,7 +2623,12 @@ mod tests {
     #[test]
     fn emit_event_only_for_conf_d() {
         let _env_lock = crate::tests_support::log::env_lock().lock().unwrap();
-        let _override = EnvOverride::new(&["PATH", "PEZ_SUPPRESS_EMIT", "PEZ_TEST_FISH_LOG"]);
+        let _override = EnvOverride::new(&[
+            "PATH",
+            "PEZ_SUPPRESS_EMIT",
+            "PEZ_TEST_FISH_LOG",
+            "PEZ_DATA_DIR",
+        ]);
         let temp_dir = tempfile::tempdir().unwrap();
         let bin_dir = temp_dir.path().join("bin");
         std::fs::create_dir_all(&bin_dir).unwrap();
@@ -1322,6 +2645,7 @@ mod tests {
             std::env::set_var("PATH", format!("{}:{}", bin_dir.display(), existing_path));
             std::env::remove_var("PEZ_SUPPRESS_EMIT");
             std::env::set_var("PEZ_TEST_FISH_LOG", &log_path);
+            std::env::set_var("PEZ_DATA_DIR", temp_dir.path().join("data"));
         }
 
         let repo = PluginRepo::new(None, "owner".to_string(), "repo".to_string()).unwrap();
@@ -1340,6 +2664,7 @@ mod tests {
                     name: "beta.fish".to_string(),
                 },
             ],
+            ..Default::default()
         };
 
         emit_event(&plugin, &utils::Event::Install).unwrap();
@@ -1347,6 +2672,7 @@ mod tests {
         let log_contents = std::fs::read_to_string(&log_path).unwrap_or_default();
         assert!(log_contents.contains("emit alpha_install"));
         assert!(!log_contents.contains("emit beta_install"));
+        assert!(temp_dir.path().join("data/.state/owner/repo").is_dir());
     }
 
     #[test]
@@ -1372,6 +2698,8 @@ mod tests {
 
         let plugin_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Url {
                 url: remote_url.clone(),
                 version: None,
@@ -1379,10 +2707,16 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let repo_for_id = plugin_spec.get_plugin_repo().unwrap();
         test_env.setup_config(config::Config {
             plugins: Some(vec![plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(crate::lock_file::LockFile {
             version: 1,
@@ -1392,6 +2726,7 @@ mod tests {
                 source: remote_url.clone(),
                 commit_sha: expected_commit.clone(),
                 files: vec![],
+                ..Default::default()
             }],
         });
 
@@ -1432,6 +2767,8 @@ mod tests {
 
         let plugin_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Url {
                 url: remote_url.clone(),
                 version: None,
@@ -1439,11 +2776,17 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let repo_for_id = plugin_spec.get_plugin_repo().unwrap();
         let repo_path = test_env.data_dir.join(repo_for_id.as_str());
         test_env.setup_config(config::Config {
             plugins: Some(vec![plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(crate::lock_file::LockFile {
             version: 1,
@@ -1453,6 +2796,7 @@ mod tests {
                 source: remote_url,
                 commit_sha: "deadbeef".to_string(),
                 files: vec![],
+                ..Default::default()
             }],
         });
 
@@ -1496,13 +2840,21 @@ mod tests {
 
         let plugin_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Path {
                 path: source_dir.to_string_lossy().to_string(),
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let repo_for_id = plugin_spec.get_plugin_repo().unwrap();
         test_env.setup_config(config::Config {
             plugins: Some(vec![plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(crate::lock_file::LockFile {
             version: 1,
@@ -1512,6 +2864,7 @@ mod tests {
                 source: source_dir.to_string_lossy().to_string(),
                 commit_sha: "local".to_string(),
                 files: vec![],
+                ..Default::default()
             }],
         });
 
@@ -1554,6 +2907,8 @@ mod tests {
 
         let plugin_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Url {
                 url: remote_url.clone(),
                 version: None,
@@ -1561,10 +2916,16 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let repo_for_id = plugin_spec.get_plugin_repo().unwrap();
         test_env.setup_config(config::Config {
             plugins: Some(vec![plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(crate::lock_file::LockFile {
             version: 1,
@@ -1608,13 +2969,21 @@ mod tests {
 
         let plugin_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Path {
                 path: source_dir.to_string_lossy().to_string(),
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let repo_for_id = plugin_spec.get_plugin_repo().unwrap();
         test_env.setup_config(config::Config {
             plugins: Some(vec![plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(crate::lock_file::LockFile {
             version: 1,
@@ -1658,6 +3027,8 @@ mod tests {
         test_env.setup_config(config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: PluginSource::Repo {
                     repo: repo_keep.clone(),
                     version: None,
@@ -1665,7 +3036,13 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(crate::lock_file::LockFile {
             version: 1,
@@ -1676,6 +3053,7 @@ mod tests {
                     source: repo_keep.default_remote_source(),
                     commit_sha: "keep-sha".to_string(),
                     files: vec![],
+                    ..Default::default()
                 },
                 Plugin {
                     name: repo_extra.repo.clone(),
@@ -1683,6 +3061,7 @@ mod tests {
                     source: repo_extra.default_remote_source(),
                     commit_sha: "extra-sha".to_string(),
                     files: vec![],
+                    ..Default::default()
                 },
             ],
         });
@@ -1739,6 +3118,8 @@ mod tests {
 
         let plugin_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Url {
                 url: remote_url.clone(),
                 version: None,
@@ -1746,9 +3127,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         test_env.setup_config(config::Config {
             plugins: Some(vec![plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let lock_plugin = Plugin {
@@ -1757,6 +3144,7 @@ mod tests {
             source: remote_url.clone(),
             commit_sha: "old-lock-sha".to_string(),
             files: vec![],
+            ..Default::default()
         };
         test_env.setup_lock_file(crate::lock_file::LockFile {
             version: 1,
@@ -1830,6 +3218,8 @@ mod tests {
 
         let plugin_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Url {
                 url: remote_url.clone(),
                 version: None,
@@ -1837,9 +3227,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         test_env.setup_config(config::Config {
             plugins: Some(vec![plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let lock_plugin = Plugin {
@@ -1848,6 +3244,7 @@ mod tests {
             source: remote_url.clone(),
             commit_sha: first_commit.clone(),
             files: vec![],
+            ..Default::default()
         };
         test_env.setup_lock_file(crate::lock_file::LockFile {
             version: 1,
@@ -1,18 +1,65 @@
-use crate::{cli, config, git, lock_file::Plugin, resolver, utils};
+use crate::{
+    cli, config, duplicates, git,
+    lock_file::{LockFile, Plugin},
+    output,
+    output::JsonStyle,
+    resolver,
+    runtime::RuntimeOptions,
+    update_cache::{self, CachePolicy},
+    utils,
+};
 use std::io::Write;
 
 use console::Emoji;
 use serde_json::json;
-use tabled::{Table, Tabled};
-use tracing::{info, warn};
+use tabled::{Table, Tabled, builder::Builder};
+use tracing::{debug, info, warn};
 
-#[derive(Debug, Tabled)]
+/// Exit code for `--fail-if-outdated` when any plugin has an update.
+const EXIT_OUTDATED: u8 = 1;
+
+#[derive(Debug)]
 struct PluginRow {
     name: String,
     repo: String,
     source: String,
     selector: String,
     commit: String,
+    updated: String,
+}
+
+/// The table columns, in the order shown when `--columns` is not given.
+const DEFAULT_COLUMNS: &[cli::ListColumn] = &[
+    cli::ListColumn::Name,
+    cli::ListColumn::Repo,
+    cli::ListColumn::Source,
+    cli::ListColumn::Selector,
+    cli::ListColumn::Commit,
+    cli::ListColumn::Updated,
+];
+
+impl PluginRow {
+    fn cell(&self, column: cli::ListColumn) -> &str {
+        match column {
+            cli::ListColumn::Name => &self.name,
+            cli::ListColumn::Repo => &self.repo,
+            cli::ListColumn::Source => &self.source,
+            cli::ListColumn::Selector => &self.selector,
+            cli::ListColumn::Commit => &self.commit,
+            cli::ListColumn::Updated => &self.updated,
+        }
+    }
+}
+
+fn column_header(column: cli::ListColumn) -> &'static str {
+    match column {
+        cli::ListColumn::Name => "name",
+        cli::ListColumn::Repo => "repo",
+        cli::ListColumn::Source => "source",
+        cli::ListColumn::Selector => "selector",
+        cli::ListColumn::Commit => "commit",
+        cli::ListColumn::Updated => "updated",
+    }
 }
 
 #[derive(Debug, Tabled)]
@@ -24,17 +71,29 @@ struct PluginOutdatedRow {
     latest: String,
 }
 
-struct OutdatedPlugin {
-    plugin: Plugin,
-    latest: String,
+pub(crate) struct OutdatedPlugin {
+    pub(crate) plugin: Plugin,
+    pub(crate) latest: String,
+}
+
+/// Result of comparing plugins against their remote (or cached) state.
+#[derive(Default)]
+pub(crate) struct OutdatedCheck {
+    pub(crate) outdated: Vec<OutdatedPlugin>,
+    /// Plugins skipped under [`CachePolicy::CachedOnly`], with the selection that had no
+    /// cached result.
+    pub(crate) unchecked: Vec<(Plugin, String)>,
 }
 
-pub(crate) fn run(args: &cli::ListArgs) -> anyhow::Result<String> {
-    let mut stdout = std::io::stdout();
-    run_with_writer(args, &mut stdout)
+pub(crate) fn run(args: &cli::ListArgs, options: &RuntimeOptions) -> anyhow::Result<String> {
+    run_with_writer(args, options, &mut output::Stdout)
 }
 
-fn run_with_writer<W: Write>(args: &cli::ListArgs, writer: &mut W) -> anyhow::Result<String> {
+fn run_with_writer<W: Write>(
+    args: &cli::ListArgs,
+    options: &RuntimeOptions,
+    writer: &mut W,
+) -> anyhow::Result<String> {
     let result = utils::load_lock_file();
     if result.is_err() {
         info!("No plugins installed!");
@@ -49,6 +108,26 @@ fn run_with_writer<W: Write>(args: &cli::ListArgs, writer: &mut W) -> anyhow::Re
             return Ok(String::new());
         }
     };
+    if matches!(args.format, Some(cli::ListFormat::Github)) && !args.outdated {
+        anyhow::bail!("--format github only applies to --outdated");
+    }
+    if args.duplicates {
+        let output = list_duplicates(
+            &lock_file,
+            config_opt.as_ref(),
+            args.format.clone(),
+            options.json_style,
+        )?;
+        writer.write_all(output.as_bytes())?;
+        return Ok(output);
+    }
+
+    let format = args.format.clone().unwrap_or(cli::ListFormat::Plain);
+    if (args.sort.is_some() || args.columns.is_some()) && !matches!(format, cli::ListFormat::Table)
+    {
+        anyhow::bail!("--sort and --columns only apply to --format table");
+    }
+
     let mut plugins = lock_file.plugins.clone();
     if let Some(filter) = &args.filter {
         match filter {
@@ -57,6 +136,10 @@ fn run_with_writer<W: Write>(args: &cli::ListArgs, writer: &mut W) -> anyhow::Re
             cli::ListFilter::Remote => plugins.retain(|p| !git::is_local_source(&p.source)),
         }
     }
+    if let Some(stale) = args.stale {
+        let now = update_cache::now_secs();
+        plugins.retain(|p| is_stale(p, stale, now));
+    }
     let plugins = &plugins;
     if plugins.is_empty() {
         info!("No plugins installed!");
@@ -64,26 +147,77 @@ fn run_with_writer<W: Write>(args: &cli::ListArgs, writer: &mut W) -> anyhow::Re
     }
 
     let output = if args.outdated {
-        match args.format.clone().unwrap_or(cli::ListFormat::Plain) {
-            cli::ListFormat::Table => list_outdated_table(plugins, config_opt.as_ref())?,
-            cli::ListFormat::Json => list_outdated_json(plugins, config_opt.as_ref())?,
-            cli::ListFormat::Plain => list_outdated(plugins, config_opt.as_ref())?,
+        let policy = cache_policy(args);
+        let cfg = config_opt.as_ref();
+        match (&format, format.structured()) {
+            (_, Some(structured)) => {
+                list_outdated_json(plugins, cfg, &policy, structured, options.json_style)?
+            }
+            (cli::ListFormat::Table, _) => list_outdated_table(plugins, cfg, &policy)?,
+            (cli::ListFormat::Github, _) => list_outdated_github(plugins, cfg, &policy)?,
+            _ => list_outdated(plugins, cfg, &policy)?,
         }
     } else {
-        match args.format.clone().unwrap_or(cli::ListFormat::Plain) {
-            cli::ListFormat::Table => list_table(plugins, config_opt.as_ref()),
-            cli::ListFormat::Json => list_json(plugins, config_opt.as_ref())?,
-            cli::ListFormat::Plain => list(plugins),
+        match (&format, format.structured()) {
+            (_, Some(structured)) => {
+                list_json(plugins, config_opt.as_ref(), structured, options.json_style)?
+            }
+            (cli::ListFormat::Table, _) => list_table(
+                plugins,
+                config_opt.as_ref(),
+                args.sort,
+                args.columns.as_deref().unwrap_or(DEFAULT_COLUMNS),
+            ),
+            _ => list(plugins),
         }
     };
 
     if !output.is_empty() {
         writer.write_all(output.as_bytes())?;
     }
+    // The outdated listings render nothing when every plugin is up to date.
+    if args.fail_if_outdated && !output.is_empty() {
+        return Err(utils::ExitError {
+            code: EXIT_OUTDATED,
+            message: "plugin updates are available; run `pez upgrade` to apply them".into(),
+        }
+        .into());
+    }
 
     Ok(output)
 }
 
+fn cache_policy(args: &cli::ListArgs) -> CachePolicy {
+    if args.cached {
+        CachePolicy::CachedOnly
+    } else if let Some(max_age) = args.max_age {
+        CachePolicy::MaxAge(max_age)
+    } else {
+        CachePolicy::Refresh
+    }
+}
+
+/// Plugins without a recorded update time (locks written before v2) count as stale.
+pub(crate) fn is_stale(plugin: &Plugin, max_age: std::time::Duration, now: u64) -> bool {
+    match plugin.updated_at {
+        Some(updated_at) => now.saturating_sub(updated_at) >= max_age.as_secs(),
+        None => true,
+    }
+}
+
+pub(crate) fn format_age(updated_at: Option<u64>, now: u64) -> String {
+    let Some(updated_at) = updated_at else {
+        return "-".into();
+    };
+    let secs = now.saturating_sub(updated_at);
+    match secs {
+        0..60 => "just now".into(),
+        60..3_600 => format!("{}m ago", secs / 60),
+        3_600..86_400 => format!("{}h ago", secs / 3_600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
 fn list(plugins: &[Plugin]) -> String {
     render_plugins_plain(plugins)
 }
@@ -97,14 +231,17 @@ fn render_plugins_plain(plugins: &[Plugin]) -> String {
     output
 }
 
-fn list_table(plugins: &[Plugin], config: Option<&crate::config::Config>) -> String {
+fn list_table(
+    plugins: &[Plugin],
+    config: Option<&crate::config::Config>,
+    sort: Option<cli::ListSort>,
+    columns: &[cli::ListColumn],
+) -> String {
     fn short7(s: &str) -> String {
         s.chars().take(7).collect()
     }
-    fn selector_of(
-        cfg: Option<&crate::config::Config>,
-        repo: &crate::models::PluginRepo,
-    ) -> String {
+    fn selector_of(cfg: Option<&crate::config::Config>, plugin: &Plugin) -> String {
+        let repo = &plugin.repo;
         let cfg = match cfg {
             Some(c) => c,
             None => return "-".into(),
@@ -143,27 +280,51 @@ fn list_table(plugins: &[Plugin], config: Option<&crate::config::Config>) -> Str
                 if let Some(v) = version {
                     return format!("version:{}", v);
                 }
-                "-".into()
+                // Unpinned plugins follow the remote's default branch.
+                match &plugin.default_branch {
+                    Some(b) => format!("default:{}", b),
+                    None => "-".into(),
+                }
             }
             crate::config::PluginSource::Path { .. } => "local".into(),
         }
     }
+    let mut plugins: Vec<&Plugin> = plugins.iter().collect();
+    if let Some(sort) = sort {
+        let key = |p: &Plugin| match sort {
+            cli::ListSort::Name => p.get_name(),
+            cli::ListSort::Repo => p.repo.as_str(),
+            cli::ListSort::Commit => p.commit_sha.clone(),
+            cli::ListSort::Source => p.source.clone(),
+        };
+        plugins.sort_by_cached_key(|p| key(p));
+    }
+    let now = update_cache::now_secs();
     let plugin_rows = plugins
-        .iter()
+        .into_iter()
         .map(|p| PluginRow {
             name: p.get_name(),
             repo: p.repo.as_str().clone(),
             source: p.source.clone(),
-            selector: selector_of(config, &p.repo),
+            selector: selector_of(config, p),
             commit: short7(&p.commit_sha),
+            updated: format_age(p.updated_at, now),
         })
         .collect::<Vec<PluginRow>>();
-    let table = Table::new(&plugin_rows);
-    table.to_string()
+    let mut builder = Builder::new();
+    builder.push_record(columns.iter().map(|&c| column_header(c)));
+    for row in &plugin_rows {
+        builder.push_record(columns.iter().map(|&c| row.cell(c)));
+    }
+    builder.build().to_string()
 }
 
-fn list_outdated(plugins: &[Plugin], config: Option<&config::Config>) -> anyhow::Result<String> {
-    let outdated_plugins = get_outdated_plugins(plugins, config)?;
+fn list_outdated(
+    plugins: &[Plugin],
+    config: Option<&config::Config>,
+    policy: &CachePolicy,
+) -> anyhow::Result<String> {
+    let outdated_plugins = get_outdated_plugins(plugins, config, policy)?;
     if outdated_plugins.is_empty() {
         info!("{}All plugins are up to date!", Emoji("🎉 ", ""));
         return Ok(String::new());
@@ -175,40 +336,46 @@ fn list_outdated(plugins: &[Plugin], config: Option<&config::Config>) -> anyhow:
     Ok(render_plugins_plain(&plugins_only))
 }
 
-fn get_outdated_plugins(
+pub(crate) fn get_outdated_plugins(
     plugins: &[Plugin],
     config: Option<&config::Config>,
+    policy: &CachePolicy,
 ) -> anyhow::Result<Vec<OutdatedPlugin>> {
+    let check = check_outdated(plugins, config, policy)?;
+    for (plugin, selection_desc) in &check.unchecked {
+        warn!(
+            "No cached update check for {} ({selection_desc}). Skipping outdated check.",
+            plugin.repo
+        );
+    }
+    Ok(check.outdated)
+}
+
+pub(crate) fn check_outdated(
+    plugins: &[Plugin],
+    config: Option<&config::Config>,
+    policy: &CachePolicy,
+) -> anyhow::Result<OutdatedCheck> {
     let data_dir = utils::load_pez_data_dir()?;
+    let cache_path = utils::load_pez_cache_dir()?.join(update_cache::UPDATE_CHECK_FILE_NAME);
+    let mut cache = update_cache::load(&cache_path);
+    let mut cache_dirty = false;
+    let now = update_cache::now_secs();
     let mut outdated_plugins: Vec<OutdatedPlugin> = Vec::new();
+    let mut unchecked: Vec<(Plugin, String)> = Vec::new();
 
     for plugin in plugins {
         if git::is_local_source(&plugin.source) {
             continue;
         }
 
-        let repo_path = data_dir.join(plugin.repo.as_str());
-        let repo = match git2::Repository::open(&repo_path) {
-            Ok(repo) => repo,
-            Err(err) => {
-                warn!(
-                    "Failed to open repository for {} at {}: {err:?}",
-                    plugin.repo,
-                    repo_path.display()
-                );
-                continue;
-            }
-        };
-
         let mut selection = resolver::Selection::DefaultHead;
         let mut selection_desc = describe_selection(&selection);
         let mut selection_from_config = false;
+        let mut channel = resolver::Channel::Stable;
 
         if let Some(cfg) = config
-            && let Some(specs) = &cfg.plugins
-            && let Some(spec) = specs
-                .iter()
-                .find(|candidate| candidate.get_plugin_repo().ok().as_ref() == Some(&plugin.repo))
+            && let Some(spec) = cfg.find_plugin_spec(&plugin.repo)
         {
             match spec.to_resolved() {
                 Ok(resolved) => {
@@ -218,6 +385,10 @@ fn get_outdated_plugins(
                     }
                     selection = resolver::selection_from_ref_kind(&resolved.ref_kind);
                     selection_desc = describe_selection(&selection);
+                    channel = resolved.channel;
+                    if channel == resolver::Channel::Prerelease {
+                        selection_desc.push_str(" (prerelease)");
+                    }
                     selection_from_config = true;
                 }
                 Err(err) => {
@@ -229,7 +400,35 @@ fn get_outdated_plugins(
             }
         }
 
-        let latest = match git::resolve_selection(&repo, &selection) {
+        if let Some(latest) = cache.lookup(&plugin.repo, &selection_desc, policy, now) {
+            debug!(repo = %plugin.repo, selection = %selection_desc, "Using cached update check");
+            if plugin.commit_sha != latest {
+                outdated_plugins.push(OutdatedPlugin {
+                    plugin: plugin.clone(),
+                    latest: latest.to_string(),
+                });
+            }
+            continue;
+        }
+        if *policy == CachePolicy::CachedOnly {
+            unchecked.push((plugin.clone(), selection_desc));
+            continue;
+        }
+
+        let repo_path = data_dir.join(plugin.repo.as_str());
+        let repo = match git2::Repository::open(&repo_path) {
+            Ok(repo) => repo,
+            Err(err) => {
+                warn!(
+                    "Failed to open repository for {} at {}: {err:?}",
+                    plugin.repo,
+                    repo_path.display()
+                );
+                continue;
+            }
+        };
+
+        let latest = match git::resolve_selection(&repo, &selection, channel) {
             Ok(commit) => commit,
             Err(err) => {
                 if selection_from_config {
@@ -255,6 +454,8 @@ fn get_outdated_plugins(
                 }
             }
         };
+        cache.record(&plugin.repo, &selection_desc, &latest, now);
+        cache_dirty = true;
 
         if plugin.commit_sha != latest {
             outdated_plugins.push(OutdatedPlugin {
@@ -264,17 +465,36 @@ fn get_outdated_plugins(
         }
     }
 
-    Ok(outdated_plugins)
+    if cache_dirty && let Err(err) = cache.save(&cache_path) {
+        // The cache is optional; audit-only runs on read-only trees should stay quiet.
+        if utils::is_read_only_error(&err) {
+            debug!(
+                "Skipping update-check cache at {}: {err:?}",
+                cache_path.display()
+            );
+        } else {
+            warn!(
+                "Failed to write update-check cache at {}: {err:?}",
+                cache_path.display()
+            );
+        }
+    }
+
+    Ok(OutdatedCheck {
+        outdated: outdated_plugins,
+        unchecked,
+    })
 }
 
 fn list_outdated_table(
     plugins: &[Plugin],
     config: Option<&config::Config>,
+    policy: &CachePolicy,
 ) -> anyhow::Result<String> {
     fn short7(s: &str) -> String {
         s.chars().take(7).collect()
     }
-    let outdated_plugins = get_outdated_plugins(plugins, config)?;
+    let outdated_plugins = get_outdated_plugins(plugins, config, policy)?;
     if outdated_plugins.is_empty() {
         info!("{}All plugins are up to date!", Emoji("🎉 ", ""));
         return Ok(String::new());
@@ -294,7 +514,115 @@ fn list_outdated_table(
     Ok(table.to_string())
 }
 
-fn list_json(plugins: &[Plugin], config: Option<&crate::config::Config>) -> anyhow::Result<String> {
+/// A workflow warning per outdated plugin, for `--format github` in CI.
+fn list_outdated_github(
+    plugins: &[Plugin],
+    config: Option<&config::Config>,
+    policy: &CachePolicy,
+) -> anyhow::Result<String> {
+    let outdated_plugins = get_outdated_plugins(plugins, config, policy)?;
+    if outdated_plugins.is_empty() {
+        info!("{}All plugins are up to date!", Emoji("🎉 ", ""));
+        return Ok(String::new());
+    }
+    let mut output = String::new();
+    for entry in &outdated_plugins {
+        let repo = entry.plugin.repo.as_str();
+        let short = |sha: &str| sha.chars().take(7).collect::<String>();
+        output.push_str(&output::github_annotation(
+            output::Annotation::Warning,
+            &format!("pez: {repo} is outdated"),
+            &format!(
+                "{repo} can be updated from {} to {}; run `pez upgrade {repo}`",
+                short(&entry.plugin.commit_sha),
+                short(&entry.latest)
+            ),
+        ));
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+#[derive(Debug, Tabled)]
+struct DuplicateRow {
+    path: String,
+    plugins: String,
+    suggestion: String,
+}
+
+fn list_duplicates(
+    lock_file: &LockFile,
+    config: Option<&config::Config>,
+    format: Option<cli::ListFormat>,
+    style: JsonStyle,
+) -> anyhow::Result<String> {
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let data_dir = utils::load_pez_data_dir()?;
+    let found =
+        duplicates::installed_and_configured(lock_file, config, &fish_config_dir, &data_dir);
+    let format = format.unwrap_or(cli::ListFormat::Plain);
+    if found.is_empty() && format.structured().is_none() {
+        info!(
+            "{}No files are claimed by more than one plugin.",
+            Emoji("🎉 ", "")
+        );
+        return Ok(String::new());
+    }
+
+    fn describe(claim: &duplicates::Claim) -> String {
+        if claim.installed {
+            claim.plugin.clone()
+        } else {
+            format!("{} (not installed)", claim.plugin)
+        }
+    }
+    let output = match format {
+        cli::ListFormat::Github => anyhow::bail!("--format github only applies to --outdated"),
+        cli::ListFormat::Plain => {
+            let mut out = String::new();
+            for duplicate in &found {
+                out.push_str(&format!("{}\n", duplicate.path.display()));
+                for claim in &duplicate.claims {
+                    out.push_str(&format!("  - {}\n", describe(claim)));
+                }
+                out.push_str(&format!("  hint: {}\n", duplicate.suggestion()));
+            }
+            out
+        }
+        cli::ListFormat::Table => {
+            let rows: Vec<DuplicateRow> = found
+                .iter()
+                .map(|d| DuplicateRow {
+                    path: d.path.display().to_string(),
+                    plugins: d.claims.iter().map(describe).collect::<Vec<_>>().join("\n"),
+                    suggestion: d.suggestion(),
+                })
+                .collect();
+            Table::new(&rows).to_string()
+        }
+        cli::ListFormat::Json | cli::ListFormat::Yaml => {
+            let entries: Vec<_> = found
+                .iter()
+                .map(|d| {
+                    json!({
+                        "path": d.path,
+                        "plugins": d.claims,
+                        "suggestion": d.suggestion(),
+                    })
+                })
+                .collect();
+            output::to_structured(&entries, format.structured().unwrap(), style)?
+        }
+    };
+    Ok(output)
+}
+
+fn list_json(
+    plugins: &[Plugin],
+    config: Option<&crate::config::Config>,
+    format: output::Structured,
+    style: JsonStyle,
+) -> anyhow::Result<String> {
     fn selector_of(
         cfg: Option<&crate::config::Config>,
         repo: &crate::models::PluginRepo,
@@ -344,18 +672,23 @@ fn list_json(plugins: &[Plugin], config: Option<&crate::config::Config>) -> anyh
                 "repo": p.repo.as_str(),
                 "source": p.source,
                 "selector": selector_of(config, &p.repo),
+                "default_branch": p.default_branch,
                 "commit": p.commit_sha,
+                "updated_at": p.updated_at,
             }))
             .collect::<Vec<_>>()
     );
-    Ok(serde_json::to_string_pretty(&value)?)
+    output::to_structured(&value, format, style)
 }
 
 fn list_outdated_json(
     plugins: &[Plugin],
     config: Option<&config::Config>,
+    policy: &CachePolicy,
+    format: output::Structured,
+    style: JsonStyle,
 ) -> anyhow::Result<String> {
-    let outdated_plugins = get_outdated_plugins(plugins, config)?;
+    let outdated_plugins = get_outdated_plugins(plugins, config, policy)?;
     if outdated_plugins.is_empty() {
         info!("{}All plugins are up to date!", Emoji("🎉 ", ""));
         return Ok(String::new());
@@ -374,10 +707,10 @@ fn list_outdated_json(
             })
             .collect::<Vec<_>>()
     );
-    Ok(serde_json::to_string_pretty(&value)?)
+    output::to_structured(&value, format, style)
 }
 
-fn describe_selection(selection: &resolver::Selection) -> String {
+pub(crate) fn describe_selection(selection: &resolver::Selection) -> String {
     match selection {
         resolver::Selection::DefaultHead => "origin/HEAD".to_string(),
         resolver::Selection::Latest => "latest".to_string(),
@@ -412,6 +745,7 @@ mod tests {
                 source: "source".to_string(),
                 commit_sha: "commit_sha".to_string(),
                 files: vec![],
+                ..Default::default()
             },
             Plugin {
                 name: "name2".to_string(),
@@ -423,6 +757,7 @@ mod tests {
                 source: "source2".to_string(),
                 commit_sha: "commit_sha2".to_string(),
                 files: vec![],
+                ..Default::default()
             },
         ];
 
@@ -437,10 +772,17 @@ mod tests {
         let args = cli::ListArgs {
             format: Some(cli::ListFormat::Plain),
             outdated: false,
+            max_age: None,
+            cached: false,
+            fail_if_outdated: false,
             filter: Some(cli::ListFilter::Remote),
+            stale: None,
+            duplicates: false,
+            sort: None,
+            columns: None,
         };
 
-        let output = with_env(&env, || run(&args).unwrap());
+        let output = with_env(&env, || run(&args, &RuntimeOptions::default()).unwrap());
         assert!(output.contains("owner/remote"));
         assert!(!output.contains("owner/local"));
     }
@@ -452,11 +794,20 @@ mod tests {
         let args = cli::ListArgs {
             format: Some(cli::ListFormat::Plain),
             outdated: false,
+            max_age: None,
+            cached: false,
+            fail_if_outdated: false,
             filter: Some(cli::ListFilter::Remote),
+            stale: None,
+            duplicates: false,
+            sort: None,
+            columns: None,
         };
 
         let mut buffer = Vec::new();
-        let output = with_env(&env, || run_with_writer(&args, &mut buffer).unwrap());
+        let output = with_env(&env, || {
+            run_with_writer(&args, &RuntimeOptions::default(), &mut buffer).unwrap()
+        });
         let printed = String::from_utf8(buffer).unwrap();
         assert_eq!(printed, output);
         assert!(!printed.is_empty());
@@ -469,14 +820,57 @@ mod tests {
         let args = cli::ListArgs {
             format: Some(cli::ListFormat::Table),
             outdated: false,
+            max_age: None,
+            cached: false,
+            fail_if_outdated: false,
             filter: None,
+            stale: None,
+            duplicates: false,
+            sort: None,
+            columns: None,
         };
 
-        let output = with_env(&env, || run(&args).unwrap());
+        let output = with_env(&env, || run(&args, &RuntimeOptions::default()).unwrap());
         assert!(output.contains("branch:main"));
         assert!(output.contains("abcdefg"));
     }
 
+    #[test]
+    fn list_table_sorts_rows_and_picks_columns() {
+        let mut env = TestEnvironmentSetup::new();
+        setup_list_env(&mut env);
+        let mut args = cli::ListArgs {
+            format: Some(cli::ListFormat::Table),
+            outdated: false,
+            max_age: None,
+            cached: false,
+            fail_if_outdated: false,
+            filter: None,
+            stale: None,
+            duplicates: false,
+            sort: Some(cli::ListSort::Name),
+            columns: Some(vec![cli::ListColumn::Repo, cli::ListColumn::Commit]),
+        };
+
+        let output = with_env(&env, || run(&args, &RuntimeOptions::default()).unwrap());
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[1].contains("repo") && lines[1].contains("commit"));
+        assert!(!output.contains("source") && !output.contains("branch:main"));
+        let local = output.find("owner/local").unwrap();
+        let remote = output.find("owner/remote").unwrap();
+        assert!(local < remote, "{output}");
+        assert!(
+            lines[3]
+                .trim_start_matches('|')
+                .trim()
+                .starts_with("owner/local")
+        );
+
+        args.format = Some(cli::ListFormat::Json);
+        let err = with_env(&env, || run(&args, &RuntimeOptions::default()).unwrap_err());
+        assert!(err.to_string().contains("--format table"), "{err}");
+    }
+
     #[test]
     fn list_json_includes_selector() {
         let mut env = TestEnvironmentSetup::new();
@@ -484,10 +878,17 @@ mod tests {
         let args = cli::ListArgs {
             format: Some(cli::ListFormat::Json),
             outdated: false,
+            max_age: None,
+            cached: false,
+            fail_if_outdated: false,
             filter: None,
+            stale: None,
+            duplicates: false,
+            sort: None,
+            columns: None,
         };
 
-        let output = with_env(&env, || run(&args).unwrap());
+        let output = with_env(&env, || run(&args, &RuntimeOptions::default()).unwrap());
         let remote = remote_repo.as_str();
         let value: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
         let plugin = value
@@ -510,6 +911,8 @@ mod tests {
         let config = config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: None,
@@ -517,7 +920,13 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         };
         let plugins = vec![Plugin {
             name: "remote".to_string(),
@@ -525,13 +934,97 @@ mod tests {
             source: repo.default_remote_source(),
             commit_sha: "abcdefghi".to_string(),
             files: vec![],
+            ..Default::default()
         }];
 
-        let output = list_table(&plugins, Some(&config));
+        let output = list_table(&plugins, Some(&config), None, DEFAULT_COLUMNS);
         assert!(output.contains("branch:main"));
         assert!(output.contains(repo_str.as_str()));
     }
 
+    #[test]
+    fn unpinned_plugins_show_the_recorded_default_branch() {
+        let repo: PluginRepo = "owner/remote".parse().unwrap();
+        let config = config::Config {
+            plugins: Some(vec![PluginSpec {
+                name: None,
+                prerelease: false,
+                disabled: false,
+                source: config::PluginSource::Repo {
+                    repo: repo.clone(),
+                    version: None,
+                    branch: None,
+                    tag: None,
+                    commit: None,
+                },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
+            }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        };
+        let mut plugins = vec![Plugin {
+            name: "remote".to_string(),
+            repo: repo.clone(),
+            source: repo.default_remote_source(),
+            commit_sha: "abcdefghi".to_string(),
+            files: vec![],
+            ..Default::default()
+        }];
+        assert!(!list_table(&plugins, Some(&config), None, DEFAULT_COLUMNS).contains("default:"));
+
+        plugins[0].default_branch = Some("trunk".to_string());
+        assert!(
+            list_table(&plugins, Some(&config), None, DEFAULT_COLUMNS).contains("default:trunk")
+        );
+        let json = list_json(
+            &plugins,
+            Some(&config),
+            output::Structured::Json,
+            JsonStyle::Pretty,
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_str(json.trim()).unwrap();
+        assert_eq!(json[0]["default_branch"].as_str(), Some("trunk"));
+    }
+
+    #[test]
+    fn stale_filter_and_age_use_updated_at() {
+        let day = 86_400;
+        let now = 200 * day;
+        let mut plugin = Plugin {
+            name: "old".to_string(),
+            repo: PluginRepo::new(None, "owner".into(), "old".into()).unwrap(),
+            source: "https://example.com/owner/old".to_string(),
+            commit_sha: "abcdefghi".to_string(),
+            files: vec![],
+            ..Default::default()
+        };
+        let ninety_days = std::time::Duration::from_secs(90 * day);
+        assert!(is_stale(&plugin, ninety_days, now));
+        assert_eq!(format_age(plugin.updated_at, now), "-");
+
+        plugin.updated_at = Some(now - 100 * day);
+        assert!(is_stale(&plugin, ninety_days, now));
+        assert_eq!(format_age(plugin.updated_at, now), "100d ago");
+
+        plugin.updated_at = Some(now - 3 * 3_600);
+        assert!(!is_stale(&plugin, ninety_days, now));
+        assert_eq!(format_age(plugin.updated_at, now), "3h ago");
+        assert_eq!(format_age(Some(now), now), "just now");
+
+        let output = list_table(&[plugin.clone()], None, None, DEFAULT_COLUMNS);
+        assert!(output.contains("updated"));
+        assert!(output.contains("ago"));
+        let json: serde_json::Value = serde_json::from_str(
+            &list_json(&[plugin], None, output::Structured::Json, JsonStyle::Pretty).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json[0]["updated_at"].as_u64(), Some(now - 3 * 3_600));
+    }
+
     #[test]
     fn describe_selection_formats_variants() {
         assert_eq!(
@@ -587,6 +1080,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn list_duplicates_reports_shared_destinations() {
+        let mut env = TestEnvironmentSetup::new();
+        let shared = |repo: &str, name: &str| {
+            let repo: PluginRepo = repo.parse().unwrap();
+            Plugin {
+                name: repo.repo.clone(),
+                source: repo.default_remote_source(),
+                repo,
+                commit_sha: "abc".into(),
+                files: vec![crate::lock_file::PluginFile {
+                    dir: crate::models::TargetDir::Functions,
+                    name: name.into(),
+                }],
+                ..Default::default()
+            }
+        };
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![
+                shared("owner/a", "x.fish"),
+                shared("owner/b", "x.fish"),
+                shared("owner/c", "c.fish"),
+            ],
+        });
+        let mut args = cli::ListArgs {
+            format: None,
+            outdated: false,
+            max_age: None,
+            cached: false,
+            fail_if_outdated: false,
+            filter: None,
+            stale: None,
+            duplicates: true,
+            sort: None,
+            columns: None,
+        };
+
+        let output = with_env(&env, || run(&args, &RuntimeOptions::default()).unwrap());
+        let expected_path = env.fish_config_dir.join("functions/x.fish");
+        assert!(output.starts_with(&format!("{}\n", expected_path.display())));
+        assert!(output.contains("  - owner/a\n  - owner/b\n"));
+        assert!(output.contains("pez uninstall owner/b"));
+        assert!(!output.contains("c.fish"));
+
+        args.format = Some(cli::ListFormat::Json);
+        let output = with_env(&env, || run(&args, &RuntimeOptions::default()).unwrap());
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 1);
+        assert_eq!(value[0]["plugins"][1]["repo"], "owner/b");
+        assert_eq!(value[0]["plugins"][1]["installed"], true);
+
+        assert!(
+            <cli::Cli as clap::Parser>::try_parse_from([
+                "pez",
+                "list",
+                "--duplicates",
+                "--outdated"
+            ])
+            .is_err()
+        );
+    }
+
     fn with_env<F: FnOnce() -> R, R>(env: &TestEnvironmentSetup, f: F) -> R {
         let _lock = env_lock().lock().unwrap();
         let _guard = EnvOverride::new(&["__fish_config_dir", "PEZ_CONFIG_DIR", "PEZ_DATA_DIR"]);
@@ -618,6 +1174,7 @@ mod tests {
                     source: remote_repo.default_remote_source(),
                     commit_sha: "abcdefghi".to_string(),
                     files: vec![],
+                    ..Default::default()
                 },
                 Plugin {
                     name: "local".to_string(),
@@ -625,12 +1182,15 @@ mod tests {
                     source: "/tmp/local".to_string(),
                     commit_sha: "localsha".to_string(),
                     files: vec![],
+                    ..Default::default()
                 },
             ],
         });
         env.setup_config(config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: remote_repo.clone(),
                     version: None,
@@ -638,7 +1198,13 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         (remote_repo, local_repo)
     }
@@ -834,6 +1400,8 @@ mod tests {
         let config = config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: None,
@@ -841,7 +1409,13 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         };
 
         let plugins = vec![Plugin {
@@ -850,9 +1424,10 @@ mod tests {
             source: remote,
             commit_sha: base_commit.clone(),
             files: vec![],
+            ..Default::default()
         }];
 
-        let output = list_outdated(&plugins, Some(&config)).unwrap();
+        let output = list_outdated(&plugins, Some(&config), &CachePolicy::Refresh).unwrap();
         assert_eq!(output, format!("{}\n", repo_str));
         assert_ne!(base_commit, branch_commit);
         drop(tmp);
@@ -875,6 +1450,8 @@ mod tests {
         let config = config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: None,
@@ -882,7 +1459,13 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         };
 
         let plugins = vec![Plugin {
@@ -891,9 +1474,10 @@ mod tests {
             source: remote,
             commit_sha: base_commit.clone(),
             files: vec![],
+            ..Default::default()
         }];
 
-        let output = list_outdated_table(&plugins, Some(&config)).unwrap();
+        let output = list_outdated_table(&plugins, Some(&config), &CachePolicy::Refresh).unwrap();
         assert!(output.contains(&base_commit[..7]));
         assert!(output.contains(&branch_commit[..7]));
         drop(tmp);
@@ -917,6 +1501,8 @@ mod tests {
         let config = config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: None,
@@ -924,7 +1510,13 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         };
 
         let plugins = vec![Plugin {
@@ -933,9 +1525,17 @@ mod tests {
             source: remote,
             commit_sha: base_commit.clone(),
             files: vec![],
+            ..Default::default()
         }];
 
-        let output = list_outdated_json(&plugins, Some(&config)).unwrap();
+        let output = list_outdated_json(
+            &plugins,
+            Some(&config),
+            &CachePolicy::Refresh,
+            output::Structured::Json,
+            JsonStyle::Pretty,
+        )
+        .unwrap();
         let value: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
         let entry = value.as_array().unwrap().first().unwrap();
         assert_eq!(entry["repo"].as_str(), Some(repo_str.as_str()));
@@ -944,6 +1544,117 @@ mod tests {
         drop(tmp);
     }
 
+    #[test]
+    fn list_outdated_serves_cache_without_contacting_remote() {
+        let _lock = env_lock().lock().unwrap();
+        let env = TestEnvironmentSetup::new();
+        let _env_guard = configure_env(&env);
+
+        let repo = PluginRepo {
+            host: None,
+            owner: "owner".into(),
+            repo: "cached".into(),
+        };
+        let plugins = vec![Plugin {
+            name: "cached".into(),
+            repo: repo.clone(),
+            source: repo.default_remote_source(),
+            commit_sha: "current".into(),
+            files: vec![],
+            ..Default::default()
+        }];
+        let cache_path = utils::load_pez_cache_dir()
+            .unwrap()
+            .join(update_cache::UPDATE_CHECK_FILE_NAME);
+        let mut cache = update_cache::UpdateCheckCache::default();
+        cache.record(&repo, "origin/HEAD", "newer", 0);
+        cache.save(&cache_path).unwrap();
+
+        let cached = get_outdated_plugins(&plugins, None, &CachePolicy::CachedOnly).unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].latest, "newer");
+
+        // The entry is far older than the max age and the clone is missing, so nothing is served.
+        let expired = get_outdated_plugins(
+            &plugins,
+            None,
+            &CachePolicy::MaxAge(std::time::Duration::from_secs(60)),
+        )
+        .unwrap();
+        assert!(expired.is_empty());
+    }
+
+    #[test]
+    fn fail_if_outdated_exits_one_only_when_updates_exist() {
+        let _lock = env_lock().lock().unwrap();
+        let mut env = TestEnvironmentSetup::new();
+        let _env_guard = configure_env(&env);
+
+        let repo: PluginRepo = "owner/cached".parse().unwrap();
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![Plugin {
+                name: "cached".into(),
+                repo: repo.clone(),
+                source: repo.default_remote_source(),
+                commit_sha: "current".into(),
+                files: vec![],
+                ..Default::default()
+            }],
+        });
+        let cache_path = utils::load_pez_cache_dir()
+            .unwrap()
+            .join(update_cache::UPDATE_CHECK_FILE_NAME);
+        let record = |latest: &str| {
+            let mut cache = update_cache::UpdateCheckCache::default();
+            cache.record(&repo, "origin/HEAD", latest, 0);
+            cache.save(&cache_path).unwrap();
+        };
+        let args = cli::ListArgs {
+            format: None,
+            outdated: true,
+            max_age: None,
+            cached: true,
+            fail_if_outdated: true,
+            filter: None,
+            stale: None,
+            duplicates: false,
+            sort: None,
+            columns: None,
+        };
+
+        record("newer");
+        let mut written = Vec::new();
+        let err = run_with_writer(&args, &RuntimeOptions::default(), &mut written).unwrap_err();
+        let exit = err.downcast_ref::<utils::ExitError>().expect("exit error");
+        assert_eq!(exit.code, EXIT_OUTDATED);
+        assert_eq!(String::from_utf8(written).unwrap(), "owner/cached\n");
+
+        let github = cli::ListArgs {
+            format: Some(cli::ListFormat::Github),
+            fail_if_outdated: false,
+            ..args.clone()
+        };
+        assert_eq!(
+            run_with_writer(&github, &RuntimeOptions::default(), &mut Vec::new()).unwrap(),
+            "::warning title=pez%3A owner/cached is outdated::owner/cached can be updated \
+             from current to newer; run `pez upgrade owner/cached`\n"
+        );
+        let not_outdated = cli::ListArgs {
+            outdated: false,
+            ..github.clone()
+        };
+        assert!(
+            run_with_writer(&not_outdated, &RuntimeOptions::default(), &mut Vec::new()).is_err()
+        );
+
+        record("current");
+        assert_eq!(
+            run_with_writer(&args, &RuntimeOptions::default(), &mut Vec::new()).unwrap(),
+            ""
+        );
+    }
+
     #[test]
     fn list_outdated_respects_branch_selector() {
         let _lock = env_lock().lock().unwrap();
@@ -961,6 +1672,8 @@ mod tests {
         let config = config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: None,
@@ -968,7 +1681,13 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         };
         env.setup_config(config.clone());
 
@@ -980,11 +1699,13 @@ mod tests {
                 source: remote.clone(),
                 commit_sha: base_commit.clone(),
                 files: vec![],
+                ..Default::default()
             }],
         });
 
         let plugins = env.lock_file.as_ref().unwrap().plugins.clone();
-        let outdated = get_outdated_plugins(&plugins, Some(&config)).unwrap();
+        let outdated =
+            get_outdated_plugins(&plugins, Some(&config), &CachePolicy::Refresh).unwrap();
         assert_eq!(outdated.len(), 1);
         assert_eq!(outdated[0].latest, branch_commit);
 
@@ -1009,6 +1730,8 @@ mod tests {
         let config = config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: None,
@@ -1016,7 +1739,13 @@ mod tests {
                     tag: Some("v1.0.0".into()),
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         };
         env.setup_config(config.clone());
 
@@ -1028,11 +1757,13 @@ mod tests {
                 source: remote.clone(),
                 commit_sha: tag_commit.clone(),
                 files: vec![],
+                ..Default::default()
             }],
         });
 
         let plugins = env.lock_file.as_ref().unwrap().plugins.clone();
-        let outdated = get_outdated_plugins(&plugins, Some(&config)).unwrap();
+        let outdated =
+            get_outdated_plugins(&plugins, Some(&config), &CachePolicy::Refresh).unwrap();
         assert!(outdated.is_empty());
 
         // ensure fixture not dropped early
@@ -1057,6 +1788,8 @@ mod tests {
         let config = config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: Some("v1".into()),
@@ -1064,7 +1797,13 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         };
         env.setup_config(config.clone());
 
@@ -1076,11 +1815,13 @@ mod tests {
                 source: remote.clone(),
                 commit_sha: v1_commit.clone(),
                 files: vec![],
+                ..Default::default()
             }],
         });
 
         let plugins = env.lock_file.as_ref().unwrap().plugins.clone();
-        let outdated = get_outdated_plugins(&plugins, Some(&config)).unwrap();
+        let outdated =
+            get_outdated_plugins(&plugins, Some(&config), &CachePolicy::Refresh).unwrap();
         assert_eq!(outdated.len(), 1);
         assert_eq!(outdated[0].latest, latest_commit);
         drop(tmp);
@@ -1103,6 +1844,8 @@ mod tests {
         let config = config::Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: None,
@@ -1110,7 +1853,13 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         };
         env.setup_config(config.clone());
 
@@ -1122,11 +1871,13 @@ mod tests {
                 source: remote,
                 commit_sha: base_commit.clone(),
                 files: vec![],
+                ..Default::default()
             }],
         });
 
         let plugins = env.lock_file.as_ref().unwrap().plugins.clone();
-        let (logs, result) = capture_logs(|| get_outdated_plugins(&plugins, Some(&config)));
+        let (logs, result) =
+            capture_logs(|| get_outdated_plugins(&plugins, Some(&config), &CachePolicy::Refresh));
         let outdated = result.unwrap();
         assert!(outdated.is_empty());
         assert!(
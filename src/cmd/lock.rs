@@ -0,0 +1,485 @@
+use crate::{
+    cli::{self, LockDiffArgs},
+    config::Config,
+    git,
+    lock_file::{self, LockFile, Plugin},
+    models::{PluginRepo, ResolvedInstallTarget},
+    output::{self, out, outln},
+    resolver,
+    runtime::RuntimeOptions,
+    utils,
+};
+
+use anyhow::Context;
+use console::Emoji;
+use serde_derive::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Read},
+    path,
+};
+use tracing::{debug, info};
+
+/// A plugin's locked commit and the tags pointing at it, when a local clone knows them.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct Revision {
+    pub(crate) commit: String,
+    pub(crate) tags: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct LockedPlugin {
+    pub(crate) repo: String,
+    #[serde(flatten)]
+    pub(crate) revision: Revision,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct PluginChange {
+    pub(crate) repo: String,
+    pub(crate) from: Revision,
+    pub(crate) to: Revision,
+    /// Old and new `source` when the plugin moved to another URL or path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) source: Option<[String; 2]>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub(crate) struct LockDiff {
+    pub(crate) added: Vec<LockedPlugin>,
+    pub(crate) removed: Vec<LockedPlugin>,
+    pub(crate) changed: Vec<PluginChange>,
+}
+
+impl LockDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub(crate) fn run(args: &cli::LockArgs, options: &RuntimeOptions) -> anyhow::Result<()> {
+    match &args.command {
+        None => {
+            let _ = run_regenerate()?;
+        }
+        Some(cli::LockCommand::Diff(diff_args)) => {
+            let _ = run_diff(diff_args, options)?;
+        }
+    }
+    Ok(())
+}
+
+/// Resolve every plugin in pez.toml again and rewrite pez-lock.toml. Nothing is copied
+/// to or removed from the fish config directory; `pez upgrade --to-lock` applies it.
+fn run_regenerate() -> anyhow::Result<LockDiff> {
+    let (config, _) = utils::load_config()?;
+    let (old, lock_file_path) = utils::load_or_create_lock_file()?;
+    let data_dir = utils::load_pez_data_dir()?;
+
+    let fresh = regenerate(&config, &old, &data_dir)?;
+    fresh.save(&lock_file_path)?;
+    let diff = diff(&old, &fresh, local_tags(&data_dir));
+    if diff.is_empty() {
+        info!("{}pez-lock.toml is up to date.", Emoji("🎉 ", ""));
+    } else {
+        out!("{}", render_plain(&diff));
+        info!(
+            "{}Wrote {}; run `pez upgrade --to-lock` to install these commits",
+            Emoji("🔒 ", ""),
+            lock_file_path.display()
+        );
+    }
+    Ok(diff)
+}
+
+/// A lock file with each pez.toml plugin at the commit its selector picks today.
+/// Installed files are unchanged, so entries keep the files, backups, and update time
+/// already recorded. Plugins no longer in pez.toml stay until `pez prune` removes them.
+fn regenerate(config: &Config, old: &LockFile, data_dir: &path::Path) -> anyhow::Result<LockFile> {
+    let mut fresh = lock_file::init();
+    for spec in config.plugins.iter().flatten() {
+        let resolved = spec.to_resolved()?;
+        let previous = old.get_plugin_by_repo(&resolved.plugin_repo);
+        let (commit_sha, default_branch) = if resolved.is_local {
+            ("local".to_string(), None)
+        } else {
+            resolve_commit(&resolved, data_dir)?
+        };
+        debug!("Locked {} at {}", resolved.plugin_repo, commit_sha);
+        fresh.plugins.push(Plugin {
+            name: spec.get_name()?,
+            repo: resolved.plugin_repo.clone(),
+            source: resolved.source.clone(),
+            commit_sha,
+            files: previous.map(|p| p.files.clone()).unwrap_or_default(),
+            backups: previous.map(|p| p.backups.clone()).unwrap_or_default(),
+            updated_at: previous.and_then(|p| p.updated_at),
+            default_branch: default_branch
+                .or_else(|| previous.and_then(|p| p.default_branch.clone())),
+            disabled_files: previous
+                .map(|p| p.disabled_files.clone())
+                .unwrap_or_default(),
+        });
+    }
+    for plugin in &old.plugins {
+        if !fresh.contains_repo(&plugin.repo) {
+            info!(
+                "{}Kept {}: no longer in pez.toml; run `pez prune` to remove it",
+                Emoji("🚧 ", ""),
+                plugin.repo
+            );
+            fresh.plugins.push(plugin.clone());
+        }
+    }
+    Ok(fresh)
+}
+
+/// Fetch into the plugin's clone and resolve its selector there. Plugins without a
+/// clone are resolved in a throwaway one, so `pez install` still clones them later.
+/// Also returns the remote's default branch, when the clone knows it.
+fn resolve_commit(
+    resolved: &ResolvedInstallTarget,
+    data_dir: &path::Path,
+) -> anyhow::Result<(String, Option<String>)> {
+    let selection = resolver::selection_from_ref_kind(&resolved.ref_kind);
+    let resolve = |repo: &git2::Repository| {
+        let commit = git::resolve_selection(repo, &selection, resolved.channel)
+            .with_context(|| format!("failed to resolve {}", resolved.plugin_repo))?;
+        Ok((commit, git::default_branch(repo)))
+    };
+    match git2::Repository::open(data_dir.join(resolved.plugin_repo.as_str())) {
+        Ok(repo) => {
+            git::fetch_all(&repo)?;
+            resolve(&repo)
+        }
+        Err(_) => {
+            let temp = tempfile::tempdir()?;
+            let repo = git::clone_repository(&resolved.source, temp.path())?;
+            resolve(&repo)
+        }
+    }
+}
+
+fn run_diff(args: &LockDiffArgs, options: &RuntimeOptions) -> anyhow::Result<LockDiff> {
+    if args.old == args.new && args.old.as_os_str() == "-" {
+        anyhow::bail!("Only one of the lock files can be read from stdin");
+    }
+    let old = read_lock_file(&args.old)?;
+    let new = read_lock_file(&args.new)?;
+
+    let data_dir = utils::load_pez_data_dir()?;
+    let diff = diff(&old, &new, local_tags(&data_dir));
+
+    match args.format {
+        Some(cli::LockDiffFormat::Json) => {
+            outln!("{}", output::to_json(&diff, options.json_style)?)
+        }
+        None if diff.is_empty() => info!("No plugin changes between the lock files."),
+        None => out!("{}", render_plain(&diff)),
+    }
+    Ok(diff)
+}
+
+/// Names the tags at a commit from the plugin's clone in the data dir, if it has one.
+fn local_tags(data_dir: &path::Path) -> impl FnMut(&PluginRepo, &str) -> Vec<String> + '_ {
+    let mut clones: BTreeMap<String, Option<git2::Repository>> = BTreeMap::new();
+    move |repo, sha| {
+        let clone = clones.entry(repo.as_str()).or_insert_with(|| {
+            git2::Repository::open(data_dir.join(repo.as_str()))
+                .inspect_err(|e| debug!("No clone to name tags for {}: {}", repo, e))
+                .ok()
+        });
+        clone
+            .as_ref()
+            .and_then(|clone| git::tags_at_commit(clone, sha).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Read a lock file from `path`, or from stdin when it is `-`.
+fn read_lock_file(path: &path::Path) -> anyhow::Result<LockFile> {
+    if path.as_os_str() == "-" {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content)?;
+        return lock_file::parse(&content, "stdin");
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+    lock_file::parse(&content, &path.display().to_string())
+}
+
+/// Compare plugins by repo. `tags` names the tags at a commit of a repo.
+fn diff(
+    old: &LockFile,
+    new: &LockFile,
+    mut tags: impl FnMut(&PluginRepo, &str) -> Vec<String>,
+) -> LockDiff {
+    let by_repo = |lock: &LockFile| -> BTreeMap<String, Plugin> {
+        lock.plugins
+            .iter()
+            .map(|p| (p.repo.as_str(), p.clone()))
+            .collect()
+    };
+    let (old, new) = (by_repo(old), by_repo(new));
+    let mut revision = |plugin: &Plugin| Revision {
+        commit: plugin.commit_sha.clone(),
+        tags: tags(&plugin.repo, &plugin.commit_sha),
+    };
+
+    let mut diff = LockDiff::default();
+    for (repo, plugin) in &new {
+        match old.get(repo) {
+            None => diff.added.push(LockedPlugin {
+                repo: repo.clone(),
+                revision: revision(plugin),
+            }),
+            Some(previous)
+                if previous.commit_sha != plugin.commit_sha || previous.source != plugin.source =>
+            {
+                diff.changed.push(PluginChange {
+                    repo: repo.clone(),
+                    from: revision(previous),
+                    to: revision(plugin),
+                    source: (previous.source != plugin.source)
+                        .then(|| [previous.source.clone(), plugin.source.clone()]),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    for (repo, plugin) in &old {
+        if !new.contains_key(repo) {
+            diff.removed.push(LockedPlugin {
+                repo: repo.clone(),
+                revision: revision(plugin),
+            });
+        }
+    }
+    diff
+}
+
+fn describe(revision: &Revision) -> String {
+    let short = revision.commit.get(..7).unwrap_or(&revision.commit);
+    if revision.tags.is_empty() {
+        short.to_string()
+    } else {
+        format!("{short} ({})", revision.tags.join(", "))
+    }
+}
+
+fn render_plain(diff: &LockDiff) -> String {
+    let width = diff
+        .added
+        .iter()
+        .chain(&diff.removed)
+        .map(|p| p.repo.len())
+        .chain(diff.changed.iter().map(|c| c.repo.len()))
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for plugin in &diff.added {
+        out.push_str(&format!(
+            "+ {:width$}  {}\n",
+            plugin.repo,
+            describe(&plugin.revision)
+        ));
+    }
+    for plugin in &diff.removed {
+        out.push_str(&format!(
+            "- {:width$}  {}\n",
+            plugin.repo,
+            describe(&plugin.revision)
+        ));
+    }
+    for change in &diff.changed {
+        if change.from.commit != change.to.commit {
+            out.push_str(&format!(
+                "~ {:width$}  {} -> {}\n",
+                change.repo,
+                describe(&change.from),
+                describe(&change.to)
+            ));
+        }
+        if let Some([from, to]) = &change.source {
+            out.push_str(&format!(
+                "~ {:width$}  source {} -> {}\n",
+                change.repo, from, to
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugin(repo: &str, sha: &str) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: sha.into(),
+            files: vec![],
+            ..Default::default()
+        }
+    }
+
+    fn lock(plugins: Vec<Plugin>) -> LockFile {
+        LockFile {
+            version: 2,
+            plugins,
+        }
+    }
+
+    fn tag_names(_: &PluginRepo, sha: &str) -> Vec<String> {
+        match sha {
+            "1111111aaaa" => vec!["v1.0.0".into()],
+            "2222222bbbb" => vec!["v1.1.0".into(), "latest".into()],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_plugins() {
+        let mut moved = plugin("owner/moved", "3333333cccc");
+        let old = lock(vec![
+            plugin("owner/kept", "0000000dddd"),
+            plugin("owner/bumped", "1111111aaaa"),
+            plugin("owner/gone", "4444444eeee"),
+            moved.clone(),
+        ]);
+        moved.source = "https://gitlab.com/owner/moved".into();
+        let new = lock(vec![
+            plugin("owner/new", "5555555ffff"),
+            plugin("owner/kept", "0000000dddd"),
+            plugin("owner/bumped", "2222222bbbb"),
+            moved,
+        ]);
+
+        let diff = diff(&old, &new, tag_names);
+        assert_eq!(
+            diff.added
+                .iter()
+                .map(|p| p.repo.as_str())
+                .collect::<Vec<_>>(),
+            vec!["owner/new"]
+        );
+        assert_eq!(diff.removed[0].repo, "owner/gone");
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.changed[0].repo, "owner/bumped");
+        assert_eq!(diff.changed[0].to.tags, vec!["v1.1.0", "latest"]);
+        assert_eq!(diff.changed[0].source, None);
+
+        assert_eq!(
+            render_plain(&diff),
+            "\
++ owner/new     5555555
+- owner/gone    4444444
+~ owner/bumped  1111111 (v1.0.0) -> 2222222 (v1.1.0, latest)
+~ owner/moved   source https://github.com/owner/moved -> https://gitlab.com/owner/moved
+"
+        );
+        assert!(super::diff(&old, &old, tag_names).is_empty());
+    }
+
+    #[test]
+    fn read_lock_file_reports_path_in_errors() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("pez-lock.toml");
+        fs::write(&path, "version = 99\nplugins = []\n").unwrap();
+        let err = read_lock_file(&path).unwrap_err();
+        assert!(
+            err.to_string().contains(&path.display().to_string()),
+            "{err}"
+        );
+        assert!(read_lock_file(&temp.path().join("missing.toml")).is_err());
+    }
+
+    #[test]
+    fn regenerate_resolves_selectors_and_keeps_installed_state() {
+        use crate::{
+            config::{PluginSource, PluginSpec},
+            lock_file::PluginFile,
+            models::TargetDir,
+        };
+
+        let temp = tempfile::tempdir().unwrap();
+        let upstream_path = temp.path().join("upstream");
+        let upstream = git2::Repository::init(&upstream_path).unwrap();
+        let sig = git2::Signature::now("pez", "pez@example.com").unwrap();
+        let tree = upstream
+            .find_tree(upstream.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let tagged = upstream
+            .commit(Some("HEAD"), &sig, &sig, "v1", &tree, &[])
+            .unwrap();
+        upstream
+            .tag_lightweight(
+                "v1.0.0",
+                &upstream.find_object(tagged, None).unwrap(),
+                false,
+            )
+            .unwrap();
+        let parent = upstream.find_commit(tagged).unwrap();
+        upstream
+            .commit(Some("HEAD"), &sig, &sig, "next", &tree, &[&parent])
+            .unwrap();
+        let data_dir = temp.path().join("data");
+        git2::Repository::clone(
+            upstream_path.to_str().unwrap(),
+            data_dir.join("owner/tagged"),
+        )
+        .unwrap();
+
+        let spec = |source| PluginSpec {
+            name: None,
+            prerelease: false,
+            disabled: false,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
+            source,
+        };
+        let local_path = temp.path().join("local-plugin");
+        let config = Config {
+            plugins: Some(vec![
+                spec(PluginSource::Repo {
+                    repo: "owner/tagged".parse().unwrap(),
+                    version: None,
+                    branch: None,
+                    tag: Some("v1.0.0".into()),
+                    commit: None,
+                }),
+                spec(PluginSource::Path {
+                    path: local_path.display().to_string(),
+                }),
+            ]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        };
+        let mut installed = plugin("owner/tagged", "0000000dddd");
+        installed.files = vec![PluginFile {
+            dir: TargetDir::Functions,
+            name: "tagged.fish".into(),
+        }];
+        installed.updated_at = Some(42);
+        let old = lock(vec![installed, plugin("owner/gone", "4444444eeee")]);
+
+        let fresh = regenerate(&config, &old, &data_dir).unwrap();
+        let repos: Vec<String> = fresh.plugins.iter().map(|p| p.repo.as_str()).collect();
+        assert_eq!(repos.len(), 3);
+        assert_eq!(repos[0], "owner/tagged");
+        assert_eq!(repos[2], "owner/gone");
+
+        let tagged_entry = &fresh.plugins[0];
+        assert_eq!(tagged_entry.commit_sha, tagged.to_string());
+        assert_eq!(tagged_entry.files.len(), 1);
+        assert_eq!(tagged_entry.updated_at, Some(42));
+        assert_eq!(fresh.plugins[1].commit_sha, "local");
+        assert!(fresh.plugins[1].files.is_empty());
+    }
+}
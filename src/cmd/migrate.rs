@@ -1,25 +1,26 @@
 use crate::{
-    cli::{InstallArgs, MigrateArgs},
+    cli::{InstallArgs, MigrateArgs, MigrateFrom},
     config::{self, PluginSource, PluginSpec},
-    models::{InstallTarget, ResolvedInstallTarget},
+    git,
+    models::{InstallTarget, PluginRepo, ResolvedInstallTarget, TargetDir},
+    output::outln,
+    resolver,
+    runtime::RuntimeOptions,
     utils,
 };
 use console::Emoji;
-use std::{
-    fs,
-    io::{BufRead, BufReader},
-};
+use std::{fs, path};
 use tracing::{error, info, warn};
 
 #[derive(Clone)]
-struct MigratedEntry {
+pub(crate) struct MigratedEntry {
     raw: String,
     resolved: ResolvedInstallTarget,
-    spec: PluginSpec,
+    pub(crate) spec: PluginSpec,
 }
 
 impl MigratedEntry {
-    fn new(raw: String, resolved: ResolvedInstallTarget) -> Self {
+    pub(crate) fn new(raw: String, resolved: ResolvedInstallTarget) -> Self {
         let spec = config::PluginSpec::from_resolved(&resolved);
         Self {
             raw,
@@ -29,7 +30,18 @@ impl MigratedEntry {
     }
 }
 
-fn dedup_entries(entries: Vec<MigratedEntry>) -> Vec<MigratedEntry> {
+/// fisher refs are bare git refs, so `owner/repo@<full sha>` is a commit pin, which
+/// pez spells `@commit:<sha>`.
+fn fisher_commit_ref(entry: &str) -> String {
+    match entry.rsplit_once('@') {
+        Some((base, sha)) if sha.len() == 40 && sha.chars().all(|c| c.is_ascii_hexdigit()) => {
+            format!("{base}@commit:{sha}")
+        }
+        _ => entry.to_string(),
+    }
+}
+
+pub(crate) fn dedup_entries(entries: Vec<MigratedEntry>) -> Vec<MigratedEntry> {
     let mut unique: Vec<MigratedEntry> = Vec::new();
     for entry in entries {
         if let Some(pos) = unique
@@ -93,6 +105,80 @@ fn spec_ref_descriptor(spec: &PluginSpec) -> Option<MigratedRef> {
     }
 }
 
+/// Add `entries` to the plugin list of `cfg`, or replace the list when `replace` is
+/// set. Existing specs are only updated as `should_update_existing` allows. Returns
+/// the entries that add or change a spec; with `dry_run`, `cfg` is left untouched.
+pub(crate) fn merge_entries(
+    cfg: &mut config::Config,
+    entries: &[MigratedEntry],
+    replace: bool,
+    dry_run: bool,
+) -> Vec<MigratedEntry> {
+    let mut planned: Vec<MigratedEntry> = Vec::new();
+
+    if replace {
+        planned = entries.to_vec();
+        if dry_run {
+        } else {
+            let specs: Vec<PluginSpec> = planned.iter().map(|entry| entry.spec.clone()).collect();
+            cfg.plugins = Some(specs);
+        }
+    } else if let Some(list) = cfg.plugins.as_mut() {
+        for entry in entries {
+            let repo = &entry.resolved.plugin_repo;
+            let existing_index = list.iter().position(|spec| {
+                spec.get_plugin_repo()
+                    .is_ok_and(|existing_repo| existing_repo == *repo)
+            });
+
+            if let Some(idx) = existing_index {
+                if should_update_existing(&list[idx], &entry.spec) {
+                    if !dry_run {
+                        let existing = &mut list[idx];
+                        existing.source = entry.spec.source.clone();
+                    }
+                    planned.push(entry.clone());
+                }
+            } else {
+                if dry_run {
+                } else {
+                    list.push(entry.spec.clone());
+                }
+                planned.push(entry.clone());
+            }
+        }
+    } else {
+        planned = entries.to_vec();
+        if dry_run {
+        } else {
+            let specs: Vec<PluginSpec> = planned.iter().map(|entry| entry.spec.clone()).collect();
+            cfg.plugins = Some(specs);
+        }
+    }
+    planned
+}
+
+/// Install `entries` from the targets they were read from.
+pub(crate) async fn install_entries(
+    entries: &[MigratedEntry],
+    options: &RuntimeOptions,
+) -> anyhow::Result<()> {
+    let targets: Vec<_> = entries
+        .iter()
+        .map(|entry| InstallTarget::from_raw(entry.raw.clone()))
+        .collect();
+    let install_args = InstallArgs {
+        plugins: Some(targets),
+        force: false,
+        prune: false,
+        dry_run: false,
+        yes: false,
+        temp: false,
+        edit: false,
+    };
+    crate::cmd::install::run(&install_args, options).await
+}
+
 fn should_update_existing(existing: &PluginSpec, incoming: &PluginSpec) -> bool {
     if existing.source == incoming.source {
         return false;
@@ -119,7 +205,7 @@ fn should_update_existing(existing: &PluginSpec, incoming: &PluginSpec) -> bool
     }
 }
 
-fn describe_spec(spec: &PluginSpec) -> String {
+pub(crate) fn describe_spec(spec: &PluginSpec) -> String {
     let mut base = match &spec.source {
         PluginSource::Repo { repo, .. } => repo.as_str(),
         PluginSource::Url { url, .. } => url.clone(),
@@ -142,6 +228,12 @@ fn describe_spec(spec: &PluginSpec) -> String {
 
 fn suggested_migrate_command(args: &MigrateArgs) -> String {
     let mut command = String::from("pez migrate");
+    if !matches!(args.from, MigrateFrom::Fisher) {
+        command.push_str(&format!(" --from {}", from_name(&args.from)));
+    }
+    if let Some(path) = &args.path {
+        command.push_str(&format!(" {}", path.display()));
+    }
     if args.force {
         command.push_str(" --force");
     }
@@ -179,7 +271,8 @@ fn print_next_steps(args: &MigrateArgs, planned_count: usize, install_executed:
 
     if planned_count == 0 {
         info!(
-            "  1) No config changes were applied. Update fish_plugins and rerun pez migrate if needed."
+            "  1) No config changes were applied. Update {} and rerun pez migrate if needed.",
+            source_name(&args.from)
         );
         info!("  2) Verify current plugins: pez list --format table");
         info!("  3) Run diagnostics: pez doctor");
@@ -192,29 +285,69 @@ fn print_next_steps(args: &MigrateArgs, planned_count: usize, install_executed:
     info!("  4) Enable shell hooks if needed: pez activate fish | source");
 }
 
-pub(crate) async fn run(args: &MigrateArgs) -> anyhow::Result<()> {
-    let fish_config_dir = utils::load_fish_config_dir()?;
-    let fisher_plugins_path = fish_config_dir.join("fish_plugins");
-    if !fisher_plugins_path.exists() {
-        error!(
-            "{}fish_plugins not found at {}",
-            Emoji("❌ ", ""),
-            fisher_plugins_path.display()
-        );
-        anyhow::bail!("fish_plugins not found");
+/// Where an importer reads from when no PATH is given; `None` when it needs one.
+fn default_path(from: &MigrateFrom, fish_config_dir: &path::Path) -> Option<path::PathBuf> {
+    match from {
+        MigrateFrom::Fisher => Some(fish_config_dir.join("fish_plugins")),
+        MigrateFrom::Plug => Some(fish_config_dir.join("config.fish")),
+        MigrateFrom::Dir => None,
+        MigrateFrom::Omf => utils::load_omf_dir().ok(),
+        MigrateFrom::Fundle => Some(fish_config_dir.to_path_buf()),
+    }
+}
+
+fn from_name(from: &MigrateFrom) -> &'static str {
+    match from {
+        MigrateFrom::Fisher => "fisher",
+        MigrateFrom::Plug => "plug",
+        MigrateFrom::Dir => "dir",
+        MigrateFrom::Omf => "omf",
+        MigrateFrom::Fundle => "fundle",
+    }
+}
+
+/// What the importer reads, for messages.
+fn source_name(from: &MigrateFrom) -> &'static str {
+    match from {
+        MigrateFrom::Fisher => "fish_plugins",
+        MigrateFrom::Plug => "plugin list",
+        MigrateFrom::Dir => "plugin directory",
+        MigrateFrom::Omf => "Oh My Fish",
+        MigrateFrom::Fundle => "fish config",
     }
+}
+
+/// Run the importer selected with `--from`. Importers only turn their source into
+/// entries; deduping, merging into pez.toml, `--dry-run`, and `--install` are shared.
+fn read_entries(from: &MigrateFrom, path: &path::Path) -> anyhow::Result<Vec<MigratedEntry>> {
+    match from {
+        MigrateFrom::Fisher => Ok(parse_fisher_plugins(&fs::read_to_string(path)?)),
+        MigrateFrom::Plug => Ok(parse_plug_list(&fs::read_to_string(path)?)),
+        MigrateFrom::Dir => read_plugin_dirs(path),
+        MigrateFrom::Omf => read_omf(path),
+        MigrateFrom::Fundle => read_fundle(path),
+    }
+}
 
-    info!(
-        "{}Reading {}",
-        Emoji("📄 ", ""),
-        fisher_plugins_path.display()
-    );
+/// Resolve one install target, warning about (and skipping) ones pez cannot parse.
+pub(crate) fn resolve_entry(raw: String, line: &str) -> Option<MigratedEntry> {
+    match InstallTarget::from_raw(raw.clone()).resolve() {
+        Ok(resolved) => Some(MigratedEntry::new(raw, resolved)),
+        Err(err) => {
+            warn!(
+                "{}Skipping unrecognized entry: {} ({err})",
+                Emoji("⚠ ", ""),
+                line
+            );
+            None
+        }
+    }
+}
 
-    let file = fs::File::open(&fisher_plugins_path)?;
-    let reader = BufReader::new(file);
+/// fisher's `fish_plugins`: one target per line, `jorgebucaran/fisher` itself excluded.
+pub(crate) fn parse_fisher_plugins(content: &str) -> Vec<MigratedEntry> {
     let mut entries: Vec<MigratedEntry> = Vec::new();
-    for line in reader.lines() {
-        let line = line?;
+    for line in content.lines() {
         let trimmed = line.trim();
         if trimmed.is_empty() || trimmed.starts_with('#') {
             continue;
@@ -253,23 +386,268 @@ pub(crate) async fn run(args: &MigrateArgs) -> anyhow::Result<()> {
             }
         }
 
-        let target = InstallTarget::from_raw(trimmed);
-        match target.resolve() {
-            Ok(resolved) => {
-                if resolved.plugin_repo.owner == "jorgebucaran"
-                    && resolved.plugin_repo.repo == "fisher"
-                {
-                    continue;
-                }
-                entries.push(MigratedEntry::new(trimmed.to_string(), resolved));
+        let Some(entry) = resolve_entry(fisher_commit_ref(trimmed), trimmed) else {
+            continue;
+        };
+        let repo = &entry.resolved.plugin_repo;
+        if repo.owner == "jorgebucaran" && repo.repo == "fisher" {
+            continue;
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+/// `plug <target>` lines (the target optionally quoted), as plug-style managers declare
+/// plugins in fish config. Every other line is ignored.
+fn parse_plug_list(content: &str) -> Vec<MigratedEntry> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.split_once(" #").map_or(line, |(code, _)| code).trim();
+            let mut words = line.split_whitespace();
+            if !matches!(words.next(), Some("plug" | "Plug")) {
+                return None;
             }
-            Err(err) => warn!(
-                "{}Skipping unrecognized entry: {} ({err})",
+            let target = words.next()?.trim_matches(|c| c == '\'' || c == '"');
+            if target.is_empty() || target.starts_with('-') {
+                return None;
+            }
+            resolve_entry(target.to_string(), line)
+        })
+        .collect()
+}
+
+/// fundle declarations from a fish script, or given a fish config directory, from its
+/// `config.fish` and `conf.d/*.fish`.
+fn read_fundle(path: &path::Path) -> anyhow::Result<Vec<MigratedEntry>> {
+    if !path.is_dir() {
+        return Ok(parse_fundle_plugins(&fs::read_to_string(path)?));
+    }
+    let mut scripts = vec![path.join("config.fish")];
+    if let Ok(dir) = fs::read_dir(path.join(TargetDir::ConfD.as_str())) {
+        let mut conf_d: Vec<path::PathBuf> = dir
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "fish"))
+            .collect();
+        conf_d.sort();
+        scripts.extend(conf_d);
+    }
+    let mut entries = Vec::new();
+    for script in scripts.iter().filter(|script| script.is_file()) {
+        entries.extend(parse_fundle_plugins(&fs::read_to_string(script)?));
+    }
+    Ok(entries)
+}
+
+/// `fundle plugin 'owner/repo[#ref]' [--url=<url>[#ref]] [--path=<dir>]` lines. A
+/// `#ref` (tag, branch, or commit) becomes pez's bare `@ref`, which also tries a
+/// branch before a tag. A `--url` replaces the GitHub repo; with a ref it is kept
+/// only as `host/owner/repo@ref`, since pez targets cannot pin a URL. `--path`
+/// plugins live in a subdirectory, which pez cannot install, so they are skipped.
+fn parse_fundle_plugins(content: &str) -> Vec<MigratedEntry> {
+    fn unquote(word: &str) -> &str {
+        word.trim_matches(|c| c == '\'' || c == '"')
+    }
+    let mut entries = Vec::new();
+    for line in content.lines() {
+        let line = line.split_once(" #").map_or(line, |(code, _)| code).trim();
+        let words: Vec<&str> = line.split_whitespace().map(unquote).collect();
+        let ["fundle", "plugin", rest @ ..] = words.as_slice() else {
+            continue;
+        };
+        let mut name = None;
+        let mut url = None;
+        let mut subdir = None;
+        let mut rest = rest.iter();
+        while let Some(word) = rest.next() {
+            let (option, inline) = match word.split_once('=') {
+                Some((option, value)) if option.starts_with("--") => (option, Some(value)),
+                _ => (*word, None),
+            };
+            if !option.starts_with("--") {
+                name.get_or_insert(*word);
+                continue;
+            }
+            let value = inline.or_else(|| rest.next().copied()).map(unquote);
+            match option {
+                "--url" => url = value,
+                "--path" => subdir = value,
+                _ => {}
+            }
+        }
+        let Some(name) = name else {
+            continue;
+        };
+        if let Some(subdir) = subdir {
+            warn!(
+                "{}Skipping {}: pez cannot install a plugin from a subdirectory ({})",
                 Emoji("⚠ ", ""),
-                trimmed
+                line,
+                subdir
+            );
+            continue;
+        }
+        let split_ref = |target: &str| match target.rsplit_once('#') {
+            Some((base, reference)) if !reference.is_empty() => {
+                (base.to_string(), Some(reference.to_string()))
+            }
+            _ => (target.trim_end_matches('#').to_string(), None),
+        };
+        let (name, name_ref) = split_ref(name);
+        let (url, url_ref) = match url.map(split_ref) {
+            Some((url, reference)) => (Some(url), reference),
+            None => (None, None),
+        };
+        let raw = match (url, url_ref.or(name_ref)) {
+            (None, None) => Some(name),
+            (None, Some(reference)) => Some(format!("{name}@{reference}")),
+            (Some(url), None) => Some(url),
+            (Some(url), Some(reference)) => PluginRepo::from_remote_url(&url)
+                .map(|repo| format!("{}@{reference}", repo.as_str())),
+        };
+        match raw {
+            Some(raw) => entries.extend(resolve_entry(raw, line)),
+            None => warn!(
+                "{}Skipping {}: pez can only pin a ref on URLs of the form host/owner/repo",
+                Emoji("⚠ ", ""),
+                line
             ),
         }
     }
+    entries
+}
+
+/// Every subdirectory of `dir` laid out like a fish plugin (`functions/`, `conf.d/`,
+/// ...), as a local path entry. Other subdirectories are reported and skipped.
+fn read_plugin_dirs(dir: &path::Path) -> anyhow::Result<Vec<MigratedEntry>> {
+    let dir = fs::canonicalize(dir)?;
+    let mut plugin_dirs: Vec<path::PathBuf> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    plugin_dirs.sort();
+
+    let mut entries = Vec::new();
+    for plugin_dir in plugin_dirs {
+        let has_layout = TargetDir::all()
+            .iter()
+            .any(|target| plugin_dir.join(target.as_str()).is_dir());
+        if !has_layout {
+            warn!(
+                "{}Skipping {}: no functions, completions, conf.d, or themes directory",
+                Emoji("⚠ ", ""),
+                plugin_dir.display()
+            );
+            continue;
+        }
+        let raw = plugin_dir.to_string_lossy().to_string();
+        entries.extend(resolve_entry(raw.clone(), &raw));
+    }
+    Ok(entries)
+}
+
+/// Oh My Fish: given its directory, the `package`/`theme` lines of its `bundle` plus
+/// every package cloned under `pkg/` and `themes/`; given a file, just that bundle.
+/// URLs are used as-is and names map to the repo their clone was installed from; names
+/// without a clone cannot be mapped and are reported. omf itself is left out.
+fn read_omf(path: &path::Path) -> anyhow::Result<Vec<MigratedEntry>> {
+    let (bundle, omf_dir) = if path.is_dir() {
+        (path.join("bundle"), path.to_path_buf())
+    } else {
+        (path.to_path_buf(), utils::load_omf_dir()?)
+    };
+
+    let mut entries = Vec::new();
+    if bundle.is_file() {
+        for line in fs::read_to_string(&bundle)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let kind_dir = match line.split_once(char::is_whitespace) {
+                Some(("package", target)) => Some(("pkg", target.trim())),
+                Some(("theme", target)) => Some(("themes", target.trim())),
+                _ => None,
+            };
+            let Some((kind_dir, target)) = kind_dir else {
+                warn!(
+                    "{}Skipping unrecognized bundle line: {}",
+                    Emoji("⚠ ", ""),
+                    line
+                );
+                continue;
+            };
+            let raw = if resolver::is_remote_url(target) {
+                Some(target.to_string())
+            } else {
+                git::origin_url(&omf_dir.join(kind_dir).join(target))
+            };
+            match raw {
+                Some(raw) => entries.extend(resolve_entry(raw, line)),
+                None => warn!(
+                    "{}Skipping {}: no clone under {} to map it to a repo; add it with `pez install <owner/repo>`",
+                    Emoji("⚠ ", ""),
+                    line,
+                    omf_dir.join(kind_dir).display()
+                ),
+            }
+        }
+    }
+
+    for kind_dir in ["pkg", "themes"] {
+        let Ok(dir) = fs::read_dir(omf_dir.join(kind_dir)) else {
+            continue;
+        };
+        let mut clones: Vec<path::PathBuf> = dir
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        clones.sort();
+        for clone in clones {
+            match git::origin_url(&clone) {
+                Some(url) => entries.extend(resolve_entry(url.clone(), &url)),
+                None => warn!(
+                    "{}Skipping {}: not a git clone with an origin remote",
+                    Emoji("⚠ ", ""),
+                    clone.display()
+                ),
+            }
+        }
+    }
+
+    entries.retain(|entry| {
+        let repo = &entry.resolved.plugin_repo;
+        !(repo.owner == "oh-my-fish" && repo.repo == "oh-my-fish")
+    });
+    Ok(entries)
+}
+
+pub(crate) async fn run(args: &MigrateArgs, options: &RuntimeOptions) -> anyhow::Result<()> {
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let source_path = match args
+        .path
+        .clone()
+        .or_else(|| default_path(&args.from, &fish_config_dir))
+    {
+        Some(path) => path,
+        None => anyhow::bail!(
+            "`pez migrate --from {}` needs a PATH to read",
+            from_name(&args.from)
+        ),
+    };
+    if !source_path.exists() {
+        error!(
+            "{}{} not found at {}",
+            Emoji("❌ ", ""),
+            source_name(&args.from),
+            source_path.display()
+        );
+        anyhow::bail!("{} not found", source_name(&args.from));
+    }
+
+    info!("{}Reading {}", Emoji("📄 ", ""), source_path.display());
+    let entries = read_entries(&args.from, &source_path)?;
 
     if entries.is_empty() {
         warn!("{}No valid entries to migrate.", Emoji("⚠ ", ""));
@@ -285,47 +663,7 @@ pub(crate) async fn run(args: &MigrateArgs) -> anyhow::Result<()> {
     }
 
     let (mut cfg, cfg_path) = utils::load_or_create_config()?;
-    let mut planned: Vec<MigratedEntry> = Vec::new();
-
-    if args.force {
-        planned = entries.clone();
-        if args.dry_run {
-        } else {
-            let specs: Vec<PluginSpec> = planned.iter().map(|entry| entry.spec.clone()).collect();
-            cfg.plugins = Some(specs);
-        }
-    } else if let Some(list) = cfg.plugins.as_mut() {
-        for entry in &entries {
-            let repo = &entry.resolved.plugin_repo;
-            let existing_index = list.iter().position(|spec| {
-                spec.get_plugin_repo()
-                    .is_ok_and(|existing_repo| existing_repo == *repo)
-            });
-
-            if let Some(idx) = existing_index {
-                if should_update_existing(&list[idx], &entry.spec) {
-                    if !args.dry_run {
-                        let existing = &mut list[idx];
-                        existing.source = entry.spec.source.clone();
-                    }
-                    planned.push(entry.clone());
-                }
-            } else {
-                if args.dry_run {
-                } else {
-                    list.push(entry.spec.clone());
-                }
-                planned.push(entry.clone());
-            }
-        }
-    } else {
-        planned = entries.clone();
-        if args.dry_run {
-        } else {
-            let specs: Vec<PluginSpec> = planned.iter().map(|entry| entry.spec.clone()).collect();
-            cfg.plugins = Some(specs);
-        }
-    }
+    let planned = merge_entries(&mut cfg, &entries, args.force, args.dry_run);
 
     if args.dry_run {
         info!("{}Dry run: planned updates to pez.toml", Emoji("🧪 ", ""));
@@ -334,7 +672,7 @@ pub(crate) async fn run(args: &MigrateArgs) -> anyhow::Result<()> {
         info!("{}Updated {}", Emoji("✅ ", ""), cfg_path.display());
     }
     for p in &planned {
-        println!("  - {}", describe_spec(&p.spec));
+        outln!("  - {}", describe_spec(&p.spec));
     }
     if planned.is_empty() {
         info!("{}Nothing to update.", Emoji("ℹ ", ""));
@@ -342,17 +680,8 @@ pub(crate) async fn run(args: &MigrateArgs) -> anyhow::Result<()> {
 
     let mut install_executed = false;
     if !args.dry_run && args.install && !planned.is_empty() {
-        let targets: Vec<_> = planned
-            .iter()
-            .map(|entry| InstallTarget::from_raw(entry.raw.clone()))
-            .collect();
-        let install_args = InstallArgs {
-            plugins: Some(targets),
-            force: false,
-            prune: false,
-        };
         info!("{}Installing migrated plugins...", Emoji("🚀 ", ""));
-        crate::cmd::install::run(&install_args).await?;
+        install_entries(&planned, options).await?;
         install_executed = true;
     }
 
@@ -418,7 +747,7 @@ mod tests {
 
     fn run_migrate(args: &MigrateArgs) -> anyhow::Result<()> {
         let rt = tokio::runtime::Runtime::new().unwrap();
-        rt.block_on(super::run(args))
+        rt.block_on(super::run(args, &RuntimeOptions::default()))
     }
 
     #[test]
@@ -446,6 +775,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
 
         run_migrate(&args).unwrap();
@@ -499,6 +830,8 @@ mod tests {
 
         let existing_spec = PluginSpec {
             name: Some("gitnow".to_string()),
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: PluginRepo {
                     host: None,
@@ -510,9 +843,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![existing_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let target = InstallTarget::from_raw("joseluisq/gitnow@2.13.0");
@@ -532,6 +871,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -561,6 +902,8 @@ mod tests {
 
         let existing_spec = PluginSpec {
             name: Some("gitnow".to_string()),
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: PluginRepo {
                     host: None,
@@ -572,9 +915,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![existing_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
@@ -584,6 +933,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -614,6 +965,8 @@ mod tests {
 
         let existing_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: PluginRepo {
                     host: None,
@@ -625,9 +978,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![existing_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
@@ -637,6 +996,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -672,6 +1033,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         let (logs, result) = crate::tests_support::log::capture_logs(|| run_migrate(&args));
         assert!(result.is_ok());
@@ -706,7 +1069,12 @@ mod tests {
             ("PEZ_CONFIG_DIR", env.config_dir.clone().into_os_string()),
         ]);
 
-        env.setup_config(config::Config { plugins: None });
+        env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
         fs::write(&fish_plugins_path, "git@bitbucket.org:team/pkg.git\n").unwrap();
@@ -715,6 +1083,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -745,7 +1115,12 @@ mod tests {
             ("PEZ_CONFIG_DIR", env.config_dir.clone().into_os_string()),
         ]);
 
-        env.setup_config(config::Config { plugins: None });
+        env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
         fs::write(&fish_plugins_path, "owner/repo@\n").unwrap();
@@ -754,6 +1129,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -773,7 +1150,12 @@ mod tests {
             ("PEZ_CONFIG_DIR", env.config_dir.clone().into_os_string()),
         ]);
 
-        env.setup_config(config::Config { plugins: None });
+        env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
         fs::write(
@@ -786,6 +1168,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         let (logs, result) = crate::tests_support::log::capture_logs(|| run_migrate(&args));
         assert!(result.is_ok());
@@ -817,6 +1201,8 @@ mod tests {
 
         let existing_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Url {
                 url: "git@bitbucket.org:team/pkg.git".to_string(),
                 version: Some("2.0.0".to_string()),
@@ -824,9 +1210,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![existing_spec.clone()]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
@@ -836,6 +1228,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -865,6 +1259,8 @@ mod tests {
 
         let existing_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Url {
                 url: "git@bitbucket.org:team/pkg.git".to_string(),
                 version: None,
@@ -872,9 +1268,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![existing_spec.clone()]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
@@ -884,6 +1286,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -898,6 +1302,190 @@ mod tests {
         }
     }
 
+    #[test]
+    fn plug_list_reads_plug_lines_only() {
+        let entries = parse_plug_list(
+            "set -g fish_greeting\nplug 'owner/one@v1.0.0'\n  plug \"gitlab.com/group/two\" # prompt\nPlug owner/three\nplug --update\n# plug owner/commented\n",
+        );
+        let described: Vec<String> = entries.iter().map(|e| describe_spec(&e.spec)).collect();
+        assert_eq!(
+            described,
+            vec!["owner/one@v1.0.0", "gitlab.com/group/two", "owner/three"]
+        );
+    }
+
+    #[test]
+    fn migrates_plugin_directories_as_local_paths() {
+        let mut env = TestEnvironmentSetup::new();
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let vars = env_vars(&env);
+        let _guard = EnvGuard::set(&vars);
+        env.setup_config(config::init());
+
+        let plugins_dir = env._temp_dir.path().join("vendored");
+        fs::create_dir_all(plugins_dir.join("prompt").join("functions")).unwrap();
+        fs::create_dir_all(plugins_dir.join("keys").join("conf.d")).unwrap();
+        fs::create_dir_all(plugins_dir.join("notes")).unwrap();
+
+        let missing_path = MigrateArgs {
+            dry_run: false,
+            force: false,
+            install: false,
+            from: MigrateFrom::Dir,
+            path: None,
+        };
+        assert!(run_migrate(&missing_path).is_err());
+
+        let args = MigrateArgs {
+            path: Some(plugins_dir.clone()),
+            ..missing_path
+        };
+        run_migrate(&args).unwrap();
+
+        let cfg = config::load(&env.config_path).unwrap();
+        let plugins = cfg.plugins.unwrap();
+        let paths: Vec<&str> = plugins
+            .iter()
+            .map(|spec| match &spec.source {
+                PluginSource::Path { path } => path.as_str(),
+                other => panic!("expected a path source, got {other:?}"),
+            })
+            .collect();
+        let canonical = fs::canonicalize(&plugins_dir).unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                canonical.join("keys").to_str().unwrap(),
+                canonical.join("prompt").to_str().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn migrates_omf_bundle_and_installed_packages() {
+        let mut env = TestEnvironmentSetup::new();
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let omf_dir = env._temp_dir.path().join("omf");
+        let mut vars = env_vars(&env);
+        vars.push(("OMF_PATH", omf_dir.clone().into_os_string()));
+        let _guard = EnvGuard::set(&vars);
+        env.setup_config(config::init());
+
+        let clone = |kind: &str, name: &str, url: &str| {
+            let dir = omf_dir.join(kind).join(name);
+            fs::create_dir_all(&dir).unwrap();
+            git2::Repository::init(&dir)
+                .unwrap()
+                .remote("origin", url)
+                .unwrap();
+        };
+        clone("pkg", "omf", "https://github.com/oh-my-fish/oh-my-fish");
+        clone("pkg", "z", "https://github.com/jethrokuan/z");
+        clone(
+            "themes",
+            "bobthefish",
+            "https://github.com/oh-my-fish/theme-bobthefish",
+        );
+        fs::write(
+            omf_dir.join("bundle"),
+            "package z\npackage https://gitlab.com/owner/remote\npackage unknown\ntheme bobthefish\n",
+        )
+        .unwrap();
+
+        let args = MigrateArgs {
+            dry_run: false,
+            force: false,
+            install: false,
+            from: MigrateFrom::Omf,
+            path: None,
+        };
+        run_migrate(&args).unwrap();
+
+        let cfg = config::load(&env.config_path).unwrap();
+        let repos: Vec<String> = cfg
+            .plugins
+            .unwrap()
+            .iter()
+            .map(|spec| spec.get_plugin_repo().unwrap().as_str())
+            .collect();
+        assert_eq!(
+            repos,
+            vec![
+                "jethrokuan/z",
+                "gitlab.com/owner/remote",
+                "oh-my-fish/theme-bobthefish"
+            ]
+        );
+    }
+
+    #[test]
+    fn migrates_fundle_plugins_from_config_files() {
+        let mut env = TestEnvironmentSetup::new();
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let _guard = EnvGuard::set(&env_vars(&env));
+        env.setup_config(config::init());
+
+        fs::write(
+            env.fish_config_dir.join("config.fish"),
+            "fundle plugin 'edc/bass'\n\
+             fundle plugin 'owner/tagged#v1.0' # pinned\n\
+             fundle plugin 'x/y' --url='https://gitlab.com/owner/remote'\n\
+             fundle plugin 'sub/dir' --path 'themes/x'\n\
+             fundle init\n",
+        )
+        .unwrap();
+        let conf_d = env.fish_config_dir.join("conf.d");
+        fs::create_dir_all(&conf_d).unwrap();
+        fs::write(
+            conf_d.join("extra.fish"),
+            "fundle plugin \"jethrokuan/z\"\n",
+        )
+        .unwrap();
+
+        let args = MigrateArgs {
+            dry_run: false,
+            force: false,
+            install: false,
+            from: MigrateFrom::Fundle,
+            path: None,
+        };
+        run_migrate(&args).unwrap();
+
+        let plugins = config::load(&env.config_path).unwrap().plugins.unwrap();
+        let repos: Vec<String> = plugins
+            .iter()
+            .map(|spec| spec.get_plugin_repo().unwrap().as_str())
+            .collect();
+        assert_eq!(
+            repos,
+            vec![
+                "edc/bass",
+                "owner/tagged",
+                "gitlab.com/owner/remote",
+                "jethrokuan/z"
+            ]
+        );
+        match &plugins[1].source {
+            PluginSource::Repo { version, .. } => assert_eq!(version.as_deref(), Some("v1.0")),
+            other => panic!("expected repo source, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn fisher_commit_ref_maps_full_shas_only() {
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(
+            fisher_commit_ref(&format!("owner/repo@{sha}")),
+            format!("owner/repo@commit:{sha}")
+        );
+        assert_eq!(fisher_commit_ref("owner/repo@v1.2.0"), "owner/repo@v1.2.0");
+        assert_eq!(
+            fisher_commit_ref("owner/repo@deadbeef"),
+            "owner/repo@deadbeef"
+        );
+        assert_eq!(fisher_commit_ref("owner/repo"), "owner/repo");
+    }
+
     #[test]
     fn migrated_ref_suffixes_render() {
         assert_eq!(
@@ -924,6 +1512,8 @@ mod tests {
         };
         let with_tag = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: repo.clone(),
                 version: None,
@@ -931,11 +1521,16 @@ mod tests {
                 tag: Some("v1".to_string()),
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         assert_eq!(describe_spec(&with_tag), "owner/repo@tag:v1");
 
         let empty_version = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo,
                 version: Some(String::new()),
@@ -943,6 +1538,9 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         assert_eq!(describe_spec(&empty_version), "owner/repo");
     }
@@ -951,6 +1549,8 @@ mod tests {
     fn describe_spec_falls_back_to_repo_for_empty_base() {
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Url {
                 url: String::new(),
                 version: Some("1.0.0".to_string()),
@@ -958,14 +1558,19 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
-        assert_eq!(describe_spec(&spec), "owner/@1.0.0");
+        assert_eq!(describe_spec(&spec), "url/repo@1.0.0");
     }
 
     #[test]
     fn should_update_existing_handles_unpinned_sources() {
         let existing = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: PluginRepo {
                     host: None,
@@ -977,9 +1582,14 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let incoming = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: PluginRepo {
                     host: Some("example.com".to_string()),
@@ -991,6 +1601,9 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         assert!(should_update_existing(&existing, &incoming));
     }
@@ -999,6 +1612,8 @@ mod tests {
     fn should_update_existing_preserves_custom_url() {
         let existing = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Url {
                 url: "https://example.com/owner/repo".to_string(),
                 version: None,
@@ -1006,9 +1621,14 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let incoming = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: PluginRepo {
                     host: None,
@@ -1020,6 +1640,9 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         assert!(!should_update_existing(&existing, &incoming));
     }
@@ -1028,15 +1651,25 @@ mod tests {
     fn should_update_existing_allows_path_updates() {
         let existing = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Path {
                 path: "/tmp/one".to_string(),
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let incoming = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Path {
                 path: "/tmp/two".to_string(),
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         assert!(should_update_existing(&existing, &incoming));
     }
@@ -1050,6 +1683,8 @@ mod tests {
         };
         let existing = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: repo.clone(),
                 version: Some("1.0.0".to_string()),
@@ -1057,9 +1692,14 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let incoming_same = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: repo.clone(),
                 version: Some("1.0.0".to_string()),
@@ -1067,9 +1707,14 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let incoming_new = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo,
                 version: Some("2.0.0".to_string()),
@@ -1077,6 +1722,9 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         assert!(!should_update_existing(&existing, &incoming_same));
         assert!(should_update_existing(&existing, &incoming_new));
@@ -1089,7 +1737,12 @@ mod tests {
         let vars = env_vars(&env);
         let _guard = EnvGuard::set(&vars);
 
-        env.setup_config(config::Config { plugins: None });
+        env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
         fs::write(
             &fish_plugins_path,
@@ -1101,6 +1754,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -1134,6 +1789,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         let (logs, result) = crate::tests_support::log::capture_logs(|| run_migrate(&args));
         assert!(result.is_ok());
@@ -1153,6 +1810,8 @@ mod tests {
 
         let existing_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: PluginRepo {
                     host: None,
@@ -1164,9 +1823,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![existing_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
@@ -1176,6 +1841,8 @@ mod tests {
             dry_run: true,
             force: true,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -1194,6 +1861,8 @@ mod tests {
 
         let existing_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: PluginRepo {
                     host: None,
@@ -1205,9 +1874,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![existing_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
@@ -1217,6 +1892,8 @@ mod tests {
             dry_run: true,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -1249,6 +1926,8 @@ mod tests {
             dry_run: true,
             force: false,
             install: true,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -1275,6 +1954,8 @@ mod tests {
             dry_run: true,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         let (logs, result) = crate::tests_support::log::capture_logs(|| run_migrate(&args));
         assert!(result.is_ok());
@@ -1310,6 +1991,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: true,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         let (logs, result) = crate::tests_support::log::capture_logs(|| run_migrate(&args));
         assert!(result.is_ok());
@@ -1334,6 +2017,8 @@ mod tests {
 
         let existing_spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: PluginRepo {
                     host: None,
@@ -1345,9 +2030,15 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![existing_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         let fish_plugins_path = env.fish_config_dir.join("fish_plugins");
@@ -1359,6 +2050,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: true,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         run_migrate(&args).unwrap();
 
@@ -1380,6 +2073,8 @@ mod tests {
             dry_run: false,
             force: false,
             install: false,
+            from: MigrateFrom::Fisher,
+            path: None,
         };
         let (logs, result) = crate::tests_support::log::capture_logs(|| run_migrate(&args));
         assert!(result.is_ok());
@@ -1,11 +1,44 @@
 pub mod activate;
+pub mod cache;
+pub mod changelog;
+pub mod check;
 pub mod completion;
+pub mod completion_data;
+pub mod diff;
 pub mod doctor;
+pub mod edit;
+pub mod enable;
+pub mod export;
 pub mod files;
+pub mod gc;
+pub mod import;
+pub mod info;
 pub mod init;
 pub mod install;
 pub mod list;
+pub mod lock;
 pub mod migrate;
+pub mod open;
+pub mod outdated;
+pub mod pin;
 pub mod prune;
+pub mod repair;
+pub mod rollback;
+pub mod search;
+pub mod smoke;
+pub mod snapshot;
+pub mod stats;
+pub mod status;
+pub mod sync;
+pub mod theme;
+pub mod tree;
+#[cfg(feature = "tui")]
+pub mod ui;
 pub mod uninstall;
+pub mod update;
 pub mod upgrade;
+pub mod verify;
+pub mod verify_activate;
+pub mod version;
+pub mod which;
+pub mod why;
@@ -0,0 +1,139 @@
+use crate::{cli::OpenArgs, output::outln, resolver, utils};
+
+use anyhow::Context;
+use console::Emoji;
+use std::process::{Command, Stdio};
+use tracing::info;
+
+/// Open a plugin's homepage, found from its source in pez-lock.toml or pez.toml.
+pub(crate) fn run(args: &OpenArgs) -> anyhow::Result<String> {
+    let url = resolver::web_url(&plugin_source(&args.plugin)?)?;
+    if args.print {
+        outln!("{url}");
+        return Ok(url);
+    }
+    open_in_browser(&url)?;
+    info!("{}Opened {url}", Emoji("🌐 ", ""));
+    Ok(url)
+}
+
+/// The locked source when the plugin is installed, else the source its pez.toml entry
+/// resolves to.
+fn plugin_source(query: &str) -> anyhow::Result<String> {
+    let lock_error = match utils::load_lock_file() {
+        Ok((lock_file, _)) => match lock_file.find_plugin(query) {
+            Ok(plugin) => return Ok(plugin.source.clone()),
+            Err(e) => Some(e),
+        },
+        Err(_) => None,
+    };
+    if let Ok((config, _)) = utils::load_config()
+        && let Ok(target) = resolver::resolve_target(query)
+        && let Some(spec) = config.find_plugin_spec(&target.plugin_repo)
+    {
+        return Ok(spec.to_resolved()?.source);
+    }
+    Err(lock_error
+        .unwrap_or_else(|| anyhow::anyhow!("{query} is not installed or declared in pez.toml")))
+}
+
+/// `$BROWSER` when set, else the platform opener (`open` on macOS, `xdg-open` elsewhere).
+fn open_in_browser(url: &str) -> anyhow::Result<()> {
+    let opener = std::env::var("BROWSER")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(target_os = "macos") {
+                "open".to_string()
+            } else {
+                "xdg-open".to_string()
+            }
+        });
+    let mut parts = opener.split_whitespace();
+    let program = parts.next().unwrap_or("xdg-open");
+    let status = Command::new(program)
+        .args(parts)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run `{program}`; use `pez open --print` instead"))?;
+    if !status.success() {
+        anyhow::bail!("`{program} {url}` exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{Config, PluginSource, PluginSpec},
+        lock_file::{LockFile, Plugin},
+        tests_support::{env::TestEnvironmentSetup, log::env_lock},
+    };
+
+    #[test]
+    fn run_prints_urls_from_lock_then_config() {
+        let mut env = TestEnvironmentSetup::new();
+        let locked: crate::models::PluginRepo = "owner/locked".parse().unwrap();
+        env.setup_config(Config {
+            plugins: Some(vec![PluginSpec {
+                name: None,
+                prerelease: false,
+                disabled: false,
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
+                source: PluginSource::Url {
+                    url: "git@gitlab.com:team/declared.git".into(),
+                    version: None,
+                    branch: None,
+                    tag: None,
+                    commit: None,
+                },
+            }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![Plugin {
+                name: "locked".into(),
+                source: "ssh://git@github.com/owner/locked.git".into(),
+                repo: locked,
+                commit_sha: "0123456789abcdef".into(),
+                files: vec![],
+                ..Default::default()
+            }],
+        });
+
+        let _lock = env_lock().lock().unwrap();
+        let saved = std::env::var_os("PEZ_CONFIG_DIR");
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+        }
+        let open = |plugin: &str| {
+            run(&OpenArgs {
+                plugin: plugin.into(),
+                print: true,
+            })
+        };
+        let results = (
+            open("locked").unwrap(),
+            open("gitlab.com/team/declared").unwrap(),
+            open("owner/unknown").is_err(),
+        );
+        unsafe {
+            match saved {
+                Some(v) => std::env::set_var("PEZ_CONFIG_DIR", v),
+                None => std::env::remove_var("PEZ_CONFIG_DIR"),
+            }
+        }
+
+        assert_eq!(results.0, "https://github.com/owner/locked");
+        assert_eq!(results.1, "https://gitlab.com/team/declared");
+        assert!(results.2);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    cli::{OutdatedArgs, OutdatedFormat},
+    cmd::list::{self, OutdatedPlugin},
+    git::{self, CommitSummary},
+    output::{self, out, outln},
+    runtime::RuntimeOptions,
+    update_cache::CachePolicy,
+    utils,
+};
+
+use console::Emoji;
+use serde_derive::Serialize;
+use std::path;
+use tracing::info;
+
+/// Behind commits listed per plugin in the plain report; JSON lists them all.
+const MAX_LISTED_COMMITS: usize = 10;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct OutdatedEntry {
+    pub(crate) name: String,
+    pub(crate) repo: String,
+    pub(crate) current: String,
+    pub(crate) latest: String,
+    /// Commits between `current` and `latest`, newest first; `None` when the clone does
+    /// not have them (e.g. a cached check whose objects were never fetched).
+    pub(crate) behind: Option<Vec<CommitSummary>>,
+    /// The closest tag reachable from `latest`.
+    pub(crate) latest_tag: Option<String>,
+}
+
+/// Report plugins with updates available. Fails (exit code 1) when there are any, so
+/// CI can gate on it; a clean run exits 0.
+pub(crate) fn run(
+    args: &OutdatedArgs,
+    options: &RuntimeOptions,
+) -> anyhow::Result<Vec<OutdatedEntry>> {
+    let Ok((lock_file, _)) = utils::load_lock_file() else {
+        info!("No plugins installed!");
+        return Ok(vec![]);
+    };
+    let config = utils::load_config().ok().map(|(c, _)| c);
+    let policy = if args.cached {
+        CachePolicy::CachedOnly
+    } else if let Some(max_age) = args.max_age {
+        CachePolicy::MaxAge(max_age)
+    } else {
+        CachePolicy::Refresh
+    };
+    let outdated = list::get_outdated_plugins(&lock_file.plugins, config.as_ref(), &policy)?;
+    let entries = entries(outdated, &utils::load_pez_data_dir()?);
+
+    match args.format {
+        Some(OutdatedFormat::Json) => outln!("{}", output::to_json(&entries, options.json_style)?),
+        None if entries.is_empty() => {
+            info!("{}All plugins are up to date!", Emoji("🎉 ", ""))
+        }
+        None => out!("{}", render_plain(&entries)),
+    }
+    if !entries.is_empty() {
+        anyhow::bail!(
+            "{} plugin(s) have updates available; run `pez upgrade` to apply them",
+            entries.len()
+        );
+    }
+    Ok(entries)
+}
+
+/// Describe each outdated plugin from what its clone already has.
+pub(crate) fn entries(outdated: Vec<OutdatedPlugin>, data_dir: &path::Path) -> Vec<OutdatedEntry> {
+    outdated
+        .into_iter()
+        .map(|entry| {
+            let repo = git2::Repository::open(data_dir.join(entry.plugin.repo.as_str())).ok();
+            let behind = repo.as_ref().and_then(|repo| {
+                git::commits_between(repo, &entry.plugin.commit_sha, &entry.latest).ok()
+            });
+            let latest_tag = repo
+                .as_ref()
+                .and_then(|repo| git::nearest_tag(repo, &entry.latest));
+            OutdatedEntry {
+                name: entry.plugin.get_name(),
+                repo: entry.plugin.repo.as_str(),
+                current: entry.plugin.commit_sha,
+                latest: entry.latest,
+                behind,
+                latest_tag,
+            }
+        })
+        .collect()
+}
+
+fn render_plain(entries: &[OutdatedEntry]) -> String {
+    entries.iter().map(render_entry).collect()
+}
+
+/// `repo current -> latest (N commit(s) behind, latest tag T)` and the newest commits.
+pub(crate) fn render_entry(entry: &OutdatedEntry) -> String {
+    let mut notes = Vec::new();
+    if let Some(commits) = &entry.behind {
+        notes.push(format!("{} commit(s) behind", commits.len()));
+    }
+    if let Some(tag) = &entry.latest_tag {
+        notes.push(format!("latest tag {tag}"));
+    }
+    let notes = if notes.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", notes.join(", "))
+    };
+    let mut out = format!(
+        "{} {} -> {}{notes}\n",
+        entry.repo,
+        short_sha(&entry.current),
+        short_sha(&entry.latest)
+    );
+    let commits = entry.behind.as_deref().unwrap_or_default();
+    for commit in commits.iter().take(MAX_LISTED_COMMITS) {
+        out.push_str(&format!(
+            "    {} {}\n",
+            short_sha(&commit.sha),
+            commit.summary
+        ));
+    }
+    if commits.len() > MAX_LISTED_COMMITS {
+        out.push_str(&format!(
+            "    ... and {} more\n",
+            commits.len() - MAX_LISTED_COMMITS
+        ));
+    }
+    out
+}
+
+fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lock_file::Plugin, models::PluginRepo};
+    use std::fs;
+
+    fn commit(repo: &git2::Repository, message: &str) -> git2::Oid {
+        let sig = git2::Signature::now("pez", "pez@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    #[test]
+    fn entries_list_behind_commits_from_the_clone() {
+        let temp = tempfile::tempdir().unwrap();
+        let clone = temp.path().join("owner/behind");
+        fs::create_dir_all(&clone).unwrap();
+        let git_repo = git2::Repository::init(&clone).unwrap();
+        let base = commit(&git_repo, "init");
+        commit(&git_repo, "Fix prompt");
+        let latest = commit(&git_repo, "Add completions");
+        git_repo
+            .tag_lightweight(
+                "v2.0.0",
+                &git_repo.find_object(latest, None).unwrap(),
+                false,
+            )
+            .unwrap();
+
+        let plugin = |repo: &str, current: &str| {
+            let repo: PluginRepo = repo.parse().unwrap();
+            Plugin {
+                name: repo.repo.clone(),
+                source: repo.default_remote_source(),
+                repo,
+                commit_sha: current.into(),
+                files: vec![],
+                ..Default::default()
+            }
+        };
+        let entries = entries(
+            vec![
+                OutdatedPlugin {
+                    plugin: plugin("owner/behind", &base.to_string()),
+                    latest: latest.to_string(),
+                },
+                OutdatedPlugin {
+                    plugin: plugin("owner/uncloned", "1111111111"),
+                    latest: "2222222222".into(),
+                },
+            ],
+            temp.path(),
+        );
+
+        let summaries: Vec<&str> = entries[0]
+            .behind
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|c| c.summary.as_str())
+            .collect();
+        assert_eq!(summaries, vec!["Add completions", "Fix prompt"]);
+        assert_eq!(entries[1].behind, None);
+
+        let rendered = render_plain(&entries);
+        assert!(rendered.contains(&format!(
+            "owner/behind {} -> {} (2 commit(s) behind, latest tag v2.0.0)\n",
+            short_sha(&base.to_string()),
+            short_sha(&latest.to_string())
+        )));
+        assert!(rendered.contains(&format!(
+            "    {} Add completions\n",
+            short_sha(&latest.to_string())
+        )));
+        assert!(rendered.contains("owner/uncloned 1111111 -> 2222222\n"));
+    }
+}
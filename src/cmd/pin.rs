@@ -0,0 +1,376 @@
+use crate::{
+    cli::PinArgs,
+    config::{PluginSource, PluginSpec},
+    git,
+    models::PluginRepo,
+    utils,
+};
+
+use console::Emoji;
+use tracing::{info, warn};
+
+/// Pin each plugin's pez.toml entry to the commit recorded in the lock file, so
+/// `pez upgrade` leaves it alone.
+pub(crate) fn pin(args: &PinArgs) -> anyhow::Result<()> {
+    let (mut config, config_path) = utils::load_config()?;
+    let (lock_file, _) = utils::load_lock_file()?;
+
+    let mut changed = false;
+    for plugin_repo in unique(&args.plugins) {
+        let locked = lock_file.get_plugin_by_repo(plugin_repo).ok_or_else(|| {
+            anyhow::anyhow!("{plugin_repo} is not installed; run `pez install` first")
+        })?;
+        if git::is_local_source(&locked.source) {
+            anyhow::bail!("{plugin_repo} is a local path source and cannot be pinned");
+        }
+        let spec = config
+            .find_plugin_spec_mut(plugin_repo)
+            .ok_or_else(|| anyhow::anyhow!("{plugin_repo} is not declared in pez.toml"))?;
+        let sha = locked.commit_sha.as_str();
+        let replaced = spec.pin_commit(sha)?;
+        if replaced.as_deref() == Some(&format!("commit = \"{sha}\"")) {
+            info!(
+                "{}{} is already pinned to {}",
+                Emoji("📌 ", ""),
+                plugin_repo,
+                short(sha)
+            );
+            continue;
+        }
+        changed = true;
+        match replaced {
+            Some(previous) => info!(
+                "{}Pinned {} to {} (was {})",
+                Emoji("📌 ", ""),
+                plugin_repo,
+                short(sha),
+                previous
+            ),
+            None => info!(
+                "{}Pinned {} to {}",
+                Emoji("📌 ", ""),
+                plugin_repo,
+                short(sha)
+            ),
+        }
+    }
+
+    if changed {
+        config.save(&config_path)?;
+    }
+    Ok(())
+}
+
+/// Remove the version/branch/tag/commit selector from each plugin's pez.toml entry.
+/// The lock file keeps the current commit until the next `pez upgrade`.
+pub(crate) fn unpin(args: &PinArgs) -> anyhow::Result<()> {
+    let (mut config, config_path) = utils::load_config()?;
+
+    let mut changed = false;
+    for plugin_repo in unique(&args.plugins) {
+        let spec = config
+            .find_plugin_spec_mut(plugin_repo)
+            .ok_or_else(|| anyhow::anyhow!("{plugin_repo} is not declared in pez.toml"))?;
+        match spec.unpin()? {
+            Some(previous) => {
+                changed = true;
+                info!(
+                    "{}Unpinned {} (removed {}); run `pez upgrade {}` to update it",
+                    Emoji("🔓 ", ""),
+                    plugin_repo,
+                    previous,
+                    plugin_repo
+                );
+            }
+            None => info!("{}{} is not pinned", Emoji("🔓 ", ""), plugin_repo),
+        }
+    }
+
+    if changed {
+        config.save(&config_path)?;
+    }
+    Ok(())
+}
+
+/// Pin every remote plugin in pez.toml to its locked commit in one pass. Local path
+/// sources are left alone, and plugins that are not installed are reported and skipped.
+pub(crate) fn freeze() -> anyhow::Result<()> {
+    let (mut config, config_path) = utils::load_config()?;
+    let (lock_file, _) = utils::load_lock_file()?;
+
+    let mut frozen = 0;
+    for spec in config.plugins.iter_mut().flatten() {
+        if matches!(spec.source, PluginSource::Path { .. }) {
+            continue;
+        }
+        let plugin_repo = spec.get_plugin_repo()?;
+        let Some(locked) = lock_file.get_plugin_by_repo(&plugin_repo) else {
+            warn!(
+                "{} {} Skipping {}: not installed; run `pez install` first",
+                Emoji("⚠ ", ""),
+                utils::label_warning(),
+                plugin_repo
+            );
+            continue;
+        };
+        if commit_selector(spec) == Some(locked.commit_sha.as_str()) {
+            continue;
+        }
+        spec.pin_commit(&locked.commit_sha)?;
+        frozen += 1;
+    }
+
+    if frozen == 0 {
+        info!(
+            "{}Every plugin is already pinned to its locked commit",
+            Emoji("📌 ", "")
+        );
+        return Ok(());
+    }
+    config.save(&config_path)?;
+    info!(
+        "{}Pinned {} plugin(s) to their locked commits; `pez unfreeze` drops the pins",
+        Emoji("📌 ", ""),
+        frozen
+    );
+    Ok(())
+}
+
+/// Drop every `commit` selector from pez.toml, leaving other selectors alone.
+pub(crate) fn unfreeze() -> anyhow::Result<()> {
+    let (mut config, config_path) = utils::load_config()?;
+
+    let mut thawed = 0;
+    for spec in config.plugins.iter_mut().flatten() {
+        if commit_selector(spec).is_some() {
+            spec.unpin()?;
+            thawed += 1;
+        }
+    }
+
+    if thawed == 0 {
+        info!("{}No plugin is pinned to a commit", Emoji("🔓 ", ""));
+        return Ok(());
+    }
+    config.save(&config_path)?;
+    info!(
+        "{}Unpinned {} plugin(s); run `pez upgrade` to update them",
+        Emoji("🔓 ", ""),
+        thawed
+    );
+    Ok(())
+}
+
+fn commit_selector(spec: &PluginSpec) -> Option<&str> {
+    match &spec.source {
+        PluginSource::Repo { commit, .. } | PluginSource::Url { commit, .. } => commit.as_deref(),
+        PluginSource::Path { .. } => None,
+    }
+}
+
+pub(crate) fn unique(plugins: &[PluginRepo]) -> Vec<&PluginRepo> {
+    let mut seen: Vec<&PluginRepo> = Vec::new();
+    for plugin in plugins {
+        if !seen.contains(&plugin) {
+            seen.push(plugin);
+        }
+    }
+    seen
+}
+
+fn short(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{self, PluginSource, PluginSpec},
+        lock_file::{LockFile, Plugin},
+        tests_support::{env::TestEnvironmentSetup, log::env_lock},
+    };
+
+    const SHA: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    fn spec(repo: &PluginRepo, tag: Option<&str>) -> PluginSpec {
+        PluginSpec {
+            name: None,
+            prerelease: false,
+            disabled: false,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
+            source: PluginSource::Repo {
+                repo: repo.clone(),
+                version: None,
+                branch: None,
+                tag: tag.map(str::to_string),
+                commit: None,
+            },
+        }
+    }
+
+    fn with_config_dir<T>(env: &TestEnvironmentSetup, f: impl FnOnce() -> T) -> T {
+        let _lock = env_lock().lock().unwrap();
+        let saved = std::env::var_os("PEZ_CONFIG_DIR");
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+        }
+        let result = f();
+        unsafe {
+            match saved {
+                Some(v) => std::env::set_var("PEZ_CONFIG_DIR", v),
+                None => std::env::remove_var("PEZ_CONFIG_DIR"),
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn pin_then_unpin_rewrites_selector() {
+        let repo: PluginRepo = "owner/pinned".parse().unwrap();
+        let mut env = TestEnvironmentSetup::new();
+        env.setup_config(config::Config {
+            plugins: Some(vec![spec(&repo, Some("v1"))]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![Plugin {
+                name: "pinned".into(),
+                repo: repo.clone(),
+                source: repo.default_remote_source(),
+                commit_sha: SHA.into(),
+                files: vec![],
+                ..Default::default()
+            }],
+        });
+        let args = PinArgs {
+            plugins: vec![repo.clone()],
+        };
+
+        with_config_dir(&env, || pin(&args)).unwrap();
+        let pinned = config::load(&env.config_path).unwrap();
+        assert_eq!(
+            pinned.find_plugin_spec(&repo).unwrap().source,
+            PluginSource::Repo {
+                repo: repo.clone(),
+                version: None,
+                branch: None,
+                tag: None,
+                commit: Some(SHA.into()),
+            }
+        );
+        // Pinning again leaves the file untouched.
+        let before = std::fs::read_to_string(&env.config_path).unwrap();
+        with_config_dir(&env, || pin(&args)).unwrap();
+        assert_eq!(std::fs::read_to_string(&env.config_path).unwrap(), before);
+
+        with_config_dir(&env, || unpin(&args)).unwrap();
+        let unpinned = config::load(&env.config_path).unwrap();
+        assert_eq!(
+            unpinned.find_plugin_spec(&repo).unwrap(),
+            &spec(&repo, None)
+        );
+    }
+
+    #[test]
+    fn pin_rejects_plugins_missing_from_lock_or_config() {
+        let installed: PluginRepo = "owner/installed".parse().unwrap();
+        let mut env = TestEnvironmentSetup::new();
+        env.setup_config(config::Config {
+            plugins: Some(vec![spec(&"owner/declared".parse().unwrap(), None)]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![Plugin {
+                name: "installed".into(),
+                repo: installed.clone(),
+                source: installed.default_remote_source(),
+                commit_sha: SHA.into(),
+                files: vec![],
+                ..Default::default()
+            }],
+        });
+
+        let err = with_config_dir(&env, || {
+            pin(&PinArgs {
+                plugins: vec!["owner/declared".parse().unwrap()],
+            })
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("is not installed"), "{err}");
+
+        let err = with_config_dir(&env, || {
+            pin(&PinArgs {
+                plugins: vec![installed.clone()],
+            })
+        })
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("is not declared in pez.toml"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn freeze_pins_installed_plugins_and_unfreeze_drops_commits() {
+        let installed: PluginRepo = "owner/installed".parse().unwrap();
+        let missing: PluginRepo = "owner/missing".parse().unwrap();
+        let local = PluginSpec {
+            name: Some("local".into()),
+            prerelease: false,
+            disabled: false,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
+            source: PluginSource::Path {
+                path: "/plugins/local".into(),
+            },
+        };
+        let mut named = spec(&installed, Some("v1"));
+        named.name = Some("renamed".into());
+        let mut env = TestEnvironmentSetup::new();
+        env.setup_config(config::Config {
+            plugins: Some(vec![named.clone(), spec(&missing, None), local.clone()]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        env.setup_lock_file(LockFile {
+            version: 2,
+            plugins: vec![Plugin {
+                name: "renamed".into(),
+                repo: installed.clone(),
+                source: installed.default_remote_source(),
+                commit_sha: SHA.into(),
+                files: vec![],
+                ..Default::default()
+            }],
+        });
+
+        with_config_dir(&env, freeze).unwrap();
+        let frozen = config::load(&env.config_path).unwrap().plugins.unwrap();
+        let mut pinned = spec(&installed, None);
+        pinned.name = Some("renamed".into());
+        pinned.source = PluginSource::Repo {
+            repo: installed.clone(),
+            version: None,
+            branch: None,
+            tag: None,
+            commit: Some(SHA.into()),
+        };
+        assert_eq!(frozen, vec![pinned, spec(&missing, None), local.clone()]);
+
+        with_config_dir(&env, unfreeze).unwrap();
+        let thawed = config::load(&env.config_path).unwrap().plugins.unwrap();
+        named.source = spec(&installed, None).source;
+        assert_eq!(thawed, vec![named, spec(&missing, None), local]);
+    }
+}
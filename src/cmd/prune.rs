@@ -1,12 +1,15 @@
 use crate::{
+    backup,
     cli::PruneArgs,
-    config,
+    config, git,
     lock_file::{LockFile, Plugin},
+    prompt,
+    runtime::RuntimeOptions,
     utils,
 };
 use console::Emoji;
 use futures::{StreamExt, stream};
-use std::{fs, io, path};
+use std::{collections::HashSet, fs, path, sync::Arc};
 use tracing::{info, warn};
 
 struct PruneContext<'a> {
@@ -15,9 +18,45 @@ struct PruneContext<'a> {
     config: &'a config::Config,
     lock_file: &'a mut LockFile,
     lock_file_path: &'a path::Path,
+    except: &'a [String],
 }
 
-pub(crate) async fn run(args: &PruneArgs) -> anyhow::Result<()> {
+/// Flags shared by `pez prune` and `pez install --prune`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PruneOptions {
+    pub(crate) force: bool,
+    pub(crate) dry_run: bool,
+    pub(crate) yes: bool,
+    /// How many plugins are removed at once.
+    pub(crate) jobs: usize,
+    /// Patterns of unused plugins to keep (`pez prune --except`).
+    pub(crate) except: Vec<String>,
+}
+
+pub(crate) async fn run(args: &PruneArgs, options: &RuntimeOptions) -> anyhow::Result<()> {
+    let _backup = (!args.no_backup)
+        .then(|| backup::start_run("prune", args.backup_dir.as_deref()))
+        .transpose()?;
+    if args.temp {
+        let removed = prune_temp_installs(args.dry_run)?;
+        if removed == 0 {
+            info!("{}No temporary plugins installed.", Emoji("🎉 ", ""));
+        }
+        return Ok(());
+    }
+
+    prune_unused(PruneOptions {
+        force: args.force,
+        dry_run: args.dry_run,
+        yes: args.yes,
+        jobs: options.jobs(),
+        except: args.except.clone(),
+    })
+    .await
+}
+
+/// Remove plugins recorded in pez-lock.toml but no longer listed in pez.toml.
+pub(crate) async fn prune_unused(options: PruneOptions) -> anyhow::Result<()> {
     let fish_config_dir = utils::load_fish_config_dir()?;
     let data_dir = utils::load_pez_data_dir()?;
     let (config, _) = utils::load_config()?;
@@ -28,43 +67,89 @@ pub(crate) async fn run(args: &PruneArgs) -> anyhow::Result<()> {
         config: &config,
         lock_file: &mut lock_file,
         lock_file_path: &lock_file_path,
+        except: &options.except,
     };
 
-    if args.dry_run {
+    if options.dry_run {
         info!("{}Starting dry run prune process...", Emoji("🔍 ", ""));
-        dry_run(args.force, &mut ctx)?;
+        dry_run(options.force, &mut ctx)?;
         info!(
             "{}Dry run completed. No files have been removed.",
             Emoji("🎉 ", "")
         );
     } else {
         info!("{}Starting prune process...", Emoji("🔍 ", ""));
-        prune_parallel(args.force, args.yes, &mut ctx).await?;
+        prune_parallel(options.force, options.yes, options.jobs, &mut ctx).await?;
     }
 
     Ok(())
 }
 
-fn confirm_removal() -> anyhow::Result<bool> {
-    warn!(
-        "{}Are you sure you want to continue? [y/N]",
-        Emoji("🚧 ", "")
-    );
-    let mut input = String::new();
-    #[cfg(test)]
-    if let Some(forced) = take_confirm_input_for_tests() {
-        input = forced;
+/// Remove every plugin recorded by `install --temp` and drop the temp lockfile.
+/// Plugins that have since been installed for real are left in place.
+/// Returns the number of temporary plugins found.
+pub(crate) fn prune_temp_installs(dry_run: bool) -> anyhow::Result<usize> {
+    let (temp_lock_file, temp_lock_file_path) = utils::load_or_create_temp_lock_file()?;
+    if temp_lock_file.plugins.is_empty() {
+        return Ok(0);
+    }
+
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let data_dir = utils::load_pez_data_dir()?;
+    let managed = utils::load_lock_file().ok().map(|(lock, _)| lock);
+
+    if dry_run {
+        info!(
+            "{}Temporary plugins that would be removed:",
+            Emoji("🐟 ", "")
+        );
     } else {
-        io::stdin().read_line(&mut input)?;
+        info!("{}Removing temporary plugins...", Emoji("🧹 ", ""));
     }
-    #[cfg(not(test))]
-    {
-        io::stdin().read_line(&mut input)?;
+    for plugin in &temp_lock_file.plugins {
+        if managed
+            .as_ref()
+            .is_some_and(|lock| lock.contains_repo(&plugin.repo))
+        {
+            info!(
+                "  - {} (now managed by pez.toml; kept)",
+                plugin.display_label()
+            );
+            continue;
+        }
+        info!("  - {}", plugin.display_label());
+        if dry_run {
+            continue;
+        }
+
+        utils::emit_plugin_events(plugin, &utils::Event::Uninstall);
+        let repo_path = data_dir.join(plugin.repo.as_str());
+        if !git::is_local_source(&plugin.source) && repo_path.exists() {
+            fs::remove_dir_all(&repo_path)?;
+        }
+        for file in &plugin.files {
+            let dest_path = file.get_path(&fish_config_dir);
+            if dest_path.exists()
+                && let Err(e) = backup::remove_file(&dest_path)
+            {
+                warn!("Failed to remove {}: {:?}", dest_path.display(), e);
+            }
+        }
+        backup::restore_all_displaced_files(plugin, &fish_config_dir);
+        utils::remove_plugin_state_dir(&plugin.repo);
+    }
+
+    if !dry_run {
+        fs::remove_file(&temp_lock_file_path)?;
     }
-    Ok(input.trim().to_lowercase() == "y")
+    Ok(temp_lock_file.plugins.len())
 }
 
-fn find_unused_plugins(
+pub(crate) fn confirm_removal() -> anyhow::Result<bool> {
+    prompt::confirm("Are you sure you want to continue?", false)
+}
+
+pub(crate) fn find_unused_plugins(
     config: &config::Config,
     lock_file: &LockFile,
 ) -> anyhow::Result<Vec<Plugin>> {
@@ -87,103 +172,58 @@ fn find_unused_plugins(
         .collect())
 }
 
-#[allow(dead_code)]
-fn prune<F>(
-    force: bool,
-    yes: bool,
-    confirm_removal: F,
-    ctx: &mut PruneContext,
-) -> anyhow::Result<()>
-where
-    F: Fn() -> anyhow::Result<bool>,
-{
-    info!("{}Checking for unused plugins...", Emoji("🔍 ", ""));
-
-    let remove_plugins: Vec<_> = find_unused_plugins(ctx.config, ctx.lock_file)?;
-    if remove_plugins.is_empty() {
-        info!(
-            "{}No unused plugins found. Your environment is clean!",
-            Emoji("🎉 ", "")
-        );
-        return Ok(());
-    }
-
-    if ctx.config.plugins.is_none() {
-        warn!(
-            "{} {} No plugins are defined in pez.toml.",
-            Emoji("🚧 ", ""),
-            crate::utils::label_warning()
-        );
-        warn!(
-            "{}All plugins defined in pez-lock.toml will be removed.",
-            Emoji("🚧 ", "")
-        );
+/// Whether `pattern` matches the whole of `text`, with `*` for any run of
+/// characters and `?` for exactly one.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern = regex::escape(pattern)
+        .replace(r"\*", ".*")
+        .replace(r"\?", ".");
+    regex::Regex::new(&format!("^{pattern}$")).is_ok_and(|re| re.is_match(text))
+}
 
-        if !yes && !confirm_removal()? {
-            anyhow::bail!("{}Prune process aborted.", Emoji("🚧 ", ""));
-        }
-    }
+fn except_matches(pattern: &str, plugin: &Plugin) -> bool {
+    wildcard_match(pattern, &plugin.repo.as_str()) || wildcard_match(pattern, &plugin.name)
+}
 
-    for plugin in remove_plugins {
-        let repo_path = ctx.data_dir.join(plugin.repo.as_str());
-        if repo_path.exists() {
-            fs::remove_dir_all(&repo_path)?;
-        } else {
-            let path_display = repo_path.display();
+/// Unused plugins minus the ones `--except` keeps; kept ones are reported, as are
+/// patterns that match no unused plugin.
+fn plugins_to_remove(ctx: &PruneContext) -> anyhow::Result<Vec<Plugin>> {
+    let (kept, remove): (Vec<Plugin>, Vec<Plugin>) =
+        find_unused_plugins(ctx.config, ctx.lock_file)?
+            .into_iter()
+            .partition(|plugin| {
+                ctx.except
+                    .iter()
+                    .any(|pattern| except_matches(pattern, plugin))
+            });
+    for pattern in ctx.except {
+        if !kept.iter().any(|plugin| except_matches(pattern, plugin)) {
             warn!(
-                "{} {} Repository directory at {} does not exist.",
-                Emoji("🚧 ", ""),
-                crate::utils::label_warning(),
-                path_display
+                "{}--except {} matches no unused plugin",
+                Emoji("⚠ ", ""),
+                pattern
             );
-
-            if !force {
-                info!(
-                    "{}Detected plugin files based on pez-lock.toml:",
-                    Emoji("📄 ", ""),
-                );
-
-                plugin.files.iter().for_each(|file| {
-                    let dest_path = file.get_path(ctx.fish_config_dir);
-                    info!("   - {}", dest_path.display());
-                });
-                info!("If you want to remove these files, use the --force flag.");
-                continue;
-            }
         }
-
-        info!(
-            "{}Removing plugin files based on pez-lock.toml:",
-            Emoji("🗑️  ", ""),
-        );
-        plugin.files.iter().for_each(|file| {
-            let dest_path = file.get_path(ctx.fish_config_dir);
-            if dest_path.exists() {
-                let path_display = dest_path.display();
-                info!("   - {}", path_display);
-                if let Err(e) = fs::remove_file(&dest_path) {
-                    warn!("Failed to remove {}: {:?}", path_display, e);
-                }
-            }
-        });
-        ctx.lock_file.remove_plugin(&plugin.source);
-        ctx.lock_file.save(ctx.lock_file_path)?;
     }
-    info!(
-        "\n{}All uninstalled plugins have been pruned successfully!",
-        Emoji("🎉 ", "")
-    );
-
-    Ok(())
+    for plugin in &kept {
+        info!("{}Keeping {} (--except)", Emoji("📌 ", ""), plugin.repo);
+    }
+    Ok(remove)
 }
 
-async fn prune_parallel(force: bool, yes: bool, ctx: &mut PruneContext<'_>) -> anyhow::Result<()> {
-    prune_parallel_with_confirm(force, yes, ctx, confirm_removal).await
+async fn prune_parallel(
+    force: bool,
+    yes: bool,
+    jobs: usize,
+    ctx: &mut PruneContext<'_>,
+) -> anyhow::Result<()> {
+    prune_parallel_with_confirm(force, yes, jobs, ctx, confirm_removal).await
 }
 
 async fn prune_parallel_with_confirm<F>(
     force: bool,
     yes: bool,
+    jobs: usize,
     ctx: &mut PruneContext<'_>,
     confirm_removal: F,
 ) -> anyhow::Result<()>
@@ -192,7 +232,7 @@ where
 {
     info!("{}Checking for unused plugins...", Emoji("🔍 ", ""));
 
-    let remove_plugins: Vec<_> = find_unused_plugins(ctx.config, ctx.lock_file)?;
+    let remove_plugins = plugins_to_remove(ctx)?;
     if remove_plugins.is_empty() {
         info!(
             "{}No unused plugins found. Your environment is clean!",
@@ -217,15 +257,25 @@ where
         }
     }
 
-    let jobs = utils::load_jobs().max(1);
+    let jobs = jobs.max(1);
     let fish_config_dir = ctx.fish_config_dir.to_path_buf();
     let data_dir = ctx.data_dir.to_path_buf();
+    // Identical files shared with plugins that stay installed are kept.
+    let kept: Arc<HashSet<path::PathBuf>> = Arc::new(
+        ctx.lock_file
+            .plugins
+            .iter()
+            .filter(|p| !remove_plugins.iter().any(|r| r.repo == p.repo))
+            .flat_map(|p| p.resolve_paths(&fish_config_dir, None))
+            .collect(),
+    );
 
     let tasks = stream::iter(remove_plugins.iter())
         .map(|plugin| {
             let plugin = plugin.clone();
             let fish_config_dir = fish_config_dir.clone();
             let data_dir = data_dir.clone();
+            let kept = Arc::clone(&kept);
             async move {
                 let repo_path = data_dir.join(plugin.repo.as_str());
                 if repo_path.exists() {
@@ -256,16 +306,20 @@ where
                     "{}Removing plugin files based on pez-lock.toml:",
                     Emoji("🗑️  ", ""),
                 );
+                utils::emit_plugin_events(&plugin, &utils::Event::Uninstall);
                 for file in &plugin.files {
                     let dest_path = fish_config_dir.join(file.dir.as_str()).join(&file.name);
-                    if dest_path.exists() {
+                    if dest_path.exists() && !kept.contains(&dest_path) {
                         let to_delete = dest_path.clone();
-                        let _ = tokio::task::spawn_blocking(move || fs::remove_file(&to_delete))
-                            .await
-                            .map_err(|e| anyhow::anyhow!(e))
-                            .and_then(|res| res.map_err(|e| anyhow::anyhow!(e)));
+                        let _ =
+                            tokio::task::spawn_blocking(move || backup::remove_file(&to_delete))
+                                .await
+                                .map_err(|e| anyhow::anyhow!(e))
+                                .and_then(|res| res.map_err(|e| anyhow::anyhow!(e)));
                     }
                 }
+                backup::restore_all_displaced_files(&plugin, &fish_config_dir);
+                utils::remove_plugin_state_dir(&plugin.repo);
 
                 Ok(Some(plugin.source.clone()))
             }
@@ -307,23 +361,7 @@ fn dry_run(force: bool, ctx: &mut PruneContext) -> anyhow::Result<()> {
         );
     }
 
-    let remove_plugins: Vec<_> = if ctx.config.plugins.is_none() {
-        ctx.lock_file.plugins.clone()
-    } else {
-        ctx.lock_file
-            .plugins
-            .iter()
-            .filter(|plugin| {
-                !ctx.config
-                    .plugins
-                    .as_ref()
-                    .unwrap()
-                    .iter()
-                    .any(|p| p.get_plugin_repo().is_ok_and(|r| r == plugin.repo))
-            })
-            .cloned()
-            .collect()
-    };
+    let remove_plugins = plugins_to_remove(ctx)?;
 
     info!("{}Plugins that would be removed:", Emoji("🐟 ", ""));
     remove_plugins.iter().for_each(|plugin| {
@@ -372,43 +410,6 @@ fn dry_run(force: bool, ctx: &mut PruneContext) -> anyhow::Result<()> {
     Ok(())
 }
 
-#[cfg(test)]
-fn confirm_input_store() -> &'static std::sync::Mutex<Option<String>> {
-    static CONFIRM_INPUT: std::sync::OnceLock<std::sync::Mutex<Option<String>>> =
-        std::sync::OnceLock::new();
-    CONFIRM_INPUT.get_or_init(|| std::sync::Mutex::new(None))
-}
-
-#[cfg(test)]
-fn take_confirm_input_for_tests() -> Option<String> {
-    confirm_input_store().lock().unwrap().take()
-}
-
-#[cfg(test)]
-struct ConfirmInputGuard {
-    prev: Option<String>,
-}
-
-#[cfg(test)]
-impl ConfirmInputGuard {
-    fn new(value: Option<String>) -> Self {
-        let store = confirm_input_store();
-        let mut guard = store.lock().unwrap();
-        let prev = guard.take();
-        *guard = value;
-        Self { prev }
-    }
-}
-
-#[cfg(test)]
-impl Drop for ConfirmInputGuard {
-    fn drop(&mut self) {
-        let store = confirm_input_store();
-        let mut guard = store.lock().unwrap();
-        *guard = self.prev.take();
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use std::{ffi::OsString, future::Future, vec};
@@ -469,21 +470,6 @@ mod tests {
         f().await
     }
 
-    struct JobsGuard;
-
-    impl JobsGuard {
-        fn set(value: usize) -> Self {
-            utils::set_cli_jobs_override(Some(value));
-            Self
-        }
-    }
-
-    impl Drop for JobsGuard {
-        fn drop(&mut self) {
-            utils::clear_cli_jobs_override_for_tests();
-        }
-    }
-
     struct TestDataBuilder {
         used_plugin: Plugin,
         unused_plugin: Plugin,
@@ -506,6 +492,7 @@ mod tests {
                         dir: TargetDir::Functions,
                         name: "used.fish".to_string(),
                     }],
+                    ..Default::default()
                 },
                 unused_plugin: Plugin {
                     name: "unused-repo".to_string(),
@@ -520,9 +507,12 @@ mod tests {
                         dir: TargetDir::Functions,
                         name: "unused.fish".to_string(),
                     }],
+                    ..Default::default()
                 },
                 used_plugin_spec: PluginSpec {
                     name: None,
+                    prerelease: false,
+                    disabled: false,
                     source: PluginSource::Repo {
                         repo: PluginRepo {
                             host: None,
@@ -534,6 +524,9 @@ mod tests {
                         tag: None,
                         commit: None,
                     },
+                    dir: None,
+                    events: vec![],
+                    disable_files: vec![],
                 },
             }
         }
@@ -563,6 +556,7 @@ mod tests {
                     .as_mut()
                     .expect("Lock file is not initialized"),
                 lock_file_path: &self.lock_file_path,
+                except: &[],
             }
         }
     }
@@ -573,6 +567,9 @@ mod tests {
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -595,23 +592,26 @@ mod tests {
     #[test]
     fn confirm_removal_accepts_yes_input() {
         let _lock = env_lock().lock().unwrap();
-        let _guard = ConfirmInputGuard::new(Some("y\n".to_string()));
+        let _answers = prompt::ScriptedAnswers::new(&["y\n"]);
         assert!(confirm_removal().unwrap());
     }
 
     #[test]
     fn confirm_removal_rejects_non_yes_input() {
         let _lock = env_lock().lock().unwrap();
-        let _guard = ConfirmInputGuard::new(Some("no\n".to_string()));
+        let _answers = prompt::ScriptedAnswers::new(&["no\n"]);
         assert!(!confirm_removal().unwrap());
     }
 
-    #[test]
-    fn test_prune() {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prune() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -621,7 +621,7 @@ mod tests {
 
         let mut ctx = test_env.create_context();
 
-        let result = prune(false, false, || Ok(false), &mut ctx);
+        let result = prune_parallel_with_confirm(false, false, 1, &mut ctx, || Ok(false)).await;
         assert!(result.is_ok());
 
         let saved_lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -645,12 +645,15 @@ mod tests {
         );
     }
 
-    #[test]
-    fn test_prune_empty_remove_plugins() {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prune_empty_remove_plugins() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -661,7 +664,7 @@ mod tests {
         let mut ctx = test_env.create_context();
         let prev_plugins_len = ctx.lock_file.plugins.len();
 
-        let result = prune(false, false, || Ok(false), &mut ctx);
+        let result = prune_parallel_with_confirm(false, false, 1, &mut ctx, || Ok(false)).await;
         assert!(result.is_ok());
 
         let saved_lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -676,11 +679,16 @@ mod tests {
         );
     }
 
-    #[test]
-    fn test_prune_empty_config_without_yes_and_confirm_removal_true() {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prune_empty_config_without_yes_and_confirm_removal_true() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
-        test_env.setup_config(config::Config { plugins: None });
+        test_env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
         test_env.setup_lock_file(LockFile {
             version: 1,
             plugins: vec![test_data.unused_plugin],
@@ -689,7 +697,7 @@ mod tests {
 
         let mut ctx = test_env.create_context();
 
-        let result = prune(false, false, || Ok(true), &mut ctx);
+        let result = prune_parallel_with_confirm(false, false, 1, &mut ctx, || Ok(true)).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -700,11 +708,16 @@ mod tests {
         );
     }
 
-    #[test]
-    fn test_prune_empty_config_without_yes_and_confirm_removal_false() {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prune_empty_config_without_yes_and_confirm_removal_false() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
-        test_env.setup_config(config::Config { plugins: None });
+        test_env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
         test_env.setup_lock_file(LockFile {
             version: 1,
             plugins: vec![test_data.unused_plugin],
@@ -714,7 +727,7 @@ mod tests {
         let mut ctx = test_env.create_context();
         let prev_plugins_len = ctx.lock_file.plugins.len();
 
-        let result = prune(false, false, || Ok(false), &mut ctx);
+        let result = prune_parallel_with_confirm(false, false, 1, &mut ctx, || Ok(false)).await;
         assert!(result.is_err_and(|e| e.to_string().contains("Prune process aborted.")));
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -729,11 +742,16 @@ mod tests {
         );
     }
 
-    #[test]
-    fn test_prune_empty_config_with_yes() {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prune_empty_config_with_yes() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
-        test_env.setup_config(config::Config { plugins: None });
+        test_env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
         test_env.setup_lock_file(LockFile {
             version: 1,
             plugins: vec![test_data.unused_plugin],
@@ -742,7 +760,7 @@ mod tests {
 
         let mut ctx = test_env.create_context();
 
-        let result = prune(false, true, || Ok(false), &mut ctx);
+        let result = prune_parallel_with_confirm(false, true, 1, &mut ctx, || Ok(false)).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -753,12 +771,15 @@ mod tests {
         );
     }
 
-    #[test]
-    fn test_prune_empty_config_missing_data_dir_with_force() {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prune_empty_config_missing_data_dir_with_force() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -772,7 +793,7 @@ mod tests {
 
         let mut ctx = test_env.create_context();
 
-        let result = prune(true, false, || Ok(false), &mut ctx);
+        let result = prune_parallel_with_confirm(true, false, 1, &mut ctx, || Ok(false)).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -787,12 +808,15 @@ mod tests {
         );
     }
 
-    #[test]
-    fn test_prune_empty_config_missing_data_dir_without_force() {
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_prune_empty_config_missing_data_dir_without_force() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -802,7 +826,7 @@ mod tests {
 
         let mut ctx = test_env.create_context();
 
-        let result = prune(false, false, || Ok(false), &mut ctx);
+        let result = prune_parallel_with_confirm(false, false, 1, &mut ctx, || Ok(false)).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -815,11 +839,13 @@ mod tests {
 
     #[tokio::test(flavor = "multi_thread")]
     async fn prune_parallel_missing_repo_with_force_removes_plugin() {
-        let _jobs = JobsGuard::set(1);
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -828,7 +854,7 @@ mod tests {
         test_env.setup_fish_config();
 
         let mut ctx = test_env.create_context();
-        let result = prune_parallel(true, true, &mut ctx).await;
+        let result = prune_parallel(true, true, 1, &mut ctx).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -850,11 +876,13 @@ mod tests {
 
     #[tokio::test(flavor = "multi_thread")]
     async fn prune_parallel_does_not_save_when_no_sources_removed() {
-        let _jobs = JobsGuard::set(1);
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -868,7 +896,7 @@ mod tests {
         fs::set_permissions(&test_env.lock_file_path, perms).unwrap();
 
         let mut ctx = test_env.create_context();
-        let result = prune_parallel(false, true, &mut ctx).await;
+        let result = prune_parallel(false, true, 1, &mut ctx).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -883,23 +911,32 @@ mod tests {
     async fn prune_parallel_aborts_without_yes_when_confirm_false() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
-        test_env.setup_config(config::Config { plugins: None });
+        test_env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
         test_env.setup_lock_file(LockFile {
             version: 1,
             plugins: vec![test_data.unused_plugin],
         });
 
         let mut ctx = test_env.create_context();
-        let result = prune_parallel_with_confirm(false, false, &mut ctx, || Ok(false)).await;
+        let result = prune_parallel_with_confirm(false, false, 1, &mut ctx, || Ok(false)).await;
         assert!(result.is_err_and(|e| e.to_string().contains("Prune process aborted.")));
     }
 
     #[tokio::test(flavor = "multi_thread")]
     async fn prune_parallel_skips_confirm_when_yes() {
-        let _jobs = JobsGuard::set(1);
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
-        test_env.setup_config(config::Config { plugins: None });
+        test_env.setup_config(config::Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
         test_env.setup_lock_file(LockFile {
             version: 1,
             plugins: vec![test_data.unused_plugin],
@@ -908,7 +945,7 @@ mod tests {
         test_env.setup_fish_config();
 
         let mut ctx = test_env.create_context();
-        let result = prune_parallel_with_confirm(true, true, &mut ctx, || Ok(false)).await;
+        let result = prune_parallel_with_confirm(true, true, 1, &mut ctx, || Ok(false)).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -921,11 +958,13 @@ mod tests {
 
     #[tokio::test(flavor = "multi_thread")]
     async fn prune_parallel_missing_repo_without_force_keeps_lock() {
-        let _jobs = JobsGuard::set(1);
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -936,7 +975,7 @@ mod tests {
         ]);
 
         let mut ctx = test_env.create_context();
-        let result = prune_parallel_with_confirm(false, true, &mut ctx, || Ok(true)).await;
+        let result = prune_parallel_with_confirm(false, true, 1, &mut ctx, || Ok(true)).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -949,11 +988,13 @@ mod tests {
 
     #[tokio::test(flavor = "multi_thread")]
     async fn prune_parallel_removes_unused_plugin_and_keeps_used() {
-        let _jobs = JobsGuard::set(1);
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -963,7 +1004,7 @@ mod tests {
         test_env.setup_fish_config();
 
         let mut ctx = test_env.create_context();
-        let result = prune_parallel_with_confirm(false, true, &mut ctx, || Ok(true)).await;
+        let result = prune_parallel_with_confirm(false, true, 1, &mut ctx, || Ok(true)).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
@@ -983,12 +1024,51 @@ mod tests {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn prune_parallel_keeps_plugins_matching_except() {
+        let mut test_env = TestEnvironmentSetup::new();
+        let test_data = TestDataBuilder::new().build();
+        let mut experiment = test_data.unused_plugin.clone();
+        experiment.name = "experiment".to_string();
+        experiment.repo.owner = "other".to_string();
+        experiment.repo.repo = "experiment".to_string();
+        experiment.source = "https://example.com/other/experiment".to_string();
+        test_env.setup_config(config::Config {
+            plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        test_env.setup_lock_file(LockFile {
+            version: 1,
+            plugins: vec![test_data.used_plugin, test_data.unused_plugin, experiment],
+        });
+        test_env.setup_data_repo(test_env.lock_file.as_ref().unwrap().get_plugin_repos());
+        test_env.setup_fish_config();
+
+        let except = vec!["*/experim?nt".to_string()];
+        let mut ctx = test_env.create_context();
+        ctx.except = &except;
+        prune_parallel_with_confirm(false, true, 1, &mut ctx, || Ok(true))
+            .await
+            .unwrap();
+
+        let lock_file = lock_file::load(ctx.lock_file_path).unwrap();
+        let repos: Vec<String> = lock_file.plugins.iter().map(|p| p.repo.as_str()).collect();
+        assert_eq!(repos, vec!["owner/used-repo", "other/experiment"]);
+        assert!(ctx.data_dir.join("other/experiment").exists());
+        assert!(!ctx.data_dir.join("owner/unused-repo").exists());
+    }
+
     #[test]
     fn test_prune_dry_run() {
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -1029,6 +1109,9 @@ mod tests {
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -1046,11 +1129,13 @@ mod tests {
 
     #[tokio::test(flavor = "multi_thread")]
     async fn run_removes_unused_plugin() {
-        let _jobs = JobsGuard::set(1);
         let mut test_env = TestEnvironmentSetup::new();
         let test_data = TestDataBuilder::new().build();
         test_env.setup_config(config::Config {
             plugins: Some(vec![test_data.used_plugin_spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         test_env.setup_lock_file(LockFile {
             version: 1,
@@ -1063,9 +1148,17 @@ mod tests {
             force: false,
             dry_run: false,
             yes: true,
+            temp: false,
+            except: vec![],
+            backup_dir: None,
+            no_backup: true,
         };
 
-        let result = with_env_async(&test_env, || run(&args)).await;
+        let options = RuntimeOptions {
+            jobs: Some(1),
+            ..Default::default()
+        };
+        let result = with_env_async(&test_env, || run(&args, &options)).await;
         assert!(result.is_ok());
 
         let lock_file = lock_file::load(&test_env.lock_file_path).unwrap();
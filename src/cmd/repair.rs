@@ -0,0 +1,382 @@
+use crate::{
+    backup,
+    cli::RepairArgs,
+    git,
+    lock_file::{Plugin, PluginFile},
+    utils,
+};
+
+use console::Emoji;
+use sha2::{Digest, Sha256};
+use std::{fs, path};
+use tracing::{info, warn};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct RepairSummary {
+    pub(crate) restored: usize,
+    pub(crate) unavailable: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileState {
+    Intact,
+    Missing,
+    Modified,
+}
+
+/// One installed file compared with the plugin's locked contents.
+pub(crate) struct FileCheck {
+    pub(crate) dest: path::PathBuf,
+    /// Only existence is checked when the locked contents are unavailable.
+    pub(crate) state: FileState,
+    /// The locked contents, or why they could not be read.
+    pub(crate) expected: anyhow::Result<Vec<u8>>,
+}
+
+/// Re-copy missing or modified plugin files from the local checkout at the locked
+/// commit. Never touches the network.
+pub(crate) fn run(args: &RepairArgs) -> anyhow::Result<RepairSummary> {
+    let _backup = backup::start_run("repair", None)?;
+    let (lock_file, _) = utils::load_lock_file()?;
+    let lock_file = lock_file.without_disabled(utils::load_config().ok().map(|(c, _)| c).as_ref());
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let data_dir = utils::load_pez_data_dir()?;
+
+    let plugins: Vec<&Plugin> = match &args.plugins {
+        Some(repos) => repos
+            .iter()
+            .map(|repo| {
+                lock_file
+                    .get_plugin_by_repo(repo)
+                    .ok_or_else(|| anyhow::anyhow!("Plugin is not installed: {}", repo))
+            })
+            .collect::<anyhow::Result<_>>()?,
+        None => lock_file.plugins.iter().collect(),
+    };
+
+    let mut summary = RepairSummary::default();
+    for plugin in plugins {
+        let plugin_summary = repair_plugin(plugin, &data_dir, &fish_config_dir)?;
+        summary.restored += plugin_summary.restored;
+        summary.unavailable += plugin_summary.unavailable;
+    }
+
+    if summary.restored == 0 && summary.unavailable == 0 {
+        info!("{}All plugin files are intact.", Emoji("🎉 ", ""));
+    } else {
+        info!(
+            "{}Restored {} file(s); {} could not be restored.",
+            Emoji("🔧 ", ""),
+            summary.restored,
+            summary.unavailable
+        );
+    }
+    Ok(summary)
+}
+
+fn repair_plugin(
+    plugin: &Plugin,
+    data_dir: &path::Path,
+    fish_config_dir: &path::Path,
+) -> anyhow::Result<RepairSummary> {
+    let mut summary = RepairSummary::default();
+    for FileCheck {
+        dest,
+        state,
+        expected,
+    } in check_plugin_files(plugin, data_dir, fish_config_dir)
+    {
+        if state == FileState::Intact {
+            continue;
+        }
+        let expected = match expected {
+            Ok(expected) => expected,
+            Err(e) => {
+                warn!("   - {} cannot be restored: {:#}", dest.display(), e);
+                summary.unavailable += 1;
+                continue;
+            }
+        };
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if dest.exists() {
+            backup::back_up_file(&dest)?;
+        }
+        fs::write(&dest, &expected)?;
+        let reason = match state {
+            FileState::Missing => "missing",
+            _ => "modified",
+        };
+        info!("   - {} ({}; restored)", dest.display(), reason);
+        summary.restored += 1;
+    }
+    Ok(summary)
+}
+
+/// Outcome of [`restore_missing`] for one plugin.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct MissingFiles {
+    pub(crate) restored: Vec<path::PathBuf>,
+    /// Recorded files the locked source does not contain, so they cannot come back.
+    pub(crate) not_in_source: Vec<PluginFile>,
+}
+
+/// Re-copy only the files of `plugin` that are gone, leaving modified files alone.
+/// Nothing is done when the clone or the locked commit cannot be read.
+pub(crate) fn restore_missing(
+    plugin: &Plugin,
+    data_dir: &path::Path,
+    fish_config_dir: &path::Path,
+) -> anyhow::Result<MissingFiles> {
+    let mut missing = MissingFiles::default();
+    let Ok(source) = PluginContents::open(plugin, data_dir) else {
+        return Ok(missing);
+    };
+    for file in &plugin.files {
+        let dest = file.get_path(fish_config_dir);
+        if dest.exists() {
+            continue;
+        }
+        match source.read(&plugin.source_path(file)) {
+            Ok(contents) => {
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&dest, contents)?;
+                missing.restored.push(dest);
+            }
+            Err(_) => missing.not_in_source.push(file.clone()),
+        }
+    }
+    Ok(missing)
+}
+
+/// Compare each of `plugin`'s installed files with the locked commit in its clone
+/// (or the directory of a local source). Reads only; nothing is fetched.
+pub(crate) fn check_plugin_files(
+    plugin: &Plugin,
+    data_dir: &path::Path,
+    fish_config_dir: &path::Path,
+) -> Vec<FileCheck> {
+    let source = PluginContents::open(plugin, data_dir);
+    if let Err(e) = &source {
+        warn!(
+            "{} {} Cannot read {}: {:#}",
+            Emoji("🚧 ", ""),
+            utils::label_warning(),
+            plugin.display_label(),
+            e
+        );
+    }
+    plugin
+        .files
+        .iter()
+        .map(|file| {
+            let dest = file.get_path(fish_config_dir);
+            let expected = match &source {
+                Ok(source) => source.read(&plugin.source_path(file)),
+                Err(_) => Err(anyhow::anyhow!("source unavailable")),
+            };
+            let state = file_state(&dest, expected.as_deref().ok());
+            FileCheck {
+                dest,
+                state,
+                expected,
+            }
+        })
+        .collect()
+}
+
+/// Compare a destination file with the expected contents. Without expected
+/// contents only existence is checked.
+fn file_state(dest: &path::Path, expected: Option<&[u8]>) -> FileState {
+    let Ok(actual) = fs::read(dest) else {
+        return FileState::Missing;
+    };
+    match expected {
+        Some(expected) if Sha256::digest(&actual) != Sha256::digest(expected) => {
+            FileState::Modified
+        }
+        _ => FileState::Intact,
+    }
+}
+
+enum PluginContents {
+    /// Files read from the git objects of the locked commit, independent of the worktree.
+    Locked {
+        repo: git2::Repository,
+        commit: git2::Oid,
+    },
+    /// Local path sources have no commit; read the directory as-is.
+    Local(path::PathBuf),
+}
+
+impl PluginContents {
+    fn open(plugin: &Plugin, data_dir: &path::Path) -> anyhow::Result<Self> {
+        if git::is_local_source(&plugin.source) {
+            return Ok(Self::Local(path::PathBuf::from(&plugin.source)));
+        }
+        let repo = git2::Repository::open(data_dir.join(plugin.repo.as_str()))?;
+        let commit = git2::Oid::from_str(&plugin.commit_sha)?;
+        repo.find_commit(commit)?;
+        Ok(Self::Locked { repo, commit })
+    }
+
+    fn read(&self, rel: &path::Path) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Locked { repo, commit } => {
+                let tree = repo.find_commit(*commit)?.tree()?;
+                let blob = tree.get_path(rel)?.to_object(repo)?.peel_to_blob()?;
+                Ok(blob.content().to_vec())
+            }
+            Self::Local(root) => Ok(fs::read(root.join(rel))?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lock_file::{LockFile, PluginFile},
+        models::{PluginRepo, TargetDir},
+        tests_support::env::TestEnvironmentSetup,
+    };
+    use std::ffi::OsString;
+
+    struct EnvOverride {
+        saved: Vec<(&'static str, Option<OsString>)>,
+    }
+
+    impl EnvOverride {
+        fn new(keys: &[&'static str]) -> Self {
+            Self {
+                saved: keys.iter().map(|k| (*k, std::env::var_os(k))).collect(),
+            }
+        }
+    }
+
+    impl Drop for EnvOverride {
+        fn drop(&mut self) {
+            for (key, value) in self.saved.drain(..) {
+                unsafe {
+                    match value {
+                        Some(v) => std::env::set_var(key, v),
+                        None => std::env::remove_var(key),
+                    }
+                }
+            }
+        }
+    }
+
+    fn commit_all(repo: &git2::Repository, message: &str) -> git2::Oid {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("pez", "pez@example.com").unwrap();
+        let parents: Vec<git2::Commit> = repo
+            .head()
+            .ok()
+            .and_then(|h| h.peel_to_commit().ok())
+            .into_iter()
+            .collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    #[test]
+    fn repair_restores_missing_and_modified_files_from_locked_commit() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut env = TestEnvironmentSetup::new();
+        let _env = EnvOverride::new(&["__fish_config_dir", "PEZ_CONFIG_DIR", "PEZ_DATA_DIR"]);
+        unsafe {
+            std::env::set_var("__fish_config_dir", &env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+        }
+
+        let repo_id: PluginRepo = "owner/repair".parse().unwrap();
+        let repo_path = env.data_dir.join(repo_id.as_str());
+        fs::create_dir_all(repo_path.join("functions")).unwrap();
+        fs::create_dir_all(repo_path.join("conf.d")).unwrap();
+        let repo = git2::Repository::init(&repo_path).unwrap();
+        fs::write(repo_path.join("functions/a.fish"), "locked a").unwrap();
+        fs::write(repo_path.join("conf.d/b.fish"), "locked b").unwrap();
+        let locked = commit_all(&repo, "locked");
+        // The worktree moves past the locked commit; repair must still use the lock.
+        fs::write(repo_path.join("functions/a.fish"), "newer a").unwrap();
+        commit_all(&repo, "newer");
+
+        let fish = env.fish_config_dir.clone();
+        fs::create_dir_all(fish.join("conf.d")).unwrap();
+        fs::create_dir_all(fish.join("functions")).unwrap();
+        fs::write(fish.join("conf.d/b.fish"), "edited by user").unwrap();
+
+        env.setup_lock_file(LockFile {
+            version: 1,
+            plugins: vec![Plugin {
+                name: "repair".into(),
+                repo: repo_id.clone(),
+                source: repo_id.default_remote_source(),
+                commit_sha: locked.to_string(),
+                files: vec![
+                    PluginFile {
+                        dir: TargetDir::Functions,
+                        name: "a.fish".into(),
+                    },
+                    PluginFile {
+                        dir: TargetDir::ConfD,
+                        name: "b.fish".into(),
+                    },
+                ],
+                ..Default::default()
+            }],
+        });
+
+        let args = RepairArgs {
+            plugins: Some(vec![repo_id]),
+        };
+        let summary = run(&args).unwrap();
+        assert_eq!(
+            summary,
+            RepairSummary {
+                restored: 2,
+                unavailable: 0
+            }
+        );
+        assert_eq!(
+            fs::read_to_string(fish.join("functions/a.fish")).unwrap(),
+            "locked a"
+        );
+        assert_eq!(
+            fs::read_to_string(fish.join("conf.d/b.fish")).unwrap(),
+            "locked b"
+        );
+
+        assert_eq!(run(&args).unwrap(), RepairSummary::default());
+    }
+
+    #[test]
+    fn repair_rejects_unknown_plugin() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut env = TestEnvironmentSetup::new();
+        let _env = EnvOverride::new(&["__fish_config_dir", "PEZ_CONFIG_DIR", "PEZ_DATA_DIR"]);
+        unsafe {
+            std::env::set_var("__fish_config_dir", &env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+        }
+        env.setup_lock_file(crate::lock_file::init());
+
+        let err = run(&RepairArgs {
+            plugins: Some(vec!["owner/missing".parse().unwrap()]),
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("not installed"));
+    }
+}
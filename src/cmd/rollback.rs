@@ -0,0 +1,342 @@
+use crate::{
+    backup,
+    cli::RollbackArgs,
+    cmd::upgrade,
+    git,
+    journal::{self, State},
+    lock_file::{self, LockFile, Plugin},
+    output::outln,
+    utils,
+};
+
+use console::Emoji;
+use std::{collections::HashSet, fs, path};
+use tracing::{info, warn};
+
+/// How one plugin differs between the current lock and the one being applied.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Change {
+    /// Not in the applied lock; its files are removed.
+    Remove(Plugin),
+    /// Not installed now; cloned again if needed and re-copied.
+    Restore(Plugin),
+    /// At another commit (or file set); checked out back at `plugin.commit_sha`.
+    Revert { plugin: Plugin, from: String },
+}
+
+impl Change {
+    fn render(&self) -> String {
+        match self {
+            Change::Remove(plugin) => format!("remove  {}", plugin.display_label()),
+            Change::Restore(plugin) => format!(
+                "restore {} at {}",
+                plugin.display_label(),
+                short_sha(&plugin.commit_sha)
+            ),
+            Change::Revert { plugin, from } => format!(
+                "revert  {} {} -> {}",
+                plugin.display_label(),
+                short_sha(from),
+                short_sha(&plugin.commit_sha)
+            ),
+        }
+    }
+}
+
+/// Put pez.toml, pez-lock.toml, clones, and installed files back to where they were
+/// before the last install, upgrade, or uninstall. Rolling back again redoes it.
+pub(crate) fn run(args: &RollbackArgs) -> anyhow::Result<Vec<Change>> {
+    let Some(journal) = journal::load(&journal::journal_path()?)? else {
+        anyhow::bail!("Nothing to roll back: no install, upgrade, or uninstall has been recorded");
+    };
+    info!(
+        "{}Rolling back `pez {}`:",
+        Emoji("⏪ ", ""),
+        journal.command
+    );
+    let target = State {
+        lock: journal.lock,
+        config: journal.config,
+    };
+    let _backup = (!args.dry_run)
+        .then(|| backup::start_run("rollback", None))
+        .transpose()?;
+    let changes = apply(target, "the rollback journal", args.dry_run)?;
+    if !args.dry_run {
+        info!(
+            "{}Rolled back `pez {}`. Run `pez rollback` again to redo it.",
+            Emoji("🎉 ", ""),
+            journal.command
+        );
+    }
+    Ok(changes)
+}
+
+/// Make the installed plugins match `target`: write its pez.toml and pez-lock.toml,
+/// remove plugins it does not list, and check out and re-copy the ones that differ.
+/// With `dry_run` only the planned changes are printed.
+pub(crate) fn apply(target: State, origin: &str, dry_run: bool) -> anyhow::Result<Vec<Change>> {
+    let current_lock = parse_lock(State::read()?.lock.as_deref(), "pez-lock.toml")?;
+    let target_lock = parse_lock(target.lock.as_deref(), origin)?;
+    let changes = plan(&current_lock, &target_lock);
+    for change in &changes {
+        outln!("{}", change.render());
+    }
+    if dry_run {
+        return Ok(changes);
+    }
+
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let data_dir = utils::load_pez_data_dir()?;
+    for change in &changes {
+        if let Change::Remove(plugin) = change {
+            utils::emit_plugin_events(plugin, &utils::Event::Uninstall);
+        }
+    }
+    remove_stale_files(&current_lock, &target_lock, &fish_config_dir);
+    target.write()?;
+
+    for change in &changes {
+        match change {
+            Change::Remove(_) => {}
+            Change::Restore(plugin) | Change::Revert { plugin, .. } => {
+                restore_plugin(plugin, &data_dir)?
+            }
+        }
+    }
+    Ok(changes)
+}
+
+fn parse_lock(content: Option<&str>, origin: &str) -> anyhow::Result<LockFile> {
+    match content {
+        Some(content) => lock_file::parse(content, origin),
+        None => Ok(lock_file::init()),
+    }
+}
+
+fn plan(current: &LockFile, previous: &LockFile) -> Vec<Change> {
+    let mut changes: Vec<Change> = current
+        .plugins
+        .iter()
+        .filter(|plugin| !previous.contains_repo(&plugin.repo))
+        .cloned()
+        .map(Change::Remove)
+        .collect();
+    for plugin in &previous.plugins {
+        match current.get_plugin_by_repo(&plugin.repo) {
+            None => changes.push(Change::Restore(plugin.clone())),
+            Some(now) if now.commit_sha != plugin.commit_sha || now.files != plugin.files => {
+                changes.push(Change::Revert {
+                    plugin: plugin.clone(),
+                    from: now.commit_sha.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    changes
+}
+
+/// Delete installed files the restored lock no longer lists.
+fn remove_stale_files(current: &LockFile, previous: &LockFile, fish_config_dir: &path::Path) {
+    let kept: HashSet<path::PathBuf> = previous
+        .plugins
+        .iter()
+        .flat_map(|plugin| plugin.resolve_paths(fish_config_dir, None))
+        .collect();
+    for dest in current
+        .plugins
+        .iter()
+        .flat_map(|plugin| plugin.resolve_paths(fish_config_dir, None))
+        .filter(|dest| !kept.contains(dest))
+    {
+        if dest.exists()
+            && let Err(e) = backup::remove_file(&dest)
+        {
+            warn!("Failed to remove {}: {:?}", dest.display(), e);
+        }
+    }
+}
+
+/// Bring `plugin` back to its entry in the restored lock: clone it again when the
+/// clone is gone, then check out the locked commit and re-copy its files.
+pub(crate) fn restore_plugin(plugin: &Plugin, data_dir: &path::Path) -> anyhow::Result<()> {
+    if git::is_local_source(&plugin.source) {
+        let mut restored = Plugin {
+            files: vec![],
+            ..plugin.clone()
+        };
+        utils::copy_plugin_files_from_repo(path::Path::new(&plugin.source), &mut restored)?;
+        utils::emit_plugin_events(&restored, &utils::Event::Install);
+        return Ok(());
+    }
+    let repo_path = data_dir.join(plugin.repo.as_str());
+    if !repo_path.exists() {
+        info!(
+            "{}Cloning {} again",
+            Emoji("🐟 ", ""),
+            plugin.display_label()
+        );
+        if let Some(parent) = repo_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        git::clone_repository(&plugin.source, &repo_path)?;
+    }
+    upgrade::sync_plugin_to_lock(
+        &plugin.repo,
+        upgrade::UpgradeOptions {
+            force: true,
+            allow_dirty_repo: false,
+            since: None,
+            jobs: 1,
+        },
+    )?;
+    Ok(())
+}
+
+fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lock_file::PluginFile,
+        models::{PluginRepo, TargetDir},
+        tests_support::{env::TestEnvironmentSetup, log::env_lock},
+    };
+
+    fn plugin(repo: &str, commit: &str, files: &[&str]) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: commit.into(),
+            files: files
+                .iter()
+                .map(|name| PluginFile {
+                    dir: TargetDir::Functions,
+                    name: name.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn lock(plugins: Vec<Plugin>) -> LockFile {
+        LockFile {
+            version: 2,
+            plugins,
+        }
+    }
+
+    #[test]
+    fn plan_lists_removed_restored_and_reverted_plugins() {
+        let current = lock(vec![
+            plugin("owner/kept", "aaaaaaa1", &["kept.fish"]),
+            plugin("owner/new", "bbbbbbb2", &["new.fish"]),
+            plugin("owner/upgraded", "ccccccc3", &["up.fish"]),
+        ]);
+        let previous = lock(vec![
+            plugin("owner/kept", "aaaaaaa1", &["kept.fish"]),
+            plugin("owner/upgraded", "ddddddd4", &["up.fish"]),
+            plugin("owner/gone", "eeeeeee5", &["gone.fish"]),
+        ]);
+
+        let rendered: Vec<String> = plan(&current, &previous)
+            .iter()
+            .map(Change::render)
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "remove  owner/new".to_string(),
+                "revert  owner/upgraded ccccccc -> ddddddd".to_string(),
+                "restore owner/gone at eeeeeee".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn run_restores_previous_lock_config_and_files() {
+        let mut env = TestEnvironmentSetup::new();
+        let source = env.data_dir.parent().unwrap().join("local-plugin");
+        fs::create_dir_all(source.join("functions")).unwrap();
+        fs::write(source.join("functions/old.fish"), "old").unwrap();
+        let local: PluginRepo = "local/local-plugin".parse().unwrap();
+        let local_plugin = Plugin {
+            name: "local-plugin".into(),
+            repo: local,
+            source: source.display().to_string(),
+            commit_sha: "local".into(),
+            files: vec![PluginFile {
+                dir: TargetDir::Functions,
+                name: "old.fish".into(),
+            }],
+            ..Default::default()
+        };
+        // The rolled-back command replaced the local plugin with owner/new.
+        env.setup_lock_file(lock(vec![plugin("owner/new", "bbbbbbb2", &["new.fish"])]));
+        fs::create_dir_all(env.fish_config_dir.join("functions")).unwrap();
+        fs::write(env.fish_config_dir.join("functions/new.fish"), "new").unwrap();
+        let previous_lock = toml::to_string(&lock(vec![local_plugin])).unwrap();
+        let previous_config = "[[plugins]]\nrepo = \"owner/kept\"\n";
+
+        let _lock = env_lock().lock().unwrap();
+        let saved: Vec<_> = [
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "__fish_config_dir",
+            "PEZ_SUPPRESS_EMIT",
+        ]
+        .iter()
+        .map(|key| (*key, std::env::var_os(key)))
+        .collect();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+            std::env::set_var("__fish_config_dir", &env.fish_config_dir);
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+        }
+        journal::save(
+            &journal::journal_path().unwrap(),
+            &journal::Journal {
+                time: 0,
+                command: "install".into(),
+                lock: Some(previous_lock.clone()),
+                config: Some(previous_config.into()),
+            },
+        )
+        .unwrap();
+
+        let changes = run(&RollbackArgs { dry_run: true }).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(env.fish_config_dir.join("functions/new.fish").exists());
+
+        run(&RollbackArgs { dry_run: false }).unwrap();
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+
+        assert!(!env.fish_config_dir.join("functions/new.fish").exists());
+        assert_eq!(
+            fs::read_to_string(env.fish_config_dir.join("functions/old.fish")).unwrap(),
+            "old"
+        );
+        assert_eq!(
+            fs::read_to_string(&env.lock_file_path).unwrap(),
+            previous_lock
+        );
+        assert_eq!(
+            fs::read_to_string(&env.config_path).unwrap(),
+            previous_config
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{aliases, cli, git, models::PluginRepo, output, runtime::RuntimeOptions, utils};
+
+use console::Emoji;
+use serde_derive::Serialize;
+use std::{fs, io::Write, path};
+use tabled::{Table, Tabled};
+use tracing::{debug, info, warn};
+
+/// Curated list of fish plugins whose README serves as the search index.
+const INDEX_REPO: &str = "https://github.com/jorgebucaran/awsm.fish";
+/// Cached copy of the index README under the pez cache directory.
+pub(crate) const INDEX_FILE_NAME: &str = "search-index.md";
+const INDEX_TTL_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct SearchResult {
+    pub(crate) repo: String,
+    pub(crate) name: String,
+    pub(crate) description: Option<String>,
+    /// Only known when built with the `forge-api` feature.
+    pub(crate) stars: Option<u64>,
+}
+
+#[derive(Debug, Tabled)]
+struct SearchRow {
+    repo: String,
+    stars: String,
+    description: String,
+}
+
+pub(crate) fn run(args: &cli::SearchArgs, options: &RuntimeOptions) -> anyhow::Result<String> {
+    run_with_writer(args, options, &mut output::Stdout)
+}
+
+fn run_with_writer<W: Write>(
+    args: &cli::SearchArgs,
+    options: &RuntimeOptions,
+    writer: &mut W,
+) -> anyhow::Result<String> {
+    let query = args.query.join(" ");
+    let index_path = utils::load_pez_cache_dir()?.join(INDEX_FILE_NAME);
+    let markdown = if args.offline {
+        fs::read_to_string(&index_path).ok()
+    } else {
+        load_index(&index_path, args.refresh)
+    };
+    if markdown.is_none() {
+        warn!(
+            "{} Plugin index unavailable; searching bundled aliases only.",
+            utils::label_warning()
+        );
+    }
+    let entries = with_bundled_aliases(parse_index(markdown.as_deref().unwrap_or_default()));
+
+    let mut results = search(&entries, &query);
+    results.truncate(args.limit);
+    if !args.offline {
+        fill_stars(&mut results);
+    }
+
+    let format = args.format.clone().unwrap_or(cli::ListFormat::Plain);
+    if results.is_empty() && format.structured().is_none() {
+        info!("{}No plugins matched '{}'.", Emoji("🔍 ", ""), query);
+        return Ok(String::new());
+    }
+    let output = match format {
+        cli::ListFormat::Plain => render_plain(&results),
+        cli::ListFormat::Table => {
+            let rows: Vec<SearchRow> = results
+                .iter()
+                .map(|r| SearchRow {
+                    repo: r.repo.clone(),
+                    stars: r.stars.map_or_else(|| "-".into(), |s| s.to_string()),
+                    description: r.description.clone().unwrap_or_default(),
+                })
+                .collect();
+            Table::new(&rows).to_string()
+        }
+        cli::ListFormat::Json | cli::ListFormat::Yaml => {
+            output::to_structured(&results, format.structured().unwrap(), options.json_style)?
+        }
+        cli::ListFormat::Github => {
+            anyhow::bail!("--format github is only supported by `pez list --outdated`")
+        }
+    };
+    writer.write_all(output.as_bytes())?;
+    Ok(output)
+}
+
+/// Read the cached index, refreshing it first when it is missing, older than a day,
+/// or `refresh` is set. A failed refresh falls back to the stale copy.
+fn load_index(path: &path::Path, refresh: bool) -> Option<String> {
+    let fresh = !refresh
+        && fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age.as_secs() < INDEX_TTL_SECS);
+    if !fresh {
+        match fetch_index() {
+            Ok(markdown) => {
+                if let Err(e) = path
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(path, &markdown))
+                {
+                    warn!(
+                        "Failed to cache plugin index at {}: {:?}",
+                        path.display(),
+                        e
+                    );
+                }
+                return Some(markdown);
+            }
+            Err(e) => warn!(
+                "{} Could not refresh the plugin index from {}: {:#}",
+                utils::label_warning(),
+                INDEX_REPO,
+                e
+            ),
+        }
+    }
+    fs::read_to_string(path).ok()
+}
+
+fn fetch_index() -> anyhow::Result<String> {
+    let temp = tempfile::tempdir()?;
+    git::clone_repository(INDEX_REPO, temp.path())?;
+    Ok(fs::read_to_string(temp.path().join("README.md"))?)
+}
+
+/// Entries from markdown list items such as
+/// `- [z](https://github.com/jethrokuan/z) - Pure-fish z directory jumping.`
+fn parse_index(markdown: &str) -> Vec<SearchResult> {
+    let mut entries: Vec<SearchResult> = Vec::new();
+    for line in markdown.lines() {
+        let Some(item) = line
+            .trim_start()
+            .strip_prefix("- [")
+            .or_else(|| line.trim_start().strip_prefix("* ["))
+        else {
+            continue;
+        };
+        let Some((name, rest)) = item.split_once("](") else {
+            continue;
+        };
+        let Some((url, rest)) = rest.split_once(')') else {
+            continue;
+        };
+        let Some(repo) = PluginRepo::from_remote_url(url).filter(is_forge_repo) else {
+            continue;
+        };
+        let description = rest
+            .trim_start_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '—' | '–' | ':'))
+            .trim();
+        let repo = repo.as_str();
+        if entries.iter().any(|e| e.repo == repo) {
+            continue;
+        }
+        entries.push(SearchResult {
+            repo,
+            name: name.to_string(),
+            description: (!description.is_empty()).then(|| description.to_string()),
+            stars: None,
+        });
+    }
+    entries
+}
+
+/// The index also links to docs and articles; keep only links to code forges.
+fn is_forge_repo(repo: &PluginRepo) -> bool {
+    repo.host.as_deref().is_none_or(|host| {
+        ["gitlab", "codeberg.org", "bitbucket.org", "sr.ht", "gitea"]
+            .iter()
+            .any(|forge| host.contains(forge))
+    })
+}
+
+fn with_bundled_aliases(mut entries: Vec<SearchResult>) -> Vec<SearchResult> {
+    for (alias, repo) in aliases::BUNDLED {
+        if !entries.iter().any(|e| e.repo == *repo) {
+            entries.push(SearchResult {
+                repo: repo.to_string(),
+                name: alias.to_string(),
+                description: None,
+                stars: None,
+            });
+        }
+    }
+    entries
+}
+
+/// Entries matching every word of `query`, best matches first: exact names, then
+/// names/repos containing the word, then owners, then descriptions.
+fn search(entries: &[SearchResult], query: &str) -> Vec<SearchResult> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let mut scored: Vec<(usize, &SearchResult)> = entries
+        .iter()
+        .filter_map(|entry| {
+            terms
+                .iter()
+                .map(|term| term_score(entry, term))
+                .sum::<Option<usize>>()
+                .map(|score| (score, entry))
+        })
+        .collect();
+    scored.sort_by(|(a_score, a), (b_score, b)| a_score.cmp(b_score).then(a.repo.cmp(&b.repo)));
+    scored.into_iter().map(|(_, entry)| entry.clone()).collect()
+}
+
+fn term_score(entry: &SearchResult, term: &str) -> Option<usize> {
+    let name = entry.name.to_lowercase();
+    let repo = entry.repo.to_lowercase();
+    let (owner, repo_name) = repo.rsplit_once('/')?;
+    let owner = owner.rsplit('/').next().unwrap_or(owner);
+    let bare = |s: &str| s.trim_end_matches(".fish").to_string();
+    if bare(&name) == bare(term) || bare(repo_name) == bare(term) {
+        Some(0)
+    } else if name.contains(term) || repo_name.contains(term) {
+        Some(1)
+    } else if owner.contains(term) {
+        Some(2)
+    } else if entry
+        .description
+        .as_deref()
+        .is_some_and(|d| d.to_lowercase().contains(term))
+    {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+fn render_plain(results: &[SearchResult]) -> String {
+    let width = results.iter().map(|r| r.repo.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for result in results {
+        let mut line = format!("{:width$}", result.repo);
+        if let Some(stars) = result.stars {
+            line.push_str(&format!("  ★ {stars}"));
+        }
+        if let Some(description) = &result.description {
+            line.push_str("  ");
+            line.push_str(description);
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Look up stars (and missing descriptions) for the listed results through the forge API.
+#[cfg(feature = "forge-api")]
+fn fill_stars(results: &mut [SearchResult]) {
+    let client = match crate::forge::ForgeClient::new() {
+        Ok(client) => client,
+        Err(e) => {
+            debug!("Forge client unavailable: {:?}", e);
+            return;
+        }
+    };
+    for result in results.iter_mut() {
+        let Ok(repo) = result.repo.parse::<PluginRepo>() else {
+            continue;
+        };
+        match client.repo_info(&repo) {
+            Ok(info) => {
+                result.stars = info.stars;
+                if result.description.is_none() {
+                    result.description = info.description;
+                }
+            }
+            Err(e) => debug!("No forge metadata for {}: {:#}", result.repo, e),
+        }
+    }
+}
+
+#[cfg(not(feature = "forge-api"))]
+fn fill_stars(_results: &mut [SearchResult]) {
+    debug!("Built without forge-api; star counts are unavailable");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_support::log::env_lock;
+
+    const INDEX: &str = "\
+# awsm.fish
+
+## Plugins
+
+- [autopair.fish](https://github.com/jorgebucaran/autopair.fish) - Auto-complete matching pairs.
+- [z](https://github.com/jethrokuan/z) — Directory jumping.
+* [fzf.fish](https://github.com/PatrickF1/fzf.fish): Fzf key bindings.
+- [Fish docs](https://fishshell.com/docs/current/) - Not a plugin.
+- [gitlab-plugin](https://gitlab.com/someone/gitlab-plugin)
+";
+
+    fn entry(repo: &str, name: &str, description: Option<&str>) -> SearchResult {
+        SearchResult {
+            repo: repo.into(),
+            name: name.into(),
+            description: description.map(str::to_string),
+            stars: None,
+        }
+    }
+
+    #[test]
+    fn parse_index_reads_markdown_list_items() {
+        let entries = parse_index(INDEX);
+        assert_eq!(
+            entries,
+            vec![
+                entry(
+                    "jorgebucaran/autopair.fish",
+                    "autopair.fish",
+                    Some("Auto-complete matching pairs.")
+                ),
+                entry("jethrokuan/z", "z", Some("Directory jumping.")),
+                entry("PatrickF1/fzf.fish", "fzf.fish", Some("Fzf key bindings.")),
+                entry("gitlab.com/someone/gitlab-plugin", "gitlab-plugin", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn search_ranks_names_before_descriptions() {
+        let entries = vec![
+            entry("someone/jump", "jump", Some("Like z but slower")),
+            entry("jethrokuan/z", "z", Some("Directory jumping.")),
+            entry("PatrickF1/fzf.fish", "fzf.fish", Some("Fzf key bindings.")),
+        ];
+        let repos = |query: &str| {
+            search(&entries, query)
+                .into_iter()
+                .map(|r| r.repo)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(repos("jump"), vec!["someone/jump", "jethrokuan/z"]);
+        assert_eq!(repos("FZF"), vec!["PatrickF1/fzf.fish"]);
+        assert_eq!(repos("fzf bindings"), vec!["PatrickF1/fzf.fish"]);
+        assert!(repos("fzf jump").is_empty());
+    }
+
+    #[test]
+    fn run_uses_fresh_cached_index_and_bundled_aliases() {
+        let _lock = env_lock().lock().unwrap();
+        let saved = std::env::var_os("PEZ_CACHE_DIR");
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join(INDEX_FILE_NAME), INDEX).unwrap();
+        unsafe {
+            std::env::set_var("PEZ_CACHE_DIR", temp.path());
+        }
+
+        let mut args = cli::SearchArgs {
+            query: vec!["pairs".into()],
+            format: Some(cli::ListFormat::Json),
+            limit: 20,
+            refresh: false,
+            offline: true,
+        };
+        let mut out = Vec::new();
+        let json = run_with_writer(&args, &RuntimeOptions::default(), &mut out).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["repo"], "jorgebucaran/autopair.fish");
+
+        // Bundled aliases are searchable even when the index lacks them.
+        args.query = vec!["tide".into()];
+        args.format = None;
+        let plain = run_with_writer(&args, &RuntimeOptions::default(), &mut out).unwrap();
+        assert_eq!(plain, "IlanCosman/tide\n");
+
+        unsafe {
+            match saved {
+                Some(v) => std::env::set_var("PEZ_CACHE_DIR", v),
+                None => std::env::remove_var("PEZ_CACHE_DIR"),
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{cli::SmokeArgs, lock_file, models::TargetDir, output::outln};
+
+use console::Emoji;
+use std::{
+    fs, path,
+    process::{Command, Output},
+};
+use tracing::info;
+
+const FIXTURE_NAME: &str = "pez_smoke";
+/// Local path plugins are recorded under the `local` owner.
+const FIXTURE_REPO: &str = "local/pez_smoke";
+const MARKER_ENV: &str = "PEZ_SMOKE_MARKER";
+
+const FIXTURE_FUNCTION: &str = "function pez_smoke\n    echo ok\nend\n";
+const FIXTURE_COMPLETION: &str = "complete -c pez_smoke -f\n";
+const FIXTURE_CONF: &str = "\
+function _pez_smoke_install --on-event pez_smoke_install
+    echo install >> $PEZ_SMOKE_MARKER
+end
+function _pez_smoke_uninstall --on-event pez_smoke_uninstall
+    echo uninstall >> $PEZ_SMOKE_MARKER
+end
+";
+
+#[derive(Debug)]
+pub(crate) struct SmokeCheck {
+    pub(crate) name: &'static str,
+    pub(crate) status: &'static str,
+    pub(crate) details: String,
+}
+
+impl SmokeCheck {
+    fn ok(name: &'static str, details: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: "ok",
+            details: details.into(),
+        }
+    }
+
+    fn error(name: &'static str, details: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: "error",
+            details: details.into(),
+        }
+    }
+
+    fn skip(name: &'static str, details: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: "skip",
+            details: details.into(),
+        }
+    }
+}
+
+/// Temp directories standing in for the fish config and data dirs, plus the fixture.
+struct Sandbox {
+    root: path::PathBuf,
+    fish_config_dir: path::PathBuf,
+    plugin_dir: path::PathBuf,
+    marker: path::PathBuf,
+    fish_available: bool,
+}
+
+impl Sandbox {
+    fn create(root: &path::Path) -> anyhow::Result<Self> {
+        let plugin_dir = root.join("fixture").join(FIXTURE_NAME);
+        for (dir, content) in fixture_files() {
+            let dir = plugin_dir.join(dir.as_str());
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join(format!("{FIXTURE_NAME}.fish")), content)?;
+        }
+        Ok(Self {
+            root: root.to_path_buf(),
+            fish_config_dir: root.join("config").join("fish"),
+            plugin_dir,
+            marker: root.join("events.log"),
+            fish_available: Command::new("fish").arg("--version").output().is_ok(),
+        })
+    }
+
+    /// Run this pez binary with every directory pointed into the sandbox. fish is
+    /// pointed there too, so emitted events load the fixture's conf.d file.
+    fn pez(&self, args: &[&str]) -> anyhow::Result<Output> {
+        let mut command = Command::new(std::env::current_exe()?);
+        command
+            .args(args)
+            .env("XDG_CONFIG_HOME", self.root.join("config"))
+            .env("XDG_DATA_HOME", self.root.join("data"))
+            .env(MARKER_ENV, &self.marker)
+            .env_remove("__fish_config_dir")
+            .env_remove("__fish_user_data_dir")
+            .env_remove("PEZ_CONFIG_DIR")
+            .env_remove("PEZ_DATA_DIR")
+            .env_remove("PEZ_TARGET_DIR")
+            .env_remove("PEZ_CACHE_DIR")
+            .env_remove("PEZ_FISH_PRIVATE")
+            .env_remove("RUST_LOG")
+            .env("RUST_BACKTRACE", "0");
+        if self.fish_available {
+            command.env_remove("PEZ_SUPPRESS_EMIT");
+        } else {
+            command.env("PEZ_SUPPRESS_EMIT", "1");
+        }
+        Ok(command.output()?)
+    }
+
+    fn events(&self) -> Vec<String> {
+        fs::read_to_string(&self.marker)
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+fn fixture_files() -> [(TargetDir, &'static str); 3] {
+    [
+        (TargetDir::Functions, FIXTURE_FUNCTION),
+        (TargetDir::Completions, FIXTURE_COMPLETION),
+        (TargetDir::ConfD, FIXTURE_CONF),
+    ]
+}
+
+/// Install and uninstall a built-in fixture plugin in a throwaway sandbox and
+/// report each step. The real fish config and pez state are never touched.
+pub(crate) fn run(args: &SmokeArgs) -> anyhow::Result<Vec<SmokeCheck>> {
+    let temp = tempfile::Builder::new().prefix("pez-smoke-").tempdir()?;
+    let sandbox = Sandbox::create(temp.path())?;
+    info!(
+        "{}Running smoke test in {}",
+        Emoji("🧪 ", ""),
+        temp.path().display()
+    );
+
+    let checks = run_checks(&sandbox)?;
+    for check in &checks {
+        outln!(
+            "{} {:<14} - {}",
+            status_prefix(check.status),
+            check.name,
+            check.details
+        );
+    }
+
+    if args.keep {
+        let kept = temp.keep();
+        info!("Sandbox kept at {}", kept.display());
+    }
+    if checks.iter().any(|check| check.status == "error") {
+        anyhow::bail!("smoke test failed");
+    }
+    info!("{}pez works on this system.", Emoji("🎉 ", ""));
+    Ok(checks)
+}
+
+fn run_checks(sandbox: &Sandbox) -> anyhow::Result<Vec<SmokeCheck>> {
+    let mut checks = Vec::new();
+
+    let install = sandbox.pez(&["install", &sandbox.plugin_dir.to_string_lossy()])?;
+    if !install.status.success() {
+        checks.push(SmokeCheck::error("install", failure_details(&install)));
+        return Ok(checks);
+    }
+    checks.push(SmokeCheck::ok("install", "pez install exited successfully"));
+    checks.push(check_files_installed(sandbox));
+    checks.push(check_lock(sandbox, true));
+    checks.push(check_event(sandbox, "install_event", "install"));
+
+    // Local plugins have no clone in the data dir, which uninstall only accepts with --force.
+    let uninstall = sandbox.pez(&["uninstall", "--force", FIXTURE_REPO])?;
+    if !uninstall.status.success() {
+        checks.push(SmokeCheck::error("uninstall", failure_details(&uninstall)));
+        return Ok(checks);
+    }
+    checks.push(SmokeCheck::ok(
+        "uninstall",
+        "pez uninstall exited successfully",
+    ));
+    checks.push(check_files_removed(sandbox));
+    checks.push(check_lock(sandbox, false));
+    checks.push(check_event(sandbox, "uninstall_event", "uninstall"));
+    Ok(checks)
+}
+
+fn check_files_installed(sandbox: &Sandbox) -> SmokeCheck {
+    for (dir, expected) in fixture_files() {
+        let dest = sandbox
+            .fish_config_dir
+            .join(dir.as_str())
+            .join(format!("{FIXTURE_NAME}.fish"));
+        match fs::read_to_string(&dest) {
+            Ok(content) if content == expected => {}
+            Ok(_) => return SmokeCheck::error("files", format!("{} differs", dest.display())),
+            Err(e) => return SmokeCheck::error("files", format!("{}: {e}", dest.display())),
+        }
+    }
+    SmokeCheck::ok("files", "functions, completions and conf.d files copied")
+}
+
+fn check_files_removed(sandbox: &Sandbox) -> SmokeCheck {
+    let left: Vec<String> = fixture_files()
+        .iter()
+        .map(|(dir, _)| {
+            sandbox
+                .fish_config_dir
+                .join(dir.as_str())
+                .join(format!("{FIXTURE_NAME}.fish"))
+        })
+        .filter(|dest| dest.exists())
+        .map(|dest| dest.display().to_string())
+        .collect();
+    if left.is_empty() {
+        SmokeCheck::ok("files_removed", "plugin files removed")
+    } else {
+        SmokeCheck::error("files_removed", format!("left behind: {}", left.join(", ")))
+    }
+}
+
+fn check_lock(sandbox: &Sandbox, installed: bool) -> SmokeCheck {
+    let name = if installed { "lock" } else { "lock_removed" };
+    let lock_path = sandbox.fish_config_dir.join("pez-lock.toml");
+    let lock = match lock_file::load(&lock_path) {
+        Ok(lock) => lock,
+        Err(e) => return SmokeCheck::error(name, format!("{e:#}")),
+    };
+    let entry = lock
+        .plugins
+        .iter()
+        .find(|plugin| plugin.repo.as_str() == FIXTURE_REPO);
+    match (installed, entry) {
+        (true, Some(plugin)) if plugin.files.len() == fixture_files().len() => SmokeCheck::ok(
+            name,
+            format!("{FIXTURE_REPO} recorded with {} files", plugin.files.len()),
+        ),
+        (true, Some(plugin)) => SmokeCheck::error(
+            name,
+            format!(
+                "{FIXTURE_REPO} recorded with {} files, expected {}",
+                plugin.files.len(),
+                fixture_files().len()
+            ),
+        ),
+        (true, None) => SmokeCheck::error(name, format!("{FIXTURE_REPO} missing")),
+        (false, None) => SmokeCheck::ok(name, format!("{FIXTURE_REPO} no longer recorded")),
+        (false, Some(_)) => SmokeCheck::error(name, format!("{FIXTURE_REPO} still recorded")),
+    }
+}
+
+fn check_event(sandbox: &Sandbox, name: &'static str, event: &str) -> SmokeCheck {
+    if !sandbox.fish_available {
+        return SmokeCheck::skip(name, "fish not found on PATH");
+    }
+    if sandbox.events().iter().any(|line| line == event) {
+        SmokeCheck::ok(name, format!("{FIXTURE_NAME}_{event} handled by fish"))
+    } else {
+        SmokeCheck::error(name, format!("{FIXTURE_NAME}_{event} was not handled"))
+    }
+}
+
+fn failure_details(output: &Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("Error: "))
+        .or_else(|| stderr.lines().rev().find(|line| !line.trim().is_empty()));
+    format!(
+        "exited with {}: {}",
+        output.status,
+        message.unwrap_or("no output")
+    )
+}
+
+fn status_prefix(status: &str) -> &'static str {
+    match status {
+        "ok" => "✔",
+        "skip" => "-",
+        _ => "✖",
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    backup,
+    cli::{RestoreArgs, SnapshotArgs},
+    cmd::rollback::{self, Change},
+    journal::State,
+    output::outln,
+    utils,
+};
+
+use console::Emoji;
+use std::{fs, path};
+use tracing::info;
+
+/// Directory under the pez data dir holding one subdirectory per named snapshot.
+pub(crate) const SNAPSHOT_DIR_NAME: &str = ".snapshots";
+
+const LOCK_FILE_NAME: &str = "pez-lock.toml";
+const CONFIG_FILE_NAME: &str = "pez.toml";
+
+/// Save pez.toml and pez-lock.toml as snapshot `name`, or list snapshots without a name.
+pub(crate) fn save(args: &SnapshotArgs) -> anyhow::Result<()> {
+    let root = snapshots_dir()?;
+    let Some(name) = &args.name else {
+        let names = list(&root)?;
+        if names.is_empty() {
+            info!("No snapshots yet; create one with `pez snapshot <name>`.");
+        }
+        for name in names {
+            outln!("{name}");
+        }
+        return Ok(());
+    };
+    let dir = snapshot_dir(&root, name)?;
+    if dir.exists() && !args.force {
+        anyhow::bail!("Snapshot '{name}' already exists; pass --force to replace it");
+    }
+    let state = State::read()?;
+    if state.lock.is_none() && state.config.is_none() {
+        anyhow::bail!("Nothing to snapshot: neither pez.toml nor pez-lock.toml exists");
+    }
+    write(&dir, &state)?;
+    info!(
+        "{}Saved snapshot '{}'. Run `pez restore {}` to return to it.",
+        Emoji("📸 ", ""),
+        name,
+        name
+    );
+    Ok(())
+}
+
+/// Bring pez.toml, pez-lock.toml, clones, and installed files back to snapshot `name`.
+pub(crate) fn restore(args: &RestoreArgs) -> anyhow::Result<Vec<Change>> {
+    let dir = snapshot_dir(&snapshots_dir()?, &args.name)?;
+    if !dir.is_dir() {
+        let known = list(&snapshots_dir()?)?;
+        anyhow::bail!(
+            "No snapshot named '{}'{}",
+            args.name,
+            if known.is_empty() {
+                String::new()
+            } else {
+                format!("; available: {}", known.join(", "))
+            }
+        );
+    }
+    info!("{}Restoring snapshot '{}':", Emoji("⏪ ", ""), args.name);
+    let _backup = (!args.dry_run)
+        .then(|| backup::start_run("restore", None))
+        .transpose()?;
+    let changes = rollback::apply(
+        read(&dir)?,
+        &format!("snapshot '{}'", args.name),
+        args.dry_run,
+    )?;
+    if !args.dry_run {
+        info!(
+            "{}Restored snapshot '{}'. `pez rollback` undoes this.",
+            Emoji("🎉 ", ""),
+            args.name
+        );
+    }
+    Ok(changes)
+}
+
+fn snapshots_dir() -> anyhow::Result<path::PathBuf> {
+    Ok(utils::load_pez_data_dir()?.join(SNAPSHOT_DIR_NAME))
+}
+
+/// Names become directory names, so only plain file-name characters are accepted.
+fn snapshot_dir(root: &path::Path, name: &str) -> anyhow::Result<path::PathBuf> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        anyhow::bail!(
+            "Invalid snapshot name '{name}': use letters, digits, '-', '_', or '.', not starting with '.'"
+        );
+    }
+    Ok(root.join(name))
+}
+
+fn list(root: &path::Path) -> anyhow::Result<Vec<String>> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// A file missing from the snapshot was missing when it was taken.
+fn write(dir: &path::Path, state: &State) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (file_name, content) in [
+        (LOCK_FILE_NAME, &state.lock),
+        (CONFIG_FILE_NAME, &state.config),
+    ] {
+        let file = dir.join(file_name);
+        match content {
+            Some(content) => fs::write(&file, content)?,
+            None if file.exists() => fs::remove_file(&file)?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+fn read(dir: &path::Path) -> anyhow::Result<State> {
+    let read_optional = |file_name: &str| -> anyhow::Result<Option<String>> {
+        let file = dir.join(file_name);
+        if file.exists() {
+            Ok(Some(fs::read_to_string(file)?))
+        } else {
+            Ok(None)
+        }
+    };
+    Ok(State {
+        lock: read_optional(LOCK_FILE_NAME)?,
+        config: read_optional(CONFIG_FILE_NAME)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_names_must_be_plain_file_names() {
+        let root = path::Path::new("/data/.snapshots");
+        assert_eq!(
+            snapshot_dir(root, "before-upgrade_2.0").unwrap(),
+            root.join("before-upgrade_2.0")
+        );
+        for bad in ["", ".hidden", "../escape", "a/b", "with space"] {
+            assert!(
+                snapshot_dir(root, bad).is_err(),
+                "{bad:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn write_and_read_round_trip_missing_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join(SNAPSHOT_DIR_NAME);
+        let dir = root.join("work");
+        let state = State {
+            lock: Some("version = 2\n".into()),
+            config: None,
+        };
+        write(&dir, &state).unwrap();
+        assert_eq!(read(&dir).unwrap(), state);
+
+        let replaced = State {
+            lock: None,
+            config: Some("[[plugins]]\nrepo = \"owner/repo\"\n".into()),
+        };
+        write(&dir, &replaced).unwrap();
+        assert_eq!(read(&dir).unwrap(), replaced);
+        assert_eq!(list(&root).unwrap(), vec!["work"]);
+    }
+}
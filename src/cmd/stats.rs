@@ -0,0 +1,383 @@
+use crate::{
+    cli,
+    cmd::{cache, list},
+    git,
+    history::{self, Action, HistoryEntry},
+    lock_file::LockFile,
+    models::TargetDir,
+    output::{self, out, outln},
+    runtime::RuntimeOptions,
+    update_cache, utils,
+};
+
+use serde_derive::Serialize;
+use std::{collections::BTreeMap, path};
+use tracing::info;
+
+/// How many plugins the "most upgraded" ranking shows.
+const TOP_UPGRADED: usize = 5;
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub(crate) struct UsageStats {
+    /// Install count keyed by `YYYY-MM` (UTC).
+    pub(crate) installs_per_month: BTreeMap<String, usize>,
+    pub(crate) most_upgraded: Vec<UpgradeCount>,
+    pub(crate) upgrades: usize,
+    pub(crate) average_upgrade_ms: Option<u64>,
+    /// Installed plugins, largest clone first.
+    pub(crate) plugins: Vec<PluginUsage>,
+    pub(crate) totals: DiskTotals,
+}
+
+/// Installed files per fish directory.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub(crate) struct FileCounts {
+    pub(crate) functions: usize,
+    pub(crate) completions: usize,
+    pub(crate) conf_d: usize,
+    pub(crate) themes: usize,
+}
+
+impl FileCounts {
+    fn add(&mut self, dir: &TargetDir) {
+        match dir {
+            TargetDir::Functions => self.functions += 1,
+            TargetDir::Completions => self.completions += 1,
+            TargetDir::ConfD => self.conf_d += 1,
+            TargetDir::Themes => self.themes += 1,
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.functions + self.completions + self.conf_d + self.themes
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct PluginUsage {
+    pub(crate) name: String,
+    pub(crate) repo: String,
+    /// Size of the clone under the data directory; `None` for local-path plugins and
+    /// missing clones.
+    pub(crate) clone_bytes: Option<u64>,
+    pub(crate) files: FileCounts,
+    /// Unix time of the last install or upgrade, from pez-lock.toml.
+    pub(crate) updated_at: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub(crate) struct DiskTotals {
+    pub(crate) plugins: usize,
+    pub(crate) clone_bytes: u64,
+    pub(crate) files: FileCounts,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct UpgradeCount {
+    pub(crate) plugin: String,
+    pub(crate) upgrades: usize,
+}
+
+pub(crate) fn run(args: &cli::StatsArgs, options: &RuntimeOptions) -> anyhow::Result<UsageStats> {
+    let entries = history::load(&history::history_path()?)?;
+    let mut stats = aggregate(&entries);
+    if let Ok((lock_file, _)) = utils::load_lock_file() {
+        (stats.plugins, stats.totals) = disk_usage(&lock_file, &utils::load_pez_data_dir()?);
+    }
+    match args.format {
+        Some(cli::StatsFormat::Json) => {
+            outln!("{}", output::to_json(&stats, options.json_style)?);
+        }
+        None => {
+            out!("{}", render_disk(&stats, update_cache::now_secs()));
+            if entries.is_empty() {
+                info!(
+                    "No history recorded yet. Enable it with `history = true` under [settings] in pez.toml."
+                );
+            } else {
+                out!("{}", render_plain(&stats));
+            }
+        }
+    }
+    Ok(stats)
+}
+
+fn aggregate(entries: &[HistoryEntry]) -> UsageStats {
+    let mut stats = UsageStats::default();
+    let mut upgrade_counts: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut durations: Vec<u64> = Vec::new();
+
+    for entry in entries {
+        match entry.action {
+            Action::Install => {
+                *stats
+                    .installs_per_month
+                    .entry(month_of(entry.time))
+                    .or_default() += 1;
+            }
+            Action::Upgrade => {
+                stats.upgrades += 1;
+                *upgrade_counts.entry(&entry.plugin).or_default() += 1;
+                durations.extend(entry.duration_ms);
+            }
+            Action::Uninstall => {}
+        }
+    }
+
+    let mut ranked: Vec<UpgradeCount> = upgrade_counts
+        .into_iter()
+        .map(|(plugin, upgrades)| UpgradeCount {
+            plugin: plugin.to_string(),
+            upgrades,
+        })
+        .collect();
+    // Stable sort keeps ties in plugin-name order.
+    ranked.sort_by_key(|entry| std::cmp::Reverse(entry.upgrades));
+    ranked.truncate(TOP_UPGRADED);
+    stats.most_upgraded = ranked;
+
+    if !durations.is_empty() {
+        stats.average_upgrade_ms = Some(durations.iter().sum::<u64>() / durations.len() as u64);
+    }
+    stats
+}
+
+/// Clone size and installed file counts per plugin, plus their sum.
+fn disk_usage(lock_file: &LockFile, data_dir: &path::Path) -> (Vec<PluginUsage>, DiskTotals) {
+    let mut totals = DiskTotals::default();
+    let mut plugins: Vec<PluginUsage> = lock_file
+        .plugins
+        .iter()
+        .map(|plugin| {
+            let clone = data_dir.join(plugin.repo.as_str());
+            let clone_bytes = (!git::is_local_source(&plugin.source) && clone.exists())
+                .then(|| cache::dir_size(&clone));
+            let mut files = FileCounts::default();
+            for file in &plugin.files {
+                files.add(&file.dir);
+                totals.files.add(&file.dir);
+            }
+            totals.plugins += 1;
+            totals.clone_bytes += clone_bytes.unwrap_or(0);
+            PluginUsage {
+                name: plugin.get_name(),
+                repo: plugin.repo.as_str(),
+                clone_bytes,
+                files,
+                updated_at: plugin.updated_at,
+            }
+        })
+        .collect();
+    // Stable sort keeps ties in lock file order.
+    plugins.sort_by_key(|usage| std::cmp::Reverse(usage.clone_bytes.unwrap_or(0)));
+    (plugins, totals)
+}
+
+/// `YYYY-MM` (UTC) for a Unix timestamp.
+fn month_of(secs: u64) -> String {
+    // Civil-from-days conversion (proleptic Gregorian calendar).
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}")
+}
+
+fn render_disk(stats: &UsageStats, now: u64) -> String {
+    if stats.plugins.is_empty() {
+        return String::new();
+    }
+    let width = stats
+        .plugins
+        .iter()
+        .map(|usage| usage.repo.len())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::from("Disk usage:\n");
+    for usage in &stats.plugins {
+        let size = usage
+            .clone_bytes
+            .map_or_else(|| "-".to_string(), cache::format_bytes);
+        out.push_str(&format!(
+            "  {:<width$}  {size:>9}  {}  updated {}\n",
+            usage.repo,
+            render_counts(&usage.files),
+            list::format_age(usage.updated_at, now)
+        ));
+    }
+    out.push_str(&format!(
+        "Total: {} plugin(s), {} in clones, {} file(s) installed ({})\n",
+        stats.totals.plugins,
+        cache::format_bytes(stats.totals.clone_bytes),
+        stats.totals.files.total(),
+        render_counts(&stats.totals.files)
+    ));
+    out
+}
+
+fn render_counts(files: &FileCounts) -> String {
+    format!(
+        "functions {}, completions {}, conf.d {}, themes {}",
+        files.functions, files.completions, files.conf_d, files.themes
+    )
+}
+
+fn render_plain(stats: &UsageStats) -> String {
+    let mut out = String::new();
+    out.push_str("Installs per month:\n");
+    if stats.installs_per_month.is_empty() {
+        out.push_str("  (none)\n");
+    }
+    for (month, count) in &stats.installs_per_month {
+        out.push_str(&format!("  {month}  {count}\n"));
+    }
+    out.push_str("Most upgraded plugins:\n");
+    if stats.most_upgraded.is_empty() {
+        out.push_str("  (none)\n");
+    }
+    for entry in &stats.most_upgraded {
+        out.push_str(&format!("  {}  {}\n", entry.plugin, entry.upgrades));
+    }
+    match stats.average_upgrade_ms {
+        Some(ms) => out.push_str(&format!(
+            "Average upgrade duration: {:.1}s ({} upgrades)\n",
+            ms as f64 / 1000.0,
+            stats.upgrades
+        )),
+        None => out.push_str("Average upgrade duration: -\n"),
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lock_file::{Plugin, PluginFile},
+        models::PluginRepo,
+    };
+    use std::fs;
+
+    fn entry(time: u64, action: Action, plugin: &str, duration_ms: Option<u64>) -> HistoryEntry {
+        HistoryEntry {
+            time,
+            action,
+            plugin: plugin.into(),
+            duration_ms,
+        }
+    }
+
+    #[test]
+    fn month_of_converts_unix_time() {
+        assert_eq!(month_of(0), "1970-01");
+        // 2024-02-29T12:00:00Z
+        assert_eq!(month_of(1_709_208_000), "2024-02");
+        // 2025-12-31T23:59:59Z
+        assert_eq!(month_of(1_767_225_599), "2025-12");
+    }
+
+    #[test]
+    fn aggregate_counts_installs_and_upgrades() {
+        let entries = vec![
+            entry(0, Action::Install, "owner/a", None),
+            entry(1_709_208_000, Action::Install, "owner/b", None),
+            entry(1_709_208_001, Action::Install, "owner/c", None),
+            entry(10, Action::Upgrade, "owner/b", Some(1000)),
+            entry(11, Action::Upgrade, "owner/b", Some(3000)),
+            entry(12, Action::Upgrade, "owner/a", None),
+            entry(13, Action::Uninstall, "owner/c", None),
+        ];
+        let stats = aggregate(&entries);
+        assert_eq!(
+            stats.installs_per_month,
+            BTreeMap::from([("1970-01".to_string(), 1), ("2024-02".to_string(), 2)])
+        );
+        assert_eq!(
+            stats.most_upgraded,
+            vec![
+                UpgradeCount {
+                    plugin: "owner/b".into(),
+                    upgrades: 2
+                },
+                UpgradeCount {
+                    plugin: "owner/a".into(),
+                    upgrades: 1
+                },
+            ]
+        );
+        assert_eq!(stats.upgrades, 3);
+        assert_eq!(stats.average_upgrade_ms, Some(2000));
+
+        let rendered = render_plain(&stats);
+        assert!(rendered.contains("  2024-02  2\n"));
+        assert!(rendered.contains("Average upgrade duration: 2.0s (3 upgrades)"));
+    }
+
+    #[test]
+    fn disk_usage_sizes_clones_and_counts_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let clone = temp.path().join("owner/big");
+        fs::create_dir_all(clone.join(".git")).unwrap();
+        fs::write(clone.join(".git/pack"), vec![0u8; 2048]).unwrap();
+        let plugin = |repo: &str, files: Vec<TargetDir>, updated_at: Option<u64>| {
+            let repo: PluginRepo = repo.parse().unwrap();
+            Plugin {
+                name: repo.repo.clone(),
+                source: repo.default_remote_source(),
+                repo,
+                commit_sha: "abc".into(),
+                files: files
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, dir)| PluginFile {
+                        dir,
+                        name: format!("f{i}.fish"),
+                    })
+                    .collect(),
+                updated_at,
+                ..Default::default()
+            }
+        };
+        let lock_file = LockFile {
+            version: 2,
+            plugins: vec![
+                plugin("owner/uncloned", vec![TargetDir::ConfD], None),
+                plugin(
+                    "owner/big",
+                    vec![
+                        TargetDir::Functions,
+                        TargetDir::Functions,
+                        TargetDir::Completions,
+                    ],
+                    Some(1_000),
+                ),
+            ],
+        };
+
+        let (plugins, totals) = disk_usage(&lock_file, temp.path());
+        assert_eq!(plugins[0].repo, "owner/big");
+        assert_eq!(plugins[0].clone_bytes, Some(2048));
+        assert_eq!(plugins[0].files.functions, 2);
+        assert_eq!(plugins[1].clone_bytes, None);
+        assert_eq!(totals.plugins, 2);
+        assert_eq!(totals.clone_bytes, 2048);
+        assert_eq!(totals.files.total(), 4);
+
+        let stats = UsageStats {
+            plugins,
+            totals,
+            ..Default::default()
+        };
+        let rendered = render_disk(&stats, 1_000 + 2 * 86_400);
+        assert!(rendered.contains(
+            "  owner/big         2.0 KiB  functions 2, completions 1, conf.d 0, themes 0  updated 2d ago\n"
+        ));
+        assert!(rendered.contains("  owner/uncloned          -  "));
+        assert!(rendered.contains("Total: 2 plugin(s), 2.0 KiB in clones, 4 file(s) installed"));
+    }
+}
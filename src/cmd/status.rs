@@ -0,0 +1,251 @@
+use crate::{
+    cli,
+    cmd::{doctor, list, prune},
+    config,
+    lock_file::LockFile,
+    output::{self, out, outln},
+    runtime::RuntimeOptions,
+    update_cache::CachePolicy,
+    utils,
+};
+
+use serde_derive::Serialize;
+use std::path;
+
+/// Snapshot of pez.toml, the lock file, and the installed files.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub(crate) struct Status {
+    /// Plugins declared in pez.toml.
+    pub(crate) configured: usize,
+    /// Plugins recorded in pez-lock.toml.
+    pub(crate) installed: usize,
+    /// Declared in pez.toml but not installed yet.
+    pub(crate) not_installed: Vec<String>,
+    /// Installed plugins whose clone is gone from the data directory.
+    pub(crate) missing_clones: Vec<String>,
+    /// Installed files that no longer exist under the fish config directory.
+    pub(crate) missing_files: Vec<String>,
+    /// Installed plugins behind their selected ref, per cached update checks.
+    pub(crate) outdated: Vec<String>,
+    /// Installed plugins without a cached update check.
+    pub(crate) unchecked: Vec<String>,
+    /// Installed plugins no longer declared in pez.toml.
+    pub(crate) orphaned: Vec<String>,
+}
+
+pub(crate) fn run(args: &cli::StatusArgs, options: &RuntimeOptions) -> anyhow::Result<Status> {
+    let config = utils::load_config().ok().map(|(c, _)| c);
+    let lock_file = utils::load_lock_file()
+        .map(|(l, _)| l)
+        .unwrap_or_else(|_| crate::lock_file::init());
+    let status = collect(
+        config.as_ref(),
+        &lock_file,
+        &utils::load_fish_config_dir()?,
+        &utils::load_pez_data_dir()?,
+    )?;
+    match args.format {
+        Some(cli::StatusFormat::Json) => {
+            outln!("{}", output::to_json(&status, options.json_style)?);
+        }
+        None => out!("{}", render_plain(&status)),
+    }
+    Ok(status)
+}
+
+fn collect(
+    config: Option<&config::Config>,
+    lock_file: &LockFile,
+    fish_config_dir: &path::Path,
+    pez_data_dir: &path::Path,
+) -> anyhow::Result<Status> {
+    let specs = config
+        .and_then(|c| c.plugins.as_deref())
+        .unwrap_or_default();
+    let not_installed = specs
+        .iter()
+        .filter_map(|spec| spec.get_plugin_repo().ok())
+        .filter(|repo| !lock_file.contains_repo(repo))
+        .map(|repo| repo.as_str())
+        .collect();
+
+    // Outdated state comes from the update-check cache only; `pez list --outdated`
+    // refreshes it. Keeps status fast and offline.
+    let check = list::check_outdated(&lock_file.plugins, config, &CachePolicy::CachedOnly)?;
+
+    let orphaned = match config {
+        Some(config) => prune::find_unused_plugins(config, lock_file)?
+            .iter()
+            .map(|p| p.repo.as_str())
+            .collect(),
+        None => vec![],
+    };
+
+    Ok(Status {
+        configured: specs.len(),
+        installed: lock_file.plugins.len(),
+        not_installed,
+        missing_clones: doctor::missing_clones(lock_file, pez_data_dir)
+            .iter()
+            .map(|p| p.repo.as_str())
+            .collect(),
+        missing_files: doctor::missing_target_files(
+            &lock_file.clone().without_disabled(config),
+            fish_config_dir,
+        )
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect(),
+        outdated: check
+            .outdated
+            .iter()
+            .map(|o| o.plugin.repo.as_str())
+            .collect(),
+        unchecked: check
+            .unchecked
+            .iter()
+            .map(|(p, _)| p.repo.as_str())
+            .collect(),
+        orphaned,
+    })
+}
+
+fn render_plain(status: &Status) -> String {
+    let mut out = format!(
+        "Configured: {} (pez.toml)\nInstalled:  {} (pez-lock.toml)\n",
+        status.configured, status.installed
+    );
+    let sections = [
+        ("Not installed", &status.not_installed, "pez install"),
+        ("Missing clones", &status.missing_clones, "pez repair"),
+        ("Missing files", &status.missing_files, "pez repair"),
+        ("Outdated", &status.outdated, "pez upgrade"),
+        ("Orphaned", &status.orphaned, "pez prune"),
+    ];
+    for (label, items, fix) in sections {
+        if items.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("{label} ({}; fix with `{fix}`):\n", items.len()));
+        for item in items {
+            out.push_str(&format!("  {item}\n"));
+        }
+    }
+    if !status.unchecked.is_empty() {
+        out.push_str(&format!(
+            "Update state unknown for {} plugin(s); run `pez list --outdated` to check.\n",
+            status.unchecked.len()
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{Config, PluginSource, PluginSpec},
+        lock_file::{Plugin, PluginFile},
+        models::{PluginRepo, TargetDir},
+        tests_support::log::env_lock,
+        update_cache::{self, UPDATE_CHECK_FILE_NAME},
+    };
+    use std::fs;
+
+    fn spec(repo: &str) -> PluginSpec {
+        PluginSpec {
+            name: None,
+            prerelease: false,
+            disabled: false,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
+            source: PluginSource::Repo {
+                repo: repo.parse().unwrap(),
+                version: None,
+                branch: None,
+                tag: None,
+                commit: None,
+            },
+        }
+    }
+
+    fn plugin(repo: &str, file: &str) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "old".into(),
+            files: vec![PluginFile {
+                dir: TargetDir::Functions,
+                name: file.into(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn collect_summarizes_config_lock_and_disk() {
+        let _lock = env_lock().lock().unwrap();
+        let temp = tempfile::tempdir().unwrap();
+        let fish = temp.path().join("fish");
+        let data = temp.path().join("data");
+        let cache_dir = temp.path().join("cache");
+        let saved = std::env::var_os("PEZ_CACHE_DIR");
+        unsafe {
+            std::env::set_var("PEZ_CACHE_DIR", &cache_dir);
+        }
+
+        let config = Config {
+            plugins: Some(vec![spec("owner/a"), spec("owner/b"), spec("owner/new")]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        };
+        let lock_file = LockFile {
+            version: 2,
+            plugins: vec![
+                plugin("owner/a", "a.fish"),
+                plugin("owner/b", "b.fish"),
+                plugin("owner/gone", "gone.fish"),
+            ],
+        };
+        // owner/a is intact; owner/b lost its clone and file.
+        fs::create_dir_all(data.join("owner/a")).unwrap();
+        fs::create_dir_all(data.join("owner/gone")).unwrap();
+        fs::create_dir_all(fish.join("functions")).unwrap();
+        fs::write(fish.join("functions/a.fish"), "").unwrap();
+        fs::write(fish.join("functions/gone.fish"), "").unwrap();
+        let mut cache = update_cache::UpdateCheckCache::default();
+        cache.record(&"owner/a".parse().unwrap(), "origin/HEAD", "new", 1);
+        cache.save(&cache_dir.join(UPDATE_CHECK_FILE_NAME)).unwrap();
+
+        let status = collect(Some(&config), &lock_file, &fish, &data).unwrap();
+
+        unsafe {
+            match saved {
+                Some(v) => std::env::set_var("PEZ_CACHE_DIR", v),
+                None => std::env::remove_var("PEZ_CACHE_DIR"),
+            }
+        }
+
+        assert_eq!(
+            status,
+            Status {
+                configured: 3,
+                installed: 3,
+                not_installed: vec!["owner/new".into()],
+                missing_clones: vec!["owner/b".into()],
+                missing_files: vec![fish.join("functions/b.fish").display().to_string()],
+                outdated: vec!["owner/a".into()],
+                unchecked: vec!["owner/b".into(), "owner/gone".into()],
+                orphaned: vec!["owner/gone".into()],
+            }
+        );
+        let plain = render_plain(&status);
+        assert!(plain.starts_with("Configured: 3 (pez.toml)\nInstalled:  3 (pez-lock.toml)\n"));
+        assert!(plain.contains("Orphaned (1; fix with `pez prune`):\n  owner/gone\n"));
+        assert!(plain.contains("Update state unknown for 2 plugin(s)"));
+    }
+}
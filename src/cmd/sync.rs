@@ -0,0 +1,112 @@
+use crate::{
+    backup,
+    cli::{RepairArgs, SyncArgs},
+    cmd::{install, prune, repair},
+    runtime::RuntimeOptions,
+};
+
+use console::Emoji;
+use tracing::info;
+
+/// Bring the lock file and fish config dir in line with pez.toml: drop `install --temp`
+/// trials, install what is missing, prune what is no longer declared, and restore missing
+/// or modified files. Running it again on a synced setup changes nothing.
+pub(crate) async fn run(args: &SyncArgs, options: &RuntimeOptions) -> anyhow::Result<()> {
+    info!("{}Syncing plugins with pez.toml...", Emoji("🔄 ", ""));
+    let _backup = backup::start_run("sync", None)?;
+
+    // Like every install from pez.toml, this removes `install --temp` plugins first.
+    install::install_all(&false, &true)?;
+    // Removal must not depend on the clone still existing, so always force.
+    prune::prune_unused(prune::PruneOptions {
+        force: true,
+        dry_run: false,
+        yes: args.yes,
+        jobs: options.jobs(),
+        except: Vec::new(),
+    })
+    .await?;
+    let summary = repair::run(&RepairArgs { plugins: None })?;
+    if summary.unavailable > 0 {
+        anyhow::bail!(
+            "{} file(s) could not be restored; run `pez install --force` to re-clone",
+            summary.unavailable
+        );
+    }
+
+    info!("{}Plugins are in sync with pez.toml.", Emoji("✅ ", ""));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::InstallArgs,
+        config, lock_file,
+        models::{InstallTarget, TargetDir},
+        tests_support::{env::TestEnvironmentSetup, log::env_lock},
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sync_removes_temp_installs() {
+        let _lock = env_lock().lock().unwrap();
+        let mut env = TestEnvironmentSetup::new();
+        let keys = [
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "PEZ_TARGET_DIR",
+            "PEZ_SUPPRESS_EMIT",
+            "__fish_config_dir",
+        ];
+        let saved: Vec<_> = keys
+            .iter()
+            .map(|key| (*key, std::env::var_os(key)))
+            .collect();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+            std::env::set_var("PEZ_TARGET_DIR", &env.fish_config_dir);
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+            std::env::remove_var("__fish_config_dir");
+        }
+        env.setup_config(config::init());
+        env.setup_lock_file(lock_file::init());
+        let source_dir = env._temp_dir.path().join("trial-plugin");
+        std::fs::create_dir_all(source_dir.join(TargetDir::ConfD.as_str())).unwrap();
+        std::fs::write(source_dir.join("conf.d/trial-plugin.fish"), "echo trial\n").unwrap();
+        let install_args = InstallArgs {
+            plugins: Some(vec![InstallTarget::from_raw(
+                source_dir.to_string_lossy().to_string(),
+            )]),
+            force: false,
+            prune: false,
+            dry_run: false,
+            yes: false,
+            temp: true,
+            edit: false,
+        };
+        let options = RuntimeOptions::default();
+
+        let result = tokio::task::block_in_place(|| {
+            let handle = tokio::runtime::Handle::current();
+            handle.block_on(install::run(&install_args, &options))?;
+            let installed = env.fish_config_dir.join("conf.d/trial-plugin.fish");
+            assert!(installed.exists());
+            handle.block_on(run(&SyncArgs { yes: true }, &options))?;
+            anyhow::Ok(installed)
+        });
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+        let installed = result.unwrap();
+        assert!(!installed.exists());
+        assert!(!env.data_dir.join(".temp-lock.toml").exists());
+        assert!(source_dir.exists());
+    }
+}
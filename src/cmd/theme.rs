@@ -0,0 +1,191 @@
+use crate::{
+    cli::{ThemeArgs, ThemeCommand},
+    lock_file::LockFile,
+    models::TargetDir,
+    output::outln,
+    resolver, utils,
+};
+
+use anyhow::Context;
+use console::Emoji;
+use std::{io::Write, path, process};
+use tracing::info;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ThemeEntry {
+    /// The file stem, which is what `fish_config theme` calls the theme.
+    pub(crate) name: String,
+    pub(crate) plugin: String,
+}
+
+pub(crate) fn run(args: &ThemeArgs) -> anyhow::Result<()> {
+    match &args.command {
+        ThemeCommand::List => run_list(),
+        ThemeCommand::Set(set) => run_set(&set.name),
+    }
+}
+
+fn run_list() -> anyhow::Result<()> {
+    let themes = match utils::load_lock_file() {
+        Ok((lock_file, _)) => themes(&lock_file),
+        Err(_) => vec![],
+    };
+    if themes.is_empty() {
+        info!("No plugin themes installed");
+        return Ok(());
+    }
+    let width = themes.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    for theme in &themes {
+        outln!("{:width$}  {}", theme.name, theme.plugin);
+    }
+    Ok(())
+}
+
+/// Make a plugin theme the current fish colors through `fish_config theme save`, which
+/// writes them to universal variables so every session picks them up.
+fn run_set(name: &str) -> anyhow::Result<()> {
+    let (lock_file, _) = utils::load_lock_file()?;
+    let themes = themes(&lock_file);
+    let theme = find_theme(&themes, name)?;
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let file = theme_path(&fish_config_dir, &theme.name);
+    if !file.exists() {
+        anyhow::bail!(
+            "{} is missing; run `pez repair {}` to restore it",
+            file.display(),
+            theme.plugin
+        );
+    }
+
+    // `fish_config theme save` asks before overwriting the current colors; running
+    // `pez theme set` already answers that.
+    let mut child = process::Command::new("fish")
+        .arg("-c")
+        .arg("fish_config theme save $argv[1]")
+        .arg(&theme.name)
+        .stdin(process::Stdio::piped())
+        .spawn()
+        .context("Failed to spawn fish; is it installed and on PATH?")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(b"y\n");
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("`fish_config theme save {}` failed ({status})", theme.name);
+    }
+    info!(
+        "{}Set theme {} from {}",
+        Emoji("🎨 ", ""),
+        theme.name,
+        theme.plugin
+    );
+    Ok(())
+}
+
+/// Every `themes/*.theme` file recorded in the lock, sorted by name.
+pub(crate) fn themes(lock_file: &LockFile) -> Vec<ThemeEntry> {
+    let mut themes: Vec<ThemeEntry> = lock_file
+        .plugins
+        .iter()
+        .flat_map(|plugin| {
+            plugin
+                .files
+                .iter()
+                .filter(|file| file.dir == TargetDir::Themes)
+                .filter_map(|file| file.name.strip_suffix(".theme"))
+                .map(|name| ThemeEntry {
+                    name: name.to_string(),
+                    plugin: plugin.repo.as_str(),
+                })
+        })
+        .collect();
+    themes.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.plugin.cmp(&b.plugin)));
+    themes
+}
+
+fn find_theme<'a>(themes: &'a [ThemeEntry], name: &str) -> anyhow::Result<&'a ThemeEntry> {
+    let name = name.strip_suffix(".theme").unwrap_or(name);
+    if let Some(theme) = themes.iter().find(|t| t.name == name) {
+        return Ok(theme);
+    }
+    if themes.is_empty() {
+        anyhow::bail!("No plugin themes installed; install a plugin that ships themes/*.theme");
+    }
+    let suggestion = themes
+        .iter()
+        .filter(|t| resolver::edit_distance(&t.name, name) <= 2)
+        .min_by_key(|t| resolver::edit_distance(&t.name, name))
+        .map(|t| format!(" Did you mean `{}`?", t.name))
+        .unwrap_or_default();
+    anyhow::bail!("No plugin theme named `{name}`.{suggestion} Run `pez theme list` to see them")
+}
+
+fn theme_path(fish_config_dir: &path::Path, name: &str) -> path::PathBuf {
+    fish_config_dir
+        .join(TargetDir::Themes.as_str())
+        .join(format!("{name}.theme"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lock_file::{Plugin, PluginFile},
+        models::PluginRepo,
+    };
+
+    fn plugin(repo: &str, files: &[(TargetDir, &str)]) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "0123456789abcdef".into(),
+            files: files
+                .iter()
+                .map(|(dir, name)| PluginFile {
+                    dir: dir.clone(),
+                    name: name.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn themes_come_from_lock_and_match_by_name() {
+        let lock_file = LockFile {
+            version: 2,
+            plugins: vec![
+                plugin(
+                    "catppuccin/fish",
+                    &[
+                        (TargetDir::Themes, "Catppuccin Mocha.theme"),
+                        (TargetDir::Themes, "Catppuccin Latte.theme"),
+                    ],
+                ),
+                plugin(
+                    "owner/prompt",
+                    &[
+                        (TargetDir::Functions, "fish_prompt.fish"),
+                        (TargetDir::Themes, "dracula.theme"),
+                    ],
+                ),
+            ],
+        };
+        let themes = themes(&lock_file);
+        let names: Vec<&str> = themes.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Catppuccin Latte", "Catppuccin Mocha", "dracula"]
+        );
+
+        assert_eq!(
+            find_theme(&themes, "dracula.theme").unwrap().plugin,
+            "owner/prompt"
+        );
+        let err = find_theme(&themes, "dracla").unwrap_err().to_string();
+        assert!(err.contains("Did you mean `dracula`?"), "{err}");
+        assert!(find_theme(&[], "dracula").is_err());
+    }
+}
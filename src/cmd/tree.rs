@@ -0,0 +1,376 @@
+use crate::{
+    cli::{TreeArgs, TreeFormat},
+    config::Config,
+    lock_file::LockFile,
+    models::InstallTarget,
+    output::{self, outln},
+    resolver::RefKind,
+    runtime::RuntimeOptions,
+    utils,
+};
+
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, path,
+};
+use tracing::warn;
+
+/// File at the root of a plugin's repository that declares the plugins it needs.
+pub(crate) const MANIFEST_FILE_NAME: &str = "pez-plugin.toml";
+
+#[derive(Deserialize, Debug, Default)]
+struct Manifest {
+    /// Install targets in the same forms `pez install` accepts, e.g. `owner/repo@v2`.
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct Node {
+    pub(crate) repo: String,
+    /// The entry as written in the parent's manifest; `None` for top-level plugins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) declared: Option<String>,
+    /// Not in pez-lock.toml.
+    pub(crate) missing: bool,
+    /// Installed but disabled in pez.toml.
+    pub(crate) disabled: bool,
+    /// Already on the path from the top-level plugin; its dependencies are not repeated.
+    pub(crate) cycle: bool,
+    /// Other manifests' entries that ask for a different ref of the same repo.
+    pub(crate) conflicts: Vec<String>,
+    pub(crate) dependencies: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+struct Dependency {
+    declared: String,
+    repo: String,
+    ref_kind: RefKind,
+}
+
+/// Installed plugins and the dependencies each one's manifest declares.
+struct Graph {
+    /// Repo of every installed plugin, in pez-lock.toml order, with its disabled state.
+    installed: Vec<(String, bool)>,
+    dependencies: BTreeMap<String, Vec<Dependency>>,
+}
+
+/// Print installed plugins as a tree of the dependencies they declare in `pez-plugin.toml`.
+pub(crate) fn run(args: &TreeArgs, options: &RuntimeOptions) -> anyhow::Result<Vec<Node>> {
+    let (lock_file, _) = utils::load_lock_file()?;
+    let data_dir = utils::load_pez_data_dir()?;
+    let config = utils::load_config().ok().map(|(config, _)| config);
+
+    let tree = build(&load_graph(&lock_file, &data_dir, config.as_ref()));
+    match args.format {
+        Some(TreeFormat::Json) => outln!("{}", output::to_json(&tree, options.json_style)?),
+        None => {
+            for line in render(&tree) {
+                outln!("{line}");
+            }
+        }
+    }
+    let missing = count(&tree, &|node| node.missing);
+    let conflicts = count(&tree, &|node| !node.conflicts.is_empty());
+    if missing > 0 {
+        warn!(
+            "{} {missing} declared dependenc{} not installed; add {} to pez.toml and run `pez install`.",
+            utils::label_warning(),
+            if missing == 1 { "y is" } else { "ies are" },
+            if missing == 1 { "it" } else { "them" },
+        );
+    }
+    if conflicts > 0 {
+        warn!(
+            "{} Plugins ask for different refs of the same dependency; only one can be installed.",
+            utils::label_warning()
+        );
+    }
+    Ok(tree)
+}
+
+fn load_graph(lock_file: &LockFile, data_dir: &path::Path, config: Option<&Config>) -> Graph {
+    let mut graph = Graph {
+        installed: Vec::new(),
+        dependencies: BTreeMap::new(),
+    };
+    for plugin in &lock_file.plugins {
+        let repo = plugin.repo.as_str();
+        let disabled = config.is_some_and(|config| config.is_disabled(&plugin.repo));
+        let dependencies = match read_manifest(&plugin.checkout_dir(data_dir)) {
+            Ok(dependencies) => dependencies,
+            Err(e) => {
+                warn!(
+                    "{} Ignoring {MANIFEST_FILE_NAME} of {}: {e:#}",
+                    utils::label_warning(),
+                    plugin.display_label()
+                );
+                Vec::new()
+            }
+        };
+        graph.installed.push((repo.clone(), disabled));
+        graph.dependencies.insert(repo, dependencies);
+    }
+    graph
+}
+
+/// Dependencies declared in `root`'s manifest; none when the plugin ships no manifest.
+fn read_manifest(root: &path::Path) -> anyhow::Result<Vec<Dependency>> {
+    let path = root.join(MANIFEST_FILE_NAME);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let manifest: Manifest = toml::from_str(&fs::read_to_string(&path)?)?;
+    manifest
+        .dependencies
+        .into_iter()
+        .map(|declared| {
+            let resolved = InstallTarget::from_raw(declared.as_str())
+                .resolve()
+                .map_err(|e| anyhow::anyhow!("invalid dependency `{declared}`: {e:#}"))?;
+            Ok(Dependency {
+                repo: resolved.plugin_repo.as_str(),
+                ref_kind: resolved.ref_kind,
+                declared,
+            })
+        })
+        .collect()
+}
+
+/// Top-level plugins are the ones no other installed plugin depends on. Plugins only
+/// reachable through a cycle are listed at the top level as well, so every installed
+/// plugin shows up.
+fn build(graph: &Graph) -> Vec<Node> {
+    let required: BTreeSet<&str> = graph
+        .dependencies
+        .iter()
+        .flat_map(|(repo, dependencies)| {
+            dependencies
+                .iter()
+                .filter(move |dependency| &dependency.repo != repo)
+                .map(|dependency| dependency.repo.as_str())
+        })
+        .collect();
+
+    let mut shown = BTreeSet::new();
+    let mut roots: Vec<Node> = graph
+        .installed
+        .iter()
+        .filter(|(repo, _)| !required.contains(repo.as_str()))
+        .map(|(repo, _)| node(graph, repo, None, &mut Vec::new(), &mut shown))
+        .collect();
+    for (repo, _) in &graph.installed {
+        if !shown.contains(repo) {
+            roots.push(node(graph, repo, None, &mut Vec::new(), &mut shown));
+        }
+    }
+    roots
+}
+
+fn node(
+    graph: &Graph,
+    repo: &str,
+    dependency: Option<&Dependency>,
+    path: &mut Vec<String>,
+    shown: &mut BTreeSet<String>,
+) -> Node {
+    let installed = graph.installed.iter().find(|(r, _)| r == repo);
+    let cycle = path.iter().any(|r| r == repo);
+    shown.insert(repo.to_string());
+
+    let mut dependencies = Vec::new();
+    if !cycle {
+        path.push(repo.to_string());
+        for child in graph.dependencies.get(repo).into_iter().flatten() {
+            dependencies.push(node(graph, &child.repo, Some(child), path, shown));
+        }
+        path.pop();
+    }
+    Node {
+        repo: repo.to_string(),
+        declared: dependency.map(|d| d.declared.clone()),
+        missing: installed.is_none(),
+        disabled: installed.is_some_and(|(_, disabled)| *disabled),
+        cycle,
+        conflicts: dependency.map(|d| conflicts(graph, d)).unwrap_or_default(),
+        dependencies,
+    }
+}
+
+/// Entries elsewhere that pin the same repo to a different ref. An entry without a ref
+/// accepts whatever is installed, so it never conflicts.
+fn conflicts(graph: &Graph, dependency: &Dependency) -> Vec<String> {
+    if dependency.ref_kind == RefKind::None {
+        return Vec::new();
+    }
+    graph
+        .dependencies
+        .values()
+        .flatten()
+        .filter(|other| {
+            other.repo == dependency.repo
+                && other.ref_kind != RefKind::None
+                && other.ref_kind != dependency.ref_kind
+        })
+        .map(|other| other.declared.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn count(nodes: &[Node], predicate: &dyn Fn(&Node) -> bool) -> usize {
+    nodes
+        .iter()
+        .map(|node| usize::from(predicate(node)) + count(&node.dependencies, predicate))
+        .sum()
+}
+
+fn render(nodes: &[Node]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for node in nodes {
+        lines.push(label(node));
+        render_children(&node.dependencies, "", &mut lines);
+    }
+    lines
+}
+
+fn render_children(nodes: &[Node], prefix: &str, lines: &mut Vec<String>) {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        lines.push(format!("{prefix}{branch}{}", label(node)));
+        render_children(&node.dependencies, &format!("{prefix}{indent}"), lines);
+    }
+}
+
+fn label(node: &Node) -> String {
+    let mut label = node.declared.clone().unwrap_or_else(|| node.repo.clone());
+    if node.missing {
+        label.push_str(" (missing)");
+    }
+    if node.disabled {
+        label.push_str(" (disabled)");
+    }
+    if !node.conflicts.is_empty() {
+        label.push_str(&format!(" (conflicts with {})", node.conflicts.join(", ")));
+    }
+    if node.cycle {
+        label.push_str(" (cycle)");
+    }
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lock_file::Plugin, models::PluginRepo};
+
+    fn plugin(repo: &str) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "0123456789abcdef".into(),
+            ..Default::default()
+        }
+    }
+
+    fn write_manifest(data_dir: &path::Path, repo: &str, dependencies: &[&str]) {
+        let root = data_dir.join(repo);
+        fs::create_dir_all(&root).unwrap();
+        let entries: Vec<String> = dependencies.iter().map(|d| format!("{d:?}")).collect();
+        fs::write(
+            root.join(MANIFEST_FILE_NAME),
+            format!("dependencies = [{}]\n", entries.join(", ")),
+        )
+        .unwrap();
+    }
+
+    fn lock_file(repos: &[&str]) -> LockFile {
+        LockFile {
+            version: 2,
+            plugins: repos.iter().map(|repo| plugin(repo)).collect(),
+        }
+    }
+
+    #[test]
+    fn tree_nests_dependencies_under_the_plugins_that_declare_them() {
+        let temp = tempfile::tempdir().unwrap();
+        write_manifest(temp.path(), "owner/app", &["owner/lib", "owner/gone@v1"]);
+        write_manifest(temp.path(), "owner/lib", &["owner/base"]);
+        let lock_file = lock_file(&["owner/app", "owner/lib", "owner/base", "owner/solo"]);
+
+        let tree = build(&load_graph(&lock_file, temp.path(), None));
+
+        assert_eq!(
+            render(&tree),
+            vec![
+                "owner/app",
+                "├── owner/lib",
+                "│   └── owner/base",
+                "└── owner/gone@v1 (missing)",
+                "owner/solo",
+            ]
+        );
+        assert_eq!(count(&tree, &|node| node.missing), 1);
+    }
+
+    #[test]
+    fn tree_marks_conflicting_refs_and_cycles() {
+        let temp = tempfile::tempdir().unwrap();
+        write_manifest(temp.path(), "owner/one", &["owner/shared@v1"]);
+        write_manifest(temp.path(), "owner/two", &["owner/shared@v2", "owner/loop"]);
+        write_manifest(temp.path(), "owner/loop", &["owner/two"]);
+        write_manifest(temp.path(), "owner/a", &["owner/b"]);
+        write_manifest(temp.path(), "owner/b", &["owner/a"]);
+        let lock_file = lock_file(&[
+            "owner/one",
+            "owner/two",
+            "owner/loop",
+            "owner/shared",
+            "owner/a",
+            "owner/b",
+        ]);
+
+        let tree = build(&load_graph(&lock_file, temp.path(), None));
+
+        assert_eq!(
+            render(&tree),
+            vec![
+                "owner/one",
+                "└── owner/shared@v1 (conflicts with owner/shared@v2)",
+                "owner/two",
+                "├── owner/shared@v2 (conflicts with owner/shared@v1)",
+                "└── owner/loop",
+                "    └── owner/two (cycle)",
+                "owner/a",
+                "└── owner/b",
+                "    └── owner/a (cycle)",
+            ]
+        );
+    }
+
+    #[test]
+    fn tree_ignores_unreadable_manifests() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().join("owner/broken");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join(MANIFEST_FILE_NAME),
+            "dependencies = [\"not a repo\"]\n",
+        )
+        .unwrap();
+        let lock_file = lock_file(&["owner/broken"]);
+
+        let tree = build(&load_graph(&lock_file, temp.path(), None));
+
+        assert_eq!(render(&tree), vec!["owner/broken"]);
+    }
+}
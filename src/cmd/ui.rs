@@ -0,0 +1,460 @@
+use crate::{
+    cli::{PinArgs, UninstallArgs, UpgradeArgs},
+    cmd::{list, pin, uninstall, upgrade},
+    config::{Config, PluginSource},
+    git, journal,
+    lock_file::LockFile,
+    models::PluginRepo,
+    output::{self, Capture},
+    runtime::RuntimeOptions,
+    update_cache::{self, CachePolicy},
+    utils,
+};
+
+use console::Emoji;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block, Paragraph, Row, Table, TableState},
+};
+use std::{io::IsTerminal, time::Duration};
+use tracing::{error, info};
+
+/// Log lines kept for the log pane.
+const LOG_CAPACITY: usize = 500;
+const LOG_PANE_HEIGHT: u16 = 10;
+/// How long the idle loop waits for a key before redrawing, and how often the
+/// log pane refreshes while changes are applied.
+const TICK: Duration = Duration::from_millis(100);
+const HELP: &str = "↑/↓ move  u upgrade  x uninstall  p pin  space clear  a apply  q quit";
+
+/// What a plugin is marked to do when the changes are applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mark {
+    Upgrade,
+    Uninstall,
+    Pin,
+}
+
+impl Mark {
+    fn label(self) -> &'static str {
+        match self {
+            Mark::Upgrade => "upgrade",
+            Mark::Uninstall => "uninstall",
+            Mark::Pin => "pin",
+        }
+    }
+}
+
+/// One installed plugin in the list.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Entry {
+    pub(crate) repo: PluginRepo,
+    pub(crate) commit: String,
+    pub(crate) updated: String,
+    pub(crate) status: String,
+    pub(crate) mark: Option<Mark>,
+}
+
+/// Keys the UI reacts to, independent of the terminal backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Input {
+    Up,
+    Down,
+    Enter,
+    Esc,
+    Char(char),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Redraw,
+    Apply,
+    Quit,
+}
+
+/// Marked plugins, grouped by what happens to them.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Plan {
+    pub(crate) uninstall: Vec<PluginRepo>,
+    pub(crate) upgrade: Vec<PluginRepo>,
+    pub(crate) pin: Vec<PluginRepo>,
+}
+
+impl Plan {
+    fn is_empty(&self) -> bool {
+        self.uninstall.is_empty() && self.upgrade.is_empty() && self.pin.is_empty()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct State {
+    pub(crate) entries: Vec<Entry>,
+    pub(crate) selected: usize,
+    pub(crate) logs: Vec<String>,
+}
+
+impl State {
+    pub(crate) fn new(entries: Vec<Entry>) -> Self {
+        State {
+            entries,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn handle(&mut self, input: Input) -> Action {
+        match input {
+            Input::Up | Input::Char('k') => self.selected = self.selected.saturating_sub(1),
+            Input::Down | Input::Char('j') => {
+                self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+            }
+            Input::Char('u') => self.toggle(Mark::Upgrade),
+            Input::Char('x') | Input::Char('d') => self.toggle(Mark::Uninstall),
+            Input::Char('p') => self.toggle(Mark::Pin),
+            Input::Char(' ') => {
+                if let Some(entry) = self.entries.get_mut(self.selected) {
+                    entry.mark = None;
+                }
+            }
+            Input::Char('a') | Input::Enter => {
+                if !self.plan().is_empty() {
+                    return Action::Apply;
+                }
+                self.push_logs(["Nothing is marked; use u, x, or p first.".to_string()]);
+            }
+            Input::Char('q') | Input::Esc => return Action::Quit,
+            Input::Char(_) => {}
+        }
+        Action::Redraw
+    }
+
+    fn toggle(&mut self, mark: Mark) {
+        if let Some(entry) = self.entries.get_mut(self.selected) {
+            entry.mark = (entry.mark != Some(mark)).then_some(mark);
+        }
+    }
+
+    pub(crate) fn plan(&self) -> Plan {
+        let mut plan = Plan::default();
+        for entry in &self.entries {
+            let group = match entry.mark {
+                Some(Mark::Uninstall) => &mut plan.uninstall,
+                Some(Mark::Upgrade) => &mut plan.upgrade,
+                Some(Mark::Pin) => &mut plan.pin,
+                None => continue,
+            };
+            group.push(entry.repo.clone());
+        }
+        plan
+    }
+
+    /// Swap in freshly loaded entries, keeping the cursor in range.
+    pub(crate) fn reload(&mut self, entries: Vec<Entry>) {
+        self.entries = entries;
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    pub(crate) fn push_logs(&mut self, lines: impl IntoIterator<Item = String>) {
+        self.logs.extend(lines);
+        let excess = self.logs.len().saturating_sub(LOG_CAPACITY);
+        self.logs.drain(..excess);
+    }
+}
+
+/// Full-screen view of the installed plugins: mark plugins to upgrade, uninstall, or pin,
+/// then apply the changes while their log output streams into the log pane.
+pub(crate) async fn run(options: &RuntimeOptions) -> anyhow::Result<()> {
+    if !std::io::stdout().is_terminal() {
+        anyhow::bail!("pez ui needs an interactive terminal");
+    }
+    let mut state = State::new(load_entries()?);
+    let capture = output::capture();
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut state, &capture, options).await;
+    ratatui::restore();
+    drop(capture);
+    for line in &state.logs {
+        info!("{line}");
+    }
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    state: &mut State,
+    capture: &Capture,
+    options: &RuntimeOptions,
+) -> anyhow::Result<()> {
+    loop {
+        state.push_logs(capture.take_lines());
+        terminal.draw(|frame| draw(frame, state))?;
+        let Some(input) = tokio::task::spawn_blocking(read_input).await?? else {
+            continue;
+        };
+        match state.handle(input) {
+            Action::Redraw => {}
+            Action::Quit => return Ok(()),
+            Action::Apply => {
+                let plan = state.plan();
+                let mut applying = std::pin::pin!(apply(plan, options));
+                loop {
+                    tokio::select! {
+                        () = &mut applying => break,
+                        () = tokio::time::sleep(TICK) => {
+                            state.push_logs(capture.take_lines());
+                            terminal.draw(|frame| draw(frame, state))?;
+                        }
+                    }
+                }
+                state.reload(load_entries()?);
+            }
+        }
+    }
+}
+
+/// Wait up to one tick for a key press; runs on a blocking thread so the runtime stays free.
+fn read_input() -> anyhow::Result<Option<Input>> {
+    if !event::poll(TICK)? {
+        return Ok(None);
+    }
+    let Event::Key(key) = event::read()? else {
+        return Ok(None);
+    };
+    if key.kind != KeyEventKind::Press {
+        return Ok(None);
+    }
+    Ok(match key.code {
+        KeyCode::Up => Some(Input::Up),
+        KeyCode::Down => Some(Input::Down),
+        KeyCode::Enter => Some(Input::Enter),
+        KeyCode::Esc => Some(Input::Esc),
+        KeyCode::Char(c) => Some(Input::Char(c)),
+        _ => None,
+    })
+}
+
+/// Uninstall, then upgrade, then pin; a failing step is logged and the rest still run.
+async fn apply(plan: Plan, options: &RuntimeOptions) {
+    if !plan.uninstall.is_empty() {
+        let args = UninstallArgs {
+            plugins: Some(plan.uninstall),
+            force: false,
+            stdin: false,
+            all: false,
+            yes: false,
+            backup_dir: None,
+            no_backup: false,
+        };
+        report(journal::recorded("uninstall", uninstall::run(&args, options)).await);
+    }
+    if !plan.upgrade.is_empty() {
+        let args = UpgradeArgs {
+            plugins: Some(plan.upgrade),
+            force: false,
+            allow_dirty_repo: false,
+            to_lock: false,
+            interactive: false,
+            since: None,
+            dry_run: false,
+            check: false,
+            backup_dir: None,
+            no_backup: false,
+        };
+        report(journal::recorded("upgrade", upgrade::run(&args, options)).await);
+    }
+    for repo in plan.pin {
+        report(pin::pin(&PinArgs {
+            plugins: vec![repo],
+        }));
+    }
+    info!("{}Finished applying the marked changes.", Emoji("🎉 ", ""));
+}
+
+fn report(result: anyhow::Result<()>) {
+    if let Err(e) = result {
+        error!("{} {e:#}", utils::label_error());
+    }
+}
+
+fn load_entries() -> anyhow::Result<Vec<Entry>> {
+    let (lock_file, _) = utils::load_lock_file()?;
+    let config = utils::load_config().ok().map(|(config, _)| config);
+    Ok(entries(&lock_file, config.as_ref()))
+}
+
+/// One entry per locked plugin. Update status comes from the update-check cache only, so
+/// opening the UI never touches the network.
+fn entries(lock_file: &LockFile, config: Option<&Config>) -> Vec<Entry> {
+    let check = list::check_outdated(&lock_file.plugins, config, &CachePolicy::CachedOnly)
+        .unwrap_or_default();
+    let now = update_cache::now_secs();
+    lock_file
+        .plugins
+        .iter()
+        .map(|plugin| {
+            let outdated = check
+                .outdated
+                .iter()
+                .find(|outdated| outdated.plugin.repo == plugin.repo);
+            let mut status = if git::is_local_source(&plugin.source) {
+                "local".to_string()
+            } else if let Some(outdated) = outdated {
+                format!("outdated -> {}", short(&outdated.latest))
+            } else if check
+                .unchecked
+                .iter()
+                .any(|(unchecked, _)| unchecked.repo == plugin.repo)
+            {
+                "not checked".to_string()
+            } else {
+                "up to date".to_string()
+            };
+            let spec = config.and_then(|config| config.find_plugin_spec(&plugin.repo));
+            if spec.is_some_and(|spec| spec.disabled) {
+                status.push_str(", disabled");
+            }
+            if spec.is_some_and(|spec| {
+                matches!(
+                    spec.source,
+                    PluginSource::Repo {
+                        commit: Some(_),
+                        ..
+                    } | PluginSource::Url {
+                        commit: Some(_),
+                        ..
+                    }
+                )
+            }) {
+                status.push_str(", pinned");
+            }
+            Entry {
+                repo: plugin.repo.clone(),
+                commit: short(&plugin.commit_sha).to_string(),
+                updated: list::format_age(plugin.updated_at, now),
+                status,
+                mark: None,
+            }
+        })
+        .collect()
+}
+
+fn short(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+fn draw(frame: &mut Frame, state: &State) {
+    let [list_area, log_area, help_area] = Layout::vertical([
+        Constraint::Min(3),
+        Constraint::Length(LOG_PANE_HEIGHT),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let rows = state.entries.iter().map(|entry| {
+        Row::new([
+            entry.mark.map_or("", Mark::label).to_string(),
+            entry.repo.as_str(),
+            entry.commit.clone(),
+            entry.updated.clone(),
+            entry.status.clone(),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(9),
+            Constraint::Fill(2),
+            Constraint::Length(7),
+            Constraint::Length(10),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(Row::new(["mark", "repo", "commit", "updated", "status"]).bold())
+    .row_highlight_style(Style::new().reversed())
+    .block(Block::bordered().title(" pez "));
+    let mut table_state = TableState::default().with_selected(Some(state.selected));
+    frame.render_stateful_widget(table, list_area, &mut table_state);
+
+    let visible = usize::from(LOG_PANE_HEIGHT.saturating_sub(2));
+    let logs: Vec<Line> = state.logs[state.logs.len().saturating_sub(visible)..]
+        .iter()
+        .map(|line| Line::raw(line.as_str()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(logs).block(Block::bordered().title(" log ")),
+        log_area,
+    );
+    frame.render_widget(Paragraph::new(HELP).dim(), help_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(repo: &str) -> Entry {
+        Entry {
+            repo: repo.parse().unwrap(),
+            commit: "abc1234".into(),
+            updated: "-".into(),
+            status: "up to date".into(),
+            mark: None,
+        }
+    }
+
+    #[test]
+    fn keys_move_mark_and_plan_changes() {
+        let mut state = State::new(vec![entry("owner/a"), entry("owner/b"), entry("owner/c")]);
+
+        assert_eq!(state.handle(Input::Up), Action::Redraw);
+        assert_eq!(state.selected, 0);
+        assert_eq!(state.handle(Input::Enter), Action::Redraw);
+        assert_eq!(state.logs.len(), 1);
+
+        state.handle(Input::Char('u'));
+        state.handle(Input::Down);
+        state.handle(Input::Char('p'));
+        state.handle(Input::Char('x'));
+        state.handle(Input::Char('j'));
+        state.handle(Input::Char('j'));
+        assert_eq!(state.selected, 2);
+        state.handle(Input::Char('p'));
+        state.handle(Input::Char('p'));
+
+        let repos = |names: &[&str]| -> Vec<PluginRepo> {
+            names.iter().map(|name| name.parse().unwrap()).collect()
+        };
+        assert_eq!(
+            state.plan(),
+            Plan {
+                uninstall: repos(&["owner/b"]),
+                upgrade: repos(&["owner/a"]),
+                pin: vec![],
+            }
+        );
+        assert_eq!(state.handle(Input::Char('a')), Action::Apply);
+
+        state.selected = 1;
+        state.handle(Input::Char(' '));
+        assert_eq!(state.plan().uninstall, vec![]);
+        assert_eq!(state.handle(Input::Esc), Action::Quit);
+    }
+
+    #[test]
+    fn reload_keeps_cursor_in_range_and_logs_are_bounded() {
+        let mut state = State::new(vec![entry("owner/a"), entry("owner/b")]);
+        state.selected = 1;
+        state.reload(vec![entry("owner/a")]);
+        assert_eq!(state.selected, 0);
+        state.reload(vec![]);
+        assert_eq!(state.selected, 0);
+        assert_eq!(state.handle(Input::Down), Action::Redraw);
+        assert_eq!(state.handle(Input::Char('u')), Action::Redraw);
+
+        state.push_logs((0..LOG_CAPACITY + 5).map(|i| i.to_string()));
+        assert_eq!(state.logs.len(), LOG_CAPACITY);
+        assert_eq!(state.logs[0], "5");
+    }
+}
@@ -1,14 +1,29 @@
-use crate::{cli::UninstallArgs, models::PluginRepo, models::TargetDir, utils};
+use crate::{
+    backup, cli::UninstallArgs, history, models::PluginRepo, prompt, runtime::RuntimeOptions, utils,
+};
 
 use console::Emoji;
 use futures::{StreamExt, stream};
-use std::{collections::HashSet, fs, io};
+use std::{collections::HashSet, fs, io, sync::Mutex};
 use tracing::{error, info, warn};
 
-pub(crate) async fn run(args: &UninstallArgs) -> anyhow::Result<()> {
+pub(crate) async fn run(args: &UninstallArgs, options: &RuntimeOptions) -> anyhow::Result<()> {
     info!("{}Starting uninstallation process...", Emoji("🔍 ", ""));
-    let jobs = utils::load_jobs().max(1);
+    let _backup = (!args.no_backup)
+        .then(|| backup::start_run("uninstall", args.backup_dir.as_deref()))
+        .transpose()?;
+    let jobs = options.jobs();
     let mut plugins: Vec<PluginRepo> = args.plugins.clone().unwrap_or_default();
+    if args.all {
+        plugins = installed_plugins()?;
+        if plugins.is_empty() {
+            info!("{}No plugins are installed.", Emoji("🎉 ", ""));
+            return Ok(());
+        }
+        if !args.yes && !confirm_uninstall_all(&plugins)? {
+            anyhow::bail!("{}Uninstall aborted.", Emoji("🚧 ", ""));
+        }
+    }
     if plugins.is_empty() && args.stdin {
         let stdin_plugins = read_plugins_from_stdin()?;
         plugins.extend(stdin_plugins);
@@ -40,6 +55,24 @@ pub(crate) async fn run(args: &UninstallArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn installed_plugins() -> anyhow::Result<Vec<PluginRepo>> {
+    let (lock_file, _) = utils::load_or_create_lock_file()?;
+    Ok(lock_file.plugins.into_iter().map(|p| p.repo).collect())
+}
+
+fn confirm_uninstall_all(plugins: &[PluginRepo]) -> anyhow::Result<bool> {
+    warn!(
+        "{} {} This uninstalls all {} plugin(s) recorded in pez-lock.toml:",
+        Emoji("🚧 ", ""),
+        utils::label_warning(),
+        plugins.len()
+    );
+    for plugin in plugins {
+        warn!("   - {plugin}");
+    }
+    prompt::confirm("Are you sure you want to continue?", false)
+}
+
 fn normalize_plugins(plugins: &mut Vec<PluginRepo>) {
     let mut seen = HashSet::new();
     plugins.retain(|repo| seen.insert(repo.as_str()));
@@ -77,23 +110,20 @@ fn read_plugins_from_stdin() -> anyhow::Result<Vec<PluginRepo>> {
     read_plugins_from_reader(handle)
 }
 
+/// Held while `uninstall` rewrites pez.toml and pez-lock.toml, so parallel
+/// uninstalls each start from the other's saved state instead of overwriting it.
+static STATE_FILES: Mutex<()> = Mutex::new(());
+
 pub(crate) fn uninstall(plugin_repo: &PluginRepo, force: bool) -> anyhow::Result<()> {
     let plugin_repo_str = plugin_repo.as_str();
     let config_dir = utils::load_fish_config_dir()?;
 
-    let (mut config, config_path) = utils::load_or_create_config()?;
     let repo_path = utils::load_pez_data_dir()?.join(&plugin_repo_str);
-    let (mut lock_file, lock_file_path) = utils::load_or_create_lock_file()?;
+    let (lock_file, _) = utils::load_or_create_lock_file()?;
     match lock_file.get_plugin_by_repo(plugin_repo) {
         Some(locked_plugin) => {
             let locked = locked_plugin.clone();
-            locked
-                .files
-                .iter()
-                .filter(|f| f.dir == TargetDir::ConfD)
-                .for_each(|f| {
-                    let _ = utils::emit_event(&f.name, &utils::Event::Uninstall);
-                });
+            utils::emit_plugin_events(&locked, &utils::Event::Uninstall);
 
             if repo_path.exists() {
                 fs::remove_dir_all(&repo_path)?;
@@ -127,17 +157,29 @@ pub(crate) fn uninstall(plugin_repo: &PluginRepo, force: bool) -> anyhow::Result
             );
             locked.files.iter().for_each(|file| {
                 let dest_path = config_dir.join(file.dir.as_str()).join(&file.name);
-                if dest_path.exists() {
+                if lock_file.is_claimed_by_other(plugin_repo, file) {
+                    info!(
+                        "   - {} (kept; shared with another plugin)",
+                        dest_path.display()
+                    );
+                } else if dest_path.exists() {
                     let path_display = dest_path.display();
                     info!("   - {}", path_display);
-                    if let Err(e) = fs::remove_file(&dest_path) {
+                    if let Err(e) = backup::remove_file(&dest_path) {
                         warn!("Failed to remove {}: {:?}", path_display, e);
                     }
                 }
             });
+            backup::restore_all_displaced_files(&locked, &config_dir);
+            utils::remove_plugin_state_dir(plugin_repo);
+            history::record(history::Action::Uninstall, plugin_repo, None);
+
+            let _state = STATE_FILES.lock().unwrap_or_else(|e| e.into_inner());
+            let (mut lock_file, lock_file_path) = utils::load_or_create_lock_file()?;
             lock_file.remove_plugin(&locked.source);
             lock_file.save(&lock_file_path)?;
 
+            let (mut config, config_path) = utils::load_or_create_config()?;
             if let Some(ref mut plugin_specs) = config.plugins {
                 plugin_specs.retain(|p| p.get_plugin_repo().map_or(true, |r| r != *plugin_repo));
                 config.save(&config_path)?;
@@ -204,6 +246,7 @@ mod tests {
     use super::*;
     use crate::config;
     use crate::lock_file::{self, LockFile, PluginFile};
+    use crate::models::TargetDir;
     use crate::tests_support::env::TestEnvironmentSetup;
     use crate::tests_support::log::capture_logs;
     use std::ffi::OsString;
@@ -303,6 +346,8 @@ owner/plugin-a
         };
         let spec = config::PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: config::PluginSource::Repo {
                 repo: repo.clone(),
                 version: None,
@@ -310,9 +355,15 @@ owner/plugin-a
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
         // Create repo dir and a file record in lockfile that points to a functions file
@@ -331,6 +382,7 @@ owner/plugin-a
                 dir: TargetDir::Functions,
                 name: "hello.fish".into(),
             }],
+            ..Default::default()
         };
         env.setup_lock_file(LockFile {
             version: 1,
@@ -377,6 +429,56 @@ owner/plugin-a
         }
     }
 
+    #[test]
+    fn uninstall_restores_displaced_user_files() {
+        let mut env = TestEnvironmentSetup::new();
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let _env = EnvOverride::new(&["__fish_config_dir", "PEZ_CONFIG_DIR", "PEZ_DATA_DIR"]);
+        unsafe {
+            std::env::set_var("__fish_config_dir", &env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+        }
+
+        let repo: PluginRepo = "owner/pkg".parse().unwrap();
+        env.setup_data_repo(vec![repo.clone()]);
+        let functions_dir = env.fish_config_dir.join(TargetDir::Functions.as_str());
+        std::fs::create_dir_all(&functions_dir).unwrap();
+        let dest_file = functions_dir.join("fish_prompt.fish");
+        std::fs::write(&dest_file, "plugin prompt").unwrap();
+        let backup_file = env
+            .data_dir
+            .join(crate::backup::BACKUP_DIR_NAME)
+            .join("fish_prompt.fish");
+        std::fs::create_dir_all(backup_file.parent().unwrap()).unwrap();
+        std::fs::write(&backup_file, "user prompt").unwrap();
+
+        env.setup_lock_file(LockFile {
+            version: 1,
+            plugins: vec![crate::lock_file::Plugin {
+                name: "pkg".into(),
+                repo: repo.clone(),
+                source: repo.default_remote_source(),
+                commit_sha: "abc1234".into(),
+                files: vec![PluginFile {
+                    dir: TargetDir::Functions,
+                    name: "fish_prompt.fish".into(),
+                }],
+                backups: vec![lock_file::PluginBackup {
+                    dir: TargetDir::Functions,
+                    name: "fish_prompt.fish".into(),
+                    backup: backup_file.to_string_lossy().to_string(),
+                }],
+                ..Default::default()
+            }],
+        });
+
+        uninstall(&repo, false).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&dest_file).unwrap(), "user prompt");
+        assert!(!backup_file.exists());
+    }
+
     #[test]
     fn test_uninstall_honors_target_dir_override() {
         let mut env = TestEnvironmentSetup::new();
@@ -405,6 +507,8 @@ owner/plugin-a
 
         let spec = config::PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: config::PluginSource::Repo {
                 repo: repo.clone(),
                 version: None,
@@ -412,9 +516,15 @@ owner/plugin-a
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         env.setup_data_repo(vec![repo.clone()]);
 
@@ -434,6 +544,7 @@ owner/plugin-a
                     dir: TargetDir::Functions,
                     name: "alt.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
 
@@ -490,6 +601,8 @@ owner/plugin-a
         env.setup_config(config::Config {
             plugins: Some(vec![config::PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: None,
@@ -497,7 +610,13 @@ owner/plugin-a
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         env.setup_lock_file(LockFile {
             version: 1,
@@ -510,6 +629,7 @@ owner/plugin-a
                     dir: TargetDir::Functions,
                     name: "hello.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
 
@@ -549,6 +669,23 @@ owner/plugin-a
 
     #[test]
     fn uninstall_emits_events_only_for_conf_d_files() {
+        let log_contents = uninstall_with_fake_fish(vec![]);
+        assert!(log_contents.contains("emit alpha_uninstall"));
+        assert!(!log_contents.contains("emit beta_uninstall"));
+    }
+
+    #[test]
+    fn uninstall_emits_configured_event_overrides() {
+        let log_contents =
+            uninstall_with_fake_fish(vec!["omf_install".into(), "omf_uninstall".into()]);
+        assert!(log_contents.contains("emit omf_uninstall"));
+        assert!(!log_contents.contains("emit omf_install"));
+        assert!(!log_contents.contains("emit alpha_uninstall"));
+    }
+
+    /// Uninstall a plugin with conf.d/alpha.fish and functions/beta.fish through a
+    /// fake `fish` and return the commands it received.
+    fn uninstall_with_fake_fish(events: Vec<String>) -> String {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
         let mut env = TestEnvironmentSetup::new();
         let _override = EnvOverride::new(&[
@@ -586,6 +723,8 @@ owner/plugin-a
         };
         let spec = config::PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: config::PluginSource::Repo {
                 repo: repo.clone(),
                 version: None,
@@ -593,9 +732,15 @@ owner/plugin-a
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events,
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         env.setup_data_repo(vec![repo.clone()]);
 
@@ -623,28 +768,32 @@ owner/plugin-a
                         name: "beta.fish".into(),
                     },
                 ],
+                ..Default::default()
             }],
         });
 
         uninstall(&repo, true).expect("uninstall should succeed");
 
-        let log_contents = std::fs::read_to_string(&log_path).unwrap_or_default();
-        assert!(log_contents.contains("emit alpha_uninstall"));
-        assert!(!log_contents.contains("emit beta_uninstall"));
+        std::fs::read_to_string(&log_path).unwrap_or_default()
     }
 
     #[allow(clippy::await_holding_lock)]
     #[tokio::test(flavor = "multi_thread")]
     async fn run_bails_without_plugins_or_stdin() {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
-        crate::utils::clear_cli_jobs_override_for_tests();
         let _guard = StdinGuard::new(Some("owner/from-stdin\n".to_string()));
         let args = UninstallArgs {
             plugins: None,
             force: false,
             stdin: false,
+            backup_dir: None,
+            no_backup: true,
+            all: false,
+            yes: false,
         };
-        let err = run(&args).await.expect_err("expected failure");
+        let err = run(&args, &RuntimeOptions::default())
+            .await
+            .expect_err("expected failure");
         assert!(
             err.to_string()
                 .contains("No plugins specified for uninstall")
@@ -655,7 +804,6 @@ owner/plugin-a
     #[tokio::test(flavor = "multi_thread")]
     async fn run_reads_plugins_from_stdin() {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
-        crate::utils::clear_cli_jobs_override_for_tests();
         let mut env = TestEnvironmentSetup::new();
         let _override = EnvOverride::new(&[
             "__fish_config_dir",
@@ -677,6 +825,8 @@ owner/plugin-a
         };
         let spec = config::PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: config::PluginSource::Repo {
                 repo: repo.clone(),
                 version: None,
@@ -684,9 +834,15 @@ owner/plugin-a
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         env.setup_data_repo(vec![repo.clone()]);
 
@@ -706,6 +862,7 @@ owner/plugin-a
                     dir: TargetDir::Functions,
                     name: "stdin.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
 
@@ -714,8 +871,14 @@ owner/plugin-a
             plugins: None,
             force: true,
             stdin: true,
+            backup_dir: None,
+            no_backup: true,
+            all: false,
+            yes: false,
         };
-        run(&args).await.expect("run should succeed");
+        run(&args, &RuntimeOptions::default())
+            .await
+            .expect("run should succeed");
 
         assert!(std::fs::metadata(&target_file).is_err());
         let lock = lock_file::load(&env.lock_file_path).unwrap();
@@ -726,7 +889,6 @@ owner/plugin-a
     #[tokio::test(flavor = "multi_thread")]
     async fn run_uninstalls_plugins_from_args() {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
-        crate::utils::clear_cli_jobs_override_for_tests();
         let mut env = TestEnvironmentSetup::new();
         let _override = EnvOverride::new(&[
             "__fish_config_dir",
@@ -748,6 +910,8 @@ owner/plugin-a
         };
         let spec = config::PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: config::PluginSource::Repo {
                 repo: repo.clone(),
                 version: None,
@@ -755,9 +919,15 @@ owner/plugin-a
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         env.setup_config(config::Config {
             plugins: Some(vec![spec]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
         env.setup_data_repo(vec![repo.clone()]);
 
@@ -777,18 +947,128 @@ owner/plugin-a
                     dir: TargetDir::Functions,
                     name: "args.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
 
+        let backup_dir = env._temp_dir.path().join("removed");
         let args = UninstallArgs {
             plugins: Some(vec![repo.clone()]),
             force: true,
             stdin: false,
+            backup_dir: Some(backup_dir.clone()),
+            no_backup: false,
+            all: false,
+            yes: false,
         };
-        run(&args).await.expect("run should succeed");
+        run(&args, &RuntimeOptions::default())
+            .await
+            .expect("run should succeed");
 
         assert!(std::fs::metadata(&target_file).is_err());
+        assert!(backup_dir.join("functions").join("args.fish").exists());
         let lock = lock_file::load(&env.lock_file_path).unwrap();
         assert!(lock.plugins.is_empty());
     }
+    #[allow(clippy::await_holding_lock)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn run_all_uninstalls_every_locked_plugin() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut env = TestEnvironmentSetup::new();
+        let _override = EnvOverride::new(&[
+            "__fish_config_dir",
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "PEZ_JOBS",
+        ]);
+        unsafe {
+            std::env::set_var("__fish_config_dir", &env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+            std::env::set_var("PEZ_JOBS", "4");
+        }
+
+        let repos: Vec<PluginRepo> = ["a", "b", "c"]
+            .iter()
+            .map(|name| PluginRepo {
+                host: None,
+                owner: "owner".into(),
+                repo: (*name).into(),
+            })
+            .collect();
+        env.setup_config(config::Config {
+            plugins: Some(
+                repos
+                    .iter()
+                    .map(|repo| config::PluginSpec {
+                        name: None,
+                        prerelease: false,
+                        disabled: false,
+                        source: config::PluginSource::Repo {
+                            repo: repo.clone(),
+                            version: None,
+                            branch: None,
+                            tag: None,
+                            commit: None,
+                        },
+                        dir: None,
+                        events: vec![],
+                        disable_files: vec![],
+                    })
+                    .collect(),
+            ),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        });
+        env.setup_data_repo(repos.clone());
+        let functions = env.fish_config_dir.join(TargetDir::Functions.as_str());
+        std::fs::create_dir_all(&functions).unwrap();
+        env.setup_lock_file(LockFile {
+            version: 1,
+            plugins: repos
+                .iter()
+                .map(|repo| {
+                    std::fs::write(functions.join(format!("{}.fish", repo.repo)), "").unwrap();
+                    crate::lock_file::Plugin {
+                        name: repo.repo.clone(),
+                        repo: repo.clone(),
+                        source: repo.default_remote_source(),
+                        commit_sha: "abc1234".into(),
+                        files: vec![PluginFile {
+                            dir: TargetDir::Functions,
+                            name: format!("{}.fish", repo.repo),
+                        }],
+                        ..Default::default()
+                    }
+                })
+                .collect(),
+        });
+
+        let args = UninstallArgs {
+            plugins: None,
+            force: false,
+            stdin: false,
+            backup_dir: None,
+            no_backup: true,
+            all: true,
+            yes: true,
+        };
+        run(&args, &RuntimeOptions::default())
+            .await
+            .expect("run should succeed");
+
+        for repo in &repos {
+            assert!(!env.data_dir.join(repo.as_str()).exists());
+            assert!(!functions.join(format!("{}.fish", repo.repo)).exists());
+        }
+        assert!(
+            lock_file::load(&env.lock_file_path)
+                .unwrap()
+                .plugins
+                .is_empty()
+        );
+        let config = config::load(&env.config_path).unwrap();
+        assert_eq!(config.plugins.map(|p| p.len()), Some(0));
+    }
 }
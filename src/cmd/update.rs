@@ -0,0 +1,100 @@
+use crate::{
+    backup,
+    cli::UpdateArgs,
+    cmd::{
+        list,
+        upgrade::{self, UpgradeOptions},
+    },
+    lock_file::Plugin,
+    models::PluginRepo,
+    runtime::RuntimeOptions,
+    update_cache::CachePolicy,
+    utils,
+};
+
+use console::Emoji;
+use std::{fs, path};
+use tracing::{info, warn};
+
+/// Plugins the last `pez update` upgraded, under the pez data dir. The activate wrapper
+/// reads it through `pez files --from update` to emit `update` events for those only.
+pub(crate) const LAST_UPDATE_FILE_NAME: &str = ".last-update.json";
+
+/// Refresh the update checks for installed plugins, then upgrade only the ones whose
+/// pez.toml selector resolves to a newer commit than the locked one. Unlike
+/// `pez upgrade`, plugins missing from pez.toml are not added to it. Returns the
+/// plugins that were upgraded.
+pub(crate) async fn run(
+    args: &UpdateArgs,
+    runtime: &RuntimeOptions,
+) -> anyhow::Result<Vec<PluginRepo>> {
+    record_updated(&[]);
+    let (lock_file, _) = utils::load_lock_file()?;
+    let config = utils::load_config().ok().map(|(c, _)| c);
+    if let Some(missing) = args
+        .plugins
+        .iter()
+        .flatten()
+        .find(|repo| !lock_file.contains_repo(repo))
+    {
+        anyhow::bail!("Plugin is not installed: {}", missing);
+    }
+    let candidates: Vec<Plugin> = lock_file
+        .without_disabled(config.as_ref())
+        .plugins
+        .into_iter()
+        .filter(|p| {
+            args.plugins
+                .as_ref()
+                .is_none_or(|selected| selected.contains(&p.repo))
+        })
+        .collect();
+
+    info!("{}Checking for updates...", Emoji("🔍 ", ""));
+    let found = list::get_outdated_plugins(&candidates, config.as_ref(), &CachePolicy::Refresh)?;
+    if found.is_empty() {
+        info!("{}All plugins are up to date!", Emoji("🎉 ", ""));
+        return Ok(vec![]);
+    }
+    let repos: Vec<PluginRepo> = found.into_iter().map(|o| o.plugin.repo).collect();
+
+    let _backup = (!args.no_backup)
+        .then(|| backup::start_run("update", args.backup_dir.as_deref()))
+        .transpose()?;
+    let options = UpgradeOptions {
+        allow_dirty_repo: args.allow_dirty_repo,
+        jobs: runtime.jobs(),
+        ..Default::default()
+    };
+    upgrade::upgrade_repos(repos.clone(), options).await?;
+    record_updated(&repos);
+    info!("{}Updated {} plugin(s).", Emoji("🎉 ", ""), repos.len());
+    Ok(repos)
+}
+
+fn last_update_path() -> anyhow::Result<path::PathBuf> {
+    Ok(utils::load_pez_data_dir()?.join(LAST_UPDATE_FILE_NAME))
+}
+
+/// Failures are logged and never abort the update.
+fn record_updated(repos: &[PluginRepo]) {
+    let result = last_update_path().and_then(|path| {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(repos)?)?;
+        Ok(())
+    });
+    if let Err(e) = result {
+        warn!("Failed to record updated plugins: {:?}", e);
+    }
+}
+
+/// The plugins the last `pez update` upgraded; none before the first update.
+pub(crate) fn last_updated() -> anyhow::Result<Vec<PluginRepo>> {
+    match fs::read_to_string(last_update_path()?) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e.into()),
+    }
+}
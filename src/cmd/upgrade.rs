@@ -1,43 +1,50 @@
 use crate::{
-    cli::UpgradeArgs,
-    git,
+    backup,
+    cli::{PinArgs, UpgradeArgs},
+    cmd::{list, outdated, pin},
+    git, history,
     lock_file::Plugin,
-    models::{PluginRepo, TargetDir},
-    utils,
+    models::PluginRepo,
+    output::{out, outln},
+    prompt,
+    runtime::RuntimeOptions,
+    update_cache::{self, CachePolicy},
+    utils, vendor,
 };
 
 use console::Emoji;
 use futures::{StreamExt, stream};
-use std::fs;
 use tracing::{error, info, warn};
 
-pub(crate) async fn run(args: &UpgradeArgs) -> anyhow::Result<()> {
+pub(crate) async fn run(args: &UpgradeArgs, runtime: &RuntimeOptions) -> anyhow::Result<()> {
+    if args.dry_run || args.check {
+        let repos = match &args.plugins {
+            Some(plugins) => plugins.clone(),
+            None => utils::load_config()?
+                .0
+                .plugins
+                .iter()
+                .flatten()
+                .filter_map(|p| p.get_plugin_repo().ok())
+                .collect(),
+        };
+        dry_run(repos, UpgradeOptions::new(args, runtime), args.check).await?;
+        return Ok(());
+    }
+    let _backup = (!args.no_backup)
+        .then(|| backup::start_run("upgrade", args.backup_dir.as_deref()))
+        .transpose()?;
+    if args.to_lock {
+        return sync_to_lock(args, runtime).await;
+    }
+    if args.interactive {
+        return upgrade_interactive(args, runtime).await;
+    }
     info!("{}Starting upgrade process...", Emoji("🔍 ", ""));
     if let Some(plugins) = &args.plugins {
-        let jobs = utils::load_jobs().max(1);
-        let tasks = stream::iter(plugins.iter())
-            .map(|plugin| {
-                let plugin = plugin.clone();
-                tokio::task::spawn_blocking(move || {
-                    info!("{}Upgrading plugin: {}", Emoji("✨ ", ""), &plugin);
-                    let res = upgrade(&plugin);
-                    if res.is_ok() {
-                        info!(
-                            "{}Successfully upgraded plugin: {}",
-                            Emoji("✅ ", ""),
-                            &plugin
-                        );
-                    }
-                    res
-                })
-            })
-            .buffer_unordered(jobs);
-        let results: Vec<_> = tasks.collect().await;
-        for r in results {
-            r??;
-        }
+        upgrade_selected(plugins, UpgradeOptions::new(args, runtime)).await?;
     } else {
-        upgrade_all().await?;
+        upgrade_all(UpgradeOptions::new(args, runtime)).await?;
     }
     info!(
         "{}All specified plugins have been upgraded successfully!",
@@ -47,48 +54,197 @@ pub(crate) async fn run(args: &UpgradeArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn upgrade(plugin: &PluginRepo) -> anyhow::Result<()> {
+async fn upgrade_selected(plugins: &[PluginRepo], options: UpgradeOptions) -> anyhow::Result<()> {
+    let jobs = options.jobs.max(1);
+    let tasks = stream::iter(plugins.iter())
+        .map(|plugin| {
+            let plugin = plugin.clone();
+            tokio::task::spawn_blocking(move || {
+                info!("{}Upgrading plugin: {}", Emoji("✨ ", ""), &plugin);
+                let res = upgrade(&plugin, options);
+                if res.is_ok() {
+                    info!(
+                        "{}Successfully upgraded plugin: {}",
+                        Emoji("✅ ", ""),
+                        &plugin
+                    );
+                }
+                res
+            })
+        })
+        .buffer_unordered(jobs);
+    let results: Vec<_> = tasks.collect().await;
+    for r in results {
+        r??;
+    }
+    Ok(())
+}
+
+/// What to do with one outdated plugin in `upgrade --interactive`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Decision {
+    Accept,
+    Skip,
+    Pin,
+}
+
+const DECISIONS: [(&str, Decision); 3] = [
+    ("accept", Decision::Accept),
+    ("skip", Decision::Skip),
+    ("pin", Decision::Pin),
+];
+
+/// Show each outdated plugin with its new commits and collect every decision first;
+/// nothing changes until all plugins are answered. Pinned plugins keep their locked
+/// commit in pez.toml, like `pez pin`.
+async fn upgrade_interactive(args: &UpgradeArgs, runtime: &RuntimeOptions) -> anyhow::Result<()> {
+    let (lock_file, _) = utils::load_lock_file()?;
+    let config = utils::load_config().ok().map(|(c, _)| c);
+    let candidates: Vec<Plugin> = lock_file
+        .without_disabled(config.as_ref())
+        .plugins
+        .into_iter()
+        .filter(|p| {
+            args.plugins
+                .as_ref()
+                .is_none_or(|selected| selected.contains(&p.repo))
+        })
+        .filter(|p| {
+            args.since
+                .is_none_or(|since| list::is_stale(p, since, update_cache::now_secs()))
+        })
+        .collect();
+    info!("{}Checking for updates...", Emoji("🔍 ", ""));
+    let found = list::get_outdated_plugins(&candidates, config.as_ref(), &CachePolicy::Refresh)?;
+    if found.is_empty() {
+        info!("{}All plugins are up to date!", Emoji("🎉 ", ""));
+        return Ok(());
+    }
+    let repos: Vec<PluginRepo> = found.iter().map(|o| o.plugin.repo.clone()).collect();
+    let entries = outdated::entries(found, &utils::load_pez_data_dir()?);
+
+    let choices = DECISIONS.map(|(label, _)| label);
+    let mut accepted = Vec::new();
+    let mut pinned = Vec::new();
+    for (repo, entry) in repos.into_iter().zip(&entries) {
+        out!("{}", outdated::render_entry(entry));
+        let choice = prompt::choose(&format!("Upgrade {}?", entry.repo), &choices, 0)?;
+        match DECISIONS[choice].1 {
+            Decision::Accept => accepted.push(repo),
+            Decision::Skip => {}
+            Decision::Pin => pinned.push(repo),
+        }
+    }
+
+    if !pinned.is_empty() {
+        pin::pin(&PinArgs { plugins: pinned })?;
+    }
+    if accepted.is_empty() {
+        info!("{}No plugins selected for upgrade.", Emoji("🚀 ", ""));
+        return Ok(());
+    }
+    upgrade_selected(&accepted, UpgradeOptions::new(args, runtime)).await?;
+    info!("{}Upgraded {} plugin(s).", Emoji("🎉 ", ""), accepted.len());
+    Ok(())
+}
+
+/// Per-run switches shared by every plugin in an upgrade.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct UpgradeOptions {
+    pub(crate) force: bool,
+    pub(crate) allow_dirty_repo: bool,
+    /// Skip plugins whose lock entry was updated more recently than this.
+    pub(crate) since: Option<std::time::Duration>,
+    /// How many plugins are upgraded at once.
+    pub(crate) jobs: usize,
+}
+
+impl UpgradeOptions {
+    fn new(args: &UpgradeArgs, runtime: &RuntimeOptions) -> Self {
+        Self {
+            force: args.force,
+            allow_dirty_repo: args.allow_dirty_repo,
+            since: args.since,
+            jobs: runtime.jobs(),
+        }
+    }
+}
+
+fn upgrade(plugin: &PluginRepo, options: UpgradeOptions) -> anyhow::Result<()> {
     let (mut config, config_path) = utils::load_or_create_config()?;
 
     if config.ensure_plugin_for_repo(plugin) {
         config.save(&config_path)?;
     }
 
-    upgrade_plugin(plugin)?;
+    upgrade_plugin(plugin, options)?;
 
     Ok(())
 }
 
-async fn upgrade_all() -> anyhow::Result<()> {
+async fn upgrade_all(options: UpgradeOptions) -> anyhow::Result<()> {
     let (config, _) = utils::load_or_create_config()?;
     if let Some(plugins) = &config.plugins {
         let repos: Vec<PluginRepo> = plugins
             .iter()
             .filter_map(|p| p.get_plugin_repo().ok())
             .collect();
-        let jobs = utils::load_jobs().max(1);
-        let tasks = stream::iter(repos)
-            .map(|repo| {
-                tokio::task::spawn_blocking(move || {
-                    info!("{}Upgrading plugin: {}", Emoji("✨ ", ""), &repo);
-                    upgrade_plugin(&repo)
-                })
-            })
-            .buffer_unordered(jobs);
-        let results: Vec<_> = tasks.collect().await;
-        for r in results {
-            r??;
-        }
+        upgrade_repos(repos, options).await?;
     }
 
     Ok(())
 }
 
-fn upgrade_plugin(plugin_repo: &PluginRepo) -> anyhow::Result<()> {
+/// Upgrade installed plugins in parallel, without adding them to pez.toml.
+pub(crate) async fn upgrade_repos(
+    repos: Vec<PluginRepo>,
+    options: UpgradeOptions,
+) -> anyhow::Result<()> {
+    let jobs = options.jobs.max(1);
+    let tasks = stream::iter(repos)
+        .map(|repo| {
+            tokio::task::spawn_blocking(move || {
+                info!("{}Upgrading plugin: {}", Emoji("✨ ", ""), &repo);
+                upgrade_plugin(&repo, options)
+            })
+        })
+        .buffer_unordered(jobs);
+    let results: Vec<_> = tasks.collect().await;
+    for r in results {
+        r??;
+    }
+    Ok(())
+}
+
+fn upgrade_plugin(plugin_repo: &PluginRepo, options: UpgradeOptions) -> anyhow::Result<()> {
+    let started = std::time::Instant::now();
     let (mut lock_file, lock_file_path) = utils::load_or_create_lock_file()?;
     let (config, _) = utils::load_or_create_config()?;
     let config_dir = utils::load_fish_config_dir()?;
 
+    if config.is_disabled(plugin_repo) {
+        info!(
+            "{} {} Plugin {} is disabled; skipping upgrade.",
+            Emoji("🚧 ", ""),
+            crate::utils::label_info(),
+            plugin_repo
+        );
+        return Ok(());
+    }
+    if let (Some(since), Some(locked)) = (options.since, lock_file.get_plugin_by_repo(plugin_repo))
+    {
+        let now = update_cache::now_secs();
+        if !list::is_stale(locked, since, now) {
+            info!(
+                "{} {} Plugin {} was updated {}; skipping upgrade.",
+                Emoji("🚧 ", ""),
+                crate::utils::label_info(),
+                plugin_repo,
+                list::format_age(locked.updated_at, now)
+            );
+            return Ok(());
+        }
+    }
     match lock_file.get_plugin_by_repo(plugin_repo) {
         Some(lock_file_plugin) => {
             let repo_path = utils::load_pez_data_dir()?.join(lock_file_plugin.repo.as_str());
@@ -103,34 +259,19 @@ fn upgrade_plugin(plugin_repo: &PluginRepo) -> anyhow::Result<()> {
             }
             if repo_path.exists() {
                 let repo = git2::Repository::open(&repo_path)?;
-                // Determine desired selection from config (if present); fall back to default head
-                let sel = config
-                    .plugins
-                    .as_ref()
-                    .and_then(|ps| {
-                        ps.iter()
-                            .find(|p| p.get_plugin_repo().ok().as_ref() == Some(plugin_repo))
-                            .and_then(|p| p.to_resolved().ok())
-                    })
-                    .map(|r| crate::resolver::selection_from_ref_kind(&r.ref_kind))
-                    .unwrap_or(crate::resolver::Selection::DefaultHead);
-
-                let latest_remote_commit = match git::resolve_selection(&repo, &sel) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        warn!(
-                            "Failed to resolve selection for {}: {:?}. Falling back to remote HEAD.",
-                            plugin_repo, e
-                        );
-                        git::get_latest_remote_commit(&repo)?
-                    }
-                };
-                if latest_remote_commit == lock_file_plugin.commit_sha {
+                ensure_clean_worktree(
+                    &repo,
+                    lock_file_plugin,
+                    &repo_path,
+                    options.allow_dirty_repo,
+                )?;
+                let latest_remote_commit = resolve_latest(&repo, &config, plugin_repo)?;
+                if latest_remote_commit == lock_file_plugin.commit_sha && !options.force {
                     info!(
                         "{} {} Plugin {} is already up to date.",
                         Emoji("🚀 ", ""),
                         crate::utils::label_info(),
-                        plugin_repo
+                        lock_file_plugin.display_label()
                     );
                     return Ok(());
                 }
@@ -140,32 +281,39 @@ fn upgrade_plugin(plugin_repo: &PluginRepo) -> anyhow::Result<()> {
                 lock_file_plugin.files.iter().for_each(|file| {
                     let dest_path = config_dir.join(file.dir.as_str()).join(&file.name);
                     if dest_path.exists()
-                        && let Err(e) = fs::remove_file(&dest_path)
+                        && let Err(e) = backup::remove_file(&dest_path)
                     {
                         warn!("Failed to remove {}: {:?}", dest_path.display(), e);
                     }
                 });
                 let mut updated_plugin = Plugin {
-                    name: lock_file_plugin.name.to_string(),
+                    name: config
+                        .find_plugin_spec(plugin_repo)
+                        .and_then(|spec| spec.name.clone())
+                        .unwrap_or_else(|| lock_file_plugin.name.to_string()),
                     repo: plugin_repo.clone(),
                     source: lock_file_plugin.source.clone(),
                     commit_sha: latest_remote_commit,
                     files: vec![],
+                    backups: lock_file_plugin.backups.clone(),
+                    updated_at: Some(update_cache::now_secs()),
+                    default_branch: git::default_branch(&repo)
+                        .or_else(|| lock_file_plugin.default_branch.clone()),
+                    disabled_files: vec![],
                 };
                 info!("{:?}", updated_plugin);
 
                 utils::copy_plugin_files_from_repo(&repo_path, &mut updated_plugin)?;
+                backup::restore_displaced_files(&mut updated_plugin, &config_dir);
 
-                updated_plugin
-                    .files
-                    .iter()
-                    .filter(|f| f.dir == TargetDir::ConfD)
-                    .for_each(|f| {
-                        if let Err(e) = utils::emit_event(&f.name, &utils::Event::Update) {
-                            error!("Failed to emit event for {}: {:?}", &f.name, e);
-                        }
-                    });
+                utils::emit_plugin_events(&updated_plugin, &utils::Event::Update);
 
+                vendor::warn_shadowed(std::slice::from_ref(&updated_plugin));
+                history::record(
+                    history::Action::Upgrade,
+                    plugin_repo,
+                    Some(started.elapsed()),
+                );
                 if let Err(e) = lock_file.upsert_plugin_by_repo(updated_plugin) {
                     warn!("Failed to update lock file: {:?}", e);
                 }
@@ -189,11 +337,388 @@ fn upgrade_plugin(plugin_repo: &PluginRepo) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The commit the plugin's pez.toml selector resolves to in `repo` (fetching it), or the
+/// remote HEAD when the plugin has no selector or it does not resolve.
+fn resolve_latest(
+    repo: &git2::Repository,
+    config: &crate::config::Config,
+    plugin_repo: &PluginRepo,
+) -> anyhow::Result<String> {
+    let resolved = config
+        .find_plugin_spec(plugin_repo)
+        .and_then(|p| p.to_resolved().ok());
+    let sel = resolved
+        .as_ref()
+        .map(|r| crate::resolver::selection_from_ref_kind(&r.ref_kind))
+        .unwrap_or(crate::resolver::Selection::DefaultHead);
+    let channel = resolved.map(|r| r.channel).unwrap_or_default();
+
+    match git::resolve_selection(repo, &sel, channel) {
+        Ok(c) => Ok(c),
+        Err(e) => {
+            warn!(
+                "Failed to resolve selection for {}: {:?}. Falling back to remote HEAD.",
+                plugin_repo, e
+            );
+            git::get_latest_remote_commit(repo)
+        }
+    }
+}
+
+/// A plugin `pez upgrade --dry-run` would move, with the files in the fish config dir
+/// that would change.
+#[derive(Debug, PartialEq)]
+pub(crate) struct PlannedUpgrade {
+    pub(crate) repo: PluginRepo,
+    pub(crate) from: String,
+    pub(crate) to: String,
+    pub(crate) files: Vec<FileChange>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum FileChange {
+    Added(std::path::PathBuf),
+    Modified(std::path::PathBuf),
+    Removed(std::path::PathBuf),
+}
+
+/// The exit status of `pez upgrade --check` when any plugin has an update.
+const EXIT_UPDATES_AVAILABLE: u8 = 1;
+
+/// Print what upgrading `repos` would do without touching the data dir, the lock file,
+/// or the fish config dir. With `check`, fail with [`EXIT_UPDATES_AVAILABLE`] when any
+/// plugin would move.
+async fn dry_run(
+    repos: Vec<PluginRepo>,
+    options: UpgradeOptions,
+    check: bool,
+) -> anyhow::Result<Vec<PlannedUpgrade>> {
+    info!("{}Checking for updates...", Emoji("🔍 ", ""));
+    let jobs = options.jobs.max(1);
+    let tasks = stream::iter(repos)
+        .map(|repo| tokio::task::spawn_blocking(move || plan_upgrade(&repo, options)))
+        .buffered(jobs);
+    let mut planned = Vec::new();
+    for result in tasks.collect::<Vec<_>>().await {
+        planned.extend(result??);
+    }
+
+    for upgrade in &planned {
+        outln!(
+            "{} {} -> {}",
+            upgrade.repo,
+            short_sha(&upgrade.from),
+            short_sha(&upgrade.to)
+        );
+        for change in &upgrade.files {
+            match change {
+                FileChange::Added(path) => outln!("    + {}", path.display()),
+                FileChange::Modified(path) => outln!("    ~ {}", path.display()),
+                FileChange::Removed(path) => outln!("    - {}", path.display()),
+            }
+        }
+    }
+    if planned.is_empty() {
+        info!("{}All plugins are up to date!", Emoji("🎉 ", ""));
+    } else if check {
+        return Err(utils::ExitError {
+            code: EXIT_UPDATES_AVAILABLE,
+            message: format!("{} plugin(s) have updates", planned.len()),
+        }
+        .into());
+    } else {
+        info!(
+            "{}Dry run completed. {} plugin(s) would be upgraded; nothing has changed.",
+            Emoji("🧪 ", ""),
+            planned.len()
+        );
+    }
+    Ok(planned)
+}
+
+/// Make the decisions [`upgrade_plugin`] makes, resolving the selector in a temporary
+/// clone of the plugin's origin so the existing clone is not fetched into.
+fn plan_upgrade(
+    plugin_repo: &PluginRepo,
+    options: UpgradeOptions,
+) -> anyhow::Result<Option<PlannedUpgrade>> {
+    let (lock_file, _) = utils::load_lock_file()?;
+    let config = utils::load_config()
+        .map(|(config, _)| config)
+        .unwrap_or_else(|_| crate::config::init());
+    let config_dir = utils::load_fish_config_dir()?;
+
+    if config.is_disabled(plugin_repo) {
+        return Ok(None);
+    }
+    let Some(locked) = lock_file.get_plugin_by_repo(plugin_repo) else {
+        anyhow::bail!("Plugin is not installed: {}", plugin_repo);
+    };
+    if options
+        .since
+        .is_some_and(|since| !list::is_stale(locked, since, update_cache::now_secs()))
+        || git::is_local_source(&locked.source)
+    {
+        return Ok(None);
+    }
+    let repo_path = utils::load_pez_data_dir()?.join(locked.repo.as_str());
+    let Ok(clone) = git2::Repository::open(&repo_path) else {
+        warn!(
+            "{} {} Repository directory at {} does not exist.",
+            Emoji("🚧 ", ""),
+            utils::label_warning(),
+            repo_path.display()
+        );
+        return Ok(None);
+    };
+    if !options.allow_dirty_repo && !git::dirty_paths(&clone)?.is_empty() {
+        warn!(
+            "{} {} Repository for {} has local changes; upgrading it needs --allow-dirty-repo.",
+            Emoji("🚧 ", ""),
+            utils::label_warning(),
+            locked.display_label()
+        );
+    }
+    let origin = clone
+        .find_remote("origin")
+        .ok()
+        .and_then(|remote| remote.url().map(str::to_string))
+        .unwrap_or_else(|| locked.source.clone());
+
+    let checkout = tempfile::tempdir()?;
+    let repo = git::clone_repository(&origin, checkout.path())?;
+    let latest = resolve_latest(&repo, &config, plugin_repo)?;
+    if latest == locked.commit_sha && !options.force {
+        return Ok(None);
+    }
+    git::checkout_commit(&repo, &latest)?;
+
+    let spec = config.find_plugin_spec(plugin_repo);
+    let mut new_files = std::collections::BTreeMap::new();
+    for (dir, rel, src) in utils::plugin_source_files(checkout.path(), locked)? {
+        if spec.is_some_and(|spec| spec.disables(&dir, &rel.to_string_lossy())) {
+            continue;
+        }
+        new_files.insert(config_dir.join(dir.as_str()).join(rel), src);
+    }
+    let old_files: std::collections::BTreeSet<_> = locked
+        .files
+        .iter()
+        .map(|file| file.get_path(&config_dir))
+        .collect();
+
+    let mut files = Vec::new();
+    for (dest, src) in &new_files {
+        if !old_files.contains(dest) {
+            files.push(FileChange::Added(dest.clone()));
+        } else if !utils::same_contents(src, dest) {
+            files.push(FileChange::Modified(dest.clone()));
+        }
+    }
+    for dest in old_files {
+        if !new_files.contains_key(&dest) {
+            files.push(FileChange::Removed(dest));
+        }
+    }
+    if latest == locked.commit_sha && files.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(PlannedUpgrade {
+        repo: plugin_repo.clone(),
+        from: locked.commit_sha.clone(),
+        to: latest,
+        files,
+    }))
+}
+
+/// Move clones to exactly the commits recorded in pez-lock.toml, forwards or backwards,
+/// without consulting remotes beyond fetching a locked commit that is not present yet.
+async fn sync_to_lock(args: &UpgradeArgs, runtime: &RuntimeOptions) -> anyhow::Result<()> {
+    info!("{}Syncing plugins to pez-lock.toml...", Emoji("🔍 ", ""));
+    let repos: Vec<PluginRepo> = match &args.plugins {
+        Some(plugins) => plugins.clone(),
+        None => {
+            let (lock_file, _) = utils::load_lock_file()?;
+            lock_file
+                .without_disabled(utils::load_config().ok().map(|(c, _)| c).as_ref())
+                .plugins
+                .into_iter()
+                .map(|p| p.repo)
+                .collect()
+        }
+    };
+    let options = UpgradeOptions::new(args, runtime);
+    let jobs = options.jobs.max(1);
+    let tasks = stream::iter(repos)
+        .map(|repo| tokio::task::spawn_blocking(move || sync_plugin_to_lock(&repo, options)))
+        .buffer_unordered(jobs);
+    let results: Vec<_> = tasks.collect().await;
+    let mut moved = 0;
+    for r in results {
+        if r?? {
+            moved += 1;
+        }
+    }
+    info!(
+        "{}{} plugin(s) moved to their locked commits.",
+        Emoji("🎉 ", ""),
+        moved
+    );
+    Ok(())
+}
+
+/// Returns whether the clone was moved.
+pub(crate) fn sync_plugin_to_lock(
+    plugin_repo: &PluginRepo,
+    options: UpgradeOptions,
+) -> anyhow::Result<bool> {
+    let (mut lock_file, lock_file_path) = utils::load_lock_file()?;
+    let config_dir = utils::load_fish_config_dir()?;
+    let Some(locked) = lock_file.get_plugin_by_repo(plugin_repo).cloned() else {
+        anyhow::bail!("Plugin is not installed: {}", plugin_repo);
+    };
+    if git::is_local_source(&locked.source) {
+        info!(
+            "{} {} Plugin {} is a local source; skipping.",
+            Emoji("🚧 ", ""),
+            utils::label_info(),
+            plugin_repo
+        );
+        return Ok(false);
+    }
+    let repo_path = utils::load_pez_data_dir()?.join(plugin_repo.as_str());
+    if !repo_path.exists() {
+        warn!(
+            "{} {} {} is not cloned yet; run `pez install` to install it at the locked commit.",
+            Emoji("🚧 ", ""),
+            utils::label_warning(),
+            locked.display_label()
+        );
+        return Ok(false);
+    }
+
+    let repo = git2::Repository::open(&repo_path)?;
+    let current = git::get_latest_commit_sha(&repo).ok();
+    if current.as_deref() == Some(locked.commit_sha.as_str()) && !options.force {
+        info!(
+            "{} {} Plugin {} is already at the locked commit.",
+            Emoji("🚀 ", ""),
+            utils::label_info(),
+            locked.display_label()
+        );
+        return Ok(false);
+    }
+    ensure_clean_worktree(&repo, &locked, &repo_path, options.allow_dirty_repo)?;
+
+    let oid = git2::Oid::from_str(&locked.commit_sha)?;
+    if repo.find_commit(oid).is_err() {
+        git::fetch_all(&repo)?;
+        repo.find_commit(oid).map_err(|_| {
+            anyhow::anyhow!(
+                "Locked commit {} for {} was not found in the remote",
+                locked.commit_sha,
+                locked.display_label()
+            )
+        })?;
+    }
+
+    // Remove what the current checkout installed, which may differ from the files
+    // the (newer or older) lock entry lists.
+    let mut stale: Vec<std::path::PathBuf> = utils::plugin_source_files(&repo_path, &locked)?
+        .into_iter()
+        .map(|(dir, rel, _)| config_dir.join(dir.as_str()).join(rel))
+        .collect();
+    stale.extend(locked.files.iter().map(|f| f.get_path(&config_dir)));
+    git::checkout_detached(&repo, oid)?;
+    for dest_path in stale {
+        if dest_path.exists()
+            && let Err(e) = backup::remove_file(&dest_path)
+        {
+            warn!("Failed to remove {}: {:?}", dest_path.display(), e);
+        }
+    }
+
+    let mut synced = Plugin {
+        files: vec![],
+        ..locked.clone()
+    };
+    utils::copy_plugin_files_from_repo(&repo_path, &mut synced)?;
+    backup::restore_displaced_files(&mut synced, &config_dir);
+    utils::emit_plugin_events(&synced, &utils::Event::Update);
+    if current.as_deref() == Some(synced.commit_sha.as_str()) {
+        info!(
+            "{}Reinstalled {} at {}",
+            Emoji("✅ ", ""),
+            synced.display_label(),
+            short_sha(&synced.commit_sha)
+        );
+    } else {
+        info!(
+            "{}Moved {} from {} to {}",
+            Emoji("✅ ", ""),
+            synced.display_label(),
+            current.as_deref().map(short_sha).unwrap_or("-"),
+            short_sha(&synced.commit_sha)
+        );
+    }
+
+    // Only rewrite the lock when the copied files differ from what it records, so a
+    // committed lock stays byte-for-byte unchanged in the common case.
+    if synced.files != locked.files || synced.backups != locked.backups {
+        lock_file.upsert_plugin_by_repo(synced)?;
+        lock_file.save(&lock_file_path)?;
+    }
+    Ok(true)
+}
+
+fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+/// Upgrading force-checks out the new commit, which would silently drop edits made
+/// inside the clone. Refuse unless the user opted in to discarding them.
+fn ensure_clean_worktree(
+    repo: &git2::Repository,
+    plugin: &Plugin,
+    repo_path: &std::path::Path,
+    allow_dirty_repo: bool,
+) -> anyhow::Result<()> {
+    let dirty = git::dirty_paths(repo)?;
+    if dirty.is_empty() {
+        return Ok(());
+    }
+    if allow_dirty_repo {
+        warn!(
+            "{} {} Discarding local changes in {}: {}",
+            Emoji("🚧 ", ""),
+            utils::label_warning(),
+            repo_path.display(),
+            dirty.join(", ")
+        );
+        return Ok(());
+    }
+    error!(
+        "{} {} Repository for {} has local changes:",
+        Emoji("❌ ", ""),
+        utils::label_error(),
+        plugin.display_label()
+    );
+    for path in &dirty {
+        error!("   - {}", repo_path.join(path).display());
+    }
+    error!("Commit or move these changes elsewhere, or pass --allow-dirty-repo to discard them.");
+    anyhow::bail!(
+        "Repository has local changes: {} (use --allow-dirty-repo to discard)",
+        repo_path.display()
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config;
     use crate::lock_file::{self, LockFile, PluginFile};
+    use crate::models::TargetDir;
     use crate::tests_support::env::TestEnvironmentSetup;
     use crate::tests_support::log::capture_logs;
     use std::ffi::OsString;
@@ -383,6 +908,8 @@ mod tests {
                 config::Config {
                     plugins: Some(vec![config::PluginSpec {
                         name: None,
+                        prerelease: false,
+                        disabled: false,
                         source: config::PluginSource::Repo {
                             repo: repo.clone(),
                             version: None,
@@ -390,10 +917,21 @@ mod tests {
                             tag: None,
                             commit: None,
                         },
+                        dir: None,
+                        events: vec![],
+                        disable_files: vec![],
                     }]),
+                    settings: Default::default(),
+                    aliases: Default::default(),
+                    doctor: Default::default(),
                 }
             } else {
-                config::Config { plugins: None }
+                config::Config {
+                    plugins: None,
+                    settings: Default::default(),
+                    aliases: Default::default(),
+                    doctor: Default::default(),
+                }
             };
             env.setup_config(config);
 
@@ -414,6 +952,7 @@ mod tests {
                             name: "beta.fish".into(),
                         },
                     ],
+                    ..Default::default()
                 }],
             });
 
@@ -494,11 +1033,14 @@ mod tests {
                     dir: TargetDir::Functions,
                     name: "hello.fish".into(),
                 }],
+                ..Default::default()
             }],
         });
         env.setup_config(config::Config {
             plugins: Some(vec![config::PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: repo.clone(),
                     version: None,
@@ -506,10 +1048,16 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
-        let (logs, res) = capture_logs(|| upgrade_plugin(&repo));
+        let (logs, res) = capture_logs(|| upgrade_plugin(&repo, UpgradeOptions::default()));
         assert!(res.is_ok());
         let joined = logs.join("\n");
         assert!(joined.contains("Plugin owner/pkg is already up to date."));
@@ -544,7 +1092,6 @@ mod tests {
     #[test]
     fn upgrade_plugin_uses_pinned_selection_for_repo() {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
-        crate::utils::clear_cli_jobs_override_for_tests();
         let mut fixture = UpgradeFixture::new(false);
         let _override = EnvOverride::new(&[
             "PEZ_SUPPRESS_EMIT",
@@ -562,6 +1109,8 @@ mod tests {
         fixture.env.setup_config(config::Config {
             plugins: Some(vec![config::PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: config::PluginSource::Repo {
                     repo: fixture.repo.clone(),
                     version: None,
@@ -569,10 +1118,16 @@ mod tests {
                     tag: None,
                     commit: None,
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         });
 
-        upgrade_plugin(&fixture.repo).expect("upgrade should succeed");
+        upgrade_plugin(&fixture.repo, UpgradeOptions::default()).expect("upgrade should succeed");
 
         let lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
         let updated = lock.get_plugin_by_repo(&fixture.repo).unwrap();
@@ -582,7 +1137,6 @@ mod tests {
     #[test]
     fn upgrade_plugin_updates_repo_checkout_and_files() {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
-        crate::utils::clear_cli_jobs_override_for_tests();
         let fixture = UpgradeFixture::new(false);
         let _override = EnvOverride::new(&[
             "PEZ_SUPPRESS_EMIT",
@@ -603,7 +1157,7 @@ mod tests {
         let repo = git2::Repository::open(&repo_path).unwrap();
         crate::git::checkout_commit(&repo, &fixture.first_commit).unwrap();
 
-        upgrade_plugin(&fixture.repo).expect("upgrade should succeed");
+        upgrade_plugin(&fixture.repo, UpgradeOptions::default()).expect("upgrade should succeed");
 
         let alpha_path = fixture
             .env
@@ -621,11 +1175,304 @@ mod tests {
         assert!(!beta_path.exists());
     }
 
+    #[test]
+    fn sync_plugin_to_lock_moves_checkout_down_and_up() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let mut fixture = UpgradeFixture::new(false);
+        let _override = EnvOverride::new(&[
+            "PEZ_SUPPRESS_EMIT",
+            "__fish_config_dir",
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+        ]);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+            std::env::set_var("__fish_config_dir", &fixture.env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &fixture.env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &fixture.env.data_dir);
+        }
+        let repo_path = fixture.env.data_dir.join(fixture.repo.as_str());
+        let repo = git2::Repository::open(&repo_path).unwrap();
+        let alpha = fixture.env.fish_config_dir.join("conf.d/alpha.fish");
+        let beta = fixture.env.fish_config_dir.join("functions/beta.fish");
+        std::fs::create_dir_all(alpha.parent().unwrap()).unwrap();
+        std::fs::write(&alpha, "echo two\n").unwrap();
+
+        // The clone is at the second commit; the lock (e.g. pulled from dotfiles) pins the first.
+        assert!(sync_plugin_to_lock(&fixture.repo, UpgradeOptions::default()).unwrap());
+        assert_eq!(
+            crate::git::get_latest_commit_sha(&repo).unwrap(),
+            fixture.first_commit
+        );
+        assert_eq!(std::fs::read_to_string(&alpha).unwrap(), "echo one\n");
+        assert!(beta.exists());
+        let lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
+        assert_eq!(
+            lock.get_plugin_by_repo(&fixture.repo).unwrap().commit_sha,
+            fixture.first_commit
+        );
+        assert!(!sync_plugin_to_lock(&fixture.repo, UpgradeOptions::default()).unwrap());
+
+        // Forcing a sync at the locked commit (as snapshot restore does) re-copies the
+        // files without claiming the checkout moved.
+        let (logs, res) = capture_logs(|| {
+            sync_plugin_to_lock(
+                &fixture.repo,
+                UpgradeOptions {
+                    force: true,
+                    ..Default::default()
+                },
+            )
+        });
+        assert!(res.unwrap());
+        let joined = logs.join("\n");
+        assert!(joined.contains(&format!("Reinstalled {}", fixture.repo)));
+        assert!(!joined.contains("Moved"));
+
+        // A newer lock moves it forward and drops files the old checkout installed.
+        let mut lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
+        lock.plugins[0].commit_sha = fixture.second_commit.clone();
+        lock.plugins[0].files.retain(|f| f.name == "alpha.fish");
+        fixture.env.setup_lock_file(lock);
+        let before = std::fs::read_to_string(&fixture.env.lock_file_path).unwrap();
+        assert!(sync_plugin_to_lock(&fixture.repo, UpgradeOptions::default()).unwrap());
+        assert_eq!(std::fs::read_to_string(&alpha).unwrap(), "echo two\n");
+        assert!(!beta.exists());
+        assert_eq!(
+            std::fs::read_to_string(&fixture.env.lock_file_path).unwrap(),
+            before
+        );
+    }
+
+    #[test]
+    fn upgrade_plugin_force_recopies_files_at_same_commit() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let fixture = UpgradeFixture::new(false);
+        let _override = EnvOverride::new(&[
+            "PEZ_SUPPRESS_EMIT",
+            "__fish_config_dir",
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+        ]);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+            std::env::set_var("__fish_config_dir", &fixture.env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &fixture.env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &fixture.env.data_dir);
+        }
+
+        upgrade_plugin(&fixture.repo, UpgradeOptions::default()).unwrap();
+        let alpha_path = fixture
+            .env
+            .fish_config_dir
+            .join(TargetDir::ConfD.as_str())
+            .join("alpha.fish");
+        std::fs::remove_file(&alpha_path).unwrap();
+
+        upgrade_plugin(&fixture.repo, UpgradeOptions::default()).unwrap();
+        assert!(!alpha_path.exists());
+
+        upgrade_plugin(
+            &fixture.repo,
+            UpgradeOptions {
+                force: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&alpha_path).unwrap(), "echo two\n");
+        let lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
+        let updated = lock.get_plugin_by_repo(&fixture.repo).unwrap();
+        assert_eq!(updated.commit_sha, fixture.second_commit);
+    }
+
+    #[test]
+    fn upgrade_plugin_since_skips_recently_updated_plugins() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let fixture = UpgradeFixture::new(true);
+        let _override = EnvOverride::new(&[
+            "PEZ_SUPPRESS_EMIT",
+            "__fish_config_dir",
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+        ]);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+            std::env::set_var("__fish_config_dir", &fixture.env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &fixture.env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &fixture.env.data_dir);
+        }
+        let thirty_days = std::time::Duration::from_secs(30 * 86_400);
+        let set_updated_at = |updated_at: u64| {
+            let mut lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
+            lock.plugins[0].updated_at = Some(updated_at);
+            lock.save(&fixture.env.lock_file_path).unwrap();
+        };
+        let locked_sha = || {
+            let lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
+            lock.get_plugin_by_repo(&fixture.repo)
+                .unwrap()
+                .commit_sha
+                .clone()
+        };
+        let options = UpgradeOptions {
+            since: Some(thirty_days),
+            ..Default::default()
+        };
+
+        set_updated_at(update_cache::now_secs() - 86_400);
+        upgrade_plugin(&fixture.repo, options).unwrap();
+        assert_eq!(locked_sha(), fixture.first_commit);
+
+        set_updated_at(update_cache::now_secs() - 31 * 86_400);
+        upgrade_plugin(&fixture.repo, options).unwrap();
+        assert_eq!(locked_sha(), fixture.second_commit);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dry_run_reports_commits_and_file_changes_without_changing_anything() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let fixture = UpgradeFixture::new(true);
+        let _override = EnvOverride::new(&["__fish_config_dir", "PEZ_CONFIG_DIR", "PEZ_DATA_DIR"]);
+        unsafe {
+            std::env::set_var("__fish_config_dir", &fixture.env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &fixture.env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &fixture.env.data_dir);
+        }
+        let alpha = fixture.env.fish_config_dir.join("conf.d/alpha.fish");
+        let beta = fixture.env.fish_config_dir.join("functions/beta.fish");
+        for (path, content) in [(&alpha, "echo one\n"), (&beta, "echo beta\n")] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let clone =
+            git2::Repository::open(fixture.env.data_dir.join(fixture.repo.as_str())).unwrap();
+        let head_before = clone.head().unwrap().target();
+        let lock_before = std::fs::read_to_string(&fixture.env.lock_file_path).unwrap();
+        let plan = |check: bool| {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(dry_run(
+                    vec![fixture.repo.clone()],
+                    UpgradeOptions::default(),
+                    check,
+                ))
+            })
+        };
+
+        assert_eq!(
+            plan(false).unwrap(),
+            vec![PlannedUpgrade {
+                repo: fixture.repo.clone(),
+                from: fixture.first_commit.clone(),
+                to: fixture.second_commit.clone(),
+                files: vec![
+                    FileChange::Modified(alpha.clone()),
+                    FileChange::Removed(beta.clone()),
+                ],
+            }]
+        );
+        let err = plan(true).unwrap_err();
+        assert_eq!(crate::exit_code(&err), EXIT_UPDATES_AVAILABLE);
+
+        assert_eq!(clone.head().unwrap().target(), head_before);
+        assert_eq!(
+            std::fs::read_to_string(&fixture.env.lock_file_path).unwrap(),
+            lock_before
+        );
+        assert_eq!(std::fs::read_to_string(&alpha).unwrap(), "echo one\n");
+        assert!(beta.exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn update_upgrades_outdated_plugins_without_adding_them_to_config() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let fixture = UpgradeFixture::new(false);
+        let _override = EnvOverride::new(&[
+            "PEZ_SUPPRESS_EMIT",
+            "__fish_config_dir",
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+        ]);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+            std::env::set_var("__fish_config_dir", &fixture.env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &fixture.env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &fixture.env.data_dir);
+        }
+        let update = |plugins: Option<Vec<PluginRepo>>| {
+            let args = crate::cli::UpdateArgs {
+                plugins,
+                allow_dirty_repo: false,
+                backup_dir: None,
+                no_backup: true,
+            };
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current()
+                    .block_on(crate::cmd::update::run(&args, &RuntimeOptions::default()))
+            })
+        };
+
+        assert_eq!(update(None).unwrap(), vec![fixture.repo.clone()]);
+        let lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
+        let locked = lock.get_plugin_by_repo(&fixture.repo).unwrap();
+        assert_eq!(locked.commit_sha, fixture.second_commit);
+        assert!(
+            config::load(&fixture.env.config_path)
+                .unwrap()
+                .plugins
+                .is_none()
+        );
+
+        assert!(update(None).unwrap().is_empty());
+        let missing: PluginRepo = "owner/missing".parse().unwrap();
+        let err = update(Some(vec![missing])).unwrap_err();
+        assert!(err.to_string().contains("not installed"), "{err}");
+    }
+
+    #[test]
+    fn upgrade_plugin_refuses_dirty_clone_unless_allowed() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let fixture = UpgradeFixture::new(false);
+        let _override = EnvOverride::new(&[
+            "PEZ_SUPPRESS_EMIT",
+            "__fish_config_dir",
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+        ]);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+            std::env::set_var("__fish_config_dir", &fixture.env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &fixture.env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &fixture.env.data_dir);
+        }
+
+        let repo_path = fixture.env.data_dir.join(fixture.repo.as_str());
+        let edited = repo_path.join(TargetDir::ConfD.as_str()).join("alpha.fish");
+        std::fs::write(&edited, "echo local\n").unwrap();
+
+        let err = upgrade_plugin(&fixture.repo, UpgradeOptions::default())
+            .expect_err("dirty clone should be refused");
+        assert!(err.to_string().contains("--allow-dirty-repo"), "{err}");
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "echo local\n");
+        let lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
+        let locked = lock.get_plugin_by_repo(&fixture.repo).unwrap();
+        assert_eq!(locked.commit_sha, fixture.first_commit);
+
+        upgrade_plugin(
+            &fixture.repo,
+            UpgradeOptions {
+                allow_dirty_repo: true,
+                ..Default::default()
+            },
+        )
+        .expect("upgrade should discard local changes");
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "echo two\n");
+    }
+
     #[allow(clippy::await_holding_lock)]
     #[tokio::test(flavor = "multi_thread")]
     async fn run_upgrades_selected_plugins_and_emits_events() {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
-        crate::utils::clear_cli_jobs_override_for_tests();
         let fixture = UpgradeFixture::new(false);
         let _override = EnvOverride::new(&[
             "PATH",
@@ -659,8 +1506,19 @@ mod tests {
 
         let args = UpgradeArgs {
             plugins: Some(vec![fixture.repo.clone()]),
+            force: false,
+            allow_dirty_repo: false,
+            to_lock: false,
+            interactive: false,
+            since: None,
+            dry_run: false,
+            check: false,
+            backup_dir: None,
+            no_backup: true,
         };
-        run(&args).await.expect("run should succeed");
+        run(&args, &RuntimeOptions::default())
+            .await
+            .expect("run should succeed");
 
         let cfg = config::load(&fixture.env.config_path).unwrap();
         assert!(
@@ -683,7 +1541,6 @@ mod tests {
     #[tokio::test(flavor = "multi_thread")]
     async fn run_upgrades_all_plugins() {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
-        crate::utils::clear_cli_jobs_override_for_tests();
         let fixture = UpgradeFixture::new(true);
         let _override = EnvOverride::new(&[
             "PEZ_SUPPRESS_EMIT",
@@ -700,11 +1557,95 @@ mod tests {
             std::env::set_var("PEZ_JOBS", "1");
         }
 
-        let args = UpgradeArgs { plugins: None };
-        run(&args).await.expect("run should succeed");
+        let args = UpgradeArgs {
+            plugins: None,
+            force: false,
+            allow_dirty_repo: false,
+            to_lock: false,
+            interactive: false,
+            since: None,
+            dry_run: false,
+            check: false,
+            backup_dir: None,
+            no_backup: true,
+        };
+        run(&args, &RuntimeOptions::default())
+            .await
+            .expect("run should succeed");
 
         let lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
         let updated = lock.get_plugin_by_repo(&fixture.repo).unwrap();
         assert_eq!(updated.commit_sha, fixture.second_commit);
     }
+
+    #[allow(clippy::await_holding_lock)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn interactive_upgrade_pins_or_upgrades_per_answer() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let fixture = UpgradeFixture::new(true);
+        let _override = EnvOverride::new(&[
+            "PEZ_SUPPRESS_EMIT",
+            "__fish_config_dir",
+            "PEZ_CONFIG_DIR",
+            "PEZ_DATA_DIR",
+            "PEZ_JOBS",
+            prompt::ASSUME_YES_ENV,
+        ]);
+        unsafe {
+            std::env::set_var("PEZ_SUPPRESS_EMIT", "1");
+            std::env::set_var("__fish_config_dir", &fixture.env.fish_config_dir);
+            std::env::set_var("PEZ_CONFIG_DIR", &fixture.env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &fixture.env.data_dir);
+            std::env::set_var("PEZ_JOBS", "1");
+            std::env::remove_var(prompt::ASSUME_YES_ENV);
+        }
+        let args = UpgradeArgs {
+            plugins: None,
+            force: false,
+            allow_dirty_repo: false,
+            to_lock: false,
+            interactive: true,
+            since: None,
+            dry_run: false,
+            check: false,
+            backup_dir: None,
+            no_backup: true,
+        };
+
+        {
+            let _answers = prompt::ScriptedAnswers::new(&["pin\n"]);
+            run(&args, &RuntimeOptions::default())
+                .await
+                .expect("interactive run should succeed");
+        }
+        let lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
+        assert_eq!(
+            lock.get_plugin_by_repo(&fixture.repo).unwrap().commit_sha,
+            fixture.first_commit
+        );
+        let cfg = config::load(&fixture.env.config_path).unwrap();
+        let spec = cfg.find_plugin_spec(&fixture.repo).unwrap();
+        assert!(matches!(
+            &spec.source,
+            config::PluginSource::Repo { commit: Some(sha), .. } if *sha == fixture.first_commit
+        ));
+
+        let mut cfg = cfg;
+        cfg.find_plugin_spec_mut(&fixture.repo)
+            .unwrap()
+            .unpin()
+            .unwrap();
+        cfg.save(&fixture.env.config_path).unwrap();
+        {
+            let _answers = prompt::ScriptedAnswers::new(&["a\n"]);
+            run(&args, &RuntimeOptions::default())
+                .await
+                .expect("interactive run should succeed");
+        }
+        let lock = lock_file::load(&fixture.env.lock_file_path).unwrap();
+        assert_eq!(
+            lock.get_plugin_by_repo(&fixture.repo).unwrap().commit_sha,
+            fixture.second_commit
+        );
+    }
 }
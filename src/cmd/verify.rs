@@ -0,0 +1,194 @@
+use crate::{
+    cli::{VerifyArgs, VerifyFormat},
+    cmd::repair::{self, FileState},
+    lock_file::Plugin,
+    output::{self, outln},
+    runtime::RuntimeOptions,
+    utils,
+};
+
+use console::Emoji;
+use serde_derive::Serialize;
+use std::path;
+use tracing::{info, warn};
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct FileProblem {
+    pub(crate) file: String,
+    pub(crate) repo: String,
+    /// `missing` or `modified`.
+    pub(crate) status: &'static str,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub(crate) struct VerifyReport {
+    pub(crate) checked: usize,
+    /// Files that exist but could not be compared because the clone is gone.
+    pub(crate) unverified: usize,
+    pub(crate) problems: Vec<FileProblem>,
+}
+
+/// Check that every file recorded in pez-lock.toml is installed with the contents of
+/// the locked commit. Fails when any file is missing or modified; `pez repair` fixes them.
+pub(crate) fn run(args: &VerifyArgs, options: &RuntimeOptions) -> anyhow::Result<VerifyReport> {
+    let (lock_file, _) = utils::load_lock_file()?;
+    let lock_file = lock_file.without_disabled(utils::load_config().ok().map(|(c, _)| c).as_ref());
+    let fish_config_dir = utils::load_fish_config_dir()?;
+    let data_dir = utils::load_pez_data_dir()?;
+
+    let plugins: Vec<&Plugin> = match &args.plugins {
+        Some(repos) => repos
+            .iter()
+            .map(|repo| {
+                lock_file
+                    .get_plugin_by_repo(repo)
+                    .ok_or_else(|| anyhow::anyhow!("Plugin is not installed: {}", repo))
+            })
+            .collect::<anyhow::Result<_>>()?,
+        None => lock_file.plugins.iter().collect(),
+    };
+
+    let report = verify(&plugins, &data_dir, &fish_config_dir);
+    match args.format {
+        Some(VerifyFormat::Json) => outln!("{}", output::to_json(&report, options.json_style)?),
+        None => {
+            for problem in &report.problems {
+                outln!("{:<8} {} ({})", problem.status, problem.file, problem.repo);
+            }
+        }
+    }
+
+    if report.unverified > 0 {
+        warn!(
+            "{} {} {} file(s) could not be compared because their clone is missing; run `pez install` to restore it.",
+            Emoji("🚧 ", ""),
+            utils::label_warning(),
+            report.unverified
+        );
+    }
+    if !report.problems.is_empty() {
+        anyhow::bail!(
+            "{} of {} file(s) do not match pez-lock.toml; run `pez repair` to restore them",
+            report.problems.len(),
+            report.checked
+        );
+    }
+    info!(
+        "{}All {} file(s) match pez-lock.toml.",
+        Emoji("🎉 ", ""),
+        report.checked
+    );
+    Ok(report)
+}
+
+fn verify(
+    plugins: &[&Plugin],
+    data_dir: &path::Path,
+    fish_config_dir: &path::Path,
+) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    for plugin in plugins {
+        for check in repair::check_plugin_files(plugin, data_dir, fish_config_dir) {
+            report.checked += 1;
+            let status = match check.state {
+                FileState::Intact if check.expected.is_err() => {
+                    report.unverified += 1;
+                    continue;
+                }
+                FileState::Intact => continue,
+                FileState::Missing => "missing",
+                FileState::Modified => "modified",
+            };
+            let file = check
+                .dest
+                .strip_prefix(fish_config_dir)
+                .unwrap_or(&check.dest)
+                .display()
+                .to_string();
+            report.problems.push(FileProblem {
+                file,
+                repo: plugin.repo.as_str(),
+                status,
+            });
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lock_file::PluginFile,
+        models::{PluginRepo, TargetDir},
+    };
+    use std::fs;
+
+    #[test]
+    fn verify_reports_missing_modified_and_unverified_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let fish = temp.path().join("fish");
+        let data_dir = temp.path().join("data");
+        let source = temp.path().join("local-plugin");
+        for dir in [&fish, &source] {
+            fs::create_dir_all(dir.join("functions")).unwrap();
+            fs::create_dir_all(dir.join("conf.d")).unwrap();
+        }
+        fs::write(source.join("functions/a.fish"), "a").unwrap();
+        fs::write(source.join("functions/b.fish"), "b").unwrap();
+        fs::write(source.join("conf.d/c.fish"), "c").unwrap();
+        fs::write(fish.join("functions/a.fish"), "a").unwrap();
+        fs::write(fish.join("functions/b.fish"), "edited").unwrap();
+        fs::write(fish.join("conf.d/gone.fish"), "x").unwrap();
+
+        let file = |dir: TargetDir, name: &str| PluginFile {
+            dir,
+            name: name.into(),
+        };
+        let local: PluginRepo = "local/local-plugin".parse().unwrap();
+        let cloned: PluginRepo = "owner/gone".parse().unwrap();
+        let plugins = [
+            Plugin {
+                name: "local-plugin".into(),
+                repo: local,
+                source: source.display().to_string(),
+                commit_sha: "local".into(),
+                files: vec![
+                    file(TargetDir::Functions, "a.fish"),
+                    file(TargetDir::Functions, "b.fish"),
+                    file(TargetDir::ConfD, "c.fish"),
+                ],
+                ..Default::default()
+            },
+            Plugin {
+                name: "gone".into(),
+                source: cloned.default_remote_source(),
+                repo: cloned,
+                commit_sha: "0123456789abcdef0123456789abcdef01234567".into(),
+                files: vec![file(TargetDir::ConfD, "gone.fish")],
+                ..Default::default()
+            },
+        ];
+
+        let report = verify(&plugins.iter().collect::<Vec<_>>(), &data_dir, &fish);
+        assert_eq!(
+            report,
+            VerifyReport {
+                checked: 4,
+                unverified: 1,
+                problems: vec![
+                    FileProblem {
+                        file: "functions/b.fish".into(),
+                        repo: "local/local-plugin".into(),
+                        status: "modified",
+                    },
+                    FileProblem {
+                        file: "conf.d/c.fish".into(),
+                        repo: "local/local-plugin".into(),
+                        status: "missing",
+                    },
+                ],
+            }
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::utils;
+use console::Emoji;
+use tracing::{info, warn};
+
+/// Environment variable the fish wrapper sets to its embedded version when it
+/// runs `pez` on behalf of the current shell.
+pub(crate) const ACTIVATE_VERSION_ENV: &str = "PEZ_ACTIVATE_VERSION";
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ActivateVersionStatus {
+    Match(String),
+    Mismatch { activated: String },
+    NotActivated,
+}
+
+pub(crate) fn run() -> anyhow::Result<ActivateVersionStatus> {
+    let activated = match activate_version_from_env() {
+        Some(version) => Some(version),
+        None => query_fish_activate_version(),
+    };
+    let status = evaluate(activated.as_deref());
+
+    match &status {
+        ActivateVersionStatus::Match(version) => {
+            info!(
+                "{}Activation wrapper matches pez {}",
+                Emoji("✅ ", ""),
+                version
+            );
+        }
+        ActivateVersionStatus::Mismatch { activated } => {
+            warn!(
+                "{} {} Activation wrapper is from pez {}, but the binary is {}.",
+                Emoji("⚠ ", ""),
+                utils::label_warning(),
+                activated,
+                binary_version()
+            );
+            warn!("{}", reactivate_hint());
+            anyhow::bail!("activation wrapper version mismatch");
+        }
+        ActivateVersionStatus::NotActivated => {
+            warn!(
+                "{} {} Activation wrapper not detected.",
+                Emoji("⚠ ", ""),
+                utils::label_warning()
+            );
+            warn!("{}", reactivate_hint());
+            anyhow::bail!("activation wrapper not detected");
+        }
+    }
+
+    Ok(status)
+}
+
+pub(crate) fn binary_version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+pub(crate) fn evaluate(activated: Option<&str>) -> ActivateVersionStatus {
+    match activated.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(version) if version == binary_version() => {
+            ActivateVersionStatus::Match(version.to_string())
+        }
+        Some(version) => ActivateVersionStatus::Mismatch {
+            activated: version.to_string(),
+        },
+        None => ActivateVersionStatus::NotActivated,
+    }
+}
+
+pub(crate) fn activate_version_from_env() -> Option<String> {
+    std::env::var(ACTIVATE_VERSION_ENV)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+}
+
+pub(crate) fn reactivate_hint() -> &'static str {
+    "Re-source the wrapper with `pez activate fish | source` (or start a new shell) after upgrading pez."
+}
+
+/// Ask a fresh interactive fish for the wrapper version loaded by its config.
+/// Used when `pez` is not invoked through the wrapper itself.
+fn query_fish_activate_version() -> Option<String> {
+    let output = std::process::Command::new("fish")
+        .arg("--interactive")
+        .arg("--command")
+        .arg("if set -q __pez_activate_version; echo $__pez_activate_version; end")
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluate_matches_binary_version() {
+        assert_eq!(
+            evaluate(Some(binary_version())),
+            ActivateVersionStatus::Match(binary_version().to_string())
+        );
+    }
+
+    #[test]
+    fn evaluate_reports_mismatch_and_missing() {
+        assert_eq!(
+            evaluate(Some("0.0.1")),
+            ActivateVersionStatus::Mismatch {
+                activated: "0.0.1".to_string()
+            }
+        );
+        assert_eq!(evaluate(Some("  ")), ActivateVersionStatus::NotActivated);
+        assert_eq!(evaluate(None), ActivateVersionStatus::NotActivated);
+    }
+
+    #[test]
+    fn run_uses_wrapper_env_version() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let previous = std::env::var_os(ACTIVATE_VERSION_ENV);
+        unsafe {
+            std::env::set_var(ACTIVATE_VERSION_ENV, binary_version());
+        }
+        let matched = run();
+        unsafe {
+            std::env::set_var(ACTIVATE_VERSION_ENV, "0.0.1");
+        }
+        let mismatched = run();
+        unsafe {
+            match previous {
+                Some(v) => std::env::set_var(ACTIVATE_VERSION_ENV, v),
+                None => std::env::remove_var(ACTIVATE_VERSION_ENV),
+            }
+        }
+
+        assert_eq!(
+            matched.unwrap(),
+            ActivateVersionStatus::Match(binary_version().to_string())
+        );
+        assert!(
+            mismatched
+                .unwrap_err()
+                .to_string()
+                .contains("version mismatch")
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{
+    cli, lock_file,
+    output::{self, outln},
+    runtime::RuntimeOptions,
+};
+use serde_derive::Serialize;
+
+/// Build metadata reported by `pez version`.
+#[derive(Serialize, Debug)]
+pub(crate) struct BuildInfo {
+    pub(crate) version: &'static str,
+    /// Commit the binary was built from, when known at build time.
+    pub(crate) git_sha: Option<&'static str>,
+    pub(crate) features: Vec<&'static str>,
+    pub(crate) libgit2: String,
+    pub(crate) lock_file_versions: &'static [u32],
+}
+
+pub(crate) fn run(args: &cli::VersionArgs, options: &RuntimeOptions) -> anyhow::Result<BuildInfo> {
+    let info = build_info();
+    match args.format {
+        Some(cli::VersionFormat::Json) => {
+            outln!("{}", output::to_json(&info, options.json_style)?);
+        }
+        None => {
+            outln!("{}", render_plain(&info));
+        }
+    }
+    Ok(info)
+}
+
+pub(crate) fn build_info() -> BuildInfo {
+    let (major, minor, rev) = git2::Version::get().libgit2_version();
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: option_env!("PEZ_GIT_SHA"),
+        features: enabled_features(),
+        libgit2: format!("{major}.{minor}.{rev}"),
+        lock_file_versions: lock_file::SUPPORTED_VERSIONS,
+    }
+}
+
+fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "schema-gen") {
+        features.push("schema-gen");
+    }
+    if cfg!(feature = "forge-api") {
+        features.push("forge-api");
+    }
+    if cfg!(feature = "tui") {
+        features.push("tui");
+    }
+    features
+}
+
+fn render_plain(info: &BuildInfo) -> String {
+    match info.git_sha {
+        Some(sha) => format!("pez {} ({})", info.version, &sha[..sha.len().min(12)]),
+        None => format!("pez {}", info.version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_info_reports_package_and_lock_versions() {
+        let info = build_info();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(
+            info.lock_file_versions
+                .contains(&lock_file::CURRENT_VERSION)
+        );
+        assert_eq!(info.libgit2.split('.').count(), 3);
+
+        let json: serde_json::Value = serde_json::to_value(&info).unwrap();
+        for key in [
+            "version",
+            "git_sha",
+            "features",
+            "libgit2",
+            "lock_file_versions",
+        ] {
+            assert!(json.get(key).is_some(), "missing {key}");
+        }
+    }
+
+    #[test]
+    fn render_plain_shortens_sha() {
+        let mut info = build_info();
+        info.git_sha = Some("0123456789abcdef0123");
+        assert_eq!(
+            render_plain(&info),
+            format!("pez {} (0123456789ab)", info.version)
+        );
+        info.git_sha = None;
+        assert_eq!(render_plain(&info), format!("pez {}", info.version));
+    }
+}
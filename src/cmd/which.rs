@@ -0,0 +1,219 @@
+use crate::{
+    cli::{WhichArgs, WhichFormat},
+    lock_file::{LockFile, Plugin},
+    models::TargetDir,
+    output::{self, outln},
+    runtime::RuntimeOptions,
+    utils,
+};
+
+use serde_derive::Serialize;
+use std::{fs, path};
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct Provider {
+    /// `function` or `completion`.
+    pub(crate) kind: &'static str,
+    pub(crate) plugin: String,
+    pub(crate) repo: String,
+    pub(crate) path: String,
+    /// Line of the `function` definition, for matches found with `--grep`.
+    pub(crate) line: Option<usize>,
+    /// Installed with `pez install --temp` rather than from pez.toml.
+    pub(crate) temp: bool,
+}
+
+/// Report which installed plugin(s) provide a fish function or its completions.
+pub(crate) fn run(args: &WhichArgs, options: &RuntimeOptions) -> anyhow::Result<Vec<Provider>> {
+    let config_dir = utils::load_fish_config_dir()?;
+    let (lock_file, _) = utils::load_lock_file()?;
+    let temp_lock_file = utils::load_or_create_temp_lock_file()
+        .map(|(lock, _)| lock)
+        .unwrap_or_else(|_| crate::lock_file::init());
+
+    let name = args.name.strip_suffix(".fish").unwrap_or(&args.name);
+    let mut providers = find_providers(&lock_file, name, &config_dir, args.grep, false);
+    providers.extend(find_providers(
+        &temp_lock_file,
+        name,
+        &config_dir,
+        args.grep,
+        true,
+    ));
+    if providers.is_empty() {
+        let hint = if args.grep {
+            ""
+        } else {
+            "; try --grep to also search inside plugin files"
+        };
+        anyhow::bail!("No installed plugin provides `{name}`{hint}");
+    }
+
+    match args.format {
+        Some(WhichFormat::Json) => outln!("{}", output::to_json(&providers, options.json_style)?),
+        None => {
+            for provider in &providers {
+                outln!("{}", render_plain(provider));
+            }
+        }
+    }
+    Ok(providers)
+}
+
+/// `functions/<name>.fish` and `completions/<name>.fish` recorded in the lock, then with
+/// `grep`, `function <name>` definitions inside the plugins' other functions and conf.d
+/// files.
+fn find_providers(
+    lock_file: &LockFile,
+    name: &str,
+    config_dir: &path::Path,
+    grep: bool,
+    temp: bool,
+) -> Vec<Provider> {
+    let file_name = format!("{name}.fish");
+    let mut providers = Vec::new();
+    for plugin in &lock_file.plugins {
+        for file in &plugin.files {
+            let kind = match file.dir {
+                TargetDir::Functions if file.name == file_name => "function",
+                TargetDir::Completions if file.name == file_name => "completion",
+                TargetDir::Functions | TargetDir::ConfD if grep => {
+                    let path = file.get_path(config_dir);
+                    if let Some(line) = definition_line(&path, name) {
+                        providers.push(provider(plugin, "function", &path, Some(line), temp));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            providers.push(provider(
+                plugin,
+                kind,
+                &file.get_path(config_dir),
+                None,
+                temp,
+            ));
+        }
+    }
+    providers
+}
+
+/// The 1-based line of `function <name>` in `path`, if it defines that function.
+fn definition_line(path: &path::Path, name: &str) -> Option<usize> {
+    let content = fs::read_to_string(path).ok()?;
+    content
+        .lines()
+        .position(|line| {
+            let mut words = line.split_whitespace();
+            words.next() == Some("function") && words.next() == Some(name)
+        })
+        .map(|index| index + 1)
+}
+
+fn provider(
+    plugin: &Plugin,
+    kind: &'static str,
+    path: &path::Path,
+    line: Option<usize>,
+    temp: bool,
+) -> Provider {
+    Provider {
+        kind,
+        plugin: plugin.get_name(),
+        repo: plugin.repo.as_str(),
+        path: path.display().to_string(),
+        line,
+        temp,
+    }
+}
+
+fn render_plain(provider: &Provider) -> String {
+    let temp = if provider.temp { " (temp)" } else { "" };
+    let line = provider
+        .line
+        .map(|line| format!(":{line}"))
+        .unwrap_or_default();
+    format!(
+        "{} {}{temp}: {}{line}",
+        provider.kind, provider.repo, provider.path
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lock_file::PluginFile, models::PluginRepo};
+
+    fn plugin(repo: &str, files: &[(TargetDir, &str)]) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "0123456789abcdef".into(),
+            files: files
+                .iter()
+                .map(|(dir, name)| PluginFile {
+                    dir: dir.clone(),
+                    name: name.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn find_providers_matches_files_then_definitions_with_grep() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_dir = temp.path();
+        for dir in [TargetDir::Functions, TargetDir::ConfD] {
+            fs::create_dir_all(config_dir.join(dir.as_str())).unwrap();
+        }
+        fs::write(
+            config_dir.join("conf.d/fzf.fish"),
+            "set -g x 1\n\nfunction _fzf_search --description 'search'\nend\n",
+        )
+        .unwrap();
+        let lock_file = LockFile {
+            version: 2,
+            plugins: vec![
+                plugin(
+                    "PatrickF1/fzf.fish",
+                    &[
+                        (TargetDir::Functions, "fzf_configure_bindings.fish"),
+                        (TargetDir::Completions, "fzf_configure_bindings.fish"),
+                        (TargetDir::ConfD, "fzf.fish"),
+                    ],
+                ),
+                plugin("owner/other", &[(TargetDir::ConfD, "other.fish")]),
+            ],
+        };
+        let rendered = |name: &str, grep: bool| -> Vec<String> {
+            find_providers(&lock_file, name, config_dir, grep, false)
+                .iter()
+                .map(render_plain)
+                .collect()
+        };
+
+        let root = config_dir.display();
+        assert_eq!(
+            rendered("fzf_configure_bindings", false),
+            vec![
+                format!(
+                    "function PatrickF1/fzf.fish: {root}/functions/fzf_configure_bindings.fish"
+                ),
+                format!(
+                    "completion PatrickF1/fzf.fish: {root}/completions/fzf_configure_bindings.fish"
+                ),
+            ]
+        );
+        assert!(rendered("_fzf_search", false).is_empty());
+        assert_eq!(
+            rendered("_fzf_search", true),
+            vec![format!(
+                "function PatrickF1/fzf.fish: {root}/conf.d/fzf.fish:3"
+            )]
+        );
+        assert!(rendered("_fzf", true).is_empty());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{
+    cli::{WhyArgs, WhyFormat},
+    lock_file::{LockFile, Plugin, PluginFile},
+    output::{self, outln},
+    runtime::RuntimeOptions,
+    utils,
+};
+
+use serde_derive::Serialize;
+use std::path;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct FileOwner {
+    /// Destination relative to the fish config dir, e.g. `conf.d/tide.fish`.
+    pub(crate) file: String,
+    pub(crate) plugin: String,
+    pub(crate) repo: String,
+    pub(crate) commit: String,
+    pub(crate) source: String,
+    /// Installed with `pez install --temp` rather than from pez.toml.
+    pub(crate) temp: bool,
+}
+
+/// Report which installed plugin(s) put a file into the fish config dir.
+pub(crate) fn run(args: &WhyArgs, options: &RuntimeOptions) -> anyhow::Result<Vec<FileOwner>> {
+    let config_dir = utils::load_fish_config_dir()?;
+    let (lock_file, _) = utils::load_lock_file()?;
+    let temp_lock_file = utils::load_or_create_temp_lock_file()
+        .map(|(lock, _)| lock)
+        .unwrap_or_else(|_| crate::lock_file::init());
+
+    let query = Query::parse(&args.file, &config_dir);
+    let mut owners = find_owners(&lock_file, &query, false);
+    owners.extend(find_owners(&temp_lock_file, &query, true));
+    if owners.is_empty() {
+        anyhow::bail!(
+            "{} is not installed by any plugin in pez-lock.toml",
+            args.file
+        );
+    }
+
+    match args.format {
+        Some(WhyFormat::Json) => outln!("{}", output::to_json(&owners, options.json_style)?),
+        None => {
+            for owner in &owners {
+                outln!("{}", render_plain(owner));
+            }
+        }
+    }
+    if owners.len() > 1 {
+        tracing::warn!(
+            "{} {} is claimed by {} plugins; the one installed last overwrote the others.",
+            utils::label_warning(),
+            args.file,
+            owners.len()
+        );
+    }
+    Ok(owners)
+}
+
+/// What the user asked about: a path under the fish config dir, or a bare file name
+/// (with or without `.fish`) matched in every destination directory.
+#[derive(Debug, PartialEq)]
+enum Query {
+    Path(path::PathBuf),
+    Name(String),
+}
+
+impl Query {
+    fn parse(raw: &str, config_dir: &path::Path) -> Self {
+        let raw_path = path::Path::new(raw);
+        if raw_path.is_absolute() {
+            return Query::Path(
+                relative_to(raw_path, config_dir).unwrap_or_else(|| raw_path.to_path_buf()),
+            );
+        }
+        if raw.contains('/') {
+            Query::Path(
+                raw_path
+                    .components()
+                    .filter(|c| !matches!(c, path::Component::CurDir))
+                    .collect(),
+            )
+        } else {
+            Query::Name(raw.to_string())
+        }
+    }
+
+    fn matches(&self, file: &PluginFile) -> bool {
+        match self {
+            Query::Path(path) => path == &path::Path::new(file.dir.as_str()).join(&file.name),
+            Query::Name(name) => {
+                file.name == *name || file.name.strip_suffix(".fish") == Some(name.as_str())
+            }
+        }
+    }
+}
+
+/// `path` relative to `dir`, also when one of them goes through a symlink
+/// (e.g. a dotfiles-managed `~/.config/fish`).
+fn relative_to(path: &path::Path, dir: &path::Path) -> Option<path::PathBuf> {
+    if let Ok(relative) = path.strip_prefix(dir) {
+        return Some(relative.to_path_buf());
+    }
+    let parent = path.parent()?.canonicalize().ok()?;
+    let relative = parent.strip_prefix(dir.canonicalize().ok()?).ok()?;
+    Some(relative.join(path.file_name()?))
+}
+
+fn find_owners(lock_file: &LockFile, query: &Query, temp: bool) -> Vec<FileOwner> {
+    lock_file
+        .plugins
+        .iter()
+        .flat_map(|plugin| {
+            plugin
+                .files
+                .iter()
+                .filter(|file| query.matches(file))
+                .map(move |file| owner(plugin, file, temp))
+        })
+        .collect()
+}
+
+fn owner(plugin: &Plugin, file: &PluginFile, temp: bool) -> FileOwner {
+    FileOwner {
+        file: format!("{}/{}", file.dir.as_str(), file.name),
+        plugin: plugin.get_name(),
+        repo: plugin.repo.as_str(),
+        commit: plugin.commit_sha.clone(),
+        source: plugin.source.clone(),
+        temp,
+    }
+}
+
+fn render_plain(owner: &FileOwner) -> String {
+    let temp = if owner.temp { " (temp)" } else { "" };
+    format!(
+        "{} is installed by {}{temp}\n  repo:   {}\n  commit: {}\n  source: {}",
+        owner.file, owner.plugin, owner.repo, owner.commit, owner.source
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{PluginRepo, TargetDir};
+
+    fn plugin(repo: &str, files: &[(TargetDir, &str)]) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "0123456789abcdef".into(),
+            files: files
+                .iter()
+                .map(|(dir, name)| PluginFile {
+                    dir: dir.clone(),
+                    name: name.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn find_owners_matches_paths_and_bare_names() {
+        let lock_file = LockFile {
+            version: 2,
+            plugins: vec![
+                plugin(
+                    "IlanCosman/tide",
+                    &[
+                        (TargetDir::ConfD, "tide.fish"),
+                        (TargetDir::Functions, "tide.fish"),
+                    ],
+                ),
+                plugin("owner/other", &[(TargetDir::ConfD, "other.fish")]),
+            ],
+        };
+        let config_dir = path::Path::new("/home/u/.config/fish");
+        let files = |raw: &str| -> Vec<String> {
+            find_owners(&lock_file, &Query::parse(raw, config_dir), false)
+                .into_iter()
+                .map(|owner| format!("{} {}", owner.repo, owner.file))
+                .collect()
+        };
+
+        assert_eq!(
+            files("conf.d/tide.fish"),
+            vec!["IlanCosman/tide conf.d/tide.fish"]
+        );
+        assert_eq!(
+            files("/home/u/.config/fish/conf.d/other.fish"),
+            vec!["owner/other conf.d/other.fish"]
+        );
+        assert_eq!(
+            files("tide"),
+            vec![
+                "IlanCosman/tide conf.d/tide.fish",
+                "IlanCosman/tide functions/tide.fish"
+            ]
+        );
+        assert_eq!(
+            files("./conf.d/other.fish"),
+            vec!["owner/other conf.d/other.fish"]
+        );
+        assert!(files("completions/tide.fish").is_empty());
+        assert!(files("/elsewhere/conf.d/tide.fish").is_empty());
+    }
+
+    #[test]
+    fn render_plain_lists_commit_and_source() {
+        let owner = owner(
+            &plugin("owner/repo", &[]),
+            &PluginFile {
+                dir: TargetDir::ConfD,
+                name: "repo.fish".into(),
+            },
+            true,
+        );
+        assert_eq!(
+            render_plain(&owner),
+            "conf.d/repo.fish is installed by repo (temp)\n  repo:   owner/repo\n  commit: 0123456789abcdef\n  source: https://github.com/owner/repo"
+        );
+    }
+}
@@ -9,13 +9,151 @@ use crate::resolver::{ref_kind_to_repo_source, ref_kind_to_url_source};
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct Config {
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    pub(crate) settings: Settings,
+    /// Bare names accepted by `pez install <name>`, mapped to `owner/repo` or
+    /// `host/owner/repo`. Entries override the bundled aliases.
+    #[cfg_attr(
+        feature = "schema-gen",
+        schemars(with = "std::collections::BTreeMap<String, String>")
+    )]
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub(crate) aliases: std::collections::BTreeMap<String, PluginRepo>,
+    #[serde(default, skip_serializing_if = "DoctorConfig::is_default")]
+    pub(crate) doctor: DoctorConfig,
     pub(crate) plugins: Option<Vec<PluginSpec>>,
 }
 
+/// The `[doctor]` table.
 #[cfg_attr(feature = "schema-gen", derive(schemars::JsonSchema))]
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DoctorConfig {
+    /// Names of checks `pez doctor` leaves out of its report and exit status, e.g.
+    /// `fish_config_dir`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) ignore: Vec<String>,
+}
+
+impl DoctorConfig {
+    pub(crate) fn is_default(&self) -> bool {
+        self == &DoctorConfig::default()
+    }
+}
+
+/// Global behaviour toggles from the `[settings]` table.
+#[cfg_attr(feature = "schema-gen", derive(schemars::JsonSchema))]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Settings {
+    /// Send a terminal (OSC 9) or desktop notification when a long install/upgrade finishes.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) notify: bool,
+    /// Store git objects of remote clones in one shared repository (via git alternates)
+    /// so plugins with common history, such as forks, are stored once.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) shared_objects: bool,
+    /// Warn when a plugin's completion or function shadows one shipped in fish's
+    /// vendor directories (for example by Homebrew).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) vendor_conflicts: bool,
+    /// Record installs, upgrades and uninstalls in a local log summarized by `pez stats`.
+    /// Nothing is sent anywhere.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) history: bool,
+    /// After commands that change installed plugins, set the fish universal variables
+    /// `pez_plugins` and `pez_plugin_<name>_version`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) universal_vars: bool,
+    /// After commands that change pez.toml or installed plugins, rewrite fisher's
+    /// `fish_plugins` to match, as `pez export` would.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) sync_fish_plugins: bool,
+    /// Extra repos that `pez install` refuses because they are not fish plugins.
+    #[cfg_attr(feature = "schema-gen", schemars(with = "Vec<String>"))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) blocked_repos: Vec<PluginRepo>,
+    /// Seconds one plugin's clone or fetch may take before pez gives up on the remote
+    /// (default 300; 0 waits forever).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) clone_timeout: Option<u64>,
+    /// Per-host overrides keyed by host name, e.g. `[settings.hosts."github.com"]`.
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub(crate) hosts: std::collections::BTreeMap<String, HostSettings>,
+}
+
+/// Settings for one git host from `[settings.hosts."<host>"]`.
+#[cfg_attr(feature = "schema-gen", derive(schemars::JsonSchema))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct HostSettings {
+    /// Maximum simultaneous clones/fetches against this host (default 4).
+    #[cfg_attr(feature = "schema-gen", schemars(range(min = 1)))]
+    pub(crate) max_concurrent: usize,
+}
+
+impl Settings {
+    pub(crate) fn is_default(&self) -> bool {
+        self == &Settings::default()
+    }
+
+    /// Why `repo` must not be installed as a plugin, if it is one of the built-in
+    /// non-plugin repos or listed in `blocked_repos`.
+    pub(crate) fn non_plugin_reason(&self, repo: &PluginRepo) -> Option<String> {
+        let key = blocklist_key(repo);
+        NON_PLUGIN_REPOS
+            .iter()
+            .find(|(blocked, _)| blocked.parse().is_ok_and(|b| blocklist_key(&b) == key))
+            .map(|(_, reason)| reason.to_string())
+            .or_else(|| {
+                self.blocked_repos
+                    .iter()
+                    .any(|blocked| blocklist_key(blocked) == key)
+                    .then(|| "it is listed in [settings] blocked_repos".to_string())
+            })
+    }
+}
+
+/// Repos people install by mistake, with what to do instead.
+const NON_PLUGIN_REPOS: &[(&str, &str)] = &[(
+    "tetzng/pez",
+    "pez is the plugin manager itself; to update it, run `cargo install pez` or use your package manager",
+)];
+
+/// GitHub is the default host, so `github.com/owner/repo` and `owner/repo` compare equal.
+fn blocklist_key(repo: &PluginRepo) -> String {
+    let host = repo
+        .host
+        .as_deref()
+        .filter(|host| !host.eq_ignore_ascii_case("github.com"))
+        .unwrap_or_default();
+    format!("{host}/{}", repo.owner_repo_path()).to_ascii_lowercase()
+}
+
+#[cfg_attr(feature = "schema-gen", derive(schemars::JsonSchema))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub(crate) struct PluginSpec {
     pub(crate) name: Option<String>,
+    /// Allow semver pre-release tags when resolving `version` selectors.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) prerelease: bool,
+    /// Keep the clone and lock entry but remove the plugin's files from the fish config
+    /// directory until `pez enable` copies them back.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) disabled: bool,
+    /// For gist plugins, the fish directory the gist's top-level files are copied into
+    /// (default: `functions`).
+    #[cfg_attr(feature = "schema-gen", schemars(with = "Option<String>"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) dir: Option<crate::models::TargetDir>,
+    /// Custom fish event names (e.g. `omf_install`) emitted instead of the conf.d
+    /// `<stem>_<event>` names for the lifecycle events they end with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) events: Vec<String>,
+    /// Installed files to leave out, as `<dir>/<file>` (e.g. `conf.d/keybindings.fish`),
+    /// while the rest of the plugin stays active.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) disable_files: Vec<String>,
     #[serde(flatten)]
     pub(crate) source: PluginSource,
 }
@@ -56,7 +194,12 @@ pub(crate) enum PluginSource {
 }
 
 pub(crate) fn init() -> Config {
-    Config { plugins: None }
+    Config {
+        plugins: None,
+        settings: Default::default(),
+        aliases: Default::default(),
+        doctor: Default::default(),
+    }
 }
 
 pub(crate) fn load(path: &path::PathBuf) -> anyhow::Result<Config> {
@@ -64,7 +207,7 @@ pub(crate) fn load(path: &path::PathBuf) -> anyhow::Result<Config> {
     parse_config(&content).with_context(|| format!("Invalid config file: {}", path.display()))
 }
 
-fn parse_config(content: &str) -> anyhow::Result<Config> {
+pub(crate) fn parse_config(content: &str) -> anyhow::Result<Config> {
     let config: Config = toml::from_str(content)?;
     config.validate()?;
     Ok(config)
@@ -85,10 +228,57 @@ impl Config {
                     .validate()
                     .with_context(|| format!("invalid plugins[{idx}]"))?;
             }
+            validate_unique_names(plugins)?;
+        }
+        for (host, settings) in &self.settings.hosts {
+            if settings.max_concurrent == 0 {
+                anyhow::bail!(
+                    "invalid settings.hosts.\"{host}\": max_concurrent must be at least 1"
+                );
+            }
+        }
+        for name in self.aliases.keys() {
+            if !crate::aliases::is_alias_name(name) {
+                anyhow::bail!(
+                    "invalid alias '{name}': aliases must be bare names without '/', '@', ':', or whitespace"
+                );
+            }
         }
         Ok(())
     }
 
+    /// Find the spec whose derived repo matches `plugin_repo`.
+    pub(crate) fn find_plugin_spec(&self, plugin_repo: &PluginRepo) -> Option<&PluginSpec> {
+        self.plugins.as_ref()?.iter().find(|spec| {
+            spec.get_plugin_repo()
+                .is_ok_and(|repo| &repo == plugin_repo)
+        })
+    }
+
+    /// Whether `plugin_repo` is declared with `disabled = true`.
+    pub(crate) fn is_disabled(&self, plugin_repo: &PluginRepo) -> bool {
+        self.find_plugin_spec(plugin_repo)
+            .is_some_and(|spec| spec.disabled)
+    }
+
+    /// Mutable access to the spec whose derived repo matches `plugin_repo`.
+    pub(crate) fn find_plugin_spec_mut(
+        &mut self,
+        plugin_repo: &PluginRepo,
+    ) -> Option<&mut PluginSpec> {
+        self.plugins.as_mut()?.iter_mut().find(|spec| {
+            spec.get_plugin_repo()
+                .is_ok_and(|repo| &repo == plugin_repo)
+        })
+    }
+
+    /// Display name for `plugin_repo`: the explicit `name` if configured, else the repo name.
+    pub(crate) fn plugin_name_for_repo(&self, plugin_repo: &PluginRepo) -> String {
+        self.find_plugin_spec(plugin_repo)
+            .and_then(|spec| spec.get_name().ok())
+            .unwrap_or_else(|| plugin_repo.repo.clone())
+    }
+
     /// Ensure that the config contains a plugin entry derived from the provided resolved target.
     /// Returns true when a new entry is inserted.
     pub(crate) fn ensure_plugin_from_resolved(&mut self, resolved: &ResolvedInstallTarget) -> bool {
@@ -112,6 +302,7 @@ impl Config {
             source: plugin_repo.default_remote_source(),
             ref_kind: crate::resolver::RefKind::None,
             is_local: false,
+            channel: crate::resolver::Channel::Stable,
         };
         self.ensure_plugin_from_resolved(&resolved)
     }
@@ -119,6 +310,15 @@ impl Config {
 
 impl PluginSpec {
     pub(crate) fn validate(&self) -> anyhow::Result<()> {
+        if self.dir.is_some() && !self.get_plugin_repo()?.is_gist() {
+            anyhow::bail!("dir is only supported for gist plugins");
+        }
+        for name in &self.events {
+            validate_event_name(name)?;
+        }
+        for file in &self.disable_files {
+            validate_disable_file(file)?;
+        }
         match &self.source {
             PluginSource::Repo {
                 version,
@@ -135,19 +335,51 @@ impl PluginSpec {
                 ..
             } => {
                 let _ = pick_single_ref(version, branch, tag, commit)?;
+                for (field, value) in [
+                    ("version", version),
+                    ("branch", branch),
+                    ("tag", tag),
+                    ("commit", commit),
+                ] {
+                    if let Some(value) = value
+                        && !(field == "version" && value.eq_ignore_ascii_case("latest"))
+                    {
+                        crate::resolver::normalize_selector(field, value)?;
+                    }
+                }
             }
             PluginSource::Path { path } => {
-                let expanded = expand_tilde(path)?;
+                let expanded = crate::resolver::expand_tilde(path)?;
                 if !expanded.starts_with('/') {
                     anyhow::bail!(
                         "path must be absolute or start with ~/ (after expansion must be absolute)"
                     );
                 }
+                if self.prerelease {
+                    anyhow::bail!("prerelease is only supported for repo and url sources");
+                }
             }
         }
         Ok(())
     }
 
+    /// Whether `disable_files` keeps `<dir>/<name>` out of the fish config directory.
+    pub(crate) fn disables(&self, dir: &crate::models::TargetDir, name: &str) -> bool {
+        self.disable_files
+            .iter()
+            .any(|file| file.split_once('/') == Some((dir.as_str(), name)))
+    }
+
+    /// The `events` overrides that apply to `event`, i.e. those ending in `_<event>`.
+    pub(crate) fn event_overrides(&self, event: &crate::utils::Event) -> Vec<&str> {
+        let suffix = format!("_{event}");
+        self.events
+            .iter()
+            .map(String::as_str)
+            .filter(|name| name.ends_with(&suffix))
+            .collect()
+    }
+
     pub(crate) fn get_name(&self) -> anyhow::Result<String> {
         if let Some(name) = &self.name {
             return Ok(name.clone());
@@ -156,6 +388,51 @@ impl PluginSpec {
         Ok(repo.repo)
     }
 
+    /// Replace any selector with `commit = "<sha>"`. Returns the selector it replaced,
+    /// as `field = "value"`.
+    pub(crate) fn pin_commit(&mut self, sha: &str) -> anyhow::Result<Option<String>> {
+        let previous = self.unpin()?;
+        if let PluginSource::Repo { commit, .. } | PluginSource::Url { commit, .. } =
+            &mut self.source
+        {
+            *commit = Some(sha.to_string());
+        }
+        Ok(previous)
+    }
+
+    /// Clear the version/branch/tag/commit selector. Returns the removed selector as
+    /// `field = "value"`, or `None` when the spec already tracked the default branch.
+    pub(crate) fn unpin(&mut self) -> anyhow::Result<Option<String>> {
+        let (version, branch, tag, commit) = match &mut self.source {
+            PluginSource::Repo {
+                version,
+                branch,
+                tag,
+                commit,
+                ..
+            }
+            | PluginSource::Url {
+                version,
+                branch,
+                tag,
+                commit,
+                ..
+            } => (version, branch, tag, commit),
+            PluginSource::Path { path } => {
+                anyhow::bail!("{path} is a local path source and cannot be pinned")
+            }
+        };
+        let removed = [
+            ("version", version.take()),
+            ("branch", branch.take()),
+            ("tag", tag.take()),
+            ("commit", commit.take()),
+        ]
+        .into_iter()
+        .find_map(|(field, value)| value.map(|v| format!("{field} = \"{v}\"")));
+        Ok(removed)
+    }
+
     /// Derive a PluginRepo (owner/repo) for use as a stable identifier and data dir name.
     /// - GitHub: uses provided owner/repo
     /// - Git URL: attempts to parse last two path segments as owner/repo
@@ -164,40 +441,10 @@ impl PluginSpec {
         match &self.source {
             PluginSource::Repo { repo, .. } => Ok(repo.clone()),
             PluginSource::Url { url, .. } => {
-                if let Some(repo) = crate::models::PluginRepo::from_remote_url(url) {
-                    return Ok(repo);
-                }
-                let repo_name = url
-                    .trim_end_matches('/')
-                    .trim_end_matches(".git")
-                    .rsplit('/')
-                    .next()
-                    .unwrap_or("repo")
-                    .to_string();
-                let owner = url
-                    .trim_end_matches('/')
-                    .rsplit('/')
-                    .nth(1)
-                    .unwrap_or("owner")
-                    .to_string();
-                Ok(crate::models::PluginRepo {
-                    host: None,
-                    owner,
-                    repo: repo_name,
-                })
+                crate::resolver::repo_for_url(&crate::resolver::normalize_url(url))
             }
             PluginSource::Path { path } => {
-                let expanded = expand_tilde(path)?;
-                let name = std::path::Path::new(&expanded)
-                    .file_name()
-                    .and_then(|s| s.to_str())
-                    .ok_or_else(|| anyhow::anyhow!("Invalid local path: {expanded}"))?
-                    .to_string();
-                Ok(crate::models::PluginRepo {
-                    host: None,
-                    owner: "local".to_string(),
-                    repo: name,
-                })
+                crate::resolver::repo_for_path(&crate::resolver::expand_tilde(path)?)
             }
         }
     }
@@ -214,12 +461,13 @@ impl PluginSpec {
                 commit,
             } => {
                 let src = plugin_repo.default_remote_source();
-                let refspec = pick_single_ref(version, branch, tag, commit)?;
+                let ref_kind = pick_single_ref(version, branch, tag, commit)?;
                 Ok(crate::models::ResolvedInstallTarget {
                     plugin_repo,
                     source: src,
-                    ref_kind: crate::resolver::RefKind::from(refspec),
+                    ref_kind,
                     is_local: false,
+                    channel: crate::resolver::Channel::from_prerelease(self.prerelease),
                 })
             }
             PluginSource::Url {
@@ -229,20 +477,18 @@ impl PluginSpec {
                 tag,
                 commit,
             } => {
-                let mut normalized = url.clone();
-                if !normalized.contains("://") {
-                    normalized = format!("https://{normalized}");
-                }
-                let refspec = pick_single_ref(version, branch, tag, commit)?;
+                let normalized = crate::resolver::normalize_url(url);
+                let ref_kind = pick_single_ref(version, branch, tag, commit)?;
                 Ok(crate::models::ResolvedInstallTarget {
                     plugin_repo,
                     source: normalized,
-                    ref_kind: crate::resolver::RefKind::from(refspec),
+                    ref_kind,
                     is_local: false,
+                    channel: crate::resolver::Channel::from_prerelease(self.prerelease),
                 })
             }
             PluginSource::Path { path } => {
-                let expanded = expand_tilde(path)?;
+                let expanded = crate::resolver::expand_tilde(path)?;
                 if !expanded.starts_with('/') {
                     anyhow::bail!(
                         "path must be absolute or start with ~/ (after expansion must be absolute)"
@@ -253,6 +499,7 @@ impl PluginSpec {
                     source: expanded,
                     ref_kind: crate::resolver::RefKind::None,
                     is_local: true,
+                    channel: crate::resolver::Channel::Stable,
                 })
             }
         }
@@ -272,7 +519,15 @@ impl PluginSpec {
             }
         };
 
-        PluginSpec { name: None, source }
+        PluginSpec {
+            name: None,
+            prerelease: resolved.channel == crate::resolver::Channel::Prerelease,
+            disabled: false,
+            source,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
+        }
     }
 }
 
@@ -295,7 +550,12 @@ mod internal_tests {
         };
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: s,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let r = spec.to_resolved().unwrap();
         assert_eq!(r.source, "https://github.com/o/r");
@@ -313,11 +573,31 @@ mod internal_tests {
         };
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: s,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let r = spec.to_resolved().unwrap();
         assert_eq!(r.source, "https://gitlab.com/o/r");
+        assert_eq!(r.plugin_repo.as_str(), "gitlab.com/o/r");
         matches!(r.ref_kind, crate::resolver::RefKind::Version(_));
+
+        let scp = PluginSpec {
+            source: PluginSource::Url {
+                url: "git@gitlab.com:o/r.git".into(),
+                version: None,
+                branch: None,
+                tag: None,
+                commit: None,
+            },
+            ..spec
+        };
+        let r = scp.to_resolved().unwrap();
+        assert_eq!(r.source, "git@gitlab.com:o/r.git");
+        assert_eq!(r.plugin_repo.as_str(), "gitlab.com/o/r");
     }
 
     #[test]
@@ -327,7 +607,12 @@ mod internal_tests {
         };
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: s,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let err = spec.to_resolved().unwrap_err();
         assert!(err.to_string().contains("absolute"));
@@ -348,7 +633,12 @@ mod internal_tests {
         };
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: s,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let err = spec.to_resolved().unwrap_err();
         assert!(err.to_string().contains("Multiple version selectors"));
@@ -365,6 +655,7 @@ mod internal_tests {
             source: "https://github.com/o/r".into(),
             ref_kind: crate::resolver::RefKind::Branch("dev".into()),
             is_local: false,
+            channel: crate::resolver::Channel::Stable,
         };
 
         let spec = PluginSpec::from_resolved(&resolved);
@@ -399,6 +690,7 @@ mod internal_tests {
             source: "https://gitlab.com/o/r".into(),
             ref_kind: crate::resolver::RefKind::Tag("v1.0.0".into()),
             is_local: false,
+            channel: crate::resolver::Channel::Stable,
         };
 
         let spec = PluginSpec::from_resolved(&resolved);
@@ -434,6 +726,7 @@ mod internal_tests {
             source: "/tmp/tool".into(),
             ref_kind: crate::resolver::RefKind::None,
             is_local: true,
+            channel: crate::resolver::Channel::Stable,
         };
 
         let spec = PluginSpec::from_resolved(&resolved);
@@ -446,7 +739,12 @@ mod internal_tests {
 
     #[test]
     fn ensure_plugin_from_resolved_inserts_once() {
-        let mut config = Config { plugins: None };
+        let mut config = Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        };
         let resolved = ResolvedInstallTarget {
             plugin_repo: PluginRepo {
                 host: None,
@@ -456,6 +754,7 @@ mod internal_tests {
             source: "https://github.com/o/r".into(),
             ref_kind: crate::resolver::RefKind::None,
             is_local: false,
+            channel: crate::resolver::Channel::Stable,
         };
 
         assert!(config.ensure_plugin_from_resolved(&resolved));
@@ -466,7 +765,12 @@ mod internal_tests {
 
     #[test]
     fn ensure_plugin_for_repo_inserts_default_spec() {
-        let mut config = Config { plugins: None };
+        let mut config = Config {
+            plugins: None,
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        };
         let repo = PluginRepo {
             host: None,
             owner: "o".into(),
@@ -484,27 +788,76 @@ mod internal_tests {
         }
     }
 }
-fn expand_tilde(p: &str) -> anyhow::Result<String> {
-    if let Some(stripped) = p.strip_prefix("~/") {
-        let home = std::env::var_os("HOME").ok_or_else(|| anyhow::anyhow!("HOME not set"))?;
-        Ok(std::path::Path::new(&home)
-            .join(stripped)
-            .to_string_lossy()
-            .to_string())
-    } else if p == "~" {
-        let home = std::env::var_os("HOME").ok_or_else(|| anyhow::anyhow!("HOME not set"))?;
-        Ok(std::path::PathBuf::from(home).to_string_lossy().to_string())
-    } else {
-        Ok(p.to_string())
+/// Explicit names must be non-empty and must not collide with the effective name
+/// (explicit or repo-derived) of any other entry.
+fn validate_unique_names(plugins: &[PluginSpec]) -> anyhow::Result<()> {
+    let effective: Vec<Option<String>> = plugins.iter().map(|p| p.get_name().ok()).collect();
+    for (idx, plugin) in plugins.iter().enumerate() {
+        let Some(name) = &plugin.name else {
+            continue;
+        };
+        if name.trim().is_empty() {
+            anyhow::bail!("invalid plugins[{idx}]: name must not be empty");
+        }
+        if let Some(other) = effective
+            .iter()
+            .enumerate()
+            .position(|(other_idx, other)| other_idx != idx && other.as_deref() == Some(name))
+        {
+            anyhow::bail!("duplicate plugin name '{name}' in plugins[{idx}] and plugins[{other}]");
+        }
+    }
+    Ok(())
+}
+
+/// Event overrides are passed to `fish -c emit`, so keep them to plain identifiers
+/// and require a lifecycle suffix so pez knows when to emit them.
+fn validate_event_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        anyhow::bail!(
+            "invalid event name '{name}': use letters, digits, '_' or '-' (e.g. \"omf_install\")"
+        );
+    }
+    let suffixes = ["_install", "_update", "_uninstall"];
+    if !suffixes
+        .iter()
+        .any(|suffix| name.len() > suffix.len() && name.ends_with(suffix))
+    {
+        anyhow::bail!(
+            "invalid event name '{name}': must end with _install, _update, or _uninstall"
+        );
+    }
+    Ok(())
+}
+
+/// `disable_files` entries name an installed file relative to the fish config directory.
+fn validate_disable_file(file: &str) -> anyhow::Result<()> {
+    let valid = file.split_once('/').is_some_and(|(dir, name)| {
+        crate::models::TargetDir::all()
+            .iter()
+            .any(|target| target.as_str() == dir)
+            && !name.is_empty()
+            && name.split('/').all(|part| !part.is_empty() && part != "..")
+    });
+    if !valid {
+        anyhow::bail!(
+            "invalid disable_files entry '{file}': use <dir>/<file> with dir functions, completions, conf.d, or themes (e.g. \"conf.d/keybindings.fish\")"
+        );
     }
+    Ok(())
 }
 
+/// The `RefKind` for a spec's selector fields; at most one may be set.
 fn pick_single_ref(
     version: &Option<String>,
     branch: &Option<String>,
     tag: &Option<String>,
     commit: &Option<String>,
-) -> anyhow::Result<Option<String>> {
+) -> anyhow::Result<RefKind> {
     let mut vals = vec![];
     if let Some(v) = version {
         vals.push(("version", v.clone()));
@@ -524,7 +877,13 @@ fn pick_single_ref(
             "Multiple version selectors set: {kinds}. Please specify only one of version, branch, tag, or commit."
         ));
     }
-    Ok(vals.into_iter().next().map(|(_, v)| v))
+    Ok(match vals.into_iter().next() {
+        None => RefKind::None,
+        Some(("branch", v)) => RefKind::Branch(v),
+        Some(("tag", v)) => RefKind::Tag(v),
+        Some(("commit", v)) => RefKind::Commit(v),
+        Some((_, v)) => RefKind::from(Some(v)),
+    })
 }
 
 impl From<Option<String>> for crate::resolver::RefKind {
@@ -560,33 +919,6 @@ fn ref_to_kind(val: Option<String>) -> RefKind {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests_support::log::env_lock;
-    use std::ffi::OsString;
-
-    struct EnvGuard {
-        vars: Vec<(&'static str, Option<OsString>)>,
-    }
-
-    impl EnvGuard {
-        fn capture(keys: &[&'static str]) -> Self {
-            let vars = keys
-                .iter()
-                .map(|&key| (key, std::env::var_os(key)))
-                .collect();
-            Self { vars }
-        }
-    }
-
-    impl Drop for EnvGuard {
-        fn drop(&mut self) {
-            for (key, value) in &self.vars {
-                match value {
-                    Some(val) => unsafe { std::env::set_var(key, val.clone()) },
-                    None => unsafe { std::env::remove_var(key) },
-                }
-            }
-        }
-    }
 
     #[test]
     fn repo_to_resolved_latest() {
@@ -603,7 +935,12 @@ mod tests {
         };
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: s,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let r = spec.to_resolved().unwrap();
         assert_eq!(r.source, "https://github.com/o/r");
@@ -621,7 +958,12 @@ mod tests {
         };
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: s,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let r = spec.to_resolved().unwrap();
         assert_eq!(r.source, "https://gitlab.com/o/r");
@@ -635,7 +977,12 @@ mod tests {
         };
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: s,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let err = spec.to_resolved().unwrap_err();
         assert!(err.to_string().contains("absolute"));
@@ -656,7 +1003,12 @@ mod tests {
         };
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: s,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         let err = spec.to_resolved().unwrap_err();
         assert!(err.to_string().contains("Multiple version selectors"));
@@ -666,6 +1018,8 @@ mod tests {
     fn get_name_prefers_explicit_name() {
         let spec = PluginSpec {
             name: Some("custom-name".into()),
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: crate::models::PluginRepo {
                     host: None,
@@ -677,6 +1031,9 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         assert_eq!(spec.get_name().unwrap(), "custom-name");
     }
@@ -685,6 +1042,8 @@ mod tests {
     fn get_name_falls_back_to_repo_name() {
         let spec = PluginSpec {
             name: None,
+            prerelease: false,
+            disabled: false,
             source: PluginSource::Repo {
                 repo: crate::models::PluginRepo {
                     host: None,
@@ -696,41 +1055,263 @@ mod tests {
                 tag: None,
                 commit: None,
             },
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
         };
         assert_eq!(spec.get_name().unwrap(), "r");
     }
 
     #[test]
-    fn expand_tilde_resolves_home_variants() {
-        let _lock = env_lock().lock().unwrap();
-        let _guard = EnvGuard::capture(&["HOME"]);
-        let temp = tempfile::tempdir().unwrap();
+    fn parse_config_rejects_unknown_top_level_field() {
+        let content = r#"
+plugins = []
+unexpected = "value"
+"#;
+        let err = parse_config(content).unwrap_err();
+        assert!(err.to_string().contains("unknown field `unexpected`"));
+    }
 
-        unsafe {
-            std::env::set_var("HOME", temp.path());
-        }
+    #[test]
+    fn parse_config_reads_settings_and_round_trips() {
+        let content = r#"
+[settings]
+notify = true
 
-        let expanded = expand_tilde("~/plugins").unwrap();
+[[plugins]]
+repo = "owner/repo"
+"#;
+        let config = parse_config(content).unwrap();
+        assert!(config.settings.notify);
+        let saved = toml::to_string(&config).unwrap();
+        assert!(saved.contains("[settings]"));
+        assert!(parse_config(&saved).unwrap().settings.notify);
+
+        let plain = parse_config("plugins = []").unwrap();
+        assert!(!plain.settings.notify);
+        assert!(!toml::to_string(&plain).unwrap().contains("settings"));
+        assert!(parse_config("[settings]\nunknown = 1").is_err());
+    }
+
+    #[test]
+    fn parse_config_rejects_malformed_selectors() {
+        let err =
+            parse_config("[[plugins]]\nrepo = \"o/r\"\ncommit = \"not-a-sha\"\n").unwrap_err();
+        assert!(format!("{err:#}").contains("7 to 40 hexadecimal"));
+        let err =
+            parse_config("[[plugins]]\nrepo = \"o/r\"\nbranch = \"my branch\"\n").unwrap_err();
+        assert!(format!("{err:#}").contains("must not contain whitespace"));
+        assert!(parse_config("[[plugins]]\nrepo = \"o/r\"\nversion = \"latest\"\n").is_ok());
+    }
+
+    #[test]
+    fn parse_config_reads_and_validates_aliases() {
+        let config = parse_config("[aliases]\nz = \"me/z\"\n").unwrap();
+        assert_eq!(config.aliases["z"].as_str(), "me/z");
+        assert!(toml::to_string(&config).unwrap().contains("[aliases]"));
+        assert!(
+            !toml::to_string(&parse_config("plugins = []").unwrap())
+                .unwrap()
+                .contains("aliases")
+        );
+
+        assert!(parse_config("[aliases]\nz = \"not-a-repo\"\n").is_err());
+        let err = parse_config("[aliases]\n\"me/z\" = \"me/z\"\n").unwrap_err();
+        assert!(err.to_string().contains("invalid alias 'me/z'"));
+    }
+
+    #[test]
+    fn parse_config_accepts_dir_only_for_gists() {
+        let config = parse_config(
+            r#"
+[[plugins]]
+repo = "gist.github.com/alice/abc123"
+dir = "conf.d"
+"#,
+        )
+        .unwrap();
+        let spec = &config.plugins.unwrap()[0];
+        assert_eq!(spec.dir, Some(crate::models::TargetDir::ConfD));
+
+        let err = parse_config(
+            r#"
+[[plugins]]
+repo = "owner/repo"
+dir = "conf.d"
+"#,
+        )
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("only supported for gist plugins"));
+    }
+
+    #[test]
+    fn parse_config_reads_prerelease_channel() {
+        let content = r#"
+[[plugins]]
+repo = "owner/beta"
+version = "2"
+prerelease = true
+
+[[plugins]]
+repo = "owner/stable"
+"#;
+        let config = parse_config(content).unwrap();
+        let specs = config.plugins.unwrap();
+        assert_eq!(
+            specs[0].to_resolved().unwrap().channel,
+            crate::resolver::Channel::Prerelease
+        );
         assert_eq!(
-            expanded,
-            temp.path().join("plugins").to_string_lossy().to_string()
+            specs[1].to_resolved().unwrap().channel,
+            crate::resolver::Channel::Stable
         );
+        let saved = toml::to_string(&specs[1]).unwrap();
+        assert!(!saved.contains("prerelease"));
+    }
 
-        let home = expand_tilde("~").unwrap();
-        assert_eq!(home, temp.path().to_string_lossy().to_string());
+    #[test]
+    fn parse_config_reads_and_validates_host_settings() {
+        let config = parse_config(
+            r#"
+[settings.hosts."github.com"]
+max_concurrent = 2
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.settings.hosts["github.com"].max_concurrent, 2);
+        assert!(
+            toml::to_string(&config)
+                .unwrap()
+                .contains("[settings.hosts.\"github.com\"]")
+        );
 
-        let unchanged = expand_tilde("/absolute/path").unwrap();
-        assert_eq!(unchanged, "/absolute/path");
+        let err =
+            parse_config("[settings.hosts.\"github.com\"]\nmax_concurrent = 0\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("max_concurrent must be at least 1")
+        );
     }
 
     #[test]
-    fn parse_config_rejects_unknown_top_level_field() {
+    fn non_plugin_reason_covers_builtin_and_configured_repos() {
+        let config = parse_config(
+            r#"
+[settings]
+blocked_repos = ["gitlab.com/owner/dotfiles"]
+"#,
+        )
+        .unwrap();
+        let reason = |repo: &str| config.settings.non_plugin_reason(&repo.parse().unwrap());
+
+        assert!(reason("tetzng/pez").unwrap().contains("cargo install pez"));
+        assert!(reason("github.com/Tetzng/PEZ").is_some());
+        assert!(reason("GitLab.com/owner/dotfiles").is_some());
+        assert!(reason("owner/dotfiles").is_none());
+        assert!(reason("owner/plugin").is_none());
+        assert!(parse_config("[settings]\nblocked_repos = [\"nope\"]\n").is_err());
+    }
+
+    #[test]
+    fn parse_config_reads_and_validates_event_overrides() {
+        let config = parse_config(
+            r#"
+[[plugins]]
+repo = "owner/omf-plugin"
+events = ["omf_install", "omf_update", "legacy-cleanup_uninstall"]
+"#,
+        )
+        .unwrap();
+        let spec = &config.plugins.unwrap()[0];
+        assert_eq!(
+            spec.event_overrides(&crate::utils::Event::Install),
+            vec!["omf_install"]
+        );
+        assert_eq!(
+            spec.event_overrides(&crate::utils::Event::Uninstall),
+            vec!["legacy-cleanup_uninstall"]
+        );
+
+        for bad in [r#"["omf"]"#, r#"["_install"]"#, r#"["omf; rm_install"]"#] {
+            let err = parse_config(&format!(
+                "[[plugins]]\nrepo = \"owner/repo\"\nevents = {bad}\n"
+            ))
+            .unwrap_err();
+            assert!(format!("{err:#}").contains("invalid event name"), "{bad}");
+        }
+    }
+
+    #[test]
+    fn parse_config_reads_and_validates_disable_files() {
+        let config = parse_config(
+            r#"
+[[plugins]]
+repo = "PatrickF1/fzf.fish"
+disable_files = ["conf.d/fzf.fish"]
+"#,
+        )
+        .unwrap();
+        let spec = &config.plugins.unwrap()[0];
+        assert!(spec.disables(&crate::models::TargetDir::ConfD, "fzf.fish"));
+        assert!(!spec.disables(&crate::models::TargetDir::Functions, "fzf.fish"));
+
+        for bad in [
+            r#"["fzf.fish"]"#,
+            r#"["bin/fzf"]"#,
+            r#"["conf.d/../x.fish"]"#,
+        ] {
+            let err = parse_config(&format!(
+                "[[plugins]]\nrepo = \"owner/repo\"\ndisable_files = {bad}\n"
+            ))
+            .unwrap_err();
+            assert!(
+                format!("{err:#}").contains("invalid disable_files entry"),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_config_rejects_prerelease_for_path_source() {
         let content = r#"
-plugins = []
-unexpected = "value"
+[[plugins]]
+path = "/opt/plugin"
+prerelease = true
 "#;
         let err = parse_config(content).unwrap_err();
-        assert!(err.to_string().contains("unknown field `unexpected`"));
+        assert!(format!("{err:#}").contains("prerelease is only supported"));
+    }
+
+    #[test]
+    fn parse_config_rejects_duplicate_plugin_names() {
+        let content = r#"
+[[plugins]]
+name = "shared"
+repo = "owner/a"
+
+[[plugins]]
+repo = "owner/shared"
+"#;
+        let err = parse_config(content).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("duplicate plugin name 'shared' in plugins[0] and plugins[1]"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn plugin_name_for_repo_prefers_configured_name() {
+        let content = r#"
+[[plugins]]
+name = "renamed"
+repo = "owner/a"
+"#;
+        let config = parse_config(content).unwrap();
+        let named = PluginRepo::new(None, "owner".into(), "a".into()).unwrap();
+        let unknown = PluginRepo::new(None, "owner".into(), "b".into()).unwrap();
+        assert_eq!(config.plugin_name_for_repo(&named), "renamed");
+        assert_eq!(config.plugin_name_for_repo(&unknown), "b");
     }
 
     #[test]
@@ -753,10 +1334,18 @@ branch = "main"
         let config = Config {
             plugins: Some(vec![PluginSpec {
                 name: None,
+                prerelease: false,
+                disabled: false,
                 source: PluginSource::Path {
                     path: "relative/plugin".to_string(),
                 },
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
             }]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
         };
         let err = config.validate().unwrap_err();
         let msg = err.to_string();
@@ -766,3 +1355,198 @@ branch = "main"
         );
     }
 }
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use crate::resolver::Channel;
+    use proptest::prelude::*;
+
+    fn segment() -> impl Strategy<Value = String> {
+        "[a-z][a-z0-9_-]{0,8}(\\.fish)?"
+    }
+
+    fn plugin_repo() -> impl Strategy<Value = PluginRepo> {
+        (
+            prop::option::of(prop::sample::select(vec!["gitlab.com", "codeberg.org"])),
+            segment(),
+            segment(),
+        )
+            .prop_map(|(host, owner, repo)| {
+                PluginRepo::new(host.map(str::to_string), owner, repo).unwrap()
+            })
+    }
+
+    /// At most one selector, with values `normalize_selector` accepts.
+    fn selector() -> impl Strategy<Value = [Option<String>; 4]> {
+        prop_oneof![
+            Just([None, None, None, None]),
+            prop_oneof![
+                Just("latest".to_string()),
+                "v[0-9]{1,2}(\\.[0-9]{1,2}){0,2}",
+            ]
+            .prop_map(|v| [Some(v), None, None, None]),
+            "[a-z][a-z0-9_-]{0,8}(/[a-z0-9][a-z0-9_-]{0,6})?".prop_map(|b| [
+                None,
+                Some(b),
+                None,
+                None
+            ]),
+            "v?[0-9]{1,2}\\.[0-9]{1,2}\\.[0-9]{1,2}".prop_map(|t| [None, None, Some(t), None]),
+            "[0-9a-f]{7,40}".prop_map(|c| [None, None, None, Some(c)]),
+        ]
+    }
+
+    fn plugin_source() -> impl Strategy<Value = PluginSource> {
+        prop_oneof![
+            (plugin_repo(), selector()).prop_map(|(repo, [version, branch, tag, commit])| {
+                PluginSource::Repo {
+                    repo,
+                    version,
+                    branch,
+                    tag,
+                    commit,
+                }
+            }),
+            (plugin_repo(), any::<bool>(), selector()).prop_map(
+                |(repo, scheme, [version, branch, tag, commit])| {
+                    let url = repo.default_remote_source();
+                    PluginSource::Url {
+                        url: if scheme {
+                            url
+                        } else {
+                            url.trim_start_matches("https://").to_string()
+                        },
+                        version,
+                        branch,
+                        tag,
+                        commit,
+                    }
+                }
+            ),
+            (prop::bool::ANY, prop::collection::vec(segment(), 1..4)).prop_map(|(tilde, parts)| {
+                PluginSource::Path {
+                    path: format!("{}/{}", if tilde { "~" } else { "" }, parts.join("/")),
+                }
+            }),
+        ]
+    }
+
+    fn plugin_spec() -> impl Strategy<Value = PluginSpec> {
+        (
+            plugin_source(),
+            any::<bool>(),
+            any::<bool>(),
+            prop::collection::vec(
+                (
+                    "[a-z][a-z0-9_-]{0,8}",
+                    prop::sample::select(vec!["install", "update", "uninstall"]),
+                )
+                    .prop_map(|(name, event)| format!("{name}_{event}")),
+                0..3,
+            ),
+        )
+            .prop_map(|(source, prerelease, disabled, events)| PluginSpec {
+                name: None,
+                prerelease: prerelease && !matches!(source, PluginSource::Path { .. }),
+                disabled,
+                dir: None,
+                events,
+                disable_files: vec![],
+                source,
+            })
+    }
+
+    fn selector_count(source: &PluginSource) -> usize {
+        match source {
+            PluginSource::Repo {
+                version,
+                branch,
+                tag,
+                commit,
+                ..
+            }
+            | PluginSource::Url {
+                version,
+                branch,
+                tag,
+                commit,
+                ..
+            } => [version, branch, tag, commit]
+                .iter()
+                .filter(|v| v.is_some())
+                .count(),
+            PluginSource::Path { .. } => 0,
+        }
+    }
+
+    proptest! {
+        // Each case parses repo segments with fresh regexes; keep the suite fast.
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn config_round_trips_through_toml(specs in prop::collection::vec(plugin_spec(), 0..6)) {
+            // Give every entry a unique explicit name so generated repos never collide.
+            let specs: Vec<PluginSpec> = specs
+                .into_iter()
+                .enumerate()
+                .map(|(idx, spec)| PluginSpec { name: Some(format!("p{idx}")), ..spec })
+                .collect();
+            let config = Config {
+                plugins: Some(specs),
+                settings: Default::default(),
+                aliases: Default::default(),
+                doctor: Default::default(),
+            };
+            let text = toml::to_string(&config).unwrap();
+            let parsed = parse_config(&text).unwrap();
+            prop_assert_eq!(parsed.plugins, config.plugins, "{}", text);
+        }
+
+        #[test]
+        fn resolver_preserves_identity_and_selectors(spec in plugin_spec()) {
+            spec.validate().unwrap();
+            prop_assert!(selector_count(&spec.source) <= 1);
+
+            let resolved = spec.to_resolved().unwrap();
+            prop_assert_eq!(&resolved.plugin_repo, &spec.get_plugin_repo().unwrap());
+            prop_assert_eq!(
+                resolved.plugin_repo.as_str().parse::<PluginRepo>().unwrap(),
+                resolved.plugin_repo.clone()
+            );
+            prop_assert_eq!(resolved.channel == Channel::Prerelease, spec.prerelease);
+            if resolved.is_local {
+                prop_assert!(resolved.source.starts_with('/'), "{}", resolved.source);
+            }
+
+            // Recording a resolved target and resolving it again is stable.
+            let recorded = PluginSpec::from_resolved(&resolved);
+            prop_assert!(selector_count(&recorded.source) <= 1);
+            recorded.validate().unwrap();
+            prop_assert_eq!(recorded.to_resolved().unwrap(), resolved);
+        }
+
+        #[test]
+        fn multiple_selectors_are_rejected(
+            repo in plugin_repo(),
+            first in 0usize..4,
+            offset in 1usize..4,
+        ) {
+            let mut selectors: [Option<String>; 4] = Default::default();
+            selectors[first] = Some("v1".to_string());
+            selectors[(first + offset) % 4] = Some("abcdef1".to_string());
+            let [version, branch, tag, commit] = selectors;
+            let spec = PluginSpec {
+                name: None,
+                prerelease: false,
+                disabled: false,
+                dir: None,
+                events: vec![],
+                disable_files: vec![],
+                source: PluginSource::Repo { repo, version, branch, tag, commit },
+            };
+            prop_assert!(spec.validate().is_err());
+            prop_assert!(spec.to_resolved().is_err());
+        }
+    }
+}
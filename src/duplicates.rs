@@ -0,0 +1,319 @@
+use crate::{
+    config::Config,
+    lock_file::{LockFile, Plugin, PluginFile},
+    utils,
+};
+
+use serde_derive::Serialize;
+use std::{collections::BTreeMap, path};
+use tracing::debug;
+
+/// A plugin that copies (or would copy) a file to a destination path.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct Claim {
+    pub(crate) plugin: String,
+    pub(crate) repo: String,
+    /// False for plugins listed in pez.toml that are not installed yet.
+    pub(crate) installed: bool,
+}
+
+/// A destination path claimed by more than one plugin whose copies differ. Claims on
+/// byte-identical files are shared, not duplicates.
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct Duplicate {
+    pub(crate) path: path::PathBuf,
+    pub(crate) claims: Vec<Claim>,
+}
+
+impl Duplicate {
+    /// A one-line hint on how to resolve the collision.
+    pub(crate) fn suggestion(&self) -> String {
+        let installed: Vec<&Claim> = self.claims.iter().filter(|c| c.installed).collect();
+        match self.claims.iter().find(|c| !c.installed) {
+            Some(pending) if !installed.is_empty() => format!(
+                "installing {} would overwrite this file; remove it from pez.toml or `pez uninstall {}`",
+                pending.repo, installed[0].repo
+            ),
+            Some(pending) => format!(
+                "these plugins are not installed yet; keep only one in pez.toml (e.g. drop {})",
+                pending.repo
+            ),
+            None => format!(
+                "the last install won; keep one plugin (e.g. `pez uninstall {}`) and run `pez repair`",
+                installed[installed.len() - 1].repo
+            ),
+        }
+    }
+}
+
+/// Destination paths of installed plugins that more than one plugin claims.
+pub(crate) fn installed(
+    lock_file: &LockFile,
+    fish_config_dir: &path::Path,
+    data_dir: &path::Path,
+) -> Vec<Duplicate> {
+    collect(
+        lock_file.plugins.iter().map(|p| (p, true)),
+        fish_config_dir,
+        data_dir,
+    )
+}
+
+/// Like [`installed`], plus the files that plugins listed in pez.toml but missing
+/// from the lock file would claim. Those are read from their local path or existing
+/// clone; entries without either are skipped.
+pub(crate) fn installed_and_configured(
+    lock_file: &LockFile,
+    config: Option<&Config>,
+    fish_config_dir: &path::Path,
+    data_dir: &path::Path,
+) -> Vec<Duplicate> {
+    let pending = config.map_or_else(Vec::new, |config| {
+        pending_plugins(lock_file, config, data_dir)
+    });
+    collect(
+        lock_file
+            .plugins
+            .iter()
+            .map(|p| (p, true))
+            .chain(pending.iter().map(|p| (p, false))),
+        fish_config_dir,
+        data_dir,
+    )
+}
+
+fn collect<'a>(
+    plugins: impl Iterator<Item = (&'a Plugin, bool)>,
+    fish_config_dir: &path::Path,
+    data_dir: &path::Path,
+) -> Vec<Duplicate> {
+    // Each claim with the file it would copy from.
+    let mut claims: BTreeMap<path::PathBuf, Vec<(Claim, path::PathBuf)>> = BTreeMap::new();
+    for (plugin, installed) in plugins {
+        for file in &plugin.files {
+            claims
+                .entry(file.get_path(fish_config_dir))
+                .or_default()
+                .push((
+                    Claim {
+                        plugin: plugin.display_label(),
+                        repo: plugin.repo.as_str(),
+                        installed,
+                    },
+                    source_path(plugin, file, data_dir),
+                ));
+        }
+    }
+    claims
+        .into_iter()
+        .filter(|(_, claims)| {
+            claims.len() > 1
+                && !claims
+                    .iter()
+                    .all(|(_, source)| utils::same_contents(&claims[0].1, source))
+        })
+        .map(|(path, claims)| Duplicate {
+            path,
+            claims: claims.into_iter().map(|(claim, _)| claim).collect(),
+        })
+        .collect()
+}
+
+/// Where the plugin's copy of `file` lives in its local path or clone.
+pub(crate) fn source_path(
+    plugin: &Plugin,
+    file: &PluginFile,
+    data_dir: &path::Path,
+) -> path::PathBuf {
+    let root = plugin.checkout_dir(data_dir);
+    if plugin.repo.is_gist() {
+        root.join(&file.name)
+    } else {
+        root.join(file.dir.as_str()).join(&file.name)
+    }
+}
+
+/// Configured plugins without a lock entry, with the files their source would provide.
+fn pending_plugins(lock_file: &LockFile, config: &Config, data_dir: &path::Path) -> Vec<Plugin> {
+    let mut out = Vec::new();
+    for spec in config.plugins.iter().flatten() {
+        let Ok(resolved) = spec.to_resolved() else {
+            continue;
+        };
+        if lock_file.contains_repo(&resolved.plugin_repo) {
+            continue;
+        }
+        let source_dir = if resolved.is_local {
+            path::PathBuf::from(&resolved.source)
+        } else {
+            data_dir.join(resolved.plugin_repo.as_str())
+        };
+        if !source_dir.is_dir() {
+            debug!(
+                "Skipping {} for duplicate check: {} does not exist",
+                resolved.plugin_repo,
+                source_dir.display()
+            );
+            continue;
+        }
+        let mut plugin = Plugin {
+            name: spec
+                .get_name()
+                .unwrap_or_else(|_| resolved.plugin_repo.repo.clone()),
+            repo: resolved.plugin_repo.clone(),
+            source: resolved.source.clone(),
+            commit_sha: String::new(),
+            files: vec![],
+            backups: vec![],
+            updated_at: None,
+            default_branch: None,
+            disabled_files: vec![],
+        };
+        match utils::plugin_source_files(&source_dir, &plugin) {
+            Ok(files) => {
+                // Files its `disable_files` lists will not be installed, so they
+                // claim nothing (a user's own copy may sit there).
+                plugin.files = files
+                    .into_iter()
+                    .map(|(dir, rel, _)| PluginFile {
+                        dir,
+                        name: rel.to_string_lossy().to_string(),
+                    })
+                    .filter(|file| !spec.disables(&file.dir, &file.name))
+                    .collect();
+                out.push(plugin);
+            }
+            Err(e) => debug!("Skipping {}: {:?}", resolved.plugin_repo, e),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{PluginSource, PluginSpec},
+        lock_file::PluginFile,
+        models::{PluginRepo, TargetDir},
+    };
+    use std::fs;
+
+    fn plugin(repo: &str, files: &[(TargetDir, &str)]) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: repo.repo.clone(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "abc".into(),
+            files: files
+                .iter()
+                .map(|(dir, name)| PluginFile {
+                    dir: dir.clone(),
+                    name: name.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reports_installed_and_configured_claims() {
+        let temp = tempfile::tempdir().unwrap();
+        let fish = temp.path().join("fish");
+        let data = temp.path().join("data");
+        let lock_file = LockFile {
+            version: 2,
+            plugins: vec![
+                plugin(
+                    "owner/a",
+                    &[
+                        (TargetDir::Functions, "shared.fish"),
+                        (TargetDir::Functions, "a.fish"),
+                    ],
+                ),
+                plugin("owner/b", &[(TargetDir::Functions, "shared.fish")]),
+                plugin("owner/c", &[(TargetDir::Completions, "c.fish")]),
+            ],
+        };
+
+        // owner/d is configured and cloned but not installed; owner/e is not cloned.
+        let clone = data.join("owner/d/completions");
+        fs::create_dir_all(&clone).unwrap();
+        fs::write(clone.join("c.fish"), "").unwrap();
+        let spec = |repo: &str| PluginSpec {
+            name: None,
+            prerelease: false,
+            disabled: false,
+            dir: None,
+            events: vec![],
+            disable_files: vec![],
+            source: PluginSource::Repo {
+                repo: repo.parse().unwrap(),
+                version: None,
+                branch: None,
+                tag: None,
+                commit: None,
+            },
+        };
+        let config = Config {
+            plugins: Some(vec![spec("owner/a"), spec("owner/d"), spec("owner/e")]),
+            settings: Default::default(),
+            aliases: Default::default(),
+            doctor: Default::default(),
+        };
+
+        assert_eq!(installed(&lock_file, &fish, &data).len(), 1);
+
+        let duplicates = installed_and_configured(&lock_file, Some(&config), &fish, &data);
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[0].path, fish.join("completions/c.fish"));
+        assert_eq!(
+            duplicates[0]
+                .claims
+                .iter()
+                .map(|c| (c.repo.as_str(), c.installed))
+                .collect::<Vec<_>>(),
+            vec![("owner/c", true), ("owner/d", false)]
+        );
+        assert!(duplicates[0].suggestion().contains("installing owner/d"));
+        assert_eq!(duplicates[1].path, fish.join("functions/shared.fish"));
+        assert!(
+            duplicates[1]
+                .suggestion()
+                .contains("`pez uninstall owner/b`")
+        );
+
+        // Disabling the clashing file in owner/d's entry settles it.
+        let mut config = config;
+        config.plugins.as_mut().unwrap()[1].disable_files = vec!["completions/c.fish".into()];
+        let duplicates = installed_and_configured(&lock_file, Some(&config), &fish, &data);
+        assert_eq!(duplicates.len(), 1);
+    }
+
+    #[test]
+    fn identical_copies_are_shared_not_duplicates() {
+        let temp = tempfile::tempdir().unwrap();
+        let fish = temp.path().join("fish");
+        let data = temp.path().join("data");
+        for (repo, contents) in [
+            ("owner/a", "function shared; end\n"),
+            ("owner/b", "function shared; end\n"),
+            ("owner/c", "function shared; echo c; end\n"),
+        ] {
+            let functions = data.join(repo).join("functions");
+            fs::create_dir_all(&functions).unwrap();
+            fs::write(functions.join("shared.fish"), contents).unwrap();
+        }
+        let shared = [(TargetDir::Functions, "shared.fish")];
+        let lock_file = |repos: &[&str]| LockFile {
+            version: 2,
+            plugins: repos.iter().map(|repo| plugin(repo, &shared)).collect(),
+        };
+
+        assert!(installed(&lock_file(&["owner/a", "owner/b"]), &fish, &data).is_empty());
+        let conflicting = installed(&lock_file(&["owner/a", "owner/b", "owner/c"]), &fish, &data);
+        assert_eq!(conflicting.len(), 1);
+        assert_eq!(conflicting[0].claims.len(), 3);
+    }
+}
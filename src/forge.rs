@@ -0,0 +1,311 @@
+//! Minimal client for the GitHub and GitLab REST APIs (cargo feature `forge-api`).
+//!
+//! Responses are cached under `<cache dir>/forge` and revalidated with ETags, so
+//! repeated lookups stay within anonymous rate limits. Tokens are read from
+//! `GITHUB_TOKEN`/`GH_TOKEN` and `GITLAB_TOKEN`.
+
+use crate::{models::PluginRepo, update_cache, utils};
+
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{fs, path, time::Duration};
+use tracing::{debug, warn};
+
+/// How long a cached response is used without asking the API again.
+const CACHE_TTL_SECS: u64 = 60 * 60;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const USER_AGENT: &str = concat!("pez/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Forge {
+    GitHub,
+    GitLab,
+}
+
+impl Forge {
+    /// The forge hosting `repo` and its API base URL, when pez knows how to talk to it.
+    pub(crate) fn for_repo(repo: &PluginRepo) -> Option<(Self, String)> {
+        match repo.host.as_deref() {
+            None | Some("github.com") => Some((Self::GitHub, "https://api.github.com".into())),
+            Some(host) if host == "gitlab.com" || host.starts_with("gitlab.") => {
+                Some((Self::GitLab, format!("https://{host}/api/v4")))
+            }
+            _ => None,
+        }
+    }
+
+    fn token(self) -> Option<String> {
+        let keys: &[&str] = match self {
+            Self::GitHub => &["GITHUB_TOKEN", "GH_TOKEN"],
+            Self::GitLab => &["GITLAB_TOKEN"],
+        };
+        keys.iter()
+            .filter_map(|key| std::env::var(key).ok())
+            .find(|value| !value.is_empty())
+    }
+
+    fn repo_url(self, base: &str, repo: &PluginRepo) -> String {
+        match self {
+            Self::GitHub => format!("{base}/repos/{}/{}", repo.owner, repo.repo),
+            Self::GitLab => format!("{base}/projects/{}%2F{}", repo.owner, repo.repo),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct RepoInfo {
+    pub(crate) description: Option<String>,
+    /// GitHub stargazers or GitLab stars.
+    pub(crate) stars: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CachedResponse {
+    fetched_at: u64,
+    etag: Option<String>,
+    body: String,
+}
+
+pub(crate) struct ForgeClient {
+    agent: ureq::Agent,
+    cache_dir: path::PathBuf,
+}
+
+impl ForgeClient {
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        Ok(Self::with_cache_dir(
+            utils::load_pez_cache_dir()?.join("forge"),
+        ))
+    }
+
+    fn with_cache_dir(cache_dir: path::PathBuf) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .http_status_as_error(false)
+            .user_agent(USER_AGENT)
+            .build()
+            .into();
+        Self { agent, cache_dir }
+    }
+
+    pub(crate) fn repo_info(&self, repo: &PluginRepo) -> anyhow::Result<RepoInfo> {
+        let (forge, base) = supported(repo)?;
+        let body = self.get(forge, &forge.repo_url(&base, repo))?;
+        parse_repo_info(&body)
+    }
+
+    /// GET `url`, serving fresh cache entries directly and revalidating stale ones.
+    /// When rate limited, a stale cached body is returned with a warning.
+    fn get(&self, forge: Forge, url: &str) -> anyhow::Result<String> {
+        let cache_path = self.cache_path(url);
+        let cached = read_cache(&cache_path);
+        let now = update_cache::now_secs();
+        if let Some(cached) = &cached
+            && now.saturating_sub(cached.fetched_at) < CACHE_TTL_SECS
+        {
+            debug!("forge cache hit: {}", url);
+            return Ok(cached.body.clone());
+        }
+
+        let mut request = self.agent.get(url).header("Accept", "application/json");
+        if let Some(token) = forge.token() {
+            request = match forge {
+                Forge::GitHub => request.header("Authorization", format!("Bearer {token}")),
+                Forge::GitLab => request.header("PRIVATE-TOKEN", token),
+            };
+        }
+        if let Some(etag) = cached.as_ref().and_then(|c| c.etag.as_deref()) {
+            request = request.header("If-None-Match", etag);
+        }
+
+        let mut response = request.call()?;
+        let status = response.status().as_u16();
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+
+        if status == 304
+            && let Some(mut cached) = cached
+        {
+            cached.fetched_at = now;
+            write_cache(&cache_path, &cached);
+            return Ok(cached.body);
+        }
+        if let Some(message) = rate_limit_message(
+            status,
+            header("x-ratelimit-remaining").or_else(|| header("ratelimit-remaining")),
+            header("x-ratelimit-reset").or_else(|| header("ratelimit-reset")),
+            header("retry-after"),
+            now,
+        ) {
+            if let Some(cached) = cached {
+                warn!(
+                    "{} {}; using cached response for {}",
+                    utils::label_warning(),
+                    message,
+                    url
+                );
+                return Ok(cached.body);
+            }
+            anyhow::bail!("{message}");
+        }
+        if !(200..300).contains(&status) {
+            anyhow::bail!("{} returned HTTP {}", url, status);
+        }
+
+        let etag = header("etag");
+        let body = response.body_mut().read_to_string()?;
+        write_cache(
+            &cache_path,
+            &CachedResponse {
+                fetched_at: now,
+                etag,
+                body: body.clone(),
+            },
+        );
+        Ok(body)
+    }
+
+    fn cache_path(&self, url: &str) -> path::PathBuf {
+        let digest = Sha256::digest(url.as_bytes());
+        let name: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+        self.cache_dir.join(format!("{name}.json"))
+    }
+}
+
+fn supported(repo: &PluginRepo) -> anyhow::Result<(Forge, String)> {
+    Forge::for_repo(repo)
+        .ok_or_else(|| anyhow::anyhow!("No forge API support for {}", repo.as_str()))
+}
+
+fn read_cache(path: &path::Path) -> Option<CachedResponse> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_cache(path: &path::Path, entry: &CachedResponse) {
+    let result = (|| -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(entry)?)?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        debug!("Failed to write forge cache {}: {:?}", path.display(), e);
+    }
+}
+
+/// A user-facing message when the response signals an exhausted rate limit.
+fn rate_limit_message(
+    status: u16,
+    remaining: Option<String>,
+    reset: Option<String>,
+    retry_after: Option<String>,
+    now: u64,
+) -> Option<String> {
+    let exhausted = remaining.as_deref() == Some("0");
+    if status != 429 && !(status == 403 && exhausted) {
+        return None;
+    }
+    let wait = retry_after.and_then(|v| v.parse::<u64>().ok()).or_else(|| {
+        reset
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(|reset| reset.saturating_sub(now))
+    });
+    let mut message = "forge API rate limit exceeded".to_string();
+    if let Some(wait) = wait {
+        message.push_str(&format!(" (resets in {}m)", wait.div_ceil(60)));
+    }
+    message.push_str("; set GITHUB_TOKEN or GITLAB_TOKEN for a higher limit");
+    Some(message)
+}
+
+fn parse_repo_info(body: &str) -> anyhow::Result<RepoInfo> {
+    let value: serde_json::Value = serde_json::from_str(body)?;
+    let text = |key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+    Ok(RepoInfo {
+        description: text("description"),
+        stars: ["stargazers_count", "star_count"]
+            .iter()
+            .find_map(|key| value.get(*key).and_then(|v| v.as_u64())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn for_repo_maps_hosts_to_api_bases() {
+        let github: PluginRepo = "owner/repo".parse().unwrap();
+        let (forge, base) = Forge::for_repo(&github).unwrap();
+        assert_eq!(forge, Forge::GitHub);
+        assert_eq!(
+            forge.repo_url(&base, &github),
+            "https://api.github.com/repos/owner/repo"
+        );
+
+        let gitlab: PluginRepo = "gitlab.com/owner/repo".parse().unwrap();
+        let (forge, base) = Forge::for_repo(&gitlab).unwrap();
+        assert_eq!(
+            forge.repo_url(&base, &gitlab),
+            "https://gitlab.com/api/v4/projects/owner%2Frepo"
+        );
+
+        assert!(Forge::for_repo(&"codeberg.org/o/r".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn parses_repo_info() {
+        let info = parse_repo_info(
+            r#"{"description":"A prompt","archived":true,"default_branch":"main","stargazers_count":42}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            info,
+            RepoInfo {
+                description: Some("A prompt".into()),
+                stars: Some(42),
+            }
+        );
+    }
+
+    #[test]
+    fn rate_limit_message_reports_reset() {
+        assert!(rate_limit_message(200, Some("0".into()), None, None, 0).is_none());
+        assert!(rate_limit_message(403, Some("10".into()), None, None, 0).is_none());
+        let message =
+            rate_limit_message(403, Some("0".into()), Some("1000".into()), None, 700).unwrap();
+        assert!(message.contains("resets in 5m"));
+        let message = rate_limit_message(429, None, None, Some("30".into()), 0).unwrap();
+        assert!(message.contains("resets in 1m"));
+    }
+
+    #[test]
+    fn get_serves_fresh_cache_without_network() {
+        let temp = tempfile::tempdir().unwrap();
+        let client = ForgeClient::with_cache_dir(temp.path().to_path_buf());
+        // An unroutable URL proves the response comes from the cache.
+        let url = "http://127.0.0.1:9/repos/owner/repo";
+        write_cache(
+            &client.cache_path(url),
+            &CachedResponse {
+                fetched_at: update_cache::now_secs(),
+                etag: None,
+                body: "{}".into(),
+            },
+        );
+        assert_eq!(client.get(Forge::GitHub, url).unwrap(), "{}");
+    }
+}
@@ -0,0 +1,6 @@
+//! Entry points for the cargo-fuzz targets under `fuzz/`.
+
+/// Parse `raw` the way `pez install <raw>` does. Errors are expected; panics are bugs.
+pub fn install_target_resolve(raw: &str) {
+    let _ = crate::models::InstallTarget::from_raw(raw).resolve();
+}
@@ -1,8 +1,12 @@
-use crate::resolver::Selection;
+use crate::resolver::{Channel, Selection};
 use git2::{Cred, Error, FetchOptions, RemoteCallbacks};
-use std::path;
 #[cfg(test)]
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    fmt, path,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
 
 #[cfg(test)]
 static CALLBACKS_CONFIGURED: AtomicUsize = AtomicUsize::new(0);
@@ -13,21 +17,239 @@ pub(crate) fn clone_repository(
     repo_url: &str,
     target_path: &path::Path,
 ) -> anyhow::Result<git2::Repository> {
-    let callbacks = setup_remote_callbacks();
-    let fetch_options = setup_fetch_options(callbacks);
-
+    tracing::debug!(
+        target: crate::trace::GIT,
+        url = repo_url,
+        path = %target_path.display(),
+        "Cloning"
+    );
+    let _permit = crate::host_limit::acquire(repo_url);
+    let deadline = Deadline::start();
+    let fetch_options = setup_fetch_options(setup_remote_callbacks(deadline));
     let mut clone_options = git2::build::RepoBuilder::new();
     clone_options.fetch_options(fetch_options);
-    let repo = clone_options.clone(repo_url, target_path)?;
+    let repo = clone_options
+        .clone(repo_url, target_path)
+        .map_err(|e| deadline.classify(repo_url, e))?;
+
+    Ok(repo)
+}
+
+/// Clone `repo_url` into `target_path`, keeping its objects in the shared bare
+/// repository at `store_path` and linking to them through `objects/info/alternates`.
+///
+/// Objects are fetched into the store under `refs/pez/<key>/...`, so forks of an
+/// already-fetched repo only download (and store) what differs. The resulting clone
+/// has the same remote-tracking refs, tags and checked-out default branch as
+/// [`clone_repository`].
+pub(crate) fn clone_repository_shared(
+    repo_url: &str,
+    target_path: &path::Path,
+    store_path: &path::Path,
+    key: &str,
+) -> anyhow::Result<git2::Repository> {
+    tracing::debug!(
+        target: crate::trace::GIT,
+        url = repo_url,
+        path = %target_path.display(),
+        store = %store_path.display(),
+        "Cloning through shared object store"
+    );
+    let store = match git2::Repository::open_bare(store_path) {
+        Ok(store) => store,
+        Err(_) => git2::Repository::init_bare(store_path)?,
+    };
+    let namespace = format!("refs/pez/{key}");
+    let default_branch = {
+        let _permit = crate::host_limit::acquire(repo_url);
+        let deadline = Deadline::start();
+        let mut remote = store.remote_anonymous(repo_url)?;
+        let default_branch = {
+            let connection = remote
+                .connect_auth(
+                    git2::Direction::Fetch,
+                    Some(setup_remote_callbacks(deadline)),
+                    None,
+                )
+                .map_err(|e| deadline.classify(repo_url, e))?;
+            connection
+                .default_branch()
+                .ok()
+                .and_then(|buf| buf.as_str().map(str::to_string))
+        };
+        let mut fetch_options = setup_fetch_options(setup_remote_callbacks(deadline));
+        fetch_options.download_tags(git2::AutotagOption::None);
+        remote
+            .fetch(
+                &[
+                    format!("+refs/heads/*:{namespace}/heads/*"),
+                    format!("+refs/tags/*:{namespace}/tags/*"),
+                ],
+                Some(&mut fetch_options),
+                None,
+            )
+            .map_err(|e| deadline.classify(repo_url, e))?;
+        default_branch
+    };
+
+    let repo = git2::Repository::init(target_path)?;
+    let store_objects = std::fs::canonicalize(store.path())?.join("objects");
+    let info_dir = repo.path().join("objects").join("info");
+    std::fs::create_dir_all(&info_dir)?;
+    std::fs::write(
+        info_dir.join("alternates"),
+        format!("{}\n", store_objects.display()),
+    )?;
+    // Reopen so the object database picks up the alternate.
+    drop(repo);
+    let repo = git2::Repository::open(target_path)?;
+    repo.remote("origin", repo_url)?;
+
+    let heads_prefix = format!("{namespace}/heads/");
+    let tags_prefix = format!("{namespace}/tags/");
+    for reference in store.references_glob(&format!("{namespace}/*"))? {
+        let reference = reference?;
+        let (Some(name), Some(oid)) = (reference.name(), reference.target()) else {
+            continue;
+        };
+        let local_name = if let Some(branch) = name.strip_prefix(&heads_prefix) {
+            format!("refs/remotes/origin/{branch}")
+        } else if let Some(tag) = name.strip_prefix(&tags_prefix) {
+            format!("refs/tags/{tag}")
+        } else {
+            continue;
+        };
+        repo.reference(&local_name, oid, true, "pez: clone from shared objects")?;
+    }
+
+    let branch = default_branch
+        .as_deref()
+        .and_then(|name| name.strip_prefix("refs/heads/"))
+        .ok_or_else(|| anyhow::anyhow!("remote {repo_url} did not report a default branch"))?;
+    let tracking = format!("refs/remotes/origin/{branch}");
+    let head_oid = repo
+        .find_reference(&tracking)?
+        .target()
+        .ok_or_else(|| anyhow::anyhow!("{tracking} has no target"))?;
+    repo.reference_symbolic(
+        "refs/remotes/origin/HEAD",
+        &tracking,
+        true,
+        "pez: clone from shared objects",
+    )?;
+    repo.branch(branch, &repo.find_commit(head_oid)?, true)?;
+    repo.set_head(&format!("refs/heads/{branch}"))?;
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force();
+    repo.checkout_head(Some(&mut checkout))?;
 
     Ok(repo)
 }
 
-fn setup_remote_callbacks() -> RemoteCallbacks<'static> {
+/// Default for `[settings] clone_timeout`, in seconds.
+pub(crate) const DEFAULT_CLONE_TIMEOUT_SECS: u64 = 300;
+
+/// A clone or fetch that did not finish within `[settings] clone_timeout`.
+#[derive(Debug)]
+pub(crate) struct TimedOut {
+    pub(crate) url: String,
+    pub(crate) after: Duration,
+    source: git2::Error,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} did not respond within {}s",
+            self.url,
+            self.after.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for TimedOut {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// The [`TimedOut`] anywhere in `err`'s chain, if a remote hung.
+pub(crate) fn timed_out(err: &anyhow::Error) -> Option<&TimedOut> {
+    err.chain().find_map(|e| e.downcast_ref::<TimedOut>())
+}
+
+/// `[settings] clone_timeout` (0 disables it), read once per process. The same limit
+/// is handed to libgit2 as its connect and read timeout, which covers remotes that
+/// stop sending data before any progress callback fires.
+fn clone_timeout() -> Option<Duration> {
+    static TIMEOUT: OnceLock<Option<Duration>> = OnceLock::new();
+    *TIMEOUT.get_or_init(|| {
+        let secs = crate::utils::load_config()
+            .ok()
+            .and_then(|(config, _)| config.settings.clone_timeout)
+            .unwrap_or(DEFAULT_CLONE_TIMEOUT_SECS);
+        let limit = (secs > 0).then(|| Duration::from_secs(secs));
+        if let Some(limit) = limit {
+            let millis = i32::try_from(limit.as_millis()).unwrap_or(i32::MAX);
+            // SAFETY: runs once, before this process starts its first network operation.
+            unsafe {
+                let _ = git2::opts::set_server_connect_timeout_in_milliseconds(millis);
+                let _ = git2::opts::set_server_timeout_in_milliseconds(millis);
+            }
+        }
+        limit
+    })
+}
+
+/// Time budget for one network operation against a remote.
+#[derive(Clone, Copy, Debug)]
+struct Deadline {
+    started: Instant,
+    limit: Option<Duration>,
+}
+
+impl Deadline {
+    fn start() -> Self {
+        Self::with_limit(clone_timeout())
+    }
+
+    fn with_limit(limit: Option<Duration>) -> Self {
+        Self {
+            started: Instant::now(),
+            limit,
+        }
+    }
+
+    fn expired(&self) -> bool {
+        self.limit
+            .is_some_and(|limit| self.started.elapsed() >= limit)
+    }
+
+    /// Turn a libgit2 timeout, or the abort our progress callbacks raise once the
+    /// deadline passed, into a [`TimedOut`] naming `url`.
+    fn classify(&self, url: &str, err: git2::Error) -> anyhow::Error {
+        let hung = err.code() == git2::ErrorCode::Timeout
+            || (err.code() == git2::ErrorCode::User && self.expired());
+        match self.limit {
+            Some(after) if hung => anyhow::Error::new(TimedOut {
+                url: url.to_string(),
+                after,
+                source: err,
+            }),
+            _ => err.into(),
+        }
+    }
+}
+
+fn setup_remote_callbacks(deadline: Deadline) -> RemoteCallbacks<'static> {
     let mut callbacks = RemoteCallbacks::new();
     // Use libgit2's default credential negotiation which covers HTTPS, SSH agent,
     // and other common flows. This matches the behavior used in clone_repository.
     callbacks.credentials(|_, _, _| Cred::default());
+    // Returning false aborts the transfer with a `User` error.
+    callbacks.transfer_progress(move |_| !deadline.expired());
+    callbacks.sideband_progress(move |_| !deadline.expired());
     #[cfg(test)]
     CALLBACKS_CONFIGURED.fetch_add(1, Ordering::SeqCst);
     callbacks
@@ -50,6 +272,12 @@ pub(crate) fn get_latest_commit_sha(repo: &git2::Repository) -> Result<String, g
 }
 
 pub(crate) fn checkout_detached(repo: &git2::Repository, oid: git2::Oid) -> anyhow::Result<()> {
+    tracing::debug!(
+        target: crate::trace::GIT,
+        path = %repo.path().display(),
+        commit = %oid,
+        "Checking out"
+    );
     repo.set_head_detached(oid)?;
     if repo.is_bare() {
         return Ok(());
@@ -60,6 +288,22 @@ pub(crate) fn checkout_detached(repo: &git2::Repository, oid: git2::Oid) -> anyh
     Ok(())
 }
 
+/// Paths of tracked files with uncommitted changes in the worktree or index.
+/// Untracked and ignored files are not reported since checkouts leave them alone.
+pub(crate) fn dirty_paths(repo: &git2::Repository) -> anyhow::Result<Vec<String>> {
+    if repo.is_bare() {
+        return Ok(vec![]);
+    }
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    let statuses = repo.statuses(Some(&mut options))?;
+    Ok(statuses
+        .iter()
+        .filter(|entry| entry.status() != git2::Status::CURRENT)
+        .filter_map(|entry| entry.path().map(str::to_string))
+        .collect())
+}
+
 pub(crate) fn checkout_commit(repo: &git2::Repository, commit: &str) -> anyhow::Result<()> {
     let oid = git2::Oid::from_str(commit)?;
     checkout_detached(repo, oid)
@@ -76,6 +320,13 @@ pub(crate) fn checkout_ref(repo: &git2::Repository, refspec: &str) -> anyhow::Re
     Ok(obj.id().to_string())
 }
 
+/// The URL of the `origin` remote of the clone at `clone`, if it has one.
+pub(crate) fn origin_url(clone: &path::Path) -> Option<String> {
+    let repo = git2::Repository::open(clone).ok()?;
+    let remote = repo.find_remote("origin").ok()?;
+    remote.url().map(str::to_string)
+}
+
 /// Rough heuristic: a source is a local path if it starts with '/', './', '../', or '~'.
 pub(crate) fn is_local_source(source: &str) -> bool {
     source.starts_with('/')
@@ -85,19 +336,24 @@ pub(crate) fn is_local_source(source: &str) -> bool {
 }
 
 pub(crate) fn fetch_all(repo: &git2::Repository) -> anyhow::Result<()> {
-    let cb = setup_remote_callbacks();
+    tracing::debug!(target: crate::trace::GIT, path = %repo.path().display(), "Fetching origin");
+    let mut remote = repo.find_remote("origin")?;
+    let _permit = remote.url().and_then(crate::host_limit::acquire);
+    let deadline = Deadline::start();
     let mut fo = FetchOptions::new();
-    fo.remote_callbacks(cb);
+    fo.remote_callbacks(setup_remote_callbacks(deadline));
     fo.download_tags(git2::AutotagOption::All);
-    let mut remote = repo.find_remote("origin")?;
-    remote.fetch(
-        &[
-            "refs/heads/*:refs/remotes/origin/*",
-            "refs/tags/*:refs/tags/*",
-        ],
-        Some(&mut fo),
-        None,
-    )?;
+    let url = remote.url().unwrap_or("origin").to_string();
+    remote
+        .fetch(
+            &[
+                "refs/heads/*:refs/remotes/origin/*",
+                "refs/tags/*:refs/tags/*",
+            ],
+            Some(&mut fo),
+            None,
+        )
+        .map_err(|e| deadline.classify(&url, e))?;
     Ok(())
 }
 
@@ -119,6 +375,16 @@ pub(crate) fn get_remote_head_commit(repo: &git2::Repository) -> anyhow::Result<
     Ok(oid.to_string())
 }
 
+/// The branch `refs/remotes/origin/HEAD` points at, i.e. what the remote reported as its
+/// default branch when it was cloned.
+pub(crate) fn default_branch(repo: &git2::Repository) -> Option<String> {
+    let reference = repo.find_reference("refs/remotes/origin/HEAD").ok()?;
+    reference
+        .symbolic_target()?
+        .strip_prefix("refs/remotes/origin/")
+        .map(str::to_string)
+}
+
 pub(crate) fn get_remote_branch_commit(
     repo: &git2::Repository,
     branch: &str,
@@ -152,15 +418,84 @@ pub(crate) fn list_tags(repo: &git2::Repository) -> anyhow::Result<Vec<String>>
     Ok(tags)
 }
 
+/// Tags (peeled to commits) that point at `sha`, without fetching.
+pub(crate) fn tags_at_commit(repo: &git2::Repository, sha: &str) -> anyhow::Result<Vec<String>> {
+    let oid = git2::Oid::from_str(sha)?;
+    let mut tags = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let Ok(reference) = repo.find_reference(&format!("refs/tags/{name}")) else {
+            continue;
+        };
+        if reference
+            .peel_to_commit()
+            .is_ok_and(|commit| commit.id() == oid)
+        {
+            tags.push(name.to_string());
+        }
+    }
+    Ok(tags)
+}
+
+/// One commit in a log: full hash, first line of the message, author name, and commit
+/// time (Unix seconds).
+#[derive(serde_derive::Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct CommitSummary {
+    pub(crate) sha: String,
+    pub(crate) summary: String,
+    pub(crate) author: String,
+    pub(crate) time: i64,
+}
+
+/// Commits reachable from `to` but not from `from`, newest first, without fetching.
+pub(crate) fn commits_between(
+    repo: &git2::Repository,
+    from: &str,
+    to: &str,
+) -> anyhow::Result<Vec<CommitSummary>> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    walk.push(git2::Oid::from_str(to)?)?;
+    walk.hide(git2::Oid::from_str(from)?)?;
+    let mut commits = Vec::new();
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        commits.push(CommitSummary {
+            sha: commit.id().to_string(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+            author: commit.author().name().unwrap_or_default().to_string(),
+            time: commit.time().seconds(),
+        });
+    }
+    Ok(commits)
+}
+
+/// The closest tag reachable from `sha` (`git describe --tags --abbrev=0`), if any.
+pub(crate) fn nearest_tag(repo: &git2::Repository, sha: &str) -> Option<String> {
+    let commit = repo.find_commit(git2::Oid::from_str(sha).ok()?).ok()?;
+    let describe = commit
+        .as_object()
+        .describe(git2::DescribeOptions::new().describe_tags())
+        .ok()?;
+    describe
+        .format(Some(git2::DescribeFormatOptions::new().abbreviated_size(0)))
+        .ok()
+}
+
 pub(crate) fn resolve_selection(
     repo: &git2::Repository,
     sel: &Selection,
+    channel: Channel,
 ) -> anyhow::Result<String> {
     match sel {
         Selection::DefaultHead | Selection::Latest => get_remote_head_commit(repo),
         Selection::Branch(name) => {
             if let Some(c) = get_remote_branch_commit(repo, name)? {
-                tracing::debug!(branch = name, commit = %c, "Resolved branch to commit");
+                tracing::debug!(
+                    target: crate::trace::RESOLVER,
+                    branch = name,
+                    commit = %c,
+                    "Resolved branch to commit"
+                );
                 Ok(c)
             } else {
                 anyhow::bail!(format!("Branch not found: {name}"))
@@ -168,7 +503,12 @@ pub(crate) fn resolve_selection(
         }
         Selection::Tag(t) => {
             if let Some(c) = get_tag_commit(repo, t)? {
-                tracing::debug!(tag = t, commit = %c, "Resolved tag to commit");
+                tracing::debug!(
+                    target: crate::trace::RESOLVER,
+                    tag = t,
+                    commit = %c,
+                    "Resolved tag to commit"
+                );
                 Ok(c)
             } else {
                 anyhow::bail!(format!("Tag not found: {t}"))
@@ -179,18 +519,27 @@ pub(crate) fn resolve_selection(
                 .revparse_single(sha)
                 .map_err(|e| anyhow::anyhow!("Failed to resolve commit '{sha}': {e}"))?;
             let id = obj.peel_to_commit()?.id().to_string();
-            tracing::debug!(commit = %id, "Resolved explicit commit");
+            tracing::debug!(
+                target: crate::trace::RESOLVER,
+                commit = %id,
+                "Resolved explicit commit"
+            );
             Ok(id)
         }
         Selection::Version(v) => {
-            let id = resolve_version(repo, v)?;
-            tracing::debug!(version = v, commit = %id, "Resolved version to commit");
+            let id = resolve_version(repo, v, channel)?;
+            tracing::debug!(
+                target: crate::trace::RESOLVER,
+                version = v,
+                commit = %id,
+                "Resolved version to commit"
+            );
             Ok(id)
         }
     }
 }
 
-fn resolve_version(repo: &git2::Repository, v: &str) -> anyhow::Result<String> {
+fn resolve_version(repo: &git2::Repository, v: &str, channel: Channel) -> anyhow::Result<String> {
     if v == "latest" {
         return get_remote_head_commit(repo);
     }
@@ -198,7 +547,7 @@ fn resolve_version(repo: &git2::Repository, v: &str) -> anyhow::Result<String> {
         return Ok(c);
     }
     let tags = list_tags(repo)?;
-    if let Some(tag) = pick_tag_for_version(&tags, v)?
+    if let Some(tag) = pick_tag_for_version(&tags, v, channel)?
         && let Some(c) = get_tag_commit(repo, &tag)?
     {
         return Ok(c);
@@ -206,7 +555,11 @@ fn resolve_version(repo: &git2::Repository, v: &str) -> anyhow::Result<String> {
     anyhow::bail!(format!("No matching branch or tag for version: {v}"))
 }
 
-fn pick_tag_for_version(tags: &[String], v: &str) -> anyhow::Result<Option<String>> {
+pub(crate) fn pick_tag_for_version(
+    tags: &[String],
+    v: &str,
+    channel: Channel,
+) -> anyhow::Result<Option<String>> {
     use semver::Version;
     let v_trim = v.trim_start_matches('v');
     let parts: Vec<&str> = v_trim.split('.').collect();
@@ -215,8 +568,8 @@ fn pick_tag_for_version(tags: &[String], v: &str) -> anyhow::Result<Option<Strin
         let name = t.trim();
         let name_trim = name.trim_start_matches('v');
         if let Ok(ver) = Version::parse(name_trim) {
-            // Exclude pre-release tags by default
-            if ver.pre.is_empty() {
+            // Exclude pre-release tags unless the plugin opted into them
+            if ver.pre.is_empty() || channel == Channel::Prerelease {
                 semver_tags.push((ver, name.to_string()));
             }
         }
@@ -227,7 +580,12 @@ fn pick_tag_for_version(tags: &[String], v: &str) -> anyhow::Result<Option<Strin
             && let Ok(want) = Version::parse(v_trim)
             && let Some((_, tag)) = semver_tags.iter().find(|(sv, _)| *sv == want)
         {
-            tracing::debug!(version = %v, tag = %tag, "Matched exact semver tag");
+            tracing::debug!(
+                target: crate::trace::RESOLVER,
+                version = %v,
+                tag = %tag,
+                "Matched exact semver tag"
+            );
             return Ok(Some(tag.clone()));
         }
         let want_major = parts.first().and_then(|s| s.parse::<u64>().ok());
@@ -241,14 +599,24 @@ fn pick_tag_for_version(tags: &[String], v: &str) -> anyhow::Result<Option<Strin
                 candidates.sort_by(|a, b| a.0.cmp(&b.0));
                 let tag = candidates.last().map(|(_, tag)| tag.clone());
                 if let Some(ref t) = tag {
-                    tracing::debug!(version = %v, tag = %t, "Selected highest semver tag by prefix");
+                    tracing::debug!(
+                        target: crate::trace::RESOLVER,
+                        version = %v,
+                        tag = %t,
+                        "Selected highest semver tag by prefix"
+                    );
                 }
                 return Ok(tag);
             }
         }
     }
     if tags.iter().any(|t| t == v) {
-        tracing::debug!(version = %v, tag = %v, "Matched non-semver exact tag");
+        tracing::debug!(
+            target: crate::trace::RESOLVER,
+            version = %v,
+            tag = %v,
+            "Matched non-semver exact tag"
+        );
         return Ok(Some(v.to_string()));
     }
     let mut candidates: Vec<(Vec<u64>, String)> = Vec::new();
@@ -274,7 +642,12 @@ fn pick_tag_for_version(tags: &[String], v: &str) -> anyhow::Result<Option<Strin
         candidates.sort_by(|a, b| a.0.cmp(&b.0));
         let tag = candidates.last().map(|(_, tag)| tag.clone());
         if let Some(ref t) = tag {
-            tracing::debug!(version = %v, tag = %t, "Selected highest non-semver dotted suffix tag");
+            tracing::debug!(
+                target: crate::trace::RESOLVER,
+                version = %v,
+                tag = %t,
+                "Selected highest non-semver dotted suffix tag"
+            );
         }
         return Ok(tag);
     }
@@ -316,15 +689,18 @@ pub(crate) fn get_latest_remote_commit(repo: &git2::Repository) -> anyhow::Resul
 
         let mut remote = repo.find_remote(&remote_name)?;
 
-        let cb = setup_remote_callbacks();
+        let _permit = remote.url().and_then(crate::host_limit::acquire);
+        let deadline = Deadline::start();
         let mut fetch_options = FetchOptions::new();
-        fetch_options.remote_callbacks(cb);
-
-        remote.fetch(
-            &["refs/heads/*:refs/remotes/origin/*"],
-            Some(&mut fetch_options),
-            None,
-        )?;
+        fetch_options.remote_callbacks(setup_remote_callbacks(deadline));
+        let url = remote.url().unwrap_or(&remote_name).to_string();
+        remote
+            .fetch(
+                &["refs/heads/*:refs/remotes/origin/*"],
+                Some(&mut fetch_options),
+                None,
+            )
+            .map_err(|e| deadline.classify(&url, e))?;
 
         let remote_branch_ref = format!("refs/remotes/{remote_name}/{branch_name}");
         let remote_ref = match repo.find_reference(&remote_branch_ref) {
@@ -348,15 +724,18 @@ pub(crate) fn get_latest_remote_commit(repo: &git2::Repository) -> anyhow::Resul
 
         let mut remote = repo.find_remote(remote_name)?;
 
-        let cb = setup_remote_callbacks();
+        let _permit = remote.url().and_then(crate::host_limit::acquire);
+        let deadline = Deadline::start();
         let mut fetch_options = FetchOptions::new();
-        fetch_options.remote_callbacks(cb);
-
-        remote.fetch(
-            &["refs/heads/*:refs/remotes/origin/*"],
-            Some(&mut fetch_options),
-            None,
-        )?;
+        fetch_options.remote_callbacks(setup_remote_callbacks(deadline));
+        let url = remote.url().unwrap_or(remote_name).to_string();
+        remote
+            .fetch(
+                &["refs/heads/*:refs/remotes/origin/*"],
+                Some(&mut fetch_options),
+                None,
+            )
+            .map_err(|e| deadline.classify(&url, e))?;
 
         let remote_head_ref = format!("refs/remotes/{remote_name}/HEAD");
         let remote_head_ref = match repo.find_reference(&remote_head_ref) {
@@ -404,6 +783,74 @@ mod tests {
         (repo, commit_oid)
     }
 
+    #[test]
+    fn tags_at_commit_peels_annotated_tags() {
+        let tmp = tempdir().unwrap();
+        let (repo, oid) = init_repo_with_commit(tmp.path());
+        let commit = repo.find_commit(oid).unwrap();
+        repo.tag_lightweight("v1.0.0", commit.as_object(), false)
+            .unwrap();
+        let sig = repo.signature().unwrap();
+        repo.tag("release", commit.as_object(), &sig, "release", false)
+            .unwrap();
+        fs::write(tmp.path().join("next.txt"), "next").unwrap();
+        let next = commit_file(&repo, Path::new("next.txt"), "next");
+        repo.tag_lightweight("v1.1.0", &repo.find_object(next, None).unwrap(), false)
+            .unwrap();
+
+        assert_eq!(
+            tags_at_commit(&repo, &oid.to_string()).unwrap(),
+            vec!["release", "v1.0.0"]
+        );
+        assert_eq!(
+            tags_at_commit(&repo, &next.to_string()).unwrap(),
+            vec!["v1.1.0"]
+        );
+    }
+
+    #[test]
+    fn commits_between_and_nearest_tag_read_local_history() {
+        let tmp = tempdir().unwrap();
+        let (repo, base) = init_repo_with_commit(tmp.path());
+        fs::write(tmp.path().join("a.txt"), "a").unwrap();
+        let first = commit_file(&repo, Path::new("a.txt"), "Add a\n\nDetails");
+        fs::write(tmp.path().join("b.txt"), "b").unwrap();
+        let second = commit_file(&repo, Path::new("b.txt"), "Add b");
+
+        let commits = commits_between(&repo, &base.to_string(), &second.to_string()).unwrap();
+        let time = |oid: git2::Oid| repo.find_commit(oid).unwrap().time().seconds();
+        assert_eq!(
+            commits,
+            vec![
+                CommitSummary {
+                    sha: second.to_string(),
+                    summary: "Add b".into(),
+                    author: "tester".into(),
+                    time: time(second),
+                },
+                CommitSummary {
+                    sha: first.to_string(),
+                    summary: "Add a".into(),
+                    author: "tester".into(),
+                    time: time(first),
+                },
+            ]
+        );
+        assert!(
+            commits_between(&repo, &second.to_string(), &second.to_string())
+                .unwrap()
+                .is_empty()
+        );
+
+        assert_eq!(nearest_tag(&repo, &second.to_string()), None);
+        repo.tag_lightweight("v1.0.0", &repo.find_object(first, None).unwrap(), false)
+            .unwrap();
+        assert_eq!(
+            nearest_tag(&repo, &second.to_string()).as_deref(),
+            Some("v1.0.0")
+        );
+    }
+
     fn commit_file(repo: &git2::Repository, rel_path: &Path, message: &str) -> git2::Oid {
         let mut index = repo.index().unwrap();
         index.add_path(rel_path).unwrap();
@@ -429,14 +876,39 @@ mod tests {
     #[test]
     fn setup_remote_callbacks_configures_credentials() {
         CALLBACKS_CONFIGURED.store(0, Ordering::SeqCst);
-        let _ = setup_remote_callbacks();
+        let _ = setup_remote_callbacks(Deadline::with_limit(None));
         assert!(CALLBACKS_CONFIGURED.load(Ordering::SeqCst) > 0);
     }
 
+    #[test]
+    fn deadline_reports_hung_remotes_as_timed_out() {
+        let url = "https://example.com/owner/repo";
+        let aborted = || git2::Error::new(git2::ErrorCode::User, git2::ErrorClass::Net, "aborted");
+        let expired = Deadline::with_limit(Some(Duration::ZERO));
+        assert!(expired.expired());
+
+        let err = expired.classify(url, aborted());
+        let timeout = timed_out(&err).expect("callback abort after the deadline is a timeout");
+        assert_eq!(timeout.url, url);
+        assert_eq!(
+            err.to_string(),
+            "https://example.com/owner/repo did not respond within 0s"
+        );
+
+        let running = Deadline::with_limit(Some(Duration::from_secs(3600)));
+        assert!(timed_out(&running.classify(url, aborted())).is_none());
+        let socket = git2::Error::new(git2::ErrorCode::Timeout, git2::ErrorClass::Net, "timed out");
+        assert!(timed_out(&running.classify(url, socket)).is_some());
+
+        let disabled = Deadline::with_limit(None);
+        assert!(!disabled.expired());
+        assert!(timed_out(&disabled.classify(url, aborted())).is_none());
+    }
+
     #[test]
     fn setup_fetch_options_configures_download_tags() {
         FETCH_OPTIONS_CONFIGURED.store(0, Ordering::SeqCst);
-        let cb = setup_remote_callbacks();
+        let cb = setup_remote_callbacks(Deadline::with_limit(None));
         let _ = setup_fetch_options(cb);
         assert!(FETCH_OPTIONS_CONFIGURED.load(Ordering::SeqCst) > 0);
     }
@@ -460,6 +932,28 @@ mod tests {
         assert_eq!(checked, commit_oid.to_string());
     }
 
+    #[test]
+    fn dirty_paths_reports_modified_tracked_files_only() {
+        let tmp = tempdir().unwrap();
+        let (repo, _) = init_repo_with_commit(tmp.path());
+        assert!(dirty_paths(&repo).unwrap().is_empty());
+
+        std::fs::write(tmp.path().join("untracked.fish"), "echo hi").unwrap();
+        assert!(dirty_paths(&repo).unwrap().is_empty());
+
+        let tracked = repo
+            .head()
+            .unwrap()
+            .peel_to_tree()
+            .unwrap()
+            .iter()
+            .next()
+            .and_then(|entry| entry.name().map(str::to_string))
+            .unwrap();
+        std::fs::write(tmp.path().join(&tracked), "edited").unwrap();
+        assert_eq!(dirty_paths(&repo).unwrap(), vec![tracked]);
+    }
+
     #[test]
     fn checkout_commit_updates_worktree() {
         let tmp = tempdir().unwrap();
@@ -506,12 +1000,37 @@ mod tests {
             "v2.0.0".to_string(),
             "v1.3.0-beta1".to_string(),
         ];
-        let sel = pick_tag_for_version(&tags, "v1").unwrap().unwrap();
+        let sel = pick_tag_for_version(&tags, "v1", Channel::Stable)
+            .unwrap()
+            .unwrap();
         assert_eq!(sel, "v1.2.1");
-        let exact = pick_tag_for_version(&tags, "v2.0.0").unwrap().unwrap();
+        let exact = pick_tag_for_version(&tags, "v2.0.0", Channel::Stable)
+            .unwrap()
+            .unwrap();
         assert_eq!(exact, "v2.0.0");
     }
 
+    #[test]
+    fn pick_tag_for_version_includes_prereleases_on_prerelease_channel() {
+        let tags = vec![
+            "v1.2.1".to_string(),
+            "v1.3.0-beta.1".to_string(),
+            "v2.0.0-rc.1".to_string(),
+        ];
+        let stable = pick_tag_for_version(&tags, "v1", Channel::Stable)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stable, "v1.2.1");
+        let beta = pick_tag_for_version(&tags, "v1", Channel::Prerelease)
+            .unwrap()
+            .unwrap();
+        assert_eq!(beta, "v1.3.0-beta.1");
+        let rc = pick_tag_for_version(&tags, "2", Channel::Prerelease)
+            .unwrap()
+            .unwrap();
+        assert_eq!(rc, "v2.0.0-rc.1");
+    }
+
     #[test]
     fn pick_tag_for_version_dotted_non_semver_prefix() {
         let tags = vec![
@@ -520,7 +1039,9 @@ mod tests {
             "v1.4.5".to_string(),
             "2.0.0".to_string(),
         ];
-        let sel = pick_tag_for_version(&tags, "1").unwrap().unwrap();
+        let sel = pick_tag_for_version(&tags, "1", Channel::Stable)
+            .unwrap()
+            .unwrap();
         // Should prefer highest among 1.x.y (either with or without v prefix)
         assert!(sel == "1.3.0" || sel == "v1.4.5");
     }
@@ -528,7 +1049,9 @@ mod tests {
     #[test]
     fn pick_tag_for_version_prefers_exact_semver_match() {
         let tags = vec!["1.2.3".to_string(), "1.2.4".to_string()];
-        let sel = pick_tag_for_version(&tags, "1.2.3").unwrap().unwrap();
+        let sel = pick_tag_for_version(&tags, "1.2.3", Channel::Stable)
+            .unwrap()
+            .unwrap();
         assert_eq!(sel, "1.2.3");
     }
 
@@ -539,21 +1062,25 @@ mod tests {
             "1.2.3".to_string(),
             "1.3.0".to_string(),
         ];
-        let sel = pick_tag_for_version(&tags, "1.2").unwrap().unwrap();
+        let sel = pick_tag_for_version(&tags, "1.2", Channel::Stable)
+            .unwrap()
+            .unwrap();
         assert_eq!(sel, "1.2.3");
     }
 
     #[test]
     fn pick_tag_for_version_missing_non_semver_returns_none() {
         let tags = vec!["alpha".to_string(), "beta".to_string()];
-        let sel = pick_tag_for_version(&tags, "release").unwrap();
+        let sel = pick_tag_for_version(&tags, "release", Channel::Stable).unwrap();
         assert!(sel.is_none());
     }
 
     #[test]
     fn pick_tag_for_version_non_semver_dotted_suffix() {
         let tags = vec!["1.2.0-beta".to_string(), "1.3.0-rc1".to_string()];
-        let sel = pick_tag_for_version(&tags, "1").unwrap().unwrap();
+        let sel = pick_tag_for_version(&tags, "1", Channel::Stable)
+            .unwrap()
+            .unwrap();
         assert_eq!(sel, "1.3.0-rc1");
     }
 
@@ -620,6 +1147,70 @@ mod tests {
         assert!(tags.iter().any(|tag| tag == "orphan"));
     }
 
+    #[test]
+    fn clone_repository_shared_reuses_store_objects() {
+        let tmp = tempdir().unwrap();
+        let origin_path = tmp.path().join("origin.git");
+        let workdir_path = tmp.path().join("work");
+        let store_path = tmp.path().join("store");
+
+        let origin = git2::Repository::init_bare(&origin_path).unwrap();
+        let (work, commit_oid) = init_repo_with_commit(&workdir_path);
+        let obj = work.find_object(commit_oid, None).unwrap();
+        work.tag_lightweight("v1.0.0", &obj, false).unwrap();
+        work.remote("origin", origin_path.to_str().unwrap())
+            .unwrap();
+        let head_ref = work.head().unwrap().name().unwrap().to_string();
+        {
+            let mut remote = work.find_remote("origin").unwrap();
+            remote
+                .connect(git2::Direction::Push)
+                .and_then(|_| {
+                    remote.push(
+                        &[
+                            format!("{head_ref}:{head_ref}").as_str(),
+                            "refs/tags/v1.0.0:refs/tags/v1.0.0",
+                        ],
+                        None,
+                    )
+                })
+                .unwrap();
+        }
+        origin.set_head(&head_ref).unwrap();
+
+        let url = origin_path.to_str().unwrap();
+        let first =
+            clone_repository_shared(url, &tmp.path().join("a"), &store_path, "owner/a").unwrap();
+        let fork =
+            clone_repository_shared(url, &tmp.path().join("b"), &store_path, "fork/a").unwrap();
+
+        for repo in [&first, &fork] {
+            assert_eq!(get_latest_commit_sha(repo).unwrap(), commit_oid.to_string());
+            assert!(repo.path().join("objects/info/alternates").exists());
+            assert!(repo.find_reference("refs/remotes/origin/HEAD").is_ok());
+            assert_eq!(
+                default_branch(repo).as_deref(),
+                head_ref.strip_prefix("refs/heads/")
+            );
+            assert_eq!(list_tags(repo).unwrap(), vec!["v1.0.0".to_string()]);
+            assert_eq!(
+                get_remote_head_commit(repo).unwrap(),
+                commit_oid.to_string()
+            );
+        }
+        assert!(repo_has_no_local_objects(&first));
+        assert!(tmp.path().join("a").join("README.md").exists());
+    }
+
+    fn repo_has_no_local_objects(repo: &git2::Repository) -> bool {
+        let objects = repo.path().join("objects");
+        walkdir::WalkDir::new(&objects)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .all(|e| e.path().starts_with(objects.join("info")))
+    }
+
     #[test]
     fn get_latest_remote_commit_from_local_remote_repo() {
         use std::fs;
@@ -0,0 +1,134 @@
+use crate::{models::PluginRepo, update_cache, utils};
+
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{BufRead, Write},
+    path,
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+/// Append-only log under the pez data dir, one JSON object per line.
+pub(crate) const HISTORY_FILE_NAME: &str = ".history.jsonl";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Action {
+    Install,
+    Upgrade,
+    Uninstall,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct HistoryEntry {
+    /// Unix time (seconds) the action completed.
+    pub(crate) time: u64,
+    pub(crate) action: Action,
+    pub(crate) plugin: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) duration_ms: Option<u64>,
+}
+
+pub(crate) fn history_path() -> anyhow::Result<path::PathBuf> {
+    Ok(utils::load_pez_data_dir()?.join(HISTORY_FILE_NAME))
+}
+
+fn enabled() -> bool {
+    utils::load_config().is_ok_and(|(config, _)| config.settings.history)
+}
+
+/// With `[settings] history = true`, append an entry to the local history log.
+/// Failures are logged and never abort the command.
+pub(crate) fn record(action: Action, plugin: &PluginRepo, duration: Option<Duration>) {
+    if !enabled() {
+        return;
+    }
+    let entry = HistoryEntry {
+        time: update_cache::now_secs(),
+        action,
+        plugin: plugin.as_str(),
+        duration_ms: duration.map(|d| d.as_millis() as u64),
+    };
+    if let Err(e) = history_path().and_then(|path| append(&path, &entry)) {
+        warn!("Failed to record history: {:?}", e);
+    }
+}
+
+fn append(path: &path::Path, entry: &HistoryEntry) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Read the history log, skipping lines that do not parse. A missing log is empty.
+pub(crate) fn load(path: &path::Path) -> anyhow::Result<Vec<HistoryEntry>> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries = Vec::new();
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => debug!("Skipping unreadable history line: {e}"),
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_and_load_round_trip_skipping_bad_lines() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("nested").join(HISTORY_FILE_NAME);
+        assert!(load(&path).unwrap().is_empty());
+
+        let entry = HistoryEntry {
+            time: 42,
+            action: Action::Upgrade,
+            plugin: "owner/repo".into(),
+            duration_ms: Some(1500),
+        };
+        append(&path, &entry).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+        append(
+            &path,
+            &HistoryEntry {
+                time: 43,
+                action: Action::Install,
+                plugin: "owner/other".into(),
+                duration_ms: None,
+            },
+        )
+        .unwrap();
+
+        let entries = load(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], entry);
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .contains(r#""action":"upgrade""#)
+        );
+    }
+}
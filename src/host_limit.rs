@@ -0,0 +1,179 @@
+use crate::utils;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Condvar, Mutex, OnceLock},
+};
+use tracing::debug;
+
+/// Simultaneous clones/fetches allowed per host unless `[settings.hosts."<host>"]`
+/// sets `max_concurrent`. Keeps high `--jobs` values from tripping abuse detection.
+pub(crate) const DEFAULT_MAX_CONCURRENT: usize = 4;
+
+/// Counting semaphore keyed by host. Network operations run on blocking threads, so
+/// waiting on a condition variable is fine.
+pub(crate) struct HostLimiter {
+    limits: BTreeMap<String, usize>,
+    active: Mutex<HashMap<String, usize>>,
+    released: Condvar,
+}
+
+/// Slot held for the duration of one network operation; released on drop.
+pub(crate) struct HostPermit<'a> {
+    limiter: &'a HostLimiter,
+    host: String,
+}
+
+impl HostLimiter {
+    pub(crate) fn new(limits: BTreeMap<String, usize>) -> Self {
+        Self {
+            limits,
+            active: Mutex::new(HashMap::new()),
+            released: Condvar::new(),
+        }
+    }
+
+    fn limit(&self, host: &str) -> usize {
+        self.limits
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(host))
+            .map_or(DEFAULT_MAX_CONCURRENT, |(_, limit)| *limit)
+            .max(1)
+    }
+
+    /// Block until `host` has a free slot.
+    pub(crate) fn acquire(&self, host: &str) -> HostPermit<'_> {
+        let host = host.to_ascii_lowercase();
+        let limit = self.limit(&host);
+        let mut active = self.active.lock().unwrap();
+        while active.get(&host).copied().unwrap_or(0) >= limit {
+            debug!(target: crate::trace::GIT, host, limit, "Waiting for a free host slot");
+            active = self.released.wait(active).unwrap();
+        }
+        *active.entry(host.clone()).or_default() += 1;
+        HostPermit {
+            limiter: self,
+            host,
+        }
+    }
+}
+
+impl Drop for HostPermit<'_> {
+    fn drop(&mut self) {
+        let mut active = self.limiter.active.lock().unwrap();
+        if let Some(count) = active.get_mut(&self.host) {
+            *count = count.saturating_sub(1);
+        }
+        self.limiter.released.notify_all();
+    }
+}
+
+/// Process-wide limiter configured from pez.toml on first use.
+fn global() -> &'static HostLimiter {
+    static LIMITER: OnceLock<HostLimiter> = OnceLock::new();
+    LIMITER.get_or_init(|| {
+        let limits = utils::load_config()
+            .map(|(config, _)| {
+                config
+                    .settings
+                    .hosts
+                    .into_iter()
+                    .map(|(host, settings)| (host, settings.max_concurrent))
+                    .collect()
+            })
+            .unwrap_or_default();
+        HostLimiter::new(limits)
+    })
+}
+
+/// Take a slot for the host of `url`; local paths and unparsable URLs are not limited.
+pub(crate) fn acquire(url: &str) -> Option<HostPermit<'static>> {
+    host_of(url).map(|host| global().acquire(&host))
+}
+
+/// Host name of a git URL (`https://host/...`, `ssh://user@host:22/...`, or
+/// `user@host:path`).
+pub(crate) fn host_of(url: &str) -> Option<String> {
+    let authority = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            if scheme.eq_ignore_ascii_case("file") {
+                return None;
+            }
+            rest.split('/').next()?
+        }
+        None => {
+            let (authority, _) = url.split_once(':')?;
+            if authority.contains('/') {
+                return None;
+            }
+            authority
+        }
+    };
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn host_of_parses_git_urls() {
+        assert_eq!(
+            host_of("https://github.com/owner/repo").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            host_of("ssh://git@GitLab.com:2222/group/repo.git").as_deref(),
+            Some("gitlab.com")
+        );
+        assert_eq!(
+            host_of("git@codeberg.org:owner/repo.git").as_deref(),
+            Some("codeberg.org")
+        );
+        assert_eq!(host_of("/home/me/plugin"), None);
+        assert_eq!(host_of("./plugin:v2/x"), None);
+        assert_eq!(host_of("file:///tmp/repo"), None);
+    }
+
+    #[test]
+    fn limiter_caps_concurrency_per_host() {
+        let limiter = Arc::new(HostLimiter::new(BTreeMap::from([(
+            "GitHub.com".to_string(),
+            2,
+        )])));
+        assert_eq!(limiter.limit("github.com"), 2);
+        assert_eq!(limiter.limit("gitlab.com"), DEFAULT_MAX_CONCURRENT);
+
+        let current = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..6)
+            .map(|_| {
+                let limiter = Arc::clone(&limiter);
+                let current = Arc::clone(&current);
+                let peak = Arc::clone(&peak);
+                thread::spawn(move || {
+                    let _permit = limiter.acquire("github.com");
+                    let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    current.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert!(limiter.active.lock().unwrap().values().all(|n| *n == 0));
+    }
+}
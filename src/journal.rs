@@ -0,0 +1,178 @@
+use crate::{update_cache, utils};
+
+use serde_derive::{Deserialize, Serialize};
+use std::{fs, future::Future, path};
+use tracing::warn;
+
+/// State before the last command that changed installed plugins, under the pez data dir.
+pub(crate) const JOURNAL_FILE_NAME: &str = ".journal.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct Journal {
+    /// Unix time (seconds) the command finished.
+    pub(crate) time: u64,
+    /// The command that changed the state, e.g. `upgrade`.
+    pub(crate) command: String,
+    /// pez-lock.toml before the command ran; `None` when there was none.
+    pub(crate) lock: Option<String>,
+    /// pez.toml before the command ran; `None` when there was none.
+    pub(crate) config: Option<String>,
+}
+
+/// Raw pez-lock.toml and pez.toml contents; a missing file is `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct State {
+    pub(crate) lock: Option<String>,
+    pub(crate) config: Option<String>,
+}
+
+impl State {
+    pub(crate) fn read() -> anyhow::Result<Self> {
+        Ok(Self {
+            lock: read_optional(&lock_path()?)?,
+            config: read_optional(&config_path()?)?,
+        })
+    }
+
+    /// Write both files back, removing the ones that did not exist.
+    pub(crate) fn write(&self) -> anyhow::Result<()> {
+        write_optional(&lock_path()?, self.lock.as_deref())?;
+        write_optional(&config_path()?, self.config.as_deref())
+    }
+}
+
+pub(crate) fn journal_path() -> anyhow::Result<path::PathBuf> {
+    Ok(utils::load_pez_data_dir()?.join(JOURNAL_FILE_NAME))
+}
+
+pub(crate) fn lock_path() -> anyhow::Result<path::PathBuf> {
+    Ok(utils::load_lock_file_dir()?.join("pez-lock.toml"))
+}
+
+fn config_path() -> anyhow::Result<path::PathBuf> {
+    Ok(utils::load_pez_config_dir()?.join("pez.toml"))
+}
+
+/// Run `operation` and, when it changed pez-lock.toml or pez.toml (even if it then
+/// failed), remember the previous state for `pez rollback`. Failures to record are
+/// logged and never abort the command.
+pub(crate) async fn recorded<F, T>(command: &str, operation: F) -> anyhow::Result<T>
+where
+    F: Future<Output = anyhow::Result<T>>,
+{
+    let before = State::read();
+    let result = operation.await;
+    if let Ok(before) = before
+        && State::read().is_ok_and(|after| after != before)
+    {
+        let journal = Journal {
+            time: update_cache::now_secs(),
+            command: command.to_string(),
+            lock: before.lock,
+            config: before.config,
+        };
+        if let Err(e) = journal_path().and_then(|path| save(&path, &journal)) {
+            warn!("Failed to record state for `pez rollback`: {:?}", e);
+        }
+    }
+    result
+}
+
+pub(crate) fn save(path: &path::Path, journal: &Journal) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(journal)?)?;
+    Ok(())
+}
+
+/// The recorded journal, or `None` when nothing has been recorded yet.
+pub(crate) fn load(path: &path::Path) -> anyhow::Result<Option<Journal>> {
+    match read_optional(path)? {
+        Some(content) => Ok(Some(serde_json::from_str(&content)?)),
+        None => Ok(None),
+    }
+}
+
+fn read_optional(path: &path::Path) -> anyhow::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_optional(path: &path::Path, content: Option<&str>) -> anyhow::Result<()> {
+    match content {
+        Some(content) => {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_support::{env::TestEnvironmentSetup, log::env_lock};
+
+    #[test]
+    fn recorded_keeps_previous_state_only_when_it_changed() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let env = TestEnvironmentSetup::new();
+        let _lock = env_lock().lock().unwrap();
+        let saved: Vec<_> = ["PEZ_CONFIG_DIR", "PEZ_DATA_DIR", "__fish_config_dir"]
+            .iter()
+            .map(|key| (*key, std::env::var_os(key)))
+            .collect();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &env.config_dir);
+            std::env::set_var("PEZ_DATA_DIR", &env.data_dir);
+            std::env::set_var("__fish_config_dir", &env.fish_config_dir);
+        }
+        let journal_file = journal_path().unwrap();
+        let lock_file = lock_path().unwrap();
+
+        runtime
+            .block_on(recorded("list", async { Ok(()) }))
+            .unwrap();
+        assert_eq!(load(&journal_file).unwrap(), None);
+
+        fs::write(&lock_file, "version = 2\n").unwrap();
+        let result: anyhow::Result<()> = runtime.block_on(recorded("install", async {
+            fs::write(&lock_file, "version = 2\n# changed\n")?;
+            anyhow::bail!("failed halfway")
+        }));
+        assert!(result.is_err());
+        let journal = load(&journal_file).unwrap().unwrap();
+        assert_eq!(journal.command, "install");
+        assert_eq!(journal.lock.as_deref(), Some("version = 2\n"));
+        assert_eq!(journal.config, None);
+
+        State {
+            lock: None,
+            config: None,
+        }
+        .write()
+        .unwrap();
+        assert!(!lock_file.exists());
+
+        unsafe {
+            for (key, value) in saved {
+                match value {
+                    Some(v) => std::env::set_var(key, v),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,418 @@
+//! Validation against the subset of JSON Schema (draft-07) that `config.schema.json`
+//! uses, with messages phrased for pez.toml rather than for the schema.
+
+use regex::Regex;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Where a value sits in the document, e.g. `plugins[2].tag`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct InstancePath(pub(crate) Vec<PathSegment>);
+
+impl fmt::Display for InstancePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if i == 0 => write!(f, "{key}")?,
+                PathSegment::Key(key) => write!(f, ".{key}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SchemaError {
+    pub(crate) path: InstancePath,
+    pub(crate) message: String,
+}
+
+/// Every violation of `schema` in `instance`, in document order.
+pub(crate) fn validate(schema: &Value, instance: &Value) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    Validator { root: schema }.check(schema, instance, &mut Vec::new(), &mut errors);
+    errors
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl Validator<'_> {
+    fn is_valid(&self, schema: &Value, instance: &Value) -> bool {
+        let mut errors = Vec::new();
+        self.check(schema, instance, &mut Vec::new(), &mut errors);
+        errors.is_empty()
+    }
+
+    fn check(
+        &self,
+        schema: &Value,
+        instance: &Value,
+        path: &mut Vec<PathSegment>,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let Some(schema) = schema.as_object() else {
+            return;
+        };
+
+        if let Some(target) = schema.get("$ref").and_then(Value::as_str) {
+            match target
+                .strip_prefix('#')
+                .and_then(|pointer| self.root.pointer(pointer))
+            {
+                Some(resolved) => self.check(resolved, instance, path, errors),
+                None => fail(errors, path, format!("schema reference {target} not found")),
+            }
+            return;
+        }
+
+        if let Some(expected) = schema.get("type")
+            && !type_matches(expected, instance)
+        {
+            let expected = match expected {
+                Value::Array(types) => types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+                other => other.as_str().unwrap_or_default().to_string(),
+            };
+            fail(
+                errors,
+                path,
+                format!("expected {expected}, found {}", type_name(instance)),
+            );
+            return;
+        }
+
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+            && !allowed.contains(instance)
+        {
+            fail(
+                errors,
+                path,
+                format!("must be one of {}", list(allowed.iter())),
+            );
+        }
+
+        if let (Some(pattern), Some(text)) = (
+            schema.get("pattern").and_then(Value::as_str),
+            instance.as_str(),
+        ) && Regex::new(pattern).is_ok_and(|re| !re.is_match(text))
+        {
+            fail(errors, path, format!("`{text}` does not match `{pattern}`"));
+        }
+
+        if let (Some(minimum), Some(number)) = (
+            schema.get("minimum").and_then(Value::as_f64),
+            instance.as_f64(),
+        ) && number < minimum
+        {
+            fail(errors, path, format!("must be at least {minimum}"));
+        }
+
+        if let Some(object) = instance.as_object() {
+            for key in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                if !object.contains_key(key) {
+                    fail(errors, path, format!("missing `{key}`"));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, value) in object {
+                path.push(PathSegment::Key(key.clone()));
+                match (
+                    properties.and_then(|p| p.get(key)),
+                    schema.get("additionalProperties"),
+                ) {
+                    (Some(property), _) => self.check(property, value, path, errors),
+                    (None, Some(Value::Bool(false))) => {
+                        fail(errors, path, format!("unknown key `{key}`"))
+                    }
+                    (None, Some(additional)) => self.check(additional, value, path, errors),
+                    (None, None) => {}
+                }
+                path.pop();
+            }
+        }
+
+        if let (Some(items), Some(array)) = (schema.get("items"), instance.as_array()) {
+            for (index, item) in array.iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                self.check(items, item, path, errors);
+                path.pop();
+            }
+        }
+
+        for sub in schema
+            .get("allOf")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            self.check(sub, instance, path, errors);
+        }
+
+        if let Some(branches) = schema.get("anyOf").and_then(Value::as_array)
+            && !branches.iter().any(|b| self.is_valid(b, instance))
+        {
+            errors.push(self.no_branch_error(branches, path));
+        }
+
+        if let Some(branches) = schema.get("oneOf").and_then(Value::as_array) {
+            let matched: Vec<&Value> = branches
+                .iter()
+                .filter(|b| self.is_valid(b, instance))
+                .collect();
+            match matched.len() {
+                1 => {}
+                0 => errors.push(self.no_branch_error(branches, path)),
+                _ => {
+                    let keys: Option<Vec<&str>> = matched
+                        .iter()
+                        .map(|b| required_keys(b))
+                        .collect::<Option<Vec<_>>>()
+                        .map(|keys| keys.concat());
+                    let message = match keys.as_deref() {
+                        Some([a, b]) => format!("both `{a}` and `{b}` set; use only one"),
+                        Some(keys) => format!("{} all set; use only one", backticked(keys)),
+                        None => "matches more than one allowed form".to_string(),
+                    };
+                    fail(errors, path, message);
+                }
+            }
+        }
+
+        if let Some(forbidden) = schema.get("not")
+            && self.is_valid(forbidden, instance)
+        {
+            fail(errors, path, not_message(forbidden, instance, None));
+        }
+
+        if let (Some(condition), Some(then)) = (schema.get("if"), schema.get("then"))
+            && self.is_valid(condition, instance)
+        {
+            // `then: { not: ... }` is the only shape pez uses; say what it conflicts with.
+            match then.get("not") {
+                Some(forbidden) if then.as_object().is_some_and(|t| t.len() == 1) => {
+                    if self.is_valid(forbidden, instance) {
+                        fail(
+                            errors,
+                            path,
+                            not_message(forbidden, instance, required_keys(condition)),
+                        );
+                    }
+                }
+                _ => self.check(then, instance, path, errors),
+            }
+        }
+    }
+
+    fn no_branch_error(&self, branches: &[Value], path: &[PathSegment]) -> SchemaError {
+        let keys: Option<Vec<&str>> = branches
+            .iter()
+            .map(required_keys)
+            .collect::<Option<Vec<_>>>()
+            .map(|keys| keys.concat());
+        SchemaError {
+            path: InstancePath(path.to_vec()),
+            message: match keys {
+                Some(keys) => format!("one of {} is required", backticked(&keys)),
+                None => "does not match any allowed form".to_string(),
+            },
+        }
+    }
+}
+
+fn fail(errors: &mut Vec<SchemaError>, path: &[PathSegment], message: String) {
+    errors.push(SchemaError {
+        path: InstancePath(path.to_vec()),
+        message,
+    });
+}
+
+/// The keys a schema of the form `{ "required": [...] }`, or an `anyOf` of those,
+/// asks for.
+fn required_keys(schema: &Value) -> Option<Vec<&str>> {
+    let object = schema.as_object()?;
+    if object.len() != 1 {
+        return None;
+    }
+    if let Some(required) = object.get("required") {
+        return required.as_array()?.iter().map(Value::as_str).collect();
+    }
+    let branches = object.get("anyOf")?.as_array()?;
+    branches
+        .iter()
+        .map(required_keys)
+        .collect::<Option<Vec<_>>>()
+        .map(|keys| keys.concat())
+}
+
+/// Explain a forbidden `not` match by naming the keys that caused it.
+fn not_message(forbidden: &Value, instance: &Value, context: Option<Vec<&str>>) -> String {
+    let present: Vec<&str> = required_keys(forbidden)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|key| instance.get(key).is_some())
+        .collect();
+    let subject = match present.as_slice() {
+        [] => "this value is not allowed".to_string(),
+        [key] => format!("`{key}` is not allowed"),
+        keys => format!("{} are not allowed", backticked(keys)),
+    };
+    match context {
+        Some(keys) if !keys.is_empty() => format!("{subject} with {}", backticked(&keys)),
+        _ => subject,
+    }
+}
+
+fn backticked(keys: &[&str]) -> String {
+    keys.iter()
+        .map(|key| format!("`{key}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn list<'a>(values: impl Iterator<Item = &'a Value>) -> String {
+    values
+        .map(|value| match value {
+            Value::String(s) => format!("`{s}`"),
+            other => format!("`{other}`"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn type_matches(expected: &Value, instance: &Value) -> bool {
+    match expected {
+        Value::String(name) => is_type(name, instance),
+        Value::Array(names) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .any(|name| is_type(name, instance)),
+        _ => true,
+    }
+}
+
+fn is_type(name: &str, instance: &Value) -> bool {
+    match name {
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "boolean" => instance.is_boolean(),
+        "null" => instance.is_null(),
+        "number" => instance.is_number(),
+        "integer" => instance.is_i64() || instance.is_u64(),
+        _ => true,
+    }
+}
+
+fn type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "table",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn messages(schema: &Value, instance: &Value) -> Vec<String> {
+        validate(schema, instance)
+            .into_iter()
+            .map(|e| format!("{}: {}", e.path, e.message))
+            .collect()
+    }
+
+    #[test]
+    fn reports_types_unknown_keys_and_patterns_with_paths() {
+        let schema = json!({
+            "type": "object",
+            "additionalProperties": false,
+            "definitions": { "Port": { "type": "integer", "minimum": 1 } },
+            "properties": {
+                "items": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {
+                            "name": { "type": "string", "pattern": "^[a-z]+$" },
+                            "port": { "$ref": "#/definitions/Port" },
+                            "kind": { "enum": ["a", "b"] }
+                        }
+                    }
+                }
+            }
+        });
+        let instance = json!({
+            "items": [
+                { "name": "ok", "port": 1 },
+                { "name": "Bad", "port": 0, "kind": "c", "extra": true },
+                { "name": 3 }
+            ],
+            "other": 1
+        });
+        assert_eq!(
+            messages(&schema, &instance),
+            vec![
+                "items[1].extra: unknown key `extra`",
+                "items[1].kind: must be one of `a`, `b`",
+                "items[1].name: `Bad` does not match `^[a-z]+$`",
+                "items[1].port: must be at least 1",
+                "items[2].name: expected string, found integer",
+                "other: unknown key `other`",
+            ]
+        );
+    }
+
+    #[test]
+    fn explains_required_key_combinators() {
+        let selector = json!({ "anyOf": [{ "required": ["tag"] }, { "required": ["branch"] }] });
+        let schema = json!({
+            "allOf": [
+                { "oneOf": [{ "required": ["repo"] }, { "required": ["path"] }] },
+                { "oneOf": [{ "not": selector }, { "required": ["tag"] }, { "required": ["branch"] }] },
+                { "if": { "required": ["path"] }, "then": { "not": selector } }
+            ]
+        });
+
+        assert!(validate(&schema, &json!({ "repo": "o/r", "tag": "v1" })).is_empty());
+        assert_eq!(
+            messages(
+                &schema,
+                &json!({ "repo": "o/r", "tag": "v1", "branch": "main" })
+            ),
+            vec![": both `tag` and `branch` set; use only one"]
+        );
+        assert_eq!(
+            messages(&schema, &json!({ "tag": "v1" })),
+            vec![": one of `repo`, `path` is required"]
+        );
+        assert_eq!(
+            messages(&schema, &json!({ "path": "/p", "tag": "v1" })),
+            vec![": `tag` is not allowed with `path`"]
+        );
+    }
+}
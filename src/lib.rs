@@ -2,27 +2,54 @@ use clap::Parser;
 use tracing::Level;
 use tracing_subscriber::EnvFilter;
 
+mod aliases;
+mod autoload;
+mod backup;
 mod cli;
 mod cmd;
 mod config;
+mod duplicates;
+#[cfg(feature = "forge-api")]
+mod forge;
 mod git;
+mod history;
+mod host_limit;
+mod journal;
+mod json_schema;
 mod lock_file;
 mod models;
+mod notify;
+mod output;
+mod prompt;
 mod resolver;
+mod runtime;
+mod trace;
+mod universal_vars;
+mod update_cache;
 mod utils;
+mod vendor;
 
 #[cfg(feature = "schema-gen")]
 pub mod schema;
 
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+
 #[cfg(test)]
 mod tests_support;
 
+/// The process exit code for an error returned by [`run`]: 1 unless the command chose
+/// another one.
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    err.downcast_ref::<utils::ExitError>()
+        .map_or(1, |exit| exit.code)
+}
+
 pub async fn run() -> anyhow::Result<()> {
     let cli = cli::Cli::parse();
-    let jobs_override = cli.jobs;
-    utils::set_cli_jobs_override(jobs_override);
+    let options = runtime::RuntimeOptions::from_cli(&cli);
     // Configure console color policy up front (affects console::style rendering)
-    let colors_enabled = utils::colors_enabled_for_stderr();
+    let colors_enabled = options.colors_enabled();
     console::set_colors_enabled(colors_enabled);
     console::set_colors_enabled_stderr(colors_enabled);
 
@@ -32,9 +59,11 @@ pub async fn run() -> anyhow::Result<()> {
         1 => Level::INFO,
         _ => Level::DEBUG,
     };
-    let filter = std::env::var("RUST_LOG")
+    let base = std::env::var("RUST_LOG")
         .ok()
         .unwrap_or_else(|| level.as_str().to_lowercase());
+    let traced: Vec<&str> = cli.trace.iter().map(|t| t.target()).collect();
+    let filter = trace::filter_directives(&base, &traced);
 
     tracing_subscriber::fmt()
         .compact()
@@ -43,47 +72,211 @@ pub async fn run() -> anyhow::Result<()> {
         .without_time()
         .with_env_filter(EnvFilter::new(filter))
         .with_ansi(colors_enabled)
+        .with_writer(|| output::Stdout)
         .init();
 
     match &cli.command {
-        cli::Commands::Init => {
-            cmd::init::run()?;
+        cli::Commands::Init(args) => {
+            cmd::init::run(args)?;
         }
         cli::Commands::Install(args) => {
-            cmd::install::run(args).await?;
+            journal::recorded(
+                "install",
+                notify::notify_on_finish("install", cmd::install::run(args, &options)),
+            )
+            .await?;
         }
         cli::Commands::Uninstall(args) => {
-            cmd::uninstall::run(args).await?;
+            journal::recorded("uninstall", cmd::uninstall::run(args, &options)).await?;
         }
         cli::Commands::Upgrade(args) => {
-            cmd::upgrade::run(args).await?;
+            journal::recorded(
+                "upgrade",
+                notify::notify_on_finish("upgrade", cmd::upgrade::run(args, &options)),
+            )
+            .await?;
+        }
+        cli::Commands::Update(args) => {
+            journal::recorded(
+                "update",
+                notify::notify_on_finish("update", cmd::update::run(args, &options)),
+            )
+            .await?;
+        }
+        cli::Commands::Rollback(args) => {
+            journal::recorded("rollback", async { cmd::rollback::run(args) }).await?;
+        }
+        cli::Commands::Snapshot(args) => {
+            cmd::snapshot::save(args)?;
+        }
+        cli::Commands::Restore(args) => {
+            journal::recorded("restore", async { cmd::snapshot::restore(args) }).await?;
         }
         cli::Commands::List(args) => {
-            let _ = cmd::list::run(args)?;
+            let _ = cmd::list::run(args, &options)?;
         }
         cli::Commands::Prune(args) => {
-            cmd::prune::run(args).await?;
+            cmd::prune::run(args, &options).await?;
+        }
+        cli::Commands::Cache(args) => {
+            cmd::cache::run(args)?;
+        }
+        cli::Commands::Gc(args) => {
+            cmd::gc::run(args)?;
+        }
+        cli::Commands::Lock(args) => {
+            cmd::lock::run(args, &options)?;
+        }
+        cli::Commands::Pin(args) => {
+            cmd::pin::pin(args)?;
+        }
+        cli::Commands::Unpin(args) => {
+            cmd::pin::unpin(args)?;
+        }
+        cli::Commands::Freeze => {
+            cmd::pin::freeze()?;
+        }
+        cli::Commands::Unfreeze => {
+            cmd::pin::unfreeze()?;
+        }
+        cli::Commands::Disable(args) => {
+            cmd::enable::disable(args)?;
+        }
+        cli::Commands::Enable(args) => {
+            cmd::enable::enable(args)?;
+        }
+        cli::Commands::Edit => {
+            cmd::edit::run()?;
+        }
+        cli::Commands::Status(args) => {
+            let _ = cmd::status::run(args, &options)?;
+        }
+        cli::Commands::Diff(args) => {
+            let _ = cmd::diff::run(args, &options)?;
+        }
+        cli::Commands::Outdated(args) => {
+            let _ = cmd::outdated::run(args, &options)?;
+        }
+        cli::Commands::Changelog(args) => {
+            let _ = cmd::changelog::run(args)?;
+        }
+        cli::Commands::Info(args) => {
+            let _ = cmd::info::run(args, &options)?;
+        }
+        cli::Commands::Path(args) => {
+            let _ = cmd::info::path(args)?;
         }
         cli::Commands::Doctor(args) => {
-            let _ = cmd::doctor::run(args)?;
+            let _ = cmd::doctor::run(args, &options)?;
+        }
+        cli::Commands::Check => {
+            cmd::check::run()?;
+        }
+        cli::Commands::Smoke(args) => {
+            let _ = cmd::smoke::run(args)?;
         }
         cli::Commands::Migrate(args) => {
-            cmd::migrate::run(args).await?;
+            cmd::migrate::run(args, &options).await?;
+        }
+        cli::Commands::Export(args) => {
+            cmd::export::run(args)?;
+        }
+        cli::Commands::Import(args) => {
+            cmd::import::run(args, &options).await?;
         }
         cli::Commands::Files(args) => {
-            let _ = cmd::files::run(args)?;
+            let _ = cmd::files::run(args, &options)?;
+        }
+        cli::Commands::Why(args) => {
+            let _ = cmd::why::run(args, &options)?;
+        }
+        cli::Commands::Which(args) => {
+            let _ = cmd::which::run(args, &options)?;
+        }
+        cli::Commands::Theme(args) => {
+            cmd::theme::run(args)?;
+        }
+        cli::Commands::Open(args) => {
+            let _ = cmd::open::run(args)?;
+        }
+        cli::Commands::Tree(args) => {
+            let _ = cmd::tree::run(args, &options)?;
+        }
+        #[cfg(feature = "tui")]
+        cli::Commands::Ui => {
+            cmd::ui::run(&options).await?;
         }
         cli::Commands::Activate(args) => match args.shell {
             cli::ShellType::Fish => {
                 let _ = cmd::activate::run_fish();
             }
         },
-        cli::Commands::Completions { shell } => match shell {
-            cli::ShellType::Fish => {
+        cli::Commands::Repair(args) => {
+            let _ = cmd::repair::run(args)?;
+        }
+        cli::Commands::Verify(args) => {
+            let _ = cmd::verify::run(args, &options)?;
+        }
+        cli::Commands::Sync(args) => {
+            notify::notify_on_finish("sync", cmd::sync::run(args, &options)).await?;
+        }
+        cli::Commands::VerifyActivate => {
+            let _ = cmd::verify_activate::run()?;
+        }
+        cli::Commands::Version(args) => {
+            let _ = cmd::version::run(args, &options)?;
+        }
+        cli::Commands::Stats(args) => {
+            let _ = cmd::stats::run(args, &options)?;
+        }
+        cli::Commands::Search(args) => {
+            let _ = cmd::search::run(args, &options)?;
+        }
+        cli::Commands::Completions(args) => match (&args.command, &args.shell) {
+            (Some(cli::CompletionsCommand::SelfTest), _) => {
+                cmd::completion::self_test()?;
+            }
+            (None, Some(cli::ShellType::Fish)) | (None, None) => {
                 let _ = cmd::completion::generate_fish_completion()?;
             }
         },
+        cli::Commands::CompletionData => {
+            let _ = cmd::completion_data::run(&options)?;
+        }
+    }
+
+    if mutates_plugins(&cli.command) {
+        universal_vars::publish();
+        cmd::export::sync_fish_plugins();
     }
 
     Ok(())
 }
+
+/// Commands that can change pez.toml or what is installed, after which the
+/// post-command hooks run.
+fn mutates_plugins(command: &cli::Commands) -> bool {
+    match command {
+        cli::Commands::Install(_)
+        | cli::Commands::Uninstall(_)
+        | cli::Commands::Upgrade(_)
+        | cli::Commands::Update(_)
+        | cli::Commands::Rollback(_)
+        | cli::Commands::Restore(_)
+        | cli::Commands::Prune(_)
+        | cli::Commands::Gc(_)
+        | cli::Commands::Sync(_)
+        | cli::Commands::Migrate(_)
+        | cli::Commands::Import(_)
+        | cli::Commands::Pin(_)
+        | cli::Commands::Unpin(_)
+        | cli::Commands::Freeze
+        | cli::Commands::Unfreeze
+        | cli::Commands::Disable(_)
+        | cli::Commands::Enable(_)
+        | cli::Commands::Edit => true,
+        #[cfg(feature = "tui")]
+        cli::Commands::Ui => true,
+        _ => false,
+    }
+}
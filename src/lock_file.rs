@@ -1,9 +1,9 @@
-use crate::{models::PluginRepo, models::TargetDir};
+use crate::{models::PluginRepo, models::TargetDir, resolver};
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 use serde_derive::{Deserialize, Serialize};
 use std::{fs, path};
-use tracing::error;
+use tracing::{error, warn};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct LockFile {
@@ -11,16 +11,44 @@ pub(crate) struct LockFile {
     pub(crate) plugins: Vec<Plugin>,
 }
 
+/// Lock file format versions this build can read.
+/// Version 2 added per-plugin `updated_at` timestamps.
+pub(crate) const SUPPORTED_VERSIONS: &[u32] = &[1, CURRENT_VERSION];
+pub(crate) const CURRENT_VERSION: u32 = 2;
+
 pub(crate) fn init() -> LockFile {
     LockFile {
-        version: 1,
+        version: CURRENT_VERSION,
         plugins: vec![],
     }
 }
 
 pub(crate) fn load(path: &path::Path) -> anyhow::Result<LockFile> {
     let content = fs::read_to_string(path)?;
-    let lock_file = toml::from_str(&content)?;
+    parse(&content, &path.display().to_string())
+}
+
+/// Parse lock file contents; `origin` names the source in error messages.
+pub(crate) fn parse(content: &str, origin: &str) -> anyhow::Result<LockFile> {
+    let mut lock_file: LockFile = toml::from_str(content)?;
+    if !SUPPORTED_VERSIONS.contains(&lock_file.version) {
+        anyhow::bail!(
+            "Unsupported lock file version {} in {} (supported: {:?})",
+            lock_file.version,
+            origin,
+            SUPPORTED_VERSIONS
+        );
+    }
+    // Older versions only lack optional fields; the next save writes the current version.
+    lock_file.version = CURRENT_VERSION;
+    // Entries written before sources were compared canonically may repeat a plugin under
+    // another spelling of its URL; the next save writes the merged list.
+    for dropped in lock_file.dedupe_sources() {
+        warn!(
+            "Merged duplicate lock entry for {} (source {}) in {}",
+            dropped.repo, dropped.source, origin
+        );
+    }
 
     Ok(lock_file)
 }
@@ -37,7 +65,7 @@ impl LockFile {
         if self
             .plugins
             .iter()
-            .any(|p| p.source == plugin.source || p.name == plugin.name)
+            .any(|p| p.has_source(&plugin.source) || p.name == plugin.name)
         {
             error!(
                 "Plugin already exists: name={}, source={}",
@@ -54,7 +82,7 @@ impl LockFile {
     }
 
     pub(crate) fn remove_plugin(&mut self, source: &str) {
-        self.plugins.retain(|p| p.source != source);
+        self.plugins.retain(|p| !p.has_source(source));
     }
 
     pub(crate) fn get_plugin_by_repo(&self, repo: &PluginRepo) -> Option<&Plugin> {
@@ -69,8 +97,11 @@ impl LockFile {
 
     pub(crate) fn merge_plugins(&mut self, new_plugins: Vec<Plugin>) {
         for new_plugin in new_plugins {
-            let source = new_plugin.source.clone();
-            if let Some(plugin) = self.plugins.iter_mut().find(|p| p.source == source) {
+            if let Some(plugin) = self
+                .plugins
+                .iter_mut()
+                .find(|p| p.has_source(&new_plugin.source))
+            {
                 *plugin = new_plugin;
             } else {
                 self.plugins.push(new_plugin);
@@ -78,11 +109,52 @@ impl LockFile {
         }
     }
 
+    /// Collapse entries whose sources are the same plugin spelled differently, keeping
+    /// the last one (the most recently written) and folding in the files only the
+    /// others recorded, so uninstall still removes them. Returns the dropped entries.
+    pub(crate) fn dedupe_sources(&mut self) -> Vec<Plugin> {
+        let mut dropped = Vec::new();
+        let mut kept: Vec<Plugin> = Vec::with_capacity(self.plugins.len());
+        for plugin in self.plugins.drain(..).rev() {
+            match kept.iter_mut().find(|k| k.has_source(&plugin.source)) {
+                Some(newer) => {
+                    for file in &plugin.files {
+                        if !newer.files.contains(file) {
+                            newer.files.push(file.clone());
+                        }
+                    }
+                    dropped.push(plugin);
+                }
+                None => kept.push(plugin),
+            }
+        }
+        kept.reverse();
+        self.plugins = kept;
+        dropped
+    }
+
+    /// Drop plugins `config` disables. Their files are not installed, so checks of the
+    /// installed files should not expect them.
+    pub(crate) fn without_disabled(mut self, config: Option<&crate::config::Config>) -> Self {
+        if let Some(config) = config {
+            self.plugins.retain(|p| !config.is_disabled(&p.repo));
+        }
+        self
+    }
+
     /// Returns true if a plugin with the given repo exists.
     pub(crate) fn contains_repo(&self, repo: &PluginRepo) -> bool {
         self.plugins.iter().any(|p| &p.repo == repo)
     }
 
+    /// Whether a plugin other than `repo` also lists `file`, as when plugins ship
+    /// byte-identical copies of a shared helper.
+    pub(crate) fn is_claimed_by_other(&self, repo: &PluginRepo, file: &PluginFile) -> bool {
+        self.plugins
+            .iter()
+            .any(|p| &p.repo != repo && p.files.contains(file))
+    }
+
     /// Upsert a plugin by repo (or source) semantics. If repo exists, update; otherwise add.
     pub(crate) fn upsert_plugin_by_repo(&mut self, plugin: Plugin) -> anyhow::Result<()> {
         if self.contains_repo(&plugin.repo) {
@@ -107,27 +179,132 @@ impl LockFile {
         }
         Ok(out)
     }
+
+    /// Resolve a partial identifier such as `tide` or `ilan` to one installed plugin.
+    /// Exact matches on the repo, the repo name, or the plugin name win; otherwise the
+    /// query must be a prefix of exactly one of them (case-insensitive).
+    pub(crate) fn find_plugin(&self, query: &str) -> anyhow::Result<&Plugin> {
+        let query_lower = query.to_ascii_lowercase();
+        let keys = |plugin: &Plugin| {
+            [
+                plugin.repo.as_str(),
+                plugin.repo.owner_repo_path(),
+                plugin.repo.repo.clone(),
+                plugin.get_name(),
+            ]
+            .map(|key| key.to_ascii_lowercase())
+        };
+        let matching = |accept: &dyn Fn(&str) -> bool| -> Vec<&Plugin> {
+            self.plugins
+                .iter()
+                .filter(|plugin| keys(plugin).iter().any(|key| accept(key)))
+                .collect()
+        };
+
+        let mut candidates = matching(&|key| key == query_lower);
+        if candidates.is_empty() {
+            candidates = matching(&|key| key.starts_with(&query_lower));
+        }
+        match candidates.as_slice() {
+            [plugin] => Ok(plugin),
+            [] => {
+                let suggestion = self
+                    .plugins
+                    .iter()
+                    .filter(|plugin| {
+                        keys(plugin)
+                            .iter()
+                            .any(|key| crate::resolver::edit_distance(key, &query_lower) <= 2)
+                    })
+                    .map(|plugin| format!("; did you mean '{}'?", plugin.repo))
+                    .next()
+                    .unwrap_or_default();
+                Err(anyhow!("No installed plugin matches '{query}'{suggestion}"))
+            }
+            many => {
+                let labels: Vec<String> =
+                    many.iter().map(|plugin| plugin.display_label()).collect();
+                Err(anyhow!(
+                    "'{query}' matches several installed plugins: {}; use owner/repo to pick one",
+                    labels.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// Full identifiers of installed plugins are used as-is; anything else is matched
+    /// with [`LockFile::find_plugin`], so `tide` finds `IlanCosman/tide`. A full
+    /// identifier that matches nothing is returned unchanged for the command to report.
+    pub(crate) fn resolve_plugin_arg(&self, raw: &str) -> anyhow::Result<PluginRepo> {
+        match parse_plugin_arg(raw) {
+            Ok(repo) if self.contains_repo(&repo) => Ok(repo),
+            parsed => match self.find_plugin(raw) {
+                Ok(plugin) => Ok(plugin.repo.clone()),
+                Err(fuzzy_err) => parsed.map_err(|_| fuzzy_err),
+            },
+        }
+    }
+}
+
+fn parse_plugin_arg(raw: &str) -> anyhow::Result<PluginRepo> {
+    // Try full InstallTarget parsing to allow URLs and @ref, but ignore ref in the lookup.
+    match crate::models::InstallTarget::from_raw(raw).resolve() {
+        Ok(resolved) => Ok(resolved.plugin_repo),
+        Err(_) => raw
+            .parse::<PluginRepo>()
+            .map_err(|e| anyhow!(e))
+            .context("Failed to parse plugin identifier"),
+    }
 }
 
 pub(crate) const AUTO_GENERATED_COMMENT: &str =
     "# This file is automatically generated by pez. Do not edit it manually.\n";
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(test, derive(Default))]
 pub(crate) struct Plugin {
     pub(crate) name: String,
     pub(crate) repo: PluginRepo,
     pub(crate) source: String,
     pub(crate) commit_sha: String,
     pub(crate) files: Vec<PluginFile>,
+    /// User files this plugin overwrote at install time, restored when it stops shipping them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) backups: Vec<PluginBackup>,
+    /// Unix time (seconds) the plugin was last installed at a new commit or upgraded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) updated_at: Option<u64>,
+    /// The remote's default branch at install time, which is what an unpinned plugin tracks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) default_branch: Option<String>,
+    /// Files the plugin ships that `disable_files` in pez.toml keeps out of the fish config
+    /// directory, so they can be copied back once the entry is removed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) disabled_files: Vec<PluginFile>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub(crate) struct PluginFile {
     pub(crate) dir: TargetDir,
     pub(crate) name: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct PluginBackup {
+    pub(crate) dir: TargetDir,
+    pub(crate) name: String,
+    /// Absolute path of the preserved copy.
+    pub(crate) backup: String,
+}
+
 impl Plugin {
+    /// Whether `source` names this plugin's source, ignoring differences in spelling
+    /// such as a `.git` suffix or ssh versus https.
+    pub(crate) fn has_source(&self, source: &str) -> bool {
+        self.source == source
+            || resolver::canonical_source(&self.source) == resolver::canonical_source(source)
+    }
+
     pub(crate) fn get_name(&self) -> String {
         if self.name.is_empty() {
             let parts: Vec<&str> = self.source.split("/").collect();
@@ -137,6 +314,35 @@ impl Plugin {
         }
     }
 
+    /// Human-readable identifier used in logs and diagnostics: the repo, prefixed by the
+    /// plugin name when it differs from the repo name.
+    pub(crate) fn display_label(&self) -> String {
+        let name = self.get_name();
+        if name == self.repo.repo {
+            self.repo.as_str()
+        } else {
+            format!("{name} ({})", self.repo)
+        }
+    }
+
+    /// The plugin's local path, or its clone under `data_dir` for remote sources.
+    pub(crate) fn checkout_dir(&self, data_dir: &path::Path) -> path::PathBuf {
+        if crate::git::is_local_source(&self.source) {
+            path::PathBuf::from(&self.source)
+        } else {
+            data_dir.join(self.repo.as_str())
+        }
+    }
+
+    /// Path of `file` inside the plugin checkout. Gist files live at the top level.
+    pub(crate) fn source_path(&self, file: &PluginFile) -> path::PathBuf {
+        if self.repo.is_gist() {
+            path::PathBuf::from(&file.name)
+        } else {
+            path::Path::new(file.dir.as_str()).join(&file.name)
+        }
+    }
+
     pub(crate) fn resolve_paths(
         &self,
         config_dir: &path::Path,
@@ -171,6 +377,7 @@ mod tests {
             source: source.to_string(),
             commit_sha: "deadbeef".to_string(),
             files: vec![],
+            ..Default::default()
         }
     }
 
@@ -189,6 +396,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sources_match_across_equivalent_spellings() {
+        let mut lock = init();
+        lock.add_plugin(plugin_with("https://github.com/owner/repo", "alpha"))
+            .expect("add initial plugin");
+        assert!(
+            lock.add_plugin(plugin_with("git@github.com:owner/repo.git", "beta"))
+                .is_err()
+        );
+
+        lock.merge_plugins(vec![plugin_with("https://github.com/owner/repo/", "alpha")]);
+        assert_eq!(lock.plugins.len(), 1);
+        assert_eq!(lock.plugins[0].source, "https://github.com/owner/repo/");
+
+        lock.remove_plugin("ssh://git@github.com/owner/repo.git");
+        assert!(lock.plugins.is_empty());
+    }
+
+    #[test]
+    fn load_merges_entries_for_the_same_source() {
+        let file = |name: &str| PluginFile {
+            dir: TargetDir::Functions,
+            name: name.to_string(),
+        };
+        let mut older = plugin_with("https://github.com/owner/repo.git", "repo");
+        older.files = vec![file("old.fish"), file("shared.fish")];
+        let mut newer = plugin_with("https://github.com/owner/repo", "repo");
+        newer.commit_sha = "cafe".to_string();
+        newer.files = vec![file("shared.fish")];
+        let other = plugin_with("https://github.com/owner/other", "other");
+        let content = toml::to_string(&LockFile {
+            version: CURRENT_VERSION,
+            plugins: vec![older, other, newer],
+        })
+        .unwrap();
+
+        let lock = parse(&content, "pez-lock.toml").unwrap();
+        let names: Vec<_> = lock.plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["other", "repo"]);
+        let merged = &lock.plugins[1];
+        assert_eq!(merged.commit_sha, "cafe");
+        assert_eq!(merged.files, vec![file("shared.fish"), file("old.fish")]);
+    }
+
     #[test]
     fn add_plugin_rejects_duplicate_name() {
         let mut lock = init();
@@ -215,6 +466,7 @@ mod tests {
                     source: "https://example.com/owner/alpha".to_string(),
                     commit_sha: "old".to_string(),
                     files: vec![],
+                    ..Default::default()
                 },
                 Plugin {
                     name: "beta".to_string(),
@@ -222,6 +474,7 @@ mod tests {
                     source: "https://example.com/owner/beta".to_string(),
                     commit_sha: "stable".to_string(),
                     files: vec![],
+                    ..Default::default()
                 },
             ],
         };
@@ -232,6 +485,7 @@ mod tests {
             source: "https://example.com/owner/alpha".to_string(),
             commit_sha: "new".to_string(),
             files: vec![],
+            ..Default::default()
         };
         let new_plugin = Plugin {
             name: "gamma".to_string(),
@@ -239,6 +493,7 @@ mod tests {
             source: "https://example.com/owner/gamma".to_string(),
             commit_sha: "fresh".to_string(),
             files: vec![],
+            ..Default::default()
         };
 
         lock.merge_plugins(vec![updated_alpha.clone(), new_plugin.clone()]);
@@ -275,6 +530,7 @@ mod tests {
                 source: "https://example.com/owner/alpha".to_string(),
                 commit_sha: "deadbeef".to_string(),
                 files: vec![],
+                ..Default::default()
             }],
         };
 
@@ -290,6 +546,7 @@ mod tests {
             source: "https://example.com/owner/repo".to_string(),
             commit_sha: "deadbeef".to_string(),
             files: vec![],
+            ..Default::default()
         };
         assert_eq!(named.get_name(), "custom");
 
@@ -299,7 +556,134 @@ mod tests {
             source: "https://example.com/owner/repo".to_string(),
             commit_sha: "deadbeef".to_string(),
             files: vec![],
+            ..Default::default()
         };
         assert_eq!(unnamed.get_name(), "repo");
     }
+
+    #[test]
+    fn plugin_display_label_includes_custom_name() {
+        let mut plugin = plugin_with("https://github.com/owner/repo", "repo");
+        assert_eq!(plugin.display_label(), "owner/repo");
+
+        plugin.name = "custom".to_string();
+        assert_eq!(plugin.display_label(), "custom (owner/repo)");
+    }
+
+    #[test]
+    fn find_plugin_matches_exact_names_and_unique_prefixes() {
+        let installed = |owner: &str, repo: &str| Plugin {
+            name: repo.to_string(),
+            repo: plugin_repo(owner, repo),
+            source: format!("https://github.com/{owner}/{repo}"),
+            ..plugin_with("", "")
+        };
+        let lock = LockFile {
+            version: 2,
+            plugins: vec![
+                installed("IlanCosman", "tide"),
+                installed("jethrokuan", "fzf"),
+                installed("PatrickF1", "fzf.fish"),
+            ],
+        };
+        let found = |query: &str| lock.find_plugin(query).map(|p| p.repo.as_str());
+
+        assert_eq!(found("tide").unwrap(), "IlanCosman/tide");
+        assert_eq!(found("TI").unwrap(), "IlanCosman/tide");
+        assert_eq!(found("ilan").unwrap(), "IlanCosman/tide");
+        assert_eq!(found("fzf").unwrap(), "jethrokuan/fzf");
+        assert_eq!(found("fzf.").unwrap(), "PatrickF1/fzf.fish");
+
+        let ambiguous = found("f").unwrap_err().to_string();
+        assert!(
+            ambiguous.contains("jethrokuan/fzf, PatrickF1/fzf.fish"),
+            "{ambiguous}"
+        );
+        let typo = found("tdie").unwrap_err().to_string();
+        assert!(typo.contains("did you mean 'IlanCosman/tide'"), "{typo}");
+    }
+
+    #[test]
+    fn load_upgrades_v1_and_rejects_unknown_versions() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("pez-lock.toml");
+        fs::write(
+            &path,
+            "version = 1\n\n[[plugins]]\nname = \"repo\"\nrepo = \"owner/repo\"\nsource = \"https://github.com/owner/repo\"\ncommit_sha = \"deadbeef\"\nfiles = []\n",
+        )
+        .unwrap();
+        let lock = load(&path).unwrap();
+        assert_eq!(lock.version, CURRENT_VERSION);
+        assert_eq!(lock.plugins[0].updated_at, None);
+
+        fs::write(&path, "version = 99\nplugins = []\n").unwrap();
+        let err = load(&path).unwrap_err();
+        assert!(err.to_string().contains("Unsupported lock file version 99"));
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn segment() -> impl Strategy<Value = String> {
+        "[A-Za-z0-9_-][A-Za-z0-9_.-]{0,10}[A-Za-z0-9_-]"
+    }
+
+    fn target_dir() -> impl Strategy<Value = TargetDir> {
+        prop::sample::select(vec![
+            TargetDir::Functions,
+            TargetDir::Completions,
+            TargetDir::ConfD,
+            TargetDir::Themes,
+        ])
+    }
+
+    fn plugin() -> impl Strategy<Value = Plugin> {
+        (
+            prop::option::of(prop::sample::select(vec!["gitlab.com", "gist.github.com"])),
+            segment(),
+            segment(),
+            "[0-9a-f]{40}",
+            prop::collection::vec((target_dir(), "[a-z_]{1,8}\\.fish"), 0..4),
+            prop::option::of(any::<u32>()),
+        )
+            .prop_map(|(host, owner, repo, commit_sha, files, updated_at)| {
+                let repo = PluginRepo::new(host.map(str::to_string), owner, repo).unwrap();
+                Plugin {
+                    name: repo.repo.clone(),
+                    source: repo.default_remote_source(),
+                    repo,
+                    commit_sha,
+                    files: files
+                        .into_iter()
+                        .map(|(dir, name)| PluginFile { dir, name })
+                        .collect(),
+                    updated_at: updated_at.map(u64::from),
+                    ..Default::default()
+                }
+            })
+    }
+
+    proptest! {
+        // Each case parses repo segments with fresh regexes; keep the suite fast.
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn lock_file_round_trips_through_save_and_load(
+            plugins in prop::collection::vec(plugin(), 0..5),
+        ) {
+            let temp = tempfile::tempdir().unwrap();
+            let path = temp.path().join("pez-lock.toml");
+            let lock = LockFile { version: CURRENT_VERSION, plugins };
+            lock.save(&path).unwrap();
+            let loaded = load(&path).unwrap();
+            prop_assert_eq!(&loaded.plugins, &lock.plugins);
+            for plugin in &lock.plugins {
+                prop_assert_eq!(plugin.repo.as_str().parse::<PluginRepo>().unwrap(), plugin.repo.clone());
+                prop_assert!(loaded.get_plugin_by_repo(&plugin.repo).is_some());
+            }
+        }
+    }
 }
@@ -1,4 +1,12 @@
+use std::process::ExitCode;
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    pez::run().await
+async fn main() -> ExitCode {
+    match pez::run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(pez::exit_code(&err))
+        }
+    }
 }
@@ -52,6 +52,7 @@ impl std::str::FromStr for TargetDir {
 // Core typed identifiers and inputs used across CLI and core logic
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(test, derive(Default))]
 #[serde(try_from = "String", into = "String")]
 pub(crate) struct PluginRepo {
     pub host: Option<String>,
@@ -103,6 +104,14 @@ impl PluginRepo {
         }
     }
 
+    /// Gists are git repositories whose files sit at the top level instead of in
+    /// `functions/`, `conf.d/`, and so on.
+    pub fn is_gist(&self) -> bool {
+        self.host
+            .as_deref()
+            .is_some_and(|h| h.eq_ignore_ascii_case(GIST_HOST))
+    }
+
     pub fn from_remote_url(raw: &str) -> Option<Self> {
         parse_standard_url(raw)
             .or_else(|| parse_scp_like(raw))
@@ -139,6 +148,38 @@ impl std::str::FromStr for PluginRepo {
     }
 }
 
+pub(crate) const GIST_HOST: &str = "gist.github.com";
+const GIST_RAW_HOST: &str = "gist.githubusercontent.com";
+
+/// Map a raw gist file URL (`https://gist.githubusercontent.com/<user>/<id>/raw[/<rev>]/<file>`)
+/// to the gist repository and, when present, the revision it points at.
+pub(crate) fn parse_gist_raw_url(raw: &str) -> Option<(PluginRepo, Option<String>)> {
+    let parsed = Url::parse(raw).ok()?;
+    if !parsed
+        .host_str()
+        .is_some_and(|h| h.eq_ignore_ascii_case(GIST_RAW_HOST))
+    {
+        return None;
+    }
+    let segments: Vec<&str> = parsed.path_segments()?.filter(|s| !s.is_empty()).collect();
+    let [user, id, "raw", rest @ ..] = segments.as_slice() else {
+        return None;
+    };
+    let repo = PluginRepo::new(
+        Some(GIST_HOST.to_string()),
+        (*user).to_string(),
+        (*id).to_string(),
+    )
+    .ok()?;
+    let revision = match rest {
+        [rev, _file, ..] if rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Some((*rev).to_string())
+        }
+        _ => None,
+    };
+    Some((repo, revision))
+}
+
 fn validate_repo_segment(segment: &str) -> Result<(), &'static str> {
     let re = Regex::new(r"^[a-zA-Z0-9_.-]+$").unwrap();
     if re.is_match(segment) && !segment.ends_with('.') {
@@ -188,8 +229,8 @@ fn parse_scp_like(raw: &str) -> Option<(Option<String>, String, String)> {
     }
     let (host_part, path_part) = raw.split_once(':')?;
     let host_str = host_part
-        .strip_prefix("git@")
-        .unwrap_or(host_part)
+        .rsplit_once('@')
+        .map_or(host_part, |(_, host)| host)
         .to_string();
     let host = if host_str.eq_ignore_ascii_case("github.com") {
         None
@@ -294,58 +335,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn install_target_resolves_host_metadata() {
-        struct Case {
-            raw: &'static str,
-            expected_host: Option<&'static str>,
-            expected_owner: &'static str,
-            expected_repo: &'static str,
-            expected_source: &'static str,
-        }
-
-        let cases = [
-            Case {
-                raw: "owner/repo",
-                expected_host: None,
-                expected_owner: "owner",
-                expected_repo: "repo",
-                expected_source: "https://github.com/owner/repo",
-            },
-            Case {
-                raw: "gitlab.com/owner/repo",
-                expected_host: Some("gitlab.com"),
-                expected_owner: "owner",
-                expected_repo: "repo",
-                expected_source: "https://gitlab.com/owner/repo",
-            },
-            Case {
-                raw: "https://gitlab.com/owner/repo.git",
-                expected_host: Some("gitlab.com"),
-                expected_owner: "owner",
-                expected_repo: "repo",
-                expected_source: "https://gitlab.com/owner/repo.git",
-            },
-            Case {
-                raw: "git@bitbucket.org:team/pkg.git",
-                expected_host: Some("bitbucket.org"),
-                expected_owner: "team",
-                expected_repo: "pkg",
-                expected_source: "git@bitbucket.org:team/pkg.git",
-            },
-        ];
-
-        for case in cases {
-            let resolved = crate::models::InstallTarget::from_raw(case.raw)
-                .resolve()
-                .expect("resolve target");
-            assert_eq!(resolved.plugin_repo.host.as_deref(), case.expected_host);
-            assert_eq!(resolved.plugin_repo.owner, case.expected_owner);
-            assert_eq!(resolved.plugin_repo.repo, case.expected_repo);
-            assert_eq!(resolved.source, case.expected_source);
-        }
-    }
-
     #[test]
     fn plugin_spec_from_resolved_preserves_host_metadata() {
         struct Case {
@@ -475,6 +464,24 @@ mod tests {
         assert!(http_like.is_some());
     }
 
+    #[test]
+    fn parse_gist_raw_url_extracts_repo_and_revision() {
+        let rev = "0123456789abcdef0123456789abcdef01234567";
+        let (repo, revision) = parse_gist_raw_url(&format!(
+            "https://gist.githubusercontent.com/alice/abc123/raw/{rev}/greet.fish"
+        ))
+        .unwrap();
+        assert_eq!(repo.as_str(), "gist.github.com/alice/abc123");
+        assert!(repo.is_gist());
+        assert_eq!(revision.as_deref(), Some(rev));
+
+        let (_, revision) =
+            parse_gist_raw_url("https://gist.githubusercontent.com/alice/abc123/raw/greet.fish")
+                .unwrap();
+        assert_eq!(revision, None);
+        assert!(parse_gist_raw_url("https://github.com/alice/abc123/raw/x").is_none());
+    }
+
     #[test]
     fn install_target_round_trip_preserves_raw() {
         let target = InstallTarget {
@@ -492,6 +499,7 @@ mod tests {
 /// - `gitlab.com/owner/repo`
 /// - `gitlab.com/owner/repo@branch:main`
 /// - `https://example.com/owner/repo`
+/// - `gist.github.com/user/<id>` or a raw gist file URL
 /// - `~/path/to/repo` or `./relative/path`
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(try_from = "String", into = "String")]
@@ -499,6 +507,17 @@ pub(crate) struct InstallTarget {
     pub(crate) raw: String,
 }
 
+impl InstallTarget {
+    pub fn from_raw<S: Into<String>>(s: S) -> Self {
+        InstallTarget { raw: s.into() }
+    }
+
+    /// Parse the raw string; see [`crate::resolver::resolve_target`] for the rules.
+    pub fn resolve(&self) -> anyhow::Result<ResolvedInstallTarget> {
+        crate::resolver::resolve_target(&self.raw)
+    }
+}
+
 impl TryFrom<String> for InstallTarget {
     type Error = String;
 
@@ -531,4 +550,6 @@ pub(crate) struct ResolvedInstallTarget {
     pub ref_kind: crate::resolver::RefKind,
     /// Whether the source is a local filesystem path.
     pub is_local: bool,
+    /// Release channel used when resolving `version` selectors.
+    pub channel: crate::resolver::Channel,
 }
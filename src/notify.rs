@@ -0,0 +1,103 @@
+use crate::utils;
+
+use std::{
+    future::Future,
+    io::Write,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+/// Operations finishing faster than this are not worth a notification.
+const MIN_DURATION: Duration = Duration::from_secs(10);
+
+/// Run `operation` and, when `[settings] notify = true` and it took long enough,
+/// tell the user it finished.
+pub(crate) async fn notify_on_finish<F, T>(command: &str, operation: F) -> anyhow::Result<T>
+where
+    F: Future<Output = anyhow::Result<T>>,
+{
+    let started = Instant::now();
+    let result = operation.await;
+    if started.elapsed() >= MIN_DURATION && enabled() {
+        send(&message(command, result.is_ok()));
+    }
+    result
+}
+
+fn enabled() -> bool {
+    utils::load_config().is_ok_and(|(config, _)| config.settings.notify)
+}
+
+fn message(command: &str, succeeded: bool) -> String {
+    if succeeded {
+        format!("pez {command} finished")
+    } else {
+        format!("pez {command} failed")
+    }
+}
+
+/// OSC 9 is understood by iTerm2, WezTerm, kitty, Windows Terminal and others;
+/// terminals without support ignore it.
+fn osc9_sequence(message: &str) -> String {
+    let body: String = message
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    format!("\x1b]9;{body}\x07")
+}
+
+fn send(message: &str) {
+    if console::Term::stderr().is_term() {
+        let mut stderr = std::io::stderr();
+        let _ = stderr.write_all(osc9_sequence(message).as_bytes());
+        let _ = stderr.flush();
+        return;
+    }
+    if let Err(e) = send_desktop(message) {
+        debug!("Desktop notification failed: {:?}", e);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn send_desktop(message: &str) -> std::io::Result<()> {
+    let escaped = message.replace('\\', "\\\\").replace('"', "\\\"");
+    std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "display notification \"{escaped}\" with title \"pez\""
+        ))
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|_| ())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn send_desktop(message: &str) -> std::io::Result<()> {
+    std::process::Command::new("notify-send")
+        .arg("pez")
+        .arg(message)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_reports_outcome() {
+        assert_eq!(message("upgrade", true), "pez upgrade finished");
+        assert_eq!(message("install", false), "pez install failed");
+    }
+
+    #[test]
+    fn osc9_sequence_replaces_control_characters() {
+        assert_eq!(
+            osc9_sequence("pez\x07upgrade\nfinished"),
+            "\x1b]9;pez upgrade finished\x07"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use serde::Serialize;
+use std::{
+    fmt,
+    io::{self, Write},
+    sync::{Mutex, OnceLock},
+};
+
+/// Layout of JSON printed by `--format json`, chosen with the global `--json-style`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum JsonStyle {
+    /// Indented, for reading
+    #[default]
+    Pretty,
+    /// One line, for piping
+    Compact,
+}
+
+/// Machine-readable encodings behind the `json`/`yaml` values of `--format` options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Structured {
+    Json,
+    Yaml,
+}
+
+/// Level of a GitHub Actions workflow annotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Annotation {
+    Warning,
+    Error,
+}
+
+/// A GitHub Actions workflow command such as `::warning title=...::message`, which the
+/// runner shows as an annotation on the job. Newlines and `%` are escaped, as are `:`
+/// and `,` in the title.
+pub(crate) fn github_annotation(level: Annotation, title: &str, message: &str) -> String {
+    let data = |s: &str| {
+        s.replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    };
+    let property = |s: &str| data(s).replace(':', "%3A").replace(',', "%2C");
+    let command = match level {
+        Annotation::Warning => "warning",
+        Annotation::Error => "error",
+    };
+    format!("::{command} title={}::{}", property(title), data(message))
+}
+
+/// Render `value` in `format`, JSON in `style`; both encodings sort object keys the
+/// same way.
+pub(crate) fn to_structured<T: Serialize + ?Sized>(
+    value: &T,
+    format: Structured,
+    style: JsonStyle,
+) -> anyhow::Result<String> {
+    match format {
+        Structured::Json => to_json(value, style),
+        Structured::Yaml => to_yaml(value),
+    }
+}
+
+/// Render `value` as a YAML document without the trailing newline, matching
+/// [`to_json`] so callers can `println!` either.
+pub(crate) fn to_yaml<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<String> {
+    let value = serde_json::to_value(value)?;
+    Ok(serde_yaml::to_string(&value)?.trim_end().to_string())
+}
+
+/// Render `value` as JSON in `style`, the global `--json-style`. Object keys are sorted
+/// so output is stable across releases regardless of struct field order.
+pub(crate) fn to_json<T: Serialize + ?Sized>(
+    value: &T,
+    style: JsonStyle,
+) -> anyhow::Result<String> {
+    // serde_json's Map is a BTreeMap, so a round trip through Value sorts every object.
+    let value = serde_json::to_value(value)?;
+    Ok(match style {
+        JsonStyle::Pretty => serde_json::to_string_pretty(&value)?,
+        JsonStyle::Compact => serde_json::to_string(&value)?,
+    })
+}
+
+/// Command output on stdout. When the reader has gone away (`pez list | head -1`), pez
+/// exits quietly with success instead of failing with a broken-pipe error.
+pub(crate) struct Stdout;
+
+impl Write for Stdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(captured) = capture_store().lock().unwrap().as_mut() {
+            captured.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+        exit_on_broken_pipe(io::stdout().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        exit_on_broken_pipe(io::stdout().flush())
+    }
+}
+
+fn exit_on_broken_pipe<T>(result: io::Result<T>) -> io::Result<T> {
+    if is_broken_pipe(&result) {
+        std::process::exit(0);
+    }
+    result
+}
+
+fn is_broken_pipe<T>(result: &io::Result<T>) -> bool {
+    matches!(result, Err(err) if err.kind() == io::ErrorKind::BrokenPipe)
+}
+
+/// Output written through [`Stdout`] (log lines included) while a [`Capture`] is alive.
+fn capture_store() -> &'static Mutex<Option<Vec<u8>>> {
+    static CAPTURE: OnceLock<Mutex<Option<Vec<u8>>>> = OnceLock::new();
+    CAPTURE.get_or_init(|| Mutex::new(None))
+}
+
+/// Collects everything pez would print to stdout instead of writing it to the terminal,
+/// for `pez ui`'s log pane. Printing resumes when the capture is dropped.
+#[cfg(feature = "tui")]
+pub(crate) struct Capture(());
+
+#[cfg(feature = "tui")]
+pub(crate) fn capture() -> Capture {
+    *capture_store().lock().unwrap() = Some(Vec::new());
+    Capture(())
+}
+
+#[cfg(feature = "tui")]
+impl Capture {
+    /// Complete lines written since the last call, without color codes.
+    pub(crate) fn take_lines(&self) -> Vec<String> {
+        let mut store = capture_store().lock().unwrap();
+        let Some(captured) = store.as_mut() else {
+            return Vec::new();
+        };
+        let Some(end) = captured.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let complete: Vec<u8> = captured.drain(..=end).collect();
+        String::from_utf8_lossy(&complete)
+            .lines()
+            .map(|line| console::strip_ansi_codes(line).into_owned())
+            .collect()
+    }
+}
+
+#[cfg(feature = "tui")]
+impl Drop for Capture {
+    fn drop(&mut self) {
+        *capture_store().lock().unwrap() = None;
+    }
+}
+
+/// Backs [`out!`] and [`outln!`]; panics on other write errors like `print!` does.
+pub(crate) fn print(args: fmt::Arguments) {
+    if let Some(captured) = capture_store().lock().unwrap().as_mut() {
+        captured.extend_from_slice(args.to_string().as_bytes());
+        return;
+    }
+    let mut stdout = io::stdout().lock();
+    let result = exit_on_broken_pipe(stdout.write_fmt(args).and_then(|()| stdout.flush()));
+    if let Err(err) = result {
+        panic!("failed printing to stdout: {err}");
+    }
+}
+
+/// `print!` through [`Stdout`]'s broken-pipe handling.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::output::print(format_args!($($arg)*))
+    };
+}
+
+/// `println!` through [`Stdout`]'s broken-pipe handling.
+macro_rules! outln {
+    () => {
+        $crate::output::print(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+pub(crate) use {out, outln};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde_derive::Serialize)]
+    struct Sample {
+        zeta: u8,
+        alpha: Vec<Nested>,
+    }
+
+    #[derive(serde_derive::Serialize)]
+    struct Nested {
+        b: bool,
+        a: Option<&'static str>,
+    }
+
+    #[test]
+    fn github_annotation_escapes_data_and_title() {
+        assert_eq!(
+            github_annotation(Annotation::Error, "pez: a, b", "100%\ndone"),
+            "::error title=pez%3A a%2C b::100%25%0Adone"
+        );
+        assert_eq!(
+            github_annotation(Annotation::Warning, "t", "m"),
+            "::warning title=t::m"
+        );
+    }
+
+    #[test]
+    fn to_json_sorts_keys_in_both_styles() {
+        let sample = Sample {
+            zeta: 1,
+            alpha: vec![Nested { b: true, a: None }],
+        };
+        assert_eq!(
+            to_json(&sample, JsonStyle::Compact).unwrap(),
+            r#"{"alpha":[{"a":null,"b":true}],"zeta":1}"#
+        );
+        assert_eq!(
+            to_structured(&sample, Structured::Yaml, JsonStyle::Pretty).unwrap(),
+            "alpha:\n- a: null\n  b: true\nzeta: 1"
+        );
+        assert_eq!(
+            to_json(&sample, JsonStyle::Pretty).unwrap(),
+            "{\n  \"alpha\": [\n    {\n      \"a\": null,\n      \"b\": true\n    }\n  ],\n  \"zeta\": 1\n}"
+        );
+    }
+
+    #[test]
+    fn only_broken_pipe_errors_end_the_process() {
+        let broken: io::Result<()> = Err(io::ErrorKind::BrokenPipe.into());
+        let denied: io::Result<()> = Err(io::ErrorKind::PermissionDenied.into());
+        assert!(is_broken_pipe(&broken));
+        assert!(!is_broken_pipe(&Ok(())));
+        assert!(exit_on_broken_pipe(denied).is_err());
+    }
+
+    #[cfg(feature = "tui")]
+    #[test]
+    fn capture_collects_complete_lines_without_colors() {
+        let capture = capture();
+        Stdout.write_all(b"\x1b[1mfirst\x1b[0m\nsec").unwrap();
+        let first = capture.take_lines();
+        outln!("ond");
+        let second = capture.take_lines();
+        drop(capture);
+
+        // Other tests may print while the capture is alive.
+        assert!(first.contains(&"first".to_string()), "{first:?}");
+        assert!(second.contains(&"second".to_string()), "{second:?}");
+        assert!(capture_store().lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,170 @@
+use console::Emoji;
+use std::io::{self, IsTerminal};
+use tracing::{info, warn};
+
+/// Set to `1` (or `true`) to answer every confirmation with yes, like `--yes`.
+pub(crate) const ASSUME_YES_ENV: &str = "PEZ_ASSUME_YES";
+
+/// Whether someone can answer a prompt: stdin is a terminal.
+pub(crate) fn interactive() -> bool {
+    #[cfg(test)]
+    if scripted_answers().lock().unwrap().is_some() {
+        return true;
+    }
+    io::stdin().is_terminal()
+}
+
+fn assume_yes() -> bool {
+    std::env::var(ASSUME_YES_ENV)
+        .is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+}
+
+/// Ask a yes/no question. An empty answer picks `default`. `PEZ_ASSUME_YES` answers
+/// yes without asking; without a terminal to ask on, the answer is no, so nothing
+/// destructive happens unattended.
+pub(crate) fn confirm(question: &str, default: bool) -> anyhow::Result<bool> {
+    if assume_yes() {
+        info!("{question} yes ({ASSUME_YES_ENV} is set)");
+        return Ok(true);
+    }
+    if !interactive() {
+        warn!(
+            "{}{question} no (stdin is not a terminal; pass --yes or set {ASSUME_YES_ENV}=1 to confirm)",
+            Emoji("🚧 ", "")
+        );
+        return Ok(false);
+    }
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    warn!("{}{question} {hint}", Emoji("🚧 ", ""));
+    Ok(parse_answer(&read_answer()?, default))
+}
+
+/// Ask `question` with several answers, e.g. `["accept", "skip", "pin"]`, and return
+/// the index of the one picked. An answer matches by its first letter or in full; an
+/// empty answer picks `default`, and `PEZ_ASSUME_YES` picks it without asking. Unlike
+/// `confirm` there is no safe answer to assume, so a session without a terminal fails.
+pub(crate) fn choose(question: &str, choices: &[&str], default: usize) -> anyhow::Result<usize> {
+    if assume_yes() {
+        info!("{question} {} ({ASSUME_YES_ENV} is set)", choices[default]);
+        return Ok(default);
+    }
+    if !interactive() {
+        anyhow::bail!("{question} needs an answer, but stdin is not a terminal");
+    }
+    let hint = choices
+        .iter()
+        .enumerate()
+        .map(|(i, choice)| {
+            let (first, rest) = choice.split_at(1);
+            if i == default {
+                format!("[{}]{rest}", first.to_ascii_uppercase())
+            } else {
+                format!("({first}){rest}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    loop {
+        warn!("{}{question} {hint}", Emoji("🚧 ", ""));
+        let input = read_answer()?;
+        let input = input.trim().to_ascii_lowercase();
+        if input.is_empty() {
+            return Ok(default);
+        }
+        if let Some(index) = choices
+            .iter()
+            .position(|choice| *choice == input || choice.get(..1) == Some(input.as_str()))
+        {
+            return Ok(index);
+        }
+        warn!("Please answer {}.", choices.join(", "));
+    }
+}
+
+fn parse_answer(input: &str, default: bool) -> bool {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "" => default,
+        "y" | "yes" => true,
+        _ => false,
+    }
+}
+
+fn read_answer() -> anyhow::Result<String> {
+    #[cfg(test)]
+    if let Some(answers) = scripted_answers().lock().unwrap().as_mut() {
+        return Ok(answers.pop_front().unwrap_or_default());
+    }
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input)
+}
+
+#[cfg(test)]
+fn scripted_answers() -> &'static std::sync::Mutex<Option<std::collections::VecDeque<String>>> {
+    static ANSWERS: std::sync::Mutex<Option<std::collections::VecDeque<String>>> =
+        std::sync::Mutex::new(None);
+    &ANSWERS
+}
+
+/// Answers prompts from a script instead of stdin while alive, as if on a terminal.
+/// Hold `env_lock` alongside it.
+#[cfg(test)]
+pub(crate) struct ScriptedAnswers {
+    prev: Option<std::collections::VecDeque<String>>,
+}
+
+#[cfg(test)]
+impl ScriptedAnswers {
+    pub(crate) fn new(answers: &[&str]) -> Self {
+        let mut store = scripted_answers().lock().unwrap();
+        let prev = store.replace(answers.iter().map(|a| a.to_string()).collect());
+        Self { prev }
+    }
+}
+
+#[cfg(test)]
+impl Drop for ScriptedAnswers {
+    fn drop(&mut self) {
+        *scripted_answers().lock().unwrap() = self.prev.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_support::log::env_lock;
+
+    #[test]
+    fn confirm_and_choose_use_answers_defaults_and_assume_yes() {
+        let _lock = env_lock().lock().unwrap();
+        let saved = std::env::var_os(ASSUME_YES_ENV);
+        unsafe { std::env::remove_var(ASSUME_YES_ENV) };
+
+        {
+            let _answers = ScriptedAnswers::new(&["y\n", "no\n", "\n", "\n", "YES"]);
+            assert!(confirm("Continue?", false).unwrap());
+            assert!(!confirm("Continue?", true).unwrap());
+            assert!(!confirm("Continue?", false).unwrap());
+            assert!(confirm("Continue?", true).unwrap());
+            assert!(confirm("Continue?", false).unwrap());
+        }
+
+        {
+            let choices = ["accept", "skip", "pin"];
+            let _answers = ScriptedAnswers::new(&["p\n", "\n", "nope\n", "Skip\n"]);
+            assert_eq!(choose("Upgrade?", &choices, 0).unwrap(), 2);
+            assert_eq!(choose("Upgrade?", &choices, 0).unwrap(), 0);
+            assert_eq!(choose("Upgrade?", &choices, 0).unwrap(), 1);
+        }
+
+        unsafe { std::env::set_var(ASSUME_YES_ENV, "1") };
+        assert!(confirm("Continue?", false).unwrap());
+        assert_eq!(choose("Upgrade?", &["accept", "skip"], 1).unwrap(), 1);
+        unsafe {
+            match saved {
+                Some(v) => std::env::set_var(ASSUME_YES_ENV, v),
+                None => std::env::remove_var(ASSUME_YES_ENV),
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{cli::Cli, output::JsonStyle, utils::ColorChoice};
+
+use std::env;
+
+/// The global flags (`--jobs`, `--color`, `--json-style`), read once in [`crate::run`] and
+/// handed to the commands that use them. Per-command flags such as `--yes` and
+/// `--offline` stay in each command's args.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct RuntimeOptions {
+    /// `--jobs`; see [`RuntimeOptions::jobs`] for the fallbacks.
+    pub(crate) jobs: Option<usize>,
+    /// `--color`.
+    pub(crate) color: ColorChoice,
+    /// `--json-style`, for commands printing `--format json`.
+    pub(crate) json_style: JsonStyle,
+}
+
+impl RuntimeOptions {
+    pub(crate) fn from_cli(cli: &Cli) -> Self {
+        Self {
+            jobs: cli.jobs,
+            color: cli.color,
+            json_style: cli.json_style,
+        }
+    }
+
+    /// How many clones, upgrades, uninstalls, and prunes run at once: `--jobs`, then
+    /// `PEZ_JOBS`, then 4.
+    pub(crate) fn jobs(&self) -> usize {
+        if let Some(jobs) = self.jobs {
+            return jobs.max(1);
+        }
+        if let Ok(val) = env::var("PEZ_JOBS")
+            && let Ok(n) = val.parse::<usize>()
+        {
+            return n.max(1);
+        }
+        4
+    }
+
+    pub(crate) fn colors_enabled(&self) -> bool {
+        crate::utils::colors_enabled(self.color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn from_cli_reads_global_flags() {
+        let cli = Cli::parse_from([
+            "pez",
+            "--jobs",
+            "3",
+            "--color",
+            "never",
+            "--json-style",
+            "compact",
+            "list",
+        ]);
+        assert_eq!(
+            RuntimeOptions::from_cli(&cli),
+            RuntimeOptions {
+                jobs: Some(3),
+                color: ColorChoice::Never,
+                json_style: JsonStyle::Compact,
+            }
+        );
+    }
+
+    #[test]
+    fn jobs_prefers_cli_value_then_env_then_default() {
+        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
+        let saved = env::var_os("PEZ_JOBS");
+        unsafe {
+            env::set_var("PEZ_JOBS", "8");
+        }
+        let cli = RuntimeOptions {
+            jobs: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(cli.jobs(), 2);
+        assert_eq!(RuntimeOptions::default().jobs(), 8);
+        unsafe {
+            env::set_var("PEZ_JOBS", "0");
+        }
+        assert_eq!(RuntimeOptions::default().jobs(), 1);
+        unsafe {
+            env::remove_var("PEZ_JOBS");
+        }
+        assert_eq!(RuntimeOptions::default().jobs(), 4);
+        unsafe {
+            match saved {
+                Some(value) => env::set_var("PEZ_JOBS", value),
+                None => env::remove_var("PEZ_JOBS"),
+            }
+        }
+    }
+}
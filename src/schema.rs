@@ -85,6 +85,26 @@ fn plugin_spec_schema() -> Value {
         "additionalProperties": false,
         "properties": {
             "name": { "type": "string" },
+            "prerelease": { "type": "boolean" },
+            "disabled": { "type": "boolean" },
+            "dir": {
+                "type": "string",
+                "enum": ["functions", "completions", "conf.d", "themes"]
+            },
+            "events": {
+                "type": "array",
+                "items": {
+                    "type": "string",
+                    "pattern": "^[A-Za-z0-9_-]+_(?:install|update|uninstall)$"
+                }
+            },
+            "disable_files": {
+                "type": "array",
+                "items": {
+                    "type": "string",
+                    "pattern": "^(?:functions|completions|conf\\.d|themes)/[^/]"
+                }
+            },
             "repo": {
                 "type": "string",
                 "pattern": "^(?:[A-Za-z0-9.-]+/)?[A-Za-z0-9_.-]+/[A-Za-z0-9_.-]+$"
@@ -119,6 +139,10 @@ fn plugin_spec_schema() -> Value {
             {
                 "if": { "required": ["path"] },
                 "then": no_selector
+            },
+            {
+                "if": { "required": ["path"] },
+                "then": { "not": { "required": ["prerelease"] } }
             }
         ]
     })
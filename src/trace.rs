@@ -0,0 +1,28 @@
+//! Tracing targets for the subsystems `--trace <SUBSYSTEM>` can enable on their own.
+
+pub(crate) const GIT: &str = "git";
+pub(crate) const INSTALL: &str = "install";
+pub(crate) const COPY: &str = "copy";
+pub(crate) const RESOLVER: &str = "resolver";
+
+/// Build the `EnvFilter` directives: `base` for everything, plus debug output for
+/// each traced subsystem.
+pub(crate) fn filter_directives(base: &str, traced: &[&str]) -> String {
+    let mut directives = vec![base.to_string()];
+    directives.extend(traced.iter().map(|target| format!("{target}=debug")));
+    directives.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_directives_add_debug_per_target() {
+        assert_eq!(filter_directives("info", &[]), "info");
+        assert_eq!(
+            filter_directives("info", &[GIT, RESOLVER]),
+            "info,git=debug,resolver=debug"
+        );
+    }
+}
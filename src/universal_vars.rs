@@ -0,0 +1,163 @@
+use crate::{
+    git,
+    lock_file::{LockFile, Plugin},
+    utils,
+};
+
+use anyhow::Context;
+use std::path;
+use tracing::{debug, warn};
+
+/// Universal variable holding every installed repo (`owner/repo`).
+pub(crate) const PLUGINS_VAR: &str = "pez_plugins";
+
+fn enabled() -> bool {
+    utils::load_config().is_ok_and(|(config, _)| config.settings.universal_vars)
+}
+
+/// Mirror the lock file into fish universal variables when
+/// `[settings] universal_vars = true`. Called after commands that change what is
+/// installed; failures are logged and never fail the command.
+pub(crate) fn publish() {
+    if !enabled() {
+        return;
+    }
+    // Private mode must not persist anything, universal variables included.
+    if std::env::var_os(utils::FISH_PRIVATE_ENV).is_some() {
+        debug!("Skipping fish universal variables in private mode");
+        return;
+    }
+    let result = utils::load_lock_file().and_then(|(lock_file, _)| {
+        let data_dir = utils::load_pez_data_dir()?;
+        run_fish(&script(&lock_file, |plugin| version_of(plugin, &data_dir)))
+    });
+    if let Err(e) = result {
+        warn!(
+            "{} Failed to update fish universal variables: {:#}",
+            utils::label_warning(),
+            e
+        );
+    }
+}
+
+/// Name of the per-plugin variable, e.g. `pez_plugin_z_version` for `jethrokuan/z`.
+/// Characters fish does not allow in variable names become `_`.
+fn version_var_name(plugin: &Plugin) -> String {
+    let name: String = plugin
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("pez_plugin_{name}_version")
+}
+
+/// A tag at the locked commit when the clone knows one, otherwise the short SHA.
+fn version_of(plugin: &Plugin, data_dir: &path::Path) -> String {
+    git2::Repository::open(data_dir.join(plugin.repo.as_str()))
+        .ok()
+        .and_then(|repo| git::tags_at_commit(&repo, &plugin.commit_sha).ok())
+        .and_then(|tags| tags.into_iter().last())
+        .unwrap_or_else(|| {
+            plugin
+                .commit_sha
+                .get(..7)
+                .unwrap_or(&plugin.commit_sha)
+                .to_string()
+        })
+}
+
+/// Replace all pez universal variables in one fish invocation, erasing version
+/// variables of plugins that are gone.
+fn script(lock_file: &LockFile, mut version: impl FnMut(&Plugin) -> String) -> String {
+    let repos: Vec<String> = lock_file
+        .plugins
+        .iter()
+        .map(|plugin| quote(&plugin.repo.as_str()))
+        .collect();
+    let mut script = format!("set --universal {PLUGINS_VAR} {}\n", repos.join(" "));
+    script.push_str(
+        "for var in (set --universal --names | string match 'pez_plugin_*_version')\n    set --erase --universal $var\nend\n",
+    );
+    for plugin in &lock_file.plugins {
+        script.push_str(&format!(
+            "set --universal {} {}\n",
+            version_var_name(plugin),
+            quote(&version(plugin))
+        ));
+    }
+    script
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn run_fish(script: &str) -> anyhow::Result<()> {
+    let status = std::process::Command::new("fish")
+        .arg("-c")
+        .arg(script)
+        .status()
+        .context("Failed to spawn fish to set universal variables")?;
+    if !status.success() {
+        anyhow::bail!("fish exited with {status}");
+    }
+    debug!("Updated fish universal variables");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PluginRepo;
+
+    fn plugin(repo: &str, name: &str) -> Plugin {
+        let repo: PluginRepo = repo.parse().unwrap();
+        Plugin {
+            name: name.into(),
+            source: repo.default_remote_source(),
+            repo,
+            commit_sha: "0123456789abcdef".into(),
+            files: vec![],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn script_sets_list_and_version_vars() {
+        let lock_file = LockFile {
+            version: 2,
+            plugins: vec![
+                plugin("jethrokuan/z", "z"),
+                plugin("gitlab.com/owner/fish-plugin", "fish-plugin"),
+            ],
+        };
+        let script = script(&lock_file, |plugin| {
+            if plugin.name == "z" {
+                "v1.2.0".into()
+            } else {
+                "0123456".into()
+            }
+        });
+        assert_eq!(
+            script,
+            "\
+set --universal pez_plugins 'jethrokuan/z' 'gitlab.com/owner/fish-plugin'
+for var in (set --universal --names | string match 'pez_plugin_*_version')
+    set --erase --universal $var
+end
+set --universal pez_plugin_z_version 'v1.2.0'
+set --universal pez_plugin_fish_plugin_version '0123456'
+"
+        );
+        assert_eq!(quote(r"it's a\b"), r"'it\'s a\\b'");
+    }
+
+    #[test]
+    fn version_falls_back_to_short_sha_without_clone() {
+        let temp = tempfile::tempdir().unwrap();
+        assert_eq!(
+            version_of(&plugin("owner/repo", "repo"), temp.path()),
+            "0123456"
+        );
+    }
+}
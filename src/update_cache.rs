@@ -0,0 +1,172 @@
+use crate::models::PluginRepo;
+
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs, path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+pub(crate) const UPDATE_CHECK_FILE_NAME: &str = "update-check.toml";
+
+/// Results of previous remote lookups, keyed by repo and selector so that
+/// changing a plugin's selector never serves a stale answer.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub(crate) struct UpdateCheckCache {
+    #[serde(default)]
+    pub(crate) entries: Vec<UpdateCheckEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct UpdateCheckEntry {
+    pub(crate) repo: PluginRepo,
+    pub(crate) selection: String,
+    pub(crate) latest: String,
+    /// Seconds since the Unix epoch.
+    pub(crate) checked_at: u64,
+}
+
+/// How `list --outdated` may use cached remote lookups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CachePolicy {
+    /// Always query remotes and refresh the cache.
+    Refresh,
+    /// Serve cached results younger than the given age; refresh the rest.
+    MaxAge(Duration),
+    /// Never query remotes; serve cached results of any age.
+    CachedOnly,
+}
+
+/// Load the cache, treating a missing or unreadable file as empty.
+pub(crate) fn load(path: &path::Path) -> UpdateCheckCache {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return UpdateCheckCache::default(),
+    };
+    match toml::from_str(&content) {
+        Ok(cache) => cache,
+        Err(err) => {
+            warn!(
+                "Ignoring unreadable update-check cache at {}: {err}",
+                path.display()
+            );
+            UpdateCheckCache::default()
+        }
+    }
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl UpdateCheckCache {
+    pub(crate) fn save(&self, path: &path::Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, repo: &PluginRepo, selection: &str) -> Option<&UpdateCheckEntry> {
+        self.entries
+            .iter()
+            .find(|e| &e.repo == repo && e.selection == selection)
+    }
+
+    /// Return the cached latest commit when `policy` allows serving it at `now`.
+    pub(crate) fn lookup(
+        &self,
+        repo: &PluginRepo,
+        selection: &str,
+        policy: &CachePolicy,
+        now: u64,
+    ) -> Option<&str> {
+        let entry = self.get(repo, selection)?;
+        let usable = match policy {
+            CachePolicy::Refresh => false,
+            CachePolicy::CachedOnly => true,
+            CachePolicy::MaxAge(max_age) => {
+                now.saturating_sub(entry.checked_at) <= max_age.as_secs()
+            }
+        };
+        usable.then_some(entry.latest.as_str())
+    }
+
+    pub(crate) fn record(&mut self, repo: &PluginRepo, selection: &str, latest: &str, now: u64) {
+        let entry = UpdateCheckEntry {
+            repo: repo.clone(),
+            selection: selection.to_string(),
+            latest: latest.to_string(),
+            checked_at: now,
+        };
+        match self
+            .entries
+            .iter_mut()
+            .find(|e| &e.repo == repo && e.selection == selection)
+        {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(name: &str) -> PluginRepo {
+        PluginRepo::new(None, "owner".into(), name.into()).unwrap()
+    }
+
+    #[test]
+    fn lookup_honours_policy_and_age() {
+        let mut cache = UpdateCheckCache::default();
+        cache.record(&repo("a"), "origin/HEAD", "abc", 1_000);
+
+        let a = repo("a");
+        assert_eq!(
+            cache.lookup(&a, "origin/HEAD", &CachePolicy::Refresh, 1_000),
+            None
+        );
+        assert_eq!(
+            cache.lookup(&a, "origin/HEAD", &CachePolicy::CachedOnly, 9_999_999),
+            Some("abc")
+        );
+        let hour = CachePolicy::MaxAge(Duration::from_secs(3_600));
+        assert_eq!(cache.lookup(&a, "origin/HEAD", &hour, 4_600), Some("abc"));
+        assert_eq!(cache.lookup(&a, "origin/HEAD", &hour, 4_601), None);
+        assert_eq!(
+            cache.lookup(&a, "branch:dev", &CachePolicy::CachedOnly, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn record_replaces_existing_entry_and_round_trips() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("nested").join(UPDATE_CHECK_FILE_NAME);
+        let mut cache = UpdateCheckCache::default();
+        cache.record(&repo("a"), "origin/HEAD", "old", 1);
+        cache.record(&repo("a"), "origin/HEAD", "new", 2);
+        cache.save(&path).unwrap();
+
+        let loaded = load(&path);
+        assert_eq!(loaded.entries.len(), 1);
+        assert_eq!(loaded.entries[0].latest, "new");
+        assert_eq!(loaded.entries[0].checked_at, 2);
+    }
+
+    #[test]
+    fn load_treats_missing_or_invalid_file_as_empty() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join(UPDATE_CHECK_FILE_NAME);
+        assert!(load(&path).entries.is_empty());
+
+        fs::write(&path, "entries = 'nope'").unwrap();
+        assert!(load(&path).entries.is_empty());
+    }
+}
@@ -1,15 +1,11 @@
 use crate::{
-    config,
+    autoload, backup, config,
     lock_file::{self, LockFile, Plugin, PluginFile},
-    models::TargetDir,
+    models::{PluginRepo, TargetDir},
 };
 use anyhow::Context;
 use console::Emoji;
-use std::{
-    collections::HashSet,
-    env, fmt, fs, path,
-    sync::{Mutex, OnceLock},
-};
+use std::{collections::HashSet, env, fmt, fs, path};
 use tracing::{debug, error, info, warn};
 use walkdir::WalkDir;
 
@@ -71,6 +67,25 @@ pub(crate) fn load_fish_data_dir() -> anyhow::Result<path::PathBuf> {
     Ok(home.join(".local/share/fish"))
 }
 
+/// Oh My Fish's install directory: `$OMF_PATH`, else `omf` under the XDG data home.
+pub(crate) fn load_omf_dir() -> anyhow::Result<path::PathBuf> {
+    if let Some(dir) = env::var_os("OMF_PATH") {
+        return Ok(path::PathBuf::from(dir));
+    }
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        return Ok(path::PathBuf::from(dir).join("omf"));
+    }
+    Ok(home_dir()?.join(".local/share/omf"))
+}
+
+/// Where fisher 3 kept its plugin clones (`<host>/<owner>/<repo>`).
+pub(crate) fn load_fisher_v3_dir() -> anyhow::Result<path::PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return Ok(path::PathBuf::from(dir).join("fisher"));
+    }
+    Ok(home_dir()?.join(".config/fisher"))
+}
+
 pub(crate) fn load_pez_data_dir() -> anyhow::Result<path::PathBuf> {
     if let Some(dir) = env::var_os("PEZ_DATA_DIR") {
         return Ok(path::PathBuf::from(dir));
@@ -80,32 +95,65 @@ pub(crate) fn load_pez_data_dir() -> anyhow::Result<path::PathBuf> {
     Ok(fish_data_dir.join("pez"))
 }
 
-pub(crate) fn load_jobs() -> usize {
-    if let Some(override_jobs) = cli_jobs_override().lock().unwrap().as_ref().copied() {
-        return override_jobs;
-    }
-    if let Ok(val) = env::var("PEZ_JOBS")
-        && let Ok(n) = val.parse::<usize>()
-    {
-        return n.max(1);
+/// Cache directory for disposable data such as remote update checks.
+/// Lives under the pez data dir as a dot-directory so it cannot collide with clone paths.
+pub(crate) fn load_pez_cache_dir() -> anyhow::Result<path::PathBuf> {
+    if let Some(dir) = env::var_os("PEZ_CACHE_DIR") {
+        return Ok(path::PathBuf::from(dir));
     }
-    4
+
+    Ok(load_pez_data_dir()?.join(".cache"))
 }
 
-pub(crate) fn set_cli_jobs_override(value: Option<usize>) {
-    *cli_jobs_override().lock().unwrap() = value;
+/// Bare repository holding objects shared by clones when `[settings] shared_objects = true`.
+/// Clones reference it through git alternates, so it must outlive every clone using it.
+pub(crate) fn load_pez_shared_objects_dir() -> anyhow::Result<path::PathBuf> {
+    Ok(load_pez_data_dir()?.join(".objects"))
 }
 
-fn cli_jobs_override() -> &'static Mutex<Option<usize>> {
-    static JOBS_OVERRIDE: OnceLock<Mutex<Option<usize>>> = OnceLock::new();
-    JOBS_OVERRIDE.get_or_init(|| Mutex::new(None))
+/// Writable per-plugin directories that hooks receive as `PEZ_PLUGIN_STATE_DIR`.
+pub(crate) fn load_pez_state_dir() -> anyhow::Result<path::PathBuf> {
+    Ok(load_pez_data_dir()?.join(".state"))
 }
 
-#[cfg(test)]
-pub(crate) fn clear_cli_jobs_override_for_tests() {
-    *cli_jobs_override().lock().unwrap() = None;
+pub(crate) fn plugin_state_dir(plugin_repo: &PluginRepo) -> anyhow::Result<path::PathBuf> {
+    Ok(load_pez_state_dir()?.join(plugin_repo.as_str()))
 }
 
+/// Create the plugin's state directory if needed and return its path.
+pub(crate) fn ensure_plugin_state_dir(plugin_repo: &PluginRepo) -> anyhow::Result<path::PathBuf> {
+    let dir = plugin_state_dir(plugin_repo)?;
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create state directory {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Delete the plugin's state directory once the plugin is gone.
+pub(crate) fn remove_plugin_state_dir(plugin_repo: &PluginRepo) {
+    let Ok(dir) = plugin_state_dir(plugin_repo) else {
+        return;
+    };
+    if dir.exists()
+        && let Err(e) = fs::remove_dir_all(&dir)
+    {
+        warn!("Failed to remove {}: {:?}", dir.display(), e);
+    }
+}
+
+/// Whether `err` comes from writing to a read-only location.
+pub(crate) fn is_read_only_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<std::io::Error>())
+        .any(|io| {
+            matches!(
+                io.kind(),
+                std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem
+            )
+        })
+}
+
+/// Read pez.toml without touching the filesystem. Read-only commands (`list`, `files`,
+/// `doctor`) must use this and [`load_lock_file`], never the `load_or_create_*` variants.
 pub(crate) fn load_config() -> anyhow::Result<(config::Config, path::PathBuf)> {
     let config_path = load_pez_config_dir()?.join("pez.toml");
 
@@ -118,6 +166,7 @@ pub(crate) fn load_config() -> anyhow::Result<(config::Config, path::PathBuf)> {
     Ok((config, config_path))
 }
 
+/// Like [`load_config`], but creates the config directory for commands that will save.
 pub(crate) fn load_or_create_config() -> anyhow::Result<(config::Config, path::PathBuf)> {
     let config_dir = load_pez_config_dir()?;
     if !config_dir.exists() {
@@ -145,6 +194,7 @@ pub(crate) fn load_lock_file() -> anyhow::Result<(LockFile, path::PathBuf)> {
     Ok((lock_file, lock_file_path))
 }
 
+/// Like [`load_lock_file`], but creates the lock file directory for commands that will save.
 pub(crate) fn load_or_create_lock_file() -> anyhow::Result<(LockFile, path::PathBuf)> {
     let lock_file_dir = load_lock_file_dir()?;
     if !lock_file_dir.exists() {
@@ -160,6 +210,19 @@ pub(crate) fn load_or_create_lock_file() -> anyhow::Result<(LockFile, path::Path
     Ok((lock_file, lock_file_path))
 }
 
+/// Lockfile-shaped record of `install --temp` plugins. Kept under the data dir so it
+/// never mixes with the user's pez.toml / pez-lock.toml.
+pub(crate) fn load_or_create_temp_lock_file() -> anyhow::Result<(LockFile, path::PathBuf)> {
+    let temp_lock_file_path = load_pez_data_dir()?.join(".temp-lock.toml");
+    let temp_lock_file = if temp_lock_file_path.exists() {
+        lock_file::load(&temp_lock_file_path)?
+    } else {
+        lock_file::init()
+    };
+
+    Ok((temp_lock_file, temp_lock_file_path))
+}
+
 pub(crate) fn copy_plugin_files_from_repo(
     repo_path: &path::Path,
     plugin: &mut Plugin,
@@ -180,32 +243,63 @@ pub(crate) struct CopyOutcome {
     pub skipped_due_to_duplicate: bool,
 }
 
-pub(crate) fn copy_plugin_files(
+fn expected_extension(target_dir: &TargetDir) -> &'static str {
+    match target_dir {
+        TargetDir::Themes => "theme",
+        _ => "fish",
+    }
+}
+
+/// Where gist files land: the `dir` configured for the plugin, else `functions`.
+fn gist_target_dir(plugin: &Plugin) -> TargetDir {
+    load_config()
+        .ok()
+        .and_then(|(config, _)| config.find_plugin_spec(&plugin.repo)?.dir.clone())
+        .unwrap_or(TargetDir::Functions)
+}
+
+/// The plugin's pez.toml entry when it lists `disable_files`.
+fn disabling_spec(plugin: &Plugin) -> Option<config::PluginSpec> {
+    load_config()
+        .ok()
+        .and_then(|(config, _)| config.find_plugin_spec(&plugin.repo).cloned())
+        .filter(|spec| !spec.disable_files.is_empty())
+}
+
+/// List the files a plugin checkout provides as (destination dir, relative path, source).
+/// Regular repos use `functions/`, `completions/`, `conf.d/`, and `themes/` recursively;
+/// gists keep their files at the top level.
+pub(crate) fn plugin_source_files(
     repo_path: &path::Path,
-    fish_config_dir: &path::Path,
-    plugin: &mut Plugin,
-    mut dedupe: Option<&mut HashSet<path::PathBuf>>,
-    skip_on_duplicate: bool,
-) -> anyhow::Result<CopyOutcome> {
-    let mut outcome = CopyOutcome::default();
-    let target_dirs = TargetDir::all();
-    let mut to_copy: Vec<(TargetDir, path::PathBuf)> = Vec::new();
+    plugin: &Plugin,
+) -> anyhow::Result<Vec<(TargetDir, path::PathBuf, path::PathBuf)>> {
+    let mut files = Vec::new();
+    if plugin.repo.is_gist() {
+        let fish_dir = gist_target_dir(plugin);
+        let mut entries = fs::read_dir(repo_path)?
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+            .map(|e| e.path())
+            .collect::<Vec<_>>();
+        entries.sort();
+        for src in entries {
+            let target_dir = match src.extension().and_then(|s| s.to_str()) {
+                Some("fish") => fish_dir.clone(),
+                Some("theme") => TargetDir::Themes,
+                _ => continue,
+            };
+            let rel = path::PathBuf::from(src.file_name().unwrap_or_default());
+            files.push((target_dir, rel, src));
+        }
+        return Ok(files);
+    }
 
-    // Scan phase: gather files and check duplicates early
-    for target_dir in &target_dirs {
+    for target_dir in TargetDir::all() {
         let target_path = repo_path.join(target_dir.as_str());
         if !target_path.exists() {
             continue;
         }
-        let dest_dir = fish_config_dir.join(target_dir.as_str());
-        if !dest_dir.exists() {
-            fs::create_dir_all(&dest_dir)?;
-        }
-
-        let expected_ext = match target_dir {
-            TargetDir::Themes => Some("theme"),
-            _ => Some("fish"),
-        };
+        let expected_ext = expected_extension(&target_dir);
         for entry in WalkDir::new(&target_path)
             .into_iter()
             .filter_map(Result::ok)
@@ -214,9 +308,7 @@ pub(crate) fn copy_plugin_files(
             if entry.file_type().is_dir() {
                 continue;
             }
-            if let Some(ext) = expected_ext
-                && entry_path.extension().and_then(|s| s.to_str()) != Some(ext)
-            {
+            if entry_path.extension().and_then(|s| s.to_str()) != Some(expected_ext) {
                 continue;
             }
             let rel = entry_path.strip_prefix(&target_path).with_context(|| {
@@ -226,37 +318,127 @@ pub(crate) fn copy_plugin_files(
                     entry_path.display()
                 )
             })?;
-            let dest_path = dest_dir.join(rel);
-            if let Some(set) = dedupe.as_deref_mut()
-                && set.contains(&dest_path)
-                && skip_on_duplicate
-            {
-                warn!(
-                    "{} Duplicate detected. Skipping plugin due to collision: {}",
-                    Emoji("🚨 ", ""),
-                    dest_path.display()
-                );
-                outcome.skipped_due_to_duplicate = true;
-                return Ok(outcome);
+            files.push((
+                target_dir.clone(),
+                rel.to_path_buf(),
+                entry_path.to_path_buf(),
+            ));
+        }
+    }
+    Ok(files)
+}
+
+pub(crate) fn copy_plugin_files(
+    repo_path: &path::Path,
+    fish_config_dir: &path::Path,
+    plugin: &mut Plugin,
+    mut dedupe: Option<&mut HashSet<path::PathBuf>>,
+    skip_on_duplicate: bool,
+) -> anyhow::Result<CopyOutcome> {
+    let mut outcome = CopyOutcome::default();
+    // (destination dir, path relative to it, source file, already installed by another
+    // plugin with identical contents)
+    let mut to_copy: Vec<(TargetDir, path::PathBuf, path::PathBuf, bool)> = Vec::new();
+
+    // Mirror the plugin's directories even when they hold no files yet.
+    if !plugin.repo.is_gist() {
+        for target_dir in TargetDir::all() {
+            let dest_dir = fish_config_dir.join(target_dir.as_str());
+            if repo_path.join(target_dir.as_str()).exists() && !dest_dir.exists() {
+                fs::create_dir_all(&dest_dir)?;
+            }
+        }
+    }
+
+    // Scan phase: gather files and check duplicates early
+    let disabling = disabling_spec(plugin);
+    plugin.disabled_files.clear();
+    for (target_dir, rel, src) in plugin_source_files(repo_path, plugin)? {
+        let dest_path = fish_config_dir.join(target_dir.as_str()).join(&rel);
+        let name = rel.to_string_lossy().to_string();
+        if disabling
+            .as_ref()
+            .is_some_and(|spec| spec.disables(&target_dir, &name))
+        {
+            info!("   - {} (disable_files; skipped)", dest_path.display());
+            plugin.disabled_files.push(PluginFile {
+                dir: target_dir,
+                name,
+            });
+            continue;
+        }
+        if let Some(set) = dedupe.as_deref_mut()
+            && set.contains(&dest_path)
+            && skip_on_duplicate
+        {
+            if same_contents(&src, &dest_path) {
+                to_copy.push((target_dir, rel, src, true));
+                continue;
             }
-            to_copy.push((target_dir.clone(), rel.to_path_buf()));
+            warn!(
+                "{} Duplicate detected. Skipping plugin due to collision: {}",
+                Emoji("🚨 ", ""),
+                dest_path.display()
+            );
+            outcome.skipped_due_to_duplicate = true;
+            return Ok(outcome);
         }
+        to_copy.push((target_dir, rel, src, false));
     }
 
     // Copy phase
-    for (dir, rel) in to_copy.iter() {
-        let src = repo_path.join(dir.as_str()).join(rel);
+    let mut displaced: Option<backup::DisplacedFiles> = None;
+    for (dir, rel, src, shared) in to_copy.iter() {
         let dest = fish_config_dir.join(dir.as_str()).join(rel);
+        if *shared {
+            // Byte-identical to what an earlier plugin installed: keep that copy and
+            // record the file for both plugins.
+            info!("   - {} (identical, shared)", dest.display());
+            plugin.files.push(PluginFile {
+                dir: dir.clone(),
+                name: rel.to_string_lossy().to_string(),
+            });
+            outcome.file_count += 1;
+            continue;
+        }
         if let Some(parent) = dest.parent()
             && !parent.exists()
         {
             fs::create_dir_all(parent)?;
         }
+        let name = rel.to_string_lossy().to_string();
+        if dest.exists() {
+            let displaced = match displaced.as_mut() {
+                Some(displaced) => displaced,
+                None => displaced.insert(backup::DisplacedFiles::load(fish_config_dir)?),
+            };
+            displaced.preserve(plugin, dir, &name, &dest)?;
+            if !same_contents(src, &dest) {
+                backup::back_up_file(&dest)?;
+            }
+        }
         info!("   - {}", dest.display());
-        fs::copy(&src, &dest)?;
+        debug!(
+            target: crate::trace::COPY,
+            src = %src.display(),
+            dest = %dest.display(),
+            "Copying plugin file"
+        );
+        fs::copy(src, &dest)?;
+        if *dir == TargetDir::Functions
+            && let Ok(contents) = fs::read_to_string(src)
+            && let Some(misnamed) = autoload::check_file(&plugin.display_label(), &name, &contents)
+        {
+            warn!(
+                "{} {} {}",
+                Emoji("⚠ ", ""),
+                label_warning(),
+                misnamed.describe()
+            );
+        }
         plugin.files.push(PluginFile {
             dir: dir.clone(),
-            name: rel.to_string_lossy().to_string(),
+            name,
         });
         outcome.file_count += 1;
         if let Some(set) = dedupe.as_deref_mut() {
@@ -267,6 +449,11 @@ pub(crate) fn copy_plugin_files(
     Ok(outcome)
 }
 
+/// True when both files can be read and have the same bytes.
+pub(crate) fn same_contents(a: &path::Path, b: &path::Path) -> bool {
+    matches!((fs::read(a), fs::read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
 #[allow(dead_code)]
 fn copy_plugin_files_recursive(
     target_path: &path::Path,
@@ -333,38 +520,124 @@ impl fmt::Display for Event {
     }
 }
 
-pub(crate) fn emit_event(file_name_or_path: &str, event: &Event) -> anyhow::Result<()> {
-    // Allow callers (e.g., fish wrapper) to suppress out-of-process emits to
-    // avoid duplicate hooks when the shell itself handles events in-process.
-    if std::env::var_os("PEZ_SUPPRESS_EMIT").is_some() {
-        return Ok(());
-    }
-
-    let stem_opt = path::Path::new(file_name_or_path)
+/// `<stem>_<event>`, the name a conf.d file's lifecycle event is emitted under.
+pub(crate) fn conf_d_event_name(file_name_or_path: &str, event: &Event) -> Option<String> {
+    let stem = path::Path::new(file_name_or_path)
         .file_stem()
         .and_then(|s| s.to_str());
-    match stem_opt {
-        Some(stem) => {
-            let output = std::process::Command::new("fish")
-                .arg("-c")
-                .arg(format!("emit {stem}_{event}"))
-                .spawn()
-                .context("Failed to spawn fish to emit event")?
-                .wait_with_output()?;
-            debug!("Emitted event: {}_{}", stem, event);
-
-            if !output.status.success() {
-                error!("Command executed with failing error code");
-            }
-        }
-        None => {
-            warn!(
-                "Could not extract plugin name from file name: {}",
-                file_name_or_path
+    if stem.is_none() {
+        warn!(
+            "Could not extract plugin name from file name: {}",
+            file_name_or_path
+        );
+    }
+    stem.map(|stem| format!("{stem}_{event}"))
+}
+
+/// The `events` overrides configured for `plugin` in pez.toml that apply to `event`.
+pub(crate) fn configured_event_names(plugin: &Plugin, event: &Event) -> Vec<String> {
+    load_config()
+        .ok()
+        .and_then(|(config, _)| {
+            config.find_plugin_spec(&plugin.repo).map(|spec| {
+                spec.event_overrides(event)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect()
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// The event names fired for `plugin`: its `events` overrides when any apply, otherwise
+/// `<stem>_<event>` for each conf.d file. `pez files --with-state-dir` hands the same
+/// names to the activate wrapper.
+pub(crate) fn plugin_event_names(plugin: &Plugin, event: &Event) -> Vec<String> {
+    let overrides = configured_event_names(plugin, event);
+    if !overrides.is_empty() {
+        return overrides;
+    }
+    plugin
+        .files
+        .iter()
+        .filter(|f| f.dir == TargetDir::ConfD)
+        .filter_map(|f| conf_d_event_name(&f.name, event))
+        .collect()
+}
+
+/// Emit `event` for an installed plugin under the names from [`plugin_event_names`].
+pub(crate) fn emit_plugin_events(plugin: &Plugin, event: &Event) {
+    for name in plugin_event_names(plugin, event) {
+        if let Err(e) = emit_named_event(&name, &plugin.repo) {
+            error!(
+                "Failed to emit event for {} ({}): {:?}",
+                name,
+                plugin.display_label(),
+                e
             );
         }
     }
+}
+
+/// Set by the activation wrapper when fish runs with `--private`.
+pub(crate) const FISH_PRIVATE_ENV: &str = "PEZ_FISH_PRIVATE";
+
+#[derive(Debug, PartialEq)]
+enum EmitMode {
+    /// The caller (e.g., the fish wrapper) emits events in-process itself.
+    Suppressed,
+    Spawn,
+    /// Events cannot meaningfully fire; the reason is logged once.
+    Skip(&'static str),
+}
 
+fn emit_mode() -> EmitMode {
+    if env::var_os("PEZ_SUPPRESS_EMIT").is_some() {
+        return EmitMode::Suppressed;
+    }
+    if env::var_os(FISH_PRIVATE_ENV).is_some() {
+        return EmitMode::Skip("fish is running in private mode");
+    }
+    if !fish_on_path() {
+        return EmitMode::Skip("fish was not found on PATH");
+    }
+    EmitMode::Spawn
+}
+
+fn fish_on_path() -> bool {
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join("fish").is_file()))
+}
+
+fn emit_named_event(name: &str, plugin_repo: &PluginRepo) -> anyhow::Result<()> {
+    match emit_mode() {
+        EmitMode::Suppressed => return Ok(()),
+        EmitMode::Skip(reason) => {
+            static SKIP_NOTICE: std::sync::Once = std::sync::Once::new();
+            SKIP_NOTICE.call_once(|| info!("Skipping plugin events: {reason}"));
+            debug!("Skipped event: {}", name);
+            return Ok(());
+        }
+        EmitMode::Spawn => {}
+    }
+
+    let mut command = std::process::Command::new("fish");
+    command.arg("-c").arg(format!("emit {name}"));
+    match ensure_plugin_state_dir(plugin_repo) {
+        Ok(state_dir) => {
+            command.env("PEZ_PLUGIN_STATE_DIR", state_dir);
+        }
+        Err(e) => warn!("{:?}", e),
+    }
+    let output = command
+        .spawn()
+        .context("Failed to spawn fish to emit event")?
+        .wait_with_output()?;
+    debug!("Emitted event: {}", name);
+
+    if !output.status.success() {
+        error!("Command executed with failing error code");
+    }
     Ok(())
 }
 
@@ -380,6 +653,28 @@ fn warn_no_plugin_files() {
 
 // --- Color-aware labels ----------------------------------------------------
 // Colored labels when ANSI is supported; plain otherwise.
+
+/// When to color output, chosen with the global `--color`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ColorChoice {
+    /// Detect from the terminal and NO_COLOR/CLICOLOR/FORCE_COLOR
+    #[default]
+    Auto,
+    /// Always emit ANSI colors
+    Always,
+    /// Never emit ANSI colors
+    Never,
+}
+
+/// `--color always|never` wins over every environment variable; `auto` detects.
+pub(crate) fn colors_enabled(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => colors_enabled_for_stderr(),
+    }
+}
+
 pub(crate) fn colors_enabled_for_stderr() -> bool {
     colors_enabled_for(&console::Term::stderr())
 }
@@ -428,12 +723,28 @@ pub(crate) fn label_notice() -> &'static str {
     "[Notice]"
 }
 
+/// A failure that exits with `code` instead of 1, for commands whose exit status
+/// tells kinds of findings apart (e.g. `doctor` warnings vs errors).
+#[derive(Debug)]
+pub(crate) struct ExitError {
+    pub(crate) code: u8,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for ExitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ExitError {}
+
 #[cfg(test)]
 mod tests {
     use config::{PluginSource, PluginSpec};
 
     use super::*;
-    use crate::models::PluginRepo;
+
     use crate::models::TargetDir;
     use crate::tests_support::env::TestEnvironmentSetup;
     use crate::tests_support::log::{capture_logs, env_lock};
@@ -464,6 +775,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn emit_mode_skips_without_fish_or_in_private_mode() {
+        let _lock = env_lock().lock().unwrap();
+        let _guard = EnvGuard::capture(&["PATH", "PEZ_SUPPRESS_EMIT", FISH_PRIVATE_ENV]);
+        let temp_dir = tempfile::tempdir().unwrap();
+        unsafe {
+            std::env::set_var("PATH", temp_dir.path());
+            std::env::remove_var("PEZ_SUPPRESS_EMIT");
+            std::env::remove_var(FISH_PRIVATE_ENV);
+        }
+        assert_eq!(emit_mode(), EmitMode::Skip("fish was not found on PATH"));
+
+        fs::write(temp_dir.path().join("fish"), "").unwrap();
+        assert_eq!(emit_mode(), EmitMode::Spawn);
+
+        unsafe { std::env::set_var(FISH_PRIVATE_ENV, "1") };
+        assert_eq!(
+            emit_mode(),
+            EmitMode::Skip("fish is running in private mode")
+        );
+
+        unsafe { std::env::set_var("PEZ_SUPPRESS_EMIT", "1") };
+        assert_eq!(emit_mode(), EmitMode::Suppressed);
+    }
+
     #[test]
     fn load_pez_config_dir_prefers_config_dir_over_target_dir() {
         let _lock = crate::tests_support::log::env_lock().lock().unwrap();
@@ -549,34 +885,6 @@ mod tests {
         assert_eq!(resolved, target_dir);
     }
 
-    #[test]
-    fn load_jobs_prefers_cli_override() {
-        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
-        let _guard = EnvGuard::capture(&["PEZ_JOBS"]);
-        clear_cli_jobs_override_for_tests();
-        unsafe {
-            std::env::set_var("PEZ_JOBS", "8");
-        }
-        set_cli_jobs_override(Some(2));
-        assert_eq!(load_jobs(), 2);
-        clear_cli_jobs_override_for_tests();
-    }
-
-    #[test]
-    fn load_jobs_falls_back_to_env_when_override_absent() {
-        let _lock = crate::tests_support::log::env_lock().lock().unwrap();
-        let _guard = EnvGuard::capture(&["PEZ_JOBS"]);
-        clear_cli_jobs_override_for_tests();
-        unsafe {
-            std::env::set_var("PEZ_JOBS", "6");
-        }
-        assert_eq!(load_jobs(), 6);
-        unsafe {
-            std::env::remove_var("PEZ_JOBS");
-        }
-        assert_eq!(load_jobs(), 4);
-    }
-
     #[test]
     fn home_dir_uses_home_env() {
         let _lock = env_lock().lock().unwrap();
@@ -646,6 +954,17 @@ mod tests {
         assert_eq!(resolved, temp.path().join(".local/share/fish"));
     }
 
+    #[test]
+    fn is_read_only_error_checks_io_causes() {
+        let denied =
+            anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied))
+                .context("writing cache");
+        assert!(is_read_only_error(&denied));
+        let missing = anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(!is_read_only_error(&missing));
+        assert!(!is_read_only_error(&anyhow::anyhow!("other")));
+    }
+
     #[test]
     fn load_or_create_config_creates_missing_dir() {
         let _lock = env_lock().lock().unwrap();
@@ -720,9 +1039,12 @@ mod tests {
                     source: "https://example.com/owner/repo".to_string(),
                     commit_sha: "sha".to_string(),
                     files: vec![],
+                    ..Default::default()
                 },
                 plugin_spec: PluginSpec {
                     name: None,
+                    prerelease: false,
+                    disabled: false,
                     source: PluginSource::Repo {
                         repo: PluginRepo {
                             host: None,
@@ -734,6 +1056,9 @@ mod tests {
                         tag: None,
                         commit: None,
                     },
+                    dir: None,
+                    events: vec![],
+                    disable_files: vec![],
                 },
             }
         }
@@ -905,11 +1230,12 @@ mod tests {
         std::fs::create_dir_all(test_env.data_dir.join(repo.as_str())).unwrap();
         test_env.add_plugin_files_to_repo(&repo, &plugin_files);
 
-        // Pre-create the destination path and mark it as already occupied in dedupe set
+        // Pre-create the destination path with different contents and mark it as
+        // already occupied in dedupe set
         let dest_dir = test_env.fish_config_dir.join(TargetDir::Functions.as_str());
         std::fs::create_dir_all(&dest_dir).unwrap();
         let existing_dest = dest_dir.join("sample.fish");
-        std::fs::File::create(&existing_dest).unwrap();
+        std::fs::write(&existing_dest, "function sample; echo other; end\n").unwrap();
 
         let mut dedupe = std::collections::HashSet::new();
         dedupe.insert(existing_dest.clone());
@@ -933,6 +1259,55 @@ mod tests {
         assert!(std::fs::metadata(&existing_dest).is_ok());
     }
 
+    #[test]
+    fn copy_plugin_files_dedupe_shares_identical_files() {
+        let test_env = TestEnvironmentSetup::new();
+        let mut test_data = TestDataBuilder::new().build();
+        let plugin_files = vec![
+            PluginFile {
+                dir: TargetDir::Functions,
+                name: "sample.fish".to_string(),
+            },
+            PluginFile {
+                dir: TargetDir::Functions,
+                name: "own.fish".to_string(),
+            },
+        ];
+        let repo = test_data.plugin_spec.get_plugin_repo().unwrap();
+        test_env.add_plugin_files_to_repo(&repo, &plugin_files);
+
+        // Another plugin already installed a byte-identical (empty) sample.fish.
+        let dest_dir = test_env.fish_config_dir.join(TargetDir::Functions.as_str());
+        std::fs::create_dir_all(&dest_dir).unwrap();
+        let existing_dest = dest_dir.join("sample.fish");
+        std::fs::File::create(&existing_dest).unwrap();
+        let mut dedupe = std::collections::HashSet::from([existing_dest]);
+
+        let repo_path = test_env.data_dir.join(repo.as_str());
+        let outcome = copy_plugin_files(
+            &repo_path,
+            &test_env.fish_config_dir,
+            &mut test_data.plugin,
+            Some(&mut dedupe),
+            true,
+        )
+        .expect("copy should not error");
+
+        assert!(!outcome.skipped_due_to_duplicate);
+        assert_eq!(outcome.file_count, 2);
+        let mut names: Vec<&str> = test_data
+            .plugin
+            .files
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["own.fish", "sample.fish"]);
+        assert!(dest_dir.join("own.fish").exists());
+        // The shared file was not displaced into a backup.
+        assert!(test_data.plugin.backups.is_empty());
+    }
+
     #[test]
     fn copy_plugin_files_from_repo_warns_when_empty() {
         let _lock = env_lock().lock().unwrap();
@@ -965,6 +1340,7 @@ mod tests {
             source: "https://example.com/owner/repo".to_string(),
             commit_sha: "sha".to_string(),
             files: vec![],
+            ..Default::default()
         };
 
         let (logs, result) = capture_logs(|| copy_plugin_files_from_repo(&repo_path, &mut plugin));
@@ -1044,6 +1420,54 @@ mod tests {
         assert!(test_env.fish_config_dir.join("functions").exists());
     }
 
+    #[test]
+    fn copy_plugin_files_uses_top_level_files_for_gists() {
+        let _lock = env_lock().lock().unwrap();
+        let _guard = EnvGuard::capture(&["PEZ_CONFIG_DIR"]);
+        let test_env = TestEnvironmentSetup::new();
+        unsafe {
+            std::env::set_var("PEZ_CONFIG_DIR", &test_env.config_dir);
+        }
+        let repo = PluginRepo::new(
+            Some("gist.github.com".into()),
+            "alice".into(),
+            "abc123".into(),
+        )
+        .unwrap();
+        let repo_path = test_env.data_dir.join(repo.as_str());
+        std::fs::create_dir_all(&repo_path).unwrap();
+        std::fs::write(repo_path.join("greet.fish"), "function greet; end").unwrap();
+        std::fs::write(repo_path.join("README.md"), "docs").unwrap();
+        let mut plugin = Plugin {
+            name: "abc123".to_string(),
+            repo,
+            source: "https://gist.github.com/alice/abc123".to_string(),
+            commit_sha: "sha".to_string(),
+            files: vec![],
+            ..Default::default()
+        };
+
+        let outcome = copy_plugin_files(
+            &repo_path,
+            &test_env.fish_config_dir,
+            &mut plugin,
+            None,
+            false,
+        )
+        .unwrap();
+        assert_eq!(outcome.file_count, 1);
+        assert!(
+            test_env
+                .fish_config_dir
+                .join("functions/greet.fish")
+                .exists()
+        );
+        assert_eq!(
+            plugin.source_path(&plugin.files[0]),
+            path::PathBuf::from("greet.fish")
+        );
+    }
+
     #[test]
     fn copy_plugin_files_includes_themes_and_counts() {
         let test_env = TestEnvironmentSetup::new();
@@ -1177,6 +1601,26 @@ mod tests {
         assert!(!colors_enabled_for_stderr());
     }
 
+    #[test]
+    fn color_choice_overrides_environment() {
+        let _lock = env_lock().lock().unwrap();
+        let _guard = EnvGuard::capture(&["NO_COLOR", "CLICOLOR_FORCE"]);
+
+        unsafe {
+            std::env::set_var("NO_COLOR", "1");
+            std::env::remove_var("CLICOLOR_FORCE");
+        }
+        assert!(colors_enabled(ColorChoice::Always));
+        assert!(!colors_enabled(ColorChoice::Auto));
+
+        unsafe {
+            std::env::remove_var("NO_COLOR");
+            std::env::set_var("CLICOLOR_FORCE", "1");
+        }
+        assert!(!colors_enabled(ColorChoice::Never));
+        assert!(colors_enabled(ColorChoice::Auto));
+    }
+
     #[test]
     fn colors_enabled_for_stderr_force_color_overrides_term() {
         let _lock = env_lock().lock().unwrap();
@@ -1213,15 +1657,13 @@ mod tests {
     }
 
     #[test]
-    fn emit_event_warns_when_stem_missing() {
-        let _lock = env_lock().lock().unwrap();
-        let _guard = EnvGuard::capture(&["PEZ_SUPPRESS_EMIT"]);
-        unsafe {
-            std::env::remove_var("PEZ_SUPPRESS_EMIT");
-        }
-
-        let (logs, result) = capture_logs(|| emit_event("", &Event::Install));
-        assert!(result.is_ok());
+    fn conf_d_event_name_warns_when_stem_missing() {
+        let (logs, name) = capture_logs(|| conf_d_event_name("", &Event::Install));
+        assert_eq!(name, None);
+        assert_eq!(
+            conf_d_event_name("conf.d/plugin.fish", &Event::Update).as_deref(),
+            Some("plugin_update")
+        );
         assert!(
             logs.iter()
                 .any(|msg| msg.contains("Could not extract plugin name"))
@@ -1294,15 +1736,23 @@ mod tests {
 
     #[cfg(unix)]
     #[test]
-    fn emit_event_logs_error_on_failed_command() {
+    fn emit_named_event_logs_error_on_failed_command() {
         use std::os::unix::fs::PermissionsExt;
 
         let _lock = env_lock().lock().unwrap();
-        let _guard = EnvGuard::capture(&["PEZ_SUPPRESS_EMIT", "PATH"]);
+        let _guard = EnvGuard::capture(&["PEZ_SUPPRESS_EMIT", "PATH", "PEZ_DATA_DIR"]);
 
         let temp = tempfile::tempdir().unwrap();
         let fish_path = temp.path().join("fish");
-        std::fs::write(&fish_path, "#!/bin/sh\nexit 1\n").unwrap();
+        let seen_state_dir = temp.path().join("state-dir.txt");
+        std::fs::write(
+            &fish_path,
+            format!(
+                "#!/bin/sh\nprintf %s \"$PEZ_PLUGIN_STATE_DIR\" > {}\nexit 1\n",
+                seen_state_dir.display()
+            ),
+        )
+        .unwrap();
         let mut perms = std::fs::metadata(&fish_path).unwrap().permissions();
         perms.set_mode(0o755);
         std::fs::set_permissions(&fish_path, perms).unwrap();
@@ -1313,13 +1763,28 @@ mod tests {
         unsafe {
             std::env::remove_var("PEZ_SUPPRESS_EMIT");
             std::env::set_var("PATH", new_path);
+            std::env::set_var("PEZ_DATA_DIR", temp.path().join("data"));
         }
 
-        let (logs, result) = capture_logs(|| emit_event("plugin.fish", &Event::Install));
+        let (logs, result) = capture_logs(|| emit_named_event("plugin_install", &test_repo()));
         assert!(result.is_ok());
         assert!(
             logs.iter()
                 .any(|msg| msg.contains("Command executed with failing error code"))
         );
+
+        // Hooks receive an existing per-plugin state dir.
+        let state_dir = temp.path().join("data/.state/owner/repo");
+        assert!(state_dir.is_dir());
+        assert_eq!(
+            std::fs::read_to_string(&seen_state_dir).unwrap(),
+            state_dir.display().to_string()
+        );
+        remove_plugin_state_dir(&test_repo());
+        assert!(!state_dir.exists());
+    }
+
+    fn test_repo() -> PluginRepo {
+        PluginRepo::new(None, "owner".into(), "repo".into()).unwrap()
     }
 }
@@ -0,0 +1,193 @@
+use crate::{lock_file::Plugin, models::TargetDir, utils};
+
+use console::Emoji;
+use std::{
+    path,
+    process::{Command, Stdio},
+};
+use tracing::{debug, warn};
+
+/// Completion and function directories that packages such as Homebrew install into.
+/// Files pez copies into the fish config dir come earlier in fish's search path and
+/// silently take precedence over these.
+#[derive(Debug, Default)]
+pub(crate) struct VendorDirs {
+    completions: Vec<path::PathBuf>,
+    functions: Vec<path::PathBuf>,
+}
+
+/// A plugin file that hides a vendor-provided file of the same name.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Shadowed {
+    pub(crate) plugin: String,
+    pub(crate) name: String,
+    pub(crate) vendor_path: path::PathBuf,
+}
+
+impl VendorDirs {
+    /// Ask fish for `$fish_complete_path`/`$fish_function_path`; fall back to the
+    /// `XDG_DATA_DIRS` layout fish uses when fish cannot be run.
+    pub(crate) fn detect() -> Self {
+        match query_fish() {
+            Ok(output) => Self::from_fish_output(&output),
+            Err(e) => {
+                debug!("Falling back to XDG_DATA_DIRS for vendor dirs: {:?}", e);
+                let data_dirs = std::env::var("XDG_DATA_DIRS")
+                    .ok()
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+                Self::from_data_dirs(&data_dirs)
+            }
+        }
+    }
+
+    /// Parse lines prefixed with `c` (complete path) or `f` (function path), keeping
+    /// only the `vendor_*.d` entries.
+    fn from_fish_output(output: &str) -> Self {
+        let mut dirs = Self::default();
+        for line in output.lines() {
+            let (list, dir) = match line.split_at_checked(1) {
+                Some(("c", dir)) => (&mut dirs.completions, dir),
+                Some(("f", dir)) => (&mut dirs.functions, dir),
+                _ => continue,
+            };
+            let dir = path::PathBuf::from(dir);
+            let is_vendor = dir
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("vendor_") && n.ends_with(".d"));
+            if is_vendor {
+                list.push(dir);
+            }
+        }
+        dirs
+    }
+
+    fn from_data_dirs(data_dirs: &str) -> Self {
+        let mut dirs = Self::default();
+        for base in data_dirs.split(':').filter(|d| !d.is_empty()) {
+            let fish = path::Path::new(base).join("fish");
+            dirs.completions.push(fish.join("vendor_completions.d"));
+            dirs.functions.push(fish.join("vendor_functions.d"));
+        }
+        dirs
+    }
+
+    pub(crate) fn shadowed_by(&self, plugin: &Plugin) -> Vec<Shadowed> {
+        let mut out = Vec::new();
+        for file in &plugin.files {
+            let dirs = match file.dir {
+                TargetDir::Completions => &self.completions,
+                TargetDir::Functions => &self.functions,
+                _ => continue,
+            };
+            for dir in dirs {
+                let vendor_path = dir.join(&file.name);
+                if vendor_path.is_file() {
+                    out.push(Shadowed {
+                        plugin: plugin.display_label(),
+                        name: file.name.clone(),
+                        vendor_path,
+                    });
+                }
+            }
+        }
+        out
+    }
+}
+
+fn query_fish() -> anyhow::Result<String> {
+    let output = Command::new("fish")
+        .arg("--no-config")
+        .arg("-c")
+        .arg("printf '%s\\n' c$fish_complete_path f$fish_function_path")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("fish exited with {}", output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub(crate) fn enabled() -> bool {
+    utils::load_config().is_ok_and(|(config, _)| config.settings.vendor_conflicts)
+}
+
+/// With `[settings] vendor_conflicts = true`, warn about plugin files that shadow
+/// vendor completions or functions.
+pub(crate) fn warn_shadowed(plugins: &[Plugin]) {
+    if plugins.is_empty() || !enabled() {
+        return;
+    }
+    let vendor = VendorDirs::detect();
+    for shadowed in plugins.iter().flat_map(|p| vendor.shadowed_by(p)) {
+        warn!(
+            "{} {} {} from {} shadows vendor-provided {}",
+            Emoji("⚠ ", ""),
+            utils::label_warning(),
+            shadowed.name,
+            shadowed.plugin,
+            shadowed.vendor_path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lock_file::PluginFile, models::PluginRepo};
+    use std::fs;
+
+    #[test]
+    fn from_fish_output_keeps_vendor_dirs_only() {
+        let dirs = VendorDirs::from_fish_output(
+            "c/home/u/.config/fish/completions\nc/opt/homebrew/share/fish/vendor_completions.d\nc/usr/share/fish/completions\nf/usr/share/fish/vendor_functions.d\n",
+        );
+        assert_eq!(
+            dirs.completions,
+            vec![path::PathBuf::from(
+                "/opt/homebrew/share/fish/vendor_completions.d"
+            )]
+        );
+        assert_eq!(
+            dirs.functions,
+            vec![path::PathBuf::from("/usr/share/fish/vendor_functions.d")]
+        );
+    }
+
+    #[test]
+    fn shadowed_by_reports_matching_vendor_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dirs = VendorDirs::from_data_dirs(&temp.path().display().to_string());
+        let vendor_completions = temp.path().join("fish/vendor_completions.d");
+        fs::create_dir_all(&vendor_completions).unwrap();
+        fs::write(vendor_completions.join("git.fish"), "").unwrap();
+
+        let plugin = Plugin {
+            name: "git".into(),
+            repo: PluginRepo::new(None, "owner".into(), "git".into()).unwrap(),
+            source: "https://example.com/owner/git".into(),
+            commit_sha: "sha".into(),
+            files: vec![
+                PluginFile {
+                    dir: TargetDir::Completions,
+                    name: "git.fish".into(),
+                },
+                PluginFile {
+                    dir: TargetDir::ConfD,
+                    name: "git.fish".into(),
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            dirs.shadowed_by(&plugin),
+            vec![Shadowed {
+                plugin: "owner/git".into(),
+                name: "git.fish".into(),
+                vendor_path: vendor_completions.join("git.fish"),
+            }]
+        );
+    }
+}
@@ -197,12 +197,13 @@ fn cli_migrate_install_list_doctor_flow() {
         &target_dir,
     );
     assert_success(&doctor, "doctor");
-    let checks = parse_json_stdout(&doctor);
-    let has_error = checks.as_array().is_some_and(|arr| {
-        arr.iter()
-            .any(|check| check.get("status").and_then(|v| v.as_str()) == Some("error"))
-    });
-    assert!(!has_error, "doctor reported error: {}", checks);
+    let report = parse_json_stdout(&doctor);
+    assert_eq!(report["schema_version"], 1, "doctor report: {report}");
+    let checks = report["checks"].as_array().expect("doctor checks array");
+    let has_error = checks
+        .iter()
+        .any(|check| check["severity"].as_str() == Some("error"));
+    assert!(!has_error, "doctor reported error: {report}");
 
     assert!(
         target_dir
@@ -254,12 +255,13 @@ fn cli_migrate_with_install_flag_completes_flow() {
         &target_dir,
     );
     assert_success(&doctor, "doctor");
-    let checks = parse_json_stdout(&doctor);
-    let has_error = checks.as_array().is_some_and(|arr| {
-        arr.iter()
-            .any(|check| check.get("status").and_then(|v| v.as_str()) == Some("error"))
-    });
-    assert!(!has_error, "doctor reported error: {}", checks);
+    let report = parse_json_stdout(&doctor);
+    assert_eq!(report["schema_version"], 1, "doctor report: {report}");
+    let checks = report["checks"].as_array().expect("doctor checks array");
+    let has_error = checks
+        .iter()
+        .any(|check| check["severity"].as_str() == Some("error"));
+    assert!(!has_error, "doctor reported error: {report}");
 
     assert!(config_dir.join("pez-lock.toml").exists());
     assert!(
@@ -304,3 +306,261 @@ fn cli_migrate_dry_run_install_does_not_mutate() {
             .exists()
     );
 }
+
+/// Every path under `root` with its contents (files) or `None` (directories).
+fn snapshot_tree(root: &Path) -> Vec<(PathBuf, Option<Vec<u8>>)> {
+    let mut entries = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                entries.push((path.clone(), None));
+                pending.push(path);
+            } else {
+                entries.push((path.clone(), Some(fs::read(&path).unwrap())));
+            }
+        }
+    }
+    entries.sort();
+    entries
+}
+
+const READ_ONLY_COMMANDS: &[&[&str]] = &[
+    &["list"],
+    &["list", "--format", "json"],
+    &["list", "--format", "yaml"],
+    &["list", "--duplicates"],
+    &["list", "--outdated", "--cached"],
+    &["files", "--all"],
+    &["status"],
+    &["status", "--format", "json"],
+    &["doctor"],
+    &["doctor", "--format", "json"],
+    &["doctor", "--format", "yaml"],
+    &["export"],
+    &["why", "plugin-read-only"],
+    &["verify"],
+    &["diff"],
+    &["completion-data"],
+    &["outdated", "--cached"],
+    &["snapshot"],
+    &["check"],
+    &["theme", "list"],
+];
+
+#[test]
+fn cli_read_only_commands_do_not_create_directories() {
+    let temp = tempdir().unwrap();
+    let config_dir = temp.path().join("config");
+    let data_dir = temp.path().join("data");
+    let target_dir = temp.path().join("fish");
+
+    for args in READ_ONLY_COMMANDS {
+        run_pez(args, &config_dir, &data_dir, &target_dir);
+    }
+    assert!(
+        snapshot_tree(temp.path()).is_empty(),
+        "read-only commands created {:?}",
+        snapshot_tree(temp.path())
+    );
+}
+
+#[test]
+fn cli_read_only_commands_leave_existing_state_untouched() {
+    let temp = tempdir().unwrap();
+    let config_dir = temp.path().join("config");
+    let data_dir = temp.path().join("data");
+    let target_dir = temp.path().join("fish");
+    let plugin_dir = make_local_plugin(temp.path(), "plugin-read-only");
+    let install = run_pez(
+        &["install", plugin_dir.to_str().unwrap()],
+        &config_dir,
+        &data_dir,
+        &target_dir,
+    );
+    assert_success(&install, "install");
+
+    let lock_path = config_dir.join("pez-lock.toml");
+    let mut perms = fs::metadata(&lock_path).unwrap().permissions();
+    perms.set_readonly(true);
+    fs::set_permissions(&lock_path, perms).unwrap();
+
+    let before = snapshot_tree(temp.path());
+    for args in READ_ONLY_COMMANDS {
+        let output = run_pez(args, &config_dir, &data_dir, &target_dir);
+        assert_success(&output, &args.join(" "));
+    }
+    assert_eq!(snapshot_tree(temp.path()), before);
+}
+
+#[test]
+fn cli_json_style_compact_prints_one_sorted_line() {
+    let temp = tempdir().unwrap();
+    let config_dir = temp.path().join("config");
+    let data_dir = temp.path().join("data");
+    let target_dir = temp.path().join("fish");
+    let plugin_dir = make_local_plugin(temp.path(), "plugin-json-style");
+    let install = run_pez(
+        &["install", plugin_dir.to_str().unwrap()],
+        &config_dir,
+        &data_dir,
+        &target_dir,
+    );
+    assert_success(&install, "install");
+
+    let output = run_pez(
+        &["list", "--format", "json", "--json-style", "compact"],
+        &config_dir,
+        &data_dir,
+        &target_dir,
+    );
+    assert_success(&output, "list --json-style compact");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim_end().lines().count(), 1, "{stdout}");
+    // Parsing into a Value would sort the keys itself, so check the raw text.
+    let keys: Vec<String> = parse_json_stdout(&output)[0]
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(|key| format!("\"{key}\":"))
+        .collect();
+    let positions: Vec<usize> = keys.iter().map(|key| stdout.find(key).unwrap()).collect();
+    assert!(positions.is_sorted(), "{stdout}");
+}
+
+#[test]
+fn cli_output_to_a_closed_pipe_exits_quietly() {
+    let temp = tempdir().unwrap();
+    let config_dir = temp.path().join("config");
+    let data_dir = temp.path().join("data");
+    let target_dir = temp.path().join("fish");
+    let plugin_dir = make_local_plugin(temp.path(), "plugin-closed-pipe");
+    let install = run_pez(
+        &["install", plugin_dir.to_str().unwrap()],
+        &config_dir,
+        &data_dir,
+        &target_dir,
+    );
+    assert_success(&install, "install");
+
+    for args in [
+        &["list"][..],
+        &["list", "--format", "json"],
+        &["files", "--all"],
+        &["doctor"],
+        &["activate", "fish"],
+        &["completions", "fish"],
+    ] {
+        // Like `pez list | head -0`: the reader is gone before pez writes anything.
+        let (reader, writer) = std::io::pipe().unwrap();
+        drop(reader);
+        let mut cmd = pez_command();
+        apply_test_env(&mut cmd, &config_dir, &data_dir, &target_dir);
+        let output = cmd.args(args).stdout(writer).output().unwrap();
+        let context = args.join(" ");
+        assert_success(&output, &context);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!stderr.contains("Broken pipe"), "{context}: {stderr}");
+    }
+}
+
+#[test]
+fn cli_sync_installs_prunes_and_restores() {
+    let temp = tempdir().unwrap();
+    let config_dir = temp.path().join("config");
+    let data_dir = temp.path().join("data");
+    let target_dir = temp.path().join("fish");
+    let kept = make_local_plugin(temp.path(), "plugin-kept");
+    let dropped = make_local_plugin(temp.path(), "plugin-dropped");
+    for plugin in [&kept, &dropped] {
+        let install = run_pez(
+            &["install", plugin.to_str().unwrap()],
+            &config_dir,
+            &data_dir,
+            &target_dir,
+        );
+        assert_success(&install, "install");
+    }
+
+    let kept_file = target_dir.join("conf.d").join("plugin-kept.fish");
+    let dropped_file = target_dir.join("conf.d").join("plugin-dropped.fish");
+    let added = make_local_plugin(temp.path(), "plugin-added");
+    fs::write(
+        config_dir.join("pez.toml"),
+        format!(
+            "[[plugins]]\npath = {:?}\n\n[[plugins]]\npath = {:?}\n",
+            kept.to_str().unwrap(),
+            added.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    fs::remove_file(&kept_file).unwrap();
+
+    let output = run_pez(&["sync"], &config_dir, &data_dir, &target_dir);
+    assert_success(&output, "sync");
+    assert!(kept_file.exists());
+    assert!(target_dir.join("conf.d").join("plugin-added.fish").exists());
+    assert!(!dropped_file.exists());
+    let lock = fs::read_to_string(config_dir.join("pez-lock.toml")).unwrap();
+    assert!(!lock.contains("plugin-dropped"), "{lock}");
+
+    let before = snapshot_tree(temp.path());
+    let again = run_pez(&["sync"], &config_dir, &data_dir, &target_dir);
+    assert_success(&again, "second sync");
+    assert_eq!(snapshot_tree(temp.path()), before);
+}
+
+#[test]
+fn cli_import_adds_listed_plugins_and_installs() {
+    let temp = tempdir().unwrap();
+    let config_dir = temp.path().join("config");
+    let data_dir = temp.path().join("data");
+    let target_dir = temp.path().join("fish");
+    let first = make_local_plugin(temp.path(), "plugin-import-a");
+    let second = make_local_plugin(temp.path(), "plugin-import-b");
+    let list = temp.path().join("plugins.txt");
+    fs::write(
+        &list,
+        format!(
+            "# imported\n{}\n\n{}\n{}\n",
+            first.display(),
+            second.display(),
+            first.display()
+        ),
+    )
+    .unwrap();
+
+    let output = run_pez(
+        &["import", list.to_str().unwrap(), "--install"],
+        &config_dir,
+        &data_dir,
+        &target_dir,
+    );
+    assert_success(&output, "import --install");
+    let config = fs::read_to_string(config_dir.join("pez.toml")).unwrap();
+    assert_eq!(config.matches("[[plugins]]").count(), 2, "{config}");
+    assert!(target_dir.join("conf.d/plugin-import-a.fish").exists());
+    assert!(target_dir.join("conf.d/plugin-import-b.fish").exists());
+}
+
+#[test]
+fn cli_smoke_passes_in_sandbox_and_leaves_real_dirs_alone() {
+    let temp = tempdir().unwrap();
+    let config_dir = temp.path().join("config");
+    let data_dir = temp.path().join("data");
+    let target_dir = temp.path().join("fish");
+
+    let output = run_pez(&["smoke"], &config_dir, &data_dir, &target_dir);
+    assert_success(&output, "smoke");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for check in ["install", "files", "lock", "uninstall", "files_removed"] {
+        assert!(
+            stdout
+                .lines()
+                .any(|line| line.starts_with(&format!("✔ {check} "))),
+            "{stdout}"
+        );
+    }
+    assert!(snapshot_tree(temp.path()).is_empty());
+}